@@ -2,18 +2,31 @@ use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
+use anyhow::Context;
 use eframe::egui;
+use letrecovery_config::InstallPlan;
 
-use crate::core::config::{ConfigFileManager, OperationType};
+use crate::core::backend::{native_backend, SystemBackend};
+use crate::core::config::{ConfigFileManager, InstallConfig, OperationType};
 use crate::core::dism::DismProgress;
+use crate::core::dry_run::DryRunBackend;
+use crate::core::journal::{insert_snapshot, InstallJournal, StepOutputs};
+use crate::core::raw_image::FileSystemKind;
+use crate::core::rollback::{self, RollbackContext};
 use crate::ui::progress::{InstallStep, BackupStep, ProgressState, ProgressUI};
-use crate::utils::reboot_pe;
+
+/// 继续中断安装的提示在无人操作时自动继续的等待时间
+const RESUME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 格式化前失败时，回滚前留给用户阅读错误信息的时间
+const ROLLBACK_DELAY: Duration = Duration::from_secs(15);
 
 /// 递归查找目录中的所有 CAB 文件
-fn find_cab_files_in_directory(dir: &str) -> Vec<PathBuf> {
+fn find_cab_files_in_directory(dir: &Path) -> Vec<PathBuf> {
     let mut cab_files = Vec::new();
-    find_cab_files_recursive(Path::new(dir), &mut cab_files);
+    find_cab_files_recursive(dir, &mut cab_files);
     cab_files
 }
 
@@ -46,8 +59,8 @@ pub enum WorkerMessage {
     SetProgress(u8),
     /// 更新状态消息
     SetStatus(String),
-    /// 标记完成
-    Completed,
+    /// 标记完成，`message` 为完成提示，`reboot` 表示随后会自动重启
+    Completed { message: String, reboot: bool },
     /// 标记失败
     Failed(String),
 }
@@ -61,6 +74,18 @@ pub struct App {
     started: bool,
     /// 操作类型
     operation_type: Option<OperationType>,
+    /// 上次中断的安装，等待用户选择继续或重新开始
+    resume_offer: Option<ResumeOffer>,
+}
+
+/// 上次中断的安装
+struct ResumeOffer {
+    /// 继续时开始的步骤
+    step: InstallStep,
+    /// 上次失败的原因
+    last_error: Option<String>,
+    /// 无人操作时自动继续的时间
+    deadline: Instant,
 }
 
 impl App {
@@ -77,12 +102,35 @@ impl App {
             None => ProgressState::new_install(),
         }));
 
+        let resume_offer = match operation_type {
+            Some(OperationType::Install) => Self::detect_interrupted_install(),
+            _ => None,
+        };
+
         Self {
             progress_state,
             message_rx: None,
             started: false,
             operation_type,
+            resume_offer,
+        }
+    }
+
+    /// 检查安装日志，之前开始过的安装可以从上次完成的步骤继续
+    fn detect_interrupted_install() -> Option<ResumeOffer> {
+        let data_partition = ConfigFileManager::find_data_partition()?;
+        let journal = InstallJournal::open(ConfigFileManager::get_data_dir(&data_partition));
+        if !journal.has_progress() {
+            return None;
         }
+
+        let step = journal.resume_step();
+        log::info!("检测到中断的安装，可从「{}」继续", step.name());
+        Some(ResumeOffer {
+            step,
+            last_error: journal.last_error().map(str::to_string),
+            deadline: Instant::now() + RESUME_TIMEOUT,
+        })
     }
 
     /// 设置中文字体（从PE的X盘加载微软雅黑）
@@ -118,8 +166,8 @@ impl App {
         ctx.set_fonts(fonts);
     }
 
-    /// 启动工作线程
-    fn start_worker(&mut self) {
+    /// 启动工作线程，安装从 `start_from` 步骤开始
+    fn start_worker(&mut self, start_from: InstallStep) {
         if self.started {
             return;
         }
@@ -131,12 +179,19 @@ impl App {
         let operation_type = self.operation_type;
 
         thread::spawn(move || {
+            let backend = match native_backend() {
+                Ok(backend) => backend,
+                Err(e) => {
+                    let _ = tx.send(WorkerMessage::Failed(format!("{:#}", e)));
+                    return;
+                }
+            };
             match operation_type {
                 Some(OperationType::Install) => {
-                    execute_install_workflow(tx);
+                    execute_install_workflow(backend, tx, start_from);
                 }
                 Some(OperationType::Backup) => {
-                    execute_backup_workflow(tx);
+                    execute_backup_workflow(backend, tx);
                 }
                 None => {
                     let _ = tx.send(WorkerMessage::Failed("未检测到安装或备份配置".to_string()));
@@ -163,8 +218,8 @@ impl App {
                         WorkerMessage::SetStatus(s) => {
                             state.status_message = s;
                         }
-                        WorkerMessage::Completed => {
-                            state.mark_completed();
+                        WorkerMessage::Completed { message, .. } => {
+                            state.mark_completed(message);
                         }
                         WorkerMessage::Failed(e) => {
                             state.mark_failed(&e);
@@ -178,9 +233,34 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // 上次安装中断时，先询问继续还是重新开始
+        if let Some(offer) = &self.resume_offer {
+            let remaining = offer.deadline.saturating_duration_since(Instant::now());
+            let mut choice = None;
+            egui::CentralPanel::default().show(ctx, |ui| {
+                choice = ProgressUI::show_resume_prompt(
+                    ui,
+                    offer.step,
+                    offer.last_error.as_deref(),
+                    remaining.as_secs(),
+                );
+            });
+            if remaining.is_zero() {
+                choice = choice.or(Some(true));
+            }
+
+            if let Some(resume) = choice {
+                let start_from = if resume { offer.step } else { InstallStep::FormatPartition };
+                self.resume_offer = None;
+                self.start_worker(start_from);
+            }
+            ctx.request_repaint();
+            return;
+        }
+
         // 启动工作线程
         if !self.started {
-            self.start_worker();
+            self.start_worker(InstallStep::FormatPartition);
         }
 
         // 处理消息
@@ -198,33 +278,96 @@ impl eframe::App for App {
     }
 }
 
-/// 执行安装工作流
-fn execute_install_workflow(tx: Sender<WorkerMessage>) {
-    use crate::core::bcdedit::BootManager;
-    use crate::core::dism::Dism;
-    use crate::core::disk::DiskManager;
-    use crate::core::ghost::Ghost;
-    use crate::ui::advanced_options::apply_advanced_options;
+/// 安装流程上下文
+struct InstallContext<'a> {
+    backend: &'a dyn SystemBackend,
+    config: InstallConfig,
+    data_partition: String,
+    data_dir: String,
+    target_partition: String,
+    image_path: String,
+}
+
+impl InstallContext<'_> {
+    fn apply_dir(&self) -> String {
+        format!("{}\\", self.target_partition)
+    }
+}
+
+/// 演练安装，返回实际运行时将执行的操作
+///
+/// 与实际安装走同一流程，只是改动类操作由 [`DryRunBackend`] 记录而不执行；
+/// 有中断的安装时，与界面上自动继续一样从日志中的下一步开始。
+pub fn plan_install(backend: &dyn SystemBackend) -> InstallPlan {
+    let start_from = resume_step(backend);
 
+    let dry_run = DryRunBackend::new(backend);
+    let (tx, rx) = channel::<WorkerMessage>();
+    execute_install_workflow(&dry_run, tx, start_from);
+
+    let mut plan = dry_run.into_plan();
+    if start_from != InstallStep::FormatPartition {
+        plan.notes.insert(0, format!("继续上次中断的安装，从「{}」开始", start_from.name()));
+    }
+    for message in rx.try_iter() {
+        if let WorkerMessage::Failed(error) = message {
+            plan.note(format!("实际运行时将失败: {}", error));
+        }
+    }
+    plan
+}
+
+/// 安装日志中记录的继续步骤，没有中断的安装时从头开始
+pub fn resume_step(backend: &dyn SystemBackend) -> InstallStep {
+    backend
+        .find_data_partition()
+        .map(|partition| InstallJournal::open(backend.path(&ConfigFileManager::get_data_dir(&partition))))
+        .filter(InstallJournal::has_progress)
+        .map_or(InstallStep::FormatPartition, |journal| journal.resume_step())
+}
+
+/// 执行安装工作流
+///
+/// `start_from` 为开始的步骤，继续中断的安装时跳过日志中已完成的步骤。
+pub fn execute_install_workflow(backend: &dyn SystemBackend, tx: Sender<WorkerMessage>, start_from: InstallStep) {
     log::info!("========== 开始PE安装流程 ==========");
 
+    let mut rollback_ctx = RollbackContext::default();
+
     // 查找配置文件所在分区
-    let data_partition = match ConfigFileManager::find_data_partition() {
+    let data_partition = match backend.find_data_partition() {
         Some(p) => p,
         None => {
-            let _ = tx.send(WorkerMessage::Failed("未找到安装配置文件".to_string()));
+            fail_before_format(backend, &tx, &rollback_ctx, "查找配置", "未找到安装配置文件".to_string());
             return;
         }
     };
 
     log::info!("数据分区: {}", data_partition);
     let _ = tx.send(WorkerMessage::SetStatus(format!("数据分区: {}", data_partition)));
+    rollback_ctx.data_partition = Some(data_partition.clone());
+
+    // 日志中已有记录说明之前已开始改动目标分区，原系统不再完好，失败时不回滚
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let mut journal = if backend.is_dry_run() {
+        InstallJournal::open_read_only(backend.path(&data_dir))
+    } else {
+        InstallJournal::open(backend.path(&data_dir))
+    };
+    let can_rollback = !journal.has_progress();
+    let fail = |ctx: &RollbackContext, stage: &str, error: String| {
+        if can_rollback {
+            fail_before_format(backend, &tx, ctx, stage, error);
+        } else {
+            let _ = tx.send(WorkerMessage::Failed(error));
+        }
+    };
 
     // 读取安装配置
-    let config = match ConfigFileManager::read_install_config(&data_partition) {
+    let config = match backend.read_install_config(&data_partition) {
         Ok(c) => c,
         Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(format!("读取配置失败: {}", e)));
+            fail(&rollback_ctx, "读取配置", format!("读取配置失败: {:#}", e));
             return;
         }
     };
@@ -232,49 +375,188 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
     log::info!("目标分区: {}", config.target_partition);
     log::info!("镜像文件: {}", config.image_path);
 
-    // 查找安装标记分区
-    let target_partition = ConfigFileManager::find_install_marker_partition()
-        .unwrap_or_else(|| config.target_partition.clone());
+    // 按卷标识解析目标分区（盘符在 PE 中可能已变化），标记文件作为备用
+    let target_partition = backend.resolve_install_target(&config);
+    rollback_ctx.target_partition = Some(target_partition.clone());
+    rollback_ctx.original_guid = config.original_guid.clone();
+
+    if start_from != InstallStep::FormatPartition {
+        log::info!("继续上次中断的安装，从「{}」开始", start_from.name());
+    }
+
+    // BitLocker 锁定的分区无法核对，也不能直接格式化
+    if let Ok(FileSystemKind::BitLocker) = backend.volume_file_system(&target_partition) {
+        fail(
+            &rollback_ctx,
+            "检查目标分区",
+            format!("目标分区 {} 已被 BitLocker 锁定，请先在原系统中关闭 BitLocker 后重新发起安装", target_partition),
+        );
+        return;
+    }
+
+    // 核对目标分区是否仍是发起安装时的那个分区（之前已开始安装时以日志中记录的状态为准）
+    if let Err(e) = ConfigFileManager::check_target_snapshot(
+        &target_partition,
+        backend.partition_snapshot(&target_partition),
+        |actual| journal.expected_target(&config.target_snapshot, actual),
+    ) {
+        fail(&rollback_ctx, "检查目标分区", format!("{:#}", e));
+        return;
+    }
 
     // 构建完整镜像路径
-    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
     let image_path = format!("{}\\{}", data_dir, config.image_path);
 
-    if !std::path::Path::new(&image_path).exists() {
-        let _ = tx.send(WorkerMessage::Failed(format!("镜像文件不存在: {}", image_path)));
+    if start_from.index() <= InstallStep::ApplyImage.index() && !backend.path(&image_path).exists() {
+        fail(&rollback_ctx, "检查镜像文件", format!("镜像文件不存在: {}", image_path));
         return;
     }
 
     log::info!("完整镜像路径: {}", image_path);
 
-    // Step 1: 格式化分区
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::FormatPartition));
+    let ctx = InstallContext {
+        backend,
+        config,
+        data_partition,
+        data_dir,
+        target_partition,
+        image_path,
+    };
+
+    let steps = InstallStep::all()
+        .into_iter()
+        .filter(|step| *step != InstallStep::Complete && step.index() >= start_from.index());
+    for step in steps {
+        let _ = tx.send(WorkerMessage::SetInstallStep(step));
+        if let Err(e) = journal.begin(step) {
+            log::warn!("写入安装日志失败: {:#}", e);
+        }
+
+        match run_install_step(step, &ctx, &tx) {
+            Ok(mut outputs) => {
+                // 清理步骤已删除数据目录（包括日志本身），不再记录
+                if step != InstallStep::Cleanup {
+                    if let Ok(snapshot) = backend.partition_snapshot(&ctx.target_partition) {
+                        insert_snapshot(&mut outputs, &snapshot);
+                    }
+                    if let Err(e) = journal.complete(step, outputs) {
+                        log::warn!("写入安装日志失败: {:#}", e);
+                    }
+                }
+                let _ = tx.send(WorkerMessage::SetProgress(100));
+            }
+            Err(e) => {
+                let message = format!("{:#}", e);
+                if let Err(e) = journal.fail(step, &message) {
+                    log::warn!("写入安装日志失败: {:#}", e);
+                }
+                let _ = tx.send(WorkerMessage::Failed(message));
+                return;
+            }
+        }
+    }
+
+    // 完成
+    let reboot = ctx.config.auto_reboot;
+    let message = if reboot {
+        "系统安装完成！即将重启..."
+    } else {
+        "系统安装完成！请手动重启计算机。"
+    };
+    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Complete));
+    let _ = tx.send(WorkerMessage::Completed { message: message.to_string(), reboot });
+
+    log::info!("========== PE安装流程完成 ==========");
+
+    // 未勾选立即重启时留在 PE 中，由用户手动重启
+    if reboot {
+        log::info!("即将重启...");
+        backend.sleep(Duration::from_secs(3));
+        backend.reboot();
+    }
+}
+
+/// 安装在改动目标分区前失败：显示错误，稍后回滚到原系统并重启
+fn fail_before_format(
+    backend: &dyn SystemBackend,
+    tx: &Sender<WorkerMessage>,
+    ctx: &RollbackContext,
+    stage: &str,
+    error: String,
+) {
+    log::error!("安装在格式化前失败 ({}): {}", stage, error);
+    let _ = tx.send(WorkerMessage::Failed(error.clone()));
+    let _ = tx.send(WorkerMessage::SetStatus(format!(
+        "目标分区未被改动，{} 秒后回滚并重启到原系统...",
+        ROLLBACK_DELAY.as_secs()
+    )));
+    backend.sleep(ROLLBACK_DELAY);
+
+    let report = rollback::rollback_install(backend, ctx, stage, &error);
+    let status = if report.rollback_warnings.is_empty() {
+        "已回滚，正在重启到原系统...".to_string()
+    } else {
+        format!("回滚未完全完成（{}），正在重启...", report.rollback_warnings.join("；"))
+    };
+    let _ = tx.send(WorkerMessage::SetStatus(status));
+    backend.sleep(Duration::from_secs(3));
+    backend.reboot();
+}
+
+/// 执行单个安装步骤，返回需要记入日志的输出
+///
+/// 每个步骤都可以重复执行：继续中断的安装时，中断的步骤会从头再执行一次。
+fn run_install_step(
+    step: InstallStep,
+    ctx: &InstallContext,
+    tx: &Sender<WorkerMessage>,
+) -> anyhow::Result<StepOutputs> {
+    match step {
+        InstallStep::FormatPartition => step_format_partition(ctx, tx),
+        InstallStep::ApplyImage => step_apply_image(ctx, tx),
+        InstallStep::ImportDrivers => step_import_drivers(ctx, tx),
+        InstallStep::InstallCabPackages => step_install_cab_packages(ctx, tx),
+        InstallStep::RepairBoot => step_repair_boot(ctx, tx),
+        InstallStep::ApplyAdvancedOptions => step_apply_advanced_options(ctx, tx),
+        InstallStep::GenerateUnattend => step_generate_unattend(ctx, tx),
+        InstallStep::Cleanup => step_cleanup(ctx, tx),
+        InstallStep::Complete => Ok(StepOutputs::new()),
+    }
+}
+
+/// Step 1: 格式化分区
+fn step_format_partition(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
     let _ = tx.send(WorkerMessage::SetStatus("正在格式化目标分区...".to_string()));
 
     // 使用卷标参数（如果有配置的话）
-    let volume_label = if config.volume_label.is_empty() {
+    let volume_label = if ctx.config.volume_label.is_empty() {
         None
     } else {
-        Some(config.volume_label.as_str())
+        Some(ctx.config.volume_label.as_str())
     };
-    
-    match DiskManager::format_partition_with_label(&target_partition, volume_label) {
-        Ok(_) => {
-            log::info!("分区格式化成功");
-            let _ = tx.send(WorkerMessage::SetProgress(100));
-        }
-        Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(format!("格式化分区失败: {}", e)));
-            return;
+
+    ctx.backend
+        .format_partition(&ctx.target_partition, volume_label)
+        .context("格式化分区失败")?;
+    log::info!("分区格式化成功");
+    Ok(StepOutputs::new())
+}
+
+/// Step 2: 释放镜像
+///
+/// 释放 WIM/ESD 前先清空目标分区，中断后重新释放不需要再次格式化。
+fn step_apply_image(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
+    if ctx.config.is_gho {
+        if !ctx.backend.ghost_available() {
+            anyhow::bail!("Ghost工具不可用");
         }
+    } else {
+        let _ = tx.send(WorkerMessage::SetStatus("正在清空目标分区...".to_string()));
+        ctx.backend.clear_volume(&ctx.target_partition).context("清空目标分区失败")?;
     }
 
-    // Step 2: 释放镜像
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyImage));
     let _ = tx.send(WorkerMessage::SetStatus("正在释放系统镜像...".to_string()));
 
-    let apply_dir = format!("{}\\", target_partition);
-
     // 创建进度通道
     let (progress_tx, progress_rx) = channel::<DismProgress>();
     let tx_clone = tx.clone();
@@ -286,46 +568,37 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
         }
     });
 
-    let apply_result = if config.is_gho {
-        // GHO镜像使用Ghost
-        let ghost = Ghost::new();
-        if !ghost.is_available() {
-            let _ = tx.send(WorkerMessage::Failed("Ghost工具不可用".to_string()));
-            return;
-        }
-
-        let partitions = DiskManager::get_partitions().unwrap_or_default();
-        ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, Some(progress_tx))
-    } else {
-        // WIM/ESD使用DISM
-        let dism = Dism::new();
-        dism.apply_image(&image_path, &apply_dir, config.volume_index, Some(progress_tx))
-    };
+    let apply_result = ctx
+        .backend
+        .apply_image(&ctx.config, &ctx.image_path, &ctx.target_partition, Some(progress_tx));
 
     // 等待进度监控线程结束
     let _ = progress_handle.join();
 
-    if let Err(e) = apply_result {
-        let _ = tx.send(WorkerMessage::Failed(format!("释放镜像失败: {}", e)));
-        return;
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
+    apply_result.context("释放镜像失败")?;
+    Ok(StepOutputs::from([
+        ("image".to_string(), ctx.config.image_path.clone()),
+        ("volume_index".to_string(), ctx.config.volume_index.to_string()),
+    ]))
+}
 
-    // Step 3: 导入驱动
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ImportDrivers));
+/// Step 3: 导入驱动
+fn step_import_drivers(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
+    let apply_dir = ctx.apply_dir();
+    let config = &ctx.config;
 
     // 根据 driver_action_mode 决定是否导入驱动
     // 0 = 无, 1 = 仅保存（不导入）, 2 = 自动导入
-    let driver_path = format!("{}\\drivers", data_dir);
-    let driver_path_exists = std::path::Path::new(&driver_path).exists();
-    
-    if config.should_import_drivers() && driver_path_exists {
+    let driver_path = format!("{}\\drivers", ctx.data_dir);
+    let driver_path_exists = ctx.backend.path(&driver_path).exists();
+
+    let result = if config.should_import_drivers() && driver_path_exists {
         let _ = tx.send(WorkerMessage::SetStatus("正在导入驱动...".to_string()));
-        
+
         // 创建进度通道
         let (driver_progress_tx, driver_progress_rx) = channel::<DismProgress>();
         let tx_driver = tx.clone();
-        
+
         // 启动进度监控线程
         let driver_progress_handle = thread::spawn(move || {
             while let Ok(progress) = driver_progress_rx.recv() {
@@ -333,34 +606,35 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                 let _ = tx_driver.send(WorkerMessage::SetStatus(format!("导入驱动: {}", progress.status)));
             }
         });
-        
-        let dism = Dism::new();
-        match dism.add_drivers_offline_with_progress(&apply_dir, &driver_path, Some(driver_progress_tx)) {
+
+        let result = match ctx.backend.add_drivers(&apply_dir, &driver_path, Some(driver_progress_tx)) {
             Ok(_) => {
                 log::info!("驱动导入成功");
+                "imported"
             }
             Err(e) => {
                 log::warn!("导入驱动失败: {}", e);
                 // 不中断安装流程，继续执行
+                "failed"
             }
-        }
-        
+        };
+
         // 等待进度监控线程结束
         let _ = driver_progress_handle.join();
-        
+
         // 同时检查驱动目录中是否有 CAB 文件并安装
-        let cab_files_in_driver_dir = find_cab_files_in_directory(&driver_path);
+        let cab_files_in_driver_dir = find_cab_files_in_directory(&ctx.backend.path(&driver_path));
         if !cab_files_in_driver_dir.is_empty() {
             log::info!("在驱动目录中发现 {} 个 CAB 文件，将一并安装", cab_files_in_driver_dir.len());
             let _ = tx.send(WorkerMessage::SetStatus(format!(
-                "正在安装驱动目录中的 {} 个 CAB 更新包...", 
+                "正在安装驱动目录中的 {} 个 CAB 更新包...",
                 cab_files_in_driver_dir.len()
             )));
-            
+
             // 创建进度通道
             let (cab_progress_tx, cab_progress_rx) = channel::<DismProgress>();
             let tx_cab = tx.clone();
-            
+
             // 启动进度监控线程
             let cab_progress_handle = thread::spawn(move || {
                 while let Ok(progress) = cab_progress_rx.recv() {
@@ -368,9 +642,8 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                     let _ = tx_cab.send(WorkerMessage::SetStatus(format!("安装CAB: {}", progress.status)));
                 }
             });
-            
-            let dism = Dism::new();
-            match dism.add_packages_offline_from_dir(&apply_dir, &driver_path, Some(cab_progress_tx)) {
+
+            match ctx.backend.add_packages(&apply_dir, &driver_path, Some(cab_progress_tx)) {
                 Ok((success, fail)) => {
                     log::info!("驱动目录中的CAB安装完成: {} 成功, {} 失败", success, fail);
                 }
@@ -378,113 +651,154 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                     log::warn!("驱动目录中的CAB安装失败: {}", e);
                 }
             }
-            
+
             let _ = cab_progress_handle.join();
         }
+        result
     } else if config.should_import_drivers() && !driver_path_exists {
         log::info!("驱动目录不存在，跳过驱动导入: {}", driver_path);
         let _ = tx.send(WorkerMessage::SetStatus("跳过驱动导入（目录不存在）".to_string()));
+        "skipped"
     } else if config.has_driver_data() {
         // SaveOnly 模式：驱动已保存但不导入
         let _ = tx.send(WorkerMessage::SetStatus("跳过驱动导入（仅保存模式）".to_string()));
         log::info!("驱动操作模式为仅保存，跳过驱动导入");
+        "skipped"
     } else {
         let _ = tx.send(WorkerMessage::SetStatus("跳过驱动导入".to_string()));
         log::info!("驱动操作模式为无，跳过驱动导入");
-    }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
+        "skipped"
+    };
 
-    // Step 4: 安装CAB更新包
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::InstallCabPackages));
+    Ok(StepOutputs::from([("drivers".to_string(), result.to_string())]))
+}
 
-    if config.install_cab_packages {
-        let cab_path = format!("{}\\updates", data_dir);
-        if std::path::Path::new(&cab_path).exists() {
-            let _ = tx.send(WorkerMessage::SetStatus("正在安装更新包...".to_string()));
-            
-            // 创建进度通道
-            let (cab_progress_tx, cab_progress_rx) = channel::<DismProgress>();
-            let tx_cab = tx.clone();
-            
-            // 启动进度监控线程
-            let cab_progress_handle = thread::spawn(move || {
-                while let Ok(progress) = cab_progress_rx.recv() {
-                    let _ = tx_cab.send(WorkerMessage::SetProgress(progress.percentage));
-                    let _ = tx_cab.send(WorkerMessage::SetStatus(format!("安装更新: {}", progress.status)));
-                }
-            });
-            
-            let dism = Dism::new();
-            match dism.add_packages_offline_from_dir(&apply_dir, &cab_path, Some(cab_progress_tx)) {
-                Ok((success, fail)) => {
-                    log::info!("CAB更新包安装完成: {} 成功, {} 失败", success, fail);
-                    let _ = tx.send(WorkerMessage::SetStatus(
-                        format!("更新包安装完成: {} 成功, {} 失败", success, fail)
-                    ));
-                }
-                Err(e) => {
-                    log::warn!("CAB更新包安装失败: {}", e);
-                    // 不中断安装流程，继续执行
-                }
-            }
-            
-            // 等待进度监控线程结束
-            let _ = cab_progress_handle.join();
-        } else {
-            log::info!("更新包目录不存在，跳过CAB安装: {}", cab_path);
-            let _ = tx.send(WorkerMessage::SetStatus("跳过更新包安装（目录不存在）".to_string()));
-        }
-    } else {
+/// Step 4: 安装CAB更新包
+fn step_install_cab_packages(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
+    let mut outputs = StepOutputs::new();
+    if !ctx.config.install_cab_packages {
         let _ = tx.send(WorkerMessage::SetStatus("跳过更新包安装".to_string()));
         log::info!("未启用CAB更新包安装");
+        return Ok(outputs);
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
 
-    // Step 5: 修复引导
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::RepairBoot));
+    let cab_path = format!("{}\\updates", ctx.data_dir);
+    if !ctx.backend.path(&cab_path).exists() {
+        log::info!("更新包目录不存在，跳过CAB安装: {}", cab_path);
+        let _ = tx.send(WorkerMessage::SetStatus("跳过更新包安装（目录不存在）".to_string()));
+        return Ok(outputs);
+    }
+
+    let _ = tx.send(WorkerMessage::SetStatus("正在安装更新包...".to_string()));
+
+    // 创建进度通道
+    let (cab_progress_tx, cab_progress_rx) = channel::<DismProgress>();
+    let tx_cab = tx.clone();
+
+    // 启动进度监控线程
+    let cab_progress_handle = thread::spawn(move || {
+        while let Ok(progress) = cab_progress_rx.recv() {
+            let _ = tx_cab.send(WorkerMessage::SetProgress(progress.percentage));
+            let _ = tx_cab.send(WorkerMessage::SetStatus(format!("安装更新: {}", progress.status)));
+        }
+    });
+
+    // 已安装的更新包 DISM 会跳过，重复执行不影响结果
+    match ctx.backend.add_packages(&ctx.apply_dir(), &cab_path, Some(cab_progress_tx)) {
+        Ok((success, fail)) => {
+            log::info!("CAB更新包安装完成: {} 成功, {} 失败", success, fail);
+            let _ = tx.send(WorkerMessage::SetStatus(
+                format!("更新包安装完成: {} 成功, {} 失败", success, fail)
+            ));
+            outputs.insert("succeeded".to_string(), success.to_string());
+            outputs.insert("failed".to_string(), fail.to_string());
+        }
+        Err(e) => {
+            log::warn!("CAB更新包安装失败: {}", e);
+            // 不中断安装流程，继续执行
+        }
+    }
+
+    // 等待进度监控线程结束
+    let _ = cab_progress_handle.join();
+    Ok(outputs)
+}
+
+/// Step 5: 修复引导
+fn step_repair_boot(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
+    use crate::core::bcdedit::BootRepairOptions;
+
     let _ = tx.send(WorkerMessage::SetStatus("正在修复引导...".to_string()));
 
-    let boot_manager = BootManager::new();
-    let use_uefi = DiskManager::detect_uefi_mode();
+    let use_uefi = ctx.backend.detect_uefi_mode();
+
+    let repair_options = BootRepairOptions::from(&ctx.config);
+    let report = ctx
+        .backend
+        .repair_boot(&ctx.target_partition, use_uefi, &repair_options)
+        .context("修复引导失败")?;
+    log::info!("{}", report.summary());
+    if !repair_options.side_by_side {
+        if let Err(e) = ctx.backend.delete_stale_original_entry(
+            &ctx.config.original_guid,
+            &ctx.target_partition,
+            report.new_entry_guid.as_deref(),
+        ) {
+            log::warn!("删除原引导项失败: {}", e);
+        }
+    }
 
-    if let Err(e) = boot_manager.repair_boot_advanced(&target_partition, use_uefi) {
-        let _ = tx.send(WorkerMessage::Failed(format!("修复引导失败: {}", e)));
-        return;
+    // Win7 在 UEFI 下需要 UefiSeven 才能启动，补丁失败不中断安装
+    if use_uefi && ctx.config.win7_uefi_patch {
+        let _ = tx.send(WorkerMessage::SetStatus("正在应用 Win7 UEFI 补丁 (UefiSeven)...".to_string()));
+        if let Err(e) = ctx.backend.apply_uefiseven_patch(&ctx.data_partition, &ctx.target_partition) {
+            log::warn!("UefiSeven 补丁应用失败: {}", e);
+        }
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
 
-    // Step 6: 应用高级选项
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyAdvancedOptions));
+    let mut outputs = StepOutputs::from([(
+        "boot_mode".to_string(),
+        (if use_uefi { "uefi" } else { "legacy" }).to_string(),
+    )]);
+    if let Some(guid) = report.new_entry_guid {
+        outputs.insert("boot_entry".to_string(), guid);
+    }
+    Ok(outputs)
+}
+
+/// Step 6: 应用高级选项
+fn step_apply_advanced_options(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
     let _ = tx.send(WorkerMessage::SetStatus("正在应用高级选项...".to_string()));
 
-    if let Err(e) = apply_advanced_options(&target_partition, &config) {
+    if let Err(e) = ctx.backend.apply_advanced_options(&ctx.target_partition, &ctx.config) {
         log::warn!("应用高级选项失败: {}", e);
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // Step 7: 生成无人值守配置
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::GenerateUnattend));
+    Ok(StepOutputs::new())
+}
 
-    if config.unattended {
+/// Step 7: 生成无人值守配置
+fn step_generate_unattend(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
+    if ctx.config.unattended {
         let _ = tx.send(WorkerMessage::SetStatus("正在生成无人值守配置...".to_string()));
-        if let Err(e) = generate_unattend_xml(&target_partition, &config) {
+        if let Err(e) = generate_unattend_xml(ctx.backend, &ctx.target_partition, &ctx.config) {
             log::warn!("生成无人值守配置失败: {}", e);
         }
     } else {
         let _ = tx.send(WorkerMessage::SetStatus("跳过无人值守配置".to_string()));
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
+    Ok(StepOutputs::new())
+}
 
-    // Step 8: 清理临时文件
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Cleanup));
+/// Step 8: 清理临时文件
+fn step_cleanup(ctx: &InstallContext, tx: &Sender<WorkerMessage>) -> anyhow::Result<StepOutputs> {
     let _ = tx.send(WorkerMessage::SetStatus("正在清理临时文件...".to_string()));
 
-    ConfigFileManager::cleanup_all(&data_partition, &target_partition);
+    ctx.backend.cleanup_handoff(&ctx.data_partition, &ctx.target_partition);
     let _ = tx.send(WorkerMessage::SetProgress(50));
 
     // 清理自动创建的数据分区并扩展目标分区
     let _ = tx.send(WorkerMessage::SetStatus("正在清理自动创建的分区...".to_string()));
-    match DiskManager::cleanup_auto_created_partition_and_extend(&target_partition) {
+    match ctx.backend.remove_auto_created_partition(&ctx.target_partition) {
         Ok(_) => {
             log::info!("自动创建分区清理完成");
         }
@@ -493,31 +807,18 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
             log::warn!("清理自动创建分区失败: {}", e);
         }
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // 完成
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Complete));
-    let _ = tx.send(WorkerMessage::Completed);
-
-    log::info!("========== PE安装流程完成 ==========");
-
-    // PE环境下安装完成后强制重启
-    log::info!("即将重启...");
-    std::thread::sleep(std::time::Duration::from_secs(3));
-    reboot_pe();
+    Ok(StepOutputs::new())
 }
 
 /// 执行备份工作流
-fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
-    use crate::core::bcdedit::BootManager;
+pub fn execute_backup_workflow(backend: &dyn SystemBackend, tx: Sender<WorkerMessage>) {
     use crate::core::config::BackupFormat;
-    use crate::core::dism::Dism;
-    use crate::core::ghost::Ghost;
+    use crate::core::raw_image;
 
     log::info!("========== 开始PE备份流程 ==========");
 
     // 查找配置文件所在分区
-    let data_partition = match ConfigFileManager::find_data_partition() {
+    let data_partition = match backend.find_data_partition() {
         Some(p) => p,
         None => {
             let _ = tx.send(WorkerMessage::Failed("未找到备份配置文件".to_string()));
@@ -531,10 +832,10 @@ fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
     let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::ReadConfig));
     let _ = tx.send(WorkerMessage::SetStatus("正在读取备份配置...".to_string()));
 
-    let config = match ConfigFileManager::read_backup_config(&data_partition) {
+    let config = match backend.read_backup_config(&data_partition) {
         Ok(c) => c,
         Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(format!("读取配置失败: {}", e)));
+            let _ = tx.send(WorkerMessage::Failed(format!("读取配置失败: {:#}", e)));
             return;
         }
     };
@@ -547,14 +848,26 @@ fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
     }
     let _ = tx.send(WorkerMessage::SetProgress(100));
 
-    // 查找备份标记分区
-    let source_partition = ConfigFileManager::find_backup_marker_partition()
-        .unwrap_or_else(|| config.source_partition.clone());
+    // 按卷标识解析源分区（盘符在 PE 中可能已变化），标记文件作为备用
+    let source_partition = backend.resolve_backup_source(&config);
 
     // Step 2: 执行备份
     let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::CaptureImage));
-    
-    let capture_dir = format!("{}\\", source_partition);
+
+    let status = match config.format {
+        BackupFormat::Gho => {
+            if !backend.ghost_available() {
+                let _ = tx.send(WorkerMessage::Failed("Ghost工具不可用".to_string()));
+                return;
+            }
+            "正在使用Ghost备份系统...".to_string()
+        }
+        BackupFormat::Esd => "正在备份系统（ESD高压缩）...".to_string(),
+        BackupFormat::Swm => format!("正在备份系统（SWM分卷，每卷{}MB）...", config.swm_split_size),
+        BackupFormat::Raw => "正在创建原始扇区镜像...".to_string(),
+        BackupFormat::Wim => "正在执行系统备份...".to_string(),
+    };
+    let _ = tx.send(WorkerMessage::SetStatus(status));
 
     // 创建进度通道
     let (progress_tx, progress_rx) = channel::<DismProgress>();
@@ -566,78 +879,7 @@ fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
         }
     });
 
-    let backup_result = match config.format {
-        BackupFormat::Gho => {
-            // GHO格式使用Ghost
-            let _ = tx.send(WorkerMessage::SetStatus("正在使用Ghost备份系统...".to_string()));
-            let ghost = Ghost::new();
-            if !ghost.is_available() {
-                drop(progress_handle);
-                let _ = tx.send(WorkerMessage::Failed("Ghost工具不可用".to_string()));
-                return;
-            }
-            
-            // Ghost备份
-            ghost.create_image_from_letter(&source_partition, &config.save_path, Some(progress_tx))
-        }
-        BackupFormat::Esd => {
-            // ESD格式使用DISM高压缩
-            let _ = tx.send(WorkerMessage::SetStatus("正在备份系统（ESD高压缩）...".to_string()));
-            let dism = Dism::new();
-            if config.incremental && std::path::Path::new(&config.save_path).exists() {
-                dism.append_image_esd(
-                    &config.save_path,
-                    &capture_dir,
-                    &config.name,
-                    &config.description,
-                    Some(progress_tx),
-                )
-            } else {
-                dism.capture_image_esd(
-                    &config.save_path,
-                    &capture_dir,
-                    &config.name,
-                    &config.description,
-                    Some(progress_tx),
-                )
-            }
-        }
-        BackupFormat::Swm => {
-            // SWM分卷格式
-            let _ = tx.send(WorkerMessage::SetStatus(format!("正在备份系统（SWM分卷，每卷{}MB）...", config.swm_split_size).to_string()));
-            let dism = Dism::new();
-            dism.capture_image_swm(
-                &config.save_path,
-                &capture_dir,
-                &config.name,
-                &config.description,
-                config.swm_split_size,
-                Some(progress_tx),
-            )
-        }
-        BackupFormat::Wim => {
-            // 标准WIM格式
-            let _ = tx.send(WorkerMessage::SetStatus("正在执行系统备份...".to_string()));
-            let dism = Dism::new();
-            if config.incremental && std::path::Path::new(&config.save_path).exists() {
-                dism.append_image(
-                    &config.save_path,
-                    &capture_dir,
-                    &config.name,
-                    &config.description,
-                    Some(progress_tx),
-                )
-            } else {
-                dism.capture_image(
-                    &config.save_path,
-                    &capture_dir,
-                    &config.name,
-                    &config.description,
-                    Some(progress_tx),
-                )
-            }
-        }
-    };
+    let backup_result = backend.capture_image(&config, &source_partition, Some(progress_tx));
 
     // 等待进度监控线程结束
     let _ = progress_handle.join();
@@ -652,48 +894,52 @@ fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
     let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::VerifyBackup));
     let _ = tx.send(WorkerMessage::SetStatus("正在验证备份文件...".to_string()));
 
-    // 对于SWM格式，检查第一个分卷文件
-    let verify_path = if config.format == BackupFormat::Swm {
-        // SWM的第一个文件可能是 xxx.swm 或 xxx.swm
-        config.save_path.clone()
-    } else {
-        config.save_path.clone()
-    };
-    
-    if !std::path::Path::new(&verify_path).exists() {
+    // SWM格式检查第一个分卷文件（即保存路径）
+    let verify_path = backend.path(&config.save_path);
+    if !verify_path.exists() {
         let _ = tx.send(WorkerMessage::Failed("备份文件验证失败".to_string()));
         return;
     }
+    if config.format == BackupFormat::Raw {
+        let tx_verify = tx.clone();
+        let verified = raw_image::verify_image(&verify_path, &mut |percentage, _| {
+            let _ = tx_verify.send(WorkerMessage::SetProgress(percentage));
+        });
+        if let Err(e) = verified {
+            let _ = tx.send(WorkerMessage::Failed(format!("备份文件验证失败: {}", e)));
+            return;
+        }
+    }
     let _ = tx.send(WorkerMessage::SetProgress(100));
 
     // Step 4: 恢复引导
     let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::RepairBoot));
     let _ = tx.send(WorkerMessage::SetStatus("正在恢复引导...".to_string()));
 
-    let boot_manager = BootManager::new();
     // 删除当前PE引导项
-    let _ = boot_manager.delete_current_boot_entry();
+    let _ = backend.delete_current_boot_entry();
     let _ = tx.send(WorkerMessage::SetProgress(100));
 
     // Step 5: 清理
     let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::Cleanup));
     let _ = tx.send(WorkerMessage::SetStatus("正在清理临时文件...".to_string()));
 
-    ConfigFileManager::cleanup_partition_markers(&source_partition);
-    ConfigFileManager::cleanup_data_dir(&data_partition);
-    ConfigFileManager::cleanup_pe_dir(&data_partition);
+    backend.cleanup_handoff(&data_partition, &source_partition);
     let _ = tx.send(WorkerMessage::SetProgress(100));
 
     // 完成
     let _ = tx.send(WorkerMessage::SetBackupStep(BackupStep::Complete));
-    let _ = tx.send(WorkerMessage::Completed);
+    let _ = tx.send(WorkerMessage::Completed {
+        message: format!("系统备份完成！保存位置: {}，即将重启...", config.save_path),
+        reboot: true,
+    });
 
     log::info!("========== PE备份流程完成 ==========");
 
     // 自动重启
     log::info!("即将重启...");
-    std::thread::sleep(std::time::Duration::from_secs(3));
-    reboot_pe();
+    backend.sleep(Duration::from_secs(3));
+    backend.reboot();
 }
 
 /// 生成无人值守XML
@@ -708,10 +954,14 @@ fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
 /// - windowsPE pass: 基本设置
 /// - specialize pass: 部署脚本执行
 /// - oobeSystem pass: OOBE设置、用户账户、首次登录命令
-fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::InstallConfig) -> anyhow::Result<()> {
+fn generate_unattend_xml(
+    backend: &dyn SystemBackend,
+    target_partition: &str,
+    config: &crate::core::config::InstallConfig,
+) -> anyhow::Result<()> {
     use crate::ui::advanced_options::get_scripts_dir_name;
     use crate::core::system_utils::{get_file_version, get_offline_system_architecture};
-    use std::path::Path;
+    use crate::core::unattend::{Command, UnattendSettings, WindowsVersion};
     
     let username = if config.custom_username.is_empty() { 
         "User".to_string() 
@@ -719,313 +969,341 @@ fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::I
         config.custom_username.clone() 
     };
 
-    let scripts_dir = get_scripts_dir_name();
+    let scripts_dir = format!("%SystemDrive%\\{}", get_scripts_dir_name());
 
     // 检测目标系统架构
-    let arch = get_offline_system_architecture(Path::new(target_partition));
+    let target_root = backend.path(target_partition);
+    let arch = get_offline_system_architecture(&target_root);
     let arch_str = arch.as_unattend_str();
     log::info!("[UNATTEND] 检测到目标系统架构: {}", arch_str);
 
     // 通过 ntdll.dll 文件版本检测目标系统版本
-    // Windows 7: 6.1.x, Windows 8: 6.2.x, Windows 8.1: 6.3.x, Windows 10/11: 10.0.x
-    let ntdll_path = Path::new(target_partition).join("Windows").join("System32").join("ntdll.dll");
-    let (is_win7, is_win8) = match get_file_version(&ntdll_path) {
+    let ntdll_path = target_root.join("Windows").join("System32").join("ntdll.dll");
+    let version = match get_file_version(&ntdll_path) {
         Some((major, minor, build, _)) => {
             log::info!("[UNATTEND] 检测到目标系统版本 (ntdll.dll): {}.{}.{}", major, minor, build);
-            
-            let is_win7 = major == 6 && minor == 1;
-            let is_win8 = major == 6 && (minor == 2 || minor == 3);
-            (is_win7, is_win8)
+            WindowsVersion::from_nt_version(major, minor)
         }
         None => {
             log::warn!("[UNATTEND] 无法读取 ntdll.dll 版本: {:?}, 默认使用 Win10/11 配置", ntdll_path);
-            (false, false)
+            WindowsVersion::Win10
         }
     };
 
-    // 构建 FirstLogonCommands
-    let mut first_logon_commands = String::new();
-    let mut order = 1;
-
     // 首次登录脚本（如果存在）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c if exist %SystemDrive%\{}\firstlogon.bat call %SystemDrive%\{}\firstlogon.bat</CommandLine>
-                    <Description>Run first login script</Description>
-                </SynchronousCommand>"#, order, scripts_dir, scripts_dir));
-    order += 1;
+    let mut first_logon_commands = vec![Command::new(
+        format!("cmd /c if exist {0}\\firstlogon.bat call {0}\\firstlogon.bat", scripts_dir),
+        "Run first login script",
+    )];
 
     // 如果需要删除UWP应用（仅 Win10/11 支持）
-    if config.remove_uwp_apps && !is_win7 && !is_win8 {
-        first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>powershell -ExecutionPolicy Bypass -File %SystemDrive%\{}\remove_uwp.ps1</CommandLine>
-                    <Description>Remove preinstalled UWP apps</Description>
-                </SynchronousCommand>"#, order, scripts_dir));
-        order += 1;
+    if config.remove_uwp_apps && version.supports_uwp_removal() {
+        first_logon_commands.push(Command::new(
+            format!("powershell -ExecutionPolicy Bypass -File {}\\remove_uwp.ps1", scripts_dir),
+            "Remove preinstalled UWP apps",
+        ));
     }
 
     // 清理脚本目录（最后执行）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c rd /s /q %SystemDrive%\{}</CommandLine>
-                    <Description>Cleanup scripts directory</Description>
-                </SynchronousCommand>"#, order, scripts_dir));
-
-    // 根据系统版本生成不同的 XML 内容
-    let xml_content = if is_win7 {
-        // Windows 7 专用无人值守配置
-        // Win7 不支持: HideOnlineAccountScreens, HideWirelessSetupInOOBE, SkipMachineOOBE, SkipUserOOBE, HideLocalAccountScreen, HideOEMRegistrationScreen(家庭版)
-        generate_win7_unattend_xml(&username, &scripts_dir, &first_logon_commands, arch_str)
-    } else if is_win8 {
-        // Windows 8/8.1 无人值守配置
-        // Win8 支持部分 Win10 的选项，但不支持所有
-        generate_win8_unattend_xml(&username, &scripts_dir, &first_logon_commands, arch_str)
-    } else {
-        // Windows 10/11 无人值守配置（默认）
-        generate_win10_unattend_xml(&username, &scripts_dir, &first_logon_commands, arch_str)
-    };
-
-    let panther_dir = format!("{}\\Windows\\Panther", target_partition);
-    std::fs::create_dir_all(&panther_dir)?;
+    first_logon_commands.push(Command::new(
+        format!("cmd /c rd /s /q {}", scripts_dir),
+        "Cleanup scripts directory",
+    ));
+
+    let xml_content = UnattendSettings {
+        version,
+        arch: arch_str.to_string(),
+        username,
+        locale: None,
+        deploy_commands: vec![Command::new(
+            format!("cmd /c if exist {0}\\deploy.bat call {0}\\deploy.bat", scripts_dir),
+            "Run custom deploy script",
+        )],
+        first_logon_commands,
+    }
+    .to_xml();
 
-    let unattend_path = format!("{}\\unattend.xml", panther_dir);
-    std::fs::write(&unattend_path, &xml_content)?;
-    log::info!("[UNATTEND] 已写入: {} ({})", unattend_path, 
-        if is_win7 { "Win7配置" } else if is_win8 { "Win8配置" } else { "Win10/11配置" });
+    let unattend_path = format!("{}\\Windows\\Panther\\unattend.xml", target_partition);
+    backend.write_file(&unattend_path, &xml_content)?;
+    log::info!("[UNATTEND] 已写入: {} ({})", unattend_path, version.name());
 
     // 同时写入到 Sysprep 目录
     let sysprep_dir = format!("{}\\Windows\\System32\\Sysprep", target_partition);
-    if std::path::Path::new(&sysprep_dir).exists() {
+    if backend.path(&sysprep_dir).exists() {
         let sysprep_unattend = format!("{}\\unattend.xml", sysprep_dir);
-        let _ = std::fs::write(&sysprep_unattend, &xml_content);
+        let _ = backend.write_file(&sysprep_unattend, &xml_content);
         log::info!("[UNATTEND] 已写入: {}", sysprep_unattend);
     }
 
     Ok(())
 }
 
-/// 生成 Windows 7 专用的无人值守配置
-/// 
-/// Win7 的 OOBE 配置与 Win10/11 有显著差异：
-/// - 不支持 HideOnlineAccountScreens
-/// - 不支持 HideWirelessSetupInOOBE  
-/// - 不支持 SkipMachineOOBE / SkipUserOOBE
-/// - 不支持 HideLocalAccountScreen
-/// - 不支持 HideOEMRegistrationScreen（家庭版不支持）
-/// - 需要设置 NetworkLocation 来跳过网络位置选择
-fn generate_win7_unattend_xml(username: &str, scripts_dir: &str, first_logon_commands: &str, arch: &str) -> String {
-    // Win7 使用最小化的OOBE配置以确保兼容所有版本（包括家庭版）
-    format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{scripts_dir}\deploy.bat call %SystemDrive%\{scripts_dir}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch, scripts_dir = scripts_dir, username = username, first_logon_commands = first_logon_commands)
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::backend::fake::FakeBackend;
+    use crate::core::config::{BackupConfig, BackupFormat};
+    use letrecovery_config::{
+        to_json, unix_now, PartitionSnapshot, BACKUP_CONFIG_FILE, FAILURE_REPORT_FILE, INSTALL_CONFIG_FILE,
+    };
 
-/// 生成 Windows 8/8.1 专用的无人值守配置
-/// 
-/// Win8/8.1 支持部分 Win10 的选项：
-/// - 支持 HideLocalAccountScreen
-/// - 不支持 HideOnlineAccountScreens
-/// - 不支持 HideWirelessSetupInOOBE
-/// - 不支持 SkipMachineOOBE / SkipUserOOBE
-fn generate_win8_unattend_xml(username: &str, scripts_dir: &str, first_logon_commands: &str, arch: &str) -> String {
-    format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{scripts_dir}\deploy.bat call %SystemDrive%\{scripts_dir}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch, scripts_dir = scripts_dir, username = username, first_logon_commands = first_logon_commands)
-}
+    const DATA_DIR: &str = "D:\\LetRecovery_Data";
 
-/// 生成 Windows 10/11 无人值守配置
-/// 
-/// 完整支持所有 OOBE 跳过选项：
-/// - HideLocalAccountScreen
-/// - HideOnlineAccountScreens
-/// - HideWirelessSetupInOOBE
-/// - SkipMachineOOBE
-/// - SkipUserOOBE
-fn generate_win10_unattend_xml(username: &str, scripts_dir: &str, first_logon_commands: &str, arch: &str) -> String {
-    format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{scripts_dir}\deploy.bat call %SystemDrive%\{scripts_dir}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-                <SkipMachineOOBE>true</SkipMachineOOBE>
-                <SkipUserOOBE>true</SkipUserOOBE>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch, scripts_dir = scripts_dir, username = username, first_logon_commands = first_logon_commands)
+    fn snapshot(serial: u32) -> PartitionSnapshot {
+        PartitionSnapshot { size_bytes: 64 << 30, serial, label: "System".to_string() }
+    }
+
+    /// 数据分区 D: 上准备好配置和镜像，目标分区 C: 上有旧系统
+    fn install_backend(name: &str) -> FakeBackend {
+        install_backend_with(name, |_| {})
+    }
+
+    fn install_backend_with(name: &str, customize: impl FnOnce(&mut InstallConfig)) -> FakeBackend {
+        let backend = FakeBackend::new(name);
+        let mut config = InstallConfig {
+            target_partition: "C:".to_string(),
+            target_snapshot: snapshot(0x1234),
+            image_path: "install.wim".to_string(),
+            volume_index: 1,
+            volume_label: "Windows".to_string(),
+            original_guid: "{original}".to_string(),
+            unattended: true,
+            auto_reboot: true,
+            ..Default::default()
+        };
+        customize(&mut config);
+        backend.set_snapshot("C:", snapshot(0x1234));
+
+        let data_dir = backend.path(DATA_DIR);
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("install.wim"), b"wim").unwrap();
+        std::fs::write(data_dir.join(INSTALL_CONFIG_FILE), to_json(&config, unix_now(), &backend.nonce)).unwrap();
+        std::fs::create_dir_all(backend.path("C:\\Windows\\System32")).unwrap();
+        std::fs::write(backend.path("C:\\old_file.txt"), b"old").unwrap();
+        backend
+    }
+
+    fn run_install(backend: &FakeBackend, start_from: InstallStep) -> Vec<WorkerMessage> {
+        let (tx, rx) = channel();
+        execute_install_workflow(backend, tx, start_from);
+        rx.try_iter().collect()
+    }
+
+    fn completed(messages: &[WorkerMessage]) -> bool {
+        messages.iter().any(|m| matches!(m, WorkerMessage::Completed { .. }))
+    }
+
+    fn failure(messages: &[WorkerMessage]) -> Option<&str> {
+        messages.iter().find_map(|m| match m {
+            WorkerMessage::Failed(e) => Some(e.as_str()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_install_workflow_runs_all_steps() {
+        let backend = install_backend("install");
+        let messages = run_install(&backend, InstallStep::FormatPartition);
+
+        assert!(completed(&messages), "{:?}", failure(&messages));
+        assert_eq!(
+            backend.operations(),
+            [
+                "format_partition",
+                "apply_image",
+                "repair_boot",
+                "delete_stale_original_entry",
+                "apply_advanced_options",
+                "cleanup_handoff",
+                "remove_auto_created_partition",
+                "reboot",
+            ]
+        );
+        assert!(!backend.path("C:\\old_file.txt").exists());
+        assert!(backend.path("C:\\Windows\\Panther\\unattend.xml").is_file());
+        assert!(!backend.path(DATA_DIR).exists());
+    }
+
+    #[test]
+    fn test_install_without_auto_reboot_stays_in_pe() {
+        let backend = install_backend_with("no_reboot", |config| config.auto_reboot = false);
+        let messages = run_install(&backend, InstallStep::FormatPartition);
+
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            WorkerMessage::Completed { message, reboot: false } if message.contains("请手动重启")
+        )));
+        let operations = backend.operations();
+        assert!(operations.contains(&"cleanup_handoff".to_string()));
+        assert!(!operations.contains(&"reboot".to_string()));
+    }
+
+    #[test]
+    fn test_install_applies_uefiseven_patch() {
+        let backend = install_backend_with("uefiseven", |config| config.win7_uefi_patch = true);
+        backend.fail_on("apply_uefiseven_patch");
+        let messages = run_install(&backend, InstallStep::FormatPartition);
+
+        // 补丁失败不中断安装
+        assert!(completed(&messages), "{:?}", failure(&messages));
+        assert!(backend.calls().contains(&"apply_uefiseven_patch D: C:".to_string()));
+    }
+
+    #[test]
+    fn test_install_rolls_back_when_target_changed() {
+        let backend = install_backend("rollback");
+        backend.set_snapshot("C:", snapshot(0x9999));
+        let messages = run_install(&backend, InstallStep::FormatPartition);
+
+        assert!(failure(&messages).is_some_and(|e| e.contains("拒绝格式化")));
+        let operations = backend.operations();
+        assert!(!operations.contains(&"format_partition".to_string()));
+        assert_eq!(operations.last().map(String::as_str), Some("reboot"));
+        assert!(backend.path("C:\\old_file.txt").exists());
+        assert!(backend.path(&format!("C:\\{}", FAILURE_REPORT_FILE)).is_file());
+    }
+
+    #[test]
+    fn test_install_resumes_from_failed_step() {
+        let backend = install_backend("resume");
+        backend.fail_on("repair_boot");
+        let messages = run_install(&backend, InstallStep::FormatPartition);
+        assert!(failure(&messages).is_some_and(|e| e.contains("修复引导失败")));
+        assert!(!backend.path(&format!("C:\\{}", FAILURE_REPORT_FILE)).exists());
+
+        let journal = InstallJournal::open(backend.path(DATA_DIR));
+        assert_eq!(journal.resume_step(), InstallStep::RepairBoot);
+
+        // 格式化改变了序列号，继续时按日志中的快照核对
+        backend.clear_failures();
+        backend.clear_calls();
+        let messages = run_install(&backend, journal.resume_step());
+        assert!(completed(&messages), "{:?}", failure(&messages));
+        let operations = backend.operations();
+        assert_eq!(operations.first().map(String::as_str), Some("repair_boot"));
+        assert!(!operations.contains(&"format_partition".to_string()));
+        assert!(!operations.contains(&"apply_image".to_string()));
+    }
+
+    #[test]
+    fn test_dry_run_plans_install_without_changes() {
+        use crate::core::disk::AUTO_CREATED_PARTITION_MARKER;
+        use crate::core::journal::JOURNAL_FILE;
+        use letrecovery_config::{DriverActionMode, PlanAction};
+
+        let backend = install_backend_with("dry_run", |config| {
+            config.volume_index = 3;
+            config.driver_action_mode = DriverActionMode::AutoImport;
+            config.install_cab_packages = true;
+            config.remove_shortcut_arrow = true;
+            config.disable_uac = true;
+        });
+        let driver_dir = backend.path(&format!("{}\\drivers\\net", DATA_DIR));
+        std::fs::create_dir_all(&driver_dir).unwrap();
+        std::fs::write(driver_dir.join("net.inf"), b"inf").unwrap();
+        std::fs::create_dir_all(backend.path(&format!("{}\\updates", DATA_DIR))).unwrap();
+        std::fs::write(backend.path(&format!("{}\\updates\\kb1.cab", DATA_DIR)), b"cab").unwrap();
+        std::fs::create_dir_all(backend.path("Y:")).unwrap();
+        std::fs::write(backend.path(&format!("Y:\\{}", AUTO_CREATED_PARTITION_MARKER)), b"").unwrap();
+
+        let plan = plan_install(&backend);
+        assert!(plan.notes.is_empty(), "{:?}", plan.notes);
+        let expected = [
+            PlanAction::FormatPartition { partition: "C:".to_string(), label: Some("Windows".to_string()) },
+            PlanAction::ClearPartition { partition: "C:".to_string() },
+            PlanAction::ApplyImage {
+                image: format!("{}\\install.wim", DATA_DIR),
+                volume_index: 3,
+                ghost: false,
+                partition: "C:".to_string(),
+            },
+            PlanAction::InjectDrivers {
+                partition: "C:".to_string(),
+                source: format!("{}\\drivers", DATA_DIR),
+                drivers: vec!["net\\net.inf".to_string()],
+            },
+            PlanAction::InstallPackages {
+                partition: "C:".to_string(),
+                source: format!("{}\\updates", DATA_DIR),
+                packages: vec!["kb1.cab".to_string()],
+            },
+            PlanAction::WriteBootEntry {
+                partition: "C:".to_string(),
+                uefi: Some(true),
+                description: String::new(),
+                side_by_side: false,
+            },
+            PlanAction::DeleteBootEntry {
+                identifier: "{original}".to_string(),
+                reason: "原系统引导项，仍指向 C: 时删除".to_string(),
+            },
+            PlanAction::ApplyTweaks {
+                partition: "C:".to_string(),
+                tweaks: vec!["移除快捷方式小箭头".to_string(), "禁用用户账户控制".to_string()],
+            },
+            PlanAction::WriteFile {
+                path: "C:\\Windows\\Panther\\unattend.xml".to_string(),
+                purpose: "无人值守配置".to_string(),
+            },
+            PlanAction::CleanupHandoff { data_partition: "D:".to_string(), target_partition: "C:".to_string() },
+            PlanAction::DeletePartition { partition: "Y:".to_string(), merge_into: "C:".to_string() },
+            PlanAction::Reboot,
+        ];
+        assert_eq!(plan.actions, expected);
+
+        // 没有执行任何操作，也没有写入日志
+        assert!(backend.calls().is_empty(), "{:?}", backend.calls());
+        assert!(backend.path("C:\\old_file.txt").exists());
+        assert!(!backend.path("C:\\Windows\\Panther").exists());
+        assert!(!backend.path(DATA_DIR).join(JOURNAL_FILE).exists());
+    }
+
+    #[test]
+    fn test_dry_run_reports_rollback() {
+        let backend = install_backend("dry_run_rollback");
+        backend.set_snapshot("C:", snapshot(0x9999));
+
+        let plan = plan_install(&backend);
+        let expected = [
+            letrecovery_config::PlanAction::WriteFile {
+                path: format!("C:\\{}", FAILURE_REPORT_FILE),
+                purpose: "失败报告".to_string(),
+            },
+            letrecovery_config::PlanAction::Reboot,
+        ];
+        assert_eq!(plan.actions, expected);
+        assert!(plan.notes.iter().any(|n| n.contains("将删除 D:\\LetRecovery_Data")));
+        assert!(plan.to_text().contains("实际运行时将失败"));
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn test_backup_workflow() {
+        let backend = FakeBackend::new("backup");
+        let config = BackupConfig {
+            save_path: "E:\\Backup\\system.wim".to_string(),
+            name: "Backup".to_string(),
+            source_partition: "C:".to_string(),
+            format: BackupFormat::Wim,
+            ..Default::default()
+        };
+        let data_dir = backend.path(DATA_DIR);
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join(BACKUP_CONFIG_FILE), to_json(&config, unix_now(), &backend.nonce)).unwrap();
+
+        let (tx, rx) = channel();
+        execute_backup_workflow(&backend, tx);
+        let messages: Vec<_> = rx.try_iter().collect();
+
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            WorkerMessage::Completed { message, reboot: true } if message.contains("E:\\Backup\\system.wim")
+        )));
+        assert_eq!(
+            backend.operations(),
+            ["capture_image", "delete_current_boot_entry", "cleanup_handoff", "reboot"]
+        );
+        assert!(backend.path(&config.save_path).is_file());
+        assert!(!data_dir.exists());
+    }
 }
@@ -0,0 +1,780 @@
+//! 系统操作后端
+//!
+//! 安装、备份流程通过 [`SystemBackend`] 访问磁盘、卷、镜像、引导和注册表，
+//! 不直接调用 diskpart/bcdboot/DISM/reg 和 Win32 API。
+//! 实际运行时通过 [`native_backend`] 使用 `WindowsBackend`；测试中使用 [`fake::FakeBackend`]，
+//! 它把盘符映射到临时目录并记录每项操作，整个流程可以在 Linux 上运行。
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use letrecovery_config::PartitionSnapshot;
+
+use crate::core::bcdedit::{BootManager, BootRepairOptions, BootRepairReport};
+use crate::core::config::{BackupConfig, BackupFormat, ConfigFileManager, InstallConfig};
+use crate::core::disk::DiskManager;
+use crate::core::dism::{Dism, DismProgress};
+use crate::core::esp_inventory::BcdLoaderEntry;
+use crate::core::ghost::Ghost;
+use crate::core::raw_image::{self, FileSystemKind};
+use crate::core::volume_id;
+
+/// 系统操作后端
+///
+/// 分区、路径均使用 Windows 形式（如 `C:`、`D:\LetRecovery_Data`），
+/// 流程中直接读写文件时先用 [`SystemBackend::path`] 转换为本地路径。
+pub trait SystemBackend {
+    // ---------- 文件 ----------
+
+    /// 把 Windows 路径转换为可直接读写的本地路径
+    fn path(&self, path: &str) -> PathBuf;
+
+    /// 写入文件，父目录不存在时创建
+    fn write_file(&self, path: &str, content: &str) -> Result<()> {
+        let path = self.path(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content).with_context(|| format!("写入文件失败: {}", path.display()))
+    }
+
+    /// 删除文件或目录，返回是否确实删除了（不存在时为 `false`）
+    fn remove_path(&self, path: &str) -> Result<bool> {
+        let path = self.path(path);
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("删除失败: {}", path.display())),
+        }
+    }
+
+    // ---------- 卷 ----------
+
+    /// 查找包含配置文件的数据分区
+    fn find_data_partition(&self) -> Option<String>;
+
+    /// 查找包含安装标记文件的分区
+    fn find_install_marker_partition(&self) -> Option<String>;
+
+    /// 读取并校验安装配置
+    fn read_install_config(&self, data_partition: &str) -> Result<InstallConfig>;
+
+    /// 读取并校验备份配置
+    fn read_backup_config(&self, data_partition: &str) -> Result<BackupConfig>;
+
+    /// 解析安装目标分区
+    fn resolve_install_target(&self, config: &InstallConfig) -> String;
+
+    /// 解析备份源分区
+    fn resolve_backup_source(&self, config: &BackupConfig) -> String;
+
+    /// 分区当前的大小、序列号和卷标
+    fn partition_snapshot(&self, partition: &str) -> Result<PartitionSnapshot>;
+
+    /// 从引导扇区识别分区的文件系统
+    fn volume_file_system(&self, partition: &str) -> Result<FileSystemKind>;
+
+    /// 查找自动创建的数据分区
+    fn auto_created_partition(&self) -> Option<String>;
+
+    /// 删除标记文件、数据目录和 PE 目录
+    fn cleanup_handoff(&self, data_partition: &str, target_partition: &str);
+
+    // ---------- 格式化 ----------
+
+    /// 格式化分区
+    fn format_partition(&self, partition: &str, label: Option<&str>) -> Result<()>;
+
+    /// 清空分区根目录（保留 System Volume Information），用于重新释放镜像
+    fn clear_volume(&self, partition: &str) -> Result<()> {
+        let root = self.path(&format!("{}\\", partition.trim_end_matches('\\')));
+        clear_volume_contents(&root).with_context(|| format!("清空 {} 失败", root.display()))
+    }
+
+    /// 删除自动创建的数据分区并扩展目标分区
+    fn remove_auto_created_partition(&self, target_partition: &str) -> Result<()>;
+
+    // ---------- 镜像 ----------
+
+    /// Ghost 工具是否可用
+    fn ghost_available(&self) -> bool;
+
+    /// 把镜像释放到目标分区（GHO 使用 Ghost，其余使用 DISM）
+    fn apply_image(
+        &self,
+        config: &InstallConfig,
+        image_path: &str,
+        target_partition: &str,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()>;
+
+    /// 按备份配置的格式备份源分区
+    fn capture_image(
+        &self,
+        config: &BackupConfig,
+        source_partition: &str,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()>;
+
+    /// 向离线系统导入驱动
+    fn add_drivers(&self, apply_dir: &str, driver_dir: &str, progress_tx: Option<Sender<DismProgress>>) -> Result<()>;
+
+    /// 向离线系统安装目录中的 CAB 包，返回 (成功数, 失败数)
+    fn add_packages(
+        &self,
+        apply_dir: &str,
+        package_dir: &str,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<(usize, usize)>;
+
+    // ---------- 引导 ----------
+
+    /// 当前是否以 UEFI 模式启动
+    fn detect_uefi_mode(&self) -> bool;
+
+    /// 为目标系统修复引导
+    fn repair_boot(
+        &self,
+        target_partition: &str,
+        use_uefi: bool,
+        options: &BootRepairOptions,
+    ) -> Result<BootRepairReport>;
+
+    /// 删除被新系统取代的原引导项
+    fn delete_stale_original_entry(
+        &self,
+        original_guid: &str,
+        target_partition: &str,
+        new_entry_guid: Option<&str>,
+    ) -> Result<bool>;
+
+    /// 删除当前（PE）引导项
+    fn delete_current_boot_entry(&self) -> Result<()>;
+
+    /// 用数据目录中的 UefiSeven 替换 EFI 分区上的 bootmgfw.efi（Win7 UEFI 启动）
+    fn apply_uefiseven_patch(&self, data_partition: &str, target_partition: &str) -> Result<()>;
+
+    /// 当前启动的引导项（PE 自身）
+    fn current_boot_entry(&self) -> Result<BcdLoaderEntry>;
+
+    /// 启动管理器的默认引导项 GUID
+    fn default_boot_entry(&self) -> Result<String>;
+
+    /// 设置默认引导项
+    fn set_default_boot_entry(&self, guid: &str) -> Result<()>;
+
+    /// 列出所有 Windows 启动加载器条目
+    fn list_boot_entries(&self) -> Result<Vec<BcdLoaderEntry>>;
+
+    /// 删除引导项
+    fn delete_boot_entry(&self, guid: &str) -> Result<()>;
+
+    // ---------- 注册表 ----------
+
+    /// 向离线系统的注册表应用高级选项
+    fn apply_advanced_options(&self, target_partition: &str, config: &InstallConfig) -> Result<()>;
+
+    // ---------- 系统 ----------
+
+    /// 等待（留给用户阅读提示）
+    fn sleep(&self, duration: Duration);
+
+    /// 结束 PE 并重启
+    fn reboot(&self);
+
+    /// 是否为演练（不改动任何东西，流程也不写安装日志）
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+}
+
+/// 清空目录（保留 System Volume Information）
+// Windows 下 set_readonly(false) 只是去掉只读属性
+#[allow(clippy::permissions_set_readonly_false)]
+fn clear_volume_contents(root: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().eq_ignore_ascii_case("System Volume Information") {
+            continue;
+        }
+
+        // 系统镜像中有只读文件，删除前先去掉只读属性
+        let path = entry.path();
+        for item in walkdir::WalkDir::new(&path).into_iter().flatten() {
+            if let Ok(metadata) = item.metadata() {
+                let mut permissions = metadata.permissions();
+                if permissions.readonly() {
+                    permissions.set_readonly(false);
+                    let _ = std::fs::set_permissions(item.path(), permissions);
+                }
+            }
+        }
+
+        if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// 当前平台上的实际后端
+///
+/// 只有 Windows 上有实际后端，其他平台只用于编译和运行测试。
+pub fn native_backend() -> Result<&'static (dyn SystemBackend + Sync)> {
+    #[cfg(windows)]
+    {
+        Ok(&WindowsBackend)
+    }
+
+    #[cfg(not(windows))]
+    {
+        anyhow::bail!("仅支持Windows系统")
+    }
+}
+
+/// 实际的 Windows PE 环境
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowsBackend;
+
+#[cfg(windows)]
+impl SystemBackend for WindowsBackend {
+    fn path(&self, path: &str) -> PathBuf {
+        // 单独的盘符（`C:`）表示当前目录，需补上根目录
+        if path.len() == 2 && path.ends_with(':') {
+            PathBuf::from(format!("{}\\", path))
+        } else {
+            PathBuf::from(path)
+        }
+    }
+
+    fn find_data_partition(&self) -> Option<String> {
+        ConfigFileManager::find_data_partition()
+    }
+
+    fn find_install_marker_partition(&self) -> Option<String> {
+        ConfigFileManager::find_install_marker_partition()
+    }
+
+    fn read_install_config(&self, data_partition: &str) -> Result<InstallConfig> {
+        ConfigFileManager::read_install_config(data_partition)
+    }
+
+    fn read_backup_config(&self, data_partition: &str) -> Result<BackupConfig> {
+        ConfigFileManager::read_backup_config(data_partition)
+    }
+
+    fn resolve_install_target(&self, config: &InstallConfig) -> String {
+        ConfigFileManager::resolve_install_target(config)
+    }
+
+    fn resolve_backup_source(&self, config: &BackupConfig) -> String {
+        ConfigFileManager::resolve_backup_source(config)
+    }
+
+    fn partition_snapshot(&self, partition: &str) -> Result<PartitionSnapshot> {
+        volume_id::partition_snapshot(partition)
+    }
+
+    fn volume_file_system(&self, partition: &str) -> Result<FileSystemKind> {
+        let letter = partition
+            .chars()
+            .next()
+            .filter(|c| c.is_ascii_alphabetic())
+            .with_context(|| format!("分区 {} 没有盘符", partition))?;
+        raw_image::volume_file_system(letter)
+    }
+
+    fn auto_created_partition(&self) -> Option<String> {
+        DiskManager::find_auto_created_partition().map(|(letter, _)| format!("{}:", letter))
+    }
+
+    fn cleanup_handoff(&self, data_partition: &str, target_partition: &str) {
+        ConfigFileManager::cleanup_all(data_partition, target_partition);
+    }
+
+    fn format_partition(&self, partition: &str, label: Option<&str>) -> Result<()> {
+        DiskManager::format_partition_with_label(partition, label).map(|_| ())
+    }
+
+    fn remove_auto_created_partition(&self, target_partition: &str) -> Result<()> {
+        DiskManager::cleanup_auto_created_partition_and_extend(target_partition)
+    }
+
+    fn ghost_available(&self) -> bool {
+        Ghost::new().is_available()
+    }
+
+    fn apply_image(
+        &self,
+        config: &InstallConfig,
+        image_path: &str,
+        target_partition: &str,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        if config.is_gho {
+            let partitions = DiskManager::get_partitions().unwrap_or_default();
+            Ghost::new().restore_image_to_letter(image_path, target_partition, &partitions, progress_tx)
+        } else {
+            let apply_dir = format!("{}\\", target_partition);
+            Dism::new().apply_image(image_path, &apply_dir, config.volume_index, progress_tx)
+        }
+    }
+
+    fn capture_image(
+        &self,
+        config: &BackupConfig,
+        source_partition: &str,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        let capture_dir = format!("{}\\", source_partition);
+        let append = config.incremental && Path::new(&config.save_path).exists();
+        let dism = Dism::new();
+        match config.format {
+            BackupFormat::Gho => {
+                Ghost::new().create_image_from_letter(source_partition, &config.save_path, progress_tx)
+            }
+            BackupFormat::Esd if append => {
+                dism.append_image_esd(&config.save_path, &capture_dir, &config.name, &config.description, progress_tx)
+            }
+            BackupFormat::Esd => {
+                dism.capture_image_esd(&config.save_path, &capture_dir, &config.name, &config.description, progress_tx)
+            }
+            BackupFormat::Swm => dism.capture_image_swm(
+                &config.save_path,
+                &capture_dir,
+                &config.name,
+                &config.description,
+                config.swm_split_size,
+                progress_tx,
+            ),
+            BackupFormat::Raw => {
+                // 原始扇区镜像，逐扇区读取并跳过未使用的簇
+                let letter = source_partition.chars().next().unwrap_or('C');
+                let options = raw_image::RawBackupOptions {
+                    description: format!("{} {}", config.name, config.description).trim().to_string(),
+                    ..Default::default()
+                };
+                raw_image::backup_device(
+                    &raw_image::volume_device_path(letter),
+                    Path::new(&config.save_path),
+                    &options,
+                    &mut |percentage, status| {
+                        if let Some(tx) = &progress_tx {
+                            let _ = tx.send(DismProgress { percentage, status: status.to_string() });
+                        }
+                    },
+                )
+                .map(|summary| log::info!("原始镜像备份完成: {}", summary.summary()))
+            }
+            BackupFormat::Wim if append => {
+                dism.append_image(&config.save_path, &capture_dir, &config.name, &config.description, progress_tx)
+            }
+            BackupFormat::Wim => {
+                dism.capture_image(&config.save_path, &capture_dir, &config.name, &config.description, progress_tx)
+            }
+        }
+    }
+
+    fn add_drivers(&self, apply_dir: &str, driver_dir: &str, progress_tx: Option<Sender<DismProgress>>) -> Result<()> {
+        Dism::new().add_drivers_offline_with_progress(apply_dir, driver_dir, progress_tx)
+    }
+
+    fn add_packages(
+        &self,
+        apply_dir: &str,
+        package_dir: &str,
+        progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<(usize, usize)> {
+        Dism::new().add_packages_offline_from_dir(apply_dir, package_dir, progress_tx)
+    }
+
+    fn detect_uefi_mode(&self) -> bool {
+        DiskManager::detect_uefi_mode()
+    }
+
+    fn repair_boot(
+        &self,
+        target_partition: &str,
+        use_uefi: bool,
+        options: &BootRepairOptions,
+    ) -> Result<BootRepairReport> {
+        BootManager::new().repair_boot_with_options(target_partition, use_uefi, options)
+    }
+
+    fn delete_stale_original_entry(
+        &self,
+        original_guid: &str,
+        target_partition: &str,
+        new_entry_guid: Option<&str>,
+    ) -> Result<bool> {
+        BootManager::new().delete_stale_original_entry(original_guid, target_partition, new_entry_guid)
+    }
+
+    fn delete_current_boot_entry(&self) -> Result<()> {
+        BootManager::new().delete_current_boot_entry()
+    }
+
+    fn apply_uefiseven_patch(&self, data_partition: &str, target_partition: &str) -> Result<()> {
+        crate::ui::advanced_options::apply_uefiseven_patch(data_partition, target_partition)
+    }
+
+    fn current_boot_entry(&self) -> Result<BcdLoaderEntry> {
+        BootManager::new().current_entry()
+    }
+
+    fn default_boot_entry(&self) -> Result<String> {
+        BootManager::new().default_entry()
+    }
+
+    fn set_default_boot_entry(&self, guid: &str) -> Result<()> {
+        BootManager::new().set_default_entry(guid)
+    }
+
+    fn list_boot_entries(&self) -> Result<Vec<BcdLoaderEntry>> {
+        BootManager::new().list_loader_entries()
+    }
+
+    fn delete_boot_entry(&self, guid: &str) -> Result<()> {
+        BootManager::new().delete_boot_entry(guid)
+    }
+
+    fn apply_advanced_options(&self, target_partition: &str, config: &InstallConfig) -> Result<()> {
+        crate::ui::advanced_options::apply_advanced_options(target_partition, config)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn reboot(&self) {
+        crate::utils::reboot_pe();
+    }
+}
+
+/// 测试用后端：盘符映射到临时目录，记录所有操作
+#[cfg(test)]
+pub mod fake {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::sync::Mutex;
+
+    use anyhow::bail;
+
+    use super::*;
+    use crate::core::disk::AUTO_CREATED_PARTITION_MARKER;
+
+    /// 伪系统后端
+    ///
+    /// `C:\Windows` 映射为 `<临时目录>/C/Windows`。格式化会清空目录并改变序列号，
+    /// 释放镜像会创建 `Windows\System32`，其余操作只记录调用。
+    pub struct FakeBackend {
+        root: PathBuf,
+        /// 引导参数中的校验码
+        pub nonce: String,
+        /// 各分区的当前状态
+        pub snapshots: Mutex<BTreeMap<String, PartitionSnapshot>>,
+        /// 引导配置
+        pub bcd: Mutex<FakeBcd>,
+        /// 调用时返回错误的操作
+        failing: Mutex<BTreeSet<&'static str>>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    /// 伪引导配置
+    #[derive(Debug, Clone, Default)]
+    pub struct FakeBcd {
+        /// 所有启动加载器条目
+        pub entries: Vec<BcdLoaderEntry>,
+        /// 当前启动的条目
+        pub current: Option<String>,
+        /// 默认条目
+        pub default: String,
+    }
+
+    impl FakeBackend {
+        pub fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("letrecovery_backend_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).expect("创建临时目录失败");
+            Self {
+                root,
+                nonce: "0123456789abcdef".to_string(),
+                snapshots: Mutex::new(BTreeMap::new()),
+                bcd: Mutex::new(FakeBcd::default()),
+                failing: Mutex::new(BTreeSet::new()),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// 设置分区状态
+        pub fn set_snapshot(&self, partition: &str, snapshot: PartitionSnapshot) {
+            self.snapshots.lock().unwrap().insert(partition.to_ascii_uppercase(), snapshot);
+        }
+
+        /// 让某项操作失败
+        pub fn fail_on(&self, operation: &'static str) {
+            self.failing.lock().unwrap().insert(operation);
+        }
+
+        /// 恢复所有操作
+        pub fn clear_failures(&self) {
+            self.failing.lock().unwrap().clear();
+        }
+
+        /// 已执行的操作（`操作 参数`）
+        pub fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        /// 已执行的操作名
+        pub fn operations(&self) -> Vec<String> {
+            self.calls()
+                .iter()
+                .map(|call| call.split(' ').next().unwrap_or_default().to_string())
+                .collect()
+        }
+
+        pub fn clear_calls(&self) {
+            self.calls.lock().unwrap().clear();
+        }
+
+        fn record(&self, operation: &'static str, detail: String) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("{} {}", operation, detail).trim_end().to_string());
+            if self.failing.lock().unwrap().contains(operation) {
+                bail!("模拟的 {} 失败", operation);
+            }
+            Ok(())
+        }
+
+        fn read_config<T: letrecovery_config::HandoffConfig>(&self, data_partition: &str, file: &str) -> Result<T> {
+            let path = self.path(&format!("{}\\{}", ConfigFileManager::get_data_dir(data_partition), file));
+            let content = std::fs::read_to_string(&path).with_context(|| format!("读取配置文件失败: {}", path.display()))?;
+            Ok(letrecovery_config::from_json(&content, Some(&self.nonce), letrecovery_config::unix_now())?)
+        }
+    }
+
+    impl Drop for FakeBackend {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    impl SystemBackend for FakeBackend {
+        fn path(&self, path: &str) -> PathBuf {
+            let mut parts = path.split(['\\', '/']).filter(|part| !part.is_empty());
+            let mut local = self.root.clone();
+            if let Some(drive) = parts.next() {
+                local.push(drive.trim_end_matches(':').to_ascii_uppercase());
+            }
+            local.extend(parts);
+            local
+        }
+
+        fn find_data_partition(&self) -> Option<String> {
+            ('C'..='Z')
+                .map(|letter| format!("{}:", letter))
+                .find(|partition| self.path(&ConfigFileManager::get_data_dir(partition)).is_dir())
+        }
+
+        fn find_install_marker_partition(&self) -> Option<String> {
+            ('C'..='Z')
+                .map(|letter| format!("{}:", letter))
+                .find(|partition| {
+                    self.path(&format!("{}\\{}", partition, ConfigFileManager::INSTALL_MARKER)).is_file()
+                })
+        }
+
+        fn read_install_config(&self, data_partition: &str) -> Result<InstallConfig> {
+            self.read_config(data_partition, letrecovery_config::INSTALL_CONFIG_FILE)
+        }
+
+        fn read_backup_config(&self, data_partition: &str) -> Result<BackupConfig> {
+            self.read_config(data_partition, letrecovery_config::BACKUP_CONFIG_FILE)
+        }
+
+        fn resolve_install_target(&self, config: &InstallConfig) -> String {
+            config.target_partition.clone()
+        }
+
+        fn resolve_backup_source(&self, config: &BackupConfig) -> String {
+            config.source_partition.clone()
+        }
+
+        fn partition_snapshot(&self, partition: &str) -> Result<PartitionSnapshot> {
+            self.snapshots
+                .lock()
+                .unwrap()
+                .get(&partition.to_ascii_uppercase())
+                .cloned()
+                .with_context(|| format!("分区 {} 不存在", partition))
+        }
+
+        fn volume_file_system(&self, partition: &str) -> Result<FileSystemKind> {
+            self.partition_snapshot(partition).map(|_| FileSystemKind::Ntfs)
+        }
+
+        fn auto_created_partition(&self) -> Option<String> {
+            ('C'..='Z')
+                .map(|letter| format!("{}:", letter))
+                .find(|partition| self.path(&format!("{}\\{}", partition, AUTO_CREATED_PARTITION_MARKER)).is_file())
+        }
+
+        fn cleanup_handoff(&self, data_partition: &str, target_partition: &str) {
+            let _ = self.record("cleanup_handoff", format!("{} {}", data_partition, target_partition));
+            let _ = std::fs::remove_dir_all(self.path(&ConfigFileManager::get_data_dir(data_partition)));
+            let _ = std::fs::remove_dir_all(self.path(&ConfigFileManager::get_pe_dir(data_partition)));
+        }
+
+        fn format_partition(&self, partition: &str, label: Option<&str>) -> Result<()> {
+            self.record("format_partition", partition.to_string())?;
+            let root = self.path(partition);
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root)?;
+            if let Some(snapshot) = self.snapshots.lock().unwrap().get_mut(&partition.to_ascii_uppercase()) {
+                snapshot.serial = snapshot.serial.wrapping_add(1);
+                snapshot.label = label.unwrap_or_default().to_string();
+            }
+            Ok(())
+        }
+
+        fn remove_auto_created_partition(&self, target_partition: &str) -> Result<()> {
+            self.record("remove_auto_created_partition", target_partition.to_string())
+        }
+
+        fn ghost_available(&self) -> bool {
+            true
+        }
+
+        fn apply_image(
+            &self,
+            config: &InstallConfig,
+            image_path: &str,
+            target_partition: &str,
+            progress_tx: Option<Sender<DismProgress>>,
+        ) -> Result<()> {
+            self.record("apply_image", format!("{} #{} {}", image_path, config.volume_index, target_partition))?;
+            if !self.path(image_path).is_file() {
+                bail!("镜像文件不存在: {}", image_path);
+            }
+            std::fs::create_dir_all(self.path(&format!("{}\\Windows\\System32", target_partition)))?;
+            if let Some(tx) = progress_tx {
+                let _ = tx.send(DismProgress { percentage: 100, status: String::new() });
+            }
+            Ok(())
+        }
+
+        fn capture_image(
+            &self,
+            config: &BackupConfig,
+            source_partition: &str,
+            _progress_tx: Option<Sender<DismProgress>>,
+        ) -> Result<()> {
+            self.record("capture_image", format!("{} {:?} {}", source_partition, config.format, config.save_path))?;
+            let path = self.path(&config.save_path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, config.name.as_bytes())?;
+            Ok(())
+        }
+
+        fn add_drivers(&self, apply_dir: &str, driver_dir: &str, _progress_tx: Option<Sender<DismProgress>>) -> Result<()> {
+            self.record("add_drivers", format!("{} {}", driver_dir, apply_dir))
+        }
+
+        fn add_packages(
+            &self,
+            apply_dir: &str,
+            package_dir: &str,
+            _progress_tx: Option<Sender<DismProgress>>,
+        ) -> Result<(usize, usize)> {
+            self.record("add_packages", format!("{} {}", package_dir, apply_dir))?;
+            let count = std::fs::read_dir(self.path(package_dir))?.count();
+            Ok((count, 0))
+        }
+
+        fn detect_uefi_mode(&self) -> bool {
+            true
+        }
+
+        fn repair_boot(
+            &self,
+            target_partition: &str,
+            use_uefi: bool,
+            _options: &BootRepairOptions,
+        ) -> Result<BootRepairReport> {
+            self.record("repair_boot", format!("{} uefi={}", target_partition, use_uefi))?;
+            Ok(BootRepairReport {
+                new_entry_guid: Some("{11111111-2222-3333-4444-555555555555}".to_string()),
+                ..Default::default()
+            })
+        }
+
+        fn delete_stale_original_entry(
+            &self,
+            original_guid: &str,
+            _target_partition: &str,
+            _new_entry_guid: Option<&str>,
+        ) -> Result<bool> {
+            self.record("delete_stale_original_entry", original_guid.to_string())?;
+            Ok(!original_guid.is_empty())
+        }
+
+        fn delete_current_boot_entry(&self) -> Result<()> {
+            self.record("delete_current_boot_entry", String::new())
+        }
+
+        fn apply_uefiseven_patch(&self, data_partition: &str, target_partition: &str) -> Result<()> {
+            self.record("apply_uefiseven_patch", format!("{} {}", data_partition, target_partition))
+        }
+
+        fn current_boot_entry(&self) -> Result<BcdLoaderEntry> {
+            let bcd = self.bcd.lock().unwrap();
+            let current = bcd.current.as_deref().context("未找到当前引导项")?;
+            bcd.entries
+                .iter()
+                .find(|entry| entry.identifier == current)
+                .cloned()
+                .context("未找到当前引导项")
+        }
+
+        fn default_boot_entry(&self) -> Result<String> {
+            Ok(self.bcd.lock().unwrap().default.clone())
+        }
+
+        fn set_default_boot_entry(&self, guid: &str) -> Result<()> {
+            self.record("set_default_boot_entry", guid.to_string())?;
+            self.bcd.lock().unwrap().default = guid.to_string();
+            Ok(())
+        }
+
+        fn list_boot_entries(&self) -> Result<Vec<BcdLoaderEntry>> {
+            Ok(self.bcd.lock().unwrap().entries.clone())
+        }
+
+        fn delete_boot_entry(&self, guid: &str) -> Result<()> {
+            self.record("delete_boot_entry", guid.to_string())?;
+            self.bcd.lock().unwrap().entries.retain(|entry| entry.identifier != guid);
+            Ok(())
+        }
+
+        fn apply_advanced_options(&self, target_partition: &str, _config: &InstallConfig) -> Result<()> {
+            self.record("apply_advanced_options", target_partition.to_string())
+        }
+
+        fn sleep(&self, _duration: Duration) {}
+
+        fn reboot(&self) {
+            let _ = self.record("reboot", String::new());
+        }
+    }
+}
@@ -1,35 +1,78 @@
 use anyhow::Result;
 use std::path::Path;
-use std::{fs, path::PathBuf};
 
 use crate::utils::command::new_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
+use super::diskpart::DiskpartScript;
+use super::boot_code::{self, BiosBootReport};
+use super::esp_inventory::{self, BcdLoaderEntry, EspLoader, FallbackGuard};
+
+/// 引导修复选项
+#[derive(Debug, Clone, Default)]
+pub struct BootRepairOptions {
+    /// 多系统并存：保留原默认引导项，只新增一个引导项
+    pub side_by_side: bool,
+    /// 新引导项的描述（为空时使用 bcdboot 默认描述）
+    pub entry_description: String,
+}
+
+/// 引导修复报告
+#[derive(Debug, Clone, Default)]
+pub struct BootRepairReport {
+    /// 使用的 ESP 盘符（UEFI 模式）
+    pub esp_letter: Option<String>,
+    /// ESP 上保留的其他系统引导程序
+    pub kept_loaders: Vec<EspLoader>,
+    /// 被保护的其他系统回退引导程序（`EFI\Boot\bootx64.efi`）
+    pub preserved_fallback: Option<EspLoader>,
+    /// BCD 中保留的其他 Windows 引导项
+    pub other_windows_entries: Vec<BcdLoaderEntry>,
+    /// 目标系统的引导项 GUID
+    pub new_entry_guid: Option<String>,
+    /// 写入的 BIOS 引导代码（Legacy 模式）
+    pub bios_boot: Option<BiosBootReport>,
+}
+
+impl BootRepairReport {
+    /// 生成保留内容的摘要文本
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(ref fallback) = self.preserved_fallback {
+            lines.push(format!(
+                "已保留回退引导程序 {} ({})",
+                fallback.relative_path, fallback.kind
+            ));
+        }
+        for loader in &self.kept_loaders {
+            lines.push(format!("已保留引导程序 {} ({})", loader.relative_path, loader.kind));
+        }
+        for entry in &self.other_windows_entries {
+            lines.push(format!("已保留引导项 {} {}", entry.identifier, entry.description));
+        }
+        if let Some(ref boot) = self.bios_boot {
+            lines.push(format!(
+                "已写入 BIOS 引导代码：磁盘 {} 第 {} 个主分区设为活动分区",
+                boot.disk_number,
+                boot.partition_index + 1
+            ));
+            lines.extend(boot.notes.iter().cloned());
+        }
+        if lines.is_empty() {
+            "未发现其他系统的引导程序".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
 pub struct BootManager {
     bcdedit_path: String,
     bcdboot_path: String,
 }
 
 impl BootManager {
-    /// 选择一个可靠的临时目录并确保它存在（避免 WinPE 下 os error 3）。
-    fn reliable_temp_dir() -> PathBuf {
-        let candidates = [
-            PathBuf::from(r"X:\Windows\Temp"),
-            PathBuf::from(r"X:\Temp"),
-            std::env::temp_dir(),
-            PathBuf::from("X:\\"),
-        ];
-
-        for dir in candidates {
-            let _ = fs::create_dir_all(&dir);
-            if dir.exists() {
-                return dir;
-            }
-        }
-
-        std::env::temp_dir()
-    }
     pub fn new() -> Self {
         let bin_dir = get_bin_dir();
         Self {
@@ -53,21 +96,11 @@ impl BootManager {
             .trim_end_matches('\\');
 
         // Step 1: 使用 diskpart 获取该分区所在的磁盘号
-        let script1 = format!(
-            r#"select volume {}
-detail volume
-"#,
-            drive_letter
-        );
-
-        let script1_path = Self::reliable_temp_dir().join("find_disk.txt");
-        std::fs::write(&script1_path, &script1)?;
-
-        let output = new_command("diskpart")
-            .args(["/s", &script1_path.to_string_lossy()])
-            .output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = DiskpartScript::new()
+            .select_volume(drive_letter)
+            .detail_volume()
+            .run()?
+            .output;
         log::debug!("查找磁盘号:\n{}", stdout);
 
         let mut disk_num: Option<usize> = None;
@@ -92,21 +125,11 @@ detail volume
         log::info!("目标分区在磁盘 {}", disk_num);
 
         // Step 2: 查找该磁盘上的 ESP 分区
-        let script2 = format!(
-            r#"select disk {}
-list partition
-"#,
-            disk_num
-        );
-
-        let script2_path = Self::reliable_temp_dir().join("list_part.txt");
-        std::fs::write(&script2_path, &script2)?;
-
-        let output = new_command("diskpart")
-            .args(["/s", &script2_path.to_string_lossy()])
-            .output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = DiskpartScript::new()
+            .select_disk(disk_num as u32)
+            .list_partition()
+            .run()?
+            .output;
         log::debug!("分区列表:\n{}", stdout);
 
         let mut esp_partition: Option<usize> = None;
@@ -137,22 +160,12 @@ list partition
         let _ = new_command("mountvol").args(["S:", "/d"]).output();
         std::thread::sleep(std::time::Duration::from_millis(200));
 
-        let script3 = format!(
-            r#"select disk {}
-select partition {}
-assign letter=S
-"#,
-            disk_num, esp_partition
-        );
-
-        let script3_path = Self::reliable_temp_dir().join("assign_esp.txt");
-        std::fs::write(&script3_path, &script3)?;
-
-        let output = new_command("diskpart")
-            .args(["/s", &script3_path.to_string_lossy()])
-            .output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = DiskpartScript::new()
+            .select_disk(disk_num as u32)
+            .select_partition(esp_partition as u32)
+            .assign(Some('S'))
+            .run()?
+            .output;
         log::debug!("分配 ESP 盘符:\n{}", stdout);
 
         std::thread::sleep(std::time::Duration::from_millis(500));
@@ -193,22 +206,12 @@ assign letter=S
     fn find_esp_with_diskpart(&self) -> Result<String> {
         log::info!("使用 diskpart 查找 ESP");
 
-        for disk in 0..4 {
-            let script = format!(
-                r#"select disk {}
-list partition
-"#,
-                disk
-            );
-
-            let script_path = Self::reliable_temp_dir().join("check_disk.txt");
-            std::fs::write(&script_path, &script)?;
-
-            let output = new_command("diskpart")
-                .args(["/s", script_path.to_str().unwrap()])
-                .output()?;
-
-            let stdout = gbk_to_utf8(&output.stdout);
+        for disk in 0..4u32 {
+            let stdout = DiskpartScript::new()
+                .select_disk(disk)
+                .list_partition()
+                .run()?
+                .output;
 
             for line in stdout.lines() {
                 let line_lower = line.to_lowercase();
@@ -218,21 +221,11 @@ list partition
                         if part.to_lowercase().contains("partition") || *part == "分区" {
                             if let Some(num_str) = parts.get(i + 1) {
                                 if let Ok(part_num) = num_str.parse::<usize>() {
-                                    let assign_script = format!(
-                                        r#"select disk {}
-select partition {}
-assign letter=S
-"#,
-                                        disk, part_num
-                                    );
-
-                                    let assign_path =
-                                        Self::reliable_temp_dir().join("assign_esp2.txt");
-                                    std::fs::write(&assign_path, &assign_script)?;
-
-                                    let _ = new_command("diskpart")
-                                        .args(["/s", &assign_path.to_string_lossy()])
-                                        .output();
+                                    let _ = DiskpartScript::new()
+                                        .select_disk(disk)
+                                        .select_partition(part_num as u32)
+                                        .assign(Some('S'))
+                                        .run();
 
                                     std::thread::sleep(std::time::Duration::from_millis(500));
 
@@ -269,9 +262,147 @@ assign letter=S
         Ok(())
     }
 
+    /// 删除引导项
+    pub fn delete_boot_entry(&self, guid: &str) -> Result<()> {
+        let output = new_command(&self.bcdedit_path)
+            .args(["/delete", guid, "/f"])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("删除引导项失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        Ok(())
+    }
+
+    /// 列出 BCD 中的所有 Windows 启动加载器条目
+    pub fn list_loader_entries(&self) -> Result<Vec<BcdLoaderEntry>> {
+        let output = new_command(&self.bcdedit_path)
+            .args(["/enum", "osloader", "/v"])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("枚举引导项失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        Ok(esp_inventory::parse_bcd_loader_entries(&gbk_to_utf8(
+            &output.stdout,
+        )))
+    }
+
+    /// 当前启动的引导项（PE 自身）
+    pub fn current_entry(&self) -> Result<BcdLoaderEntry> {
+        let output = new_command(&self.bcdedit_path)
+            .args(["/enum", "{current}", "/v"])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("读取当前引导项失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        esp_inventory::parse_bcd_loader_entries(&gbk_to_utf8(&output.stdout))
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("未找到当前引导项"))
+    }
+
+    /// 启动管理器的默认引导项 GUID
+    pub fn default_entry(&self) -> Result<String> {
+        let output = new_command(&self.bcdedit_path)
+            .args(["/enum", "{bootmgr}", "/v"])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("读取启动管理器失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        gbk_to_utf8(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().split_once(char::is_whitespace))
+            .find(|(key, _)| key.eq_ignore_ascii_case("default") || *key == "默认")
+            .map(|(_, value)| value.trim().to_string())
+            .ok_or_else(|| anyhow::anyhow!("启动管理器没有默认引导项"))
+    }
+
+    /// 设置默认引导项
+    pub fn set_default_entry(&self, guid: &str) -> Result<()> {
+        let output = new_command(&self.bcdedit_path)
+            .args(["/default", guid])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("设置默认引导项失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        Ok(())
+    }
+
+    /// 设置引导项描述
+    pub fn set_entry_description(&self, guid: &str, description: &str) -> Result<()> {
+        let output = new_command(&self.bcdedit_path)
+            .args(["/set", guid, "description", description])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("设置引导项描述失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        Ok(())
+    }
+
+    /// 删除安装前记录的原系统引导项
+    ///
+    /// 只删除仍指向目标分区的条目，其他系统的引导项不会被删除。
+    /// 返回 `true` 表示已删除。
+    pub fn delete_stale_original_entry(
+        &self,
+        original_guid: &str,
+        windows_partition: &str,
+        new_entry_guid: Option<&str>,
+    ) -> Result<bool> {
+        if original_guid.is_empty() {
+            return Ok(false);
+        }
+        if new_entry_guid
+            .map(|g| g.eq_ignore_ascii_case(original_guid))
+            .unwrap_or(false)
+        {
+            return Ok(false);
+        }
+
+        let entries = self.list_loader_entries()?;
+        let stale = entries.iter().any(|e| {
+            e.identifier.eq_ignore_ascii_case(original_guid)
+                && e.is_on_partition(windows_partition)
+        });
+        if !stale {
+            log::info!(
+                "原引导项 {} 不指向 {}，保留",
+                original_guid,
+                windows_partition
+            );
+            return Ok(false);
+        }
+
+        self.delete_boot_entry(original_guid)?;
+        log::info!("已删除原引导项 {}", original_guid);
+        Ok(true)
+    }
+
     /// 修复指定分区的引导（高级版本，支持指定引导模式）
     pub fn repair_boot_advanced(&self, windows_partition: &str, use_uefi: bool) -> Result<()> {
+        let report = self.repair_boot_with_options(
+            windows_partition,
+            use_uefi,
+            &BootRepairOptions::default(),
+        )?;
+        log::info!("{}", report.summary());
+        Ok(())
+    }
+
+    /// 修复指定分区的引导，清点并保留其他系统的引导程序
+    pub fn repair_boot_with_options(
+        &self,
+        windows_partition: &str,
+        use_uefi: bool,
+        options: &BootRepairOptions,
+    ) -> Result<BootRepairReport> {
         let windows_path = format!("{}\\Windows", windows_partition);
+        let mut report = BootRepairReport::default();
 
         log::info!("========== 修复引导 ==========");
         log::info!("Windows 路径: {}", windows_path);
@@ -279,6 +410,7 @@ assign letter=S
             "引导模式: {}",
             if use_uefi { "UEFI" } else { "Legacy/BIOS" }
         );
+        log::info!("多系统并存: {}", options.side_by_side);
 
         // 验证 Windows 目录存在
         if !Path::new(&windows_path).exists() {
@@ -288,6 +420,21 @@ assign letter=S
         // 先删除当前PE引导项
         let _ = self.delete_current_boot_entry();
 
+        // 记录其他 Windows 引导项（bcdboot 会保留它们，这里只用于报告）
+        if let Ok(entries) = self.list_loader_entries() {
+            report.other_windows_entries = entries
+                .into_iter()
+                .filter(|e| !e.is_on_partition(windows_partition))
+                .collect();
+        }
+
+        // 多系统并存时保留原默认引导项（/d），UEFI 下新引导项排在固件启动顺序末尾（/addlast）
+        let extra_args: Vec<&str> = match (options.side_by_side, use_uefi) {
+            (true, true) => vec!["/d", "/addlast"],
+            (true, false) => vec!["/d"],
+            (false, _) => Vec::new(),
+        };
+
         if use_uefi {
             log::info!("UEFI 模式：查找 ESP 分区");
 
@@ -305,60 +452,41 @@ assign letter=S
                     let _ = std::fs::create_dir_all(&efi_ms_dir);
                     let _ = std::fs::create_dir_all(&efi_boot_dir);
 
-                    log::info!(
-                        "执行: bcdboot {} /s {} /f UEFI /l zh-cn",
-                        windows_path,
-                        esp_letter
-                    );
-                    let output = new_command(&self.bcdboot_path)
-                        .args([
-                            &windows_path,
-                            "/s",
-                            &esp_letter,
-                            "/f",
-                            "UEFI",
-                            "/l",
-                            "zh-cn",
-                        ])
-                        .output()?;
-
-                    let stdout = gbk_to_utf8(&output.stdout);
-                    let stderr = gbk_to_utf8(&output.stderr);
-
-                    log::debug!("bcdboot stdout: {}", stdout);
-                    log::debug!("bcdboot stderr: {}", stderr);
-
-                    if !output.status.success() {
-                        log::info!("重试：使用 ALL 模式");
-                        let output = new_command(&self.bcdboot_path)
-                            .args([
-                                &windows_path,
-                                "/s",
-                                &esp_letter,
-                                "/f",
-                                "ALL",
-                                "/l",
-                                "zh-cn",
-                            ])
-                            .output()?;
-
-                        let stdout = gbk_to_utf8(&output.stdout);
-                        let stderr = gbk_to_utf8(&output.stderr);
-                        log::debug!("bcdboot (ALL) stdout: {}", stdout);
-                        log::debug!("bcdboot (ALL) stderr: {}", stderr);
-
-                        if !output.status.success() {
-                            log::info!("重试：不指定引导类型");
-                            let output = new_command(&self.bcdboot_path)
-                                .args([&windows_path, "/s", &esp_letter, "/l", "zh-cn"])
-                                .output()?;
-
-                            let stderr = gbk_to_utf8(&output.stderr);
-                            if !output.status.success() {
-                                anyhow::bail!("UEFI 引导修复失败: {}", stderr);
-                            }
+                    // 清点 ESP 上的引导程序，保护其他系统的回退引导程序
+                    let esp_root = format!("{}\\", esp_letter);
+                    let inventory = esp_inventory::scan_esp(Path::new(&esp_root));
+                    for loader in inventory.foreign_loaders() {
+                        log::info!(
+                            "发现其他系统引导程序: {} ({})",
+                            loader.relative_path,
+                            loader.kind
+                        );
+                    }
+                    report.kept_loaders =
+                        inventory.foreign_loaders().into_iter().cloned().collect();
+                    report.esp_letter = Some(esp_letter.clone());
+
+                    let fallback_guard =
+                        match FallbackGuard::protect(Path::new(&esp_root), &inventory) {
+                            Ok(guard) => guard,
+                            Err(e) => anyhow::bail!(
+                                "备份其他系统的回退引导程序失败，已停止修复引导: {}",
+                                e
+                            ),
+                        };
+
+                    let bcdboot_result =
+                        self.run_bcdboot_uefi(&windows_path, &esp_letter, &extra_args);
+
+                    // 无论 bcdboot 是否成功，都写回其他系统的回退引导程序
+                    if let Some(guard) = fallback_guard {
+                        let loader = guard.loader().clone();
+                        match guard.restore() {
+                            Ok(_) => report.preserved_fallback = Some(loader),
+                            Err(e) => log::warn!("恢复回退引导程序失败: {}", e),
                         }
                     }
+                    bcdboot_result?;
 
                     // 验证引导文件
                     let bootmgfw = format!("{}\\EFI\\Microsoft\\Boot\\bootmgfw.efi", esp_letter);
@@ -368,11 +496,9 @@ assign letter=S
                         log::info!("引导文件已创建: {}", bootmgfw);
                     }
 
-                    if !Path::new(&bootx64).exists() {
-                        if Path::new(&bootmgfw).exists() {
-                            let _ = std::fs::copy(&bootmgfw, &bootx64);
-                            log::info!("已复制 bootmgfw.efi -> bootx64.efi");
-                        }
+                    if !Path::new(&bootx64).exists() && Path::new(&bootmgfw).exists() {
+                        let _ = std::fs::copy(&bootmgfw, &bootx64);
+                        log::info!("已复制 bootmgfw.efi -> bootx64.efi");
                     }
 
                     log::info!("UEFI 引导修复成功");
@@ -380,9 +506,9 @@ assign letter=S
                 Err(e) => {
                     log::warn!("查找 ESP 失败: {}，尝试默认方式", e);
 
-                    let output = new_command(&self.bcdboot_path)
-                        .args([&windows_path, "/f", "UEFI", "/l", "zh-cn"])
-                        .output()?;
+                    let mut args = vec![windows_path.as_str(), "/f", "UEFI", "/l", "zh-cn"];
+                    args.extend(&extra_args);
+                    let output = new_command(&self.bcdboot_path).args(&args).output()?;
 
                     let stdout = gbk_to_utf8(&output.stdout);
                     let stderr = gbk_to_utf8(&output.stderr);
@@ -398,22 +524,25 @@ assign letter=S
             // Legacy/BIOS 模式
             log::info!("Legacy 模式：写入 MBR 引导");
 
-            let bootsect_path = get_bin_dir().join("bootsect.exe");
-            if bootsect_path.exists() {
-                log::info!("使用 bootsect 写入引导扇区");
-                let output = new_command(&bootsect_path)
-                    .args(["/nt60", windows_partition, "/mbr"])
-                    .output()?;
-
-                let stdout = gbk_to_utf8(&output.stdout);
-                let stderr = gbk_to_utf8(&output.stderr);
-                log::debug!("bootsect stdout: {}", stdout);
-                log::debug!("bootsect stderr: {}", stderr);
+            // 写入 MBR 引导代码、设置活动分区并更新卷引导记录
+            let boot = boot_code::write_bios_boot_code(windows_partition)
+                .map_err(|e| anyhow::anyhow!("写入 BIOS 引导代码失败: {:#}", e))?;
+            log::info!(
+                "已写入 BIOS 引导代码 (磁盘 {}, 主分区 {}, {} 原引导程序: {})",
+                boot.disk_number,
+                boot.partition_index + 1,
+                boot.file_system,
+                boot.loader_before
+            );
+            for note in &boot.notes {
+                log::info!("{}", note);
             }
+            log::info!("扇区变更:\n{}", boot.diff);
+            report.bios_boot = Some(boot);
 
-            let output = new_command(&self.bcdboot_path)
-                .args([&windows_path, "/f", "BIOS", "/l", "zh-cn"])
-                .output()?;
+            let mut args = vec![windows_path.as_str(), "/f", "BIOS", "/l", "zh-cn"];
+            args.extend(&extra_args);
+            let output = new_command(&self.bcdboot_path).args(&args).output()?;
 
             let stdout = gbk_to_utf8(&output.stdout);
             let stderr = gbk_to_utf8(&output.stderr);
@@ -435,7 +564,85 @@ assign letter=S
             log::info!("Legacy 引导修复成功");
         }
 
+        // 查找目标系统的引导项，多系统并存时设置自定义描述
+        if let Ok(entries) = self.list_loader_entries() {
+            report.new_entry_guid = entries
+                .iter()
+                .rev()
+                .find(|e| e.is_on_partition(windows_partition))
+                .map(|e| e.identifier.clone());
+        }
+        if options.side_by_side && !options.entry_description.is_empty() {
+            match report.new_entry_guid {
+                Some(ref guid) => {
+                    if let Err(e) = self.set_entry_description(guid, &options.entry_description) {
+                        log::warn!("设置引导项描述失败: {}", e);
+                    } else {
+                        log::info!(
+                            "引导项 {} 描述已设置为: {}",
+                            guid,
+                            options.entry_description
+                        );
+                    }
+                }
+                None => log::warn!("未找到新引导项，无法设置描述"),
+            }
+        }
+
         log::info!("========== 引导修复完成 ==========");
+        Ok(report)
+    }
+
+    /// 执行 bcdboot 写入 UEFI 引导文件（失败时依次重试 ALL 模式和默认模式）
+    fn run_bcdboot_uefi(
+        &self,
+        windows_path: &str,
+        esp_letter: &str,
+        extra_args: &[&str],
+    ) -> Result<()> {
+        log::info!(
+            "执行: bcdboot {} /s {} /f UEFI /l zh-cn {}",
+            windows_path,
+            esp_letter,
+            extra_args.join(" ")
+        );
+        let mut args = vec![windows_path, "/s", esp_letter, "/f", "UEFI", "/l", "zh-cn"];
+        args.extend(extra_args);
+        let output = new_command(&self.bcdboot_path).args(&args).output()?;
+
+        let stdout = gbk_to_utf8(&output.stdout);
+        let stderr = gbk_to_utf8(&output.stderr);
+
+        log::debug!("bcdboot stdout: {}", stdout);
+        log::debug!("bcdboot stderr: {}", stderr);
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        log::info!("重试：使用 ALL 模式");
+        let mut args = vec![windows_path, "/s", esp_letter, "/f", "ALL", "/l", "zh-cn"];
+        args.extend(extra_args);
+        let output = new_command(&self.bcdboot_path).args(&args).output()?;
+
+        let stdout = gbk_to_utf8(&output.stdout);
+        let stderr = gbk_to_utf8(&output.stderr);
+        log::debug!("bcdboot (ALL) stdout: {}", stdout);
+        log::debug!("bcdboot (ALL) stderr: {}", stderr);
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        log::info!("重试：不指定引导类型");
+        let mut args = vec![windows_path, "/s", esp_letter, "/l", "zh-cn"];
+        args.extend(extra_args);
+        let output = new_command(&self.bcdboot_path).args(&args).output()?;
+
+        let stderr = gbk_to_utf8(&output.stderr);
+        if !output.status.success() {
+            anyhow::bail!("UEFI 引导修复失败: {}", stderr);
+        }
         Ok(())
     }
 }
@@ -1,174 +1,36 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::path::Path;
 
-/// 驱动操作模式
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub enum DriverActionMode {
-    /// 无操作
-    #[default]
-    None = 0,
-    /// 仅保存驱动（到数据目录）
-    SaveOnly = 1,
-    /// 自动导入（保存并导入到新系统）
-    AutoImport = 2,
-}
-
-impl DriverActionMode {
-    /// 从数值转换
-    pub fn from_u8(value: u8) -> Self {
-        match value {
-            1 => Self::SaveOnly,
-            2 => Self::AutoImport,
-            _ => Self::None,
-        }
-    }
-    
-    /// 是否需要导入驱动
-    pub fn should_import(&self) -> bool {
-        *self == Self::AutoImport
-    }
-    
-    /// 是否有驱动目录（SaveOnly 或 AutoImport 时都有）
-    pub fn has_drivers(&self) -> bool {
-        *self != Self::None
-    }
-}
-
-/// 系统安装配置（用于PE环境内安装）
-#[derive(Debug, Clone, Default)]
-pub struct InstallConfig {
-    /// 无人值守安装
-    pub unattended: bool,
-    /// 驱动还原（兼容旧版本）
-    pub restore_drivers: bool,
-    /// 驱动操作模式: 0=无, 1=仅保存, 2=自动导入
-    pub driver_action_mode: DriverActionMode,
-    /// 立即重启
-    pub auto_reboot: bool,
-    /// 原系统引导GUID（用于删除旧引导项）
-    pub original_guid: String,
-    /// 安装分卷索引
-    pub volume_index: u32,
-    /// 目标分区盘符
-    pub target_partition: String,
-    /// 镜像文件路径（相对于数据分区）
-    pub image_path: String,
-    /// 是否为GHO格式
-    pub is_gho: bool,
-    /// CAB更新包安装: true=安装, false=不安装
-    pub install_cab_packages: bool,
-
-    // 高级选项
-    /// 移除快捷方式小箭头
-    pub remove_shortcut_arrow: bool,
-    /// Win11恢复经典右键
-    pub restore_classic_context_menu: bool,
-    /// OOBE绕过强制联网
-    pub bypass_nro: bool,
-    /// 禁用Windows更新
-    pub disable_windows_update: bool,
-    /// 禁用Windows安全中心
-    pub disable_windows_defender: bool,
-    /// 禁用系统保留空间
-    pub disable_reserved_storage: bool,
-    /// 禁用用户账户控制
-    pub disable_uac: bool,
-    /// 禁用自动设备加密
-    pub disable_device_encryption: bool,
-    /// 删除预装UWP应用
-    pub remove_uwp_apps: bool,
-    /// 导入磁盘控制器驱动
-    pub import_storage_controller_drivers: bool,
-    /// 自定义用户名
-    pub custom_username: String,
-    /// 自定义系统盘卷标
-    pub volume_label: String,
-    
-    // Win7 专用选项
-    /// Win7 UEFI 补丁（使用 UefiSeven）
-    pub win7_uefi_patch: bool,
-    /// Win7 注入USB3驱动
-    pub win7_inject_usb3_driver: bool,
-    /// Win7 注入NVMe驱动
-    pub win7_inject_nvme_driver: bool,
-    /// Win7 修复ACPI蓝屏
-    pub win7_fix_acpi_bsod: bool,
-    /// Win7 修复存储控制器蓝屏
-    pub win7_fix_storage_bsod: bool,
-}
+use letrecovery_config::{HandoffConfig, PartitionSnapshot};
 
-impl InstallConfig {
-    /// 判断是否需要导入驱动
-    /// 优先使用新的driver_action_mode，兼容旧的restore_drivers
-    pub fn should_import_drivers(&self) -> bool {
-        // 优先使用新的driver_action_mode
-        if self.driver_action_mode != DriverActionMode::None {
-            self.driver_action_mode.should_import()
-        } else {
-            // 兼容旧版本
-            self.restore_drivers
-        }
-    }
-    
-    /// 判断是否有驱动目录需要处理
-    pub fn has_driver_data(&self) -> bool {
-        self.driver_action_mode.has_drivers() || self.restore_drivers
-    }
-}
+use crate::core::volume_id::{self, VolumeIdentity};
+use crate::utils::cmd::create_command;
+use crate::utils::encoding::gbk_to_utf8;
 
-/// 备份格式
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub enum BackupFormat {
-    #[default]
-    Wim = 0,
-    Esd = 1,
-    Swm = 2,
-    Gho = 3,
-}
+// 配置类型与序列化由正常系统端和PE端共用的 letrecovery-config 提供
+pub use letrecovery_config::{BackupConfig, BackupFormat, InstallConfig};
 
-impl BackupFormat {
-    /// 从数值转换
-    pub fn from_u8(value: u8) -> Self {
-        match value {
-            0 => Self::Wim,
-            1 => Self::Esd,
-            2 => Self::Swm,
-            3 => Self::Gho,
-            _ => Self::Wim,
+/// 根据安装配置生成引导修复选项
+impl From<&InstallConfig> for crate::core::bcdedit::BootRepairOptions {
+    fn from(config: &InstallConfig) -> Self {
+        Self {
+            side_by_side: config.boot_side_by_side,
+            entry_description: config.boot_entry_description.clone(),
         }
     }
 }
 
-/// 系统备份配置（用于PE环境内备份）
-#[derive(Debug, Clone, Default)]
-pub struct BackupConfig {
-    /// 备份保存路径
-    pub save_path: String,
-    /// 备份名称
-    pub name: String,
-    /// 备份描述
-    pub description: String,
-    /// 源分区盘符
-    pub source_partition: String,
-    /// 是否增量备份
-    pub incremental: bool,
-    /// 备份格式
-    pub format: BackupFormat,
-    /// SWM分卷大小（MB）
-    pub swm_split_size: u32,
-}
-
 /// 配置文件管理器
 pub struct ConfigFileManager;
 
 impl ConfigFileManager {
     /// 标记文件名
-    const INSTALL_MARKER: &'static str = "LetRecovery_Install.marker";
-    const BACKUP_MARKER: &'static str = "LetRecovery_Backup.marker";
+    pub const INSTALL_MARKER: &'static str = "LetRecovery_Install.marker";
+    pub const BACKUP_MARKER: &'static str = "LetRecovery_Backup.marker";
 
     /// 配置文件名
-    const INSTALL_CONFIG: &'static str = "LetRecovery_Install.ini";
-    const BACKUP_CONFIG: &'static str = "LetRecovery_Backup.ini";
+    const INSTALL_CONFIG: &'static str = letrecovery_config::INSTALL_CONFIG_FILE;
+    const BACKUP_CONFIG: &'static str = letrecovery_config::BACKUP_CONFIG_FILE;
 
     /// PE文件目录名
     const PE_DIR: &'static str = "LetRecovery_PE";
@@ -178,72 +40,137 @@ impl ConfigFileManager {
 
     /// 查找包含安装标记文件的分区
     pub fn find_install_marker_partition() -> Option<String> {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
-            let marker_path = format!("{}:\\{}", letter, Self::INSTALL_MARKER);
-            if Path::new(&marker_path).exists() {
-                log::info!("找到安装标记分区: {}:", letter);
-                return Some(format!("{}:", letter));
-            }
-        }
-        None
+        let partition = Self::find_marker_partition(Self::INSTALL_MARKER)?;
+        log::info!("找到安装标记分区: {}", partition);
+        Some(partition)
     }
 
     /// 查找包含备份标记文件的分区
     pub fn find_backup_marker_partition() -> Option<String> {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
-            let marker_path = format!("{}:\\{}", letter, Self::BACKUP_MARKER);
-            if Path::new(&marker_path).exists() {
-                log::info!("找到备份标记分区: {}:", letter);
-                return Some(format!("{}:", letter));
-            }
-        }
-        None
+        let partition = Self::find_marker_partition(Self::BACKUP_MARKER)?;
+        log::info!("找到备份标记分区: {}", partition);
+        Some(partition)
+    }
+
+    fn find_marker_partition(marker: &str) -> Option<String> {
+        ('C'..='Z')
+            .find(|letter| Path::new(&format!("{}:\\{}", letter, marker)).exists())
+            .map(|letter| format!("{}:", letter))
     }
 
     /// 查找包含配置文件的数据分区
+    ///
+    /// 扫描所有盘符和没有盘符的卷。有多个分区存在配置时（如上次失败残留），
+    /// 优先选择配置中记录的数据卷标识与所在卷一致的分区。
     pub fn find_data_partition() -> Option<String> {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
-            let config_path = format!("{}:\\{}\\{}", letter, Self::DATA_DIR, Self::INSTALL_CONFIG);
-            if Path::new(&config_path).exists() {
-                log::info!("找到安装配置分区: {}:", letter);
-                return Some(format!("{}:", letter));
-            }
-            let backup_config_path =
-                format!("{}:\\{}\\{}", letter, Self::DATA_DIR, Self::BACKUP_CONFIG);
-            if Path::new(&backup_config_path).exists() {
-                log::info!("找到备份配置分区: {}:", letter);
-                return Some(format!("{}:", letter));
+        let volumes = volume_id::list_volumes();
+        let mut found: Vec<String> = ('C'..='Z')
+            .map(|letter| format!("{}:", letter))
+            .chain(volumes.iter().filter(|v| v.letter.is_none()).map(|v| v.path()))
+            .filter(|partition| Self::has_config(partition))
+            .collect();
+        if found.is_empty() {
+            return None;
+        }
+
+        let index = found
+            .iter()
+            .position(|partition| {
+                let recorded = Self::read_install_config(partition)
+                    .map(|c| c.data_volume_id)
+                    .or_else(|_| Self::read_backup_config(partition).map(|c| c.data_volume_id))
+                    .unwrap_or_default();
+                VolumeIdentity::parse(&recorded)
+                    .ok()
+                    .and_then(|identity| volume_id::find_volume(&identity, &volumes))
+                    .is_some_and(|v| v.path() == *partition)
+            })
+            .unwrap_or(0);
+        let partition = found.swap_remove(index);
+        log::info!("找到配置分区: {}", partition);
+
+        // 没有盘符的卷分配盘符，便于后续工具访问
+        match volumes.iter().find(|v| v.letter.is_none() && v.path() == partition) {
+            Some(volume) => Some(volume_id::mount_path(volume)),
+            None => Some(partition),
+        }
+    }
+
+    /// 解析安装目标分区：优先按卷标识查找，其次查找标记文件，最后使用配置中的盘符
+    pub fn resolve_install_target(config: &InstallConfig) -> String {
+        Self::resolve_volume(&config.target_volume_id)
+            .or_else(Self::find_install_marker_partition)
+            .unwrap_or_else(|| config.target_partition.clone())
+    }
+
+    /// 解析备份源分区：优先按卷标识查找，其次查找标记文件，最后使用配置中的盘符
+    pub fn resolve_backup_source(config: &BackupConfig) -> String {
+        Self::resolve_volume(&config.source_volume_id)
+            .or_else(Self::find_backup_marker_partition)
+            .unwrap_or_else(|| config.source_partition.clone())
+    }
+
+    fn resolve_volume(recorded: &str) -> Option<String> {
+        if recorded.is_empty() {
+            return None;
+        }
+        let identity = match VolumeIdentity::parse(recorded) {
+            Ok(identity) => identity,
+            Err(e) => {
+                log::warn!("卷标识无效 ({}): {}", recorded, e);
+                return None;
             }
+        };
+        let resolved = volume_id::resolve(&identity);
+        match &resolved {
+            Some(partition) => log::info!("按卷标识 {} 找到分区: {}", recorded, partition),
+            None => log::warn!("未找到卷标识为 {} 的分区，改用标记文件", recorded),
         }
-        None
+        resolved
     }
 
     /// 检测操作类型 (安装或备份)
+    ///
+    /// 数据分区上存在配置，且目标分区能按卷标识或标记文件找到时才执行，
+    /// 避免误用残留的旧配置。
     pub fn detect_operation_type() -> Option<OperationType> {
-        // 先检查安装标记
-        if Self::find_install_marker_partition().is_some() {
-            if let Some(data_part) = Self::find_data_partition() {
-                let install_config_path = format!(
-                    "{}\\{}\\{}",
-                    data_part,
-                    Self::DATA_DIR,
-                    Self::INSTALL_CONFIG
-                );
-                if Path::new(&install_config_path).exists() {
+        let data_part = Self::find_data_partition()?;
+
+        // 配置存在但无法读取（如版本不符、校验失败）时仍进入对应流程，由读取配置时显示原因
+        let data_dir = Self::get_data_dir(&data_part);
+        let exists = |files: [&str; 2]| {
+            files
+                .iter()
+                .any(|file| Path::new(&format!("{}\\{}", data_dir, file)).exists())
+        };
+        match Self::read_install_config(&data_part) {
+            Ok(config) => {
+                if Self::resolve_volume(&config.target_volume_id).is_some()
+                    || Self::find_install_marker_partition().is_some()
+                {
                     return Some(OperationType::Install);
                 }
             }
+            Err(e) if exists([Self::INSTALL_CONFIG, letrecovery_config::LEGACY_INSTALL_CONFIG_FILE]) => {
+                log::warn!("安装配置无法读取: {:#}", e);
+                return Some(OperationType::Install);
+            }
+            Err(_) => {}
         }
 
-        // 再检查备份标记
-        if Self::find_backup_marker_partition().is_some() {
-            if let Some(data_part) = Self::find_data_partition() {
-                let backup_config_path =
-                    format!("{}\\{}\\{}", data_part, Self::DATA_DIR, Self::BACKUP_CONFIG);
-                if Path::new(&backup_config_path).exists() {
+        match Self::read_backup_config(&data_part) {
+            Ok(config) => {
+                if Self::resolve_volume(&config.source_volume_id).is_some()
+                    || Self::find_backup_marker_partition().is_some()
+                {
                     return Some(OperationType::Backup);
                 }
             }
+            Err(e) if exists([Self::BACKUP_CONFIG, letrecovery_config::LEGACY_BACKUP_CONFIG_FILE]) => {
+                log::warn!("备份配置无法读取: {:#}", e);
+                return Some(OperationType::Backup);
+            }
+            Err(_) => {}
         }
 
         None
@@ -251,30 +178,86 @@ impl ConfigFileManager {
 
     /// 读取安装配置
     pub fn read_install_config(data_partition: &str) -> Result<InstallConfig> {
-        let config_path = format!(
-            "{}\\{}\\{}",
-            data_partition,
-            Self::DATA_DIR,
-            Self::INSTALL_CONFIG
-        );
-        log::info!("读取安装配置: {}", config_path);
-        let content =
-            std::fs::read_to_string(&config_path).context("读取安装配置文件失败")?;
-        Self::deserialize_install_config(&content)
+        Self::read_config(data_partition, Self::INSTALL_CONFIG, letrecovery_config::LEGACY_INSTALL_CONFIG_FILE)
     }
 
     /// 读取备份配置
     pub fn read_backup_config(data_partition: &str) -> Result<BackupConfig> {
-        let config_path = format!(
-            "{}\\{}\\{}",
-            data_partition,
-            Self::DATA_DIR,
-            Self::BACKUP_CONFIG
-        );
-        log::info!("读取备份配置: {}", config_path);
-        let content =
-            std::fs::read_to_string(&config_path).context("读取备份配置文件失败")?;
-        Self::deserialize_backup_config(&content)
+        Self::read_config(data_partition, Self::BACKUP_CONFIG, letrecovery_config::LEGACY_BACKUP_CONFIG_FILE)
+    }
+
+    /// 读取配置并校验签名、有效期
+    ///
+    /// 旧版 INI 配置没有签名，不再读取，提示重新发起操作。
+    fn read_config<T: HandoffConfig>(data_partition: &str, file: &str, legacy_file: &str) -> Result<T> {
+        let data_dir = Self::get_data_dir(data_partition);
+        let config_path = format!("{}\\{}", data_dir, file);
+        let content = match std::fs::read_to_string(&config_path) {
+            Ok(content) => content,
+            Err(e) => {
+                let legacy_path = format!("{}\\{}", data_dir, legacy_file);
+                if Path::new(&legacy_path).exists() {
+                    bail!("检测到旧版配置 {}，其中没有防篡改校验信息，请在正常系统中重新发起操作", legacy_path);
+                }
+                return Err(e).with_context(|| format!("读取配置文件失败: {}", config_path));
+            }
+        };
+
+        log::info!("读取配置: {}", config_path);
+        let nonce = Self::boot_nonce();
+        if nonce.is_none() {
+            log::warn!("引导参数中没有校验码");
+        }
+        letrecovery_config::from_json(&content, nonce.as_deref(), letrecovery_config::unix_now())
+            .with_context(|| format!("配置文件校验失败: {}", config_path))
+    }
+
+    /// 当前启动项引导参数中的一次性校验码（由正常系统端写入PE引导项）
+    fn boot_nonce() -> Option<String> {
+        let output = create_command("reg.exe")
+            .args([
+                "query",
+                "HKLM\\SYSTEM\\CurrentControlSet\\Control",
+                "/v",
+                "SystemStartOptions",
+            ])
+            .output()
+            .ok()?;
+        letrecovery_config::nonce_from_start_options(&gbk_to_utf8(&output.stdout))
+    }
+
+    /// 核对目标分区，`actual` 为读取到的分区当前状态，
+    /// `expected` 根据当前状态给出应有的快照（如继续中断的安装时）
+    pub fn check_target_snapshot(
+        target_partition: &str,
+        actual: Result<PartitionSnapshot>,
+        expected: impl FnOnce(&PartitionSnapshot) -> PartitionSnapshot,
+    ) -> Result<()> {
+        let actual = actual
+            .with_context(|| format!("无法读取目标分区 {} 的信息，拒绝格式化", target_partition))?;
+        let differences = expected(&actual).differences(&actual);
+        if !differences.is_empty() {
+            bail!(
+                "目标分区 {} 与发起安装时不一致（{}），拒绝格式化",
+                target_partition,
+                differences.join("；")
+            );
+        }
+        log::info!("目标分区 {} 核对通过", target_partition);
+        Ok(())
+    }
+
+    /// 分区上是否存在安装或备份配置（包括旧版 INI 配置，读取时给出提示）
+    fn has_config(partition: &str) -> bool {
+        let data_dir = Self::get_data_dir(partition);
+        [
+            Self::INSTALL_CONFIG,
+            Self::BACKUP_CONFIG,
+            letrecovery_config::LEGACY_INSTALL_CONFIG_FILE,
+            letrecovery_config::LEGACY_BACKUP_CONFIG_FILE,
+        ]
+        .iter()
+        .any(|file| Path::new(&format!("{}\\{}", data_dir, file)).exists())
     }
 
     /// 获取数据目录路径
@@ -331,108 +314,6 @@ impl ConfigFileManager {
         Self::cleanup_data_dir(data_partition);
         Self::cleanup_pe_dir(data_partition);
     }
-
-    /// 反序列化安装配置
-    fn deserialize_install_config(content: &str) -> Result<InstallConfig> {
-        let mut config = InstallConfig::default();
-        config.volume_index = 1; // 默认值
-
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('[') || line.starts_with('#') {
-                continue;
-            }
-
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim();
-
-                match key {
-                    "Unattended" => config.unattended = value.parse().unwrap_or(false),
-                    "RestoreDrivers" => config.restore_drivers = value.parse().unwrap_or(false),
-                    "DriverActionMode" => {
-                        let mode_value: u8 = value.parse().unwrap_or(0);
-                        config.driver_action_mode = DriverActionMode::from_u8(mode_value);
-                    }
-                    "AutoReboot" => config.auto_reboot = value.parse().unwrap_or(false),
-                    "OriginalGUID" => config.original_guid = value.to_string(),
-                    "VolumeIndex" => config.volume_index = value.parse().unwrap_or(1),
-                    "TargetPartition" => config.target_partition = value.to_string(),
-                    "ImagePath" => config.image_path = value.to_string(),
-                    "IsGho" => config.is_gho = value.parse().unwrap_or(false),
-                    "InstallCabPackages" => config.install_cab_packages = value.parse().unwrap_or(false),
-                    "RemoveShortcutArrow" => {
-                        config.remove_shortcut_arrow = value.parse().unwrap_or(false)
-                    }
-                    "RestoreClassicContextMenu" => {
-                        config.restore_classic_context_menu = value.parse().unwrap_or(false)
-                    }
-                    "BypassNRO" => config.bypass_nro = value.parse().unwrap_or(false),
-                    "DisableWindowsUpdate" => {
-                        config.disable_windows_update = value.parse().unwrap_or(false)
-                    }
-                    "DisableWindowsDefender" => {
-                        config.disable_windows_defender = value.parse().unwrap_or(false)
-                    }
-                    "DisableReservedStorage" => {
-                        config.disable_reserved_storage = value.parse().unwrap_or(false)
-                    }
-                    "DisableUAC" => config.disable_uac = value.parse().unwrap_or(false),
-                    "DisableDeviceEncryption" => {
-                        config.disable_device_encryption = value.parse().unwrap_or(false)
-                    }
-                    "RemoveUWPApps" => config.remove_uwp_apps = value.parse().unwrap_or(false),
-                    "ImportStorageControllerDrivers" => {
-                        config.import_storage_controller_drivers = value.parse().unwrap_or(false)
-                    }
-                    "CustomUsername" => config.custom_username = value.to_string(),
-                    "VolumeLabel" => config.volume_label = value.to_string(),
-                    "Win7UefiPatch" => config.win7_uefi_patch = value.parse().unwrap_or(false),
-                    "Win7InjectUsb3Driver" => config.win7_inject_usb3_driver = value.parse().unwrap_or(false),
-                    "Win7InjectNvmeDriver" => config.win7_inject_nvme_driver = value.parse().unwrap_or(false),
-                    "Win7FixAcpiBsod" => config.win7_fix_acpi_bsod = value.parse().unwrap_or(false),
-                    "Win7FixStorageBsod" => config.win7_fix_storage_bsod = value.parse().unwrap_or(false),
-                    _ => {}
-                }
-            }
-        }
-
-        Ok(config)
-    }
-
-    /// 反序列化备份配置
-    fn deserialize_backup_config(content: &str) -> Result<BackupConfig> {
-        let mut config = BackupConfig::default();
-        config.swm_split_size = 4096; // 默认4GB
-
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('[') || line.starts_with('#') {
-                continue;
-            }
-
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim();
-
-                match key {
-                    "SavePath" => config.save_path = value.to_string(),
-                    "Name" => config.name = value.to_string(),
-                    "Description" => config.description = value.to_string(),
-                    "SourcePartition" => config.source_partition = value.to_string(),
-                    "Incremental" => config.incremental = value.parse().unwrap_or(false),
-                    "Format" => {
-                        let format_value: u8 = value.parse().unwrap_or(0);
-                        config.format = BackupFormat::from_u8(format_value);
-                    }
-                    "SwmSplitSize" => config.swm_split_size = value.parse().unwrap_or(4096),
-                    _ => {}
-                }
-            }
-        }
-
-        Ok(config)
-    }
 }
 
 /// 操作类型
@@ -1,35 +1,23 @@
 use anyhow::Result;
-use std::{
-    fs,
-    path::{Path, PathBuf},
-};
+use std::path::Path;
+#[cfg(windows)]
 use windows::core::PCWSTR;
+#[cfg(windows)]
 use windows::Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetDriveTypeW, GetVolumeInformationW};
 
+use crate::core::diskpart::DiskpartScript;
 use crate::utils::command::new_command;
 use crate::utils::encoding::gbk_to_utf8;
-use crate::utils::path::get_bin_dir;
 
+#[cfg(windows)]
 const DRIVE_FIXED: u32 = 3;
 
 /// 自动创建分区的标志文件名
 pub const AUTO_CREATED_PARTITION_MARKER: &str = "LetRecovery_AutoCreated.marker";
 
-/// 获取 diskpart 可执行文件路径
-/// 优先使用内置的 diskpart，如果不存在则使用系统的
-fn get_diskpart_path() -> String {
-    let builtin_diskpart = get_bin_dir().join("diskpart").join("diskpart.exe");
-    if builtin_diskpart.exists() {
-        log::info!("使用内置 diskpart: {}", builtin_diskpart.display());
-        builtin_diskpart.to_string_lossy().to_string()
-    } else {
-        log::info!("使用系统 diskpart");
-        "diskpart.exe".to_string()
-    }
-}
-
 /// 分区表类型
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum PartitionStyle {
     GPT,
     MBR,
@@ -72,29 +60,6 @@ pub struct DiskManager;
 
 impl DiskManager {
 
-    /// 选择一个可靠的临时目录并确保它存在。
-    /// WinPE 下 std::env::temp_dir() 可能指向不存在的路径，
-    /// 直接写 diskpart 脚本会触发 "系统找不到指定的路径 (os error 3)"。
-    fn reliable_temp_dir() -> PathBuf {
-        // WinPE 下最稳的两个临时目录：X:\Windows\Temp / X:\Temp
-        // 注意：WinPE 里 std::env::temp_dir() 可能指向不存在的路径，所以这里要“尽力创建”。
-        let candidates = [
-            PathBuf::from(r"X:\Windows\Temp"),
-            PathBuf::from(r"X:\Temp"),
-            std::env::temp_dir(),
-            PathBuf::from("X:\\"),
-        ];
-
-        for dir in candidates {
-            let _ = fs::create_dir_all(&dir);
-            if dir.exists() {
-                return dir;
-            }
-        }
-
-        // 兜底：即便不存在也返回一个值，避免上层因为 ? 直接编译不过/崩溃。
-        std::env::temp_dir()
-    }
     /// 获取所有固定磁盘分区列表
     pub fn get_partitions() -> Result<Vec<Partition>> {
         let mut partitions = Vec::new();
@@ -120,44 +85,7 @@ impl DiskManager {
     }
 
     fn get_partition_info(drive: &str) -> Result<Partition> {
-        let path = format!("{}\\", drive);
-        let wide_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-
-        // 获取驱动器类型
-        let drive_type = unsafe { GetDriveTypeW(PCWSTR(wide_path.as_ptr())) };
-        if drive_type != DRIVE_FIXED {
-            anyhow::bail!("Not a fixed drive");
-        }
-
-        // 获取磁盘空间
-        let mut free_bytes_available: u64 = 0;
-        let mut total_bytes: u64 = 0;
-        let mut total_free_bytes: u64 = 0;
-
-        unsafe {
-            GetDiskFreeSpaceExW(
-                PCWSTR(wide_path.as_ptr()),
-                Some(&mut free_bytes_available as *mut u64),
-                Some(&mut total_bytes as *mut u64),
-                Some(&mut total_free_bytes as *mut u64),
-            )?;
-        }
-
-        // 获取卷标
-        let mut volume_name = [0u16; 261];
-        unsafe {
-            let _ = GetVolumeInformationW(
-                PCWSTR(wide_path.as_ptr()),
-                Some(&mut volume_name),
-                None,
-                None,
-                None,
-                None,
-            );
-        }
-        let label = String::from_utf16_lossy(&volume_name)
-            .trim_end_matches('\0')
-            .to_string();
+        let (total_bytes, free_bytes_available, label) = Self::query_fixed_volume(drive)?;
 
         // PE环境下排除 X: 盘
         let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "X:".to_string());
@@ -195,26 +123,9 @@ impl DiskManager {
     /// 使用 diskpart 获取分区信息（备用方法）
     fn get_partition_style_diskpart(drive: &str) -> PartitionDetail {
         let letter = drive.chars().next().unwrap_or('C');
-        let script = format!("select volume {}\ndetail volume", letter);
-
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("dp_style.txt");
-
-        if std::fs::write(&script_path, &script).is_err() {
-            return PartitionDetail {
-                style: PartitionStyle::Unknown,
-                disk_number: None,
-                partition_number: None,
-            };
-        }
-
-        let output = match new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()
-        {
-            Ok(o) => o,
+        let stdout = match DiskpartScript::new().select_volume(letter).detail_volume().run() {
+            Ok(outcome) => outcome.output,
             Err(_) => {
-                let _ = std::fs::remove_file(&script_path);
                 return PartitionDetail {
                     style: PartitionStyle::Unknown,
                     disk_number: None,
@@ -223,9 +134,6 @@ impl DiskManager {
             }
         };
 
-        let _ = std::fs::remove_file(&script_path);
-        let stdout = gbk_to_utf8(&output.stdout);
-
         let mut disk_num: Option<u32> = None;
         let mut part_num: Option<u32> = None;
 
@@ -267,28 +175,11 @@ impl DiskManager {
 
     /// 获取指定磁盘的分区表类型
     fn get_disk_partition_style(disk_number: u32) -> PartitionStyle {
-        let script = format!("select disk {}\ndetail disk", disk_number);
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("dp_disk_style.txt");
-
-        if std::fs::write(&script_path, &script).is_err() {
-            return PartitionStyle::Unknown;
-        }
-
-        let output = match new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()
-        {
-            Ok(o) => o,
-            Err(_) => {
-                let _ = std::fs::remove_file(&script_path);
-                return PartitionStyle::Unknown;
-            }
+        let stdout = match DiskpartScript::new().select_disk(disk_number).detail_disk().run() {
+            Ok(outcome) => outcome.output.to_uppercase(),
+            Err(_) => return PartitionStyle::Unknown,
         };
 
-        let _ = std::fs::remove_file(&script_path);
-        let stdout = gbk_to_utf8(&output.stdout).to_uppercase();
-
         if stdout.contains("GPT") {
             PartitionStyle::GPT
         } else if stdout.contains("MBR") {
@@ -298,11 +189,6 @@ impl DiskManager {
         }
     }
 
-    /// 格式化指定分区
-    pub fn format_partition(partition: &str) -> Result<String> {
-        Self::format_partition_with_label(partition, None)
-    }
-    
     /// 格式化指定分区（带卷标）
     /// 
     /// 使用 cmd /c format 进行格式化，因为直接调用 format.com 在 CREATE_NO_WINDOW 模式下
@@ -520,38 +406,70 @@ impl DiskManager {
     fn delete_partition_by_letter(letter: char) -> Result<()> {
         log::info!("[CLEANUP] 删除分区 {}:", letter);
 
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            letter
-        );
+        let outcome = DiskpartScript::new()
+            .select_volume(letter)
+            .delete_partition(true)
+            .run()?;
+        log::info!("[CLEANUP] Diskpart 删除输出: {}", outcome.output);
 
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("lr_delete_part.txt");
-        std::fs::write(&script_path, &script_content)?;
+        outcome.ensure_success("删除分区失败")?;
 
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
+        log::info!("[CLEANUP] 分区 {} 删除成功", letter);
+        Ok(())
+    }
 
-        let _ = std::fs::remove_file(&script_path);
+    /// 获取固定磁盘上分区的总大小、可用空间（字节）和卷标，不是固定磁盘时返回错误
+    #[cfg(windows)]
+    fn query_fixed_volume(drive: &str) -> Result<(u64, u64, String)> {
+        let path = format!("{}\\", drive);
+        let wide_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
 
-        let output_text = gbk_to_utf8(&output.stdout);
-        log::info!("[CLEANUP] Diskpart 删除输出: {}", output_text);
+        // 获取驱动器类型
+        let drive_type = unsafe { GetDriveTypeW(PCWSTR(wide_path.as_ptr())) };
+        if drive_type != DRIVE_FIXED {
+            anyhow::bail!("Not a fixed drive");
+        }
 
-        // 检查是否有错误（但不要太严格，删除成功也可能包含一些警告）
-        let output_lower = output_text.to_lowercase();
-        let has_error = (output_lower.contains("error") || output_lower.contains("错误"))
-            && !output_lower.contains("成功") && !output_lower.contains("successfully");
-        
-        if has_error {
-            anyhow::bail!("删除分区失败: {}", output_text);
+        // 获取磁盘空间
+        let mut free_bytes_available: u64 = 0;
+        let mut total_bytes: u64 = 0;
+        let mut total_free_bytes: u64 = 0;
+
+        unsafe {
+            GetDiskFreeSpaceExW(
+                PCWSTR(wide_path.as_ptr()),
+                Some(&mut free_bytes_available as *mut u64),
+                Some(&mut total_bytes as *mut u64),
+                Some(&mut total_free_bytes as *mut u64),
+            )?;
         }
 
-        log::info!("[CLEANUP] 分区 {} 删除成功", letter);
-        Ok(())
+        // 获取卷标
+        let mut volume_name = [0u16; 261];
+        unsafe {
+            let _ = GetVolumeInformationW(
+                PCWSTR(wide_path.as_ptr()),
+                Some(&mut volume_name),
+                None,
+                None,
+                None,
+                None,
+            );
+        }
+        let label = String::from_utf16_lossy(&volume_name)
+            .trim_end_matches('\0')
+            .to_string();
+
+        Ok((total_bytes, free_bytes_available, label))
+    }
+
+    #[cfg(not(windows))]
+    fn query_fixed_volume(_drive: &str) -> Result<(u64, u64, String)> {
+        anyhow::bail!("仅支持Windows系统")
     }
 
     /// 获取分区大小（MB）
+    #[cfg(windows)]
     fn get_partition_size_mb(letter: char) -> Option<u64> {
         let path = format!("{}:\\", letter);
         let wide_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
@@ -574,6 +492,11 @@ impl DiskManager {
         }
     }
 
+    #[cfg(not(windows))]
+    fn get_partition_size_mb(_letter: char) -> Option<u64> {
+        None
+    }
+
     /// 删除分区并扩展目标分区
     fn delete_partition_and_extend(auto_letter: char, target_letter: char, disk_num: u32) -> Result<()> {
         // 记录扩展前的分区大小
@@ -583,33 +506,14 @@ impl DiskManager {
         // Step 1: 删除分区
         log::info!("[CLEANUP] Step 1: 删除分区 {}:", auto_letter);
         
-        let delete_script = format!(
-            "select volume {}\ndelete partition override",
-            auto_letter
-        );
-
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("lr_delete_part.txt");
-        std::fs::write(&script_path, &delete_script)?;
-
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
-        log::info!("[CLEANUP] 删除分区输出: {}", output_text);
+        let outcome = DiskpartScript::new()
+            .select_volume(auto_letter)
+            .delete_partition(true)
+            .run()?;
+        log::info!("[CLEANUP] 删除分区输出: {}", outcome.output);
 
         // 检查删除是否成功
-        let output_lower = output_text.to_lowercase();
-        let delete_failed = (output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed"))
-            && !output_lower.contains("成功") && !output_lower.contains("successfully");
-            
-        if delete_failed {
-            anyhow::bail!("删除分区失败: {}", output_text);
-        }
+        outcome.ensure_success("删除分区失败")?;
 
         log::info!("[CLEANUP] 分区 {} 删除成功", auto_letter);
 
@@ -688,21 +592,8 @@ impl DiskManager {
 
     /// 运行 diskpart rescan 命令刷新磁盘信息
     fn diskpart_rescan() {
-        let script_content = "rescan";
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("lr_rescan.txt");
-        
-        if std::fs::write(&script_path, script_content).is_ok() {
-            let output = new_command(&get_diskpart_path())
-                .args(["/s", script_path.to_str().unwrap()])
-                .output();
-            
-            let _ = std::fs::remove_file(&script_path);
-            
-            if let Ok(output) = output {
-                let output_text = gbk_to_utf8(&output.stdout);
-                log::info!("[CLEANUP] rescan 输出: {}", output_text);
-            }
+        if let Ok(outcome) = DiskpartScript::new().rescan().run() {
+            log::info!("[CLEANUP] rescan 输出: {}", outcome.output);
         }
     }
 
@@ -710,89 +601,41 @@ impl DiskManager {
     /// 先尝试通过卷字母扩展，如果失败则尝试通过磁盘号和分区号扩展
     fn try_extend_volume_enhanced(letter: char, disk_num: u32) -> Result<()> {
         // 方法1：通过卷字母扩展（标准方法）
-        let extend_script = format!("select volume {}\nextend", letter);
-        
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("lr_extend.txt");
-        std::fs::write(&script_path, &extend_script)?;
-
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
-        let output_lower = output_text.to_lowercase();
+        let outcome = DiskpartScript::new().select_volume(letter).extend(None).run()?;
+        log::info!("[CLEANUP] diskpart extend (by volume) 输出: {}", outcome.output);
 
-        log::info!("[CLEANUP] diskpart extend (by volume) 输出: {}", output_text);
-
-        // 检查是否成功 - 注意要排除包含失败/错误的情况
-        let has_success = output_lower.contains("成功") || output_lower.contains("successfully") 
-            || output_lower.contains("extended the volume");
-        let has_error = output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed")
-            || output_lower.contains("没有可用") || output_lower.contains("no usable")
-            || output_lower.contains("not enough") || output_lower.contains("无法");
-            
-        if has_success && !has_error {
+        if outcome.is_success() {
             return Ok(());
         }
 
         // 检查是否有明确的错误：没有可用的未分配空间
-        if output_lower.contains("没有可用") || output_lower.contains("no usable") 
-            || output_lower.contains("not enough") || output_lower.contains("空间不足") {
+        if outcome.is_no_space() {
             // 没有可用的未分配空间，直接失败
-            anyhow::bail!("没有可用的相邻未分配空间: {}", output_text);
+            anyhow::bail!("没有可用的相邻未分配空间: {}", outcome.error_message());
         }
 
         // 方法2：尝试通过磁盘号扩展（备用方法）
         log::info!("[CLEANUP] 尝试备用方法：通过磁盘号和分区号扩展");
-        
+
         // 先获取分区号
         let detail = Self::get_partition_style(&format!("{}:", letter));
         if let Some(part_num) = detail.partition_number {
-            let extend_script2 = format!(
-                "select disk {}\nselect partition {}\nextend",
-                disk_num, part_num
-            );
-            
-            let script_path2 = temp_dir.join("lr_extend2.txt");
-            std::fs::write(&script_path2, &extend_script2)?;
-
-            let output2 = new_command(&get_diskpart_path())
-                .args(["/s", script_path2.to_str().unwrap()])
-                .output()?;
-
-            let _ = std::fs::remove_file(&script_path2);
-
-            let output_text2 = gbk_to_utf8(&output2.stdout);
-            let output_lower2 = output_text2.to_lowercase();
-
-            log::info!("[CLEANUP] diskpart extend (by partition) 输出: {}", output_text2);
-
-            let has_success2 = output_lower2.contains("成功") || output_lower2.contains("successfully")
-                || output_lower2.contains("extended the volume");
-            let has_error2 = output_lower2.contains("error") || output_lower2.contains("错误")
-                || output_lower2.contains("失败") || output_lower2.contains("failed")
-                || output_lower2.contains("没有可用") || output_lower2.contains("no usable");
-                
-            if has_success2 && !has_error2 {
+            let outcome2 = DiskpartScript::new()
+                .select_disk(disk_num)
+                .select_partition(part_num)
+                .extend(None)
+                .run()?;
+            log::info!("[CLEANUP] diskpart extend (by partition) 输出: {}", outcome2.output);
+
+            if outcome2.is_success() {
                 return Ok(());
             }
-            
+
             // 备用方法也失败了，返回备用方法的错误信息
-            if has_error2 {
-                anyhow::bail!("extend 失败 (备用方法): {}", output_text2);
-            }
+            anyhow::bail!("extend 失败 (备用方法): {}", outcome2.error_message());
         }
 
         // 都失败了，返回第一次的错误
-        if has_error {
-            anyhow::bail!("extend 失败: {}", output_text);
-        }
-
-        // 不确定状态，假设失败
-        anyhow::bail!("extend 状态不确定: {}", output_text)
+        anyhow::bail!("extend 失败: {}", outcome.error_message())
     }
 }
@@ -11,6 +11,7 @@ use std::path::Path;
 use std::sync::mpsc::Sender;
 
 use crate::core::dism_exe::{DismExe, DismExeProgress};
+#[cfg(windows)]
 use crate::core::wimgapi::{WimManager, WimProgress, WIM_COMPRESS_LZX, WIM_COMPRESS_LZMS};
 
 /// 操作进度
@@ -44,6 +45,7 @@ impl Dism {
 
     /// 应用系统镜像 (WIM/ESD)
     /// 使用 wimgapi.dll 实现
+    #[cfg(windows)]
     pub fn apply_image(
         &self,
         image_file: &str,
@@ -91,6 +93,7 @@ impl Dism {
 
     /// 捕获系统镜像 (备份)
     /// 使用 wimgapi.dll 实现
+    #[cfg(windows)]
     pub fn capture_image(
         &self,
         image_file: &str,
@@ -142,6 +145,7 @@ impl Dism {
 
     /// 增量备份镜像
     /// 使用 wimgapi.dll 实现
+    #[cfg(windows)]
     pub fn append_image(
         &self,
         image_file: &str,
@@ -158,6 +162,7 @@ impl Dism {
 
     /// 捕获系统镜像为ESD格式（高压缩）
     /// 使用 wimgapi.dll + LZMS 压缩
+    #[cfg(windows)]
     pub fn capture_image_esd(
         &self,
         image_file: &str,
@@ -208,6 +213,7 @@ impl Dism {
     }
 
     /// 增量备份ESD镜像
+    #[cfg(windows)]
     pub fn append_image_esd(
         &self,
         image_file: &str,
@@ -222,6 +228,7 @@ impl Dism {
 
     /// 捕获系统镜像为SWM分卷格式
     /// 先创建WIM，然后分割
+    #[cfg(windows)]
     pub fn capture_image_swm(
         &self,
         image_file: &str,
@@ -462,6 +469,7 @@ impl Dism {
     #[allow(dead_code)]
     pub fn get_image_info(&self, image_file: &str) -> Result<Vec<ImageInfo>> {
         // 首先尝试使用 wimgapi
+        #[cfg(windows)]
         if let Ok(wim_manager) = WimManager::new() {
             if let Ok(images) = wim_manager.get_image_info(image_file) {
                 log::info!("[Dism] 从 wimgapi 成功获取 {} 个镜像信息", images.len());
@@ -126,6 +126,7 @@ impl DismExe {
 
     /// 创建隐藏窗口的 dism.exe 命令
     fn create_command(&self) -> Command {
+        #[cfg_attr(not(windows), allow(unused_mut))]
         let mut cmd = Command::new(&self.dism_path);
 
         #[cfg(windows)]
@@ -209,26 +210,26 @@ impl DismExe {
             let mut output = String::new();
 
             for line_result in reader.lines() {
-                if let Ok(line) = line_result {
-                    // 转换编码（Windows 可能使用 GBK）
-                    let decoded_line = if line.is_ascii() {
-                        line
-                    } else {
-                        gbk_to_utf8(line.as_bytes())
-                    };
-
-                    output.push_str(&decoded_line);
-                    output.push('\n');
-
-                    // 解析进度信息
-                    if let Some(ref tx) = progress_tx_clone {
-                        if let Some(progress) = Self::parse_progress_line(&decoded_line) {
-                            let _ = tx.send(progress);
-                        }
+                // 无法解码的行跳过，继续读取以免管道阻塞
+                let Ok(line) = line_result else { continue };
+                // 转换编码（Windows 可能使用 GBK）
+                let decoded_line = if line.is_ascii() {
+                    line
+                } else {
+                    gbk_to_utf8(line.as_bytes())
+                };
+
+                output.push_str(&decoded_line);
+                output.push('\n');
+
+                // 解析进度信息
+                if let Some(ref tx) = progress_tx_clone {
+                    if let Some(progress) = Self::parse_progress_line(&decoded_line) {
+                        let _ = tx.send(progress);
                     }
-
-                    log::trace!("[DISM.EXE STDOUT] {}", decoded_line);
                 }
+
+                log::trace!("[DISM.EXE STDOUT] {}", decoded_line);
             }
 
             output
@@ -240,18 +241,17 @@ impl DismExe {
             let mut error_output = String::new();
 
             for line_result in reader.lines() {
-                if let Ok(line) = line_result {
-                    let decoded_line = if line.is_ascii() {
-                        line
-                    } else {
-                        gbk_to_utf8(line.as_bytes())
-                    };
+                let Ok(line) = line_result else { continue };
+                let decoded_line = if line.is_ascii() {
+                    line
+                } else {
+                    gbk_to_utf8(line.as_bytes())
+                };
 
-                    error_output.push_str(&decoded_line);
-                    error_output.push('\n');
+                error_output.push_str(&decoded_line);
+                error_output.push('\n');
 
-                    log::trace!("[DISM.EXE STDERR] {}", decoded_line);
-                }
+                log::trace!("[DISM.EXE STDERR] {}", decoded_line);
             }
 
             error_output
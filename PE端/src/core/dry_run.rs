@@ -0,0 +1,333 @@
+//! 演练模式
+//!
+//! [`DryRunBackend`] 包装实际后端：查询类操作（查找分区、读取配置、核对目标分区、
+//! 检测启动模式等）照常执行，改动类操作只记入 [`InstallPlan`]。
+//! 安装流程本身不变，因此计划与实际运行时做出的决定一致。
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use letrecovery_config::{InstallPlan, PartitionSnapshot, PlanAction, FAILURE_REPORT_FILE};
+
+use crate::core::backend::SystemBackend;
+use crate::core::bcdedit::{BootRepairOptions, BootRepairReport};
+use crate::core::config::{BackupConfig, ConfigFileManager, InstallConfig};
+use crate::core::dism::DismProgress;
+use crate::core::esp_inventory::BcdLoaderEntry;
+use crate::core::raw_image::FileSystemKind;
+
+/// 只记录不执行的后端
+pub struct DryRunBackend<'a> {
+    inner: &'a dyn SystemBackend,
+    plan: Mutex<InstallPlan>,
+}
+
+impl<'a> DryRunBackend<'a> {
+    pub fn new(inner: &'a dyn SystemBackend) -> Self {
+        Self {
+            inner,
+            plan: Mutex::new(InstallPlan::new()),
+        }
+    }
+
+    /// 取出记录的计划
+    pub fn into_plan(self) -> InstallPlan {
+        self.plan.into_inner().unwrap_or_default()
+    }
+
+    fn push(&self, action: PlanAction) {
+        log::info!("[DRY RUN] {}", action.describe());
+        if let Ok(mut plan) = self.plan.lock() {
+            plan.push(action);
+        }
+    }
+
+    fn note(&self, note: String) {
+        log::info!("[DRY RUN] {}", note);
+        if let Ok(mut plan) = self.plan.lock() {
+            plan.note(note);
+        }
+    }
+
+    /// 目录中指定扩展名的文件（相对路径），目录不存在时为空
+    fn list_files(&self, dir: &str, extension: &str) -> Vec<String> {
+        let root = self.inner.path(dir);
+        let mut files: Vec<String> = walkdir::WalkDir::new(&root)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+            })
+            .map(|entry| relative_name(&root, entry.path()))
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+fn relative_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+impl SystemBackend for DryRunBackend<'_> {
+    fn path(&self, path: &str) -> PathBuf {
+        self.inner.path(path)
+    }
+
+    fn write_file(&self, path: &str, _content: &str) -> Result<()> {
+        let lower = path.to_ascii_lowercase();
+        let purpose = if lower.ends_with("unattend.xml") {
+            "无人值守配置"
+        } else if lower.ends_with(&FAILURE_REPORT_FILE.to_ascii_lowercase()) {
+            "失败报告"
+        } else {
+            "文件"
+        };
+        self.push(PlanAction::WriteFile {
+            path: path.to_string(),
+            purpose: purpose.to_string(),
+        });
+        Ok(())
+    }
+
+    fn remove_path(&self, path: &str) -> Result<bool> {
+        let exists = self.inner.path(path).exists();
+        if exists {
+            self.note(format!("将删除 {}", path));
+        }
+        Ok(exists)
+    }
+
+    fn find_data_partition(&self) -> Option<String> {
+        self.inner.find_data_partition()
+    }
+
+    fn find_install_marker_partition(&self) -> Option<String> {
+        self.inner.find_install_marker_partition()
+    }
+
+    fn read_install_config(&self, data_partition: &str) -> Result<InstallConfig> {
+        self.inner.read_install_config(data_partition)
+    }
+
+    fn read_backup_config(&self, data_partition: &str) -> Result<BackupConfig> {
+        self.inner.read_backup_config(data_partition)
+    }
+
+    fn resolve_install_target(&self, config: &InstallConfig) -> String {
+        self.inner.resolve_install_target(config)
+    }
+
+    fn resolve_backup_source(&self, config: &BackupConfig) -> String {
+        self.inner.resolve_backup_source(config)
+    }
+
+    fn partition_snapshot(&self, partition: &str) -> Result<PartitionSnapshot> {
+        self.inner.partition_snapshot(partition)
+    }
+
+    fn volume_file_system(&self, partition: &str) -> Result<FileSystemKind> {
+        self.inner.volume_file_system(partition)
+    }
+
+    fn auto_created_partition(&self) -> Option<String> {
+        self.inner.auto_created_partition()
+    }
+
+    fn cleanup_handoff(&self, data_partition: &str, target_partition: &str) {
+        self.push(PlanAction::CleanupHandoff {
+            data_partition: data_partition.to_string(),
+            target_partition: target_partition.to_string(),
+        });
+    }
+
+    fn format_partition(&self, partition: &str, label: Option<&str>) -> Result<()> {
+        self.push(PlanAction::FormatPartition {
+            partition: partition.to_string(),
+            label: label.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    fn clear_volume(&self, partition: &str) -> Result<()> {
+        self.push(PlanAction::ClearPartition {
+            partition: partition.to_string(),
+        });
+        Ok(())
+    }
+
+    fn remove_auto_created_partition(&self, target_partition: &str) -> Result<()> {
+        match self.inner.auto_created_partition() {
+            Some(partition) => self.push(PlanAction::DeletePartition {
+                partition,
+                merge_into: target_partition.to_string(),
+            }),
+            None => log::info!("[DRY RUN] 没有自动创建的分区需要删除"),
+        }
+        Ok(())
+    }
+
+    fn ghost_available(&self) -> bool {
+        self.inner.ghost_available()
+    }
+
+    fn apply_image(
+        &self,
+        config: &InstallConfig,
+        image_path: &str,
+        target_partition: &str,
+        _progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        self.push(PlanAction::ApplyImage {
+            image: image_path.to_string(),
+            volume_index: config.volume_index,
+            ghost: config.is_gho,
+            partition: target_partition.to_string(),
+        });
+        Ok(())
+    }
+
+    fn capture_image(
+        &self,
+        config: &BackupConfig,
+        source_partition: &str,
+        _progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<()> {
+        self.push(PlanAction::WriteFile {
+            path: config.save_path.clone(),
+            purpose: format!("{} 的备份镜像", source_partition),
+        });
+        Ok(())
+    }
+
+    fn add_drivers(&self, apply_dir: &str, driver_dir: &str, _progress_tx: Option<Sender<DismProgress>>) -> Result<()> {
+        self.push(PlanAction::InjectDrivers {
+            partition: apply_dir.trim_end_matches('\\').to_string(),
+            source: driver_dir.to_string(),
+            drivers: self.list_files(driver_dir, "inf"),
+        });
+        Ok(())
+    }
+
+    fn add_packages(
+        &self,
+        apply_dir: &str,
+        package_dir: &str,
+        _progress_tx: Option<Sender<DismProgress>>,
+    ) -> Result<(usize, usize)> {
+        let packages = self.list_files(package_dir, "cab");
+        let count = packages.len();
+        self.push(PlanAction::InstallPackages {
+            partition: apply_dir.trim_end_matches('\\').to_string(),
+            source: package_dir.to_string(),
+            packages,
+        });
+        Ok((count, 0))
+    }
+
+    fn detect_uefi_mode(&self) -> bool {
+        self.inner.detect_uefi_mode()
+    }
+
+    fn repair_boot(
+        &self,
+        target_partition: &str,
+        use_uefi: bool,
+        options: &BootRepairOptions,
+    ) -> Result<BootRepairReport> {
+        self.push(PlanAction::WriteBootEntry {
+            partition: target_partition.to_string(),
+            uefi: Some(use_uefi),
+            description: options.entry_description.clone(),
+            side_by_side: options.side_by_side,
+        });
+        Ok(BootRepairReport::default())
+    }
+
+    fn delete_stale_original_entry(
+        &self,
+        original_guid: &str,
+        target_partition: &str,
+        _new_entry_guid: Option<&str>,
+    ) -> Result<bool> {
+        if original_guid.is_empty() {
+            return Ok(false);
+        }
+        self.push(PlanAction::DeleteBootEntry {
+            identifier: original_guid.to_string(),
+            reason: format!("原系统引导项，仍指向 {} 时删除", target_partition),
+        });
+        Ok(true)
+    }
+
+    fn delete_current_boot_entry(&self) -> Result<()> {
+        self.push(PlanAction::DeleteBootEntry {
+            identifier: "{current}".to_string(),
+            reason: "PE 引导项".to_string(),
+        });
+        Ok(())
+    }
+
+    fn apply_uefiseven_patch(&self, data_partition: &str, _target_partition: &str) -> Result<()> {
+        self.note(format!(
+            "将用 {}\\uefiseven 中的 UefiSeven 替换 EFI 分区上的 bootmgfw.efi",
+            ConfigFileManager::get_data_dir(data_partition)
+        ));
+        Ok(())
+    }
+
+    fn current_boot_entry(&self) -> Result<BcdLoaderEntry> {
+        self.inner.current_boot_entry()
+    }
+
+    fn default_boot_entry(&self) -> Result<String> {
+        self.inner.default_boot_entry()
+    }
+
+    fn set_default_boot_entry(&self, guid: &str) -> Result<()> {
+        self.note(format!("回滚时将默认引导项恢复为 {}", guid));
+        Ok(())
+    }
+
+    fn list_boot_entries(&self) -> Result<Vec<BcdLoaderEntry>> {
+        self.inner.list_boot_entries()
+    }
+
+    fn delete_boot_entry(&self, guid: &str) -> Result<()> {
+        self.push(PlanAction::DeleteBootEntry {
+            identifier: guid.to_string(),
+            reason: "回滚时删除 PE 引导项".to_string(),
+        });
+        Ok(())
+    }
+
+    fn apply_advanced_options(&self, target_partition: &str, config: &InstallConfig) -> Result<()> {
+        self.push(PlanAction::ApplyTweaks {
+            partition: target_partition.to_string(),
+            tweaks: config.enabled_tweaks().into_iter().map(str::to_string).collect(),
+        });
+        Ok(())
+    }
+
+    fn sleep(&self, _duration: Duration) {}
+
+    fn reboot(&self) {
+        self.push(PlanAction::Reboot);
+    }
+
+    fn is_dry_run(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,328 @@
+//! 安装步骤日志
+//!
+//! 安装流程的每个步骤开始、完成或失败时，向数据目录中的 `install_journal.jsonl` 追加一行记录并立即落盘。
+//! PE 崩溃或断电后再次进入时，根据日志从上次完成的步骤之后继续，不必重新格式化。
+//!
+//! 每条记录带有所属安装配置的签名，正常系统端重新发起安装后，旧记录自动失效。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use letrecovery_config::PartitionSnapshot;
+
+use crate::ui::progress::InstallStep;
+
+/// 步骤输出（如新引导项 GUID、目标分区快照）
+pub type StepOutputs = BTreeMap<String, String>;
+
+/// 日志文件名
+pub const JOURNAL_FILE: &str = "install_journal.jsonl";
+
+const SNAPSHOT_SIZE: &str = "target_size";
+const SNAPSHOT_SERIAL: &str = "target_serial";
+const SNAPSHOT_LABEL: &str = "target_label";
+
+/// 步骤状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalStatus {
+    Started,
+    Completed,
+    Failed,
+}
+
+/// 一条日志记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalRecord {
+    /// 所属安装配置的签名
+    pub config: String,
+    pub step: InstallStep,
+    pub status: JournalStatus,
+    /// Unix 时间（秒）
+    pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub outputs: StepOutputs,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub error: String,
+}
+
+impl JournalRecord {
+    /// 记录中的目标分区快照
+    pub fn snapshot(&self) -> Option<PartitionSnapshot> {
+        Some(PartitionSnapshot {
+            size_bytes: self.outputs.get(SNAPSHOT_SIZE)?.parse().ok()?,
+            serial: u32::from_str_radix(self.outputs.get(SNAPSHOT_SERIAL)?, 16).ok()?,
+            label: self.outputs.get(SNAPSHOT_LABEL)?.clone(),
+        })
+    }
+}
+
+/// 把目标分区快照写入步骤输出
+pub fn insert_snapshot(outputs: &mut StepOutputs, snapshot: &PartitionSnapshot) {
+    outputs.insert(SNAPSHOT_SIZE.to_string(), snapshot.size_bytes.to_string());
+    outputs.insert(SNAPSHOT_SERIAL.to_string(), format!("{:08X}", snapshot.serial));
+    outputs.insert(SNAPSHOT_LABEL.to_string(), snapshot.label.clone());
+}
+
+/// 安装步骤日志
+pub struct InstallJournal {
+    path: PathBuf,
+    config: String,
+    records: Vec<JournalRecord>,
+    /// 演练时只在内存中记录，不写入文件
+    read_only: bool,
+}
+
+impl InstallJournal {
+    /// 打开数据目录中的日志，只保留属于当前安装配置的记录
+    pub fn open(data_dir: impl AsRef<Path>) -> Self {
+        let data_dir = data_dir.as_ref();
+        let config_path = data_dir.join(letrecovery_config::INSTALL_CONFIG_FILE);
+        let config = std::fs::read_to_string(&config_path)
+            .map(|content| config_signature(&content))
+            .unwrap_or_default();
+        let path = data_dir.join(JOURNAL_FILE);
+        let records = std::fs::read_to_string(&path)
+            .map(|content| parse_records(&content, &config))
+            .unwrap_or_default();
+        if !records.is_empty() {
+            log::info!("读取安装日志: {} 条记录", records.len());
+        }
+        Self { path, config, records, read_only: false }
+    }
+
+    /// 打开日志，之后的记录只保存在内存中（演练模式）
+    pub fn open_read_only(data_dir: impl AsRef<Path>) -> Self {
+        Self { read_only: true, ..Self::open(data_dir) }
+    }
+
+    /// 当前安装配置的全部记录
+    pub fn records(&self) -> &[JournalRecord] {
+        &self.records
+    }
+
+    /// 是否已有记录（即之前开始过这次安装）
+    pub fn has_progress(&self) -> bool {
+        !self.records.is_empty()
+    }
+
+    /// 最近一次完成的步骤
+    pub fn last_completed(&self) -> Option<InstallStep> {
+        self.records
+            .iter()
+            .rev()
+            .find(|r| r.status == JournalStatus::Completed)
+            .map(|r| r.step)
+    }
+
+    /// 继续安装时应从哪一步开始
+    pub fn resume_step(&self) -> InstallStep {
+        let next = self.last_completed().map_or(0, |step| step.index() + 1);
+        InstallStep::all()
+            .get(next)
+            .copied()
+            .unwrap_or(InstallStep::Complete)
+    }
+
+    /// 最近一次失败的错误信息（之后又有步骤完成时不返回）
+    pub fn last_error(&self) -> Option<&str> {
+        self.records
+            .iter()
+            .rev()
+            .find(|r| r.status != JournalStatus::Started)
+            .filter(|r| r.status == JournalStatus::Failed)
+            .map(|r| r.error.as_str())
+    }
+
+    /// 某步骤最近一次完成时的输出
+    pub fn outputs(&self, step: InstallStep) -> Option<&StepOutputs> {
+        self.records
+            .iter()
+            .rev()
+            .find(|r| r.step == step && r.status == JournalStatus::Completed)
+            .map(|r| &r.outputs)
+    }
+
+    /// 目标分区当前应有的快照
+    ///
+    /// 以最近一次记录的快照为准（没有时使用配置中的快照）；
+    /// 之后中断的步骤可能已改变的属性不参与核对，改用实际值。
+    pub fn expected_target(&self, initial: &PartitionSnapshot, actual: &PartitionSnapshot) -> PartitionSnapshot {
+        let last_snapshot = self.records.iter().rposition(|r| r.snapshot().is_some());
+        let mut expected = match last_snapshot {
+            Some(index) => self.records[index].snapshot().unwrap_or_default(),
+            None => initial.clone(),
+        };
+        let unfinished = self.records[last_snapshot.map_or(0, |i| i + 1)..]
+            .iter()
+            .filter(|r| r.status != JournalStatus::Completed);
+        for record in unfinished {
+            match record.step {
+                // 格式化和释放 GHO 镜像会改变序列号和卷标
+                InstallStep::FormatPartition | InstallStep::ApplyImage => {
+                    expected.serial = actual.serial;
+                    expected.label = actual.label.clone();
+                }
+                // 清理时会扩展目标分区
+                InstallStep::Cleanup => expected.size_bytes = actual.size_bytes,
+                _ => {}
+            }
+        }
+        expected
+    }
+
+    /// 记录步骤开始
+    pub fn begin(&mut self, step: InstallStep) -> Result<()> {
+        self.append(step, JournalStatus::Started, StepOutputs::new(), String::new())
+    }
+
+    /// 记录步骤完成
+    pub fn complete(&mut self, step: InstallStep, outputs: StepOutputs) -> Result<()> {
+        self.append(step, JournalStatus::Completed, outputs, String::new())
+    }
+
+    /// 记录步骤失败
+    pub fn fail(&mut self, step: InstallStep, error: &str) -> Result<()> {
+        self.append(step, JournalStatus::Failed, StepOutputs::new(), error.to_string())
+    }
+
+    fn append(&mut self, step: InstallStep, status: JournalStatus, outputs: StepOutputs, error: String) -> Result<()> {
+        let record = JournalRecord {
+            config: self.config.clone(),
+            step,
+            status,
+            timestamp: letrecovery_config::unix_now(),
+            outputs,
+            error,
+        };
+        if self.read_only {
+            self.records.push(record);
+            return Ok(());
+        }
+        let line = serde_json::to_string(&record)?;
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("打开安装日志失败: {}", self.path.display()))?;
+        writeln!(file, "{}", line)?;
+        // 断电后记录仍需可用，写入后立即落盘
+        file.sync_all()?;
+
+        self.records.push(record);
+        Ok(())
+    }
+}
+
+/// 安装配置的签名，用于区分不同次发起的安装
+fn config_signature(content: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|value| value.get("hmac")?.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// 解析日志内容，跳过断电时写了一半的行和其他配置的记录
+fn parse_records(content: &str, config: &str) -> Vec<JournalRecord> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JournalRecord>(line).ok())
+        .filter(|r| !config.is_empty() && r.config == config)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(records: Vec<(InstallStep, JournalStatus)>) -> InstallJournal {
+        InstallJournal {
+            path: PathBuf::new(),
+            config: "SIG".to_string(),
+            records: records
+                .into_iter()
+                .map(|(step, status)| JournalRecord {
+                    config: "SIG".to_string(),
+                    step,
+                    status,
+                    timestamp: 0,
+                    outputs: StepOutputs::new(),
+                    error: String::new(),
+                })
+                .collect(),
+            read_only: false,
+        }
+    }
+
+    fn snapshot(size_bytes: u64, serial: u32, label: &str) -> PartitionSnapshot {
+        PartitionSnapshot { size_bytes, serial, label: label.to_string() }
+    }
+
+    #[test]
+    fn test_resume_after_last_completed_step() {
+        let empty = journal(vec![]);
+        assert!(!empty.has_progress());
+        assert_eq!(empty.resume_step(), InstallStep::FormatPartition);
+
+        let mut j = journal(vec![
+            (InstallStep::FormatPartition, JournalStatus::Started),
+            (InstallStep::FormatPartition, JournalStatus::Completed),
+            (InstallStep::ApplyImage, JournalStatus::Started),
+        ]);
+        assert_eq!(j.resume_step(), InstallStep::ApplyImage);
+        assert_eq!(j.last_error(), None);
+
+        j.records.push(JournalRecord {
+            error: "释放镜像失败".to_string(),
+            ..j.records[2].clone()
+        });
+        j.records[3].status = JournalStatus::Failed;
+        assert_eq!(j.resume_step(), InstallStep::ApplyImage);
+        assert_eq!(j.last_error(), Some("释放镜像失败"));
+    }
+
+    #[test]
+    fn test_parse_skips_torn_lines_and_other_configs() {
+        let record = JournalRecord {
+            config: "SIG".to_string(),
+            step: InstallStep::RepairBoot,
+            status: JournalStatus::Completed,
+            timestamp: 1,
+            outputs: StepOutputs::from([("boot_entry".to_string(), "{abc}".to_string())]),
+            error: String::new(),
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        let other = line.replace("\"SIG\"", "\"OLD\"");
+        let content = format!("{}\n{}\n{}", other, line, &line[..line.len() / 2]);
+
+        assert_eq!(parse_records(&content, "SIG"), vec![record]);
+        assert!(parse_records(&content, "").is_empty());
+    }
+
+    #[test]
+    fn test_expected_target_relaxes_interrupted_steps() {
+        let initial = snapshot(100, 0x1111, "旧系统");
+        let actual = snapshot(100, 0x2222, "Windows");
+
+        // 格式化中断：序列号和卷标可能已变化
+        let j = journal(vec![(InstallStep::FormatPartition, JournalStatus::Started)]);
+        assert_eq!(j.expected_target(&initial, &actual), snapshot(100, 0x2222, "Windows"));
+
+        // 格式化完成后记录的快照为准，后续步骤不改变分区
+        let mut j = journal(vec![
+            (InstallStep::FormatPartition, JournalStatus::Completed),
+            (InstallStep::ImportDrivers, JournalStatus::Started),
+        ]);
+        insert_snapshot(&mut j.records[0].outputs, &snapshot(100, 0x3333, "Windows"));
+        assert_eq!(j.expected_target(&initial, &actual), snapshot(100, 0x3333, "Windows"));
+        assert_eq!(j.records[0].snapshot(), Some(snapshot(100, 0x3333, "Windows")));
+
+        // 清理中断：只放宽大小
+        let j = journal(vec![(InstallStep::Cleanup, JournalStatus::Started)]);
+        assert_eq!(j.expected_target(&initial, &snapshot(200, 0x1111, "旧系统")), snapshot(200, 0x1111, "旧系统"));
+    }
+}
@@ -1,12 +1,24 @@
+pub mod backend;
 pub mod bcdedit;
+#[cfg(windows)]
 pub mod cabinet;
 pub mod config;
 pub mod dism;
 pub mod dism_exe;
+#[cfg(windows)]
 pub mod dismapi;
 pub mod disk;
+#[cfg(windows)]
 pub mod driver;
+pub mod dry_run;
 pub mod ghost;
+pub mod journal;
 pub mod registry;
+pub mod rollback;
 pub mod system_utils;
+pub mod unattend;
+#[cfg(windows)]
 pub mod wimgapi;
+
+// 与正常系统端共用的磁盘模块
+pub use letrecovery_disk::{boot_code, diskpart, esp_inventory, raw_image, volume_id};
@@ -0,0 +1,260 @@
+//! 安装在格式化目标分区前失败时回滚到原系统
+//!
+//! 此时原系统完好：恢复原系统为默认引导项，删除 PE 引导项（启动加载器和 ramdisk 设备）、
+//! 标记文件和临时数据，在原系统盘根目录写入失败报告（正常系统端下次启动时显示），然后重启。
+
+use letrecovery_config::{ConfigKind, FailureReport, FAILURE_REPORT_FILE};
+
+use crate::core::backend::SystemBackend;
+use crate::core::config::ConfigFileManager;
+
+/// PE 文件目录名（正常系统端写在系统盘上）
+const PE_DIR: &str = "LetRecovery_PE";
+
+/// 回滚所需的信息，失败得越早，已知的越少
+#[derive(Debug, Clone, Default)]
+pub struct RollbackContext {
+    /// 数据分区
+    pub data_partition: Option<String>,
+    /// 目标分区
+    pub target_partition: Option<String>,
+    /// 原系统引导项 GUID（来自安装配置）
+    pub original_guid: String,
+}
+
+/// 回滚到原系统并写入失败报告，返回写入的报告
+///
+/// 各项操作互不依赖，某项失败只记入报告，不影响其余操作。调用方随后重启。
+pub fn rollback_install(backend: &dyn SystemBackend, ctx: &RollbackContext, stage: &str, error: &str) -> FailureReport {
+    log::info!("========== 回滚到原系统 ==========");
+    let mut report = FailureReport::new(ConfigKind::Install, stage, error);
+
+    // 只处理由正常系统端创建的 PE 引导项，避免误删用户自己的 PE/U 盘引导项
+    let current = backend.current_boot_entry().ok();
+    let ramdisk = current
+        .as_ref()
+        .and_then(|entry| entry.ramdisk())
+        .filter(|ramdisk| ramdisk.path.to_ascii_lowercase().contains(&PE_DIR.to_ascii_lowercase()));
+
+    // 1. 默认引导项仍是 PE 时改回原系统
+    match backend.default_boot_entry() {
+        Ok(default) => {
+            let is_pe = default.eq_ignore_ascii_case("{current}")
+                || current
+                    .as_ref()
+                    .is_some_and(|entry| entry.identifier.eq_ignore_ascii_case(&default));
+            if is_pe {
+                let current_guid = current.as_ref().map(|e| e.identifier.as_str());
+                match fallback_default(backend, &ctx.original_guid, current_guid) {
+                    Some(guid) => match backend.set_default_boot_entry(&guid) {
+                        Ok(()) => report.rollback_actions.push(format!("已将默认引导项恢复为 {}", guid)),
+                        Err(e) => report.rollback_warnings.push(format!("恢复默认引导项失败: {}", e)),
+                    },
+                    None => report
+                        .rollback_warnings
+                        .push("未找到原系统引导项，无法恢复默认引导项".to_string()),
+                }
+            } else {
+                report.rollback_actions.push(format!("默认引导项 {} 未被改动", default));
+            }
+        }
+        Err(e) => report.rollback_warnings.push(format!("读取默认引导项失败: {}", e)),
+    }
+
+    // 2. 删除 PE 引导项和 ramdisk 设备
+    match (&current, &ramdisk) {
+        (Some(entry), Some(ramdisk)) => {
+            for guid in [&entry.identifier, &ramdisk.options_guid] {
+                match backend.delete_boot_entry(guid) {
+                    Ok(()) => report.rollback_actions.push(format!("已删除 PE 引导项 {}", guid)),
+                    Err(e) => report.rollback_warnings.push(format!("删除 PE 引导项 {} 失败: {}", guid, e)),
+                }
+            }
+        }
+        _ => report
+            .rollback_warnings
+            .push("当前 PE 不是由 LetRecovery 创建的引导项启动的，未删除引导项".to_string()),
+    }
+
+    // 3. 删除标记文件和临时数据
+    let target_partition = ctx
+        .target_partition
+        .clone()
+        .or_else(|| backend.find_install_marker_partition());
+    let mut leftovers = Vec::new();
+    if let Some(data) = &ctx.data_partition {
+        leftovers.push(ConfigFileManager::get_data_dir(data));
+        leftovers.push(ConfigFileManager::get_pe_dir(data));
+    }
+    if let Some(target) = &target_partition {
+        leftovers.push(format!("{}\\{}", target, ConfigFileManager::INSTALL_MARKER));
+        leftovers.push(format!("{}\\{}", target, ConfigFileManager::BACKUP_MARKER));
+    }
+    let mut cleaned = true;
+    for path in &leftovers {
+        if let Err(e) = backend.remove_path(path) {
+            report.rollback_warnings.push(format!("删除 {} 失败: {}", path, e));
+            cleaned = false;
+        }
+    }
+    if cleaned {
+        report.rollback_actions.push("已清理标记文件和临时数据".to_string());
+    }
+
+    // 4. 删除 PE 文件，报告写在同一分区（即原系统盘）
+    let report_root = ramdisk.as_ref().map(|r| r.partition_root());
+    if let Some(root) = &report_root {
+        let pe_dir = format!("{}{}", root, PE_DIR);
+        match backend.remove_path(&pe_dir) {
+            Ok(true) => report.rollback_actions.push(format!("已删除 PE 文件 {}", pe_dir)),
+            Ok(false) => {}
+            Err(e) => report.rollback_warnings.push(format!("删除 PE 文件 {} 失败: {}", pe_dir, e)),
+        }
+    }
+
+    // 5. 写入失败报告
+    let report_root = report_root
+        .or_else(|| target_partition.as_ref().map(|t| format!("{}\\", t.trim_end_matches('\\'))));
+    match report_root {
+        Some(root) => {
+            let path = format!("{}{}", root, FAILURE_REPORT_FILE);
+            match backend.write_file(&path, &report.to_json()) {
+                Ok(()) => log::info!("已写入失败报告: {}", path),
+                Err(e) => log::warn!("写入失败报告失败 ({}): {}", path, e),
+            }
+        }
+        None => log::warn!("无法确定原系统盘，未写入失败报告"),
+    }
+
+    for action in &report.rollback_actions {
+        log::info!("[ROLLBACK] {}", action);
+    }
+    for warning in &report.rollback_warnings {
+        log::warn!("[ROLLBACK] {}", warning);
+    }
+    log::info!("========== 回滚完成 ==========");
+    report
+}
+
+/// 选择恢复为默认的引导项：优先原系统引导项，其次第一个非 PE 的引导项
+fn fallback_default(backend: &dyn SystemBackend, original_guid: &str, current: Option<&str>) -> Option<String> {
+    let entries = backend.list_boot_entries().ok()?;
+    let is_current = |guid: &str| current.is_some_and(|c| c.eq_ignore_ascii_case(guid));
+    entries
+        .iter()
+        .find(|e| !original_guid.is_empty() && e.identifier.eq_ignore_ascii_case(original_guid))
+        .or_else(|| entries.iter().find(|e| !is_current(&e.identifier) && e.ramdisk().is_none()))
+        .map(|e| e.identifier.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::backend::fake::{FakeBackend, FakeBcd};
+    use crate::core::esp_inventory::BcdLoaderEntry;
+
+    const ORIGINAL: &str = "{original}";
+    const PE_ENTRY: &str = "{pe}";
+
+    /// 原系统在 C:，PE 文件和数据目录在 D:，当前从 PE 引导项启动且它是默认引导项
+    fn pe_backend(name: &str) -> FakeBackend {
+        let backend = FakeBackend::new(name);
+        *backend.bcd.lock().unwrap() = FakeBcd {
+            entries: vec![
+                BcdLoaderEntry {
+                    identifier: ORIGINAL.to_string(),
+                    device: "partition=C:".to_string(),
+                    description: "Windows 10".to_string(),
+                },
+                BcdLoaderEntry {
+                    identifier: PE_ENTRY.to_string(),
+                    device: "ramdisk=[D:]\\LetRecovery_PE\\boot.wim,{ramdisk}".to_string(),
+                    description: "LetRecovery PE".to_string(),
+                },
+            ],
+            current: Some(PE_ENTRY.to_string()),
+            default: PE_ENTRY.to_string(),
+        };
+
+        for dir in ["D:\\LetRecovery_Data", "D:\\LetRecovery_PE", "C:\\Windows\\System32"] {
+            std::fs::create_dir_all(backend.path(dir)).unwrap();
+        }
+        std::fs::write(backend.path("D:\\LetRecovery_PE\\boot.wim"), b"wim").unwrap();
+        std::fs::write(backend.path(&format!("C:\\{}", ConfigFileManager::INSTALL_MARKER)), b"").unwrap();
+        std::fs::write(backend.path("C:\\old_file.txt"), b"old").unwrap();
+        backend
+    }
+
+    fn context() -> RollbackContext {
+        RollbackContext {
+            data_partition: Some("D:".to_string()),
+            target_partition: Some("C:".to_string()),
+            original_guid: ORIGINAL.to_string(),
+        }
+    }
+
+    fn written_report(backend: &FakeBackend) -> FailureReport {
+        let content = std::fs::read_to_string(backend.path(&format!("D:\\{}", FAILURE_REPORT_FILE))).unwrap();
+        FailureReport::from_json(&content).unwrap()
+    }
+
+    #[test]
+    fn test_rollback_restores_original_system() {
+        let backend = pe_backend("rollback_ok");
+        let report = rollback_install(&backend, &context(), "读取配置", "配置文件损坏");
+
+        assert!(report.rollback_warnings.is_empty(), "{:?}", report.rollback_warnings);
+        let bcd = backend.bcd.lock().unwrap().clone();
+        assert_eq!(bcd.default, ORIGINAL);
+        assert_eq!(bcd.entries.len(), 1);
+        assert!(backend.calls().contains(&"delete_boot_entry {ramdisk}".to_string()));
+
+        assert!(!backend.path("D:\\LetRecovery_Data").exists());
+        assert!(!backend.path("D:\\LetRecovery_PE").exists());
+        assert!(!backend.path(&format!("C:\\{}", ConfigFileManager::INSTALL_MARKER)).exists());
+
+        // 原系统完好
+        assert!(backend.path("C:\\old_file.txt").is_file());
+        assert!(backend.path("C:\\Windows\\System32").is_dir());
+        assert!(!backend.operations().contains(&"format_partition".to_string()));
+
+        assert_eq!(written_report(&backend), report);
+    }
+
+    #[test]
+    fn test_rollback_continues_after_partial_failure() {
+        let backend = pe_backend("rollback_partial");
+        backend.fail_on("set_default_boot_entry");
+        backend.fail_on("delete_boot_entry");
+        let report = rollback_install(&backend, &context(), "核对目标分区", "目标分区已变化");
+
+        // 引导项操作失败只记入报告
+        assert_eq!(report.rollback_warnings.len(), 3, "{:?}", report.rollback_warnings);
+        assert!(report.rollback_warnings[0].contains("恢复默认引导项失败"));
+        let bcd = backend.bcd.lock().unwrap().clone();
+        assert_eq!(bcd.default, PE_ENTRY);
+        assert_eq!(bcd.entries.len(), 2);
+
+        // 其余操作照常完成
+        assert!(!backend.path("D:\\LetRecovery_Data").exists());
+        assert!(!backend.path(&format!("C:\\{}", ConfigFileManager::INSTALL_MARKER)).exists());
+        assert!(backend.path("C:\\old_file.txt").is_file());
+        assert!(!backend.operations().contains(&"format_partition".to_string()));
+
+        let written = written_report(&backend);
+        assert_eq!(written.stage, "核对目标分区");
+        assert_eq!(written.rollback_warnings, report.rollback_warnings);
+    }
+
+    #[test]
+    fn test_rollback_keeps_foreign_pe_entry() {
+        let backend = pe_backend("rollback_foreign");
+        backend.bcd.lock().unwrap().entries[1].device = "ramdisk=[E:]\\sources\\boot.wim,{ramdisk}".to_string();
+        let report = rollback_install(&backend, &context(), "读取配置", "配置文件损坏");
+
+        assert!(!backend.operations().contains(&"delete_boot_entry".to_string()));
+        assert!(report.rollback_warnings.iter().any(|w| w.contains("未删除引导项")));
+        // 不知道 PE 所在分区时，报告写在目标分区
+        assert!(backend.path(&format!("C:\\{}", FAILURE_REPORT_FILE)).is_file());
+    }
+}
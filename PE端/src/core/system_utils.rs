@@ -13,7 +13,7 @@ use std::path::{Path, PathBuf};
 // =============================================================================
 
 /// Windows 版本信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct WindowsVersion {
     /// 主版本号 (如 Windows 10 = 10)
     pub major: u32,
@@ -27,18 +27,6 @@ pub struct WindowsVersion {
     pub product_name: String,
 }
 
-impl Default for WindowsVersion {
-    fn default() -> Self {
-        Self {
-            major: 0,
-            minor: 0,
-            build: 0,
-            version_string: String::new(),
-            product_name: String::new(),
-        }
-    }
-}
-
 impl WindowsVersion {
     /// 是否为 Windows 7
     pub fn is_win7(&self) -> bool {
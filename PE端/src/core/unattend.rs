@@ -0,0 +1,504 @@
+//! 无人值守应答文件 (unattend.xml)
+//!
+//! 应答文件由配置阶段 (pass) 和组件 (component) 组成，组件下是具体设置。
+//! 这里用类型描述这些结构，渲染时统一转义 XML 特殊字符，
+//! 用户名、脚本路径中出现 `&`、`<` 等字符也不会破坏文件。
+//! 不同 Windows 版本支持的 OOBE 选项不同，由 [`WindowsVersion`] 决定输出哪些设置。
+
+const UNATTEND_NAMESPACE: &str = "urn:schemas-microsoft-com:unattend";
+const WCM_NAMESPACE: &str = "http://schemas.microsoft.com/WMIConfig/2002/State";
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+const PUBLIC_KEY_TOKEN: &str = "31bf3856ad364e35";
+
+/// 缩进宽度
+const INDENT: &str = "    ";
+
+/// 目标系统版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowsVersion {
+    /// Windows 7
+    Win7,
+    /// Windows 8 / 8.1
+    Win8,
+    /// Windows 10 / 11
+    Win10,
+}
+
+impl WindowsVersion {
+    /// 按 ntdll.dll 的文件版本判断
+    ///
+    /// Windows 7: 6.1.x, Windows 8: 6.2.x, Windows 8.1: 6.3.x, 其余按 Windows 10/11 处理
+    pub fn from_nt_version(major: u32, minor: u32) -> Self {
+        match (major, minor) {
+            (6, 1) => Self::Win7,
+            (6, 2) | (6, 3) => Self::Win8,
+            _ => Self::Win10,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Win7 => "Win7配置",
+            Self::Win8 => "Win8配置",
+            Self::Win10 => "Win10/11配置",
+        }
+    }
+
+    /// 删除预装 UWP 应用的脚本只支持 Win10/11
+    pub fn supports_uwp_removal(self) -> bool {
+        self == Self::Win10
+    }
+
+    /// OOBE 设置
+    ///
+    /// - Win7 只使用最小化配置以兼容所有版本（包括家庭版），
+    ///   需要设置 NetworkLocation 来跳过网络位置选择
+    /// - Win8/8.1 额外支持 HideLocalAccountScreen
+    /// - Win10/11 支持全部跳过选项，不再需要 NetworkLocation
+    fn oobe(self) -> Element {
+        let hide = |name| Element::text(name, "true");
+        let settings = match self {
+            Self::Win7 => vec![
+                hide("HideEULAPage"),
+                Element::text("ProtectYourPC", "3"),
+                Element::text("NetworkLocation", "Home"),
+            ],
+            Self::Win8 => vec![
+                hide("HideEULAPage"),
+                hide("HideLocalAccountScreen"),
+                Element::text("ProtectYourPC", "3"),
+                Element::text("NetworkLocation", "Home"),
+            ],
+            Self::Win10 => vec![
+                hide("HideEULAPage"),
+                hide("HideLocalAccountScreen"),
+                hide("HideOnlineAccountScreens"),
+                hide("HideWirelessSetupInOOBE"),
+                Element::text("ProtectYourPC", "3"),
+                hide("SkipMachineOOBE"),
+                hide("SkipUserOOBE"),
+            ],
+        };
+        Element::parent("OOBE", settings)
+    }
+}
+
+/// 配置阶段，按执行顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pass {
+    WindowsPE,
+    Specialize,
+    OobeSystem,
+}
+
+impl Pass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::WindowsPE => "windowsPE",
+            Self::Specialize => "specialize",
+            Self::OobeSystem => "oobeSystem",
+        }
+    }
+}
+
+/// 组件名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentName {
+    Setup,
+    ShellSetup,
+    InternationalCore,
+    Deployment,
+}
+
+impl ComponentName {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Setup => "Microsoft-Windows-Setup",
+            Self::ShellSetup => "Microsoft-Windows-Shell-Setup",
+            Self::InternationalCore => "Microsoft-Windows-International-Core",
+            Self::Deployment => "Microsoft-Windows-Deployment",
+        }
+    }
+}
+
+/// 转义 XML 文本和属性值中的特殊字符
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, Clone)]
+enum Content {
+    Text(String),
+    Children(Vec<Element>),
+}
+
+/// 组件中的设置项
+#[derive(Debug, Clone)]
+pub struct Element {
+    name: &'static str,
+    /// 列表项需要 `wcm:action="add"`
+    list_item: bool,
+    content: Content,
+}
+
+impl Element {
+    pub fn text(name: &'static str, value: impl Into<String>) -> Self {
+        Self { name, list_item: false, content: Content::Text(value.into()) }
+    }
+
+    pub fn parent(name: &'static str, children: Vec<Element>) -> Self {
+        Self { name, list_item: false, content: Content::Children(children) }
+    }
+
+    /// 标记为列表项（LocalAccount、SynchronousCommand 等）
+    pub fn list_item(mut self) -> Self {
+        self.list_item = true;
+        self
+    }
+
+    fn render(&self, out: &mut String, depth: usize) {
+        let indent = INDENT.repeat(depth);
+        let action = if self.list_item { r#" wcm:action="add""# } else { "" };
+        match &self.content {
+            Content::Text(value) => {
+                out.push_str(&format!("{}<{}{}>{}</{}>\n", indent, self.name, action, escape_xml(value), self.name));
+            }
+            Content::Children(children) => {
+                out.push_str(&format!("{}<{}{}>\n", indent, self.name, action));
+                for child in children {
+                    child.render(out, depth + 1);
+                }
+                out.push_str(&format!("{}</{}>\n", indent, self.name));
+            }
+        }
+    }
+}
+
+/// 同步执行的命令（specialize 阶段的 RunSynchronous 和首次登录命令）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub command_line: String,
+    pub description: String,
+}
+
+impl Command {
+    pub fn new(command_line: impl Into<String>, description: impl Into<String>) -> Self {
+        Self { command_line: command_line.into(), description: description.into() }
+    }
+}
+
+/// 组件及其设置
+#[derive(Debug, Clone)]
+pub struct Component {
+    pub name: ComponentName,
+    pub settings: Vec<Element>,
+}
+
+impl Component {
+    pub fn new(name: ComponentName, settings: Vec<Element>) -> Self {
+        Self { name, settings }
+    }
+
+    /// windowsPE: 接受许可协议，仅在出错时显示产品密钥页面
+    pub fn setup() -> Self {
+        Self::new(
+            ComponentName::Setup,
+            vec![Element::parent(
+                "UserData",
+                vec![
+                    Element::parent("ProductKey", vec![Element::text("WillShowUI", "OnError")]),
+                    Element::text("AcceptEula", "true"),
+                ],
+            )],
+        )
+    }
+
+    /// specialize: 计算机名，`*` 表示随机生成
+    pub fn computer_name(name: &str) -> Self {
+        Self::new(ComponentName::ShellSetup, vec![Element::text("ComputerName", name)])
+    }
+
+    /// specialize: 依次执行的部署命令
+    pub fn deployment(commands: &[Command]) -> Self {
+        let items = commands
+            .iter()
+            .enumerate()
+            .map(|(i, cmd)| {
+                Element::parent(
+                    "RunSynchronousCommand",
+                    vec![
+                        Element::text("Order", (i + 1).to_string()),
+                        Element::text("Path", cmd.command_line.as_str()),
+                        Element::text("Description", cmd.description.as_str()),
+                    ],
+                )
+                .list_item()
+            })
+            .collect();
+        Self::new(ComponentName::Deployment, vec![Element::parent("RunSynchronous", items)])
+    }
+
+    /// oobeSystem: 输入法、区域和界面语言（如 zh-CN）
+    pub fn international(locale: &str) -> Self {
+        Self::new(
+            ComponentName::InternationalCore,
+            vec![
+                Element::text("InputLocale", locale),
+                Element::text("SystemLocale", locale),
+                Element::text("UILanguage", locale),
+                Element::text("UserLocale", locale),
+            ],
+        )
+    }
+
+    /// oobeSystem: OOBE 选项、空密码的本地管理员账户、自动登录一次和首次登录命令
+    pub fn oobe_shell_setup(version: WindowsVersion, username: &str, first_logon_commands: &[Command]) -> Self {
+        let empty_password = || {
+            Element::parent(
+                "Password",
+                vec![Element::text("Value", ""), Element::text("PlainText", "true")],
+            )
+        };
+
+        let account = Element::parent(
+            "LocalAccount",
+            vec![
+                empty_password(),
+                Element::text("Description", "Local User"),
+                Element::text("DisplayName", username),
+                Element::text("Group", "Administrators"),
+                Element::text("Name", username),
+            ],
+        )
+        .list_item();
+
+        let auto_logon = Element::parent(
+            "AutoLogon",
+            vec![
+                empty_password(),
+                Element::text("Enabled", "true"),
+                Element::text("LogonCount", "1"),
+                Element::text("Username", username),
+            ],
+        );
+
+        let mut settings = vec![
+            version.oobe(),
+            Element::parent("UserAccounts", vec![Element::parent("LocalAccounts", vec![account])]),
+            auto_logon,
+        ];
+
+        if !first_logon_commands.is_empty() {
+            let items = first_logon_commands
+                .iter()
+                .enumerate()
+                .map(|(i, cmd)| {
+                    Element::parent(
+                        "SynchronousCommand",
+                        vec![
+                            Element::text("Order", (i + 1).to_string()),
+                            Element::text("CommandLine", cmd.command_line.as_str()),
+                            Element::text("Description", cmd.description.as_str()),
+                        ],
+                    )
+                    .list_item()
+                })
+                .collect();
+            settings.push(Element::parent("FirstLogonCommands", items));
+        }
+
+        Self::new(ComponentName::ShellSetup, settings)
+    }
+
+    fn render(&self, out: &mut String, arch: &str, depth: usize) {
+        let indent = INDENT.repeat(depth);
+        out.push_str(&format!(
+            r#"{}<component name="{}" processorArchitecture="{}" publicKeyToken="{}" language="neutral" versionScope="nonSxS" xmlns:wcm="{}" xmlns:xsi="{}">"#,
+            indent,
+            self.name.as_str(),
+            escape_xml(arch),
+            PUBLIC_KEY_TOKEN,
+            WCM_NAMESPACE,
+            XSI_NAMESPACE
+        ));
+        out.push('\n');
+        for setting in &self.settings {
+            setting.render(out, depth + 1);
+        }
+        out.push_str(&format!("{}</component>\n", indent));
+    }
+}
+
+/// 应答文件
+#[derive(Debug, Clone)]
+pub struct Unattend {
+    /// 组件的 processorArchitecture（x86/amd64/arm64）
+    arch: String,
+    passes: Vec<(Pass, Vec<Component>)>,
+}
+
+impl Unattend {
+    pub fn new(arch: impl Into<String>) -> Self {
+        Self { arch: arch.into(), passes: Vec::new() }
+    }
+
+    /// 添加组件，同一阶段内按添加顺序输出，阶段按执行顺序输出
+    pub fn add(&mut self, pass: Pass, component: Component) -> &mut Self {
+        match self.passes.iter_mut().find(|(p, _)| *p == pass) {
+            Some((_, components)) => components.push(component),
+            None => {
+                self.passes.push((pass, vec![component]));
+                self.passes.sort_by_key(|(p, _)| *p);
+            }
+        }
+        self
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        out.push_str(&format!(
+            "<unattend xmlns=\"{}\" xmlns:wcm=\"{}\">\n",
+            UNATTEND_NAMESPACE, WCM_NAMESPACE
+        ));
+        for (pass, components) in &self.passes {
+            out.push_str(&format!("{}<settings pass=\"{}\">\n", INDENT, pass.as_str()));
+            for component in components {
+                component.render(&mut out, &self.arch, 2);
+            }
+            out.push_str(&format!("{}</settings>\n", INDENT));
+        }
+        out.push_str("</unattend>\n");
+        out
+    }
+}
+
+/// 安装流程使用的无人值守设置
+#[derive(Debug, Clone)]
+pub struct UnattendSettings {
+    pub version: WindowsVersion,
+    pub arch: String,
+    pub username: String,
+    /// 为空时不写入 International-Core，由 OOBE 使用系统默认值
+    pub locale: Option<String>,
+    pub deploy_commands: Vec<Command>,
+    pub first_logon_commands: Vec<Command>,
+}
+
+impl UnattendSettings {
+    pub fn build(&self) -> Unattend {
+        let mut unattend = Unattend::new(self.arch.as_str());
+        unattend.add(Pass::WindowsPE, Component::setup());
+        unattend.add(Pass::Specialize, Component::computer_name("*"));
+        if !self.deploy_commands.is_empty() {
+            unattend.add(Pass::Specialize, Component::deployment(&self.deploy_commands));
+        }
+        if let Some(locale) = self.locale.as_deref().filter(|l| !l.is_empty()) {
+            unattend.add(Pass::OobeSystem, Component::international(locale));
+        }
+        unattend.add(
+            Pass::OobeSystem,
+            Component::oobe_shell_setup(self.version, &self.username, &self.first_logon_commands),
+        );
+        unattend
+    }
+
+    pub fn to_xml(&self) -> String {
+        self.build().to_xml()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPTS: &str = "%SystemDrive%\\LetRecovery_Scripts";
+
+    fn settings(version: WindowsVersion, arch: &str) -> UnattendSettings {
+        let mut first_logon_commands = vec![Command::new(
+            format!("cmd /c if exist {0}\\firstlogon.bat call {0}\\firstlogon.bat", SCRIPTS),
+            "Run first login script",
+        )];
+        if version.supports_uwp_removal() {
+            first_logon_commands.push(Command::new(
+                format!("powershell -ExecutionPolicy Bypass -File {}\\remove_uwp.ps1", SCRIPTS),
+                "Remove preinstalled UWP apps",
+            ));
+        }
+        first_logon_commands.push(Command::new(format!("cmd /c rd /s /q {}", SCRIPTS), "Cleanup scripts directory"));
+
+        UnattendSettings {
+            version,
+            arch: arch.to_string(),
+            username: "User".to_string(),
+            locale: None,
+            deploy_commands: vec![Command::new(
+                format!("cmd /c if exist {0}\\deploy.bat call {0}\\deploy.bat", SCRIPTS),
+                "Run custom deploy script",
+            )],
+            first_logon_commands,
+        }
+    }
+
+    #[test]
+    fn test_golden_files() {
+        assert_eq!(
+            settings(WindowsVersion::Win7, "x86").to_xml(),
+            include_str!("../../testdata/unattend/win7.xml")
+        );
+        assert_eq!(
+            settings(WindowsVersion::Win8, "amd64").to_xml(),
+            include_str!("../../testdata/unattend/win8.xml")
+        );
+        assert_eq!(
+            settings(WindowsVersion::Win10, "amd64").to_xml(),
+            include_str!("../../testdata/unattend/win10.xml")
+        );
+    }
+
+    #[test]
+    fn test_escapes_special_characters() {
+        let mut s = settings(WindowsVersion::Win10, "amd64");
+        s.username = "Tom & <Jerry's> \"PC\"".to_string();
+        let xml = s.to_xml();
+
+        let escaped = "Tom &amp; &lt;Jerry&apos;s&gt; &quot;PC&quot;";
+        assert!(xml.contains(&format!("<Name>{}</Name>", escaped)));
+        assert!(xml.contains(&format!("<DisplayName>{}</DisplayName>", escaped)));
+        assert!(xml.contains(&format!("<Username>{}</Username>", escaped)));
+        assert!(!xml.contains("<Jerry"));
+    }
+
+    #[test]
+    fn test_version_component_sets() {
+        assert_eq!(WindowsVersion::from_nt_version(6, 1), WindowsVersion::Win7);
+        assert_eq!(WindowsVersion::from_nt_version(6, 3), WindowsVersion::Win8);
+        assert_eq!(WindowsVersion::from_nt_version(10, 0), WindowsVersion::Win10);
+
+        let win7 = settings(WindowsVersion::Win7, "x86").to_xml();
+        assert!(win7.contains("<NetworkLocation>Home</NetworkLocation>"));
+        assert!(!win7.contains("SkipMachineOOBE"));
+        assert!(!win7.contains("remove_uwp.ps1"));
+
+        // 设置区域后 International-Core 出现在 oobeSystem 阶段的 Shell-Setup 之前
+        let mut s = settings(WindowsVersion::Win10, "arm64");
+        s.locale = Some("zh-CN".to_string());
+        let xml = s.to_xml();
+        let oobe = xml.find(r#"<settings pass="oobeSystem">"#).unwrap();
+        let international = xml.find("Microsoft-Windows-International-Core").unwrap();
+        assert!(international > oobe);
+        assert!(xml[international..].contains("<UILanguage>zh-CN</UILanguage>"));
+        assert!(international < xml.rfind("Microsoft-Windows-Shell-Setup").unwrap());
+        assert!(xml.contains(r#"processorArchitecture="arm64""#));
+    }
+}
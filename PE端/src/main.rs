@@ -1,4 +1,6 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// 非 Windows 平台只编译安装/备份流程并运行测试，只有实际后端用到的代码在这里都是未使用的
+#![cfg_attr(not(windows), allow(dead_code, unused_imports))]
 
 mod app;
 mod core;
@@ -18,6 +20,25 @@ fn main() -> eframe::Result<()> {
     // 检查命令行参数
     let args: Vec<String> = std::env::args().collect();
 
+    // 演练模式：只输出安装计划，不改动任何磁盘（加 --json 输出 JSON）
+    if args.contains(&"/DRYRUN".to_string()) || args.contains(&"--dry-run".to_string()) {
+        log::info!("检测到演练模式，生成安装计划...");
+        let backend = match core::backend::native_backend() {
+            Ok(backend) => backend,
+            Err(e) => {
+                show_error_message(&format!("{:#}", e));
+                return Ok(());
+            }
+        };
+        let plan = app::plan_install(backend);
+        if args.contains(&"/JSON".to_string()) || args.contains(&"--json".to_string()) {
+            println!("{}", plan.to_json());
+        } else {
+            println!("{}", plan.to_text());
+        }
+        return Ok(());
+    }
+
     // 命令行模式（无GUI）
     if args.contains(&"/PEINSTALL".to_string()) || args.contains(&"--pe-install".to_string()) {
         log::info!("检测到PE安装模式（命令行），执行自动安装...");
@@ -98,430 +119,72 @@ fn load_icon() -> egui::IconData {
 }
 
 /// 命令行模式执行
+///
+/// 与 GUI 使用同一套安装/备份流程（包括安装日志、继续中断的安装和格式化前失败时的回滚），
+/// 只是把进度输出到控制台，失败时弹出提示。
 fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
-    use core::bcdedit::BootManager;
-    use core::config::ConfigFileManager;
-    use core::dism::Dism;
-    use core::disk::DiskManager;
-    use core::ghost::Ghost;
-    use ui::advanced_options::apply_advanced_options;
-
-    /// 递归查找目录中的所有 CAB 文件
-    fn find_cab_files_in_dir(dir: &str) -> Vec<std::path::PathBuf> {
-        fn find_recursive(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
-            if let Ok(entries) = std::fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(ext) = path.extension() {
-                            if ext.to_string_lossy().to_lowercase() == "cab" {
-                                files.push(path);
-                            }
-                        }
-                    } else if path.is_dir() {
-                        find_recursive(&path, files);
-                    }
-                }
-            }
-        }
-        let mut files = Vec::new();
-        find_recursive(std::path::Path::new(dir), &mut files);
-        files
-    }
-
-    if is_install {
-        println!("[PE INSTALL] ========== PE自动安装模式 ==========");
-
-        // 查找配置文件所在分区
-        let data_partition = match ConfigFileManager::find_data_partition() {
-            Some(p) => p,
-            None => {
-                eprintln!("[PE INSTALL] 错误: 未找到安装配置文件");
-                show_error_message("未找到安装配置文件，无法继续安装。");
-                return Ok(());
-            }
-        };
-
-        println!("[PE INSTALL] 数据分区: {}", data_partition);
-
-        // 读取安装配置
-        let config = match ConfigFileManager::read_install_config(&data_partition) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("[PE INSTALL] 错误: 读取配置失败: {}", e);
-                show_error_message(&format!("读取安装配置失败: {}", e));
-                return Ok(());
-            }
-        };
-
-        println!("[PE INSTALL] 目标分区: {}", config.target_partition);
-        println!("[PE INSTALL] 镜像文件: {}", config.image_path);
+    use std::sync::mpsc::channel;
 
-        // 查找安装标记分区
-        let target_partition = ConfigFileManager::find_install_marker_partition()
-            .unwrap_or_else(|| config.target_partition.clone());
+    use app::WorkerMessage;
 
-        // 构建完整镜像路径
-        let data_dir = ConfigFileManager::get_data_dir(&data_partition);
-        let image_path = format!("{}\\{}", data_dir, config.image_path);
-
-        if !std::path::Path::new(&image_path).exists() {
-            eprintln!("[PE INSTALL] 错误: 镜像文件不存在: {}", image_path);
-            show_error_message(&format!("镜像文件不存在: {}", image_path));
-            return Ok(());
-        }
-
-        println!("[PE INSTALL] 完整镜像路径: {}", image_path);
-
-        // Step 1: 格式化分区
-        println!("[PE INSTALL] Step 1: 格式化分区");
-        if let Err(e) = DiskManager::format_partition(&target_partition) {
-            eprintln!("[PE INSTALL] 格式化失败: {}", e);
-            show_error_message(&format!("格式化分区失败: {}", e));
+    let tag = if is_install { "[PE INSTALL]" } else { "[PE BACKUP]" };
+    let backend = match core::backend::native_backend() {
+        Ok(backend) => backend,
+        Err(e) => {
+            show_error_message(&format!("{:#}", e));
             return Ok(());
         }
+    };
+    let (tx, rx) = channel::<WorkerMessage>();
 
-        // Step 2: 释放镜像
-        println!("[PE INSTALL] Step 2: 释放镜像");
-        let apply_dir = format!("{}\\", target_partition);
-
-        let apply_result = if config.is_gho {
-            let ghost = Ghost::new();
-            if !ghost.is_available() {
-                show_error_message("Ghost工具不可用");
-                return Ok(());
-            }
-            let partitions = DiskManager::get_partitions().unwrap_or_default();
-            ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, None)
-        } else {
-            let dism = Dism::new();
-            dism.apply_image(&image_path, &apply_dir, config.volume_index, None)
-        };
-
-        if let Err(e) = apply_result {
-            eprintln!("[PE INSTALL] 释放镜像失败: {}", e);
-            show_error_message(&format!("释放镜像失败: {}", e));
-            return Ok(());
-        }
-
-        // Step 3: 导入驱动
-        println!("[PE INSTALL] Step 3: 导入驱动");
-        let driver_path = format!("{}\\drivers", data_dir);
-        let driver_path_exists = std::path::Path::new(&driver_path).exists();
-        
-        if config.should_import_drivers() && driver_path_exists {
-            let dism = Dism::new();
-            match dism.add_drivers_offline_with_progress(&apply_dir, &driver_path, None) {
-                Ok(_) => println!("[PE INSTALL] 驱动导入成功"),
-                Err(e) => {
-                    eprintln!("[PE INSTALL] 警告: 驱动导入失败: {} (继续安装)", e);
-                    log::warn!("驱动导入失败: {}", e);
-                }
-            }
-            
-            // 同时检查驱动目录中是否有 CAB 文件并安装
-            let cab_files = find_cab_files_in_dir(&driver_path);
-            if !cab_files.is_empty() {
-                println!("[PE INSTALL] 在驱动目录中发现 {} 个 CAB 文件，一并安装", cab_files.len());
-                match dism.add_packages_offline_from_dir(&apply_dir, &driver_path, None) {
-                    Ok((success, fail)) => {
-                        println!("[PE INSTALL] 驱动目录中的CAB安装完成: {} 成功, {} 失败", success, fail);
-                    }
-                    Err(e) => {
-                        eprintln!("[PE INSTALL] 警告: 驱动目录中的CAB安装失败: {} (继续安装)", e);
-                        log::warn!("驱动目录中的CAB安装失败: {}", e);
-                    }
-                }
-            }
-        } else if config.should_import_drivers() && !driver_path_exists {
-            println!("[PE INSTALL] 驱动目录不存在，跳过驱动导入");
-        } else {
-            println!("[PE INSTALL] 跳过驱动导入");
-        }
-
-        // Step 4: 安装CAB更新包
-        println!("[PE INSTALL] Step 4: 安装CAB更新包");
-        if config.install_cab_packages {
-            let cab_path = format!("{}\\updates", data_dir);
-            if std::path::Path::new(&cab_path).exists() {
-                let dism = Dism::new();
-                match dism.add_packages_offline_from_dir(&apply_dir, &cab_path, None) {
-                    Ok((success, fail)) => {
-                        println!("[PE INSTALL] CAB更新包安装完成: {} 成功, {} 失败", success, fail);
-                    }
-                    Err(e) => {
-                        eprintln!("[PE INSTALL] 警告: CAB更新包安装失败: {} (继续安装)", e);
-                        log::warn!("CAB更新包安装失败: {}", e);
-                    }
-                }
-            } else {
-                println!("[PE INSTALL] 更新包目录不存在，跳过CAB安装");
-            }
-        } else {
-            println!("[PE INSTALL] 跳过CAB更新包安装");
-        }
-
-        // Step 5: 修复引导
-        println!("[PE INSTALL] Step 5: 修复引导");
-        let boot_manager = BootManager::new();
-        let use_uefi = DiskManager::detect_uefi_mode();
-
-        if let Err(e) = boot_manager.repair_boot_advanced(&target_partition, use_uefi) {
-            eprintln!("[PE INSTALL] 修复引导失败: {}", e);
-            show_error_message(&format!("修复引导失败: {}", e));
-            return Ok(());
-        }
-
-        // Step 5.5: 如果启用了 Win7 UEFI 补丁，应用 UefiSeven
-        if use_uefi && config.win7_uefi_patch {
-            println!("[PE INSTALL] Step 5.5: 应用 Win7 UEFI 补丁 (UefiSeven)");
-            match ui::advanced_options::apply_uefiseven_patch(&data_partition, &target_partition) {
-                Ok(_) => println!("[PE INSTALL] UefiSeven 补丁应用成功"),
-                Err(e) => {
-                    // UefiSeven 补丁失败不中断安装，只记录警告
-                    eprintln!("[PE INSTALL] 警告: UefiSeven 补丁应用失败: {} (继续安装)", e);
-                    log::warn!("UefiSeven 补丁应用失败: {}", e);
-                }
-            }
-        }
-
-        // Step 6: 应用高级选项
-        println!("[PE INSTALL] Step 6: 应用高级选项");
-        let _ = apply_advanced_options(&target_partition, &config);
-
-        // Step 7: 生成无人值守配置
-        if config.unattended {
-            println!("[PE INSTALL] Step 7: 生成无人值守配置");
-            let _ = generate_unattend_xml(&target_partition, &config.custom_username);
-        }
-
-        // Step 8: 清理
-        println!("[PE INSTALL] Step 8: 清理临时文件");
-        ConfigFileManager::cleanup_all(&data_partition, &target_partition);
-
-        // Step 9: 清理自动创建的数据分区并扩展目标分区
-        println!("[PE INSTALL] Step 9: 清理自动创建的分区");
-        match DiskManager::cleanup_auto_created_partition_and_extend(&target_partition) {
-            Ok(_) => println!("[PE INSTALL] 自动创建分区清理完成"),
-            Err(e) => {
-                // 不中断安装流程，只记录警告
-                eprintln!("[PE INSTALL] 警告: 清理自动创建分区失败: {}", e);
-                log::warn!("清理自动创建分区失败: {}", e);
-            }
-        }
-
-        println!("[PE INSTALL] 安装完成!");
-
-        if config.auto_reboot {
-            println!("[PE INSTALL] 即将重启...");
-            let _ = utils::command::new_command("shutdown")
-                .args(["/r", "/t", "10", "/c", "LetRecovery 系统安装完成，即将重启..."])
-                .spawn();
-        } else {
-            show_success_message("系统安装完成！请手动重启计算机。");
+    let worker = if is_install {
+        println!("{} ========== PE自动安装模式 ==========", tag);
+        let start_from = app::resume_step(backend);
+        if start_from != ui::progress::InstallStep::FormatPartition {
+            println!("{} 继续上次中断的安装，从「{}」开始", tag, start_from.name());
         }
+        std::thread::spawn(move || app::execute_install_workflow(backend, tx, start_from))
     } else {
-        // 备份模式
-        println!("[PE BACKUP] ========== PE自动备份模式 ==========");
+        println!("{} ========== PE自动备份模式 ==========", tag);
+        std::thread::spawn(move || app::execute_backup_workflow(backend, tx))
+    };
 
-        // 查找配置文件所在分区
-        let data_partition = match ConfigFileManager::find_data_partition() {
-            Some(p) => p,
-            None => {
-                eprintln!("[PE BACKUP] 错误: 未找到备份配置文件");
-                show_error_message("未找到备份配置文件，无法继续备份。");
-                return Ok(());
+    // 流程结束（或重启）时发送端被丢弃，循环随之结束
+    for message in rx {
+        match message {
+            WorkerMessage::SetInstallStep(step) => println!("{} {}", tag, step.name()),
+            WorkerMessage::SetBackupStep(step) => println!("{} {}", tag, step.name()),
+            WorkerMessage::SetStatus(status) => println!("{} {}", tag, status),
+            WorkerMessage::SetProgress(_) => {}
+            WorkerMessage::Completed { message, reboot } => {
+                println!("{} {}", tag, message);
+                if !reboot {
+                    show_success_message(&message);
+                }
             }
-        };
-
-        println!("[PE BACKUP] 数据分区: {}", data_partition);
-
-        // 读取备份配置
-        let config = match ConfigFileManager::read_backup_config(&data_partition) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("[PE BACKUP] 错误: 读取配置失败: {}", e);
-                show_error_message(&format!("读取备份配置失败: {}", e));
-                return Ok(());
+            WorkerMessage::Failed(error) => {
+                eprintln!("{} 错误: {}", tag, error);
+                show_error_message(&error);
             }
-        };
-
-        println!("[PE BACKUP] 源分区: {}", config.source_partition);
-        println!("[PE BACKUP] 保存路径: {}", config.save_path);
-
-        // 查找备份标记分区
-        let source_partition = ConfigFileManager::find_backup_marker_partition()
-            .unwrap_or_else(|| config.source_partition.clone());
-
-        // 执行备份
-        let dism = Dism::new();
-        let capture_dir = format!("{}\\", source_partition);
-
-        let backup_result =
-            if config.incremental && std::path::Path::new(&config.save_path).exists() {
-                dism.append_image(
-                    &config.save_path,
-                    &capture_dir,
-                    &config.name,
-                    &config.description,
-                    None,
-                )
-            } else {
-                dism.capture_image(
-                    &config.save_path,
-                    &capture_dir,
-                    &config.name,
-                    &config.description,
-                    None,
-                )
-            };
-
-        if let Err(e) = backup_result {
-            eprintln!("[PE BACKUP] 备份失败: {}", e);
-            show_error_message(&format!("系统备份失败: {}", e));
-            return Ok(());
         }
-
-        // 删除PE引导项
-        let boot_manager = BootManager::new();
-        let _ = boot_manager.delete_current_boot_entry();
-
-        // 清理
-        ConfigFileManager::cleanup_partition_markers(&source_partition);
-        ConfigFileManager::cleanup_data_dir(&data_partition);
-        ConfigFileManager::cleanup_pe_dir(&data_partition);
-
-        println!("[PE BACKUP] 备份完成!");
-        show_success_message(&format!(
-            "系统备份完成！\n保存位置: {}",
-            config.save_path
-        ));
-
-        // 自动重启
-        let _ = utils::command::new_command("shutdown")
-            .args([
-                "/r",
-                "/t",
-                "10",
-                "/c",
-                "LetRecovery 系统备份完成，即将重启...",
-            ])
-            .spawn();
     }
+    let _ = worker.join();
 
     Ok(())
 }
 
-/// 生成无人值守XML
-fn generate_unattend_xml(target_partition: &str, username: &str) -> anyhow::Result<()> {
-    let username = if username.is_empty() { "User" } else { username };
-
-    let xml_content = format!(
-        r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOEMRegistrationScreen>true</HideOEMRegistrationScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-                <SkipMachineOOBE>true</SkipMachineOOBE>
-                <SkipUserOOBE>true</SkipUserOOBE>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <Username>{}</Username>
-            </AutoLogon>
-        </component>
-    </settings>
-</unattend>"#,
-        username, username, username
-    );
-
-    let panther_dir = format!("{}\\Windows\\Panther", target_partition);
-    std::fs::create_dir_all(&panther_dir)?;
-
-    let unattend_path = format!("{}\\unattend.xml", panther_dir);
-    std::fs::write(&unattend_path, &xml_content)?;
-
-    Ok(())
+/// 显示成功消息框
+fn show_success_message(message: &str) {
+    show_message_box(message, "LetRecovery PE", 0x40); // MB_ICONINFORMATION
 }
 
 /// 显示错误消息框
 fn show_error_message(message: &str) {
-    #[cfg(windows)]
-    {
-        use std::ffi::OsStr;
-        use std::os::windows::ffi::OsStrExt;
-        use std::ptr::null_mut;
-
-        let wide_message: Vec<u16> = OsStr::new(message)
-            .encode_wide()
-            .chain(std::iter::once(0))
-            .collect();
-        let wide_title: Vec<u16> = OsStr::new("LetRecovery PE 错误")
-            .encode_wide()
-            .chain(std::iter::once(0))
-            .collect();
-
-        unsafe {
-            #[link(name = "user32")]
-            extern "system" {
-                fn MessageBoxW(
-                    hwnd: *mut std::ffi::c_void,
-                    text: *const u16,
-                    caption: *const u16,
-                    utype: u32,
-                ) -> i32;
-            }
-            MessageBoxW(
-                null_mut(),
-                wide_message.as_ptr(),
-                wide_title.as_ptr(),
-                0x10,
-            ); // MB_ICONERROR
-        }
-    }
-
-    #[cfg(not(windows))]
-    {
-        eprintln!("错误: {}", message);
-    }
+    show_message_box(message, "LetRecovery PE 错误", 0x10); // MB_ICONERROR
 }
 
-/// 显示成功消息框
-fn show_success_message(message: &str) {
+/// 显示消息框，`icon` 为 MessageBoxW 的图标标志
+fn show_message_box(message: &str, title: &str, icon: u32) {
     #[cfg(windows)]
     {
         use std::ffi::OsStr;
@@ -532,7 +195,7 @@ fn show_success_message(message: &str) {
             .encode_wide()
             .chain(std::iter::once(0))
             .collect();
-        let wide_title: Vec<u16> = OsStr::new("LetRecovery PE")
+        let wide_title: Vec<u16> = OsStr::new(title)
             .encode_wide()
             .chain(std::iter::once(0))
             .collect();
@@ -551,13 +214,14 @@ fn show_success_message(message: &str) {
                 null_mut(),
                 wide_message.as_ptr(),
                 wide_title.as_ptr(),
-                0x40,
-            ); // MB_ICONINFORMATION
+                icon,
+            );
         }
     }
 
     #[cfg(not(windows))]
     {
-        println!("成功: {}", message);
+        let _ = icon;
+        eprintln!("{}: {}", title, message);
     }
 }
@@ -11,6 +11,7 @@ const SCRIPTS_DIR: &str = "LetRecovery_Scripts";
 /// 
 /// 此函数在PE环境中执行，负责将用户选择的高级选项应用到目标系统。
 /// 通过离线修改注册表和生成必要的脚本来实现各项功能。
+#[cfg(windows)]
 pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) -> anyhow::Result<()> {
     let windows_path = format!("{}\\Windows", target_partition);
     let software_hive = format!("{}\\System32\\config\\SOFTWARE", windows_path);
@@ -590,6 +591,7 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
 /// # 参数
 /// - `nvme_dir`: NVMe驱动目录
 /// - `target_partition`: 目标分区（如 "D:"）
+#[cfg(windows)]
 fn install_win7_nvme_drivers(nvme_dir: &Path, target_partition: &str) -> anyhow::Result<()> {
     // CabinetExtractor 已通过其他函数间接使用，无需直接导入
     
@@ -728,6 +730,7 @@ enum CabType {
 }
 
 /// 检测CAB文件类型
+#[cfg(windows)]
 fn detect_cab_type(cab_path: &Path) -> CabType {
     use crate::core::cabinet::CabinetExtractor;
     
@@ -804,6 +807,7 @@ fn detect_cab_type(cab_path: &Path) -> CabType {
 }
 
 /// 将CAB作为驱动包安装（解压后导入INF）
+#[cfg(windows)]
 fn install_cab_as_driver(cab_path: &Path, target_partition: &str) -> anyhow::Result<()> {
     use crate::core::cabinet::CabinetExtractor;
     
@@ -831,6 +835,7 @@ fn install_cab_as_driver(cab_path: &Path, target_partition: &str) -> anyhow::Res
 }
 
 /// 处理嵌套的CAB文件
+#[cfg(windows)]
 fn process_nested_cabs_for_drivers(dir: &Path) -> anyhow::Result<()> {
     use crate::core::cabinet::CabinetExtractor;
     
@@ -876,6 +881,7 @@ fn find_nested_cabs(dir: &Path, cabs: &mut Vec<PathBuf>) {
 }
 
 /// 备用方法：直接复制驱动文件
+#[cfg(windows)]
 fn install_cab_as_driver_fallback(cab_path: &Path, target_partition: &str) -> anyhow::Result<()> {
     use crate::core::cabinet::CabinetExtractor;
     
@@ -1025,6 +1031,7 @@ fn register_nvme_driver_services(target_partition: &str) -> anyhow::Result<()> {
 /// 
 /// 如果目录中包含 .cab 文件，会将其解压到临时目录。
 /// 支持 Windows 更新包格式（如 KB2990941、KB3087873）。
+#[cfg(windows)]
 fn prepare_win7_drivers(driver_dir: &PathBuf) -> anyhow::Result<PathBuf> {
     use crate::core::cabinet::CabinetExtractor;
     
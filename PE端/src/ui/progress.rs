@@ -1,7 +1,9 @@
 use egui::{Color32, RichText};
+use serde::{Deserialize, Serialize};
 
 /// 安装/备份步骤
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InstallStep {
     FormatPartition,
     ApplyImage,
@@ -138,6 +140,8 @@ pub struct ProgressState {
     pub status_message: String,
     /// 是否已完成
     pub is_completed: bool,
+    /// 完成提示
+    pub completion_message: String,
     /// 是否失败
     pub is_failed: bool,
     /// 错误信息
@@ -154,6 +158,7 @@ impl Default for ProgressState {
             overall_progress: 0,
             status_message: String::new(),
             is_completed: false,
+            completion_message: String::new(),
             is_failed: false,
             error_message: None,
         }
@@ -213,8 +218,9 @@ impl ProgressState {
     }
 
     /// 标记完成
-    pub fn mark_completed(&mut self) {
+    pub fn mark_completed(&mut self, message: String) {
         self.is_completed = true;
+        self.completion_message = message;
         self.overall_progress = 100;
         self.step_progress = 100;
         if self.is_install_mode {
@@ -325,13 +331,8 @@ impl ProgressUI {
             // 完成提示
             if state.is_completed {
                 ui.add_space(30.0);
-                let message = if state.is_install_mode {
-                    "系统安装完成！即将重启..."
-                } else {
-                    "系统备份完成！即将重启..."
-                };
                 ui.label(
-                    RichText::new(message)
+                    RichText::new(&state.completion_message)
                         .size(18.0)
                         .color(Color32::from_rgb(100, 255, 100))
                         .strong(),
@@ -340,6 +341,64 @@ impl ProgressUI {
         });
     }
 
+    /// 绘制继续中断安装的提示
+    ///
+    /// 返回 `Some(true)` 表示从 `step` 继续，`Some(false)` 表示重新开始。
+    pub fn show_resume_prompt(
+        ui: &mut egui::Ui,
+        step: InstallStep,
+        last_error: Option<&str>,
+        remaining_secs: u64,
+    ) -> Option<bool> {
+        let mut choice = None;
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading(RichText::new("LetRecovery PE 安装助手").size(24.0).strong());
+
+            ui.add_space(30.0);
+            ui.label(
+                RichText::new("检测到上次安装未完成")
+                    .size(18.0)
+                    .color(Color32::from_rgb(255, 180, 50)),
+            );
+
+            ui.add_space(20.0);
+            for item in InstallStep::all() {
+                let status = if item.index() < step.index() {
+                    StepStatus::Completed
+                } else {
+                    StepStatus::Pending
+                };
+                Self::show_step_item(ui, item.name(), status);
+            }
+
+            if let Some(error) = last_error {
+                ui.add_space(10.0);
+                ui.label(
+                    RichText::new(format!("上次错误: {}", error))
+                        .size(14.0)
+                        .color(Color32::from_rgb(255, 100, 100)),
+                );
+            }
+
+            ui.add_space(20.0);
+            if ui
+                .button(RichText::new(format!("从「{}」继续 ({} 秒后自动继续)", step.name(), remaining_secs)).size(16.0))
+                .clicked()
+            {
+                choice = Some(true);
+            }
+            ui.add_space(10.0);
+            if ui
+                .button(RichText::new("重新开始（重新格式化目标分区）").size(14.0))
+                .clicked()
+            {
+                choice = Some(false);
+            }
+        });
+        choice
+    }
+
     /// 显示安装步骤列表
     fn show_install_steps(ui: &mut egui::Ui, state: &ProgressState) {
         let current_idx = state.current_install_step.index();
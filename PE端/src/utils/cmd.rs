@@ -7,6 +7,7 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 /// 创建一个配置好的 Command，在 Windows 上隐藏控制台窗口
 pub fn create_command<S: AsRef<OsStr>>(program: S) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
     let mut cmd = Command::new(program);
 
     #[cfg(windows)]
@@ -16,6 +16,7 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 /// 在 Windows 上设置 CREATE_NO_WINDOW 标志以防止弹出控制台窗口
 /// 在其他平台上返回普通的 Command
 pub fn new_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
     let mut cmd = Command::new(program);
 
     #[cfg(windows)]
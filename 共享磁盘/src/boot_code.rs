@@ -0,0 +1,784 @@
+//! BIOS 引导代码写入模块
+//!
+//! 取代 bootsect.exe：写入与 NT6 MBR 行为一致的主引导代码（保留分区表和磁盘签名）、
+//! 设置活动分区，并把 NTFS/FAT32 卷引导记录的引导程序更新为加载 BOOTMGR。
+//! 所有修改先生成扇区变更计划，可输出修改前后的十六进制差异，并可直接作用于磁盘镜像文件。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::util::get_bin_dir;
+
+/// MBR 引导代码（加载到 0x7C00 后自身搬移到 0x600 执行）
+///
+/// 行为与 NT6 MBR 相同：
+/// - 查找分区表中标记为活动（0x80）的主分区
+/// - 优先使用 INT 13h 扩展（LBA）读取其第一个扇区到 0x7C00，不支持时回退到 CHS
+/// - 校验 0x55AA 签名后以 DL=驱动器号、DS:SI=分区表项跳转到 0000:7C00
+/// - 出错时显示 "Invalid partition table" / "Error loading operating system" /
+///   "Missing operating system" 并停机
+pub const MBR_BOOT_CODE: &[u8] = &[
+    0xFA, 0x31, 0xC0, 0x8E, 0xD0, 0xBC, 0x00, 0x7C, 0x8E, 0xD8, 0x8E, 0xC0, 0xFB, 0xFC, 0xBE, 0x00,
+    0x7C, 0xBF, 0x00, 0x06, 0xB9, 0x00, 0x01, 0xF3, 0xA5, 0xEA, 0x1E, 0x06, 0x00, 0x00, 0x88, 0x16,
+    0xB1, 0x06, 0xBE, 0xBE, 0x07, 0xB9, 0x04, 0x00, 0x80, 0x3C, 0x80, 0x74, 0x0A, 0x83, 0xC6, 0x10,
+    0xE2, 0xF6, 0xBE, 0xB2, 0x06, 0xEB, 0x69, 0x89, 0xF5, 0xB4, 0x41, 0xBB, 0xAA, 0x55, 0x8A, 0x16,
+    0xB1, 0x06, 0xCD, 0x13, 0x72, 0x2B, 0x81, 0xFB, 0x55, 0xAA, 0x75, 0x25, 0xF6, 0xC1, 0x01, 0x74,
+    0x20, 0x66, 0x6A, 0x00, 0x66, 0xFF, 0x76, 0x08, 0x1E, 0x68, 0x00, 0x7C, 0x6A, 0x01, 0x6A, 0x10,
+    0x89, 0xE6, 0xB4, 0x42, 0x8A, 0x16, 0xB1, 0x06, 0xCD, 0x13, 0xBC, 0x00, 0x7C, 0x72, 0x29, 0xEB,
+    0x14, 0xB8, 0x01, 0x02, 0xBB, 0x00, 0x7C, 0x8B, 0x4E, 0x02, 0x8A, 0x76, 0x01, 0x8A, 0x16, 0xB1,
+    0x06, 0xCD, 0x13, 0x72, 0x13, 0x81, 0x3E, 0xFE, 0x7D, 0x55, 0xAA, 0x75, 0x10, 0x89, 0xEE, 0x8A,
+    0x16, 0xB1, 0x06, 0xEA, 0x00, 0x7C, 0x00, 0x00, 0xBE, 0xCA, 0x06, 0xEB, 0x03, 0xBE, 0xE9, 0x06,
+    0xAC, 0x84, 0xC0, 0x74, 0x09, 0xB4, 0x0E, 0xBB, 0x07, 0x00, 0xCD, 0x10, 0xEB, 0xF2, 0xF4, 0xEB,
+    0xFD, 0x80, 0x49, 0x6E, 0x76, 0x61, 0x6C, 0x69, 0x64, 0x20, 0x70, 0x61, 0x72, 0x74, 0x69, 0x74,
+    0x69, 0x6F, 0x6E, 0x20, 0x74, 0x61, 0x62, 0x6C, 0x65, 0x00, 0x45, 0x72, 0x72, 0x6F, 0x72, 0x20,
+    0x6C, 0x6F, 0x61, 0x64, 0x69, 0x6E, 0x67, 0x20, 0x6F, 0x70, 0x65, 0x72, 0x61, 0x74, 0x69, 0x6E,
+    0x67, 0x20, 0x73, 0x79, 0x73, 0x74, 0x65, 0x6D, 0x00, 0x4D, 0x69, 0x73, 0x73, 0x69, 0x6E, 0x67,
+    0x20, 0x6F, 0x70, 0x65, 0x72, 0x61, 0x74, 0x69, 0x6E, 0x67, 0x20, 0x73, 0x79, 0x73, 0x74, 0x65,
+    0x6D, 0x00,
+];
+
+/// MBR 中引导代码区的长度（其后是磁盘签名）
+const MBR_CODE_SIZE: usize = 440;
+/// MBR 分区表偏移
+const PARTITION_TABLE_OFFSET: usize = 446;
+/// NTFS 引导区（$Boot 前 16 个 512 字节扇区）长度
+const NTFS_BOOT_REGION: usize = 8192;
+/// FAT32 NT6 引导程序使用的第二个代码扇区
+const FAT32_CODE_SECTOR: usize = 12;
+
+/// 卷引导记录的文件系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VbrFileSystem {
+    Ntfs,
+    Fat32,
+}
+
+impl VbrFileSystem {
+    /// 根据卷的第一个扇区识别文件系统
+    pub fn detect(boot_sector: &[u8]) -> Option<Self> {
+        if boot_sector.len() < 512 || boot_sector[510..512] != [0x55, 0xAA] {
+            return None;
+        }
+        if &boot_sector[3..11] == b"NTFS    " {
+            Some(VbrFileSystem::Ntfs)
+        } else if &boot_sector[0x52..0x5A] == b"FAT32   " {
+            Some(VbrFileSystem::Fat32)
+        } else {
+            None
+        }
+    }
+
+    /// 引导区总长度（字节）
+    fn boot_region_len(self, bytes_per_sector: usize) -> usize {
+        match self {
+            VbrFileSystem::Ntfs => NTFS_BOOT_REGION.max(bytes_per_sector),
+            VbrFileSystem::Fat32 => (FAT32_CODE_SECTOR + 1) * bytes_per_sector,
+        }
+    }
+
+    /// 引导区中属于引导程序的字节范围，其余部分（BPB、FSInfo 等）属于卷自身
+    fn code_ranges(self, bytes_per_sector: usize) -> Vec<Range<usize>> {
+        match self {
+            VbrFileSystem::Ntfs => vec![0..3, 0x54..0x1FE, 0x200..NTFS_BOOT_REGION],
+            VbrFileSystem::Fat32 => vec![
+                0..3,
+                0x5A..0x1FE,
+                FAT32_CODE_SECTOR * bytes_per_sector..(FAT32_CODE_SECTOR + 1) * bytes_per_sector,
+            ],
+        }
+    }
+
+    /// `bin\boot` 目录下的引导代码模板文件名
+    fn template_file_name(self) -> &'static str {
+        match self {
+            VbrFileSystem::Ntfs => "ntfs_bootmgr.bin",
+            VbrFileSystem::Fat32 => "fat32_bootmgr.bin",
+        }
+    }
+}
+
+impl fmt::Display for VbrFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VbrFileSystem::Ntfs => write!(f, "NTFS"),
+            VbrFileSystem::Fat32 => write!(f, "FAT32"),
+        }
+    }
+}
+
+/// 卷引导记录加载的引导程序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootLoaderKind {
+    /// Windows Vista 及以后的 BOOTMGR
+    Bootmgr,
+    /// Windows XP 的 NTLDR
+    Ntldr,
+    /// 其他引导程序（GRUB、Syslinux 等）
+    Unknown,
+    /// 没有引导代码
+    Empty,
+}
+
+impl fmt::Display for BootLoaderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootLoaderKind::Bootmgr => write!(f, "BOOTMGR"),
+            BootLoaderKind::Ntldr => write!(f, "NTLDR"),
+            BootLoaderKind::Unknown => write!(f, "未知引导程序"),
+            BootLoaderKind::Empty => write!(f, "无引导代码"),
+        }
+    }
+}
+
+/// 根据引导区内容判断加载的引导程序
+pub fn detect_loader(file_system: VbrFileSystem, boot_region: &[u8]) -> BootLoaderKind {
+    let bytes_per_sector = read_u16(boot_region, 11).max(512) as usize;
+    let code: Vec<u8> = file_system
+        .code_ranges(bytes_per_sector)
+        .into_iter()
+        .skip(1)
+        .filter(|r| r.end <= boot_region.len())
+        .flat_map(|r| boot_region[r].to_vec())
+        .collect();
+
+    if code.iter().all(|&b| b == 0) {
+        BootLoaderKind::Empty
+    } else if contains(&code, b"BOOTMGR") || contains(&code, &utf16(b"BOOTMGR")) {
+        BootLoaderKind::Bootmgr
+    } else if contains(&code, b"NTLDR") || contains(&code, &utf16(b"NTLDR")) {
+        BootLoaderKind::Ntldr
+    } else {
+        BootLoaderKind::Unknown
+    }
+}
+
+/// 加载 BOOTMGR 的卷引导代码模板
+///
+/// 模板是某个卷引导区的原始内容，只取其中的引导程序部分，BPB 等卷参数保持目标卷原样
+#[derive(Debug, Clone)]
+pub struct VbrTemplate {
+    pub file_system: VbrFileSystem,
+    /// 模板来源（文件路径或卷）
+    pub source: String,
+    data: Vec<u8>,
+}
+
+impl VbrTemplate {
+    /// 从引导区原始数据创建模板
+    pub fn from_bytes(data: Vec<u8>, source: &str) -> Result<Self> {
+        let file_system = VbrFileSystem::detect(&data)
+            .with_context(|| format!("{} 不是 NTFS/FAT32 卷引导记录", source))?;
+        let bytes_per_sector = read_u16(&data, 11) as usize;
+        if data.len() < file_system.boot_region_len(bytes_per_sector) {
+            bail!("{} 的引导区数据不完整", source);
+        }
+        if detect_loader(file_system, &data) != BootLoaderKind::Bootmgr {
+            bail!("{} 的引导代码不加载 BOOTMGR", source);
+        }
+        Ok(Self {
+            file_system,
+            source: source.to_string(),
+            data,
+        })
+    }
+
+    /// 从模板文件加载
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("读取 {} 失败", path.display()))?;
+        Self::from_bytes(data, &path.display().to_string())
+    }
+
+    /// 把模板的引导程序套用到目标引导区，返回新的引导区内容
+    fn apply(&self, target: &[u8], bytes_per_sector: usize) -> Result<Vec<u8>> {
+        if read_u16(&self.data, 11) as usize != bytes_per_sector && self.file_system == VbrFileSystem::Fat32 {
+            bail!("模板 {} 的扇区大小与目标卷不一致", self.source);
+        }
+        let mut result = target.to_vec();
+        for range in self.file_system.code_ranges(bytes_per_sector) {
+            if range.end > result.len() || range.end > self.data.len() {
+                bail!("模板 {} 的引导区数据不完整", self.source);
+            }
+            result[range.clone()].copy_from_slice(&self.data[range]);
+        }
+        Ok(result)
+    }
+}
+
+/// 查找可用的 BOOTMGR 引导代码模板
+///
+/// 依次尝试 `bin\boot\<fs>_bootmgr.bin` 和本机其他已加载 BOOTMGR 的同类型卷
+pub fn find_template(file_system: VbrFileSystem, exclude_letter: Option<char>) -> Option<VbrTemplate> {
+    let template_path: PathBuf = get_bin_dir().join("boot").join(file_system.template_file_name());
+    if let Ok(template) = VbrTemplate::load(&template_path) {
+        if template.file_system == file_system {
+            return Some(template);
+        }
+    }
+
+    for letter in 'C'..='Z' {
+        if Some(letter) == exclude_letter.map(|c| c.to_ascii_uppercase()) {
+            continue;
+        }
+        let Ok(mut volume) = std::fs::File::open(volume_path(letter)) else {
+            continue;
+        };
+        let Ok(region) = read_boot_region(&mut volume, 0) else {
+            continue;
+        };
+        if let Ok(template) = VbrTemplate::from_bytes(region, &format!("{}:", letter)) {
+            if template.file_system == file_system {
+                return Some(template);
+            }
+        }
+    }
+    None
+}
+
+/// 单个（或连续多个）扇区的修改
+#[derive(Debug, Clone)]
+pub struct SectorChange {
+    /// 起始 LBA（相对磁盘）
+    pub lba: u64,
+    pub description: String,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+    /// 写入失败时只记录警告（如位于卷末尾的 NTFS 备份引导扇区）
+    pub best_effort: bool,
+}
+
+impl SectorChange {
+    /// 发生变化的字节数
+    pub fn changed_bytes(&self) -> usize {
+        self.before.iter().zip(&self.after).filter(|(a, b)| a != b).count()
+    }
+
+    /// 以 16 字节为一行输出修改前后的十六进制差异，只列出有变化的行
+    pub fn diff(&self, max_rows: usize) -> String {
+        let mut lines = vec![format!(
+            "LBA {} ({}): {} 字节变化",
+            self.lba,
+            self.description,
+            self.changed_bytes()
+        )];
+        let rows: Vec<usize> = (0..self.after.len())
+            .step_by(16)
+            .filter(|&off| self.before[off..off + 16] != self.after[off..off + 16])
+            .collect();
+        for &off in rows.iter().take(max_rows) {
+            lines.push(format!("  {:04X}  - {}", off, hex(&self.before[off..off + 16])));
+            lines.push(format!("        + {}", hex(&self.after[off..off + 16])));
+        }
+        if rows.len() > max_rows {
+            lines.push(format!("  ... 另有 {} 行变化", rows.len() - max_rows));
+        }
+        lines.join("\n")
+    }
+}
+
+/// BIOS 引导代码写入计划
+#[derive(Debug, Clone)]
+pub struct BootCodePlan {
+    pub sector_size: u64,
+    /// 目标分区在 MBR 分区表中的索引（0-3）
+    pub partition_index: usize,
+    /// 目标分区起始 LBA
+    pub partition_start: u64,
+    pub file_system: VbrFileSystem,
+    /// 修改前卷引导记录加载的引导程序
+    pub loader_before: BootLoaderKind,
+    pub changes: Vec<SectorChange>,
+    pub notes: Vec<String>,
+}
+
+impl BootCodePlan {
+    /// 输出全部扇区差异
+    pub fn render_diff(&self) -> String {
+        if self.changes.is_empty() {
+            return "引导扇区无需修改".to_string();
+        }
+        self.changes
+            .iter()
+            .map(|c| c.diff(16))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 写入磁盘镜像或已打开的磁盘设备
+    pub fn apply<D: Write + Seek>(&self, dev: &mut D) -> Result<()> {
+        for change in &self.changes {
+            dev.seek(SeekFrom::Start(change.lba * self.sector_size))?;
+            dev.write_all(&change.after)
+                .with_context(|| format!("写入 LBA {} ({}) 失败", change.lba, change.description))?;
+        }
+        dev.flush()?;
+        Ok(())
+    }
+}
+
+/// 生成写入计划：MBR 引导代码、活动分区标记和卷引导记录
+///
+/// `template` 为空时，若卷引导记录已加载 BOOTMGR 则保持不变，否则报错
+pub fn plan_bios_boot<D: Read + Seek>(
+    dev: &mut D,
+    sector_size: u64,
+    partition_index: usize,
+    template: Option<&VbrTemplate>,
+) -> Result<BootCodePlan> {
+    let ss = sector_size as usize;
+    let mbr = read_at(dev, 0, ss)?;
+    if mbr[510..512] != [0x55, 0xAA] {
+        bail!("磁盘没有有效的 MBR 分区表");
+    }
+    let entries: Vec<(u8, u64, u64)> = (0..4)
+        .map(|i| {
+            let off = PARTITION_TABLE_OFFSET + i * 16;
+            (mbr[off + 4], read_u32(&mbr, off + 8) as u64, read_u32(&mbr, off + 12) as u64)
+        })
+        .collect();
+    if entries.iter().any(|&(kind, _, _)| kind == 0xEE) {
+        bail!("GPT 磁盘不使用 BIOS 引导代码");
+    }
+    let Some(&(kind, partition_start, sector_count)) = entries.get(partition_index) else {
+        bail!("分区索引 {} 超出 MBR 主分区范围", partition_index);
+    };
+    if kind == 0 || sector_count == 0 {
+        bail!("MBR 分区表第 {} 项为空", partition_index + 1);
+    }
+    if matches!(kind, 0x05 | 0x0F | 0x85) {
+        bail!("扩展分区不能设为活动分区");
+    }
+
+    let mut changes = Vec::new();
+    let mut notes = Vec::new();
+
+    // MBR：只替换引导代码和活动标记，磁盘签名与分区表保持不变
+    let mut new_mbr = mbr.clone();
+    new_mbr[..MBR_CODE_SIZE].fill(0);
+    new_mbr[..MBR_BOOT_CODE.len()].copy_from_slice(MBR_BOOT_CODE);
+    for i in 0..4 {
+        new_mbr[PARTITION_TABLE_OFFSET + i * 16] = if i == partition_index { 0x80 } else { 0x00 };
+    }
+    if new_mbr != mbr {
+        changes.push(SectorChange {
+            lba: 0,
+            description: "MBR 引导代码与活动分区".to_string(),
+            before: mbr,
+            after: new_mbr,
+            best_effort: false,
+        });
+    }
+
+    // 卷引导记录
+    let boot_sector = read_at(dev, partition_start * sector_size, ss)?;
+    let file_system = VbrFileSystem::detect(&boot_sector)
+        .context("目标分区不是 NTFS/FAT32 卷，无法写入 BOOTMGR 引导代码")?;
+    let bytes_per_sector = read_u16(&boot_sector, 11) as usize;
+    if bytes_per_sector != ss {
+        bail!("卷扇区大小 {} 与磁盘扇区大小 {} 不一致", bytes_per_sector, ss);
+    }
+    let region_len = file_system.boot_region_len(ss);
+    if file_system == VbrFileSystem::Fat32 && (read_u16(&boot_sector, 0x0E) as usize) <= FAT32_CODE_SECTOR {
+        bail!("FAT32 保留扇区不足，无法容纳 BOOTMGR 引导代码");
+    }
+    let region = read_at(dev, partition_start * sector_size, region_len)?;
+    let loader_before = detect_loader(file_system, &region);
+
+    match template {
+        Some(template) => {
+            if template.file_system != file_system {
+                bail!("模板 {} 是 {}，目标卷是 {}", template.source, template.file_system, file_system);
+            }
+            let new_region = template.apply(&region, ss)?;
+            if new_region != region {
+                notes.push(format!(
+                    "卷引导记录: {} -> BOOTMGR（模板来自 {}）",
+                    loader_before, template.source
+                ));
+                changes.push(SectorChange {
+                    lba: partition_start,
+                    description: format!("{} 卷引导记录", file_system),
+                    before: region,
+                    after: new_region.clone(),
+                    best_effort: false,
+                });
+
+                // 同步更新备份引导扇区
+                let backup_lba = match file_system {
+                    VbrFileSystem::Ntfs => partition_start + read_u64(&boot_sector, 0x28),
+                    VbrFileSystem::Fat32 => partition_start + read_u16(&boot_sector, 0x32) as u64,
+                };
+                if backup_lba > partition_start && backup_lba < partition_start + sector_count {
+                    let backup = read_at(dev, backup_lba * sector_size, ss)?;
+                    if VbrFileSystem::detect(&backup) == Some(file_system) && backup[..] != new_region[..ss] {
+                        changes.push(SectorChange {
+                            lba: backup_lba,
+                            description: format!("{} 备份引导扇区", file_system),
+                            before: backup,
+                            after: new_region[..ss].to_vec(),
+                            best_effort: file_system == VbrFileSystem::Ntfs,
+                        });
+                    }
+                }
+            }
+        }
+        None => {
+            if loader_before != BootLoaderKind::Bootmgr {
+                bail!(
+                    "卷引导记录当前为 {}，且没有可用的 {} BOOTMGR 引导代码模板（可放置于 bin\\boot\\{}）",
+                    loader_before,
+                    file_system,
+                    file_system.template_file_name()
+                );
+            }
+            notes.push("卷引导记录已加载 BOOTMGR，保持不变".to_string());
+        }
+    }
+
+    Ok(BootCodePlan {
+        sector_size,
+        partition_index,
+        partition_start,
+        file_system,
+        loader_before,
+        changes,
+        notes,
+    })
+}
+
+/// 在磁盘的 MBR 主分区中查找起始扇区与给定卷引导扇区相同的分区，返回 (分区索引, 起始 LBA)
+pub fn locate_volume<D: Read + Seek>(
+    dev: &mut D,
+    sector_size: u64,
+    boot_sector: &[u8],
+) -> Result<Option<(usize, u64)>> {
+    let ss = sector_size as usize;
+    let mbr = read_at(dev, 0, ss)?;
+    if mbr[510..512] != [0x55, 0xAA] {
+        return Ok(None);
+    }
+    for i in 0..4 {
+        let off = PARTITION_TABLE_OFFSET + i * 16;
+        let start = read_u32(&mbr, off + 8) as u64;
+        if mbr[off + 4] == 0 || mbr[off + 4] == 0xEE || start == 0 {
+            continue;
+        }
+        if let Ok(sector) = read_at(dev, start * sector_size, ss) {
+            if sector[..] == boot_sector[..ss.min(boot_sector.len())] {
+                return Ok(Some((i, start)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// BIOS 引导代码写入结果
+#[derive(Debug, Clone)]
+pub struct BiosBootReport {
+    pub disk_number: u32,
+    /// 活动分区在 MBR 分区表中的索引（0-3）
+    pub partition_index: usize,
+    pub file_system: VbrFileSystem,
+    pub loader_before: BootLoaderKind,
+    /// 修改前后的扇区差异
+    pub diff: String,
+    pub notes: Vec<String>,
+}
+
+/// 为指定 Windows 分区（如 "C:"）写入 BIOS 引导代码
+///
+/// MBR 通过 `\\.\PhysicalDriveN` 写入，卷引导记录通过卷句柄写入（Windows 允许写入已挂载卷的引导扇区）
+pub fn write_bios_boot_code(windows_partition: &str) -> Result<BiosBootReport> {
+    let letter = windows_partition
+        .chars()
+        .next()
+        .filter(|c| c.is_ascii_alphabetic())
+        .context("无效的分区盘符")?
+        .to_ascii_uppercase();
+
+    let mut volume = std::fs::File::open(volume_path(letter))
+        .with_context(|| format!("打开卷 {}: 失败", letter))?;
+    let region = read_boot_region(&mut volume, 0)?;
+    let file_system = VbrFileSystem::detect(&region)
+        .with_context(|| format!("{}: 不是 NTFS/FAT32 卷", letter))?;
+    let sector_size = read_u16(&region, 11) as u64;
+    let boot_sector = region[..sector_size as usize].to_vec();
+
+    // 通过比对卷引导扇区确定所在磁盘和分区
+    let mut matches = Vec::new();
+    for disk_number in 0..64u32 {
+        let Ok(mut disk) = std::fs::File::open(physical_drive_path(disk_number)) else {
+            continue;
+        };
+        if let Ok(Some((index, start))) = locate_volume(&mut disk, sector_size, &boot_sector) {
+            matches.push((disk_number, index, start));
+        }
+    }
+    let (disk_number, partition_index, partition_start) = match matches.as_slice() {
+        [] => bail!("{}: 不是 MBR 磁盘上的主分区", letter),
+        [single] => *single,
+        _ => bail!("有多个分区与 {}: 的引导扇区相同，无法确定目标磁盘", letter),
+    };
+
+    let template = find_template(file_system, Some(letter));
+    let plan = {
+        let mut disk = std::fs::File::open(physical_drive_path(disk_number))?;
+        plan_bios_boot(&mut disk, sector_size, partition_index, template.as_ref())?
+    };
+
+    let mut notes = plan.notes.clone();
+    if !plan.changes.is_empty() {
+        let mut disk = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(physical_drive_path(disk_number))
+            .with_context(|| format!("以写入方式打开磁盘 {} 失败", disk_number))?;
+        let mut volume = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(volume_path(letter))
+            .with_context(|| format!("以写入方式打开卷 {}: 失败", letter))?;
+
+        for change in &plan.changes {
+            let result = if change.lba >= partition_start {
+                write_at(&mut volume, (change.lba - partition_start) * sector_size, &change.after)
+            } else {
+                write_at(&mut disk, change.lba * sector_size, &change.after)
+            };
+            match result {
+                Ok(()) => {}
+                Err(e) if change.best_effort => notes.push(format!("未能更新{}: {}", change.description, e)),
+                Err(e) => return Err(e).with_context(|| format!("写入{}失败", change.description)),
+            }
+        }
+    }
+
+    Ok(BiosBootReport {
+        disk_number,
+        partition_index,
+        file_system,
+        loader_before: plan.loader_before,
+        diff: plan.render_diff(),
+        notes,
+    })
+}
+
+fn physical_drive_path(disk_number: u32) -> String {
+    format!(r"\\.\PhysicalDrive{}", disk_number)
+}
+
+fn volume_path(letter: char) -> String {
+    format!(r"\\.\{}:", letter)
+}
+
+/// 读取卷开头的完整引导区（长度由文件系统和扇区大小决定）
+fn read_boot_region<D: Read + Seek>(dev: &mut D, offset: u64) -> Result<Vec<u8>> {
+    // 设备读取必须按扇区对齐，4096 同时是 512 和 4K 扇区的整数倍
+    let mut head = vec![0u8; 4096];
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.read_exact(&mut head)?;
+    let file_system = VbrFileSystem::detect(&head).context("不是 NTFS/FAT32 卷引导记录")?;
+    let len = file_system.boot_region_len(read_u16(&head, 11) as usize);
+    let mut region = vec![0u8; len.max(4096)];
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.read_exact(&mut region)?;
+    region.truncate(len);
+    Ok(region)
+}
+
+fn read_at<D: Read + Seek>(dev: &mut D, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.read_exact(&mut buf)
+        .with_context(|| format!("读取偏移 {} 处 {} 字节失败", offset, len))?;
+    Ok(buf)
+}
+
+fn write_at<D: Write + Seek>(dev: &mut D, offset: u64, data: &[u8]) -> Result<()> {
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.write_all(data)?;
+    dev.flush()?;
+    Ok(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .unwrap_or(0)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn utf16(ascii: &[u8]) -> Vec<u8> {
+    ascii.iter().flat_map(|&b| [b, 0]).collect()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SS: u64 = 512;
+
+    fn temp_disk(name: &str, sectors: u64) -> (PathBuf, std::fs::File) {
+        let dir = std::env::temp_dir().join(format!("letrecovery_bootcode_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join(name);
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(sectors * SS).unwrap();
+        (path, file)
+    }
+
+    /// 写入带签名和两个主分区的 MBR，第二个分区为活动分区
+    fn write_mbr(file: &mut std::fs::File, parts: &[(u8, u32, u32)]) -> Vec<u8> {
+        let mut mbr = vec![0u8; 512];
+        mbr[..4].copy_from_slice(b"OLD!");
+        mbr[440..444].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+        for (i, &(kind, start, count)) in parts.iter().enumerate() {
+            let off = PARTITION_TABLE_OFFSET + i * 16;
+            mbr[off] = if i == 1 { 0x80 } else { 0 };
+            mbr[off + 4] = kind;
+            mbr[off + 8..off + 12].copy_from_slice(&start.to_le_bytes());
+            mbr[off + 12..off + 16].copy_from_slice(&count.to_le_bytes());
+        }
+        mbr[510..512].copy_from_slice(&[0x55, 0xAA]);
+        write_at(file, 0, &mbr).unwrap();
+        mbr
+    }
+
+    fn ntfs_region(total_sectors: u64, loader: &[u8], serial: u64) -> Vec<u8> {
+        let mut region = vec![0u8; NTFS_BOOT_REGION];
+        region[..3].copy_from_slice(&[0xEB, 0x52, 0x90]);
+        region[3..11].copy_from_slice(b"NTFS    ");
+        region[11..13].copy_from_slice(&512u16.to_le_bytes());
+        region[0x28..0x30].copy_from_slice(&total_sectors.to_le_bytes());
+        region[0x48..0x50].copy_from_slice(&serial.to_le_bytes());
+        region[0x100..0x100 + loader.len()].copy_from_slice(loader);
+        region[510..512].copy_from_slice(&[0x55, 0xAA]);
+        region[0x400..0x400 + loader.len() * 2].copy_from_slice(&utf16(loader));
+        region
+    }
+
+    fn fat32_region(loader: &[u8]) -> Vec<u8> {
+        let mut region = vec![0u8; 13 * 512];
+        region[..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+        region[11..13].copy_from_slice(&512u16.to_le_bytes());
+        region[0x0E..0x10].copy_from_slice(&32u16.to_le_bytes());
+        region[0x32..0x34].copy_from_slice(&6u16.to_le_bytes());
+        region[0x43..0x47].copy_from_slice(&0xCAFE_BABEu32.to_le_bytes());
+        region[0x52..0x5A].copy_from_slice(b"FAT32   ");
+        region[0x100..0x100 + loader.len()].copy_from_slice(loader);
+        region[510..512].copy_from_slice(&[0x55, 0xAA]);
+        // FSInfo
+        region[512..516].copy_from_slice(b"RRaA");
+        region[12 * 512..12 * 512 + 8].copy_from_slice(b"STAGE2\0\0");
+        region
+    }
+
+    #[test]
+    fn test_ntfs_plan_keeps_table_and_bpb() {
+        let (path, mut file) = temp_disk("ntfs.img", 16384);
+        let old_mbr = write_mbr(&mut file, &[(0x07, 2048, 4096), (0x07, 6144, 4096)]);
+        let old_region = ntfs_region(4095, b"NTLDR is missing", 0xAABB);
+        write_at(&mut file, 2048 * SS, &old_region).unwrap();
+        write_at(&mut file, (2048 + 4095) * SS, &old_region[..512]).unwrap();
+
+        let template = VbrTemplate::from_bytes(ntfs_region(9999, b"BOOTMGR is missing", 0x1111), "test").unwrap();
+        let plan = plan_bios_boot(&mut file, SS, 0, Some(&template)).unwrap();
+        assert_eq!(plan.loader_before, BootLoaderKind::Ntldr);
+        assert_eq!(plan.changes.len(), 3);
+        assert!(plan.render_diff().contains("LBA 0 (MBR"));
+        plan.apply(&mut file).unwrap();
+
+        let mbr = read_at(&mut file, 0, 512).unwrap();
+        assert_eq!(&mbr[..MBR_BOOT_CODE.len()], MBR_BOOT_CODE);
+        assert_eq!(mbr[440..444], old_mbr[440..444]);
+        assert_eq!(mbr[PARTITION_TABLE_OFFSET], 0x80);
+        assert_eq!(mbr[PARTITION_TABLE_OFFSET + 16], 0x00);
+        // 除活动标记外分区表原样保留
+        let table = PARTITION_TABLE_OFFSET..512;
+        let flags = [PARTITION_TABLE_OFFSET, PARTITION_TABLE_OFFSET + 16];
+        assert!(table.filter(|i| !flags.contains(i)).all(|i| mbr[i] == old_mbr[i]));
+
+        let region = read_at(&mut file, 2048 * SS, NTFS_BOOT_REGION).unwrap();
+        assert_eq!(region[3..0x54], old_region[3..0x54]);
+        assert_eq!(detect_loader(VbrFileSystem::Ntfs, &region), BootLoaderKind::Bootmgr);
+        let backup = read_at(&mut file, (2048 + 4095) * SS, 512).unwrap();
+        assert_eq!(backup[..], region[..512]);
+        assert_eq!(locate_volume(&mut file, SS, &region[..512]).unwrap(), Some((0, 2048)));
+
+        // 再次规划不应产生任何修改
+        let again = plan_bios_boot(&mut file, SS, 0, Some(&template)).unwrap();
+        assert!(again.changes.is_empty());
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_fat32_plan_keeps_fsinfo() {
+        let (path, mut file) = temp_disk("fat32.img", 8192);
+        write_mbr(&mut file, &[(0x0C, 2048, 4096)]);
+        let old_region = fat32_region(b"NTLDR   ");
+        write_at(&mut file, 2048 * SS, &old_region).unwrap();
+        write_at(&mut file, (2048 + 6) * SS, &old_region[..512]).unwrap();
+
+        let mut template_region = fat32_region(b"BOOTMGR ");
+        template_region[12 * 512..12 * 512 + 8].copy_from_slice(b"NT6CODE\0");
+        template_region[512..516].copy_from_slice(b"XXXX");
+        let template = VbrTemplate::from_bytes(template_region, "test").unwrap();
+        let plan = plan_bios_boot(&mut file, SS, 0, Some(&template)).unwrap();
+        plan.apply(&mut file).unwrap();
+
+        let region = read_at(&mut file, 2048 * SS, 13 * 512).unwrap();
+        assert_eq!(region[3..0x5A], old_region[3..0x5A]);
+        assert_eq!(&region[512..516], b"RRaA");
+        assert_eq!(&region[12 * 512..12 * 512 + 8], b"NT6CODE\0");
+        assert_eq!(detect_loader(VbrFileSystem::Fat32, &region), BootLoaderKind::Bootmgr);
+        let backup = read_at(&mut file, (2048 + 6) * SS, 512).unwrap();
+        assert_eq!(backup[..], region[..512]);
+        let mbr = read_at(&mut file, 0, 512).unwrap();
+        assert_eq!(mbr[PARTITION_TABLE_OFFSET], 0x80);
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_plan_without_template() {
+        let (path, mut file) = temp_disk("notemplate.img", 8192);
+        write_mbr(&mut file, &[(0x07, 2048, 4096)]);
+        write_at(&mut file, 2048 * SS, &ntfs_region(4095, b"NTLDR is missing", 1)).unwrap();
+        assert!(plan_bios_boot(&mut file, SS, 0, None).is_err());
+        assert!(plan_bios_boot(&mut file, SS, 2, None).is_err());
+
+        write_at(&mut file, 2048 * SS, &ntfs_region(4095, b"BOOTMGR is missing", 1)).unwrap();
+        let plan = plan_bios_boot(&mut file, SS, 0, None).unwrap();
+        assert_eq!(plan.changes.len(), 1);
+        assert_eq!(plan.changes[0].lba, 0);
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+}
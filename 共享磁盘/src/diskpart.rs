@@ -0,0 +1,1116 @@
+//! diskpart 脚本构建与输出解析模块
+//!
+//! 使用类型化的 [`DiskpartScript`] 生成脚本，并根据 diskpart 的消息资源
+//! （zh-CN、zh-TW、en-US 及常见欧洲/日文语言）逐条解析每个命令的执行结果，
+//! 不再依赖在整段输出里搜索 "错误"/"error" 之类的关键字。
+
+use anyhow::Result;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::util::{gbk_to_utf8, get_bin_dir, new_command};
+
+/// 获取 diskpart 可执行文件路径
+/// 优先使用内置的 diskpart，如果不存在则使用系统的
+pub fn get_diskpart_path() -> String {
+    let builtin_diskpart = get_bin_dir().join("diskpart").join("diskpart.exe");
+    if builtin_diskpart.exists() {
+        log::debug!("使用内置 diskpart: {}", builtin_diskpart.display());
+        builtin_diskpart.to_string_lossy().to_string()
+    } else {
+        log::debug!("使用系统 diskpart");
+        "diskpart.exe".to_string()
+    }
+}
+
+/// 脚本文件存放目录
+///
+/// WinPE 下 std::env::temp_dir() 可能指向不存在的路径，
+/// 直接写脚本会触发 "系统找不到指定的路径 (os error 3)"，因此按顺序尝试并创建，
+/// 临时目录不可用时改用 X: 盘上的目录
+fn script_dir() -> PathBuf {
+    let candidates = [
+        std::env::temp_dir(),
+        PathBuf::from(r"X:\Windows\Temp"),
+        PathBuf::from(r"X:\Temp"),
+        PathBuf::from("X:\\"),
+    ];
+
+    for dir in candidates {
+        let _ = std::fs::create_dir_all(&dir);
+        if dir.exists() {
+            return dir;
+        }
+    }
+
+    std::env::temp_dir()
+}
+
+/// 同一进程内脚本文件名计数器，避免并发执行时互相覆盖
+static SCRIPT_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// create partition 的分区类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatePartitionKind {
+    Primary,
+    Efi,
+    Msr,
+}
+
+impl fmt::Display for CreatePartitionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreatePartitionKind::Primary => write!(f, "primary"),
+            CreatePartitionKind::Efi => write!(f, "efi"),
+            CreatePartitionKind::Msr => write!(f, "msr"),
+        }
+    }
+}
+
+/// 单条 diskpart 命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiskpartCommand {
+    SelectDisk(u32),
+    SelectPartition(u32),
+    /// 卷号或盘符
+    SelectVolume(String),
+    Clean,
+    ConvertGpt,
+    ConvertMbr,
+    CreatePartition {
+        kind: CreatePartitionKind,
+        /// 大小（MB），None 表示使用所有剩余空间
+        size_mb: Option<u64>,
+        /// 起始偏移（KB）
+        offset_kb: Option<u64>,
+    },
+    Format {
+        fs: String,
+        label: Option<String>,
+        quick: bool,
+    },
+    /// 分配盘符，None 表示由系统自动分配
+    Assign(Option<char>),
+    /// 移除盘符，None 表示移除全部
+    Remove(Option<char>),
+    Active,
+    SetId(String),
+    GptAttributes(u64),
+    DeletePartition {
+        override_protected: bool,
+    },
+    Shrink {
+        desired_mb: u64,
+        minimum_mb: Option<u64>,
+    },
+    ShrinkQueryMax,
+    /// 扩展（MB），None 表示扩展到所有相邻可用空间
+    Extend(Option<u64>),
+    Rescan,
+    ListDisk,
+    ListVolume,
+    ListPartition,
+    DetailDisk,
+    DetailVolume,
+    DetailPartition,
+    /// 未建模的原始命令，不做结果判定
+    Raw(String),
+}
+
+impl fmt::Display for DiskpartCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskpartCommand::SelectDisk(n) => write!(f, "select disk {}", n),
+            DiskpartCommand::SelectPartition(n) => write!(f, "select partition {}", n),
+            DiskpartCommand::SelectVolume(v) => write!(f, "select volume {}", v),
+            DiskpartCommand::Clean => write!(f, "clean"),
+            DiskpartCommand::ConvertGpt => write!(f, "convert gpt"),
+            DiskpartCommand::ConvertMbr => write!(f, "convert mbr"),
+            DiskpartCommand::CreatePartition { kind, size_mb, offset_kb } => {
+                write!(f, "create partition {}", kind)?;
+                if let Some(size) = size_mb {
+                    write!(f, " size={}", size)?;
+                }
+                if let Some(offset) = offset_kb {
+                    write!(f, " offset={}", offset)?;
+                }
+                Ok(())
+            }
+            DiskpartCommand::Format { fs, label, quick } => {
+                write!(f, "format fs={}", fs)?;
+                if *quick {
+                    write!(f, " quick")?;
+                }
+                if let Some(label) = label {
+                    write!(f, " label=\"{}\"", label.replace('"', ""))?;
+                }
+                Ok(())
+            }
+            DiskpartCommand::Assign(Some(letter)) => write!(f, "assign letter={}", letter),
+            DiskpartCommand::Assign(None) => write!(f, "assign"),
+            DiskpartCommand::Remove(Some(letter)) => write!(f, "remove letter={}", letter),
+            DiskpartCommand::Remove(None) => write!(f, "remove all"),
+            DiskpartCommand::Active => write!(f, "active"),
+            DiskpartCommand::SetId(id) => write!(f, "set id={}", id),
+            DiskpartCommand::GptAttributes(attrs) => write!(f, "gpt attributes=0x{:016x}", attrs),
+            DiskpartCommand::DeletePartition { override_protected } => {
+                if *override_protected {
+                    write!(f, "delete partition override")
+                } else {
+                    write!(f, "delete partition")
+                }
+            }
+            DiskpartCommand::Shrink { desired_mb, minimum_mb } => {
+                write!(f, "shrink desired={}", desired_mb)?;
+                if let Some(minimum) = minimum_mb {
+                    write!(f, " minimum={}", minimum)?;
+                }
+                Ok(())
+            }
+            DiskpartCommand::ShrinkQueryMax => write!(f, "shrink querymax"),
+            DiskpartCommand::Extend(Some(size)) => write!(f, "extend size={}", size),
+            DiskpartCommand::Extend(None) => write!(f, "extend"),
+            DiskpartCommand::Rescan => write!(f, "rescan"),
+            DiskpartCommand::ListDisk => write!(f, "list disk"),
+            DiskpartCommand::ListVolume => write!(f, "list volume"),
+            DiskpartCommand::ListPartition => write!(f, "list partition"),
+            DiskpartCommand::DetailDisk => write!(f, "detail disk"),
+            DiskpartCommand::DetailVolume => write!(f, "detail volume"),
+            DiskpartCommand::DetailPartition => write!(f, "detail partition"),
+            DiskpartCommand::Raw(line) => write!(f, "{}", line),
+        }
+    }
+}
+
+impl DiskpartCommand {
+    /// 命令成功时 diskpart 输出的提示（小写，en-US / zh-CN / zh-TW / de-DE / fr-FR / ja-JP）
+    ///
+    /// 返回空切片的命令（list/detail 等）只输出信息，没有固定的成功提示
+    fn success_markers(&self) -> &'static [&'static str] {
+        match self {
+            DiskpartCommand::SelectDisk(_) => &[
+                "is now the selected disk",
+                "现在是所选磁盘",
+                "現在是選取的磁碟",
+                "ist jetzt der gewählte datenträger",
+                "est maintenant le disque sélectionné",
+                "が選択されました",
+            ],
+            DiskpartCommand::SelectPartition(_) => &[
+                "is now the selected partition",
+                "现在是所选分区",
+                "現在是選取的磁碟分割",
+                "ist jetzt die gewählte partition",
+                "est maintenant la partition sélectionnée",
+            ],
+            DiskpartCommand::SelectVolume(_) => &[
+                "is the selected volume",
+                "是所选卷",
+                "是選取的磁碟區",
+                "ist das gewählte volume",
+                "est le volume sélectionné",
+            ],
+            DiskpartCommand::Clean => &[
+                "succeeded in cleaning the disk",
+                "成功地清除了磁盘",
+                "已成功清除磁碟",
+                "datenträger bereinigt",
+                "a réussi à nettoyer le disque",
+                "ディスクを正常にクリーンな状態にしました",
+            ],
+            DiskpartCommand::ConvertGpt => &["to gpt format", "gpt 格式", "gpt-format", "format gpt"],
+            DiskpartCommand::ConvertMbr => &["to mbr format", "mbr 格式", "mbr-format", "format mbr"],
+            DiskpartCommand::CreatePartition { .. } => &[
+                "succeeded in creating the specified partition",
+                "成功地创建了指定分区",
+                "已成功建立指定的磁碟分割",
+                "angegebene partition wurde erfolgreich erstellt",
+                "a réussi à créer la partition spécifiée",
+                "指定したパーティションの作成に成功しました",
+            ],
+            DiskpartCommand::Format { .. } => &[
+                "successfully formatted the volume",
+                "成功格式化该卷",
+                "已成功格式化磁碟區",
+                "volume wurde erfolgreich formatiert",
+                "a formaté le volume",
+                "ボリュームは正常にフォーマットされました",
+            ],
+            DiskpartCommand::Assign(_) => &[
+                "successfully assigned the drive letter or mount point",
+                "成功地分配了驱动器号或装载点",
+                "已成功指派磁碟機代號或掛接點",
+                "laufwerkbuchstabe oder bereitstellungspunkt wurde erfolgreich zugewiesen",
+                "a attribué la lettre de lecteur ou le point de montage",
+                "ドライブ文字またはマウント ポイントは正常に割り当てられました",
+            ],
+            DiskpartCommand::Remove(_) => &[
+                "successfully removed the drive letter or mount point",
+                "成功地删除了驱动器号或装载点",
+                "已成功移除磁碟機代號或掛接點",
+                "laufwerkbuchstabe oder bereitstellungspunkt wurde erfolgreich entfernt",
+                "a supprimé la lettre de lecteur ou le point de montage",
+            ],
+            DiskpartCommand::Active => &[
+                "marked the current partition as active",
+                "将当前分区标为活动",
+                "標示為使用中",
+                "als aktiv markiert",
+                "a marqué la partition actuelle comme active",
+            ],
+            DiskpartCommand::SetId(_) => &[
+                "successfully set the partition id",
+                "成功设置了分区 id",
+                "已成功設定磁碟分割識別碼",
+                "partitions-id wurde erfolgreich festgelegt",
+                "a défini l'id de partition",
+            ],
+            DiskpartCommand::GptAttributes(_) => &[
+                "assigned the attributes to the selected gpt partition",
+                "成功将属性分配给所选 gpt 分区",
+                "已成功將屬性指派給選取的 gpt 磁碟分割",
+                "attribute wurden der gewählten gpt-partition erfolgreich zugewiesen",
+                "a attribué les attributs à la partition gpt sélectionnée",
+            ],
+            DiskpartCommand::DeletePartition { .. } => &[
+                "successfully deleted the selected partition",
+                "成功地删除了所选分区",
+                "已成功刪除選取的磁碟分割",
+                "gewählte partition wurde erfolgreich gelöscht",
+                "a supprimé la partition sélectionnée",
+            ],
+            DiskpartCommand::Shrink { .. } => &[
+                "successfully shrunk the volume",
+                "成功收缩卷",
+                "已成功壓縮磁碟區",
+                "volume wurde erfolgreich verkleinert",
+                "a réduit le volume",
+            ],
+            DiskpartCommand::ShrinkQueryMax => &[
+                "reclaimable bytes",
+                "可回收",
+                "可回收的最大位元組數",
+                "freigebbaren bytes",
+                "octets récupérables",
+            ],
+            DiskpartCommand::Extend(_) => &[
+                "successfully extended the volume",
+                "成功地扩展了卷",
+                "已成功延伸磁碟區",
+                "volume wurde erfolgreich erweitert",
+                "a étendu le volume",
+            ],
+            DiskpartCommand::Rescan => &[
+                "finished scanning your configuration",
+                "已完成扫描你的配置",
+                "已完成扫描您的配置",
+                "已完成掃描您的設定",
+                "überprüfung der konfiguration abgeschlossen",
+                "a terminé l'analyse de votre configuration",
+            ],
+            DiskpartCommand::ListDisk
+            | DiskpartCommand::ListVolume
+            | DiskpartCommand::ListPartition
+            | DiskpartCommand::DetailDisk
+            | DiskpartCommand::DetailVolume
+            | DiskpartCommand::DetailPartition
+            | DiskpartCommand::Raw(_) => &[],
+        }
+    }
+}
+
+/// 失败类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// 没有足够的可用空间（create/extend/shrink）
+    NoSpace,
+    /// 命令参数无效
+    InvalidArguments,
+    /// 没有选中磁盘/分区/卷
+    NothingSelected,
+    /// 虚拟磁盘服务（VDS）错误
+    ServiceError,
+    /// 其他 diskpart 错误
+    Other,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureKind::NoSpace => write!(f, "可用空间不足"),
+            FailureKind::InvalidArguments => write!(f, "参数无效"),
+            FailureKind::NothingSelected => write!(f, "未选择对象"),
+            FailureKind::ServiceError => write!(f, "虚拟磁盘服务错误"),
+            FailureKind::Other => write!(f, "diskpart 错误"),
+        }
+    }
+}
+
+/// diskpart 错误提示（小写），按匹配优先级排列
+const FAILURE_MARKERS: &[(FailureKind, &[&str])] = &[
+    (
+        FailureKind::NoSpace,
+        &[
+            "not enough usable space",
+            "not enough usable free space",
+            "no usable free extent",
+            "没有足够的可用",
+            "可用空间不足",
+            "沒有足夠的可用",
+            "nicht genügend nutzbarer",
+            "pas assez d'espace utilisable",
+            "十分な空き領域がありません",
+        ],
+    ),
+    (
+        FailureKind::InvalidArguments,
+        &[
+            "arguments specified for this command are not valid",
+            "为此命令指定的参数无效",
+            "為此命令指定的引數無效",
+            "argumente sind ungültig",
+            "arguments spécifiés pour cette commande ne sont pas valides",
+            "このコマンドに指定された引数は無効です",
+        ],
+    ),
+    (
+        FailureKind::NothingSelected,
+        &[
+            "there is no disk selected",
+            "there is no partition selected",
+            "there is no volume selected",
+            "没有选择磁盘",
+            "没有选择分区",
+            "没有选择卷",
+            "沒有選取磁碟",
+            "es ist kein datenträger ausgewählt",
+            "es ist keine partition ausgewählt",
+            "aucun disque n'est sélectionné",
+            "aucune partition n'est sélectionnée",
+            "ディスクが選択されていません",
+        ],
+    ),
+    (
+        FailureKind::ServiceError,
+        &[
+            "virtual disk service error",
+            "虚拟磁盘服务错误",
+            "虛擬磁碟服務錯誤",
+            "fehler des virtuellen datenträgerdienstes",
+            "erreur du service de disque virtuel",
+            "仮想ディスク サービス エラー",
+            "ошибка службы виртуальных дисков",
+        ],
+    ),
+    (
+        FailureKind::Other,
+        &[
+            "diskpart has encountered an error",
+            "diskpart 遇到错误",
+            "diskpart 遇到錯誤",
+            "fehler in diskpart",
+            "diskpart a rencontré une erreur",
+            "diskpart でエラーが発生しました",
+            "diskpart обнаружила ошибку",
+            "the disk you specified is not valid",
+            "the partition you specified is not valid",
+            "the volume you specified is not valid",
+            "指定的磁盘无效",
+            "指定的分区无效",
+            "指定的卷无效",
+            "您指定的磁碟無效",
+            "您指定的磁碟分割無效",
+            "您指定的磁碟區無效",
+            "the specified command or parameters are not supported on this system",
+            "此系统不支持指定的命令或参数",
+            "此系統不支援指定的命令或參數",
+            "access is denied.",
+            "拒绝访问。",
+            "存取被拒。",
+        ],
+    ),
+];
+
+/// 启动时输出的版本/版权/计算机名信息（小写）
+const BANNER_MARKERS: &[&str] = &[
+    "microsoft diskpart",
+    "copyright (c)",
+    "版权所有",
+    "著作權",
+    "on computer:",
+    "在计算机上",
+    "在電腦上",
+    "auf computer:",
+    "sur l'ordinateur",
+    "コンピューター:",
+];
+
+/// 单条命令的执行状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandStatus {
+    /// 匹配到成功提示（或纯信息命令产生了输出）
+    Succeeded,
+    /// 匹配到错误提示
+    Failed { kind: FailureKind, message: String },
+    /// 前面的命令失败，diskpart 已停止执行
+    NotRun,
+    /// 未匹配到已知提示（未知语言），需要结合退出码判断
+    Unconfirmed,
+}
+
+/// 单条命令的解析结果
+#[derive(Debug, Clone)]
+pub struct CommandResult {
+    pub command: DiskpartCommand,
+    pub status: CommandStatus,
+    /// 该命令对应的输出文本
+    pub output: String,
+}
+
+impl CommandResult {
+    pub fn is_failed(&self) -> bool {
+        matches!(self.status, CommandStatus::Failed { .. })
+    }
+}
+
+/// 一次 diskpart 执行的结果
+#[derive(Debug, Clone)]
+pub struct DiskpartOutcome {
+    /// 进程退出码（/s 模式下出错时非 0，与语言无关）
+    pub exit_code: Option<i32>,
+    /// 完整输出
+    pub output: String,
+    pub results: Vec<CommandResult>,
+}
+
+impl DiskpartOutcome {
+    /// 根据脚本命令和 diskpart 输出生成逐条结果
+    pub fn parse(commands: &[DiskpartCommand], output: &str, exit_code: Option<i32>) -> Self {
+        let mut results: Vec<CommandResult> = commands
+            .iter()
+            .map(|command| CommandResult {
+                command: command.clone(),
+                status: CommandStatus::Unconfirmed,
+                output: String::new(),
+            })
+            .collect();
+
+        let blocks = split_blocks(output);
+        let mut body = blocks.iter().skip_while(|block| is_banner(block)).peekable();
+        let mut cursor = 0;
+        let mut failed = false;
+
+        while let Some(block) = body.next() {
+            if cursor >= results.len() {
+                break;
+            }
+            let lower = block.to_lowercase();
+
+            if let Some(kind) = match_failure(&lower) {
+                // 错误提示后面可能还有一段补充说明（如 "请参阅系统事件日志"）
+                let mut message = block.clone();
+                while let Some(next) = body.peek() {
+                    let next_lower = next.to_lowercase();
+                    if results[cursor..]
+                        .iter()
+                        .any(|r| matches_any(&next_lower, r.command.success_markers()))
+                    {
+                        break;
+                    }
+                    message.push('\n');
+                    message.push_str(next);
+                    body.next();
+                }
+                append_output(&mut results[cursor].output, &message);
+                results[cursor].status = CommandStatus::Failed { kind, message };
+                failed = true;
+                cursor += 1;
+                break;
+            }
+
+            let matched = results[cursor..]
+                .iter()
+                .position(|r| matches_any(&lower, r.command.success_markers()))
+                .map(|offset| cursor + offset);
+
+            match matched {
+                Some(index) => {
+                    for skipped in &mut results[cursor..index] {
+                        if skipped.command.success_markers().is_empty() && !skipped.output.is_empty() {
+                            skipped.status = CommandStatus::Succeeded;
+                        }
+                    }
+                    append_output(&mut results[index].output, block);
+                    results[index].status = CommandStatus::Succeeded;
+                    cursor = index + 1;
+                }
+                None => {
+                    // 进度、列表等信息输出，归属到当前等待结果的命令
+                    append_output(&mut results[cursor].output, block);
+                }
+            }
+        }
+
+        for result in &mut results[cursor..] {
+            if failed {
+                result.status = CommandStatus::NotRun;
+            } else if result.command.success_markers().is_empty() && !result.output.is_empty() {
+                result.status = CommandStatus::Succeeded;
+            }
+        }
+
+        Self {
+            exit_code,
+            output: output.to_string(),
+            results,
+        }
+    }
+
+    /// 是否全部成功：退出码为 0 且没有匹配到任何错误提示
+    pub fn is_success(&self) -> bool {
+        matches!(self.exit_code, None | Some(0)) && self.failure().is_none()
+    }
+
+    /// 第一条失败的命令
+    pub fn failure(&self) -> Option<&CommandResult> {
+        self.results.iter().find(|r| r.is_failed())
+    }
+
+    /// 是否因可用空间不足而失败
+    pub fn is_no_space(&self) -> bool {
+        matches!(
+            self.failure().map(|r| &r.status),
+            Some(CommandStatus::Failed { kind: FailureKind::NoSpace, .. })
+        )
+    }
+
+    /// 失败原因描述
+    pub fn error_message(&self) -> String {
+        if let Some(result) = self.failure() {
+            if let CommandStatus::Failed { kind, message } = &result.status {
+                return format!("{} ({}): {}", result.command, kind, message.trim());
+            }
+        }
+        match self.exit_code {
+            Some(code) if code != 0 => format!(
+                "diskpart 退出码 {}（{}）: {}",
+                code,
+                describe_exit_code(code),
+                split_blocks(&self.output).last().map(String::as_str).unwrap_or("").trim()
+            ),
+            _ => String::new(),
+        }
+    }
+
+    /// 失败时转为错误
+    pub fn ensure_success(self, context: &str) -> Result<Self> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            anyhow::bail!("{}: {}", context, self.error_message())
+        }
+    }
+
+    /// 获取指定命令的输出（第一条满足条件的命令）
+    pub fn output_of(&self, predicate: impl Fn(&DiskpartCommand) -> bool) -> Option<&str> {
+        self.results
+            .iter()
+            .find(|r| predicate(&r.command))
+            .map(|r| r.output.as_str())
+    }
+
+    /// 解析 shrink querymax 的结果（MB）
+    pub fn shrink_querymax_mb(&self) -> Option<u64> {
+        let output = self.output_of(|c| *c == DiskpartCommand::ShrinkQueryMax)?;
+        parse_querymax_mb(output)
+    }
+}
+
+/// 解析 shrink querymax 输出中的可回收空间（MB）
+///
+/// 典型输出: "The maximum number of reclaimable bytes is:   28 GB (29339 MB)"
+/// 或 "最多可回收字节数:  28 GB (29339 MB)"，优先使用括号里的 MB 精确值
+pub fn parse_querymax_mb(output: &str) -> Option<u64> {
+    let markers = DiskpartCommand::ShrinkQueryMax.success_markers();
+    let line = output
+        .lines()
+        .find(|line| matches_any(&line.to_lowercase(), markers))?;
+
+    let sizes = sizes_in_line(line);
+    sizes
+        .iter()
+        .find(|(_, unit)| *unit == "MB")
+        .or_else(|| sizes.first())
+        .map(|(value, unit)| match *unit {
+            "KB" => value / 1024,
+            "GB" => value * 1024,
+            "TB" => value * 1024 * 1024,
+            "B" => value / 1024 / 1024,
+            _ => *value,
+        })
+}
+
+/// 提取一行中所有 "数字 单位" 对
+fn sizes_in_line(line: &str) -> Vec<(u64, &'static str)> {
+    const UNITS: &[&str] = &["KB", "MB", "GB", "TB", "B"];
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut sizes = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let mut value: u64 = 0;
+        while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == ',' || chars[i] == '.') {
+            if let Some(digit) = chars[i].to_digit(10) {
+                value = value.saturating_mul(10).saturating_add(digit as u64);
+            }
+            i += 1;
+        }
+        while i < chars.len() && chars[i] == ' ' {
+            i += 1;
+        }
+        let rest: String = chars[i..].iter().take(2).collect::<String>().to_uppercase();
+        if let Some(unit) = UNITS.iter().find(|unit| rest.starts_with(**unit)) {
+            sizes.push((value, *unit));
+        }
+    }
+    sizes
+}
+
+/// diskpart 退出码含义
+pub fn describe_exit_code(code: i32) -> &'static str {
+    match code {
+        0 => "成功",
+        1 => "发生致命错误",
+        2 => "命令参数不正确",
+        3 => "无法打开脚本或输出文件",
+        4 => "虚拟磁盘服务返回错误",
+        5 => "命令语法错误",
+        _ => "未知错误",
+    }
+}
+
+fn split_blocks(output: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+    blocks
+}
+
+fn is_banner(block: &str) -> bool {
+    matches_any(&block.to_lowercase(), BANNER_MARKERS)
+}
+
+fn match_failure(lower: &str) -> Option<FailureKind> {
+    FAILURE_MARKERS
+        .iter()
+        .find(|(_, markers)| matches_any(lower, markers))
+        .map(|(kind, _)| *kind)
+}
+
+fn matches_any(lower: &str, markers: &[&str]) -> bool {
+    markers.iter().any(|marker| lower.contains(marker))
+}
+
+fn append_output(output: &mut String, block: &str) {
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output.push_str(block);
+}
+
+/// diskpart 脚本构建器
+#[derive(Debug, Clone, Default)]
+pub struct DiskpartScript {
+    commands: Vec<DiskpartCommand>,
+}
+
+impl DiskpartScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, command: DiskpartCommand) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    pub fn select_disk(self, disk_number: u32) -> Self {
+        self.push(DiskpartCommand::SelectDisk(disk_number))
+    }
+
+    pub fn select_partition(self, partition_number: u32) -> Self {
+        self.push(DiskpartCommand::SelectPartition(partition_number))
+    }
+
+    /// 按卷号或盘符选择卷
+    pub fn select_volume(self, volume: impl fmt::Display) -> Self {
+        self.push(DiskpartCommand::SelectVolume(volume.to_string()))
+    }
+
+    pub fn clean(self) -> Self {
+        self.push(DiskpartCommand::Clean)
+    }
+
+    pub fn convert_gpt(self) -> Self {
+        self.push(DiskpartCommand::ConvertGpt)
+    }
+
+    pub fn convert_mbr(self) -> Self {
+        self.push(DiskpartCommand::ConvertMbr)
+    }
+
+    pub fn create_partition(
+        self,
+        kind: CreatePartitionKind,
+        size_mb: Option<u64>,
+        offset_kb: Option<u64>,
+    ) -> Self {
+        self.push(DiskpartCommand::CreatePartition { kind, size_mb, offset_kb })
+    }
+
+    /// 快速格式化
+    pub fn format(self, fs: &str, label: Option<&str>) -> Self {
+        self.push(DiskpartCommand::Format {
+            fs: fs.to_string(),
+            label: label.map(str::to_string),
+            quick: true,
+        })
+    }
+
+    pub fn assign(self, letter: Option<char>) -> Self {
+        self.push(DiskpartCommand::Assign(letter))
+    }
+
+    pub fn remove_letter(self, letter: Option<char>) -> Self {
+        self.push(DiskpartCommand::Remove(letter))
+    }
+
+    pub fn active(self) -> Self {
+        self.push(DiskpartCommand::Active)
+    }
+
+    pub fn set_id(self, id: &str) -> Self {
+        self.push(DiskpartCommand::SetId(id.to_string()))
+    }
+
+    pub fn gpt_attributes(self, attributes: u64) -> Self {
+        self.push(DiskpartCommand::GptAttributes(attributes))
+    }
+
+    pub fn delete_partition(self, override_protected: bool) -> Self {
+        self.push(DiskpartCommand::DeletePartition { override_protected })
+    }
+
+    pub fn shrink(self, desired_mb: u64, minimum_mb: Option<u64>) -> Self {
+        self.push(DiskpartCommand::Shrink { desired_mb, minimum_mb })
+    }
+
+    pub fn shrink_querymax(self) -> Self {
+        self.push(DiskpartCommand::ShrinkQueryMax)
+    }
+
+    pub fn extend(self, size_mb: Option<u64>) -> Self {
+        self.push(DiskpartCommand::Extend(size_mb))
+    }
+
+    pub fn rescan(self) -> Self {
+        self.push(DiskpartCommand::Rescan)
+    }
+
+    pub fn list_disk(self) -> Self {
+        self.push(DiskpartCommand::ListDisk)
+    }
+
+    pub fn list_volume(self) -> Self {
+        self.push(DiskpartCommand::ListVolume)
+    }
+
+    pub fn list_partition(self) -> Self {
+        self.push(DiskpartCommand::ListPartition)
+    }
+
+    pub fn detail_disk(self) -> Self {
+        self.push(DiskpartCommand::DetailDisk)
+    }
+
+    pub fn detail_volume(self) -> Self {
+        self.push(DiskpartCommand::DetailVolume)
+    }
+
+    pub fn detail_partition(self) -> Self {
+        self.push(DiskpartCommand::DetailPartition)
+    }
+
+    pub fn raw(self, line: &str) -> Self {
+        self.push(DiskpartCommand::Raw(line.to_string()))
+    }
+
+    pub fn commands(&self) -> &[DiskpartCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// 生成脚本文本
+    pub fn render(&self) -> String {
+        let mut script = String::new();
+        for command in &self.commands {
+            script.push_str(&command.to_string());
+            script.push('\n');
+        }
+        script
+    }
+
+    /// 使用默认 diskpart 执行
+    pub fn run(&self) -> Result<DiskpartOutcome> {
+        self.run_with(&get_diskpart_path())
+    }
+
+    /// 使用指定的 diskpart 可执行文件执行
+    pub fn run_with(&self, diskpart: &str) -> Result<DiskpartOutcome> {
+        let script = self.render();
+        let script_path = script_dir().join(format!(
+            "lr_diskpart_{}_{}.txt",
+            std::process::id(),
+            SCRIPT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        log::debug!("Diskpart 脚本内容:\n{}", script);
+        std::fs::write(&script_path, &script)?;
+
+        let output = new_command(diskpart)
+            .args(["/s", &script_path.to_string_lossy()])
+            .output();
+        let _ = std::fs::remove_file(&script_path);
+        let output = output?;
+
+        let output_text = gbk_to_utf8(&output.stdout);
+        let error_text = gbk_to_utf8(&output.stderr);
+        log::info!("Diskpart 输出: {}", output_text);
+        if !error_text.trim().is_empty() {
+            log::warn!("Diskpart 错误输出: {}", error_text);
+        }
+
+        Ok(DiskpartOutcome::parse(&self.commands, &output_text, output.status.code()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZH_CN_QUICK_PARTITION: &str = "
+Microsoft DiskPart 版本 10.0.19041.964
+
+Copyright (C) Microsoft Corporation.
+在计算机上: DESKTOP-LR
+
+磁盘 1 现在是所选磁盘。
+
+DiskPart 成功地清除了磁盘。
+
+DiskPart 已将所选磁盘成功地转更换为 GPT 格式。
+
+DiskPart 成功地创建了指定分区。
+
+  100 百分比已完成
+
+DiskPart 成功格式化该卷。
+
+DiskPart 成功地创建了指定分区。
+
+  100 百分比已完成
+
+DiskPart 成功格式化该卷。
+
+DiskPart 成功地分配了驱动器号或装载点。
+";
+
+    const EN_US_NO_SPACE: &str = "
+Microsoft DiskPart version 10.0.22621.1
+
+Copyright (C) Microsoft Corporation.
+On computer: WIN-LR
+
+Disk 0 is now the selected disk.
+
+Virtual Disk Service error:
+There is not enough usable space for this operation.
+
+";
+
+    const ZH_CN_QUERYMAX: &str = "
+Microsoft DiskPart 版本 10.0.19041.964
+
+Copyright (C) Microsoft Corporation.
+在计算机上: DESKTOP-LR
+
+卷 2 是所选卷。
+
+最多可回收字节数:   28 GB (29339 MB)
+";
+
+    const EN_US_QUERYMAX: &str = "
+Microsoft DiskPart version 10.0.22621.1
+
+Copyright (C) Microsoft Corporation.
+On computer: WIN-LR
+
+Volume 3 is the selected volume.
+
+The maximum number of reclaimable bytes is:  512 MB
+";
+
+    const ZH_TW_ARGUMENTS: &str = "
+Microsoft DiskPart 版本 10.0.19041.964
+
+Copyright (C) Microsoft Corporation.
+在電腦上: PC
+
+磁碟區 1 是選取的磁碟區。
+
+為此命令指定的引數無效。
+如需有關此命令的詳細資訊，請輸入: HELP EXTEND
+";
+
+    fn quick_partition_script() -> DiskpartScript {
+        DiskpartScript::new()
+            .select_disk(1)
+            .clean()
+            .convert_gpt()
+            .create_partition(CreatePartitionKind::Efi, Some(300), Some(1024))
+            .format("fat32", Some("EFI"))
+            .create_partition(CreatePartitionKind::Primary, None, None)
+            .format("ntfs", Some("系统错误备份"))
+            .assign(Some('D'))
+    }
+
+    #[test]
+    fn test_render_script() {
+        let script = quick_partition_script().render();
+        assert_eq!(
+            script,
+            "select disk 1\nclean\nconvert gpt\n\
+             create partition efi size=300 offset=1024\nformat fs=fat32 quick label=\"EFI\"\n\
+             create partition primary\nformat fs=ntfs quick label=\"系统错误备份\"\nassign letter=D\n"
+        );
+        let attrs = DiskpartScript::new().gpt_attributes(0x8000000000000001).render();
+        assert_eq!(attrs, "gpt attributes=0x8000000000000001\n");
+    }
+
+    #[test]
+    fn test_parse_zh_cn_success() {
+        // 卷标里带 "错误" 也不能被误判为失败
+        let script = quick_partition_script();
+        let outcome = DiskpartOutcome::parse(script.commands(), ZH_CN_QUICK_PARTITION, Some(0));
+        assert!(outcome.is_success());
+        assert!(outcome
+            .results
+            .iter()
+            .all(|r| r.status == CommandStatus::Succeeded));
+        assert!(outcome.results[4].output.contains("100 百分比已完成"));
+    }
+
+    #[test]
+    fn test_parse_en_us_failure_stops_script() {
+        let script = DiskpartScript::new()
+            .select_disk(0)
+            .create_partition(CreatePartitionKind::Primary, Some(102_400), None)
+            .format("ntfs", None)
+            .assign(None);
+        let outcome = DiskpartOutcome::parse(script.commands(), EN_US_NO_SPACE, Some(4));
+
+        assert!(!outcome.is_success());
+        assert!(outcome.is_no_space());
+        assert_eq!(outcome.results[0].status, CommandStatus::Succeeded);
+        assert!(outcome.results[1].is_failed());
+        assert_eq!(outcome.results[2].status, CommandStatus::NotRun);
+        assert_eq!(outcome.results[3].status, CommandStatus::NotRun);
+        assert!(outcome.error_message().starts_with("create partition primary size=102400"));
+    }
+
+    #[test]
+    fn test_parse_other_locales() {
+        let script = DiskpartScript::new().select_volume('E').extend(Some(1024));
+        let outcome = DiskpartOutcome::parse(script.commands(), ZH_TW_ARGUMENTS, Some(2));
+        assert_eq!(outcome.results[0].status, CommandStatus::Succeeded);
+        match &outcome.results[1].status {
+            CommandStatus::Failed { kind, message } => {
+                assert_eq!(*kind, FailureKind::InvalidArguments);
+                assert!(message.contains("HELP EXTEND"));
+            }
+            other => panic!("unexpected status: {:?}", other),
+        }
+
+        // 未知语言：没有任何已知提示，只能依靠退出码
+        let unknown = "DiskPart 버전 10.0\n\n디스크 1이(가) 선택한 디스크입니다.\n\nDiskPart에서 디스크를 정리했습니다.\n";
+        let script = DiskpartScript::new().select_disk(1).clean();
+        let ok = DiskpartOutcome::parse(script.commands(), unknown, Some(0));
+        assert!(ok.is_success());
+        assert!(ok.results.iter().all(|r| r.status == CommandStatus::Unconfirmed));
+        let failed = DiskpartOutcome::parse(script.commands(), unknown, Some(4));
+        assert!(!failed.is_success());
+        assert!(failed.error_message().contains("虚拟磁盘服务返回错误"));
+    }
+
+    #[test]
+    fn test_informational_output_is_not_failure() {
+        // detail 输出中的卷标、属性名等不能被当作错误提示
+        let output = "
+磁盘 0 现在是所选磁盘。
+
+  卷 ###      LTR  标签         FS     类型        大小     状态       信息
+  ----------  ---  -----------  -----  ----------  -------  ---------  --------
+  卷     1     D   无效备份       NTFS   磁盘分区        100 GB  正常
+
+只读  : 否
+Attribute Test Volume is not valid label
+
+DiskPart 成功将属性分配给所选 GPT 分区。
+
+DiskPart 成功格式化该卷。
+";
+        let script = DiskpartScript::new()
+            .select_disk(0)
+            .detail_disk()
+            .gpt_attributes(0x8000000000000001)
+            .format("ntfs", Some("无效备份"));
+        let outcome = DiskpartOutcome::parse(script.commands(), output, Some(0));
+        assert!(outcome.is_success());
+        assert!(outcome
+            .results
+            .iter()
+            .all(|r| r.status == CommandStatus::Succeeded));
+        assert!(outcome.results[1].output.contains("无效备份"));
+    }
+
+    #[test]
+    fn test_parse_querymax() {
+        let script = DiskpartScript::new().select_volume('C').shrink_querymax();
+        let zh = DiskpartOutcome::parse(script.commands(), ZH_CN_QUERYMAX, Some(0));
+        assert_eq!(zh.shrink_querymax_mb(), Some(29339));
+        let en = DiskpartOutcome::parse(script.commands(), EN_US_QUERYMAX, Some(0));
+        assert_eq!(en.shrink_querymax_mb(), Some(512));
+        assert_eq!(parse_querymax_mb("The maximum number of reclaimable bytes is: 2 GB"), Some(2048));
+    }
+}
@@ -0,0 +1,532 @@
+//! ESP 引导程序清点模块
+//!
+//! 在修复引导前清点 EFI 系统分区上的引导程序（GRUB、systemd-boot、rEFInd 等），
+//! 并在 bcdboot 覆盖回退引导程序 `EFI\Boot\bootx64.efi` 时保护属于其他系统的文件。
+
+use std::path::{Path, PathBuf};
+
+/// 回退引导程序相对路径（固件在没有引导项时加载）
+pub const FALLBACK_LOADER: &str = "EFI/Boot/bootx64.efi";
+
+/// 回退引导程序备份文件名（崩溃时可手动恢复）
+const FALLBACK_BACKUP_NAME: &str = "bootx64.efi.letrecovery.bak";
+
+/// 引导程序读取上限（引导程序通常只有几 MB）
+const MAX_LOADER_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 引导程序类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoaderKind {
+    /// Windows 启动管理器（bootmgfw.efi）
+    WindowsBootManager,
+    /// GRUB（grubx64.efi）
+    Grub,
+    /// Shim（shimx64.efi，用于安全启动链式加载 GRUB）
+    Shim,
+    /// systemd-boot
+    SystemdBoot,
+    /// rEFInd
+    Refind,
+    /// 无法识别的引导程序
+    Unknown,
+}
+
+impl LoaderKind {
+    /// 是否属于 Windows
+    pub fn is_windows(&self) -> bool {
+        *self == LoaderKind::WindowsBootManager
+    }
+
+    /// 显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            LoaderKind::WindowsBootManager => "Windows Boot Manager",
+            LoaderKind::Grub => "GRUB",
+            LoaderKind::Shim => "Shim",
+            LoaderKind::SystemdBoot => "systemd-boot",
+            LoaderKind::Refind => "rEFInd",
+            LoaderKind::Unknown => "未知引导程序",
+        }
+    }
+}
+
+impl std::fmt::Display for LoaderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_name())
+    }
+}
+
+/// ESP 上的单个引导程序
+#[derive(Debug, Clone, PartialEq)]
+pub struct EspLoader {
+    /// 相对 ESP 根目录的路径（使用 `/` 分隔）
+    pub relative_path: String,
+    /// 引导程序类型
+    pub kind: LoaderKind,
+    /// 文件大小（字节）
+    pub size_bytes: u64,
+}
+
+impl EspLoader {
+    /// 是否为回退引导程序 `EFI\Boot\bootx64.efi`
+    pub fn is_fallback(&self) -> bool {
+        self.relative_path.eq_ignore_ascii_case(FALLBACK_LOADER)
+    }
+}
+
+/// ESP 清点结果
+#[derive(Debug, Clone, Default)]
+pub struct EspInventory {
+    /// 所有找到的 .efi 引导程序
+    pub loaders: Vec<EspLoader>,
+}
+
+impl EspInventory {
+    /// 回退引导程序
+    pub fn fallback(&self) -> Option<&EspLoader> {
+        self.loaders.iter().find(|l| l.is_fallback())
+    }
+
+    /// 属于其他操作系统的引导程序（不含回退引导程序）
+    pub fn foreign_loaders(&self) -> Vec<&EspLoader> {
+        self.loaders
+            .iter()
+            .filter(|l| !l.is_fallback() && !l.kind.is_windows())
+            .collect()
+    }
+
+    /// 回退引导程序是否属于其他操作系统
+    pub fn fallback_is_foreign(&self) -> bool {
+        self.fallback().map(|l| !l.kind.is_windows()).unwrap_or(false)
+    }
+}
+
+/// 按文件内容识别引导程序类型
+///
+/// 先检查厂商特征字符串，再检查 Windows 启动管理器的 PDB 名称。
+pub fn classify_loader_bytes(data: &[u8]) -> LoaderKind {
+    if contains(data, b"systemd-boot") {
+        LoaderKind::SystemdBoot
+    } else if contains(data, b"rEFInd") {
+        LoaderKind::Refind
+    } else if contains(data, b"shim.efi") || contains(data, b"UEFI SHIM") {
+        LoaderKind::Shim
+    } else if contains(data, b"GNU GRUB") || contains(data, b"grub_") {
+        LoaderKind::Grub
+    } else if contains(data, b"bootmgfw.pdb") || contains(data, b"bootmgr.efi.pdb") {
+        LoaderKind::WindowsBootManager
+    } else {
+        LoaderKind::Unknown
+    }
+}
+
+/// 按路径识别引导程序类型（内容无法识别时使用）
+pub fn classify_loader_path(relative_path: &str) -> LoaderKind {
+    let lower = relative_path.to_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+
+    if lower.starts_with("efi/microsoft/") || file_name == "bootmgfw.efi" {
+        LoaderKind::WindowsBootManager
+    } else if file_name.starts_with("systemd-boot") || lower.starts_with("efi/systemd/") {
+        LoaderKind::SystemdBoot
+    } else if file_name.starts_with("refind") || lower.starts_with("efi/refind/") {
+        LoaderKind::Refind
+    } else if file_name.starts_with("shim") {
+        LoaderKind::Shim
+    } else if file_name.starts_with("grub") {
+        LoaderKind::Grub
+    } else {
+        LoaderKind::Unknown
+    }
+}
+
+/// 清点 ESP 上的所有引导程序
+///
+/// `esp_root` 为 ESP 根目录（如 `S:\`）。目录不存在时返回空清单。
+pub fn scan_esp(esp_root: &Path) -> EspInventory {
+    let mut inventory = EspInventory::default();
+    let efi_dir = match find_child_ignore_case(esp_root, "EFI") {
+        Some(dir) => dir,
+        None => return inventory,
+    };
+
+    let mut stack = vec![(efi_dir, 0usize)];
+    while let Some((dir, depth)) = stack.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth < 3 {
+                    stack.push((path, depth + 1));
+                }
+                continue;
+            }
+            let is_efi = path
+                .extension()
+                .map(|e| e.to_string_lossy().eq_ignore_ascii_case("efi"))
+                .unwrap_or(false);
+            if !is_efi {
+                continue;
+            }
+            if let Some(loader) = inspect_loader(esp_root, &path) {
+                inventory.loaders.push(loader);
+            }
+        }
+    }
+
+    inventory
+        .loaders
+        .sort_by_key(|l| l.relative_path.to_lowercase());
+    inventory
+}
+
+/// 识别单个引导程序文件
+fn inspect_loader(esp_root: &Path, path: &Path) -> Option<EspLoader> {
+    let relative_path = path
+        .strip_prefix(esp_root)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    let size_bytes = std::fs::metadata(path).ok()?.len();
+
+    let mut kind = LoaderKind::Unknown;
+    if size_bytes <= MAX_LOADER_SIZE {
+        if let Ok(data) = std::fs::read(path) {
+            kind = classify_loader_bytes(&data);
+        }
+    }
+    // 回退引导程序的路径不代表归属，只能按内容判断
+    let is_fallback = relative_path.eq_ignore_ascii_case(FALLBACK_LOADER);
+    if kind == LoaderKind::Unknown && !is_fallback {
+        kind = classify_loader_path(&relative_path);
+    }
+
+    Some(EspLoader {
+        relative_path,
+        kind,
+        size_bytes,
+    })
+}
+
+/// 在目录中查找名称匹配（忽略大小写）的子项
+fn find_child_ignore_case(dir: &Path, name: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().eq_ignore_ascii_case(name))
+                .unwrap_or(false)
+        })
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// 外部回退引导程序保护
+///
+/// bcdboot 会无条件把 bootmgfw.efi 复制为 `EFI\Boot\bootx64.efi`。
+/// 在调用 bcdboot 前保存属于其他系统的回退引导程序，调用后写回。
+#[derive(Debug)]
+pub struct FallbackGuard {
+    path: PathBuf,
+    backup_path: PathBuf,
+    original: Vec<u8>,
+    loader: EspLoader,
+}
+
+impl FallbackGuard {
+    /// 如果回退引导程序属于其他系统，则备份它
+    ///
+    /// 回退引导程序不存在或属于 Windows 时返回 `Ok(None)`。
+    pub fn protect(esp_root: &Path, inventory: &EspInventory) -> std::io::Result<Option<Self>> {
+        let loader = match inventory.fallback() {
+            Some(l) if !l.kind.is_windows() => l.clone(),
+            _ => return Ok(None),
+        };
+
+        let path = loader
+            .relative_path
+            .split('/')
+            .fold(esp_root.to_path_buf(), |p, c| p.join(c));
+        let original = std::fs::read(&path)?;
+        let backup_path = path.with_file_name(FALLBACK_BACKUP_NAME);
+        std::fs::write(&backup_path, &original)?;
+
+        log::info!(
+            "已备份其他系统的回退引导程序 ({}): {}",
+            loader.kind,
+            backup_path.display()
+        );
+
+        Ok(Some(Self {
+            path,
+            backup_path,
+            original,
+            loader,
+        }))
+    }
+
+    /// 被保护的引导程序
+    pub fn loader(&self) -> &EspLoader {
+        &self.loader
+    }
+
+    /// 写回原回退引导程序并删除备份
+    ///
+    /// 返回 `true` 表示文件曾被覆盖并已恢复。
+    pub fn restore(self) -> std::io::Result<bool> {
+        let current = std::fs::read(&self.path).unwrap_or_default();
+        let overwritten = current != self.original;
+        if overwritten {
+            std::fs::write(&self.path, &self.original)?;
+            log::info!("已恢复其他系统的回退引导程序: {}", self.path.display());
+        }
+        let _ = std::fs::remove_file(&self.backup_path);
+        Ok(overwritten)
+    }
+}
+
+/// BCD 中的一个 Windows 启动加载器条目
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BcdLoaderEntry {
+    /// 标识符 GUID（如 `{xxxxxxxx-...}`）
+    pub identifier: String,
+    /// 设备（如 `partition=C:`）
+    pub device: String,
+    /// 描述
+    pub description: String,
+}
+
+impl BcdLoaderEntry {
+    /// 条目是否指向指定分区（如 `C:`）
+    pub fn is_on_partition(&self, partition: &str) -> bool {
+        let letter = partition.trim_end_matches('\\').trim_end_matches(':');
+        if letter.is_empty() {
+            return false;
+        }
+        let device = self.device.to_uppercase();
+        device.ends_with(&format!("={}:", letter.to_uppercase()))
+    }
+
+    /// 解析 ramdisk 设备（如 `ramdisk=[D:]\LetRecovery_PE\boot.wim,{guid}`）
+    pub fn ramdisk(&self) -> Option<RamdiskDevice> {
+        let (kind, value) = self.device.split_once('=')?;
+        if !kind.trim().eq_ignore_ascii_case("ramdisk") {
+            return None;
+        }
+        let (image, options) = value.rsplit_once(',')?;
+        let (partition, path) = image.trim().strip_prefix('[')?.split_once(']')?;
+        Some(RamdiskDevice {
+            partition: partition.to_string(),
+            path: path.to_string(),
+            options_guid: options.trim().to_string(),
+        })
+    }
+}
+
+/// ramdisk 启动设备
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RamdiskDevice {
+    /// WIM 所在分区（如 `D:`，没有盘符时为 `\Device\HarddiskVolume3`）
+    pub partition: String,
+    /// WIM 在分区内的路径（如 `\LetRecovery_PE\boot.wim`）
+    pub path: String,
+    /// ramdisk 设备选项的 GUID
+    pub options_guid: String,
+}
+
+impl RamdiskDevice {
+    /// WIM 所在分区的根目录，可直接用于文件操作
+    pub fn partition_root(&self) -> String {
+        if self.partition.starts_with('\\') {
+            format!("\\\\?\\GLOBALROOT{}\\", self.partition)
+        } else {
+            format!("{}\\", self.partition.trim_end_matches('\\'))
+        }
+    }
+}
+
+/// 解析 `bcdedit /enum osloader /v` 输出
+///
+/// 兼容英文与中文输出（`identifier` / `标识符`，`description` / `描述`）。
+pub fn parse_bcd_loader_entries(output: &str) -> Vec<BcdLoaderEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<BcdLoaderEntry> = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+        let (key, value) = match trimmed.split_once(char::is_whitespace) {
+            Some((k, v)) => (k.to_lowercase(), v.trim().to_string()),
+            None => continue,
+        };
+
+        match key.as_str() {
+            "identifier" | "标识符" => {
+                if let Some(entry) = current.take() {
+                    entries.push(entry);
+                }
+                current = Some(BcdLoaderEntry {
+                    identifier: value,
+                    ..Default::default()
+                });
+            }
+            "device" | "设备" => {
+                if let Some(entry) = current.as_mut() {
+                    entry.device = value;
+                }
+            }
+            "description" | "描述" => {
+                if let Some(entry) = current.as_mut() {
+                    entry.description = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(entry) = current.take() {
+        entries.push(entry);
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_esp(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("lr_esp_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (rel, data) in files {
+            let path = root.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, data).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_classify_loader_bytes() {
+        assert_eq!(classify_loader_bytes(b"..GNU GRUB  version 2.06.."), LoaderKind::Grub);
+        assert_eq!(classify_loader_bytes(b"xx systemd-boot 252 xx"), LoaderKind::SystemdBoot);
+        assert_eq!(classify_loader_bytes(b"rEFInd Boot Manager"), LoaderKind::Refind);
+        assert_eq!(classify_loader_bytes(b"RSDS....bootmgfw.pdb"), LoaderKind::WindowsBootManager);
+        assert_eq!(classify_loader_bytes(b"nothing here"), LoaderKind::Unknown);
+    }
+
+    #[test]
+    fn test_classify_loader_path() {
+        assert_eq!(classify_loader_path("EFI/ubuntu/grubx64.efi"), LoaderKind::Grub);
+        assert_eq!(classify_loader_path("EFI/ubuntu/shimx64.efi"), LoaderKind::Shim);
+        assert_eq!(classify_loader_path("EFI/refind/refind_x64.efi"), LoaderKind::Refind);
+        assert_eq!(
+            classify_loader_path("EFI/Microsoft/Boot/bootmgfw.efi"),
+            LoaderKind::WindowsBootManager
+        );
+    }
+
+    #[test]
+    fn test_scan_esp_detects_foreign_fallback() {
+        let root = make_esp(
+            "scan",
+            &[
+                ("EFI/Microsoft/Boot/bootmgfw.efi", b"bootmgfw.pdb"),
+                ("EFI/ubuntu/grubx64.efi", b"GNU GRUB"),
+                ("EFI/Boot/bootx64.efi", b"GNU GRUB fallback"),
+            ],
+        );
+
+        let inventory = scan_esp(&root);
+        assert_eq!(inventory.loaders.len(), 3);
+        assert!(inventory.fallback_is_foreign());
+        let foreign = inventory.foreign_loaders();
+        assert_eq!(foreign.len(), 1);
+        assert_eq!(foreign[0].relative_path, "EFI/ubuntu/grubx64.efi");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_fallback_guard_restores_foreign_loader() {
+        let root = make_esp("guard", &[("EFI/Boot/bootx64.efi", b"systemd-boot loader")]);
+        let inventory = scan_esp(&root);
+
+        let guard = FallbackGuard::protect(&root, &inventory).unwrap().unwrap();
+        assert_eq!(guard.loader().kind, LoaderKind::SystemdBoot);
+
+        // 模拟 bcdboot 覆盖回退引导程序
+        let fallback = root.join("EFI").join("Boot").join("bootx64.efi");
+        std::fs::write(&fallback, b"bootmgfw.pdb").unwrap();
+
+        assert!(guard.restore().unwrap());
+        assert_eq!(std::fs::read(&fallback).unwrap(), b"systemd-boot loader");
+        assert!(!root.join("EFI").join("Boot").join(FALLBACK_BACKUP_NAME).exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_fallback_guard_skips_windows_loader() {
+        let root = make_esp("guard_win", &[("EFI/Boot/bootx64.efi", b"bootmgfw.pdb")]);
+        let inventory = scan_esp(&root);
+        assert!(FallbackGuard::protect(&root, &inventory).unwrap().is_none());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_parse_bcd_loader_entries() {
+        let output = "\
+Windows Boot Loader
+-------------------
+identifier              {11111111-1111-1111-1111-111111111111}
+device                  partition=C:
+path                    \\Windows\\system32\\winload.efi
+description             Windows 10
+
+Windows 启动加载器
+-------------------
+标识符                  {22222222-2222-2222-2222-222222222222}
+device                  partition=E:
+description             Windows 11
+";
+        let entries = parse_bcd_loader_entries(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].identifier, "{11111111-1111-1111-1111-111111111111}");
+        assert!(entries[0].is_on_partition("C:"));
+        assert!(!entries[0].is_on_partition("E:"));
+        assert_eq!(entries[1].description, "Windows 11");
+        assert!(entries[1].is_on_partition("E:\\"));
+        assert_eq!(entries[0].ramdisk(), None);
+    }
+
+    #[test]
+    fn test_parse_ramdisk_device() {
+        let entry = BcdLoaderEntry {
+            device: "ramdisk=[D:]\\LetRecovery_PE\\boot.wim,{33333333-3333-3333-3333-333333333333}".to_string(),
+            ..Default::default()
+        };
+        let ramdisk = entry.ramdisk().unwrap();
+        assert_eq!(ramdisk.partition, "D:");
+        assert_eq!(ramdisk.path, "\\LetRecovery_PE\\boot.wim");
+        assert_eq!(ramdisk.options_guid, "{33333333-3333-3333-3333-333333333333}");
+        assert_eq!(ramdisk.partition_root(), "D:\\");
+
+        let entry = BcdLoaderEntry {
+            device: "ramdisk=[\\Device\\HarddiskVolume3]\\LetRecovery_PE\\boot.wim,{guid}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            entry.ramdisk().unwrap().partition_root(),
+            "\\\\?\\GLOBALROOT\\Device\\HarddiskVolume3\\"
+        );
+    }
+}
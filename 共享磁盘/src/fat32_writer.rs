@@ -0,0 +1,763 @@
+//! FAT32 文件写入模块
+//!
+//! 不经过系统挂载，直接在 FAT32 卷（通常是镜像文件中的分区）上创建目录和写入文件：
+//! - 长文件名（LFN）与自动生成的 8.3 短文件名
+//! - 簇从前往后顺序分配，新格式化的卷上文件是连续的
+//! - `flush` 时同步全部 FAT 副本和 FSInfo
+//! - 可读回目录和文件内容，用于校验写入结果
+
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// FAT32 单个文件的最大长度
+pub const FAT32_MAX_FILE_SIZE: u64 = 0xFFFF_FFFF;
+
+const FAT_ENTRY_MASK: u32 = 0x0FFF_FFFF;
+const END_OF_CHAIN: u32 = 0x0FFF_FFFF;
+/// 大于等于此值的 FAT 项表示链结束
+const END_OF_CHAIN_MIN: u32 = 0x0FFF_FFF8;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_ARCHIVE: u8 = 0x20;
+const ATTR_LONG_NAME: u8 = 0x0F;
+const DIR_ENTRY_SIZE: usize = 32;
+/// 每个 LFN 目录项容纳的 UTF-16 字符数
+const LFN_CHARS: usize = 13;
+/// 复制文件数据时每次读取的大小
+const COPY_CHUNK: usize = 1024 * 1024;
+/// 8.3 短文件名允许的特殊字符
+const SHORT_NAME_SPECIAL: &[u8] = b"$%'-_@~`!(){}^#&";
+
+/// 目录项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FatDirEntry {
+    /// 文件名（有长文件名时为长文件名）
+    pub name: String,
+    /// 8.3 短文件名
+    pub short_name: String,
+    pub is_dir: bool,
+    pub first_cluster: u32,
+    pub size: u32,
+}
+
+/// 目录扫描结果
+struct DirScan {
+    /// 目录占用的簇链
+    chain: Vec<u32>,
+    entries: Vec<FatDirEntry>,
+    /// 已使用的短文件名（用于生成不重复的 ~N 名称）
+    short_names: Vec<[u8; 11]>,
+    /// 第一个空闲目录项的序号
+    end_slot: usize,
+}
+
+/// 可写入的 FAT32 卷
+pub struct Fat32Volume<D> {
+    dev: D,
+    sector_size: u64,
+    cluster_size: u64,
+    /// 第一个 FAT 的字节偏移
+    fat_offset: u64,
+    /// 每个 FAT 的字节数
+    fat_bytes: u64,
+    fat_count: u64,
+    /// 数据区的字节偏移
+    data_offset: u64,
+    root_cluster: u32,
+    fs_info_sector: u64,
+    /// 内存中的 FAT（包含簇 0 和簇 1）
+    fat: Vec<u32>,
+    /// 下一次分配时开始查找的簇号
+    next_free: u32,
+    dirty: bool,
+}
+
+impl<D: Read + Write + Seek> Fat32Volume<D> {
+    /// 打开 FAT32 卷并把 FAT 读入内存
+    pub fn open(mut dev: D) -> Result<Self> {
+        let mut boot = [0u8; 512];
+        dev.seek(SeekFrom::Start(0))?;
+        dev.read_exact(&mut boot).context("读取 FAT32 引导扇区失败")?;
+        if &boot[82..90] != b"FAT32   " || boot[510..512] != [0x55, 0xAA] {
+            bail!("不是 FAT32 卷");
+        }
+
+        let sector_size = read_u16(&boot, 11) as u64;
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved_sectors = read_u16(&boot, 14) as u64;
+        let fat_count = boot[16] as u64;
+        let total_sectors = read_u32(&boot, 32) as u64;
+        let fat_sectors = read_u32(&boot, 36) as u64;
+        let root_cluster = read_u32(&boot, 44);
+        let fs_info_sector = read_u16(&boot, 48) as u64;
+
+        if !sector_size.is_power_of_two() || !(512..=4096).contains(&sector_size) {
+            bail!("FAT32 扇区大小无效: {}", sector_size);
+        }
+        if !sectors_per_cluster.is_power_of_two() || !(1..=2).contains(&fat_count) || fat_sectors == 0 {
+            bail!("FAT32 引导扇区参数无效");
+        }
+        let data_sectors = reserved_sectors + fat_count * fat_sectors;
+        if total_sectors <= data_sectors {
+            bail!("FAT32 卷大小无效");
+        }
+        let cluster_count = (total_sectors - data_sectors) / sectors_per_cluster;
+        let fat_bytes = fat_sectors * sector_size;
+        let entries = (cluster_count + 2).min(fat_bytes / 4) as usize;
+        if root_cluster < 2 || root_cluster as usize >= entries {
+            bail!("FAT32 根目录簇号无效: {}", root_cluster);
+        }
+
+        let fat_offset = reserved_sectors * sector_size;
+        let mut raw = vec![0u8; entries * 4];
+        dev.seek(SeekFrom::Start(fat_offset))?;
+        dev.read_exact(&mut raw).context("读取 FAT 表失败")?;
+        let fat = raw
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]) & FAT_ENTRY_MASK)
+            .collect();
+
+        Ok(Self {
+            dev,
+            sector_size,
+            cluster_size: sectors_per_cluster * sector_size,
+            fat_offset,
+            fat_bytes,
+            fat_count,
+            data_offset: data_sectors * sector_size,
+            root_cluster,
+            fs_info_sector,
+            fat,
+            next_free: 2,
+            dirty: false,
+        })
+    }
+
+    /// 簇大小（字节）
+    pub fn cluster_size(&self) -> u64 {
+        self.cluster_size
+    }
+
+    /// 剩余空间（字节）
+    pub fn free_bytes(&self) -> u64 {
+        self.free_clusters() as u64 * self.cluster_size
+    }
+
+    /// 写入一个文件占用的空间（按簇向上取整）
+    pub fn allocation_size(&self, size: u64) -> u64 {
+        size.div_ceil(self.cluster_size) * self.cluster_size
+    }
+
+    /// 逐级创建目录，返回最后一级目录的起始簇
+    pub fn create_dir_all(&mut self, path: &str) -> Result<u32> {
+        let mut cluster = self.root_cluster;
+        for name in split_path(path) {
+            cluster = match self.find_entry(cluster, name)? {
+                Some(entry) if entry.is_dir => entry.first_cluster,
+                Some(_) => bail!("{} 已存在且不是目录", name),
+                None => self.create_dir(cluster, name)?,
+            };
+        }
+        Ok(cluster)
+    }
+
+    /// 写入文件（父目录不存在时自动创建），`size` 为要从 `reader` 读取的字节数
+    pub fn write_file(&mut self, path: &str, reader: &mut dyn Read, size: u64) -> Result<()> {
+        let mut parts = split_path(path);
+        let name = parts.pop().with_context(|| format!("无效的文件路径: {}", path))?;
+        if size > FAT32_MAX_FILE_SIZE {
+            bail!("{} 大小为 {} 字节，超过 FAT32 单文件 4 GB 限制", path, size);
+        }
+        let parent = self.create_dir_all(&parts.join("\\"))?;
+        if self.find_entry(parent, name)?.is_some() {
+            bail!("{} 已存在", path);
+        }
+
+        let cluster_count = size.div_ceil(self.cluster_size) as usize;
+        let clusters = if cluster_count == 0 {
+            Vec::new()
+        } else {
+            self.allocate(cluster_count)
+                .with_context(|| format!("写入 {} 失败", path))?
+        };
+
+        let mut remaining = size;
+        let mut buf = vec![0u8; COPY_CHUNK];
+        for run in contiguous_runs(&clusters) {
+            let run_bytes = (run.len() as u64 * self.cluster_size).min(remaining);
+            self.dev.seek(SeekFrom::Start(self.cluster_offset(run[0])))?;
+            let mut written = 0u64;
+            while written < run_bytes {
+                let n = (run_bytes - written).min(COPY_CHUNK as u64) as usize;
+                reader
+                    .read_exact(&mut buf[..n])
+                    .with_context(|| format!("读取 {} 的源数据失败", path))?;
+                self.dev
+                    .write_all(&buf[..n])
+                    .with_context(|| format!("写入 {} 的数据失败", path))?;
+                written += n as u64;
+            }
+            remaining -= run_bytes;
+        }
+
+        let first = clusters.first().copied().unwrap_or(0);
+        self.add_entry(parent, name, ATTR_ARCHIVE, first, size as u32)
+    }
+
+    /// 列出目录内容（不含 `.` 和 `..`）
+    pub fn read_dir(&mut self, path: &str) -> Result<Vec<FatDirEntry>> {
+        let cluster = self.resolve_dir(path)?;
+        Ok(self.scan_dir(cluster)?.entries)
+    }
+
+    /// 读取文件内容
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>> {
+        let mut parts = split_path(path);
+        let name = parts.pop().with_context(|| format!("无效的文件路径: {}", path))?;
+        let parent = self.resolve_dir(&parts.join("\\"))?;
+        let entry = self
+            .find_entry(parent, name)?
+            .filter(|e| !e.is_dir)
+            .with_context(|| format!("找不到文件: {}", path))?;
+        if entry.size == 0 {
+            return Ok(Vec::new());
+        }
+        let mut data = self.read_chain(entry.first_cluster)?;
+        if data.len() < entry.size as usize {
+            bail!("{} 的簇链长度不足", path);
+        }
+        data.truncate(entry.size as usize);
+        Ok(data)
+    }
+
+    /// 把 FAT 写回全部副本并更新 FSInfo
+    pub fn flush(&mut self) -> Result<()> {
+        if self.dirty {
+            let raw: Vec<u8> = self.fat.iter().flat_map(|v| v.to_le_bytes()).collect();
+            for i in 0..self.fat_count {
+                self.write_at(self.fat_offset + i * self.fat_bytes, &raw)?;
+            }
+
+            let fs_info_offset = self.fs_info_sector * self.sector_size;
+            let mut fs_info = vec![0u8; 512];
+            self.dev.seek(SeekFrom::Start(fs_info_offset))?;
+            self.dev.read_exact(&mut fs_info).context("读取 FSInfo 失败")?;
+            if read_u32(&fs_info, 0) == 0x4161_5252 && read_u32(&fs_info, 484) == 0x6141_7272 {
+                fs_info[488..492].copy_from_slice(&self.free_clusters().to_le_bytes());
+                fs_info[492..496].copy_from_slice(&self.next_free.to_le_bytes());
+                self.write_at(fs_info_offset, &fs_info)?;
+            }
+            self.dirty = false;
+        }
+        self.dev.flush()?;
+        Ok(())
+    }
+
+    /// 取回底层设备（不会自动 flush）
+    pub fn into_inner(self) -> D {
+        self.dev
+    }
+
+    fn create_dir(&mut self, parent: u32, name: &str) -> Result<u32> {
+        let cluster = self.allocate(1)?[0];
+        let (date, time) = dos_timestamp();
+        let mut data = vec![0u8; self.cluster_size as usize];
+        let mut dot = *b".          ";
+        data[..32].copy_from_slice(&short_entry(&dot, ATTR_DIRECTORY, cluster, 0, date, time));
+        dot[1] = b'.';
+        // 父目录是根目录时 `..` 的簇号写 0
+        let parent_ref = if parent == self.root_cluster { 0 } else { parent };
+        data[32..64].copy_from_slice(&short_entry(&dot, ATTR_DIRECTORY, parent_ref, 0, date, time));
+        self.write_at(self.cluster_offset(cluster), &data)?;
+        self.add_entry(parent, name, ATTR_DIRECTORY, cluster, 0)?;
+        Ok(cluster)
+    }
+
+    /// 在目录末尾追加长文件名项和短文件名项
+    fn add_entry(&mut self, dir: u32, name: &str, attr: u8, first_cluster: u32, size: u32) -> Result<()> {
+        validate_name(name)?;
+        let scan = self.scan_dir(dir)?;
+        let (short, needs_lfn) = short_name_for(name, &scan.short_names);
+        let (date, time) = dos_timestamp();
+
+        let mut slots: Vec<[u8; DIR_ENTRY_SIZE]> = Vec::new();
+        if needs_lfn {
+            slots.extend(lfn_entries(name, lfn_checksum(&short)));
+        }
+        slots.push(short_entry(&short, attr, first_cluster, size, date, time));
+
+        // 留出一个空闲项作为目录结束标记
+        let slots_per_cluster = (self.cluster_size as usize) / DIR_ENTRY_SIZE;
+        let mut chain = scan.chain;
+        let needed = scan.end_slot + slots.len() + 1;
+        if needed > chain.len() * slots_per_cluster {
+            let extra = needed.div_ceil(slots_per_cluster) - chain.len();
+            let added = self.allocate(extra)?;
+            let zeros = vec![0u8; self.cluster_size as usize];
+            for &cluster in &added {
+                self.write_at(self.cluster_offset(cluster), &zeros)?;
+            }
+            let last = *chain.last().context("目录簇链为空")?;
+            self.fat[last as usize] = added[0];
+            chain.extend(added);
+        }
+
+        for (i, slot) in slots.iter().enumerate() {
+            let index = scan.end_slot + i;
+            let cluster = chain[index / slots_per_cluster];
+            let offset = self.cluster_offset(cluster) + ((index % slots_per_cluster) * DIR_ENTRY_SIZE) as u64;
+            self.write_at(offset, slot)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_dir(&mut self, path: &str) -> Result<u32> {
+        let mut cluster = self.root_cluster;
+        for name in split_path(path) {
+            cluster = match self.find_entry(cluster, name)? {
+                Some(entry) if entry.is_dir => entry.first_cluster,
+                _ => bail!("找不到目录: {}", path),
+            };
+        }
+        Ok(cluster)
+    }
+
+    fn find_entry(&mut self, dir: u32, name: &str) -> Result<Option<FatDirEntry>> {
+        let upper = name.to_uppercase();
+        Ok(self
+            .scan_dir(dir)?
+            .entries
+            .into_iter()
+            .find(|e| e.name.to_uppercase() == upper || e.short_name == upper))
+    }
+
+    fn scan_dir(&mut self, dir: u32) -> Result<DirScan> {
+        let chain = self.chain(dir)?;
+        let mut data = Vec::with_capacity(chain.len() * self.cluster_size as usize);
+        for &cluster in &chain {
+            let mut buf = vec![0u8; self.cluster_size as usize];
+            self.dev.seek(SeekFrom::Start(self.cluster_offset(cluster)))?;
+            self.dev.read_exact(&mut buf).context("读取目录失败")?;
+            data.extend_from_slice(&buf);
+        }
+
+        let mut entries = Vec::new();
+        let mut short_names = Vec::new();
+        let mut lfn: Vec<(u8, u8, [u16; LFN_CHARS])> = Vec::new();
+        let mut end_slot = data.len() / DIR_ENTRY_SIZE;
+        for (index, slot) in data.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+            match slot[0] {
+                0x00 => {
+                    end_slot = index;
+                    break;
+                }
+                0xE5 => {
+                    lfn.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            let attr = slot[11];
+            if attr & 0x3F == ATTR_LONG_NAME {
+                lfn.push((slot[0] & 0x3F, slot[13], lfn_slot_chars(slot)));
+                continue;
+            }
+            let mut short = [0u8; 11];
+            short.copy_from_slice(&slot[..11]);
+            if short[0] == 0x05 {
+                short[0] = 0xE5;
+            }
+            short_names.push(short);
+            if attr & ATTR_VOLUME_ID != 0 || short[0] == b'.' {
+                lfn.clear();
+                continue;
+            }
+
+            let short_name = format_short_name(&short, 0);
+            let checksum = lfn_checksum(&short);
+            let name = if !lfn.is_empty() && lfn.iter().all(|(_, sum, _)| *sum == checksum) {
+                lfn.sort_by_key(|(ord, _, _)| *ord);
+                let units: Vec<u16> = lfn
+                    .iter()
+                    .flat_map(|(_, _, chars)| chars.iter().copied())
+                    .take_while(|&c| c != 0x0000 && c != 0xFFFF)
+                    .collect();
+                String::from_utf16_lossy(&units)
+            } else {
+                format_short_name(&short, slot[12])
+            };
+            lfn.clear();
+
+            let first_cluster = ((read_u16(slot, 20) as u32) << 16) | read_u16(slot, 26) as u32;
+            entries.push(FatDirEntry {
+                name,
+                short_name,
+                is_dir: attr & ATTR_DIRECTORY != 0,
+                first_cluster,
+                size: read_u32(slot, 28),
+            });
+        }
+
+        Ok(DirScan { chain, entries, short_names, end_slot })
+    }
+
+    fn read_chain(&mut self, first: u32) -> Result<Vec<u8>> {
+        let chain = self.chain(first)?;
+        let mut data = vec![0u8; chain.len() * self.cluster_size as usize];
+        let mut pos = 0usize;
+        for run in contiguous_runs(&chain) {
+            let len = run.len() * self.cluster_size as usize;
+            self.dev.seek(SeekFrom::Start(self.cluster_offset(run[0])))?;
+            self.dev.read_exact(&mut data[pos..pos + len]).context("读取文件数据失败")?;
+            pos += len;
+        }
+        Ok(data)
+    }
+
+    /// 沿 FAT 取得完整簇链
+    fn chain(&self, first: u32) -> Result<Vec<u32>> {
+        let mut chain = Vec::new();
+        let mut cluster = first;
+        loop {
+            if cluster < 2 || cluster as usize >= self.fat.len() || chain.len() >= self.fat.len() {
+                bail!("簇链损坏（簇号 {}）", cluster);
+            }
+            chain.push(cluster);
+            let next = self.fat[cluster as usize];
+            if next >= END_OF_CHAIN_MIN {
+                return Ok(chain);
+            }
+            cluster = next;
+        }
+    }
+
+    /// 分配若干个簇并链接成一条链
+    fn allocate(&mut self, count: usize) -> Result<Vec<u32>> {
+        let mut clusters = Vec::with_capacity(count);
+        let mut cluster = self.next_free.max(2) as usize;
+        while clusters.len() < count && cluster < self.fat.len() {
+            if self.fat[cluster] == 0 {
+                clusters.push(cluster as u32);
+            }
+            cluster += 1;
+        }
+        if clusters.len() < count {
+            bail!(
+                "FAT32 卷空间不足：需要 {} 字节，剩余 {} 字节",
+                count as u64 * self.cluster_size,
+                self.free_bytes()
+            );
+        }
+        for pair in clusters.windows(2) {
+            self.fat[pair[0] as usize] = pair[1];
+        }
+        self.fat[clusters[count - 1] as usize] = END_OF_CHAIN;
+        self.next_free = clusters[count - 1] + 1;
+        self.dirty = true;
+        Ok(clusters)
+    }
+
+    fn free_clusters(&self) -> u32 {
+        self.fat.iter().skip(2).filter(|&&v| v == 0).count() as u32
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_offset + (cluster as u64 - 2) * self.cluster_size
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        self.dev.seek(SeekFrom::Start(offset))?;
+        self.dev
+            .write_all(data)
+            .with_context(|| format!("写入偏移 {} 处 {} 字节失败", offset, data.len()))
+    }
+}
+
+/// 把簇列表拆分为连续的段
+fn contiguous_runs(clusters: &[u32]) -> Vec<&[u32]> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=clusters.len() {
+        if i == clusters.len() || clusters[i] != clusters[i - 1] + 1 {
+            runs.push(&clusters[start..i]);
+            start = i;
+        }
+    }
+    runs
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split(['\\', '/']).filter(|s| !s.is_empty()).collect()
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.encode_utf16().count() > 255
+        || name.chars().any(|c| c < ' ' || "\"*/:<>?\\|".contains(c))
+    {
+        bail!("无效的文件名: {}", name);
+    }
+    Ok(())
+}
+
+fn is_short_name_char(c: u8) -> bool {
+    c.is_ascii_uppercase() || c.is_ascii_digit() || SHORT_NAME_SPECIAL.contains(&c)
+}
+
+/// 生成 8.3 短文件名，返回短文件名和是否需要长文件名项
+fn short_name_for(name: &str, existing: &[[u8; 11]]) -> ([u8; 11], bool) {
+    let (base, ext) = match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos + 1..]),
+        _ => (name, ""),
+    };
+
+    // 已经是合法的大写 8.3 名称时不需要长文件名
+    let exact = |part: &str, max: usize| part.len() <= max && part.bytes().all(is_short_name_char);
+    if !base.is_empty() && exact(base, 8) && exact(ext, 3) && name.matches('.').count() <= 1 {
+        let short = pack_short(base.as_bytes(), ext.as_bytes());
+        if !existing.contains(&short) {
+            return (short, false);
+        }
+    }
+
+    let mut lossy = false;
+    let mut clean = |part: &str| -> Vec<u8> {
+        let mut out = Vec::new();
+        for c in part.chars() {
+            if c == ' ' || c == '.' {
+                lossy = true;
+                continue;
+            }
+            let upper = c.to_ascii_uppercase();
+            if upper.is_ascii() && is_short_name_char(upper as u8) {
+                out.push(upper as u8);
+            } else {
+                lossy = true;
+                out.push(b'_');
+            }
+        }
+        out
+    };
+    let mut base_clean = clean(base);
+    let mut ext_clean = clean(ext);
+    if base_clean.is_empty() {
+        base_clean.push(b'_');
+    }
+    if ext_clean.len() > 3 {
+        ext_clean.truncate(3);
+        lossy = true;
+    }
+
+    // 只是大小写不同时直接使用大写形式（例如 bootmgr -> BOOTMGR）
+    if !lossy && base_clean.len() <= 8 && name.matches('.').count() <= 1 {
+        let short = pack_short(&base_clean, &ext_clean);
+        if !existing.contains(&short) {
+            return (short, true);
+        }
+    }
+
+    for n in 1u32.. {
+        let tail = format!("~{}", n);
+        let keep = base_clean.len().min(8 - tail.len());
+        let mut candidate_base = base_clean[..keep].to_vec();
+        candidate_base.extend_from_slice(tail.as_bytes());
+        let short = pack_short(&candidate_base, &ext_clean);
+        if !existing.contains(&short) {
+            return (short, true);
+        }
+    }
+    unreachable!()
+}
+
+fn pack_short(base: &[u8], ext: &[u8]) -> [u8; 11] {
+    let mut short = [b' '; 11];
+    short[..base.len()].copy_from_slice(base);
+    short[8..8 + ext.len()].copy_from_slice(ext);
+    short
+}
+
+/// 把 11 字节短文件名格式化为 `NAME.EXT`，`case_flags` 为目录项偏移 12 处的小写标记
+fn format_short_name(short: &[u8; 11], case_flags: u8) -> String {
+    let part = |bytes: &[u8], lower: bool| {
+        let text: String = String::from_utf8_lossy(bytes).trim_end().to_string();
+        if lower {
+            text.to_ascii_lowercase()
+        } else {
+            text
+        }
+    };
+    let base = part(&short[..8], case_flags & 0x08 != 0);
+    let ext = part(&short[8..], case_flags & 0x10 != 0);
+    if ext.is_empty() {
+        base
+    } else {
+        format!("{}.{}", base, ext)
+    }
+}
+
+fn lfn_checksum(short: &[u8; 11]) -> u8 {
+    short
+        .iter()
+        .fold(0u8, |sum, &c| ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(c))
+}
+
+/// 生成长文件名项（按磁盘顺序，即最后一段在前）
+fn lfn_entries(name: &str, checksum: u8) -> Vec<[u8; DIR_ENTRY_SIZE]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    let count = units.len().div_ceil(LFN_CHARS);
+    if units.len() < count * LFN_CHARS {
+        units.push(0x0000);
+    }
+    units.resize(count * LFN_CHARS, 0xFFFF);
+
+    (1..=count)
+        .rev()
+        .map(|ord| {
+            let chars = &units[(ord - 1) * LFN_CHARS..ord * LFN_CHARS];
+            let mut slot = [0u8; DIR_ENTRY_SIZE];
+            slot[0] = ord as u8 | if ord == count { 0x40 } else { 0 };
+            slot[11] = ATTR_LONG_NAME;
+            slot[13] = checksum;
+            let offsets = (0..5).map(|i| 1 + i * 2).chain((0..6).map(|i| 14 + i * 2)).chain((0..2).map(|i| 28 + i * 2));
+            for (offset, c) in offsets.zip(chars) {
+                slot[offset..offset + 2].copy_from_slice(&c.to_le_bytes());
+            }
+            slot
+        })
+        .collect()
+}
+
+fn lfn_slot_chars(slot: &[u8]) -> [u16; LFN_CHARS] {
+    let mut chars = [0u16; LFN_CHARS];
+    let offsets = (0..5).map(|i| 1 + i * 2).chain((0..6).map(|i| 14 + i * 2)).chain((0..2).map(|i| 28 + i * 2));
+    for (c, offset) in chars.iter_mut().zip(offsets) {
+        *c = read_u16(slot, offset);
+    }
+    chars
+}
+
+fn short_entry(short: &[u8; 11], attr: u8, first_cluster: u32, size: u32, date: u16, time: u16) -> [u8; DIR_ENTRY_SIZE] {
+    let mut slot = [0u8; DIR_ENTRY_SIZE];
+    slot[..11].copy_from_slice(short);
+    if slot[0] == 0xE5 {
+        slot[0] = 0x05;
+    }
+    slot[11] = attr;
+    slot[14..16].copy_from_slice(&time.to_le_bytes());
+    slot[16..18].copy_from_slice(&date.to_le_bytes());
+    slot[18..20].copy_from_slice(&date.to_le_bytes());
+    slot[20..22].copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes());
+    slot[22..24].copy_from_slice(&time.to_le_bytes());
+    slot[24..26].copy_from_slice(&date.to_le_bytes());
+    slot[26..28].copy_from_slice(&(first_cluster as u16).to_le_bytes());
+    slot[28..32].copy_from_slice(&size.to_le_bytes());
+    slot
+}
+
+/// 当前本地时间的 DOS 日期和时间
+fn dos_timestamp() -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let now = chrono::Local::now();
+    let date = (((now.year() - 1980).clamp(0, 127) as u16) << 9) | ((now.month() as u16) << 5) | now.day() as u16;
+    let time = ((now.hour() as u16) << 11) | ((now.minute() as u16) << 5) | (now.second() / 2) as u16;
+    (date, time)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fat_format::{format_volume, FatFormatOptions, NativeFileSystem, VolumeGeometry};
+    use std::io::Cursor;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn formatted_volume(size: u64) -> Fat32Volume<Cursor<Vec<u8>>> {
+        let mut image = Cursor::new(vec![0u8; size as usize]);
+        let geometry = VolumeGeometry { total_sectors: size / 512, sector_size: 512, hidden_sectors: 2048 };
+        let options = FatFormatOptions {
+            file_system: NativeFileSystem::Fat32,
+            label: "TEST".to_string(),
+            quick: true,
+            cluster_size: None,
+        };
+        format_volume(&mut image, &geometry, &options, &mut |_, _| {}).unwrap();
+        Fat32Volume::open(image).unwrap()
+    }
+
+    #[test]
+    fn test_short_name_generation() {
+        assert_eq!(short_name_for("BOOTMGR", &[]), (*b"BOOTMGR    ", false));
+        assert_eq!(short_name_for("bootmgr", &[]), (*b"BOOTMGR    ", true));
+        assert_eq!(short_name_for("bootmgr.efi", &[]), (*b"BOOTMGR EFI", true));
+        assert_eq!(short_name_for("autorun.inf", &[]).0, *b"AUTORUN INF");
+        assert_eq!(short_name_for("install.swm", &[]).0, *b"INSTALL SWM");
+
+        let (first, _) = short_name_for("Microsoft Windows.txt", &[]);
+        assert_eq!(&first, b"MICROS~1TXT");
+        let (second, _) = short_name_for("Microsoft Office.txt", &[first]);
+        assert_eq!(&second, b"MICROS~2TXT");
+        assert_eq!(short_name_for("中文.dat", &[]).0, *b"__~1    DAT");
+        assert_eq!(short_name_for(".hidden", &[]).0, *b"HIDDEN~1   ");
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mut volume = formatted_volume(64 * MIB);
+        let free_before = volume.free_bytes();
+        let big: Vec<u8> = (0..3 * MIB as usize + 123).map(|i| (i % 251) as u8).collect();
+        let long_name = "a file with a rather long name that needs several entries.bin";
+
+        volume.write_file("bootmgr", &mut &b"BOOTMGR"[..], 7).unwrap();
+        volume.write_file("efi/boot/bootx64.efi", &mut &b"EFI"[..], 3).unwrap();
+        volume.write_file(r"sources\install.wim", &mut big.as_slice(), big.len() as u64).unwrap();
+        volume.write_file(&format!("sources/{}", long_name), &mut &b""[..], 0).unwrap();
+        assert!(volume.write_file("BOOTMGR", &mut &b"x"[..], 1).is_err());
+        volume.flush().unwrap();
+
+        // 重新打开后内容一致
+        let mut volume = Fat32Volume::open(volume.into_inner()).unwrap();
+        assert_eq!(volume.read_file("BOOTMGR").unwrap(), b"BOOTMGR");
+        assert_eq!(volume.read_file("EFI/Boot/BOOTX64.EFI").unwrap(), b"EFI");
+        assert_eq!(volume.read_file("sources/install.wim").unwrap(), big);
+        assert!(volume.read_file(&format!("sources/{}", long_name)).unwrap().is_empty());
+
+        let root = volume.read_dir("").unwrap();
+        let names: Vec<&str> = root.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["bootmgr", "efi", "sources"]);
+        assert_eq!(root[0].short_name, "BOOTMGR");
+        let sources = volume.read_dir("sources").unwrap();
+        assert_eq!(sources[1].name, long_name);
+
+        let used = free_before - volume.free_bytes();
+        assert_eq!(
+            used,
+            volume.allocation_size(7) + volume.allocation_size(3) + volume.allocation_size(big.len() as u64)
+                + 3 * volume.cluster_size()
+        );
+    }
+
+    #[test]
+    fn test_directory_grows_beyond_one_cluster() {
+        let mut volume = formatted_volume(64 * MIB);
+        let per_cluster = volume.cluster_size() as usize / DIR_ENTRY_SIZE;
+        for i in 0..per_cluster {
+            volume.write_file(&format!("dir/file number {}.txt", i), &mut &b"1"[..], 1).unwrap();
+        }
+        volume.flush().unwrap();
+        let mut volume = Fat32Volume::open(volume.into_inner()).unwrap();
+        let entries = volume.read_dir("dir").unwrap();
+        assert_eq!(entries.len(), per_cluster);
+        assert_eq!(entries.last().unwrap().name, format!("file number {}.txt", per_cluster - 1));
+        assert_eq!(volume.read_file(&format!("dir/file number {}.txt", per_cluster - 1)).unwrap(), b"1");
+    }
+}
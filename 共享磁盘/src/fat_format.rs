@@ -0,0 +1,884 @@
+//! FAT32 / exFAT 原生格式化模块
+//!
+//! Windows 格式化接口拒绝在 32 GB 以上的卷创建 FAT32，这里直接写入文件系统结构：
+//! - FAT32：最大 2 TiB（512 字节扇区），自动选择簇大小，写入 FSInfo 和备份引导扇区
+//! - exFAT：写入主/备份引导区、FAT、分配位图、大写表和根目录
+//!
+//! 目标可以是已锁定的卷设备，也可以是镜像文件。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::io::{Seek, SeekFrom, Write};
+
+/// FAT32 最少簇数（少于此值会被识别为 FAT16）
+const FAT32_MIN_CLUSTERS: u64 = 65_525;
+/// FAT32 最多簇数
+const FAT32_MAX_CLUSTERS: u64 = 0x0FFF_FFF4;
+/// exFAT 最多簇数
+const EXFAT_MAX_CLUSTERS: u64 = 0xFFFF_FFF5;
+/// FAT32 保留扇区数（不含对齐填充）
+const FAT32_RESERVED_SECTORS: u64 = 32;
+/// FAT32 备份引导扇区位置
+const FAT32_BACKUP_BOOT_SECTOR: u64 = 6;
+/// exFAT 主引导区扇区数（备份引导区紧随其后）
+const EXFAT_BOOT_REGION_SECTORS: u64 = 12;
+/// 清零时每次写入的大小
+const ZERO_CHUNK: usize = 1024 * 1024;
+
+/// 原生格式化支持的文件系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeFileSystem {
+    Fat32,
+    ExFat,
+}
+
+impl NativeFileSystem {
+    /// 从文件系统名称解析（不区分大小写）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "FAT32" => Some(NativeFileSystem::Fat32),
+            "EXFAT" => Some(NativeFileSystem::ExFat),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NativeFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NativeFileSystem::Fat32 => write!(f, "FAT32"),
+            NativeFileSystem::ExFat => write!(f, "exFAT"),
+        }
+    }
+}
+
+/// 格式化选项
+#[derive(Debug, Clone)]
+pub struct FatFormatOptions {
+    pub file_system: NativeFileSystem,
+    /// 卷标（FAT32 最多 11 个字符，exFAT 最多 11 个 UTF-16 字符）
+    pub label: String,
+    /// 快速格式化（只写入文件系统结构，不清零数据区）
+    pub quick: bool,
+    /// 簇大小（字节），None 时按卷大小自动选择
+    pub cluster_size: Option<u32>,
+}
+
+/// 卷的几何信息
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeGeometry {
+    /// 卷的总扇区数
+    pub total_sectors: u64,
+    pub sector_size: u32,
+    /// 卷在磁盘上的起始扇区（写入 BPB 的隐藏扇区数）
+    pub hidden_sectors: u64,
+}
+
+/// 格式化结果
+#[derive(Debug, Clone)]
+pub struct FormatSummary {
+    pub file_system: NativeFileSystem,
+    pub cluster_size: u32,
+    pub cluster_count: u64,
+    /// 卷序列号
+    pub serial: u32,
+}
+
+/// FAT32 布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fat32Layout {
+    pub sector_size: u32,
+    pub sectors_per_cluster: u32,
+    /// 保留扇区数（已包含使数据区按簇对齐的填充）
+    pub reserved_sectors: u32,
+    /// 每个 FAT 的扇区数
+    pub fat_sectors: u32,
+    pub cluster_count: u64,
+    pub total_sectors: u32,
+}
+
+impl Fat32Layout {
+    /// 按 Microsoft 的默认规则选择簇大小
+    pub fn default_cluster_size(volume_bytes: u64) -> u32 {
+        const MB: u64 = 1024 * 1024;
+        match volume_bytes {
+            b if b < 64 * MB => 512,
+            b if b < 128 * MB => 1024,
+            b if b < 256 * MB => 2048,
+            b if b <= 8 * 1024 * MB => 4096,
+            b if b <= 16 * 1024 * MB => 8192,
+            b if b <= 32 * 1024 * MB => 16384,
+            _ => 32768,
+        }
+    }
+
+    /// 计算布局，`cluster_size` 为空时自动选择（簇数不足时自动减小簇大小）
+    pub fn compute(geometry: &VolumeGeometry, cluster_size: Option<u32>) -> Result<Self> {
+        let ss = geometry.sector_size as u64;
+        check_sector_size(geometry.sector_size)?;
+        if geometry.total_sectors > u32::MAX as u64 {
+            bail!(
+                "卷大小 {:.1} GB 超出 FAT32 上限（{} 字节扇区最大 {} GB）",
+                (geometry.total_sectors * ss) as f64 / 1e9,
+                ss,
+                (u32::MAX as u64 * ss) >> 30
+            );
+        }
+
+        let mut cluster = match cluster_size {
+            Some(size) => size,
+            None => Self::default_cluster_size(geometry.total_sectors * ss),
+        }
+        .max(geometry.sector_size);
+        check_cluster_size(cluster, geometry.sector_size, 64 * 1024)?;
+
+        loop {
+            let layout = Self::with_cluster_size(geometry, cluster / geometry.sector_size);
+            match layout {
+                Some(layout) if layout.cluster_count >= FAT32_MIN_CLUSTERS => {
+                    if layout.cluster_count > FAT32_MAX_CLUSTERS {
+                        bail!("簇大小 {} 字节时簇数超过 FAT32 上限，请选择更大的簇", cluster);
+                    }
+                    return Ok(layout);
+                }
+                _ if cluster_size.is_none() && cluster > geometry.sector_size => cluster /= 2,
+                _ => bail!(
+                    "卷太小（{} MB），无法以 {} 字节簇格式化为 FAT32",
+                    geometry.total_sectors * ss / 1024 / 1024,
+                    cluster
+                ),
+            }
+        }
+    }
+
+    fn with_cluster_size(geometry: &VolumeGeometry, spc: u32) -> Option<Self> {
+        let total = geometry.total_sectors;
+        let ss = geometry.sector_size as u64;
+        let spc64 = spc as u64;
+
+        // FAT 大小与簇数相互依赖，迭代到 FAT 足够容纳所有簇
+        let mut fat: u64 = 1;
+        loop {
+            let data = total.checked_sub(FAT32_RESERVED_SECTORS + 2 * fat)?;
+            let need = ((data / spc64 + 2) * 4).div_ceil(ss);
+            if need <= fat {
+                break;
+            }
+            fat = need;
+        }
+
+        // 填充保留区使数据区按簇对齐
+        let pad = (spc64 - (FAT32_RESERVED_SECTORS + 2 * fat) % spc64) % spc64;
+        let reserved = FAT32_RESERVED_SECTORS + pad;
+        let data = total.checked_sub(reserved + 2 * fat)?;
+        Some(Self {
+            sector_size: geometry.sector_size,
+            sectors_per_cluster: spc,
+            reserved_sectors: reserved as u32,
+            fat_sectors: fat as u32,
+            cluster_count: data / spc64,
+            total_sectors: total as u32,
+        })
+    }
+
+    /// 数据区起始扇区（簇 2）
+    pub fn data_start(&self) -> u64 {
+        self.reserved_sectors as u64 + 2 * self.fat_sectors as u64
+    }
+
+    pub fn cluster_size(&self) -> u32 {
+        self.sector_size * self.sectors_per_cluster
+    }
+}
+
+/// exFAT 布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExFatLayout {
+    pub sector_size: u32,
+    pub sectors_per_cluster: u32,
+    pub fat_offset: u32,
+    pub fat_length: u32,
+    pub cluster_heap_offset: u32,
+    pub cluster_count: u32,
+    pub volume_length: u64,
+}
+
+impl ExFatLayout {
+    /// 按 Microsoft 的默认规则选择簇大小
+    pub fn default_cluster_size(volume_bytes: u64) -> u32 {
+        const MB: u64 = 1024 * 1024;
+        match volume_bytes {
+            b if b <= 256 * MB => 4096,
+            b if b <= 32 * 1024 * MB => 32 * 1024,
+            _ => 128 * 1024,
+        }
+    }
+
+    pub fn compute(geometry: &VolumeGeometry, cluster_size: Option<u32>) -> Result<Self> {
+        let ss = geometry.sector_size as u64;
+        check_sector_size(geometry.sector_size)?;
+        let cluster = cluster_size
+            .unwrap_or_else(|| Self::default_cluster_size(geometry.total_sectors * ss))
+            .max(geometry.sector_size);
+        check_cluster_size(cluster, geometry.sector_size, 32 * 1024 * 1024)?;
+        let spc = (cluster / geometry.sector_size) as u64;
+
+        let total = geometry.total_sectors;
+        let fat_offset = (2 * EXFAT_BOOT_REGION_SECTORS).next_multiple_of(spc);
+        let mut fat_length: u64 = 1;
+        let (heap, count) = loop {
+            let heap = (fat_offset + fat_length).next_multiple_of(spc);
+            let Some(data) = total.checked_sub(heap) else {
+                bail!("卷太小，无法格式化为 exFAT");
+            };
+            let count = data / spc;
+            let need = ((count + 2) * 4).div_ceil(ss);
+            if need <= fat_length {
+                break (heap, count);
+            }
+            fat_length = need;
+        };
+
+        // 至少需要容纳分配位图、大写表和根目录
+        if count < 16 {
+            bail!("卷太小，无法格式化为 exFAT");
+        }
+        if count > EXFAT_MAX_CLUSTERS {
+            bail!("簇大小 {} 字节时簇数超过 exFAT 上限，请选择更大的簇", cluster);
+        }
+        Ok(Self {
+            sector_size: geometry.sector_size,
+            sectors_per_cluster: spc as u32,
+            fat_offset: fat_offset as u32,
+            fat_length: fat_length as u32,
+            cluster_heap_offset: heap as u32,
+            cluster_count: count as u32,
+            volume_length: total,
+        })
+    }
+
+    pub fn cluster_size(&self) -> u32 {
+        self.sector_size * self.sectors_per_cluster
+    }
+
+    /// 指定簇的起始扇区
+    fn cluster_sector(&self, cluster: u32) -> u64 {
+        self.cluster_heap_offset as u64 + (cluster as u64 - 2) * self.sectors_per_cluster as u64
+    }
+}
+
+/// 格式化卷设备或镜像文件
+///
+/// `progress` 接收 0-100 的进度和当前步骤描述
+pub fn format_volume<D: Write + Seek>(
+    dev: &mut D,
+    geometry: &VolumeGeometry,
+    options: &FatFormatOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<FormatSummary> {
+    let serial = volume_serial();
+    match options.file_system {
+        NativeFileSystem::Fat32 => format_fat32(dev, geometry, options, serial, progress),
+        NativeFileSystem::ExFat => format_exfat(dev, geometry, options, serial, progress),
+    }
+}
+
+fn format_fat32<D: Write + Seek>(
+    dev: &mut D,
+    geometry: &VolumeGeometry,
+    options: &FatFormatOptions,
+    serial: u32,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<FormatSummary> {
+    let layout = Fat32Layout::compute(geometry, options.cluster_size)?;
+    let ss = layout.sector_size as u64;
+    let cluster_bytes = layout.cluster_size() as u64;
+    progress(
+        5,
+        &format!(
+            "FAT32: 簇大小 {} 字节, {} 个簇, FAT {} 扇区",
+            cluster_bytes, layout.cluster_count, layout.fat_sectors
+        ),
+    );
+
+    // 数据区（完整格式化时）
+    if !options.quick {
+        let start = layout.data_start() * ss;
+        let len = layout.cluster_count * cluster_bytes;
+        zero_range(dev, start, len, &mut |done| {
+            progress(10 + (done * 75 / len.max(1)) as u8, "正在清零数据区...")
+        })?;
+    }
+
+    // 保留区和两个 FAT
+    progress(85, "正在写入 FAT 表...");
+    zero_range(dev, 0, layout.data_start() * ss, &mut |_| {})?;
+    let mut fat_head = vec![0u8; ss as usize];
+    fat_head[0..4].copy_from_slice(&0x0FFF_FFF8u32.to_le_bytes());
+    fat_head[4..8].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+    // 簇 2 是根目录
+    fat_head[8..12].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+    for i in 0..2u64 {
+        write_at(dev, (layout.reserved_sectors as u64 + i * layout.fat_sectors as u64) * ss, &fat_head)?;
+    }
+
+    // 根目录
+    progress(90, "正在写入根目录...");
+    let label = fat_label(&options.label);
+    let mut root = vec![0u8; cluster_bytes as usize];
+    if let Some(ref label) = label {
+        root[0..11].copy_from_slice(label);
+        root[11] = 0x08;
+    }
+    write_at(dev, layout.data_start() * ss, &root)?;
+
+    // 引导扇区、FSInfo 及其备份
+    progress(95, "正在写入引导扇区...");
+    let boot = fat32_boot_sector(&layout, geometry.hidden_sectors, serial, label.as_ref());
+    let mut fs_info = vec![0u8; ss as usize];
+    fs_info[0..4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+    fs_info[484..488].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+    fs_info[488..492].copy_from_slice(&((layout.cluster_count - 1) as u32).to_le_bytes());
+    fs_info[492..496].copy_from_slice(&3u32.to_le_bytes());
+    fs_info[508..512].copy_from_slice(&0xAA55_0000u32.to_le_bytes());
+    let mut third = vec![0u8; ss as usize];
+    third[510..512].copy_from_slice(&[0x55, 0xAA]);
+    let mut region = boot;
+    region.extend_from_slice(&fs_info);
+    region.extend_from_slice(&third);
+    write_at(dev, FAT32_BACKUP_BOOT_SECTOR * ss, &region)?;
+    write_at(dev, 0, &region)?;
+    dev.flush()?;
+
+    progress(100, "FAT32 格式化完成");
+    Ok(FormatSummary {
+        file_system: NativeFileSystem::Fat32,
+        cluster_size: layout.cluster_size(),
+        cluster_count: layout.cluster_count,
+        serial,
+    })
+}
+
+fn fat32_boot_sector(layout: &Fat32Layout, hidden_sectors: u64, serial: u32, label: Option<&[u8; 11]>) -> Vec<u8> {
+    let mut sector = vec![0u8; layout.sector_size as usize];
+    sector[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+    sector[3..11].copy_from_slice(b"MSWIN4.1");
+    sector[11..13].copy_from_slice(&(layout.sector_size as u16).to_le_bytes());
+    sector[13] = layout.sectors_per_cluster as u8;
+    sector[14..16].copy_from_slice(&(layout.reserved_sectors as u16).to_le_bytes());
+    sector[16] = 2;
+    sector[21] = 0xF8;
+    sector[24..26].copy_from_slice(&63u16.to_le_bytes());
+    sector[26..28].copy_from_slice(&255u16.to_le_bytes());
+    sector[28..32].copy_from_slice(&(hidden_sectors.min(u32::MAX as u64) as u32).to_le_bytes());
+    sector[32..36].copy_from_slice(&layout.total_sectors.to_le_bytes());
+    sector[36..40].copy_from_slice(&layout.fat_sectors.to_le_bytes());
+    sector[44..48].copy_from_slice(&2u32.to_le_bytes());
+    sector[48..50].copy_from_slice(&1u16.to_le_bytes());
+    sector[50..52].copy_from_slice(&(FAT32_BACKUP_BOOT_SECTOR as u16).to_le_bytes());
+    sector[64] = 0x80;
+    sector[66] = 0x29;
+    sector[67..71].copy_from_slice(&serial.to_le_bytes());
+    sector[71..82].copy_from_slice(label.unwrap_or(b"NO NAME    "));
+    sector[82..90].copy_from_slice(b"FAT32   ");
+    let code = not_bootable_code(0x5A);
+    sector[0x5A..0x5A + code.len()].copy_from_slice(&code);
+    sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+    sector
+}
+
+fn format_exfat<D: Write + Seek>(
+    dev: &mut D,
+    geometry: &VolumeGeometry,
+    options: &FatFormatOptions,
+    serial: u32,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<FormatSummary> {
+    let layout = ExFatLayout::compute(geometry, options.cluster_size)?;
+    let ss = layout.sector_size as u64;
+    let cluster_bytes = layout.cluster_size() as u64;
+    progress(
+        5,
+        &format!("exFAT: 簇大小 {} 字节, {} 个簇", cluster_bytes, layout.cluster_count),
+    );
+
+    if !options.quick {
+        let start = layout.cluster_heap_offset as u64 * ss;
+        let len = layout.cluster_count as u64 * cluster_bytes;
+        zero_range(dev, start, len, &mut |done| {
+            progress(10 + (done * 75 / len.max(1)) as u8, "正在清零数据区...")
+        })?;
+    }
+
+    // 簇堆开头依次为分配位图、大写表和根目录
+    let bitmap_len = (layout.cluster_count as u64).div_ceil(8);
+    let upcase = upcase_table();
+    let bitmap_clusters = bitmap_len.div_ceil(cluster_bytes) as u32;
+    let upcase_clusters = (upcase.len() as u64).div_ceil(cluster_bytes) as u32;
+    let bitmap_cluster = 2;
+    let upcase_cluster = bitmap_cluster + bitmap_clusters;
+    let root_cluster = upcase_cluster + upcase_clusters;
+    let used = bitmap_clusters + upcase_clusters + 1;
+
+    progress(85, "正在写入 FAT 表...");
+    zero_range(dev, 0, layout.cluster_heap_offset as u64 * ss, &mut |_| {})?;
+    let mut fat = vec![0u8; ((used as usize + 2) * 4).next_multiple_of(ss as usize)];
+    fat[0..4].copy_from_slice(&0xFFFF_FFF8u32.to_le_bytes());
+    fat[4..8].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+    for (first, count) in [(bitmap_cluster, bitmap_clusters), (upcase_cluster, upcase_clusters), (root_cluster, 1)] {
+        for cluster in first..first + count {
+            let next = if cluster + 1 == first + count { 0xFFFF_FFFF } else { cluster + 1 };
+            let off = cluster as usize * 4;
+            fat[off..off + 4].copy_from_slice(&next.to_le_bytes());
+        }
+    }
+    write_at(dev, layout.fat_offset as u64 * ss, &fat)?;
+
+    progress(90, "正在写入分配位图、大写表和根目录...");
+    let mut bitmap = vec![0u8; (bitmap_clusters as u64 * cluster_bytes) as usize];
+    for bit in 0..used as usize {
+        bitmap[bit / 8] |= 1 << (bit % 8);
+    }
+    write_at(dev, layout.cluster_sector(bitmap_cluster) * ss, &bitmap)?;
+
+    let mut upcase_data = upcase.clone();
+    upcase_data.resize((upcase_clusters as u64 * cluster_bytes) as usize, 0);
+    write_at(dev, layout.cluster_sector(upcase_cluster) * ss, &upcase_data)?;
+
+    let mut root = vec![0u8; cluster_bytes as usize];
+    let label: Vec<u16> = options.label.trim().encode_utf16().take(11).collect();
+    root[0] = if label.is_empty() { 0x03 } else { 0x83 };
+    root[1] = label.len() as u8;
+    for (i, c) in label.iter().enumerate() {
+        root[2 + i * 2..4 + i * 2].copy_from_slice(&c.to_le_bytes());
+    }
+    root[32] = 0x81;
+    root[52..56].copy_from_slice(&bitmap_cluster.to_le_bytes());
+    root[56..64].copy_from_slice(&bitmap_len.to_le_bytes());
+    root[64] = 0x82;
+    root[68..72].copy_from_slice(&exfat_checksum(&upcase, &[]).to_le_bytes());
+    root[84..88].copy_from_slice(&upcase_cluster.to_le_bytes());
+    root[88..96].copy_from_slice(&(upcase.len() as u64).to_le_bytes());
+    write_at(dev, layout.cluster_sector(root_cluster) * ss, &root)?;
+
+    // 主引导区和备份引导区
+    progress(95, "正在写入引导区...");
+    let region = exfat_boot_region(&layout, geometry.hidden_sectors, serial, root_cluster);
+    write_at(dev, EXFAT_BOOT_REGION_SECTORS * ss, &region)?;
+    write_at(dev, 0, &region)?;
+    dev.flush()?;
+
+    progress(100, "exFAT 格式化完成");
+    Ok(FormatSummary {
+        file_system: NativeFileSystem::ExFat,
+        cluster_size: layout.cluster_size(),
+        cluster_count: layout.cluster_count as u64,
+        serial,
+    })
+}
+
+/// 生成 exFAT 引导区（引导扇区、8 个扩展引导扇区、OEM 参数、保留扇区和校验和扇区）
+fn exfat_boot_region(layout: &ExFatLayout, hidden_sectors: u64, serial: u32, root_cluster: u32) -> Vec<u8> {
+    let ss = layout.sector_size as usize;
+    let mut region = vec![0u8; ss * EXFAT_BOOT_REGION_SECTORS as usize];
+
+    let boot = &mut region[..ss];
+    boot[0..3].copy_from_slice(&[0xEB, 0x76, 0x90]);
+    boot[3..11].copy_from_slice(b"EXFAT   ");
+    boot[64..72].copy_from_slice(&hidden_sectors.to_le_bytes());
+    boot[72..80].copy_from_slice(&layout.volume_length.to_le_bytes());
+    boot[80..84].copy_from_slice(&layout.fat_offset.to_le_bytes());
+    boot[84..88].copy_from_slice(&layout.fat_length.to_le_bytes());
+    boot[88..92].copy_from_slice(&layout.cluster_heap_offset.to_le_bytes());
+    boot[92..96].copy_from_slice(&layout.cluster_count.to_le_bytes());
+    boot[96..100].copy_from_slice(&root_cluster.to_le_bytes());
+    boot[100..104].copy_from_slice(&serial.to_le_bytes());
+    boot[104..106].copy_from_slice(&0x0100u16.to_le_bytes());
+    boot[108] = layout.sector_size.trailing_zeros() as u8;
+    boot[109] = layout.sectors_per_cluster.trailing_zeros() as u8;
+    boot[110] = 1;
+    boot[111] = 0x80;
+    let code = not_bootable_code(120);
+    boot[120..120 + code.len()].copy_from_slice(&code);
+    boot[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+    for i in 1..=8 {
+        region[(i + 1) * ss - 2..(i + 1) * ss].copy_from_slice(&[0x55, 0xAA]);
+    }
+
+    let checksum = exfat_checksum(&region[..11 * ss], &[106, 107, 112]);
+    for chunk in region[11 * ss..].chunks_mut(4) {
+        chunk.copy_from_slice(&checksum.to_le_bytes());
+    }
+    region
+}
+
+/// exFAT 校验和（引导区和大写表共用，`skip` 为不参与计算的字节偏移）
+fn exfat_checksum(data: &[u8], skip: &[usize]) -> u32 {
+    data.iter().enumerate().fold(0u32, |sum, (i, &b)| {
+        if skip.contains(&i) {
+            sum
+        } else {
+            sum.rotate_right(1).wrapping_add(b as u32)
+        }
+    })
+}
+
+/// 生成压缩格式的 exFAT 大写表：连续的恒等映射用 0xFFFF + 长度表示
+fn upcase_table() -> Vec<u8> {
+    let upper = |c: u32| -> u16 {
+        char::from_u32(c)
+            .and_then(|ch| {
+                let mut up = ch.to_uppercase();
+                match (up.next(), up.next()) {
+                    (Some(u), None) if (u as u32) <= 0xFFFF => Some(u as u16),
+                    _ => None,
+                }
+            })
+            .unwrap_or(c as u16)
+    };
+
+    let mut entries: Vec<u16> = Vec::new();
+    let mut c: u32 = 0;
+    while c <= 0xFFFF {
+        let run = (c..=0xFFFF).take_while(|&x| upper(x) == x as u16).count() as u32;
+        if run >= 2 || (run == 1 && c == 0xFFFF) {
+            entries.push(0xFFFF);
+            entries.push(run.min(0xFFFF) as u16);
+            c += run.min(0xFFFF);
+        } else {
+            entries.push(upper(c));
+            c += 1;
+        }
+    }
+    entries.iter().flat_map(|e| e.to_le_bytes()).collect()
+}
+
+/// 非引导卷的引导代码：显示提示并停机
+fn not_bootable_code(code_offset: usize) -> Vec<u8> {
+    const MESSAGE: &[u8] = b"This is not a bootable disk.\r\n\0";
+    let message_addr = (0x7C00 + code_offset + 25) as u16;
+    let mut code = vec![
+        0xFC, // cld
+        0x31, 0xC0, // xor ax, ax
+        0x8E, 0xD8, // mov ds, ax
+        0xBE, 0x00, 0x00, // mov si, message
+        0xAC, // lodsb
+        0x84, 0xC0, // test al, al
+        0x74, 0x09, // jz halt
+        0xB4, 0x0E, // mov ah, 0x0E
+        0xBB, 0x07, 0x00, // mov bx, 7
+        0xCD, 0x10, // int 0x10
+        0xEB, 0xF2, // jmp lodsb
+        0xF4, // halt: hlt
+        0xEB, 0xFD, // jmp halt
+    ];
+    code[6..8].copy_from_slice(&message_addr.to_le_bytes());
+    code.extend_from_slice(MESSAGE);
+    code
+}
+
+/// 转换为 FAT 短卷标（大写 ASCII，11 字节，空格填充），空卷标返回 None
+fn fat_label(label: &str) -> Option<[u8; 11]> {
+    const ALLOWED: &[u8] = b"!#$%&'()-@^_`{}~ ";
+    let label = label.trim();
+    if label.is_empty() {
+        return None;
+    }
+    let mut result = [b' '; 11];
+    for (slot, c) in result.iter_mut().zip(label.chars()) {
+        let b = c.to_ascii_uppercase();
+        *slot = if b.is_ascii_alphanumeric() || (b.is_ascii() && ALLOWED.contains(&(b as u8))) {
+            b as u8
+        } else {
+            b'_'
+        };
+    }
+    Some(result)
+}
+
+fn check_sector_size(sector_size: u32) -> Result<()> {
+    if !sector_size.is_power_of_two() || !(512..=4096).contains(&sector_size) {
+        bail!("不支持的扇区大小: {}", sector_size);
+    }
+    Ok(())
+}
+
+fn check_cluster_size(cluster: u32, sector_size: u32, max: u32) -> Result<()> {
+    if !cluster.is_power_of_two() || cluster < sector_size || cluster > max {
+        bail!("无效的簇大小: {} 字节", cluster);
+    }
+    Ok(())
+}
+
+/// 根据当前时间生成卷序列号
+fn volume_serial() -> u32 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    (now.as_secs() as u32).rotate_left(16) ^ now.subsec_nanos()
+}
+
+fn write_at<D: Write + Seek>(dev: &mut D, offset: u64, data: &[u8]) -> Result<()> {
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.write_all(data)
+        .with_context(|| format!("写入偏移 {} 处 {} 字节失败", offset, data.len()))
+}
+
+/// 把指定范围清零，`on_progress` 接收已写入的字节数
+fn zero_range<D: Write + Seek>(
+    dev: &mut D,
+    offset: u64,
+    len: u64,
+    on_progress: &mut dyn FnMut(u64),
+) -> Result<()> {
+    let zeros = vec![0u8; ZERO_CHUNK];
+    dev.seek(SeekFrom::Start(offset))?;
+    let mut done = 0u64;
+    while done < len {
+        let n = (len - done).min(ZERO_CHUNK as u64) as usize;
+        dev.write_all(&zeros[..n])
+            .with_context(|| format!("清零偏移 {} 处失败", offset + done))?;
+        done += n as u64;
+        on_progress(done);
+    }
+    Ok(())
+}
+
+/// 格式化指定盘符的卷（锁定并卸载卷后直接写入）
+#[cfg(windows)]
+pub fn format_drive(
+    letter: char,
+    options: &FatFormatOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<FormatSummary> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Ioctl::{
+        DISK_GEOMETRY, FSCTL_DISMOUNT_VOLUME, FSCTL_LOCK_VOLUME, FSCTL_UNLOCK_VOLUME,
+        GET_LENGTH_INFORMATION, IOCTL_DISK_GET_DRIVE_GEOMETRY, IOCTL_DISK_GET_LENGTH_INFO,
+        IOCTL_DISK_GET_PARTITION_INFO_EX, PARTITION_INFORMATION_EX,
+    };
+    use windows::Win32::System::IO::DeviceIoControl;
+
+    /// 调用无输入的 IOCTL，输出写入 `out`
+    unsafe fn ioctl<T>(handle: HANDLE, code: u32, out: Option<&mut T>) -> windows::core::Result<()> {
+        let mut bytes_returned = 0u32;
+        let (ptr, size) = match out {
+            Some(out) => (Some(out as *mut T as *mut _), std::mem::size_of::<T>() as u32),
+            None => (None, 0),
+        };
+        DeviceIoControl(handle, code, None, 0, ptr, size, Some(&mut bytes_returned), None)
+    }
+
+    let letter = letter.to_ascii_uppercase();
+    let mut volume = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!(r"\\.\{}:", letter))
+        .with_context(|| format!("打开卷 {}: 失败", letter))?;
+    let handle = HANDLE(volume.as_raw_handle() as _);
+
+    let geometry = unsafe {
+        let mut length = GET_LENGTH_INFORMATION::default();
+        ioctl(handle, IOCTL_DISK_GET_LENGTH_INFO, Some(&mut length)).context("获取卷大小失败")?;
+        let mut disk_geometry = DISK_GEOMETRY::default();
+        ioctl(handle, IOCTL_DISK_GET_DRIVE_GEOMETRY, Some(&mut disk_geometry)).context("获取扇区大小失败")?;
+        let mut partition = PARTITION_INFORMATION_EX::default();
+        let sector_size = disk_geometry.BytesPerSector.max(512);
+        let hidden_sectors = match ioctl(handle, IOCTL_DISK_GET_PARTITION_INFO_EX, Some(&mut partition)) {
+            Ok(()) => partition.StartingOffset as u64 / sector_size as u64,
+            Err(_) => 0,
+        };
+        VolumeGeometry {
+            total_sectors: length.Length as u64 / sector_size as u64,
+            sector_size,
+            hidden_sectors,
+        }
+    };
+
+    unsafe {
+        ioctl::<()>(handle, FSCTL_LOCK_VOLUME, None)
+            .with_context(|| format!("锁定卷 {}: 失败，请关闭正在使用该分区的程序", letter))?;
+        let _ = ioctl::<()>(handle, FSCTL_DISMOUNT_VOLUME, None);
+    }
+
+    let result = format_volume(&mut volume, &geometry, options, progress);
+
+    // 解锁后系统会在下次访问时按新的文件系统重新挂载
+    unsafe {
+        let _ = ioctl::<()>(handle, FSCTL_DISMOUNT_VOLUME, None);
+        let _ = ioctl::<()>(handle, FSCTL_UNLOCK_VOLUME, None);
+    }
+    result
+}
+
+#[cfg(not(windows))]
+pub fn format_drive(
+    _letter: char,
+    _options: &FatFormatOptions,
+    _progress: &mut dyn FnMut(u8, &str),
+) -> Result<FormatSummary> {
+    bail!("仅支持Windows系统")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn temp_image(name: &str, size: u64) -> (std::path::PathBuf, std::fs::File) {
+        let dir = std::env::temp_dir().join(format!("letrecovery_fatfmt_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join(name);
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(size).unwrap();
+        (path, file)
+    }
+
+    fn read_at(file: &mut std::fs::File, offset: u64, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    fn u32_at(data: &[u8], off: usize) -> u32 {
+        u32::from_le_bytes(data[off..off + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_fat32_layout_limits() {
+        let geometry = |bytes: u64| VolumeGeometry {
+            total_sectors: bytes / 512,
+            sector_size: 512,
+            hidden_sectors: 2048,
+        };
+        // 2 TiB 以内任意大小都能布局，FAT 必须容纳全部簇
+        for bytes in [64 * MIB, 40 << 30, 500 << 30, (2u64 << 40) - MIB] {
+            let layout = Fat32Layout::compute(&geometry(bytes), None).unwrap();
+            assert!(layout.cluster_count >= FAT32_MIN_CLUSTERS);
+            assert!(layout.cluster_count <= FAT32_MAX_CLUSTERS);
+            assert!((layout.cluster_count + 2) * 4 <= layout.fat_sectors as u64 * 512);
+            assert_eq!(layout.data_start() % layout.sectors_per_cluster as u64, 0);
+            assert!(layout.data_start() + layout.cluster_count * layout.sectors_per_cluster as u64 <= bytes / 512);
+        }
+        assert_eq!(Fat32Layout::compute(&geometry(40 << 30), None).unwrap().cluster_size(), 32768);
+        assert!(Fat32Layout::compute(&geometry(3u64 << 40), None).is_err());
+        assert!(Fat32Layout::compute(&geometry(16 * MIB), None).is_err());
+    }
+
+    #[test]
+    fn test_format_fat32_image() {
+        let (path, mut file) = temp_image("fat32.img", 64 * MIB);
+        let geometry = VolumeGeometry { total_sectors: 64 * MIB / 512, sector_size: 512, hidden_sectors: 2048 };
+        let options = FatFormatOptions {
+            file_system: NativeFileSystem::Fat32,
+            label: "LetRecovery".to_string(),
+            quick: false,
+            cluster_size: None,
+        };
+        let mut last = 0;
+        let summary = format_volume(&mut file, &geometry, &options, &mut |p, _| {
+            assert!(p >= last);
+            last = p;
+        })
+        .unwrap();
+        assert_eq!(last, 100);
+
+        let head = read_at(&mut file, 0, 9 * 512);
+        let boot = &head[..512];
+        assert_eq!(&boot[82..90], b"FAT32   ");
+        assert_eq!(&boot[71..82], b"LETRECOVERY");
+        assert_eq!(u32_at(boot, 28), 2048);
+        assert_eq!(u32_at(boot, 67), summary.serial);
+        assert_eq!(head[..3 * 512], head[6 * 512..9 * 512]);
+        let fs_info = &head[512..1024];
+        assert_eq!(u32_at(fs_info, 0), 0x4161_5252);
+        assert_eq!(u32_at(fs_info, 488) as u64, summary.cluster_count - 1);
+
+        let layout = Fat32Layout::compute(&geometry, None).unwrap();
+        let fat = read_at(&mut file, layout.reserved_sectors as u64 * 512, 16);
+        assert_eq!(u32_at(&fat, 8), 0x0FFF_FFFF);
+        let root = read_at(&mut file, layout.data_start() * 512, 32);
+        assert_eq!(&root[..11], b"LETRECOVERY");
+        assert_eq!(root[11], 0x08);
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_format_exfat_image() {
+        let (path, mut file) = temp_image("exfat.img", 32 * MIB);
+        let geometry = VolumeGeometry { total_sectors: 32 * MIB / 512, sector_size: 512, hidden_sectors: 0 };
+        let options = FatFormatOptions {
+            file_system: NativeFileSystem::ExFat,
+            label: "数据盘".to_string(),
+            quick: true,
+            cluster_size: None,
+        };
+        format_volume(&mut file, &geometry, &options, &mut |_, _| {}).unwrap();
+
+        let region = read_at(&mut file, 0, 24 * 512);
+        assert_eq!(&region[3..11], b"EXFAT   ");
+        assert_eq!(region[..12 * 512], region[12 * 512..]);
+        let checksum = exfat_checksum(&region[..11 * 512], &[106, 107, 112]);
+        assert_eq!(u32_at(&region, 11 * 512), checksum);
+        assert_eq!(u32_at(&region, 12 * 512 - 4), checksum);
+
+        let layout = ExFatLayout::compute(&geometry, None).unwrap();
+        let cluster = layout.cluster_size() as u64;
+        let root_cluster = u32_at(&region, 96);
+        let root = read_at(&mut file, layout.cluster_sector(root_cluster) * 512, 96);
+        assert_eq!(root[0], 0x83);
+        assert_eq!(root[1], 3);
+        assert_eq!(root[32], 0x81);
+        assert_eq!(root[64], 0x82);
+
+        // 位图标记了位图、大写表和根目录占用的簇
+        let bitmap = read_at(&mut file, layout.cluster_sector(u32_at(&root, 52)) * 512, 1);
+        assert_eq!(bitmap[0].count_ones(), root_cluster - 2 + 1);
+
+        // 大写表校验和与目录项一致，且 'a' 映射为 'A'
+        let upcase_len = u64::from_le_bytes(root[88..96].try_into().unwrap()) as usize;
+        let upcase = read_at(&mut file, layout.cluster_sector(u32_at(&root, 84)) * 512, upcase_len);
+        assert_eq!(exfat_checksum(&upcase, &[]), u32_at(&root, 68));
+        assert!(upcase_len as u64 <= cluster * 4);
+        assert_eq!(upcase, upcase_table());
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_upcase_table_maps_ascii() {
+        let table = upcase_table();
+        let entries: Vec<u16> = table.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        // 展开压缩表
+        let mut map = Vec::new();
+        let mut i = 0;
+        while i < entries.len() {
+            if entries[i] == 0xFFFF {
+                let start = map.len() as u32;
+                map.extend((start..start + entries[i + 1] as u32).map(|c| c as u16));
+                i += 2;
+            } else {
+                map.push(entries[i]);
+                i += 1;
+            }
+        }
+        assert_eq!(map.len(), 0x10000);
+        assert_eq!(map[b'a' as usize], b'A' as u16);
+        assert_eq!(map[b'A' as usize], b'A' as u16);
+        assert_eq!(map[0x3B1], 0x391);
+    }
+}
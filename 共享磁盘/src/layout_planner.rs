@@ -0,0 +1,685 @@
+//! 分区布局规划模块
+//!
+//! 将用户设计的分区意图转换为精确的 LBA 范围：按 1 MiB 对齐，
+//! 检查 ESP / MSR / 恢复分区的最小大小以及 MBR 的限制（最多 4 个主分区、2 TiB），
+//! 并提供常用的预设布局。规划结果在写入磁盘前完成全部校验。
+
+use anyhow::{bail, Result};
+
+use super::partition_table::{
+    self, DiskGeometry, Gpt, GptEntry, GptHealth, Guid, Mbr, MbrEntry, MbrLayout, PartitionStyle,
+    PartitionTable,
+};
+
+/// 1 MiB
+pub const MIB: u64 = 1024 * 1024;
+
+/// 默认分区对齐（1 MiB）
+pub const DEFAULT_ALIGNMENT: u64 = MIB;
+
+/// ESP 最小大小（512 字节扇区）
+pub const ESP_MIN_MIB: u64 = 100;
+/// ESP 最小大小（4K 扇区，FAT32 簇数下限要求）
+pub const ESP_MIN_MIB_4K: u64 = 260;
+/// MSR 最小大小
+pub const MSR_MIN_MIB: u64 = 16;
+/// 恢复分区最小大小
+pub const RECOVERY_MIN_MIB: u64 = 500;
+/// 系统保留分区最小大小
+pub const SYSTEM_RESERVED_MIN_MIB: u64 = 100;
+/// diskpart 可格式化的 FAT32 最大大小
+pub const FAT32_MAX_MIB: u64 = 32 * 1024;
+
+/// MBR 可寻址的最大扇区数（32 位 LBA）
+const MBR_MAX_SECTORS: u64 = u32::MAX as u64;
+
+/// 分区角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionRole {
+    /// EFI 系统分区（仅 GPT）
+    Esp,
+    /// 微软保留分区（仅 GPT）
+    Msr,
+    /// Windows 恢复分区
+    Recovery,
+    /// 系统保留分区（MBR 活动分区）
+    SystemReserved,
+    /// 普通数据分区（系统分区或数据分区）
+    Basic,
+}
+
+impl std::fmt::Display for PartitionRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartitionRole::Esp => write!(f, "ESP"),
+            PartitionRole::Msr => write!(f, "MSR"),
+            PartitionRole::Recovery => write!(f, "恢复分区"),
+            PartitionRole::SystemReserved => write!(f, "系统保留"),
+            PartitionRole::Basic => write!(f, "数据分区"),
+        }
+    }
+}
+
+/// 分区大小需求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionSize {
+    /// 固定大小（MiB）
+    Mib(u64),
+    /// 扣除固定大小分区后剩余空间的百分比
+    Percent(u32),
+    /// 使用所有剩余空间（最多一个）
+    Fill,
+}
+
+/// 分区意图（用户想要的分区）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionIntent {
+    /// 分区角色
+    pub role: PartitionRole,
+    /// 大小需求
+    pub size: PartitionSize,
+    /// 卷标
+    pub label: String,
+    /// 盘符
+    pub drive_letter: Option<char>,
+    /// 文件系统（MSR 为空）
+    pub file_system: String,
+}
+
+impl PartitionIntent {
+    /// ESP 分区
+    pub fn esp(size_mib: u64) -> Self {
+        Self {
+            role: PartitionRole::Esp,
+            size: PartitionSize::Mib(size_mib),
+            label: "EFI".to_string(),
+            drive_letter: None,
+            file_system: "FAT32".to_string(),
+        }
+    }
+
+    /// MSR 分区
+    pub fn msr() -> Self {
+        Self {
+            role: PartitionRole::Msr,
+            size: PartitionSize::Mib(MSR_MIN_MIB),
+            label: String::new(),
+            drive_letter: None,
+            file_system: String::new(),
+        }
+    }
+
+    /// 恢复分区
+    pub fn recovery(size_mib: u64) -> Self {
+        Self {
+            role: PartitionRole::Recovery,
+            size: PartitionSize::Mib(size_mib),
+            label: "Recovery".to_string(),
+            drive_letter: None,
+            file_system: "NTFS".to_string(),
+        }
+    }
+
+    /// 系统保留分区（MBR 活动分区）
+    pub fn system_reserved(size_mib: u64, file_system: &str) -> Self {
+        Self {
+            role: PartitionRole::SystemReserved,
+            size: PartitionSize::Mib(size_mib),
+            label: "System".to_string(),
+            drive_letter: None,
+            file_system: file_system.to_string(),
+        }
+    }
+
+    /// 普通分区
+    pub fn basic(size: PartitionSize, label: &str) -> Self {
+        Self {
+            role: PartitionRole::Basic,
+            size,
+            label: label.to_string(),
+            drive_letter: None,
+            file_system: "NTFS".to_string(),
+        }
+    }
+}
+
+/// 规划后的分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedPartition {
+    /// 分区角色
+    pub role: PartitionRole,
+    /// 起始 LBA
+    pub first_lba: u64,
+    /// 结束 LBA（包含）
+    pub last_lba: u64,
+    /// 卷标
+    pub label: String,
+    /// 盘符
+    pub drive_letter: Option<char>,
+    /// 文件系统
+    pub file_system: String,
+}
+
+impl PlannedPartition {
+    /// 扇区数
+    pub fn sector_count(&self) -> u64 {
+        self.last_lba - self.first_lba + 1
+    }
+}
+
+/// 分区布局规划结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutPlan {
+    /// 分区表类型
+    pub style: PartitionStyle,
+    /// 磁盘几何信息
+    pub geometry: DiskGeometry,
+    /// 规划后的分区（按磁盘顺序）
+    pub partitions: Vec<PlannedPartition>,
+    /// 警告（不阻止执行）
+    pub warnings: Vec<String>,
+}
+
+impl LayoutPlan {
+    /// 分区偏移（字节）
+    pub fn offset_bytes(&self, partition: &PlannedPartition) -> u64 {
+        self.geometry.sectors_to_bytes(partition.first_lba)
+    }
+
+    /// 分区大小（字节）
+    pub fn size_bytes(&self, partition: &PlannedPartition) -> u64 {
+        self.geometry.sectors_to_bytes(partition.sector_count())
+    }
+
+    /// 未分配空间（字节）
+    pub fn unallocated_bytes(&self) -> u64 {
+        let used: u64 = self.partitions.iter().map(|p| p.sector_count()).sum();
+        self.geometry
+            .size_bytes()
+            .saturating_sub(self.geometry.sectors_to_bytes(used))
+    }
+
+    /// 转换为分区表
+    pub fn to_partition_table(&self) -> Result<PartitionTable> {
+        match self.style {
+            PartitionStyle::MBR => {
+                let mut mbr = Mbr {
+                    disk_signature: u32::from_le_bytes(
+                        Guid::new_random().0[..4].try_into().unwrap(),
+                    ),
+                    ..Default::default()
+                };
+                for (slot, p) in mbr.entries.iter_mut().zip(&self.partitions) {
+                    *slot = MbrEntry {
+                        bootable: p.role == PartitionRole::SystemReserved,
+                        partition_type: mbr_type_for(p),
+                        start_lba: p.first_lba as u32,
+                        sector_count: p.sector_count() as u32,
+                    };
+                }
+                Ok(PartitionTable::Mbr(MbrLayout {
+                    mbr,
+                    logical: Vec::new(),
+                }))
+            }
+            _ => {
+                let mut gpt = Gpt::new(&self.geometry)?;
+                gpt.entries = self
+                    .partitions
+                    .iter()
+                    .map(|p| {
+                        let (type_guid, name, attributes) = match p.role {
+                            PartitionRole::Esp => {
+                                (partition_table::ESP_PARTITION_TYPE, "EFI system partition", 0)
+                            }
+                            PartitionRole::Msr => (
+                                partition_table::MSR_PARTITION_TYPE,
+                                "Microsoft reserved partition",
+                                0,
+                            ),
+                            PartitionRole::Recovery => (
+                                partition_table::RECOVERY_PARTITION_TYPE,
+                                "Recovery",
+                                partition_table::GPT_ATTR_REQUIRED_PARTITION
+                                    | partition_table::GPT_ATTR_NO_DRIVE_LETTER,
+                            ),
+                            _ => (
+                                partition_table::BASIC_DATA_PARTITION_TYPE,
+                                "Basic data partition",
+                                0,
+                            ),
+                        };
+                        GptEntry {
+                            attributes,
+                            ..GptEntry::new(type_guid, p.first_lba, p.last_lba, name)
+                        }
+                    })
+                    .collect();
+                Ok(PartitionTable::Gpt(gpt, GptHealth::default()))
+            }
+        }
+    }
+}
+
+/// MBR 分区类型 ID
+fn mbr_type_for(partition: &PlannedPartition) -> u8 {
+    match partition.role {
+        PartitionRole::Recovery => partition_table::MBR_TYPE_RECOVERY,
+        _ if partition.file_system.eq_ignore_ascii_case("FAT32") => {
+            partition_table::MBR_TYPE_FAT32_LBA
+        }
+        _ => partition_table::MBR_TYPE_NTFS,
+    }
+}
+
+/// 预设布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutPreset {
+    /// Windows UEFI 标准布局：ESP + MSR + Windows + 恢复分区
+    WindowsUefi,
+    /// Windows BIOS 布局：系统保留 + Windows
+    WindowsBios,
+    /// 系统 + 数据各占一半（GPT）
+    SystemDataHalf,
+    /// Windows To Go：FAT32 活动引导分区 + Windows（MBR，兼容 UEFI 与 BIOS）
+    WindowsToGo,
+}
+
+impl LayoutPreset {
+    /// 所有预设
+    pub fn all() -> &'static [LayoutPreset] {
+        &[
+            LayoutPreset::WindowsUefi,
+            LayoutPreset::WindowsBios,
+            LayoutPreset::SystemDataHalf,
+            LayoutPreset::WindowsToGo,
+        ]
+    }
+
+    /// 显示名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayoutPreset::WindowsUefi => "Windows UEFI 标准",
+            LayoutPreset::WindowsBios => "Windows BIOS",
+            LayoutPreset::SystemDataHalf => "系统 + 数据 (各 50%)",
+            LayoutPreset::WindowsToGo => "Windows To Go",
+        }
+    }
+
+    /// 分区表类型
+    pub fn style(&self) -> PartitionStyle {
+        match self {
+            LayoutPreset::WindowsUefi | LayoutPreset::SystemDataHalf => PartitionStyle::GPT,
+            LayoutPreset::WindowsBios | LayoutPreset::WindowsToGo => PartitionStyle::MBR,
+        }
+    }
+
+    /// 分区意图
+    pub fn intents(&self) -> Vec<PartitionIntent> {
+        match self {
+            LayoutPreset::WindowsUefi => vec![
+                PartitionIntent::esp(300),
+                PartitionIntent::msr(),
+                PartitionIntent::basic(PartitionSize::Fill, "Windows"),
+                PartitionIntent::recovery(1024),
+            ],
+            LayoutPreset::WindowsBios => vec![
+                PartitionIntent::system_reserved(500, "NTFS"),
+                PartitionIntent::basic(PartitionSize::Fill, "Windows"),
+            ],
+            LayoutPreset::SystemDataHalf => vec![
+                PartitionIntent::esp(300),
+                PartitionIntent::msr(),
+                PartitionIntent::basic(PartitionSize::Percent(50), "Windows"),
+                PartitionIntent::basic(PartitionSize::Fill, "Data"),
+            ],
+            LayoutPreset::WindowsToGo => vec![
+                PartitionIntent::system_reserved(350, "FAT32"),
+                PartitionIntent::basic(PartitionSize::Fill, "Windows"),
+            ],
+        }
+    }
+}
+
+impl std::fmt::Display for LayoutPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// 分区布局规划器
+#[derive(Debug, Clone)]
+pub struct LayoutPlanner {
+    geometry: DiskGeometry,
+    style: PartitionStyle,
+    alignment: u64,
+}
+
+impl LayoutPlanner {
+    /// 创建规划器（默认 1 MiB 对齐）
+    pub fn new(geometry: DiskGeometry, style: PartitionStyle) -> Self {
+        Self {
+            geometry,
+            style,
+            alignment: DEFAULT_ALIGNMENT,
+        }
+    }
+
+    /// 设置对齐大小（字节，必须是扇区大小的整数倍）
+    pub fn with_alignment(mut self, alignment_bytes: u64) -> Self {
+        self.alignment = alignment_bytes;
+        self
+    }
+
+    /// ESP 最小大小（MiB）
+    pub fn esp_min_mib(&self) -> u64 {
+        if self.geometry.sector_size >= 4096 {
+            ESP_MIN_MIB_4K
+        } else {
+            ESP_MIN_MIB
+        }
+    }
+
+    /// 规划预设布局
+    pub fn plan_preset(geometry: DiskGeometry, preset: LayoutPreset) -> Result<LayoutPlan> {
+        Self::new(geometry, preset.style()).plan(&preset.intents())
+    }
+
+    /// 将分区意图转换为精确的 LBA 范围
+    pub fn plan(&self, intents: &[PartitionIntent]) -> Result<LayoutPlan> {
+        let sector = self.geometry.sector_size;
+        if self.alignment == 0 || !self.alignment.is_multiple_of(sector) {
+            bail!("对齐大小 {} 不是扇区大小 {} 的整数倍", self.alignment, sector);
+        }
+        let align = self.alignment / sector;
+        let mut warnings = Vec::new();
+
+        self.validate_intents(intents)?;
+
+        // 可用范围（对齐后），end 为开区间
+        let (first_usable, mut end) = match self.style {
+            PartitionStyle::GPT => {
+                let gpt = Gpt::new(&self.geometry)?;
+                (gpt.first_usable_lba, gpt.last_usable_lba + 1)
+            }
+            PartitionStyle::MBR => (1, self.geometry.total_sectors),
+            PartitionStyle::Unknown => bail!("请选择分区表类型"),
+        };
+        if self.style == PartitionStyle::MBR && end > MBR_MAX_SECTORS {
+            end = MBR_MAX_SECTORS;
+            warnings.push("MBR 磁盘超过 2 TiB 的部分无法使用".to_string());
+        }
+        let start = first_usable.div_ceil(align) * align;
+        let end = end / align * align;
+        if end <= start {
+            bail!("磁盘太小");
+        }
+        let available = end - start;
+
+        // 固定大小部分
+        let fixed: u64 = intents
+            .iter()
+            .filter_map(|i| match i.size {
+                PartitionSize::Mib(mib) => Some(self.mib_to_aligned_sectors(mib)),
+                _ => None,
+            })
+            .sum();
+        if fixed > available {
+            bail!(
+                "分区总大小 {} MiB 超过磁盘可用空间 {} MiB",
+                fixed * sector / MIB,
+                available * sector / MIB
+            );
+        }
+        let flexible = available - fixed;
+
+        // 百分比部分
+        let mut sizes: Vec<u64> = intents
+            .iter()
+            .map(|i| match i.size {
+                PartitionSize::Mib(mib) => self.mib_to_aligned_sectors(mib),
+                PartitionSize::Percent(pct) => flexible * pct as u64 / 100 / align * align,
+                PartitionSize::Fill => 0,
+            })
+            .collect();
+        let assigned: u64 = sizes.iter().sum();
+        if let Some(fill_idx) = intents.iter().position(|i| i.size == PartitionSize::Fill) {
+            sizes[fill_idx] = available - assigned;
+        }
+
+        let mut partitions = Vec::with_capacity(intents.len());
+        let mut cursor = start;
+        for (intent, &sectors) in intents.iter().zip(&sizes) {
+            if sectors == 0 {
+                bail!("{} 的大小为 0", intent.role);
+            }
+            let partition = PlannedPartition {
+                role: intent.role,
+                first_lba: cursor,
+                last_lba: cursor + sectors - 1,
+                label: intent.label.clone(),
+                drive_letter: intent.drive_letter.map(|c| c.to_ascii_uppercase()),
+                file_system: intent.file_system.to_uppercase(),
+            };
+            self.validate_planned(&partition)?;
+            cursor += sectors;
+            partitions.push(partition);
+        }
+
+        if self.style == PartitionStyle::GPT
+            && partitions.iter().any(|p| p.role == PartitionRole::Esp)
+            && !partitions.iter().any(|p| p.role == PartitionRole::Msr)
+        {
+            warnings.push("GPT 布局中没有 MSR 分区".to_string());
+        }
+
+        let plan = LayoutPlan {
+            style: self.style,
+            geometry: self.geometry,
+            partitions,
+            warnings,
+        };
+
+        // 最终与分区表的校验保持一致
+        match plan.to_partition_table()? {
+            PartitionTable::Mbr(layout) => layout.validate(&self.geometry)?,
+            PartitionTable::Gpt(gpt, _) => gpt.validate()?,
+            PartitionTable::Raw => {}
+        }
+        Ok(plan)
+    }
+
+    fn mib_to_aligned_sectors(&self, mib: u64) -> u64 {
+        let align = self.alignment / self.geometry.sector_size;
+        (mib * MIB / self.geometry.sector_size).div_ceil(align) * align
+    }
+
+    /// 校验分区意图
+    fn validate_intents(&self, intents: &[PartitionIntent]) -> Result<()> {
+        if intents.is_empty() {
+            bail!("请至少添加一个分区");
+        }
+        if self.style == PartitionStyle::MBR && intents.len() > 4 {
+            bail!("MBR 磁盘最多只能有 4 个主分区");
+        }
+        if intents.iter().filter(|i| i.size == PartitionSize::Fill).count() > 1 {
+            bail!("只能有一个分区使用剩余空间");
+        }
+        let percent: u32 = intents
+            .iter()
+            .filter_map(|i| match i.size {
+                PartitionSize::Percent(p) => Some(p),
+                _ => None,
+            })
+            .sum();
+        if percent > 100 {
+            bail!("百分比分区合计超过 100%");
+        }
+
+        for role in [PartitionRole::Esp, PartitionRole::Msr, PartitionRole::SystemReserved] {
+            if intents.iter().filter(|i| i.role == role).count() > 1 {
+                bail!("只能有一个{}", role);
+            }
+        }
+
+        let mut letters = Vec::new();
+        for intent in intents {
+            match (self.style, intent.role) {
+                (PartitionStyle::MBR, PartitionRole::Esp | PartitionRole::Msr) => {
+                    bail!("MBR 磁盘不支持{}分区", intent.role)
+                }
+                (PartitionStyle::GPT, PartitionRole::SystemReserved) => {
+                    bail!("GPT 磁盘不使用系统保留活动分区，请使用 ESP")
+                }
+                _ => {}
+            }
+
+            if let PartitionSize::Mib(mib) = intent.size {
+                let min = match intent.role {
+                    PartitionRole::Esp => self.esp_min_mib(),
+                    PartitionRole::Msr => MSR_MIN_MIB,
+                    PartitionRole::Recovery => RECOVERY_MIN_MIB,
+                    PartitionRole::SystemReserved => SYSTEM_RESERVED_MIN_MIB,
+                    PartitionRole::Basic => 1,
+                };
+                if mib < min {
+                    bail!("{} 至少需要 {} MiB", intent.role, min);
+                }
+            }
+
+            if let Some(letter) = intent.drive_letter {
+                let letter = letter.to_ascii_uppercase();
+                if !letter.is_ascii_uppercase() {
+                    bail!("无效的盘符: {}", letter);
+                }
+                if letters.contains(&letter) {
+                    bail!("盘符 {}: 重复", letter);
+                }
+                letters.push(letter);
+            }
+
+            validate_label(&intent.label, &intent.file_system)?;
+        }
+        Ok(())
+    }
+
+    /// 校验规划后的单个分区
+    fn validate_planned(&self, partition: &PlannedPartition) -> Result<()> {
+        let size_mib = partition.sector_count() * self.geometry.sector_size / MIB;
+        if partition.file_system == "FAT32" && size_mib > FAT32_MAX_MIB {
+            bail!("FAT32 分区不能超过 32 GB（{} 为 {} MiB）", partition.role, size_mib);
+        }
+        if self.style == PartitionStyle::MBR && partition.last_lba >= MBR_MAX_SECTORS {
+            bail!("MBR 分区不能超过 2 TiB 边界");
+        }
+        Ok(())
+    }
+}
+
+/// 校验卷标
+fn validate_label(label: &str, file_system: &str) -> Result<()> {
+    if label.contains('"') {
+        bail!("卷标不能包含双引号");
+    }
+    let max_chars = match file_system.to_uppercase().as_str() {
+        "FAT32" => 11,
+        "EXFAT" => 15,
+        _ => 32,
+    };
+    if label.chars().count() > max_chars {
+        bail!("{} 卷标最多 {} 个字符: {}", file_system, max_chars, label);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * MIB;
+
+    fn disk(size: u64) -> DiskGeometry {
+        DiskGeometry::new(512, size)
+    }
+
+    #[test]
+    fn test_uefi_preset_is_aligned() {
+        let plan = LayoutPlanner::plan_preset(disk(128 * GIB), LayoutPreset::WindowsUefi).unwrap();
+        let roles: Vec<PartitionRole> = plan.partitions.iter().map(|p| p.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                PartitionRole::Esp,
+                PartitionRole::Msr,
+                PartitionRole::Basic,
+                PartitionRole::Recovery
+            ]
+        );
+        for p in &plan.partitions {
+            assert_eq!(p.first_lba % 2048, 0);
+            assert_eq!(p.sector_count() % 2048, 0);
+        }
+        assert_eq!(plan.partitions[0].first_lba, 2048);
+        assert_eq!(plan.size_bytes(&plan.partitions[0]), 300 * MIB);
+        assert_eq!(plan.size_bytes(&plan.partitions[3]), 1024 * MIB);
+        // 只剩对齐损耗
+        assert!(plan.unallocated_bytes() <= 2 * MIB);
+        assert!(plan.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_half_preset_splits_remaining_space() {
+        let plan =
+            LayoutPlanner::plan_preset(disk(100 * GIB), LayoutPreset::SystemDataHalf).unwrap();
+        let system = plan.size_bytes(&plan.partitions[2]);
+        let data = plan.size_bytes(&plan.partitions[3]);
+        assert!(system.abs_diff(data) <= 2 * MIB);
+    }
+
+    #[test]
+    fn test_rejects_invalid_layouts() {
+        let geometry = disk(64 * GIB);
+
+        let mbr = LayoutPlanner::new(geometry, PartitionStyle::MBR);
+        let five: Vec<PartitionIntent> = (0..5)
+            .map(|_| PartitionIntent::basic(PartitionSize::Mib(1024), ""))
+            .collect();
+        assert!(mbr.plan(&five).is_err());
+        assert!(mbr.plan(&[PartitionIntent::msr()]).is_err());
+
+        let gpt = LayoutPlanner::new(geometry, PartitionStyle::GPT);
+        assert!(gpt.plan(&[PartitionIntent::esp(50)]).is_err());
+        assert!(gpt
+            .plan(&[
+                PartitionIntent::basic(PartitionSize::Fill, ""),
+                PartitionIntent::basic(PartitionSize::Fill, ""),
+            ])
+            .is_err());
+        assert!(gpt
+            .plan(&[PartitionIntent::basic(PartitionSize::Mib(65 * 1024), "")])
+            .is_err());
+
+        let mut fat = PartitionIntent::basic(PartitionSize::Mib(40 * 1024), "");
+        fat.file_system = "FAT32".to_string();
+        assert!(gpt.plan(&[fat]).is_err());
+    }
+
+    #[test]
+    fn test_mbr_is_capped_at_2tib() {
+        let plan = LayoutPlanner::plan_preset(disk(3 * 1024 * GIB), LayoutPreset::WindowsBios).unwrap();
+        assert!(plan.partitions.iter().all(|p| p.last_lba < MBR_MAX_SECTORS));
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.partitions[0].role == PartitionRole::SystemReserved);
+        let PartitionTable::Mbr(layout) = plan.to_partition_table().unwrap() else {
+            panic!("应为 MBR");
+        };
+        assert!(layout.mbr.entries[0].bootable);
+    }
+
+    #[test]
+    fn test_esp_minimum_for_4k_sectors() {
+        let planner = LayoutPlanner::new(DiskGeometry::new(4096, 64 * GIB), PartitionStyle::GPT);
+        assert!(planner.plan(&[PartitionIntent::esp(100)]).is_err());
+        assert!(planner.plan(&[PartitionIntent::esp(260)]).is_ok());
+    }
+}
@@ -0,0 +1,23 @@
+//! LetRecovery 磁盘底层模块
+//!
+//! 正常系统端与 PE 端共用的分区表、格式化、虚拟磁盘、引导代码、原始扇区镜像等实现。
+//! 除少数需要 Windows API 或外部命令的函数（挂载虚拟磁盘、锁定卷、执行 diskpart）外
+//! 全部作用于 `Read + Write + Seek` 的数据源，可在任意平台上用镜像文件测试。
+//!
+//! 需要枚举物理磁盘、调用 diskpart 或修改引导的部分仍留在各端的 `core` 模块中。
+
+pub mod boot_code;
+pub mod diskpart;
+pub mod esp_inventory;
+pub mod fat32_writer;
+pub mod fat_format;
+pub mod layout_planner;
+pub mod mbr_to_gpt;
+pub mod partition_scan;
+pub mod partition_table;
+pub mod raw_image;
+pub mod usb_layout;
+pub mod vhd;
+pub mod volume_id;
+
+mod util;
@@ -0,0 +1,549 @@
+//! MBR 转 GPT 模块
+//!
+//! 在不清除数据的前提下把 MBR 磁盘转换为 GPT：
+//! 保留所有主分区和逻辑分区的位置，按 MBR 类型设置对应的 GPT 类型 GUID，
+//! 在空闲空间中创建 ESP 和 MSR（空间不足时先缩小分区），最后重新生成 UEFI 引导。
+//!
+//! 新分区表先写入内存中的覆盖层并读回校验，确认只改动了分区表区域后才写入真实磁盘。
+//! 缩小分区、格式化 ESP 和重建引导由正常系统端完成。
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use super::layout_planner::{ESP_MIN_MIB, ESP_MIN_MIB_4K, MIB, MSR_MIN_MIB};
+use super::partition_table::{
+    DiskGeometry, Gpt, GptEntry, GptHealth, Guid, MbrEntry, MbrLayout, PartitionTable,
+    BASIC_DATA_PARTITION_TYPE, ESP_PARTITION_TYPE, GPT_ATTR_NO_DRIVE_LETTER,
+    GPT_ATTR_REQUIRED_PARTITION, LINUX_FILESYSTEM_PARTITION_TYPE, MBR_TYPE_NTFS,
+    MBR_TYPE_RECOVERY, MSR_PARTITION_TYPE, RECOVERY_PARTITION_TYPE,
+};
+
+/// MBR 上的 EFI 系统分区类型
+const MBR_TYPE_ESP: u8 = 0xEF;
+/// 动态磁盘（LDM）
+const MBR_TYPE_LDM: u8 = 0x42;
+
+/// 需要先缩小的分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShrinkRequest {
+    /// 分区编号（与 Windows/diskpart 编号一致）
+    pub partition_number: u32,
+    pub start_lba: u64,
+    pub sector_count: u64,
+    /// 需要缩小的扇区数
+    pub shrink_sectors: u64,
+    /// 原因
+    pub reason: String,
+}
+
+impl ShrinkRequest {
+    /// 需要缩小的大小（MB，向上取整，供 diskpart shrink 使用）
+    pub fn shrink_mb(&self, sector_size: u64) -> u64 {
+        (self.shrink_sectors * sector_size).div_ceil(MIB)
+    }
+}
+
+/// 可直接写入的转换结果
+#[derive(Debug, Clone)]
+pub struct GptConversion {
+    /// 新的 GPT（分区项按起始位置排序）
+    pub gpt: Gpt,
+    /// ESP 在分区项数组中的下标
+    pub esp_index: usize,
+    /// 是否为本次新建的 ESP（需要格式化）
+    pub esp_created: bool,
+    /// MSR 在分区项数组中的下标
+    pub msr_index: Option<usize>,
+    /// 原 MBR 分区（起始, 扇区数），转换后位置必须不变
+    pub preserved: Vec<(u64, u64)>,
+    pub warnings: Vec<String>,
+}
+
+impl GptConversion {
+    /// ESP 的分区编号
+    pub fn esp_partition_number(&self) -> u32 {
+        self.esp_index as u32 + 1
+    }
+}
+
+/// 转换规划结果
+#[derive(Debug, Clone)]
+pub enum ConversionStep {
+    /// 可以直接转换
+    Ready(GptConversion),
+    /// 需要先缩小分区腾出空间
+    NeedsShrink(Vec<ShrinkRequest>),
+}
+
+/// MBR 分区类型对应的 GPT 类型、属性和名称
+pub fn gpt_type_for_mbr(partition_type: u8) -> Option<(Guid, u64, &'static str)> {
+    match partition_type {
+        // NTFS/exFAT、FAT12/16/32 以及对应的隐藏类型
+        0x01 | 0x04 | 0x06 | 0x07 | 0x0B | 0x0C | 0x0E | 0x11 | 0x14 | 0x16 | 0x17 | 0x1B
+        | 0x1C | 0x1E => Some((BASIC_DATA_PARTITION_TYPE, 0, "Basic data partition")),
+        MBR_TYPE_RECOVERY => Some((
+            RECOVERY_PARTITION_TYPE,
+            GPT_ATTR_REQUIRED_PARTITION | GPT_ATTR_NO_DRIVE_LETTER,
+            "Basic data partition",
+        )),
+        MBR_TYPE_ESP => Some((ESP_PARTITION_TYPE, 0, "EFI system partition")),
+        0x83 => Some((LINUX_FILESYSTEM_PARTITION_TYPE, 0, "Linux filesystem")),
+        _ => None,
+    }
+}
+
+/// MBR 中的数据分区（主分区和逻辑分区，按 Windows 编号顺序）
+fn mbr_partitions(layout: &MbrLayout) -> Vec<MbrEntry> {
+    layout
+        .mbr
+        .entries
+        .iter()
+        .filter(|e| !e.is_empty() && !e.is_extended())
+        .chain(layout.logical.iter())
+        .copied()
+        .collect()
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+/// 在分区之间查找对齐后至少 `sectors` 大小的空闲区域
+fn find_gap(used: &[(u64, u64)], from: u64, to: u64, sectors: u64, alignment: u64) -> Option<u64> {
+    let mut ranges: Vec<(u64, u64)> = used.to_vec();
+    ranges.sort_unstable();
+    let mut cursor = from;
+    for (start, count) in ranges.iter().copied().chain(std::iter::once((to, 0))) {
+        let gap_start = align_up(cursor, alignment);
+        if start >= gap_start && start - gap_start >= sectors {
+            return Some(gap_start);
+        }
+        cursor = cursor.max(start + count);
+    }
+    None
+}
+
+/// 规划 MBR 到 GPT 的转换
+pub fn plan_conversion(layout: &MbrLayout, geometry: &DiskGeometry) -> Result<ConversionStep> {
+    let template = Gpt::new(geometry)?;
+    let usable_start = template.first_usable_lba;
+    let usable_end = template.last_usable_lba + 1;
+    let alignment = (MIB / geometry.sector_size).max(1);
+    let esp_mib = if geometry.sector_size >= 4096 { ESP_MIN_MIB_4K } else { ESP_MIN_MIB };
+    let esp_sectors = esp_mib * MIB / geometry.sector_size;
+    let msr_sectors = MSR_MIN_MIB * MIB / geometry.sector_size;
+
+    let partitions = mbr_partitions(layout);
+    if partitions.is_empty() {
+        bail!("磁盘上没有分区，直接初始化为 GPT 即可");
+    }
+    if partitions.len() > template.num_entries as usize - 2 {
+        bail!("分区数量过多，无法转换");
+    }
+
+    let mut shrinks: Vec<ShrinkRequest> = Vec::new();
+    let mut used: Vec<(u64, u64)> = Vec::new();
+    for (i, entry) in partitions.iter().enumerate() {
+        let start = entry.start_lba as u64;
+        let count = entry.sector_count as u64;
+        if entry.partition_type == MBR_TYPE_LDM {
+            bail!("动态磁盘不支持转换");
+        }
+        if gpt_type_for_mbr(entry.partition_type).is_none() {
+            bail!("分区 {} 的类型 0x{:02X} 无法对应到 GPT 类型", i + 1, entry.partition_type);
+        }
+        if start < usable_start {
+            bail!("分区 {} 从扇区 {} 开始，与 GPT 头重叠，无法无损转换", i + 1, start);
+        }
+        // 末尾的分区占用了备份 GPT 的位置，需要缩小
+        if start + count > usable_end {
+            if entry.partition_type != MBR_TYPE_NTFS {
+                bail!("分区 {} 占用了磁盘末尾的 GPT 备份区域，且不是可缩小的 NTFS 分区", i + 1);
+            }
+            shrinks.push(ShrinkRequest {
+                partition_number: i as u32 + 1,
+                start_lba: start,
+                sector_count: count,
+                shrink_sectors: align_up(start + count - usable_end, alignment),
+                reason: "为备份 GPT 腾出磁盘末尾空间".to_string(),
+            });
+        }
+        used.push((start, count));
+    }
+
+    let existing_esp = partitions.iter().position(|e| e.partition_type == MBR_TYPE_ESP);
+    let mut warnings = Vec::new();
+    let mut extra: Vec<(GptEntry, bool)> = Vec::new();
+
+    if existing_esp.is_none() {
+        // 规划时按缩小后的大小计算占用
+        let used_after: Vec<(u64, u64)> = used
+            .iter()
+            .map(|&(start, count)| {
+                let shrink = shrinks.iter().find(|s| s.start_lba == start).map(|s| s.shrink_sectors).unwrap_or(0);
+                (start, count - shrink)
+            })
+            .collect();
+
+        match find_gap(&used_after, usable_start, usable_end, esp_sectors, alignment) {
+            Some(esp_start) if shrinks.is_empty() => {
+                extra.push((
+                    GptEntry::new(ESP_PARTITION_TYPE, esp_start, esp_start + esp_sectors - 1, "EFI system partition"),
+                    true,
+                ));
+                let mut used_with_esp = used_after.clone();
+                used_with_esp.push((esp_start, esp_sectors));
+                match find_gap(&used_with_esp, usable_start, usable_end, msr_sectors, alignment) {
+                    Some(msr_start) => extra.push((
+                        GptEntry::new(
+                            MSR_PARTITION_TYPE,
+                            msr_start,
+                            msr_start + msr_sectors - 1,
+                            "Microsoft reserved partition",
+                        ),
+                        false,
+                    )),
+                    None => warnings.push("磁盘没有足够的空闲空间，未创建 MSR 分区".to_string()),
+                }
+            }
+            Some(_) => {}
+            None => {
+                // 从最大的 NTFS 分区末尾腾出 ESP + MSR 的空间（外加对齐余量）
+                let needed = esp_sectors + msr_sectors + alignment;
+                let candidate = partitions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| e.partition_type == MBR_TYPE_NTFS)
+                    .max_by_key(|(_, e)| e.sector_count)
+                    .map(|(i, e)| (i, *e))
+                    .ok_or_else(|| anyhow::anyhow!("没有可缩小的 NTFS 分区，无法为 ESP 腾出空间"))?;
+                let (index, entry) = candidate;
+                match shrinks.iter_mut().find(|s| s.partition_number == index as u32 + 1) {
+                    Some(request) => {
+                        request.shrink_sectors += needed;
+                        request.reason = "为 ESP、MSR 和备份 GPT 腾出空间".to_string();
+                    }
+                    None => shrinks.push(ShrinkRequest {
+                        partition_number: index as u32 + 1,
+                        start_lba: entry.start_lba as u64,
+                        sector_count: entry.sector_count as u64,
+                        shrink_sectors: needed,
+                        reason: "为 ESP 和 MSR 腾出空间".to_string(),
+                    }),
+                }
+            }
+        }
+    }
+
+    if !shrinks.is_empty() {
+        return Ok(ConversionStep::NeedsShrink(shrinks));
+    }
+
+    let mut entries: Vec<(GptEntry, bool)> = partitions
+        .iter()
+        .map(|e| {
+            let (type_guid, attributes, name) = gpt_type_for_mbr(e.partition_type).unwrap();
+            let mut entry = GptEntry::new(
+                type_guid,
+                e.start_lba as u64,
+                e.start_lba as u64 + e.sector_count as u64 - 1,
+                name,
+            );
+            entry.attributes = attributes;
+            (entry, false)
+        })
+        .collect();
+    entries.extend(extra);
+    entries.sort_by_key(|(e, _)| e.first_lba);
+
+    let esp_index = entries
+        .iter()
+        .position(|(e, _)| e.type_guid == ESP_PARTITION_TYPE)
+        .expect("ESP 已规划");
+    let esp_created = entries[esp_index].1;
+    let msr_index = entries.iter().position(|(e, _)| e.type_guid == MSR_PARTITION_TYPE);
+
+    let mut gpt = template;
+    gpt.entries = entries.into_iter().map(|(e, _)| e).collect();
+    gpt.validate()?;
+
+    Ok(ConversionStep::Ready(GptConversion {
+        gpt,
+        esp_index,
+        esp_created,
+        msr_index,
+        preserved: used,
+        warnings,
+    }))
+}
+
+/// 写入时缓存在内存中的磁盘覆盖层，读取未写过的扇区时读底层设备
+struct SectorOverlay<'a, D> {
+    inner: &'a mut D,
+    sector_size: u64,
+    written: HashMap<u64, Vec<u8>>,
+    position: u64,
+}
+
+impl<'a, D: Read + Seek> SectorOverlay<'a, D> {
+    fn new(inner: &'a mut D, sector_size: u64) -> Self {
+        Self {
+            inner,
+            sector_size,
+            written: HashMap::new(),
+            position: 0,
+        }
+    }
+
+    fn sector(&mut self, lba: u64) -> std::io::Result<Vec<u8>> {
+        if let Some(data) = self.written.get(&lba) {
+            return Ok(data.clone());
+        }
+        let mut data = vec![0u8; self.sector_size as usize];
+        self.inner.seek(SeekFrom::Start(lba * self.sector_size))?;
+        self.inner.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+impl<D: Read + Seek> Read for SectorOverlay<'_, D> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let lba = self.position / self.sector_size;
+        let offset = (self.position % self.sector_size) as usize;
+        let sector = self.sector(lba)?;
+        let len = buf.len().min(sector.len() - offset);
+        buf[..len].copy_from_slice(&sector[offset..offset + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<D: Read + Seek> Write for SectorOverlay<'_, D> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let lba = self.position / self.sector_size;
+        let offset = (self.position % self.sector_size) as usize;
+        let mut sector = self.sector(lba)?;
+        let len = buf.len().min(sector.len() - offset);
+        sector[offset..offset + len].copy_from_slice(&buf[..len]);
+        self.written.insert(lba, sector);
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<D: Read + Seek> Seek for SectorOverlay<'_, D> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => (self.inner.seek(SeekFrom::End(0))? as i64 + offset) as u64,
+            SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+        };
+        Ok(self.position)
+    }
+}
+
+/// 在内存覆盖层中演练写入并读回校验
+///
+/// 校验新 GPT 可以完整读回、原有分区位置不变，并且写入只落在分区表区域
+pub fn verify_conversion<D: Read + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    conversion: &GptConversion,
+) -> Result<()> {
+    let mut overlay = SectorOverlay::new(dev, geometry.sector_size);
+    conversion.gpt.write(&mut overlay, geometry)?;
+
+    let gpt = &conversion.gpt;
+    if let Some(lba) = overlay
+        .written
+        .keys()
+        .find(|&&lba| lba >= gpt.first_usable_lba && lba <= gpt.last_usable_lba)
+    {
+        bail!("校验失败：写入会改动分区数据区域的扇区 {}", lba);
+    }
+
+    let table = PartitionTable::read(&mut overlay, geometry).context("校验失败：无法读回新分区表")?;
+    let PartitionTable::Gpt(read_back, health) = table else {
+        bail!("校验失败：读回的不是 GPT 分区表");
+    };
+    if !health.is_healthy() {
+        bail!("校验失败：主 GPT 或备份 GPT 无效");
+    }
+    if read_back.entries != gpt.entries {
+        bail!("校验失败：读回的分区项与规划不一致");
+    }
+    for (start, count) in &conversion.preserved {
+        if !read_back
+            .entries
+            .iter()
+            .any(|e| e.first_lba == *start && e.sector_count() == *count)
+        {
+            bail!("校验失败：原分区 (扇区 {}, 共 {} 扇区) 位置发生变化", start, count);
+        }
+    }
+    Ok(())
+}
+
+/// 校验后把 GPT 写入磁盘
+pub fn apply_conversion<D: Read + Write + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    conversion: &GptConversion,
+) -> Result<()> {
+    verify_conversion(dev, geometry, conversion)?;
+    PartitionTable::Gpt(conversion.gpt.clone(), GptHealth::default()).write(dev, geometry)?;
+    dev.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition_table::{read_sectors, write_sectors};
+    use std::path::PathBuf;
+
+    const SS: u64 = 512;
+
+    fn temp_disk(name: &str, size: u64) -> (PathBuf, std::fs::File) {
+        let dir = std::env::temp_dir().join(format!("letrecovery_mbr2gpt_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join(name);
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(size).unwrap();
+        (path, file)
+    }
+
+    fn entry(partition_type: u8, start: u32, count: u32) -> MbrEntry {
+        MbrEntry {
+            bootable: false,
+            partition_type,
+            start_lba: start,
+            sector_count: count,
+        }
+    }
+
+    /// 写入各分区的第一个扇区作为数据标记
+    fn mark_partitions(file: &mut std::fs::File, geometry: &DiskGeometry, starts: &[u64]) {
+        for (i, start) in starts.iter().enumerate() {
+            let mut sector = vec![0xA5u8; SS as usize];
+            sector[0] = i as u8;
+            write_sectors(file, geometry, *start, &sector).unwrap();
+        }
+    }
+
+    fn check_marks(file: &mut std::fs::File, geometry: &DiskGeometry, starts: &[u64]) {
+        for (i, start) in starts.iter().enumerate() {
+            let sector = read_sectors(file, geometry, *start, 1).unwrap();
+            assert_eq!(sector[0], i as u8);
+            assert!(sector[1..].iter().all(|b| *b == 0xA5));
+        }
+    }
+
+    #[test]
+    fn test_converts_with_free_space_for_esp() {
+        let (path, mut file) = temp_disk("free.img", 512 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, SS).unwrap();
+        // 系统保留 100 MiB + 系统分区 200 MiB，末尾留有约 200 MiB 空闲
+        let mut layout = MbrLayout::default();
+        layout.mbr.disk_signature = 0xCAFE_F00D;
+        layout.mbr.entries[0] = MbrEntry { bootable: true, ..entry(MBR_TYPE_NTFS, 2048, 204_800) };
+        layout.mbr.entries[1] = entry(MBR_TYPE_NTFS, 206_848, 409_600);
+        layout.mbr.entries[2] = entry(MBR_TYPE_RECOVERY, 616_448, 20_480);
+        PartitionTable::Mbr(layout.clone()).write(&mut file, &geometry).unwrap();
+        let starts = [2048, 206_848, 616_448];
+        mark_partitions(&mut file, &geometry, &starts);
+
+        let ConversionStep::Ready(conversion) = plan_conversion(&layout, &geometry).unwrap() else {
+            panic!("应可直接转换");
+        };
+        assert!(conversion.esp_created);
+        assert!(conversion.msr_index.is_some());
+        assert!(conversion.warnings.is_empty());
+
+        apply_conversion(&mut file, &geometry, &conversion).unwrap();
+        check_marks(&mut file, &geometry, &starts);
+
+        let table = PartitionTable::read(&mut file, &geometry).unwrap();
+        assert!(matches!(&table, PartitionTable::Gpt(_, health) if health.is_healthy()));
+        let partitions = table.partitions();
+        assert_eq!(partitions.len(), 5);
+        let esp = &partitions[conversion.esp_index];
+        assert!(esp.is_esp());
+        assert_eq!(esp.start_lba % 2048, 0);
+        assert!(partitions.iter().any(|p| p.is_msr()));
+        assert!(partitions.iter().any(|p| p.is_recovery() && p.start_lba == 616_448));
+        assert_eq!(
+            (partitions[1].start_lba, partitions[1].sector_count),
+            (206_848, 409_600)
+        );
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_full_disk_requires_shrink_first() {
+        let (path, mut file) = temp_disk("full.img", 256 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, SS).unwrap();
+        let total = geometry.total_sectors;
+        // 单个 NTFS 分区占满到磁盘最后一个扇区，逻辑分区在扩展分区里
+        let mut layout = MbrLayout::default();
+        layout.mbr.entries[0] = entry(MBR_TYPE_NTFS, 2048, 200_000);
+        layout.mbr.entries[1] = entry(0x0F, 202_048, (total - 202_048) as u32);
+        layout.logical.push(entry(MBR_TYPE_NTFS, 204_096, (total - 204_096) as u32));
+        PartitionTable::Mbr(layout.clone()).write(&mut file, &geometry).unwrap();
+
+        let ConversionStep::NeedsShrink(requests) = plan_conversion(&layout, &geometry).unwrap() else {
+            panic!("应需要先缩小分区");
+        };
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].partition_number, 2);
+        assert!(requests[0].shrink_mb(SS) >= ESP_MIN_MIB + MSR_MIN_MIB);
+
+        // 模拟 diskpart shrink：逻辑分区从末尾缩小
+        let shrunk = requests[0].sector_count - requests[0].shrink_sectors;
+        layout.logical[0].sector_count = shrunk as u32;
+        layout.mbr.entries[1].sector_count = (204_096 + shrunk - 202_048) as u32;
+        PartitionTable::Mbr(layout.clone()).write(&mut file, &geometry).unwrap();
+        let starts = [2048, 204_096];
+        mark_partitions(&mut file, &geometry, &starts);
+
+        let ConversionStep::Ready(conversion) = plan_conversion(&layout, &geometry).unwrap() else {
+            panic!("缩小后应可转换");
+        };
+        apply_conversion(&mut file, &geometry, &conversion).unwrap();
+        check_marks(&mut file, &geometry, &starts);
+        let partitions = PartitionTable::read(&mut file, &geometry).unwrap().partitions();
+        assert_eq!(partitions.len(), 4);
+        assert!(partitions.iter().any(|p| p.start_lba == 204_096 && p.sector_count == shrunk));
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_rejects_unconvertible_layouts() {
+        let geometry = DiskGeometry::new(SS, 256 * 1024 * 1024);
+        let mut layout = MbrLayout::default();
+        layout.mbr.entries[0] = entry(MBR_TYPE_LDM, 2048, 100_000);
+        assert!(plan_conversion(&layout, &geometry).is_err());
+
+        // 第一个分区从扇区 1 开始，与 GPT 头冲突
+        layout.mbr.entries[0] = entry(MBR_TYPE_NTFS, 1, 100_000);
+        assert!(plan_conversion(&layout, &geometry).is_err());
+
+        layout.mbr.entries[0] = entry(0xA5, 2048, 100_000);
+        assert!(plan_conversion(&layout, &geometry).is_err());
+    }
+}
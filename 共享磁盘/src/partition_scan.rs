@@ -0,0 +1,772 @@
+//! 丢失分区扫描模块
+//!
+//! 误执行一键分区或 `clean` 后，分区表被清除但分区内的数据通常还在。
+//! 本模块扫描磁盘（或磁盘镜像）中的 NTFS 引导扇区及其备份、FAT32/FAT16/exFAT 引导记录
+//! 和 BitLocker `-FVE-FS-` 卷头，识别含 `EFI` 目录的 FAT 分区为 ESP，
+//! 据此拼出一份新的 MBR/GPT 分区表供用户确认后写回。
+//!
+//! 只作用于 `Read + Seek` 的数据源；打开物理磁盘、备份并写回分区表由正常系统端完成。
+
+use anyhow::{bail, Result};
+use std::fmt;
+use std::io::{Read, Seek};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::partition_table::{
+    read_sectors, DiskGeometry, Gpt, GptEntry, GptHealth, Guid, MbrEntry, MbrLayout,
+    PartitionStyle, PartitionTable, BASIC_DATA_PARTITION_TYPE, ESP_PARTITION_TYPE,
+    MBR_TYPE_FAT32_LBA, MBR_TYPE_NTFS,
+};
+
+/// MBR 上的 EFI 系统分区类型
+const MBR_TYPE_ESP: u8 = 0xEF;
+/// MBR 上的 FAT16 (LBA) 分区类型
+const MBR_TYPE_FAT16_LBA: u8 = 0x0E;
+/// 深度扫描每次读取的扇区数
+const DEEP_SCAN_CHUNK_SECTORS: u64 = 2048;
+/// 快速扫描检查的对齐边界：1 MiB（Vista 以后）和柱面（XP 时代的 255 × 63）
+const QUICK_SCAN_ALIGNMENTS: [u64; 2] = [2048, 16065];
+/// 快速扫描额外检查的扇区（XP 时代第一个分区从 63 开始）
+const QUICK_SCAN_EXTRA: [u64; 1] = [63];
+/// exFAT 备份引导区相对主引导区的偏移
+const EXFAT_BACKUP_OFFSET: u64 = 12;
+/// 超过 2TB 的磁盘只能使用 GPT
+const MBR_MAX_SECTORS: u64 = u32::MAX as u64;
+
+/// 扫描模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanMode {
+    /// 只检查常见对齐位置及其前一个扇区（分区末尾的 NTFS 备份引导扇区）
+    #[default]
+    Quick,
+    /// 逐扇区检查整个磁盘
+    Deep,
+}
+
+/// 识别到的文件系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoundFileSystem {
+    Ntfs,
+    Fat32,
+    Fat16,
+    Exfat,
+    BitLocker,
+}
+
+impl fmt::Display for FoundFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FoundFileSystem::Ntfs => write!(f, "NTFS"),
+            FoundFileSystem::Fat32 => write!(f, "FAT32"),
+            FoundFileSystem::Fat16 => write!(f, "FAT16"),
+            FoundFileSystem::Exfat => write!(f, "exFAT"),
+            FoundFileSystem::BitLocker => write!(f, "BitLocker"),
+        }
+    }
+}
+
+/// 可信度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// 大小需要推测，或引导扇区与分区位置对不上
+    Low,
+    /// 只找到一份引导扇区
+    Medium,
+    /// 主引导扇区和备份一致
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Confidence::Low => write!(f, "低"),
+            Confidence::Medium => write!(f, "中"),
+            Confidence::High => write!(f, "高"),
+        }
+    }
+}
+
+/// 扫描到的分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundPartition {
+    /// 起始扇区
+    pub start_lba: u64,
+    /// 扇区数（None 表示无法从卷头得知，需要按相邻分区推测）
+    pub sector_count: Option<u64>,
+    pub file_system: FoundFileSystem,
+    /// FAT 分区根目录下有 EFI 目录
+    pub is_esp: bool,
+    /// 卷标（FAT/exFAT 引导扇区中的卷标，NTFS 为空）
+    pub label: String,
+    /// 是通过备份引导扇区找到的
+    pub from_backup: bool,
+    pub confidence: Confidence,
+}
+
+impl FoundPartition {
+    /// 结束扇区（不含）
+    pub fn end_lba(&self) -> Option<u64> {
+        self.sector_count.map(|count| self.start_lba + count)
+    }
+
+    fn overlaps(&self, other: &FoundPartition) -> bool {
+        match (self.end_lba(), other.end_lba()) {
+            (Some(a_end), Some(b_end)) => self.start_lba < b_end && other.start_lba < a_end,
+            _ => self.start_lba == other.start_lba,
+        }
+    }
+
+    /// 分区描述
+    pub fn describe(&self, sector_size: u64) -> String {
+        let size = self
+            .sector_count
+            .map(|count| format!("{:.2} GB", (count * sector_size) as f64 / 1024.0 / 1024.0 / 1024.0))
+            .unwrap_or_else(|| "大小未知".to_string());
+        let mut text = format!(
+            "{} @ {} MB, {}",
+            self.file_system,
+            self.start_lba * sector_size / 1024 / 1024,
+            size
+        );
+        if self.is_esp {
+            text.push_str(", ESP");
+        }
+        if !self.label.is_empty() {
+            text.push_str(&format!(", 卷标 \"{}\"", self.label));
+        }
+        if self.from_backup {
+            text.push_str(", 来自备份引导扇区");
+        }
+        text
+    }
+}
+
+/// 扫描结果
+#[derive(Debug, Clone, Default)]
+pub struct ScanReport {
+    /// 找到的所有候选分区（按起始位置排序）
+    pub candidates: Vec<FoundPartition>,
+    /// 去除重叠后建议恢复的分区（按起始位置排序，大小已补全）
+    pub proposal: Vec<FoundPartition>,
+    /// 扫描是否被取消
+    pub cancelled: bool,
+}
+
+/// 引导扇区解析结果
+#[derive(Debug, Clone)]
+struct BootRecord {
+    file_system: FoundFileSystem,
+    /// 卷大小（磁盘扇区数）
+    sector_count: Option<u64>,
+    /// 备份引导扇区相对卷起始的偏移（扇区）
+    backup_offset: Option<u64>,
+    label: String,
+}
+
+fn le_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn le_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn fat_label(raw: &[u8]) -> String {
+    let label = String::from_utf8_lossy(raw).trim().to_string();
+    if label == "NO NAME" {
+        String::new()
+    } else {
+        label
+    }
+}
+
+/// 把文件系统内的扇区数换算为磁盘扇区数
+fn to_disk_sectors(count: u64, fs_sector_size: u64, disk_sector_size: u64) -> u64 {
+    (count * fs_sector_size).div_ceil(disk_sector_size)
+}
+
+/// 解析一个扇区，判断是否为已知文件系统的引导扇区
+fn parse_boot_record(sector: &[u8], disk_sector_size: u64) -> Option<BootRecord> {
+    if sector.len() < 512 || sector[510..512] != [0x55, 0xAA] {
+        return None;
+    }
+    let oem = &sector[3..11];
+
+    if oem == b"EXFAT   " {
+        let shift = sector[0x6C];
+        if !(9..=12).contains(&shift) {
+            return None;
+        }
+        let length = le_u64(sector, 0x48);
+        if length == 0 {
+            return None;
+        }
+        return Some(BootRecord {
+            file_system: FoundFileSystem::Exfat,
+            sector_count: Some(to_disk_sectors(length, 1 << shift, disk_sector_size)),
+            backup_offset: Some(to_disk_sectors(EXFAT_BACKUP_OFFSET, 1 << shift, disk_sector_size)),
+            label: String::new(),
+        });
+    }
+
+    let bytes_per_sector = le_u16(sector, 0x0B) as u64;
+    if !matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096) {
+        return None;
+    }
+
+    if oem == b"NTFS    " {
+        let total = le_u64(sector, 0x28);
+        if total == 0 || sector[0x0D] == 0 {
+            return None;
+        }
+        // 卷大小不含末尾的备份引导扇区
+        let count = to_disk_sectors(total + 1, bytes_per_sector, disk_sector_size);
+        return Some(BootRecord {
+            file_system: FoundFileSystem::Ntfs,
+            sector_count: Some(count),
+            backup_offset: Some(count - 1),
+            label: String::new(),
+        });
+    }
+
+    if oem == b"-FVE-FS-" {
+        let total = match le_u64(sector, 0x28) {
+            0 => le_u32(sector, 0x20) as u64,
+            n => n,
+        };
+        return Some(BootRecord {
+            file_system: FoundFileSystem::BitLocker,
+            sector_count: (total > 0)
+                .then(|| to_disk_sectors(total, bytes_per_sector, disk_sector_size)),
+            backup_offset: None,
+            label: String::new(),
+        });
+    }
+
+    let total = match le_u16(sector, 0x13) {
+        0 => le_u32(sector, 0x20) as u64,
+        n => n as u64,
+    };
+    if total == 0 || sector[0x0D] == 0 || le_u16(sector, 0x0E) == 0 {
+        return None;
+    }
+    if &sector[0x52..0x5A] == b"FAT32   " {
+        let backup = le_u16(sector, 0x32) as u64;
+        return Some(BootRecord {
+            file_system: FoundFileSystem::Fat32,
+            sector_count: Some(to_disk_sectors(total, bytes_per_sector, disk_sector_size)),
+            backup_offset: (backup > 0 && backup < 0xFFFF)
+                .then(|| to_disk_sectors(backup, bytes_per_sector, disk_sector_size)),
+            label: fat_label(&sector[0x47..0x52]),
+        });
+    }
+    if &sector[0x36..0x3E] == b"FAT16   " {
+        return Some(BootRecord {
+            file_system: FoundFileSystem::Fat16,
+            sector_count: Some(to_disk_sectors(total, bytes_per_sector, disk_sector_size)),
+            backup_offset: None,
+            label: fat_label(&sector[0x2B..0x36]),
+        });
+    }
+    None
+}
+
+/// 检查 FAT 卷根目录的第一个簇中是否有 EFI 目录
+fn fat_has_efi_dir<D: Read + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    start_lba: u64,
+    boot: &[u8],
+    file_system: FoundFileSystem,
+) -> bool {
+    let bytes_per_sector = le_u16(boot, 0x0B) as u64;
+    let sectors_per_cluster = boot[0x0D] as u64;
+    let reserved = le_u16(boot, 0x0E) as u64;
+    let num_fats = boot[0x10] as u64;
+
+    let (root_sector, root_sectors) = match file_system {
+        FoundFileSystem::Fat32 => {
+            let fat_size = le_u32(boot, 0x24) as u64;
+            let root_cluster = le_u32(boot, 0x2C) as u64;
+            if root_cluster < 2 {
+                return false;
+            }
+            (
+                reserved + num_fats * fat_size + (root_cluster - 2) * sectors_per_cluster,
+                sectors_per_cluster,
+            )
+        }
+        FoundFileSystem::Fat16 => {
+            let fat_size = le_u16(boot, 0x16) as u64;
+            let root_entries = le_u16(boot, 0x11) as u64;
+            (
+                reserved + num_fats * fat_size,
+                (root_entries * 32).div_ceil(bytes_per_sector),
+            )
+        }
+        _ => return false,
+    };
+
+    let offset = root_sector * bytes_per_sector;
+    let length = (root_sectors * bytes_per_sector).min(64 * 1024);
+    let lba = start_lba + offset / geometry.sector_size;
+    let count = (length + offset % geometry.sector_size).div_ceil(geometry.sector_size);
+    if lba + count > geometry.total_sectors {
+        return false;
+    }
+    let Ok(data) = read_sectors(dev, geometry, lba, count) else {
+        return false;
+    };
+    let skip = (offset % geometry.sector_size) as usize;
+    data[skip..]
+        .chunks_exact(32)
+        .take_while(|entry| entry[0] != 0)
+        .any(|entry| &entry[0..11] == b"EFI        " && entry[11] & 0x10 != 0)
+}
+
+fn same_sector<D: Read + Seek>(dev: &mut D, geometry: &DiskGeometry, lba: u64, expected: &[u8]) -> bool {
+    lba < geometry.total_sectors
+        && read_sectors(dev, geometry, lba, 1)
+            .map(|data| data == expected)
+            .unwrap_or(false)
+}
+
+/// 由一个引导扇区推出候选分区
+fn candidates_from_sector<D: Read + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    lba: u64,
+    sector: &[u8],
+) -> Vec<FoundPartition> {
+    let Some(record) = parse_boot_record(sector, geometry.sector_size) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    let make = |start_lba: u64, from_backup: bool, confidence: Confidence, dev: &mut D| {
+        let is_esp = matches!(record.file_system, FoundFileSystem::Fat32 | FoundFileSystem::Fat16)
+            && fat_has_efi_dir(dev, geometry, start_lba, sector, record.file_system);
+        FoundPartition {
+            start_lba,
+            sector_count: record.sector_count,
+            file_system: record.file_system,
+            is_esp,
+            label: record.label.clone(),
+            from_backup,
+            confidence,
+        }
+    };
+
+    let Some(backup_offset) = record.backup_offset else {
+        let confidence = if record.sector_count.is_some() { Confidence::Medium } else { Confidence::Low };
+        found.push(make(lba, false, confidence, dev));
+        return found;
+    };
+
+    // 当作主引导扇区：备份位置的内容一致则可信
+    if same_sector(dev, geometry, lba + backup_offset, sector) {
+        found.push(make(lba, false, Confidence::High, dev));
+        return found;
+    }
+    // 当作备份引导扇区：主引导扇区仍在时由主引导扇区负责，这里跳过
+    if lba >= backup_offset && same_sector(dev, geometry, lba - backup_offset, sector) {
+        return found;
+    }
+
+    // 只剩一份：NTFS 的 hidden sectors 记录了分区起始位置，可用来区分主/备份
+    let hidden = le_u32(sector, 0x1C) as u64;
+    if lba < backup_offset || hidden == lba {
+        found.push(make(lba, false, Confidence::Medium, dev));
+    } else if hidden == lba - backup_offset {
+        found.push(make(lba - backup_offset, true, Confidence::Medium, dev));
+    } else {
+        found.push(make(lba, false, Confidence::Low, dev));
+        found.push(make(lba - backup_offset, true, Confidence::Low, dev));
+    }
+    found
+}
+
+/// 快速扫描要检查的扇区
+fn quick_scan_lbas(geometry: &DiskGeometry) -> Vec<u64> {
+    let total = geometry.total_sectors;
+    let mut lbas: Vec<u64> = QUICK_SCAN_EXTRA.iter().copied().filter(|lba| *lba < total).collect();
+    for align in QUICK_SCAN_ALIGNMENTS {
+        let mut lba = align;
+        while lba < total {
+            lbas.push(lba);
+            lbas.push(lba - 1);
+            lba += align;
+        }
+        // 分区可能一直延伸到磁盘末尾附近
+        lbas.push(total - 1);
+    }
+    lbas.sort_unstable();
+    lbas.dedup();
+    lbas
+}
+
+/// 扫描磁盘中的丢失分区
+///
+/// `progress` 回调参数为（已扫描扇区, 总扇区）；`cancel` 置位后尽快返回已找到的结果
+pub fn scan<D: Read + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    mode: ScanMode,
+    mut progress: impl FnMut(u64, u64),
+    cancel: Option<&AtomicBool>,
+) -> Result<ScanReport> {
+    let total = geometry.total_sectors;
+    let mut report = ScanReport::default();
+    let cancelled = || cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false);
+    let sector_size = geometry.sector_size as usize;
+
+    match mode {
+        ScanMode::Quick => {
+            let lbas = quick_scan_lbas(geometry);
+            for (i, lba) in lbas.iter().enumerate() {
+                if cancelled() {
+                    report.cancelled = true;
+                    break;
+                }
+                let sector = read_sectors(dev, geometry, *lba, 1)?;
+                let found = candidates_from_sector(dev, geometry, *lba, &sector);
+                report.candidates.extend(found);
+                if i % 4096 == 0 {
+                    progress(*lba, total);
+                }
+            }
+        }
+        ScanMode::Deep => {
+            let mut lba = 0;
+            while lba < total {
+                if cancelled() {
+                    report.cancelled = true;
+                    break;
+                }
+                let count = DEEP_SCAN_CHUNK_SECTORS.min(total - lba);
+                let chunk = read_sectors(dev, geometry, lba, count)?;
+                for (i, sector) in chunk.chunks_exact(sector_size).enumerate() {
+                    // 先做廉价的签名判断，避免对每个扇区都解析
+                    if sector[510..512] != [0x55, 0xAA] {
+                        continue;
+                    }
+                    let found = candidates_from_sector(dev, geometry, lba + i as u64, sector);
+                    report.candidates.extend(found);
+                }
+                lba += count;
+                progress(lba, total);
+            }
+        }
+    }
+    progress(total, total);
+
+    report.candidates.sort_by_key(|p| (p.start_lba, std::cmp::Reverse(p.confidence)));
+    report.candidates.dedup_by(|a, b| a.start_lba == b.start_lba && a.file_system == b.file_system);
+    report.proposal = resolve_candidates(&report.candidates, geometry);
+    Ok(report)
+}
+
+/// 去除重叠的候选分区，并推测缺失的大小
+fn resolve_candidates(candidates: &[FoundPartition], geometry: &DiskGeometry) -> Vec<FoundPartition> {
+    // 和 GPT 保持一致，分区不能占用磁盘末尾的备份 GPT 区域
+    // 放不下 GPT 的磁盘上只保留大小未知的候选
+    let usable_end = Gpt::new(geometry).map(|gpt| gpt.last_usable_lba + 1).unwrap_or(0);
+    let fits = |p: &FoundPartition| p.start_lba > 0 && p.end_lba().map(|end| end <= usable_end).unwrap_or(true);
+
+    // 可信度高、大小已知的优先
+    let mut ordered: Vec<&FoundPartition> = candidates.iter().filter(|p| fits(p)).collect();
+    ordered.sort_by_key(|p| (std::cmp::Reverse(p.confidence), p.sector_count.is_none(), p.start_lba));
+
+    let mut accepted: Vec<FoundPartition> = Vec::new();
+    for candidate in ordered {
+        let conflicts = accepted.iter().any(|p| {
+            p.overlaps(candidate)
+                || (candidate.sector_count.is_none() && p.start_lba <= candidate.start_lba
+                    && p.end_lba().map(|end| candidate.start_lba < end).unwrap_or(false))
+        });
+        if !conflicts {
+            accepted.push(candidate.clone());
+        }
+    }
+    accepted.sort_by_key(|p| p.start_lba);
+
+    // 大小未知的分区延伸到下一个分区之前
+    let starts: Vec<u64> = accepted.iter().map(|p| p.start_lba).collect();
+    for (i, partition) in accepted.iter_mut().enumerate() {
+        if partition.sector_count.is_none() {
+            let next = starts.get(i + 1).copied().unwrap_or(usable_end);
+            partition.sector_count = Some(next - partition.start_lba);
+        }
+    }
+    accepted
+}
+
+/// 为找到的分区选择分区表类型
+///
+/// 原磁盘类型已知时沿用；否则超过 2TB 或找到 ESP 时用 GPT，其余用 MBR
+pub fn suggested_style(geometry: &DiskGeometry, original: PartitionStyle, partitions: &[FoundPartition]) -> PartitionStyle {
+    match original {
+        PartitionStyle::GPT | PartitionStyle::MBR => original,
+        PartitionStyle::Unknown => {
+            if geometry.total_sectors > MBR_MAX_SECTORS || partitions.iter().any(|p| p.is_esp) {
+                PartitionStyle::GPT
+            } else {
+                PartitionStyle::MBR
+            }
+        }
+    }
+}
+
+/// 根据找到的分区拼出新的分区表
+pub fn build_partition_table(
+    geometry: &DiskGeometry,
+    style: PartitionStyle,
+    partitions: &[FoundPartition],
+) -> Result<PartitionTable> {
+    if partitions.is_empty() {
+        bail!("没有可恢复的分区");
+    }
+    let sized: Vec<(u64, u64, &FoundPartition)> = partitions
+        .iter()
+        .map(|p| {
+            p.sector_count
+                .map(|count| (p.start_lba, count, p))
+                .ok_or_else(|| anyhow::anyhow!("分区 {} 的大小未知", p.describe(geometry.sector_size)))
+        })
+        .collect::<Result<_>>()?;
+
+    let table = match style {
+        PartitionStyle::GPT => {
+            let mut gpt = Gpt::new(geometry)?;
+            for (start, count, p) in sized {
+                let (type_guid, name) = if p.is_esp {
+                    (ESP_PARTITION_TYPE, "EFI system partition")
+                } else {
+                    (BASIC_DATA_PARTITION_TYPE, "Basic data partition")
+                };
+                gpt.entries.push(GptEntry::new(type_guid, start, start + count - 1, name));
+            }
+            PartitionTable::Gpt(gpt, GptHealth::default())
+        }
+        PartitionStyle::MBR => {
+            if sized.len() > 4 {
+                bail!("MBR 最多只能有 4 个主分区，找到 {} 个分区，请改用 GPT", sized.len());
+            }
+            let mut layout = MbrLayout::default();
+            layout.mbr.disk_signature = u32::from_le_bytes(Guid::new_random().0[..4].try_into().unwrap());
+            for (slot, (start, count, p)) in sized.into_iter().enumerate() {
+                if start + count > MBR_MAX_SECTORS {
+                    bail!("分区超出 MBR 2TB 寻址范围，请改用 GPT");
+                }
+                let partition_type = match p.file_system {
+                    _ if p.is_esp => MBR_TYPE_ESP,
+                    FoundFileSystem::Fat32 => MBR_TYPE_FAT32_LBA,
+                    FoundFileSystem::Fat16 => MBR_TYPE_FAT16_LBA,
+                    // exFAT 和 BitLocker 与 NTFS 共用 0x07
+                    FoundFileSystem::Ntfs | FoundFileSystem::Exfat | FoundFileSystem::BitLocker => MBR_TYPE_NTFS,
+                };
+                layout.mbr.entries[slot] = MbrEntry {
+                    bootable: false,
+                    partition_type,
+                    start_lba: start as u32,
+                    sector_count: count as u32,
+                };
+            }
+            PartitionTable::Mbr(layout)
+        }
+        PartitionStyle::Unknown => bail!("请选择分区表类型"),
+    };
+
+    match &table {
+        PartitionTable::Gpt(gpt, _) => gpt.validate()?,
+        PartitionTable::Mbr(layout) => layout.validate(geometry)?,
+        PartitionTable::Raw => {}
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition_table::write_sectors;
+    use std::path::PathBuf;
+
+    const SS: u64 = 512;
+
+    fn temp_disk(name: &str, size: u64) -> (PathBuf, std::fs::File) {
+        let dir = std::env::temp_dir().join(format!("letrecovery_scan_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join(name);
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(size).unwrap();
+        (path, file)
+    }
+
+    fn signed(mut sector: [u8; 512]) -> [u8; 512] {
+        sector[510] = 0x55;
+        sector[511] = 0xAA;
+        sector
+    }
+
+    fn ntfs_boot(start: u64, sectors: u64) -> [u8; 512] {
+        let mut s = [0u8; 512];
+        s[0..3].copy_from_slice(&[0xEB, 0x52, 0x90]);
+        s[3..11].copy_from_slice(b"NTFS    ");
+        s[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+        s[0x0D] = 8;
+        s[0x1C..0x20].copy_from_slice(&(start as u32).to_le_bytes());
+        s[0x28..0x30].copy_from_slice(&(sectors - 1).to_le_bytes());
+        s[0x48..0x50].copy_from_slice(&0x1122_3344_5566_7788u64.to_le_bytes());
+        signed(s)
+    }
+
+    /// 写入一个根目录含 EFI 目录的 FAT32 卷
+    fn write_fat32_esp(file: &mut std::fs::File, geometry: &DiskGeometry, start: u64, sectors: u64) {
+        let mut s = [0u8; 512];
+        s[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+        s[3..11].copy_from_slice(b"MSDOS5.0");
+        s[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+        s[0x0D] = 1;
+        s[0x0E..0x10].copy_from_slice(&32u16.to_le_bytes());
+        s[0x10] = 2;
+        s[0x20..0x24].copy_from_slice(&(sectors as u32).to_le_bytes());
+        s[0x24..0x28].copy_from_slice(&800u32.to_le_bytes());
+        s[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+        s[0x32..0x34].copy_from_slice(&6u16.to_le_bytes());
+        s[0x47..0x52].copy_from_slice(b"SYSTEM     ");
+        s[0x52..0x5A].copy_from_slice(b"FAT32   ");
+        let s = signed(s);
+        write_sectors(file, geometry, start, &s).unwrap();
+        write_sectors(file, geometry, start + 6, &s).unwrap();
+
+        let mut root = [0u8; 512];
+        root[0..11].copy_from_slice(b"EFI        ");
+        root[11] = 0x10;
+        write_sectors(file, geometry, start + 32 + 2 * 800, &root).unwrap();
+    }
+
+    fn exfat_boot(start: u64, sectors: u64) -> [u8; 512] {
+        let mut s = [0u8; 512];
+        s[0..3].copy_from_slice(&[0xEB, 0x76, 0x90]);
+        s[3..11].copy_from_slice(b"EXFAT   ");
+        s[0x40..0x48].copy_from_slice(&start.to_le_bytes());
+        s[0x48..0x50].copy_from_slice(&sectors.to_le_bytes());
+        s[0x6C] = 9;
+        s[0x6D] = 3;
+        signed(s)
+    }
+
+    #[test]
+    fn test_recovers_gpt_layout_after_clean() {
+        let (path, mut file) = temp_disk("gpt.img", 256 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, SS).unwrap();
+
+        // ESP: 2048 起 100 MiB；NTFS: 206848 起 80 MiB；exFAT: 370688 起 40 MiB
+        write_fat32_esp(&mut file, &geometry, 2048, 204_800);
+        let ntfs = ntfs_boot(206_848, 163_840);
+        write_sectors(&mut file, &geometry, 206_848, &ntfs).unwrap();
+        write_sectors(&mut file, &geometry, 206_848 + 163_840 - 1, &ntfs).unwrap();
+        let exfat = exfat_boot(370_688, 81_920);
+        write_sectors(&mut file, &geometry, 370_688, &exfat).unwrap();
+        write_sectors(&mut file, &geometry, 370_688 + 12, &exfat).unwrap();
+
+        for mode in [ScanMode::Quick, ScanMode::Deep] {
+            let report = scan(&mut file, &geometry, mode, |_, _| {}, None).unwrap();
+            let found: Vec<(u64, Option<u64>, FoundFileSystem)> = report
+                .proposal
+                .iter()
+                .map(|p| (p.start_lba, p.sector_count, p.file_system))
+                .collect();
+            assert_eq!(
+                found,
+                vec![
+                    (2048, Some(204_800), FoundFileSystem::Fat32),
+                    (206_848, Some(163_840), FoundFileSystem::Ntfs),
+                    (370_688, Some(81_920), FoundFileSystem::Exfat),
+                ],
+                "{:?}",
+                mode
+            );
+            assert!(report.proposal[0].is_esp);
+            assert_eq!(report.proposal[0].label, "SYSTEM");
+            assert!(report.proposal.iter().all(|p| p.confidence == Confidence::High));
+        }
+
+        let report = scan(&mut file, &geometry, ScanMode::Quick, |_, _| {}, None).unwrap();
+        let style = suggested_style(&geometry, PartitionStyle::Unknown, &report.proposal);
+        assert_eq!(style, PartitionStyle::GPT);
+        let table = build_partition_table(&geometry, style, &report.proposal).unwrap();
+        table.write(&mut file, &geometry).unwrap();
+
+        let reread = PartitionTable::read(&mut file, &geometry).unwrap();
+        let partitions = reread.partitions();
+        assert_eq!(partitions.len(), 3);
+        assert!(partitions[0].is_esp());
+        assert_eq!((partitions[1].start_lba, partitions[1].sector_count), (206_848, 163_840));
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_ntfs_found_from_backup_boot_sector() {
+        let (path, mut file) = temp_disk("backup.img", 64 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, SS).unwrap();
+
+        // 主引导扇区被清零，只剩分区末尾的备份
+        let ntfs = ntfs_boot(2048, 61_440);
+        write_sectors(&mut file, &geometry, 2048 + 61_440 - 1, &ntfs).unwrap();
+        // BitLocker 卷头（大小未知）
+        let mut fve = [0u8; 512];
+        fve[3..11].copy_from_slice(b"-FVE-FS-");
+        fve[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+        write_sectors(&mut file, &geometry, 65_536, &signed(fve)).unwrap();
+
+        let report = scan(&mut file, &geometry, ScanMode::Deep, |_, _| {}, None).unwrap();
+        assert_eq!(report.proposal.len(), 2);
+        let ntfs = &report.proposal[0];
+        assert_eq!((ntfs.start_lba, ntfs.sector_count), (2048, Some(61_440)));
+        assert!(ntfs.from_backup);
+        assert_eq!(ntfs.confidence, Confidence::Medium);
+
+        let bitlocker = &report.proposal[1];
+        assert_eq!(bitlocker.file_system, FoundFileSystem::BitLocker);
+        assert_eq!(bitlocker.confidence, Confidence::Low);
+        // 延伸到 GPT 备份区之前
+        assert_eq!(bitlocker.end_lba(), Some(Gpt::new(&geometry).unwrap().last_usable_lba + 1));
+
+        let table = build_partition_table(&geometry, PartitionStyle::MBR, &report.proposal).unwrap();
+        let PartitionTable::Mbr(layout) = &table else {
+            panic!("应为 MBR");
+        };
+        assert_eq!(layout.mbr.entries[0].partition_type, MBR_TYPE_NTFS);
+        assert_eq!(layout.mbr.entries[0].start_lba, 2048);
+        assert_ne!(layout.mbr.disk_signature, 0);
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_scan_can_be_cancelled() {
+        let (path, mut file) = temp_disk("cancel.img", 16 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, SS).unwrap();
+        let cancel = AtomicBool::new(true);
+        let report = scan(&mut file, &geometry, ScanMode::Deep, |_, _| {}, Some(&cancel)).unwrap();
+        assert!(report.cancelled);
+        assert!(report.proposal.is_empty());
+        assert!(build_partition_table(&geometry, PartitionStyle::GPT, &report.proposal).is_err());
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+}
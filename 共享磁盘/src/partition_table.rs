@@ -0,0 +1,1397 @@
+//! 分区表读写模块
+//!
+//! 纯 Rust 实现的 MBR（含扩展/逻辑分区）与 GPT 分区表解析和写入，
+//! 作用于任何实现 `Read + Write + Seek` 的块设备源（物理磁盘句柄或镜像文件），
+//! 不依赖 Windows API，可在任意平台上用稀疏文件测试。
+
+use anyhow::{bail, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// 默认逻辑扇区大小
+pub const DEFAULT_SECTOR_SIZE: u64 = 512;
+
+/// MBR 引导签名
+pub const MBR_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// GPT 保护分区类型
+pub const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xEE;
+/// 扩展分区类型（CHS）
+pub const MBR_TYPE_EXTENDED: u8 = 0x05;
+/// 扩展分区类型（LBA）
+pub const MBR_TYPE_EXTENDED_LBA: u8 = 0x0F;
+/// Linux 扩展分区类型
+pub const MBR_TYPE_EXTENDED_LINUX: u8 = 0x85;
+/// NTFS / exFAT
+pub const MBR_TYPE_NTFS: u8 = 0x07;
+/// FAT32（LBA）
+pub const MBR_TYPE_FAT32_LBA: u8 = 0x0C;
+/// Windows 恢复环境
+pub const MBR_TYPE_RECOVERY: u8 = 0x27;
+
+/// GPT 头签名
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// GPT 修订版本 1.0
+const GPT_REVISION: u32 = 0x0001_0000;
+/// GPT 头大小
+const GPT_HEADER_SIZE: u32 = 92;
+/// 默认分区项数量
+pub const GPT_DEFAULT_ENTRY_COUNT: u32 = 128;
+/// 默认分区项大小
+pub const GPT_DEFAULT_ENTRY_SIZE: u32 = 128;
+/// 允许的 GPT 分区项大小范围
+const GPT_MIN_ENTRY_SIZE: u32 = 128;
+const GPT_MAX_ENTRY_SIZE: u32 = 4096;
+/// 分区项数组大小上限，防止损坏的 GPT 头导致超大内存分配
+const GPT_MAX_ENTRY_ARRAY_BYTES: usize = 1024 * 1024;
+/// 分区名称最大长度（UTF-16 字符）
+const GPT_NAME_CHARS: usize = 36;
+
+/// 扩展分区 EBR 链的最大长度（防止损坏的链表死循环）
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+/// 计算 CRC32（IEEE 802.3，GPT 使用的算法）
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// GUID（磁盘上的混合字节序存储格式）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Guid(pub [u8; 16]);
+
+/// EFI 系统分区
+pub const ESP_PARTITION_TYPE: Guid = Guid([
+    0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b,
+]);
+
+/// 微软保留分区
+pub const MSR_PARTITION_TYPE: Guid = Guid([
+    0x16, 0xe3, 0xc9, 0xe3, 0x5c, 0x0b, 0xb8, 0x4d, 0x81, 0x7d, 0xf9, 0x2d, 0xf0, 0x02, 0x15, 0xae,
+]);
+
+/// Windows 恢复分区
+pub const RECOVERY_PARTITION_TYPE: Guid = Guid([
+    0xa4, 0xbb, 0x94, 0xde, 0xd1, 0x06, 0x40, 0x4d, 0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6, 0xac,
+]);
+
+/// 基本数据分区
+pub const BASIC_DATA_PARTITION_TYPE: Guid = Guid([
+    0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7,
+]);
+
+/// Linux 文件系统分区
+pub const LINUX_FILESYSTEM_PARTITION_TYPE: Guid = Guid([
+    0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47, 0x7d, 0xe4,
+]);
+
+/// GPT 属性：平台必需（恢复分区使用）
+pub const GPT_ATTR_REQUIRED_PARTITION: u64 = 0x0000_0000_0000_0001;
+/// GPT 属性：不自动分配盘符（恢复分区使用）
+pub const GPT_ATTR_NO_DRIVE_LETTER: u64 = 0x8000_0000_0000_0000;
+
+impl Guid {
+    /// 全零 GUID（未使用的分区项）
+    pub const ZERO: Guid = Guid([0; 16]);
+
+    /// 是否为全零 GUID
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 16]
+    }
+
+    /// 生成随机 GUID（版本 4）
+    pub fn new_random() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+
+        let mut bytes = [0u8; 16];
+        for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_usize(i);
+            hasher.write_u32(std::process::id());
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+        // 版本 4，RFC 4122 变体
+        bytes[7] = (bytes[7] & 0x0F) | 0x40;
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+        Guid(bytes)
+    }
+
+    /// 从文本格式解析（`XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX`，可带花括号）
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches('{').trim_end_matches('}');
+        let hex: String = text.chars().filter(|c| *c != '-').collect();
+        if hex.len() != 32 || text.len() != 36 {
+            return None;
+        }
+
+        let mut raw = [0u8; 16];
+        for (i, byte) in raw.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        }
+
+        // 前三段为小端存储
+        let mut bytes = raw;
+        bytes[0..4].reverse();
+        bytes[4..6].reverse();
+        bytes[6..8].reverse();
+        Some(Guid(bytes))
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+            b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13],
+            b[14], b[15]
+        )
+    }
+}
+
+/// 磁盘几何信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskGeometry {
+    /// 逻辑扇区大小（字节）
+    pub sector_size: u64,
+    /// 扇区总数
+    pub total_sectors: u64,
+}
+
+impl DiskGeometry {
+    /// 根据磁盘大小创建
+    pub fn new(sector_size: u64, size_bytes: u64) -> Self {
+        Self {
+            sector_size,
+            total_sectors: size_bytes / sector_size,
+        }
+    }
+
+    /// 从可定位的数据源（镜像文件）获取大小
+    pub fn from_stream<S: Seek>(stream: &mut S, sector_size: u64) -> Result<Self> {
+        let size = stream.seek(SeekFrom::End(0)).context("获取磁盘大小失败")?;
+        Ok(Self::new(sector_size, size))
+    }
+
+    /// 磁盘大小（字节）
+    pub fn size_bytes(&self) -> u64 {
+        self.total_sectors * self.sector_size
+    }
+
+    /// 扇区数换算为字节
+    pub fn sectors_to_bytes(&self, sectors: u64) -> u64 {
+        sectors * self.sector_size
+    }
+
+    /// 字节数换算为扇区数（向上取整）
+    pub fn bytes_to_sectors(&self, bytes: u64) -> u64 {
+        bytes.div_ceil(self.sector_size)
+    }
+}
+
+/// 读取连续扇区
+pub fn read_sectors<D: Read + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    lba: u64,
+    count: u64,
+) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; (count * geometry.sector_size) as usize];
+    dev.seek(SeekFrom::Start(lba * geometry.sector_size))?;
+    dev.read_exact(&mut buf)
+        .with_context(|| format!("读取扇区 {} 失败", lba))?;
+    Ok(buf)
+}
+
+/// 写入连续扇区（数据长度会补齐到整扇区）
+pub fn write_sectors<D: Write + Seek>(
+    dev: &mut D,
+    geometry: &DiskGeometry,
+    lba: u64,
+    data: &[u8],
+) -> Result<()> {
+    let sectors = geometry.bytes_to_sectors(data.len() as u64);
+    let mut buf = data.to_vec();
+    buf.resize((sectors * geometry.sector_size) as usize, 0);
+    dev.seek(SeekFrom::Start(lba * geometry.sector_size))?;
+    dev.write_all(&buf)
+        .with_context(|| format!("写入扇区 {} 失败", lba))?;
+    Ok(())
+}
+
+/// 把磁盘（或磁盘镜像）中的一个分区作为独立的读写设备
+pub struct PartitionIo<D> {
+    inner: D,
+    offset: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<D> PartitionIo<D> {
+    pub fn new(inner: D, offset: u64, len: u64) -> Self {
+        Self { inner, offset, len, pos: 0 }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: Read + Seek> Read for PartitionIo<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = (self.len.saturating_sub(self.pos)).min(buf.len() as u64) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        self.inner.seek(SeekFrom::Start(self.offset + self.pos))?;
+        let read = self.inner.read(&mut buf[..n])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<D: Write + Seek> Write for PartitionIo<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = (self.len.saturating_sub(self.pos)).min(buf.len() as u64) as usize;
+        if n == 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "写入超出分区范围"));
+        }
+        self.inner.seek(SeekFrom::Start(self.offset + self.pos))?;
+        let written = self.inner.write(&buf[..n])?;
+        self.pos += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<D> Seek for PartitionIo<D> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+            SeekFrom::End(d) => self.len.checked_add_signed(d),
+        };
+        self.pos = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无效的定位位置"))?;
+        Ok(self.pos)
+    }
+}
+
+// ==================== MBR ====================
+
+/// MBR 分区项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MbrEntry {
+    /// 活动分区
+    pub bootable: bool,
+    /// 分区类型 ID
+    pub partition_type: u8,
+    /// 起始 LBA
+    pub start_lba: u32,
+    /// 扇区数
+    pub sector_count: u32,
+}
+
+impl MbrEntry {
+    /// 是否为空项
+    pub fn is_empty(&self) -> bool {
+        self.partition_type == 0 || self.sector_count == 0
+    }
+
+    /// 是否为扩展分区
+    pub fn is_extended(&self) -> bool {
+        matches!(
+            self.partition_type,
+            MBR_TYPE_EXTENDED | MBR_TYPE_EXTENDED_LBA | MBR_TYPE_EXTENDED_LINUX
+        )
+    }
+
+    /// 结束 LBA（包含）
+    pub fn end_lba(&self) -> u64 {
+        self.start_lba as u64 + self.sector_count as u64 - 1
+    }
+
+    fn decode(raw: &[u8]) -> Self {
+        Self {
+            bootable: raw[0] == 0x80,
+            partition_type: raw[4],
+            start_lba: u32::from_le_bytes([raw[8], raw[9], raw[10], raw[11]]),
+            sector_count: u32::from_le_bytes([raw[12], raw[13], raw[14], raw[15]]),
+        }
+    }
+
+    fn encode(&self) -> [u8; 16] {
+        let mut raw = [0u8; 16];
+        if self.is_empty() {
+            return raw;
+        }
+        raw[0] = if self.bootable { 0x80 } else { 0x00 };
+        raw[1..4].copy_from_slice(&lba_to_chs(self.start_lba as u64));
+        raw[4] = self.partition_type;
+        raw[5..8].copy_from_slice(&lba_to_chs(self.end_lba()));
+        raw[8..12].copy_from_slice(&self.start_lba.to_le_bytes());
+        raw[12..16].copy_from_slice(&self.sector_count.to_le_bytes());
+        raw
+    }
+}
+
+/// LBA 转 CHS（255 磁头 / 63 扇区几何，超出范围时使用 1023/254/63）
+fn lba_to_chs(lba: u64) -> [u8; 3] {
+    const HEADS: u64 = 255;
+    const SECTORS: u64 = 63;
+    let cylinder = lba / (HEADS * SECTORS);
+    if cylinder > 1023 {
+        return [0xFE, 0xFF, 0xFF];
+    }
+    let head = (lba / SECTORS) % HEADS;
+    let sector = lba % SECTORS + 1;
+    [
+        head as u8,
+        (sector as u8) | (((cylinder >> 2) & 0xC0) as u8),
+        (cylinder & 0xFF) as u8,
+    ]
+}
+
+/// 主引导记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mbr {
+    /// 引导代码（440 字节）
+    pub boot_code: Vec<u8>,
+    /// 磁盘签名
+    pub disk_signature: u32,
+    /// 4 个主分区项
+    pub entries: [MbrEntry; 4],
+}
+
+impl Default for Mbr {
+    fn default() -> Self {
+        Self {
+            boot_code: vec![0; 440],
+            disk_signature: 0,
+            entries: [MbrEntry::default(); 4],
+        }
+    }
+}
+
+impl Mbr {
+    /// 从扇区数据解析
+    pub fn decode(sector: &[u8]) -> Result<Self> {
+        if sector.len() < 512 || sector[510..512] != MBR_SIGNATURE {
+            bail!("MBR 签名无效");
+        }
+        let mut mbr = Mbr {
+            boot_code: sector[..440].to_vec(),
+            disk_signature: u32::from_le_bytes([sector[440], sector[441], sector[442], sector[443]]),
+            ..Default::default()
+        };
+        for (i, entry) in mbr.entries.iter_mut().enumerate() {
+            let off = 446 + i * 16;
+            *entry = MbrEntry::decode(&sector[off..off + 16]);
+        }
+        Ok(mbr)
+    }
+
+    /// 编码为 512 字节扇区
+    pub fn encode(&self) -> [u8; 512] {
+        let mut sector = [0u8; 512];
+        let code_len = self.boot_code.len().min(440);
+        sector[..code_len].copy_from_slice(&self.boot_code[..code_len]);
+        sector[440..444].copy_from_slice(&self.disk_signature.to_le_bytes());
+        for (i, entry) in self.entries.iter().enumerate() {
+            let off = 446 + i * 16;
+            sector[off..off + 16].copy_from_slice(&entry.encode());
+        }
+        sector[510..512].copy_from_slice(&MBR_SIGNATURE);
+        sector
+    }
+
+    /// 创建 GPT 保护性 MBR
+    pub fn protective(total_sectors: u64) -> Self {
+        let mut mbr = Mbr::default();
+        mbr.entries[0] = MbrEntry {
+            bootable: false,
+            partition_type: MBR_TYPE_GPT_PROTECTIVE,
+            start_lba: 1,
+            sector_count: (total_sectors - 1).min(u32::MAX as u64) as u32,
+        };
+        mbr
+    }
+
+    /// 是否为 GPT 保护性 MBR
+    pub fn is_protective(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.partition_type == MBR_TYPE_GPT_PROTECTIVE)
+    }
+}
+
+/// MBR 分区布局（主分区 + 扩展分区中的逻辑分区）
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MbrLayout {
+    /// 主引导记录
+    pub mbr: Mbr,
+    /// 逻辑分区（start_lba 为相对磁盘起始的绝对地址）
+    pub logical: Vec<MbrEntry>,
+}
+
+impl MbrLayout {
+    /// 扩展分区项
+    pub fn extended(&self) -> Option<&MbrEntry> {
+        self.mbr.entries.iter().find(|e| !e.is_empty() && e.is_extended())
+    }
+
+    /// 校验布局（范围、重叠、逻辑分区位置）
+    pub fn validate(&self, geometry: &DiskGeometry) -> Result<()> {
+        let primaries: Vec<&MbrEntry> =
+            self.mbr.entries.iter().filter(|e| !e.is_empty()).collect();
+
+        if primaries.iter().filter(|e| e.is_extended()).count() > 1 {
+            bail!("MBR 只能有一个扩展分区");
+        }
+        for entry in &primaries {
+            if entry.start_lba == 0 {
+                bail!("分区不能从扇区 0 开始");
+            }
+            if entry.end_lba() >= geometry.total_sectors {
+                bail!("分区超出磁盘末尾 (结束扇区 {})", entry.end_lba());
+            }
+        }
+        check_overlaps(
+            primaries
+                .iter()
+                .map(|e| (e.start_lba as u64, e.end_lba()))
+                .collect(),
+        )?;
+
+        if self.logical.is_empty() {
+            return Ok(());
+        }
+        let Some(extended) = self.extended() else {
+            bail!("存在逻辑分区但没有扩展分区");
+        };
+
+        let mut container_start = extended.start_lba as u64;
+        for logical in &self.logical {
+            if logical.is_empty() || logical.is_extended() {
+                bail!("逻辑分区项无效");
+            }
+            // 每个逻辑分区前至少需要一个扇区存放 EBR
+            if (logical.start_lba as u64) <= container_start {
+                bail!("逻辑分区 (起始扇区 {}) 前没有存放 EBR 的空间", logical.start_lba);
+            }
+            if logical.end_lba() > extended.end_lba() {
+                bail!("逻辑分区超出扩展分区范围");
+            }
+            container_start = logical.end_lba() + 1;
+        }
+        Ok(())
+    }
+
+    fn read<D: Read + Seek>(dev: &mut D, geometry: &DiskGeometry, mbr: Mbr) -> Result<Self> {
+        let mut layout = MbrLayout {
+            mbr,
+            logical: Vec::new(),
+        };
+        let Some(extended) = layout.extended().copied() else {
+            return Ok(layout);
+        };
+
+        let ext_start = extended.start_lba as u64;
+        let mut ebr_lba = ext_start;
+        for _ in 0..MAX_LOGICAL_PARTITIONS {
+            let sector = read_sectors(dev, geometry, ebr_lba, 1)?;
+            let ebr = Mbr::decode(&sector).with_context(|| format!("EBR (扇区 {}) 无效", ebr_lba))?;
+
+            let data = ebr.entries[0];
+            if !data.is_empty() {
+                layout.logical.push(MbrEntry {
+                    start_lba: (ebr_lba + data.start_lba as u64) as u32,
+                    ..data
+                });
+            }
+
+            let link = ebr.entries[1];
+            if link.is_empty() || !link.is_extended() {
+                break;
+            }
+            let next = ext_start + link.start_lba as u64;
+            if next <= ebr_lba || next >= geometry.total_sectors {
+                bail!("EBR 链损坏 (扇区 {} 指向 {})", ebr_lba, next);
+            }
+            ebr_lba = next;
+        }
+        Ok(layout)
+    }
+
+    fn write<D: Write + Seek>(&self, dev: &mut D, geometry: &DiskGeometry) -> Result<()> {
+        self.validate(geometry)?;
+        write_sectors(dev, geometry, 0, &self.mbr.encode())?;
+
+        let Some(extended) = self.extended().copied() else {
+            return Ok(());
+        };
+        let ext_start = extended.start_lba as u64;
+
+        // 每个逻辑分区的 EBR 放在其所在区域的第一个扇区
+        let mut ebr_lbas = Vec::with_capacity(self.logical.len());
+        let mut container_start = ext_start;
+        for logical in &self.logical {
+            ebr_lbas.push(container_start);
+            container_start = logical.end_lba() + 1;
+        }
+
+        if self.logical.is_empty() {
+            // 空扩展分区：写入空 EBR
+            write_sectors(dev, geometry, ext_start, &Mbr::default().encode())?;
+            return Ok(());
+        }
+
+        for (i, logical) in self.logical.iter().enumerate() {
+            let ebr_lba = ebr_lbas[i];
+            let mut ebr = Mbr::default();
+            ebr.entries[0] = MbrEntry {
+                start_lba: (logical.start_lba as u64 - ebr_lba) as u32,
+                ..*logical
+            };
+            if let (Some(&next_ebr), Some(next)) = (ebr_lbas.get(i + 1), self.logical.get(i + 1)) {
+                ebr.entries[1] = MbrEntry {
+                    bootable: false,
+                    partition_type: MBR_TYPE_EXTENDED,
+                    start_lba: (next_ebr - ext_start) as u32,
+                    sector_count: (next.end_lba() - next_ebr + 1) as u32,
+                };
+            }
+            write_sectors(dev, geometry, ebr_lba, &ebr.encode())?;
+        }
+        Ok(())
+    }
+}
+
+// ==================== GPT ====================
+
+/// GPT 头
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GptHeader {
+    /// 本头所在 LBA
+    pub current_lba: u64,
+    /// 另一份头所在 LBA
+    pub backup_lba: u64,
+    /// 第一个可用 LBA
+    pub first_usable_lba: u64,
+    /// 最后一个可用 LBA
+    pub last_usable_lba: u64,
+    /// 磁盘 GUID
+    pub disk_guid: Guid,
+    /// 分区项数组起始 LBA
+    pub partition_entry_lba: u64,
+    /// 分区项数量
+    pub num_partition_entries: u32,
+    /// 单个分区项大小
+    pub partition_entry_size: u32,
+    /// 分区项数组 CRC32
+    pub partition_entries_crc32: u32,
+}
+
+impl GptHeader {
+    /// 从扇区数据解析并校验 CRC
+    pub fn decode(sector: &[u8]) -> Result<Self> {
+        if sector.len() < GPT_HEADER_SIZE as usize || &sector[0..8] != GPT_SIGNATURE {
+            bail!("GPT 头签名无效");
+        }
+        let u32_at = |off: usize| u32::from_le_bytes(sector[off..off + 4].try_into().unwrap());
+        let u64_at = |off: usize| u64::from_le_bytes(sector[off..off + 8].try_into().unwrap());
+
+        let header_size = u32_at(12) as usize;
+        if header_size < GPT_HEADER_SIZE as usize || header_size > sector.len() {
+            bail!("GPT 头大小无效: {}", header_size);
+        }
+
+        let stored_crc = u32_at(16);
+        let mut raw = sector[..header_size].to_vec();
+        raw[16..20].fill(0);
+        if crc32(&raw) != stored_crc {
+            bail!("GPT 头 CRC 校验失败");
+        }
+
+        let header = Self {
+            current_lba: u64_at(24),
+            backup_lba: u64_at(32),
+            first_usable_lba: u64_at(40),
+            last_usable_lba: u64_at(48),
+            disk_guid: Guid(sector[56..72].try_into().unwrap()),
+            partition_entry_lba: u64_at(72),
+            num_partition_entries: u32_at(80),
+            partition_entry_size: u32_at(84),
+            partition_entries_crc32: u32_at(88),
+        };
+
+        if header.num_partition_entries == 0 || header.num_partition_entries > 1024 {
+            bail!("GPT 分区项参数无效");
+        }
+        header.entry_array_len()?;
+        Ok(header)
+    }
+
+    /// 分区项数组字节数（分区项大小须在 128..=4096 且为 8 的倍数，总大小有上限）
+    pub fn entry_array_len(&self) -> Result<usize> {
+        if !(GPT_MIN_ENTRY_SIZE..=GPT_MAX_ENTRY_SIZE).contains(&self.partition_entry_size)
+            || !self.partition_entry_size.is_multiple_of(8)
+        {
+            bail!("GPT 分区项大小无效: {}", self.partition_entry_size);
+        }
+        let len = self.num_partition_entries as usize * self.partition_entry_size as usize;
+        if len > GPT_MAX_ENTRY_ARRAY_BYTES {
+            bail!("GPT 分区项数组过大: {} 字节", len);
+        }
+        Ok(len)
+    }
+
+    /// 编码为一个扇区
+    pub fn encode(&self, sector_size: u64) -> Vec<u8> {
+        let mut sector = vec![0u8; sector_size as usize];
+        sector[0..8].copy_from_slice(GPT_SIGNATURE);
+        sector[8..12].copy_from_slice(&GPT_REVISION.to_le_bytes());
+        sector[12..16].copy_from_slice(&GPT_HEADER_SIZE.to_le_bytes());
+        sector[24..32].copy_from_slice(&self.current_lba.to_le_bytes());
+        sector[32..40].copy_from_slice(&self.backup_lba.to_le_bytes());
+        sector[40..48].copy_from_slice(&self.first_usable_lba.to_le_bytes());
+        sector[48..56].copy_from_slice(&self.last_usable_lba.to_le_bytes());
+        sector[56..72].copy_from_slice(&self.disk_guid.0);
+        sector[72..80].copy_from_slice(&self.partition_entry_lba.to_le_bytes());
+        sector[80..84].copy_from_slice(&self.num_partition_entries.to_le_bytes());
+        sector[84..88].copy_from_slice(&self.partition_entry_size.to_le_bytes());
+        sector[88..92].copy_from_slice(&self.partition_entries_crc32.to_le_bytes());
+        let crc = crc32(&sector[..GPT_HEADER_SIZE as usize]);
+        sector[16..20].copy_from_slice(&crc.to_le_bytes());
+        sector
+    }
+
+    /// 分区项数组占用的扇区数
+    pub fn entry_array_sectors(&self, sector_size: u64) -> u64 {
+        (self.num_partition_entries as u64 * self.partition_entry_size as u64).div_ceil(sector_size)
+    }
+}
+
+/// GPT 分区项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GptEntry {
+    /// 分区类型 GUID
+    pub type_guid: Guid,
+    /// 分区唯一 GUID
+    pub unique_guid: Guid,
+    /// 起始 LBA
+    pub first_lba: u64,
+    /// 结束 LBA（包含）
+    pub last_lba: u64,
+    /// 属性
+    pub attributes: u64,
+    /// 分区名称
+    pub name: String,
+    /// 在分区项数组中的位置（从 0 开始），即 diskpart `select partition N` 中的 N - 1
+    ///
+    /// 为 None 时写入第一个空位；读取时只有前面存在空位的分区项才记录位置
+    pub slot: Option<u32>,
+}
+
+impl GptEntry {
+    /// 创建新分区项（随机唯一 GUID）
+    pub fn new(type_guid: Guid, first_lba: u64, last_lba: u64, name: &str) -> Self {
+        Self {
+            type_guid,
+            unique_guid: Guid::new_random(),
+            first_lba,
+            last_lba,
+            attributes: 0,
+            name: name.to_string(),
+            slot: None,
+        }
+    }
+
+    /// 扇区数
+    pub fn sector_count(&self) -> u64 {
+        self.last_lba - self.first_lba + 1
+    }
+
+    fn decode(raw: &[u8]) -> Self {
+        let name_units: Vec<u16> = raw[56..128]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&u| u != 0)
+            .collect();
+        Self {
+            type_guid: Guid(raw[0..16].try_into().unwrap()),
+            unique_guid: Guid(raw[16..32].try_into().unwrap()),
+            first_lba: u64::from_le_bytes(raw[32..40].try_into().unwrap()),
+            last_lba: u64::from_le_bytes(raw[40..48].try_into().unwrap()),
+            attributes: u64::from_le_bytes(raw[48..56].try_into().unwrap()),
+            name: String::from_utf16_lossy(&name_units),
+            slot: None,
+        }
+    }
+
+    fn encode_into(&self, raw: &mut [u8]) {
+        raw[0..16].copy_from_slice(&self.type_guid.0);
+        raw[16..32].copy_from_slice(&self.unique_guid.0);
+        raw[32..40].copy_from_slice(&self.first_lba.to_le_bytes());
+        raw[40..48].copy_from_slice(&self.last_lba.to_le_bytes());
+        raw[48..56].copy_from_slice(&self.attributes.to_le_bytes());
+        for (i, unit) in self.name.encode_utf16().take(GPT_NAME_CHARS).enumerate() {
+            raw[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+}
+
+/// 读取 GPT 时两份头的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GptHealth {
+    /// 主 GPT 头和分区项有效
+    pub primary_valid: bool,
+    /// 备份 GPT 头和分区项有效
+    pub backup_valid: bool,
+}
+
+impl GptHealth {
+    /// 两份 GPT 是否都有效
+    pub fn is_healthy(&self) -> bool {
+        self.primary_valid && self.backup_valid
+    }
+}
+
+/// GPT 分区表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gpt {
+    /// 磁盘 GUID
+    pub disk_guid: Guid,
+    /// 第一个可用 LBA
+    pub first_usable_lba: u64,
+    /// 最后一个可用 LBA
+    pub last_usable_lba: u64,
+    /// 分区项数量
+    pub num_entries: u32,
+    /// 单个分区项大小
+    pub entry_size: u32,
+    /// 已使用的分区项（按数组顺序）
+    pub entries: Vec<GptEntry>,
+}
+
+impl Gpt {
+    /// 为指定磁盘创建空 GPT（磁盘太小放不下两份 GPT 时返回错误）
+    pub fn new(geometry: &DiskGeometry) -> Result<Self> {
+        let array_sectors = (GPT_DEFAULT_ENTRY_COUNT as u64 * GPT_DEFAULT_ENTRY_SIZE as u64)
+            .div_ceil(geometry.sector_size);
+        let first_usable_lba = 2 + array_sectors;
+        let last_usable_lba = geometry
+            .total_sectors
+            .checked_sub(2 + array_sectors)
+            .filter(|&last| last >= first_usable_lba)
+            .ok_or_else(|| anyhow::anyhow!("磁盘太小，无法创建 GPT ({} 扇区)", geometry.total_sectors))?;
+        Ok(Self {
+            disk_guid: Guid::new_random(),
+            first_usable_lba,
+            last_usable_lba,
+            num_entries: GPT_DEFAULT_ENTRY_COUNT,
+            entry_size: GPT_DEFAULT_ENTRY_SIZE,
+            entries: Vec::new(),
+        })
+    }
+
+    /// 分区项数组占用的扇区数
+    pub fn entry_array_sectors(&self, sector_size: u64) -> u64 {
+        (self.num_entries as u64 * self.entry_size as u64).div_ceil(sector_size)
+    }
+
+    /// 每个分区项在数组中的位置：指定了 slot 的保持不变，其余按顺序放入空位
+    pub fn slot_indices(&self) -> Vec<usize> {
+        let mut taken: Vec<usize> = self.entries.iter().filter_map(|e| e.slot).map(|s| s as usize).collect();
+        let mut next_free = 0;
+        self.entries
+            .iter()
+            .map(|e| match e.slot {
+                Some(slot) => slot as usize,
+                None => {
+                    while taken.contains(&next_free) {
+                        next_free += 1;
+                    }
+                    taken.push(next_free);
+                    next_free
+                }
+            })
+            .collect()
+    }
+
+    /// 校验布局（范围与重叠）
+    pub fn validate(&self) -> Result<()> {
+        if self.entries.len() > self.num_entries as usize {
+            bail!("分区数量超过 GPT 分区项上限 {}", self.num_entries);
+        }
+        let mut slots = self.slot_indices();
+        if slots.iter().any(|&slot| slot >= self.num_entries as usize) {
+            bail!("分区项位置超过 GPT 分区项上限 {}", self.num_entries);
+        }
+        slots.sort_unstable();
+        if slots.windows(2).any(|w| w[0] == w[1]) {
+            bail!("多个分区项使用了同一位置");
+        }
+        for entry in &self.entries {
+            if entry.type_guid.is_zero() {
+                bail!("分区类型 GUID 不能为空");
+            }
+            if entry.first_lba > entry.last_lba {
+                bail!("分区起始扇区大于结束扇区");
+            }
+            if entry.first_lba < self.first_usable_lba || entry.last_lba > self.last_usable_lba {
+                bail!(
+                    "分区 {}-{} 超出可用范围 {}-{}",
+                    entry.first_lba,
+                    entry.last_lba,
+                    self.first_usable_lba,
+                    self.last_usable_lba
+                );
+            }
+        }
+        check_overlaps(
+            self.entries
+                .iter()
+                .map(|e| (e.first_lba, e.last_lba))
+                .collect(),
+        )
+    }
+
+    fn encode_entries(&self) -> Vec<u8> {
+        let size = self.entry_size as usize;
+        let mut array = vec![0u8; self.num_entries as usize * size];
+        for (entry, i) in self.entries.iter().zip(self.slot_indices()) {
+            entry.encode_into(&mut array[i * size..(i + 1) * size]);
+        }
+        array
+    }
+
+    fn header(&self, geometry: &DiskGeometry, primary: bool, entries_crc: u32) -> GptHeader {
+        let last_lba = geometry.total_sectors - 1;
+        let array_sectors = self.entry_array_sectors(geometry.sector_size);
+        GptHeader {
+            current_lba: if primary { 1 } else { last_lba },
+            backup_lba: if primary { last_lba } else { 1 },
+            first_usable_lba: self.first_usable_lba,
+            last_usable_lba: self.last_usable_lba,
+            disk_guid: self.disk_guid,
+            partition_entry_lba: if primary { 2 } else { last_lba - array_sectors },
+            num_partition_entries: self.num_entries,
+            partition_entry_size: self.entry_size,
+            partition_entries_crc32: entries_crc,
+        }
+    }
+
+    /// 从头读取分区项数组并校验 CRC
+    fn read_entries<D: Read + Seek>(
+        dev: &mut D,
+        geometry: &DiskGeometry,
+        header: &GptHeader,
+    ) -> Result<Self> {
+        let array_len = header.entry_array_len()?;
+        let array_sectors = header.entry_array_sectors(geometry.sector_size);
+        if header.partition_entry_lba + array_sectors > geometry.total_sectors {
+            bail!("GPT 分区项数组超出磁盘末尾");
+        }
+        let data = read_sectors(dev, geometry, header.partition_entry_lba, array_sectors)?;
+        let array = &data[..array_len];
+        if crc32(array) != header.partition_entries_crc32 {
+            bail!("GPT 分区项 CRC 校验失败");
+        }
+
+        // 空位之后的分区项记录原位置，保证编号与 diskpart 一致
+        let mut entries: Vec<GptEntry> = Vec::new();
+        for (slot, raw) in array.chunks_exact(header.partition_entry_size as usize).enumerate() {
+            let mut entry = GptEntry::decode(raw);
+            if entry.type_guid.is_zero() {
+                continue;
+            }
+            if slot != entries.len() {
+                entry.slot = Some(slot as u32);
+            }
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            disk_guid: header.disk_guid,
+            first_usable_lba: header.first_usable_lba,
+            last_usable_lba: header.last_usable_lba,
+            num_entries: header.num_partition_entries,
+            entry_size: header.partition_entry_size,
+            entries,
+        })
+    }
+
+    fn read_at<D: Read + Seek>(dev: &mut D, geometry: &DiskGeometry, lba: u64) -> Result<Self> {
+        let sector = read_sectors(dev, geometry, lba, 1)?;
+        let header = GptHeader::decode(&sector)?;
+        if header.current_lba != lba {
+            bail!("GPT 头位置不匹配 (期望 {}, 实际 {})", lba, header.current_lba);
+        }
+        Self::read_entries(dev, geometry, &header)
+    }
+
+    /// 读取 GPT（主 GPT 损坏时使用备份）
+    pub fn read<D: Read + Seek>(dev: &mut D, geometry: &DiskGeometry) -> Result<(Self, GptHealth)> {
+        let last_lba = geometry.total_sectors.saturating_sub(1);
+        let primary = Self::read_at(dev, geometry, 1);
+
+        let backup_lba = read_sectors(dev, geometry, 1, 1)
+            .ok()
+            .and_then(|s| GptHeader::decode(&s).ok())
+            .map(|h| h.backup_lba)
+            .filter(|&lba| lba > 1 && lba <= last_lba)
+            .unwrap_or(last_lba);
+        let backup = Self::read_at(dev, geometry, backup_lba);
+
+        let health = GptHealth {
+            primary_valid: primary.is_ok(),
+            backup_valid: backup.is_ok(),
+        };
+        match (primary, backup) {
+            (Ok(gpt), _) => Ok((gpt, health)),
+            (Err(_), Ok(gpt)) => {
+                log::warn!("主 GPT 损坏，使用备份 GPT");
+                Ok((gpt, health))
+            }
+            (Err(e), Err(_)) => Err(e.context("主 GPT 与备份 GPT 均无效")),
+        }
+    }
+
+    /// 写入保护性 MBR、主 GPT 和备份 GPT
+    pub fn write<D: Write + Seek>(&self, dev: &mut D, geometry: &DiskGeometry) -> Result<()> {
+        self.validate()?;
+        let array_sectors = self.entry_array_sectors(geometry.sector_size);
+        if self.first_usable_lba < 2 + array_sectors
+            || self.last_usable_lba + 1 + array_sectors >= geometry.total_sectors
+        {
+            bail!("GPT 可用范围与磁盘大小不匹配");
+        }
+
+        let array = self.encode_entries();
+        let entries_crc = crc32(&array);
+        let primary = self.header(geometry, true, entries_crc);
+        let backup = self.header(geometry, false, entries_crc);
+
+        let mut pmbr = Mbr::protective(geometry.total_sectors).encode().to_vec();
+        pmbr.resize(geometry.sector_size as usize, 0);
+        write_sectors(dev, geometry, 0, &pmbr)?;
+        write_sectors(dev, geometry, 1, &primary.encode(geometry.sector_size))?;
+        write_sectors(dev, geometry, primary.partition_entry_lba, &array)?;
+        write_sectors(dev, geometry, backup.partition_entry_lba, &array)?;
+        write_sectors(dev, geometry, backup.current_lba, &backup.encode(geometry.sector_size))?;
+        Ok(())
+    }
+}
+
+/// 检查区间是否重叠
+fn check_overlaps(mut ranges: Vec<(u64, u64)>) -> Result<()> {
+    ranges.sort_unstable();
+    for pair in ranges.windows(2) {
+        if pair[1].0 <= pair[0].1 {
+            bail!(
+                "分区重叠: {}-{} 与 {}-{}",
+                pair[0].0,
+                pair[0].1,
+                pair[1].0,
+                pair[1].1
+            );
+        }
+    }
+    Ok(())
+}
+
+// ==================== 统一接口 ====================
+
+/// 分区表类型
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PartitionStyle {
+    GPT,
+    MBR,
+    #[default]
+    Unknown,
+}
+
+impl fmt::Display for PartitionStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionStyle::GPT => write!(f, "GPT"),
+            PartitionStyle::MBR => write!(f, "MBR"),
+            PartitionStyle::Unknown => write!(f, "未知"),
+        }
+    }
+}
+
+/// 分区表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionTable {
+    /// 未初始化
+    Raw,
+    /// MBR 分区表
+    Mbr(MbrLayout),
+    /// GPT 分区表
+    Gpt(Gpt, GptHealth),
+}
+
+/// 分区类型信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionKind {
+    /// MBR 分区
+    Mbr {
+        partition_type: u8,
+        bootable: bool,
+        logical: bool,
+    },
+    /// GPT 分区
+    Gpt {
+        type_guid: Guid,
+        unique_guid: Guid,
+        attributes: u64,
+        name: String,
+    },
+}
+
+/// 分区表中的一个分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEntry {
+    /// 分区编号（从 1 开始，与 Windows 编号规则一致）
+    pub number: u32,
+    /// 起始 LBA
+    pub start_lba: u64,
+    /// 扇区数
+    pub sector_count: u64,
+    /// 类型信息
+    pub kind: PartitionKind,
+}
+
+impl PartitionEntry {
+    /// 类型文本（GPT 为类型 GUID，MBR 为 0xNN）
+    pub fn type_string(&self) -> String {
+        match &self.kind {
+            PartitionKind::Mbr { partition_type, .. } => format!("0x{:02X}", partition_type),
+            PartitionKind::Gpt { type_guid, .. } => type_guid.to_string(),
+        }
+    }
+
+    /// 是否为 EFI 系统分区
+    pub fn is_esp(&self) -> bool {
+        match &self.kind {
+            PartitionKind::Gpt { type_guid, .. } => *type_guid == ESP_PARTITION_TYPE,
+            PartitionKind::Mbr { partition_type, .. } => *partition_type == 0xEF,
+        }
+    }
+
+    /// 是否为微软保留分区
+    pub fn is_msr(&self) -> bool {
+        matches!(&self.kind, PartitionKind::Gpt { type_guid, .. } if *type_guid == MSR_PARTITION_TYPE)
+    }
+
+    /// 是否为恢复分区
+    pub fn is_recovery(&self) -> bool {
+        match &self.kind {
+            PartitionKind::Gpt { type_guid, .. } => *type_guid == RECOVERY_PARTITION_TYPE,
+            PartitionKind::Mbr { partition_type, .. } => *partition_type == MBR_TYPE_RECOVERY,
+        }
+    }
+}
+
+impl PartitionTable {
+    /// 从块设备读取分区表
+    pub fn read<D: Read + Seek>(dev: &mut D, geometry: &DiskGeometry) -> Result<Self> {
+        if geometry.total_sectors < 2 {
+            bail!("磁盘太小");
+        }
+        let sector0 = read_sectors(dev, geometry, 0, 1)?;
+        let mbr = Mbr::decode(&sector0);
+
+        // 保护性 MBR 或 MBR 缺失时尝试 GPT（可能只有 MBR 被清除）
+        let try_gpt = match &mbr {
+            Ok(mbr) => mbr.is_protective(),
+            Err(_) => true,
+        };
+        if try_gpt {
+            match Gpt::read(dev, geometry) {
+                Ok((gpt, health)) => return Ok(PartitionTable::Gpt(gpt, health)),
+                Err(e) if mbr.is_ok() => return Err(e),
+                Err(_) => return Ok(PartitionTable::Raw),
+            }
+        }
+
+        let mbr = mbr?;
+        if mbr.entries.iter().all(|e| e.is_empty()) {
+            return Ok(PartitionTable::Raw);
+        }
+        Ok(PartitionTable::Mbr(MbrLayout::read(dev, geometry, mbr)?))
+    }
+
+    /// 写入分区表
+    pub fn write<D: Write + Seek>(&self, dev: &mut D, geometry: &DiskGeometry) -> Result<()> {
+        match self {
+            PartitionTable::Raw => bail!("未初始化的磁盘没有可写入的分区表"),
+            PartitionTable::Mbr(layout) => layout.write(dev, geometry),
+            PartitionTable::Gpt(gpt, _) => gpt.write(dev, geometry),
+        }
+    }
+
+    /// 列出所有分区（不含扩展分区容器本身）
+    pub fn partitions(&self) -> Vec<PartitionEntry> {
+        match self {
+            PartitionTable::Raw => Vec::new(),
+            PartitionTable::Mbr(layout) => {
+                let primaries = layout
+                    .mbr
+                    .entries
+                    .iter()
+                    .filter(|e| !e.is_empty() && !e.is_extended())
+                    .map(|e| (e, false));
+                let logicals = layout.logical.iter().map(|e| (e, true));
+                primaries
+                    .chain(logicals)
+                    .enumerate()
+                    .map(|(i, (e, logical))| PartitionEntry {
+                        number: i as u32 + 1,
+                        start_lba: e.start_lba as u64,
+                        sector_count: e.sector_count as u64,
+                        kind: PartitionKind::Mbr {
+                            partition_type: e.partition_type,
+                            bootable: e.bootable,
+                            logical,
+                        },
+                    })
+                    .collect()
+            }
+            PartitionTable::Gpt(gpt, _) => gpt
+                .entries
+                .iter()
+                .zip(gpt.slot_indices())
+                .map(|(e, slot)| PartitionEntry {
+                    number: slot as u32 + 1,
+                    start_lba: e.first_lba,
+                    sector_count: e.sector_count(),
+                    kind: PartitionKind::Gpt {
+                        type_guid: e.type_guid,
+                        unique_guid: e.unique_guid,
+                        attributes: e.attributes,
+                        name: e.name.clone(),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::path::PathBuf;
+
+    const MIB: u64 = 1024 * 1024;
+
+    /// 创建稀疏镜像文件
+    fn sparse_image(name: &str, size: u64) -> (PathBuf, File) {
+        let dir = std::env::temp_dir().join(format!(
+            "letrecovery_pt_{}_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join("disk.img");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(size).unwrap();
+        (dir, file)
+    }
+
+    #[test]
+    fn test_crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_guid_roundtrip() {
+        let text = "C12A7328-F81F-11D2-BA4B-00A0C93EC93B";
+        let guid = Guid::parse(text).unwrap();
+        assert_eq!(guid, ESP_PARTITION_TYPE);
+        assert_eq!(guid.to_string(), text);
+        assert_eq!(Guid::parse("{ebd0a0a2-b9e5-4433-87c0-68b6b72699c7}"), Some(BASIC_DATA_PARTITION_TYPE));
+        assert!(Guid::parse("not-a-guid").is_none());
+        assert_ne!(Guid::new_random(), Guid::new_random());
+    }
+
+    #[test]
+    fn test_gpt_roundtrip_and_backup_recovery() {
+        let (dir, mut file) = sparse_image("gpt", 256 * MIB);
+        let geometry = DiskGeometry::from_stream(&mut file, DEFAULT_SECTOR_SIZE).unwrap();
+
+        let mut gpt = Gpt::new(&geometry).unwrap();
+        gpt.entries.push(GptEntry::new(ESP_PARTITION_TYPE, 2048, 206_847, "EFI system partition"));
+        gpt.entries.push(GptEntry::new(MSR_PARTITION_TYPE, 206_848, 239_615, "Microsoft reserved partition"));
+        gpt.entries.push(GptEntry::new(BASIC_DATA_PARTITION_TYPE, 239_616, gpt.last_usable_lba, "数据"));
+        gpt.write(&mut file, &geometry).unwrap();
+
+        let table = PartitionTable::read(&mut file, &geometry).unwrap();
+        let PartitionTable::Gpt(read_back, health) = &table else {
+            panic!("应为 GPT");
+        };
+        assert!(health.is_healthy());
+        assert_eq!(read_back, &gpt);
+
+        let parts = table.partitions();
+        assert_eq!(parts.len(), 3);
+        assert!(parts[0].is_esp());
+        assert!(parts[1].is_msr());
+        assert_eq!(parts[2].number, 3);
+
+        // 破坏主 GPT 头后应从备份恢复
+        write_sectors(&mut file, &geometry, 1, &[0xFFu8; 512]).unwrap();
+        let table = PartitionTable::read(&mut file, &geometry).unwrap();
+        let PartitionTable::Gpt(from_backup, health) = table else {
+            panic!("应为 GPT");
+        };
+        assert!(!health.primary_valid && health.backup_valid);
+        assert_eq!(from_backup, gpt);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_gpt_rejects_overlap() {
+        let geometry = DiskGeometry::new(DEFAULT_SECTOR_SIZE, 64 * MIB);
+        let mut gpt = Gpt::new(&geometry).unwrap();
+        gpt.entries.push(GptEntry::new(BASIC_DATA_PARTITION_TYPE, 2048, 10_000, ""));
+        gpt.entries.push(GptEntry::new(BASIC_DATA_PARTITION_TYPE, 9_000, 20_000, ""));
+        assert!(gpt.validate().is_err());
+    }
+
+    #[test]
+    fn test_gpt_new_rejects_tiny_disk() {
+        assert!(Gpt::new(&DiskGeometry::new(DEFAULT_SECTOR_SIZE, 16 * 1024)).is_err());
+        assert!(Gpt::new(&DiskGeometry::new(DEFAULT_SECTOR_SIZE, 0)).is_err());
+        assert!(Gpt::new(&DiskGeometry::new(DEFAULT_SECTOR_SIZE, MIB)).is_ok());
+    }
+
+    #[test]
+    fn test_gpt_header_rejects_bad_entry_size() {
+        let geometry = DiskGeometry::new(DEFAULT_SECTOR_SIZE, 64 * MIB);
+        let gpt = Gpt::new(&geometry).unwrap();
+        let mut header = gpt.header(&geometry, true, 0);
+        assert_eq!(header.entry_array_len().unwrap(), 128 * 128);
+
+        for (count, size) in [(128, 64), (128, 132), (128, 8192), (1024, 4096)] {
+            header.num_partition_entries = count;
+            header.partition_entry_size = size;
+            assert!(header.entry_array_len().is_err(), "{} x {}", count, size);
+            assert!(GptHeader::decode(&header.encode(DEFAULT_SECTOR_SIZE)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_gpt_keeps_slot_numbers_across_gaps() {
+        let (dir, mut file) = sparse_image("gpt_gap", 64 * MIB);
+        let geometry = DiskGeometry::from_stream(&mut file, DEFAULT_SECTOR_SIZE).unwrap();
+
+        // 第 2 个分区项为空（例如删除分区后留下的空位）
+        let mut gpt = Gpt::new(&geometry).unwrap();
+        gpt.entries.push(GptEntry::new(ESP_PARTITION_TYPE, 2048, 4095, "EFI"));
+        gpt.entries.push(GptEntry {
+            slot: Some(2),
+            ..GptEntry::new(BASIC_DATA_PARTITION_TYPE, 4096, 8191, "Data")
+        });
+        gpt.write(&mut file, &geometry).unwrap();
+
+        let table = PartitionTable::read(&mut file, &geometry).unwrap();
+        let numbers: Vec<u32> = table.partitions().iter().map(|p| p.number).collect();
+        assert_eq!(numbers, vec![1, 3]);
+        let PartitionTable::Gpt(mut read_back, _) = table else {
+            panic!("应为 GPT");
+        };
+        assert_eq!(read_back, gpt);
+
+        // 新分区填入空位，已有分区的编号不变
+        read_back.entries.push(GptEntry::new(BASIC_DATA_PARTITION_TYPE, 8192, 12_287, "New"));
+        read_back.write(&mut file, &geometry).unwrap();
+        let table = PartitionTable::read(&mut file, &geometry).unwrap();
+        let numbers: Vec<u32> = table.partitions().iter().map(|p| p.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert_eq!(table.partitions()[1].start_lba, 8192);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_mbr_with_logical_partitions_roundtrip() {
+        let (dir, mut file) = sparse_image("mbr", 64 * MIB);
+        let geometry = DiskGeometry::from_stream(&mut file, DEFAULT_SECTOR_SIZE).unwrap();
+
+        let mut layout = MbrLayout::default();
+        layout.mbr.disk_signature = 0x1234_5678;
+        layout.mbr.entries[0] = MbrEntry {
+            bootable: true,
+            partition_type: MBR_TYPE_NTFS,
+            start_lba: 2048,
+            sector_count: 40_960,
+        };
+        layout.mbr.entries[1] = MbrEntry {
+            bootable: false,
+            partition_type: MBR_TYPE_EXTENDED_LBA,
+            start_lba: 43_008,
+            sector_count: 88_064,
+        };
+        layout.logical = vec![
+            MbrEntry {
+                bootable: false,
+                partition_type: MBR_TYPE_NTFS,
+                start_lba: 45_056,
+                sector_count: 40_960,
+            },
+            MbrEntry {
+                bootable: false,
+                partition_type: MBR_TYPE_FAT32_LBA,
+                start_lba: 88_064,
+                sector_count: 43_008,
+            },
+        ];
+
+        PartitionTable::Mbr(layout.clone()).write(&mut file, &geometry).unwrap();
+        let table = PartitionTable::read(&mut file, &geometry).unwrap();
+        assert_eq!(table, PartitionTable::Mbr(layout));
+
+        let parts = table.partitions();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2].start_lba, 88_064);
+        assert_eq!(parts[2].type_string(), "0x0C");
+        assert!(matches!(parts[1].kind, PartitionKind::Mbr { logical: true, .. }));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_blank_disk_is_raw() {
+        let (dir, mut file) = sparse_image("raw", 8 * MIB);
+        let geometry = DiskGeometry::from_stream(&mut file, DEFAULT_SECTOR_SIZE).unwrap();
+        assert_eq!(PartitionTable::read(&mut file, &geometry).unwrap(), PartitionTable::Raw);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,1657 @@
+//! 原始扇区镜像模块
+//!
+//! WIM / GHO 按文件备份，无法处理 BitLocker 锁定、Linux 或未知文件系统的分区。
+//! 本模块逐扇区读取分区或整块磁盘，生成分块、带校验的 zstd 压缩镜像（`.lri`）：
+//!
+//! - 能读取文件系统位图时（NTFS `$Bitmap`、FAT 表）跳过未使用的簇，
+//!   未使用区域在镜像中不占空间，还原后读取为零
+//! - 每个数据块单独压缩并记录 CRC32，校验和还原时逐块检查
+//! - 文件头为 JSON，记录来源、大小、文件系统和压缩参数
+//! - 可导出为普通的稀疏磁盘镜像（Linux 下可用 loop 设备挂载），
+//!   Windows 下可转换为 VHD 后只读挂载
+//!
+//! 镜像布局：
+//!
+//! ```text
+//! "LRRAWIMG" | 版本 u32 | 头长度 u32 | JSON 头
+//! 数据块记录 × N：32 字节记录头 + 数据（全零块不存数据）
+//! "LRRAWIDX" | 数量 u64 | (块号 u64, 记录位置 u64) × N
+//! 索引位置 u64 | "LRRAWEND"
+//! ```
+//!
+//! 索引缺失（备份中断）时按顺序扫描数据块，但校验和还原会拒绝不完整的镜像。
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::partition_table::crc32;
+
+/// 镜像文件扩展名
+pub const IMAGE_EXTENSION: &str = "lri";
+/// 默认数据块大小（4 MiB）
+pub const DEFAULT_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+/// 默认 zstd 压缩级别
+pub const DEFAULT_LEVEL: i32 = 3;
+
+const IMAGE_MAGIC: &[u8; 8] = b"LRRAWIMG";
+const INDEX_MAGIC: &[u8; 8] = b"LRRAWIDX";
+const FOOTER_MAGIC: &[u8; 8] = b"LRRAWEND";
+const CHUNK_MAGIC: &[u8; 4] = b"CHNK";
+const IMAGE_VERSION: u32 = 1;
+const PREAMBLE_SIZE: u64 = 16;
+const RECORD_SIZE: usize = 32;
+const FOOTER_SIZE: u64 = 16;
+/// JSON 头的长度上限，防止读取损坏文件时分配过大内存
+const MAX_HEADER_LEN: u32 = 1024 * 1024;
+
+const MIN_CHUNK_SIZE: u32 = 64 * 1024;
+const MAX_CHUNK_SIZE: u32 = 64 * 1024 * 1024;
+
+/// 数据块以 zstd 压缩存储
+const CHUNK_COMPRESSED: u32 = 1;
+/// 数据块内容全为零，不存储数据
+const CHUNK_ZERO: u32 = 2;
+
+// ==================== 镜像头 ====================
+
+/// 镜像来源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourceKind {
+    /// 单个分区（卷）
+    Partition,
+    /// 整块磁盘（包含分区表）
+    Disk,
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceKind::Partition => write!(f, "分区"),
+            SourceKind::Disk => write!(f, "磁盘"),
+        }
+    }
+}
+
+/// 识别出的文件系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileSystemKind {
+    Ntfs,
+    Fat12,
+    Fat16,
+    Fat32,
+    ExFat,
+    BitLocker,
+    Unknown,
+}
+
+impl fmt::Display for FileSystemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileSystemKind::Ntfs => write!(f, "NTFS"),
+            FileSystemKind::Fat12 => write!(f, "FAT12"),
+            FileSystemKind::Fat16 => write!(f, "FAT16"),
+            FileSystemKind::Fat32 => write!(f, "FAT32"),
+            FileSystemKind::ExFat => write!(f, "exFAT"),
+            FileSystemKind::BitLocker => write!(f, "BitLocker"),
+            FileSystemKind::Unknown => write!(f, "未知"),
+        }
+    }
+}
+
+/// 镜像文件的 JSON 头
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawImageHeader {
+    pub version: u32,
+    /// 来源设备（如 `\\.\D:`、`/dev/loop0`）
+    pub source: String,
+    pub source_kind: SourceKind,
+    /// 来源设备的总字节数
+    pub size_bytes: u64,
+    pub sector_size: u32,
+    pub chunk_size: u32,
+    pub file_system: FileSystemKind,
+    /// 按文件系统位图统计的已用字节数（无法识别时等于总大小）
+    pub used_bytes: u64,
+    pub compression: String,
+    pub level: i32,
+    /// 创建时间（Unix 时间戳，秒）
+    pub created: u64,
+    pub description: String,
+}
+
+impl RawImageHeader {
+    /// 数据块总数
+    pub fn chunk_count(&self) -> u64 {
+        self.size_bytes.div_ceil(self.chunk_size as u64)
+    }
+
+    /// 第 `chunk` 块在来源设备上的偏移和长度
+    pub fn chunk_range(&self, chunk: u64) -> (u64, usize) {
+        let offset = chunk * self.chunk_size as u64;
+        let len = (self.size_bytes - offset).min(self.chunk_size as u64);
+        (offset, len as usize)
+    }
+}
+
+// ==================== 分配位图 ====================
+
+/// 来源设备上已使用的字节区间（已排序、已合并）
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationMap {
+    pub file_system: FileSystemKind,
+    ranges: Vec<(u64, u64)>,
+}
+
+impl AllocationMap {
+    /// 整个设备都视为已使用
+    pub fn full(size: u64, file_system: FileSystemKind) -> Self {
+        Self { file_system, ranges: vec![(0, size)] }
+    }
+
+    /// 识别文件系统并读取位图；无法识别或解析失败时整体视为已使用
+    pub fn detect<D: Read + Seek>(dev: &mut D, size: u64) -> Self {
+        let mut boot = vec![0u8; 512];
+        if size < 512 || read_at(dev, 0, &mut boot).is_err() {
+            return Self::full(size, FileSystemKind::Unknown);
+        }
+
+        let file_system = identify_file_system(&boot);
+        let parsed = match file_system {
+            FileSystemKind::Ntfs => Self::ntfs(dev, size, &boot),
+            FileSystemKind::Fat12 | FileSystemKind::Fat16 | FileSystemKind::Fat32 => Self::fat(dev, size, &boot),
+            _ => return Self::full(size, file_system),
+        };
+        match parsed {
+            Ok(map) => map,
+            Err(e) => {
+                log::warn!("读取 {} 分配位图失败，将完整备份: {:#}", file_system, e);
+                Self::full(size, file_system)
+            }
+        }
+    }
+
+    /// 读取 NTFS `$Bitmap`（MFT 第 6 条记录）
+    fn ntfs<D: Read + Seek>(dev: &mut D, size: u64, boot: &[u8]) -> Result<Self> {
+        let bytes_per_sector = le16(boot, 11) as u64;
+        let sectors_per_cluster = match boot[13] {
+            n @ 1..=0x80 => n as u64,
+            n => 1u64 << (256 - n as u32),
+        };
+        let cluster_size = bytes_per_sector * sectors_per_cluster;
+        if !(512..=4096).contains(&bytes_per_sector) || !cluster_size.is_power_of_two() {
+            bail!("NTFS 引导扇区参数无效");
+        }
+        let total_clusters = le64(boot, 40) / sectors_per_cluster;
+        let mft_offset = le64(boot, 48)
+            .checked_mul(cluster_size)
+            .context("MFT 位置无效")?;
+        let record_size = match boot[64] as i8 {
+            n if n > 0 => n as u64 * cluster_size,
+            n => 1u64 << (-(n as i32)),
+        };
+        if !(512..=65536).contains(&record_size) {
+            bail!("MFT 记录大小无效: {}", record_size);
+        }
+
+        let mut record = vec![0u8; record_size as usize];
+        read_at(dev, mft_offset + 6 * record_size, &mut record).context("读取 $Bitmap 记录失败")?;
+        apply_fixups(&mut record)?;
+        let bitmap = ntfs_data_attribute(dev, &record, cluster_size)?;
+
+        let mut map = RangeBuilder::new(size);
+        map.push(0, cluster_size);
+        for (byte_index, &byte) in bitmap.iter().enumerate() {
+            if byte == 0 {
+                continue;
+            }
+            for bit in 0..8u64 {
+                let cluster = byte_index as u64 * 8 + bit;
+                if cluster < total_clusters && byte & (1 << bit) != 0 {
+                    map.push(cluster * cluster_size, cluster_size);
+                }
+            }
+        }
+        // 簇区之后的扇区（备份引导扇区）始终保留
+        let cluster_end = total_clusters * cluster_size;
+        map.push(cluster_end, size.saturating_sub(cluster_end));
+        Ok(map.build(FileSystemKind::Ntfs))
+    }
+
+    /// 读取第一份 FAT 表，表项非零的簇视为已使用
+    fn fat<D: Read + Seek>(dev: &mut D, size: u64, boot: &[u8]) -> Result<Self> {
+        let layout = FatLayout::parse(boot).context("FAT 引导扇区参数无效")?;
+        let mut fat = vec![0u8; layout.fat_bytes as usize];
+        read_at(dev, layout.fat_offset, &mut fat).context("读取 FAT 表失败")?;
+
+        let mut map = RangeBuilder::new(size);
+        map.push(0, layout.data_offset);
+        let entries = (fat.len() as u64 * 8 / layout.entry_bits()).min(layout.clusters + 2);
+        for cluster in 2..entries {
+            if layout.entry(&fat, cluster) != 0 {
+                map.push(layout.data_offset + (cluster - 2) * layout.cluster_size, layout.cluster_size);
+            }
+        }
+        let data_end = layout.data_offset + layout.clusters * layout.cluster_size;
+        map.push(data_end, size.saturating_sub(data_end));
+        Ok(map.build(layout.kind))
+    }
+
+    /// 已使用的字节数
+    pub fn used_bytes(&self) -> u64 {
+        self.ranges.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// `[offset, offset + len)` 中是否有已使用的字节
+    pub fn is_used(&self, offset: u64, len: u64) -> bool {
+        let end = offset + len;
+        let index = self.ranges.partition_point(|&(_, range_end)| range_end <= offset);
+        self.ranges.get(index).is_some_and(|&(start, _)| start < end)
+    }
+
+    /// 把缓冲区（对应设备上的 `offset`）中未使用的部分清零，提高压缩率
+    pub fn zero_unused(&self, offset: u64, buf: &mut [u8]) {
+        let end = offset + buf.len() as u64;
+        let mut cursor = offset;
+        let first = self.ranges.partition_point(|&(_, range_end)| range_end <= offset);
+        for &(start, range_end) in &self.ranges[first..] {
+            if start >= end {
+                break;
+            }
+            if start > cursor {
+                buf[(cursor - offset) as usize..(start - offset) as usize].fill(0);
+            }
+            cursor = cursor.max(range_end);
+        }
+        if cursor < end {
+            buf[(cursor - offset) as usize..].fill(0);
+        }
+    }
+}
+
+/// 逐个追加区间并合并相邻部分
+struct RangeBuilder {
+    limit: u64,
+    ranges: Vec<(u64, u64)>,
+}
+
+impl RangeBuilder {
+    fn new(limit: u64) -> Self {
+        Self { limit, ranges: Vec::new() }
+    }
+
+    /// 追加区间（起点必须不小于已有区间的起点）
+    fn push(&mut self, start: u64, len: u64) {
+        let end = (start + len).min(self.limit);
+        if start >= end {
+            return;
+        }
+        match self.ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => self.ranges.push((start, end)),
+        }
+    }
+
+    fn build(mut self, file_system: FileSystemKind) -> AllocationMap {
+        self.ranges.sort_unstable();
+        let mut ranges: Vec<(u64, u64)> = Vec::with_capacity(self.ranges.len());
+        for (start, end) in self.ranges {
+            match ranges.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => ranges.push((start, end)),
+            }
+        }
+        AllocationMap { file_system, ranges }
+    }
+}
+
+/// 根据引导扇区识别文件系统
+pub fn identify_file_system(boot: &[u8]) -> FileSystemKind {
+    match &boot[3..11] {
+        b"NTFS    " => return FileSystemKind::Ntfs,
+        b"EXFAT   " => return FileSystemKind::ExFat,
+        b"-FVE-FS-" => return FileSystemKind::BitLocker,
+        _ => {}
+    }
+    match FatLayout::parse(boot) {
+        Some(layout) => layout.kind,
+        None => FileSystemKind::Unknown,
+    }
+}
+
+/// FAT 文件系统布局
+struct FatLayout {
+    kind: FileSystemKind,
+    fat_offset: u64,
+    fat_bytes: u64,
+    data_offset: u64,
+    cluster_size: u64,
+    clusters: u64,
+}
+
+impl FatLayout {
+    fn parse(boot: &[u8]) -> Option<Self> {
+        if boot[510] != 0x55 || boot[511] != 0xAA {
+            return None;
+        }
+        let bytes_per_sector = le16(boot, 11) as u64;
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved = le16(boot, 14) as u64;
+        let fat_count = boot[16] as u64;
+        let root_entries = le16(boot, 17) as u64;
+        let total = match le16(boot, 19) {
+            0 => le32(boot, 32) as u64,
+            n => n as u64,
+        };
+        let fat_sectors = match le16(boot, 22) {
+            0 => le32(boot, 36) as u64,
+            n => n as u64,
+        };
+        if ![512, 1024, 2048, 4096].contains(&bytes_per_sector)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved == 0
+            || fat_count == 0
+            || fat_sectors == 0
+        {
+            return None;
+        }
+
+        let root_sectors = (root_entries * 32).div_ceil(bytes_per_sector);
+        let data_start = reserved + fat_count * fat_sectors + root_sectors;
+        let clusters = total.checked_sub(data_start)? / sectors_per_cluster;
+        let kind = match clusters {
+            0 => return None,
+            1..=4084 => FileSystemKind::Fat12,
+            4085..=65524 => FileSystemKind::Fat16,
+            _ => FileSystemKind::Fat32,
+        };
+        Some(Self {
+            kind,
+            fat_offset: reserved * bytes_per_sector,
+            fat_bytes: fat_sectors * bytes_per_sector,
+            data_offset: data_start * bytes_per_sector,
+            cluster_size: sectors_per_cluster * bytes_per_sector,
+            clusters,
+        })
+    }
+
+    fn entry_bits(&self) -> u64 {
+        match self.kind {
+            FileSystemKind::Fat12 => 12,
+            FileSystemKind::Fat16 => 16,
+            _ => 32,
+        }
+    }
+
+    fn entry(&self, fat: &[u8], cluster: u64) -> u32 {
+        match self.kind {
+            FileSystemKind::Fat12 => {
+                let offset = (cluster + cluster / 2) as usize;
+                let value = le16(fat, offset) as u32;
+                if cluster % 2 == 1 {
+                    value >> 4
+                } else {
+                    value & 0x0FFF
+                }
+            }
+            FileSystemKind::Fat16 => le16(fat, cluster as usize * 2) as u32,
+            _ => le32(fat, cluster as usize * 4) & 0x0FFF_FFFF,
+        }
+    }
+}
+
+/// 还原 NTFS 记录的更新序列（每 512 字节末尾两个字节）
+fn apply_fixups(record: &mut [u8]) -> Result<()> {
+    if &record[0..4] != b"FILE" {
+        bail!("MFT 记录签名无效");
+    }
+    let usa_offset = le16(record, 4) as usize;
+    let usa_count = le16(record, 6) as usize;
+    if usa_count == 0 || usa_offset + usa_count * 2 > record.len() || (usa_count - 1) * 512 > record.len() {
+        bail!("MFT 记录更新序列无效");
+    }
+    let sequence = [record[usa_offset], record[usa_offset + 1]];
+    for i in 1..usa_count {
+        let end = i * 512;
+        if record[end - 2..end] != sequence {
+            bail!("MFT 记录更新序列不匹配（记录损坏）");
+        }
+        record[end - 2] = record[usa_offset + i * 2];
+        record[end - 1] = record[usa_offset + i * 2 + 1];
+    }
+    Ok(())
+}
+
+/// 读取 MFT 记录中未命名 `$DATA` 属性的内容
+fn ntfs_data_attribute<D: Read + Seek>(dev: &mut D, record: &[u8], cluster_size: u64) -> Result<Vec<u8>> {
+    const ATTR_DATA: u32 = 0x80;
+    const ATTR_END: u32 = 0xFFFF_FFFF;
+
+    let mut offset = le16(record, 20) as usize;
+    while offset + 16 <= record.len() {
+        let attr_type = le32(record, offset);
+        let attr_len = le32(record, offset + 4) as usize;
+        if attr_type == ATTR_END || attr_len == 0 || offset + attr_len > record.len() {
+            break;
+        }
+        let attr = &record[offset..offset + attr_len];
+        offset += attr_len;
+        if attr_type != ATTR_DATA || attr[9] != 0 {
+            continue;
+        }
+
+        if attr[8] == 0 {
+            let value_len = le32(attr, 16) as usize;
+            let value_offset = le16(attr, 20) as usize;
+            return attr
+                .get(value_offset..value_offset + value_len)
+                .map(|v| v.to_vec())
+                .context("$Bitmap 常驻属性越界");
+        }
+
+        let runs_offset = le16(attr, 32) as usize;
+        let real_size = le64(attr, 48);
+        if real_size > 1 << 32 {
+            bail!("$Bitmap 大小异常: {}", real_size);
+        }
+        let mut data = Vec::with_capacity(real_size as usize);
+        for (lcn, clusters) in decode_runlist(&attr[runs_offset..])? {
+            let len = clusters * cluster_size;
+            let start = data.len();
+            data.resize(start + len as usize, 0);
+            if let Some(lcn) = lcn {
+                read_at(dev, lcn * cluster_size, &mut data[start..])?;
+            }
+            if data.len() as u64 >= real_size {
+                break;
+            }
+        }
+        data.truncate(real_size as usize);
+        return Ok(data);
+    }
+    bail!("未找到 $Bitmap 的数据属性")
+}
+
+/// 解析 NTFS 数据运行列表，返回 (起始簇号, 簇数)，稀疏段的簇号为 `None`
+fn decode_runlist(runs: &[u8]) -> Result<Vec<(Option<u64>, u64)>> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    let mut lcn: i64 = 0;
+    while pos < runs.len() && runs[pos] != 0 {
+        let header = runs[pos];
+        let len_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        pos += 1;
+        if len_size == 0 || len_size > 8 || offset_size > 8 || pos + len_size + offset_size > runs.len() {
+            bail!("运行列表格式无效");
+        }
+
+        let mut length = 0u64;
+        for i in 0..len_size {
+            length |= (runs[pos + i] as u64) << (8 * i);
+        }
+        pos += len_size;
+
+        if offset_size == 0 {
+            result.push((None, length));
+            continue;
+        }
+        let mut delta = 0i64;
+        for i in 0..offset_size {
+            delta |= (runs[pos + i] as i64) << (8 * i);
+        }
+        // 符号扩展
+        let shift = 64 - 8 * offset_size as u32;
+        delta = (delta << shift) >> shift;
+        pos += offset_size;
+
+        lcn += delta;
+        if lcn < 0 {
+            bail!("运行列表簇号为负");
+        }
+        result.push((Some(lcn as u64), length));
+    }
+    Ok(result)
+}
+
+// ==================== 备份 ====================
+
+/// 备份选项
+#[derive(Debug, Clone)]
+pub struct RawBackupOptions {
+    pub source: String,
+    pub source_kind: SourceKind,
+    pub sector_size: u32,
+    pub chunk_size: u32,
+    pub level: i32,
+    /// 按文件系统位图跳过未使用的簇
+    pub skip_unused: bool,
+    pub description: String,
+}
+
+impl Default for RawBackupOptions {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            source_kind: SourceKind::Partition,
+            sector_size: 512,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            level: DEFAULT_LEVEL,
+            skip_unused: true,
+            description: String::new(),
+        }
+    }
+}
+
+/// 备份 / 校验 / 还原的统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawImageSummary {
+    pub size_bytes: u64,
+    pub used_bytes: u64,
+    /// 镜像中存储的数据块（含全零块）
+    pub stored_chunks: u64,
+    pub zero_chunks: u64,
+    /// 未使用、未存入镜像的数据块
+    pub skipped_chunks: u64,
+    /// 存储的数据字节数（压缩后）
+    pub stored_bytes: u64,
+}
+
+impl RawImageSummary {
+    pub fn summary(&self) -> String {
+        format!(
+            "总大小 {:.2} GB，已用 {:.2} GB，数据块 {} 个（全零 {} 个，跳过 {} 个），压缩后 {:.2} GB",
+            gb(self.size_bytes),
+            gb(self.used_bytes),
+            self.stored_chunks,
+            self.zero_chunks,
+            self.skipped_chunks,
+            gb(self.stored_bytes),
+        )
+    }
+}
+
+/// 数据块记录头
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ChunkRecord {
+    flags: u32,
+    offset: u64,
+    raw_len: u32,
+    stored_len: u32,
+    crc: u32,
+}
+
+impl ChunkRecord {
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut buf = [0u8; RECORD_SIZE];
+        buf[0..4].copy_from_slice(CHUNK_MAGIC);
+        buf[4..8].copy_from_slice(&self.flags.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.offset.to_le_bytes());
+        buf[16..20].copy_from_slice(&self.raw_len.to_le_bytes());
+        buf[20..24].copy_from_slice(&self.stored_len.to_le_bytes());
+        buf[24..28].copy_from_slice(&self.crc.to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8; RECORD_SIZE]) -> Option<Self> {
+        if &buf[0..4] != CHUNK_MAGIC {
+            return None;
+        }
+        Some(Self {
+            flags: le32(buf, 4),
+            offset: le64(buf, 8),
+            raw_len: le32(buf, 16),
+            stored_len: le32(buf, 20),
+            crc: le32(buf, 24),
+        })
+    }
+}
+
+/// 备份设备到镜像流
+pub fn backup<D: Read + Seek, W: Write + Seek>(
+    dev: &mut D,
+    size: u64,
+    image: &mut W,
+    options: &RawBackupOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<RawImageSummary> {
+    let chunk_size = options.chunk_size;
+    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) || chunk_size & 4095 != 0 {
+        bail!("数据块大小无效: {}", chunk_size);
+    }
+    if size == 0 {
+        bail!("来源设备大小为 0");
+    }
+
+    progress(0, "正在读取文件系统位图...");
+    let map = if options.skip_unused {
+        AllocationMap::detect(dev, size)
+    } else {
+        let mut boot = vec![0u8; 512];
+        let file_system = if size >= 512 && read_at(dev, 0, &mut boot).is_ok() {
+            identify_file_system(&boot)
+        } else {
+            FileSystemKind::Unknown
+        };
+        AllocationMap::full(size, file_system)
+    };
+    log::info!(
+        "原始镜像: {} 文件系统 {}，已用 {} / {} 字节",
+        options.source,
+        map.file_system,
+        map.used_bytes(),
+        size
+    );
+
+    let header = RawImageHeader {
+        version: IMAGE_VERSION,
+        source: options.source.clone(),
+        source_kind: options.source_kind,
+        size_bytes: size,
+        sector_size: options.sector_size,
+        chunk_size,
+        file_system: map.file_system,
+        used_bytes: map.used_bytes(),
+        compression: "zstd".to_string(),
+        level: options.level,
+        created: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        description: options.description.clone(),
+    };
+    let json = serde_json::to_vec_pretty(&header)?;
+    image.write_all(IMAGE_MAGIC)?;
+    image.write_all(&IMAGE_VERSION.to_le_bytes())?;
+    image.write_all(&(json.len() as u32).to_le_bytes())?;
+    image.write_all(&json)?;
+
+    let mut compressor = zstd::bulk::Compressor::new(options.level).context("初始化 zstd 压缩器失败")?;
+    let mut summary = RawImageSummary { size_bytes: size, used_bytes: header.used_bytes, ..Default::default() };
+    let mut index: Vec<(u64, u64)> = Vec::new();
+    let mut position = PREAMBLE_SIZE + json.len() as u64;
+    let mut buf = vec![0u8; chunk_size as usize];
+    let mut done = 0u64;
+    let mut last_percent = 0;
+
+    for chunk in 0..header.chunk_count() {
+        let (offset, len) = header.chunk_range(chunk);
+        if !map.is_used(offset, len as u64) {
+            summary.skipped_chunks += 1;
+            continue;
+        }
+
+        let data = &mut buf[..len];
+        read_at(dev, offset, data).with_context(|| format!("读取偏移 {:#x} 失败", offset))?;
+        map.zero_unused(offset, data);
+
+        let crc = crc32(data);
+        let (flags, stored) = if data.iter().all(|&b| b == 0) {
+            summary.zero_chunks += 1;
+            (CHUNK_ZERO, Vec::new())
+        } else {
+            let compressed = compressor.compress(data).context("压缩数据块失败")?;
+            if compressed.len() < len {
+                (CHUNK_COMPRESSED, compressed)
+            } else {
+                (0, data.to_vec())
+            }
+        };
+
+        let record = ChunkRecord { flags, offset, raw_len: len as u32, stored_len: stored.len() as u32, crc };
+        image.write_all(&record.encode())?;
+        image.write_all(&stored)?;
+        index.push((chunk, position));
+        position += RECORD_SIZE as u64 + stored.len() as u64;
+        summary.stored_chunks += 1;
+        summary.stored_bytes += stored.len() as u64;
+
+        done += len as u64;
+        let percent = (done * 99 / summary.used_bytes.max(1)).min(99) as u8;
+        if percent != last_percent {
+            last_percent = percent;
+            progress(percent, &format!("正在备份 {:.2} / {:.2} GB", gb(done), gb(summary.used_bytes)));
+        }
+    }
+
+    let index_pos = position;
+    image.write_all(INDEX_MAGIC)?;
+    image.write_all(&(index.len() as u64).to_le_bytes())?;
+    for (chunk, record_pos) in &index {
+        image.write_all(&chunk.to_le_bytes())?;
+        image.write_all(&record_pos.to_le_bytes())?;
+    }
+    image.write_all(&index_pos.to_le_bytes())?;
+    image.write_all(FOOTER_MAGIC)?;
+    image.flush()?;
+
+    progress(100, "备份完成");
+    Ok(summary)
+}
+
+// ==================== 读取镜像 ====================
+
+/// 已打开的原始镜像
+pub struct RawImage<R> {
+    inner: R,
+    header: RawImageHeader,
+    /// 每个数据块的记录位置，`None` 表示未存入镜像（读取为零）
+    chunks: Vec<Option<u64>>,
+    complete: bool,
+}
+
+impl<R: Read + Seek> RawImage<R> {
+    /// 解析文件头和索引（索引缺失时顺序扫描数据块）
+    pub fn open(mut inner: R) -> Result<Self> {
+        let mut preamble = [0u8; PREAMBLE_SIZE as usize];
+        inner.seek(SeekFrom::Start(0))?;
+        inner.read_exact(&mut preamble).context("读取镜像头失败")?;
+        if &preamble[0..8] != IMAGE_MAGIC {
+            bail!("不是有效的原始扇区镜像");
+        }
+        let version = le32(&preamble, 8);
+        if version != IMAGE_VERSION {
+            bail!("不支持的镜像版本: {}", version);
+        }
+        let header_len = le32(&preamble, 12);
+        if header_len > MAX_HEADER_LEN {
+            bail!("镜像头过大: {} 字节", header_len);
+        }
+        let mut json = vec![0u8; header_len as usize];
+        inner.read_exact(&mut json).context("读取镜像头失败")?;
+        let header: RawImageHeader = serde_json::from_slice(&json).context("解析镜像头失败")?;
+        if header.size_bytes == 0
+            || !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&header.chunk_size)
+            || header.chunk_count() > u32::MAX as u64
+        {
+            bail!("镜像头参数无效");
+        }
+
+        let data_start = PREAMBLE_SIZE + header_len as u64;
+        let file_len = inner.seek(SeekFrom::End(0))?;
+        let mut image = Self {
+            inner,
+            chunks: vec![None; header.chunk_count() as usize],
+            header,
+            complete: false,
+        };
+        match image.read_index(data_start, file_len) {
+            Ok(true) => image.complete = true,
+            Ok(false) => {
+                log::warn!("原始镜像缺少索引，按顺序扫描数据块");
+                image.scan(data_start, file_len)?;
+            }
+            Err(e) => {
+                log::warn!("原始镜像索引损坏，按顺序扫描数据块: {:#}", e);
+                image.chunks.fill(None);
+                image.scan(data_start, file_len)?;
+            }
+        }
+        Ok(image)
+    }
+
+    /// 读取尾部索引，没有索引时返回 `false`
+    fn read_index(&mut self, data_start: u64, file_len: u64) -> Result<bool> {
+        if file_len < data_start + FOOTER_SIZE + 16 {
+            return Ok(false);
+        }
+        let mut footer = [0u8; FOOTER_SIZE as usize];
+        read_at(&mut self.inner, file_len - FOOTER_SIZE, &mut footer)?;
+        if &footer[8..16] != FOOTER_MAGIC {
+            return Ok(false);
+        }
+
+        let index_pos = le64(&footer, 0);
+        if index_pos < data_start || index_pos + 16 > file_len - FOOTER_SIZE {
+            bail!("索引位置无效");
+        }
+        let mut index_header = [0u8; 16];
+        read_at(&mut self.inner, index_pos, &mut index_header)?;
+        let count = le64(&index_header, 8);
+        if &index_header[0..8] != INDEX_MAGIC || index_pos + 16 + count * 16 != file_len - FOOTER_SIZE {
+            bail!("索引格式无效");
+        }
+
+        let mut entries = vec![0u8; count as usize * 16];
+        self.inner.read_exact(&mut entries)?;
+        for entry in entries.chunks_exact(16) {
+            let chunk = le64(entry, 0);
+            let record_pos = le64(entry, 8);
+            if chunk >= self.chunks.len() as u64 || record_pos < data_start || record_pos >= index_pos {
+                bail!("索引项无效");
+            }
+            self.chunks[chunk as usize] = Some(record_pos);
+        }
+        Ok(true)
+    }
+
+    /// 从数据区开头顺序扫描数据块记录
+    fn scan(&mut self, data_start: u64, file_len: u64) -> Result<()> {
+        let mut position = data_start;
+        let mut raw = [0u8; RECORD_SIZE];
+        while position + RECORD_SIZE as u64 <= file_len {
+            read_at(&mut self.inner, position, &mut raw)?;
+            let Some(record) = ChunkRecord::decode(&raw) else { break };
+            let end = position + RECORD_SIZE as u64 + record.stored_len as u64;
+            if end > file_len || record.offset % self.header.chunk_size as u64 != 0 {
+                break;
+            }
+            let chunk = record.offset / self.header.chunk_size as u64;
+            if chunk >= self.chunks.len() as u64 {
+                break;
+            }
+            self.chunks[chunk as usize] = Some(position);
+            position = end;
+        }
+        Ok(())
+    }
+
+    pub fn header(&self) -> &RawImageHeader {
+        &self.header
+    }
+
+    /// 镜像是否完整（包含尾部索引）
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// 存入镜像的数据块数量
+    pub fn stored_chunks(&self) -> u64 {
+        self.chunks.iter().filter(|c| c.is_some()).count() as u64
+    }
+
+    /// 读取并校验第 `chunk` 块，未存入镜像时返回 `None`
+    pub fn read_chunk(&mut self, chunk: u64) -> Result<Option<Vec<u8>>> {
+        Ok(self.read_chunk_with_record(chunk)?.map(|(_, data)| data))
+    }
+
+    fn read_chunk_with_record(&mut self, chunk: u64) -> Result<Option<(ChunkRecord, Vec<u8>)>> {
+        let Some(position) = self.chunks.get(chunk as usize).copied().flatten() else {
+            return Ok(None);
+        };
+        let (offset, len) = self.header.chunk_range(chunk);
+
+        let mut raw = [0u8; RECORD_SIZE];
+        read_at(&mut self.inner, position, &mut raw)?;
+        let record = ChunkRecord::decode(&raw).with_context(|| format!("数据块 {} 记录头损坏", chunk))?;
+        if record.offset != offset || record.raw_len as usize != len || record.stored_len as usize > len {
+            bail!("数据块 {} 记录头与镜像头不一致", chunk);
+        }
+
+        let data = if record.flags & CHUNK_ZERO != 0 {
+            vec![0u8; len]
+        } else {
+            let mut stored = vec![0u8; record.stored_len as usize];
+            self.inner.read_exact(&mut stored)?;
+            if record.flags & CHUNK_COMPRESSED != 0 {
+                zstd::bulk::decompress(&stored, len).with_context(|| format!("数据块 {} 解压失败", chunk))?
+            } else {
+                stored
+            }
+        };
+        if data.len() != len || crc32(&data) != record.crc {
+            bail!("数据块 {}（偏移 {:#x}）校验失败，镜像已损坏", chunk, offset);
+        }
+        Ok(Some((record, data)))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// 校验镜像中的全部数据块
+pub fn verify<R: Read + Seek>(image: &mut RawImage<R>, progress: &mut dyn FnMut(u8, &str)) -> Result<RawImageSummary> {
+    if !image.is_complete() {
+        bail!("镜像不完整（缺少尾部索引），备份可能被中断");
+    }
+    let header = image.header().clone();
+    let mut summary = RawImageSummary { size_bytes: header.size_bytes, used_bytes: header.used_bytes, ..Default::default() };
+    let total = image.stored_chunks().max(1);
+    for chunk in 0..header.chunk_count() {
+        match image.read_chunk_with_record(chunk)? {
+            Some((record, _)) => {
+                summary.stored_chunks += 1;
+                summary.stored_bytes += record.stored_len as u64;
+                if record.flags & CHUNK_ZERO != 0 {
+                    summary.zero_chunks += 1;
+                }
+                let percent = (summary.stored_chunks * 100 / total) as u8;
+                progress(percent, &format!("正在校验数据块 {} / {}", summary.stored_chunks, total));
+            }
+            None => summary.skipped_chunks += 1,
+        }
+    }
+    progress(100, "校验完成");
+    Ok(summary)
+}
+
+/// 还原选项
+#[derive(Debug, Clone)]
+pub struct RawRestoreOptions {
+    /// 还原前先校验整个镜像，避免写入一半才发现损坏
+    pub verify_first: bool,
+    /// 未存入镜像的区域写零（否则保留目标设备原有内容）
+    pub zero_fill: bool,
+    /// 目标是新建的稀疏文件或动态虚拟磁盘（未写入的区域读取为零），全零块不再写入
+    pub sparse_target: bool,
+}
+
+impl Default for RawRestoreOptions {
+    fn default() -> Self {
+        Self { verify_first: true, zero_fill: false, sparse_target: false }
+    }
+}
+
+/// 把镜像还原到设备（设备容量不能小于镜像来源）
+pub fn restore<R: Read + Seek, D: Write + Seek>(
+    image: &mut RawImage<R>,
+    dev: &mut D,
+    dev_size: u64,
+    options: &RawRestoreOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<RawImageSummary> {
+    let header = image.header().clone();
+    if dev_size < header.size_bytes {
+        bail!(
+            "目标容量不足: 需要 {:.2} GB，目标只有 {:.2} GB",
+            gb(header.size_bytes),
+            gb(dev_size)
+        );
+    }
+    if options.verify_first {
+        verify(image, &mut |p, _| progress(p / 2, "正在校验镜像..."))?;
+    } else if !image.is_complete() {
+        bail!("镜像不完整（缺少尾部索引），备份可能被中断");
+    }
+
+    let (base, span) = if options.verify_first { (50, 50) } else { (0, 100) };
+    let mut summary = RawImageSummary { size_bytes: header.size_bytes, used_bytes: header.used_bytes, ..Default::default() };
+    let count = header.chunk_count();
+    let zeros = vec![0u8; header.chunk_size as usize];
+    for chunk in 0..count {
+        let (offset, len) = header.chunk_range(chunk);
+        match image.read_chunk_with_record(chunk)? {
+            Some((record, data)) => {
+                if record.flags & CHUNK_ZERO == 0 || !options.sparse_target {
+                    write_at(dev, offset, &data)?;
+                }
+                summary.stored_chunks += 1;
+                summary.stored_bytes += record.stored_len as u64;
+                if record.flags & CHUNK_ZERO != 0 {
+                    summary.zero_chunks += 1;
+                }
+            }
+            None if options.zero_fill && !options.sparse_target => {
+                write_at(dev, offset, &zeros[..len])?;
+                summary.skipped_chunks += 1;
+            }
+            None => summary.skipped_chunks += 1,
+        }
+        let percent = base + (chunk * span / count) as u8;
+        progress(percent, &format!("正在还原 {:.2} / {:.2} GB", gb(offset + len as u64), gb(header.size_bytes)));
+    }
+    dev.flush()?;
+    progress(100, "还原完成");
+    Ok(summary)
+}
+
+/// 把镜像作为普通设备顺序 / 随机读取（未存入镜像的区域读取为零）
+pub struct RawImageReader<R> {
+    image: RawImage<R>,
+    position: u64,
+    cache: Option<(u64, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> RawImageReader<R> {
+    pub fn new(image: RawImage<R>) -> Self {
+        Self { image, position: 0, cache: None }
+    }
+
+    pub fn header(&self) -> &RawImageHeader {
+        self.image.header()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.image.header().size_bytes
+    }
+}
+
+impl<R: Read + Seek> Read for RawImageReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.size();
+        if self.position >= size || buf.is_empty() {
+            return Ok(0);
+        }
+        let chunk_size = self.image.header().chunk_size as u64;
+        let chunk = self.position / chunk_size;
+        let within = (self.position % chunk_size) as usize;
+
+        if self.cache.as_ref().map(|(c, _)| *c) != Some(chunk) {
+            let data = self
+                .image
+                .read_chunk(chunk)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:#}", e)))?
+                .unwrap_or_else(|| vec![0u8; self.image.header().chunk_range(chunk).1]);
+            self.cache = Some((chunk, data));
+        }
+        let data = &self.cache.as_ref().unwrap().1;
+        let len = buf.len().min(data.len() - within);
+        buf[..len].copy_from_slice(&data[within..within + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: Read + Seek> Seek for RawImageReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.size().checked_add_signed(d),
+            SeekFrom::Current(d) => self.position.checked_add_signed(d),
+        };
+        self.position = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无效的偏移"))?;
+        Ok(self.position)
+    }
+}
+
+// ==================== 文件操作 ====================
+
+/// 打开镜像文件
+pub fn open_image(path: &Path) -> Result<RawImage<File>> {
+    let file = File::open(path).with_context(|| format!("打开镜像 {} 失败", path.display()))?;
+    RawImage::open(file).with_context(|| format!("读取镜像 {} 失败", path.display()))
+}
+
+/// 备份设备到镜像文件（文件已存在时覆盖，失败时删除半成品）
+pub fn backup_to_file<D: Read + Seek>(
+    dev: &mut D,
+    size: u64,
+    image_path: &Path,
+    options: &RawBackupOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<RawImageSummary> {
+    let file = File::create(image_path).with_context(|| format!("创建 {} 失败", image_path.display()))?;
+    let mut writer = io::BufWriter::with_capacity(4 * 1024 * 1024, file);
+    let result = backup(dev, size, &mut writer, options, progress).and_then(|summary| {
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(summary)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(image_path);
+    }
+    result
+}
+
+/// 校验镜像文件
+pub fn verify_image(image_path: &Path, progress: &mut dyn FnMut(u8, &str)) -> Result<RawImageSummary> {
+    verify(&mut open_image(image_path)?, progress)
+}
+
+/// 导出为普通磁盘镜像文件（未使用和全零的区域不写入，文件系统支持时为稀疏文件）
+///
+/// 分区镜像导出后可在 Linux 下用 `mount -o loop,ro` 挂载，磁盘镜像可用 `losetup -P`。
+pub fn export_raw(image_path: &Path, out_path: &Path, progress: &mut dyn FnMut(u8, &str)) -> Result<RawImageSummary> {
+    let mut image = open_image(image_path)?;
+    let size = image.header().size_bytes;
+    let options = RawRestoreOptions { sparse_target: true, ..Default::default() };
+
+    let result = (|| -> Result<RawImageSummary> {
+        let mut out = File::create(out_path).with_context(|| format!("创建 {} 失败", out_path.display()))?;
+        out.set_len(size)?;
+        let summary = restore(&mut image, &mut out, size, &options, progress)?;
+        out.sync_all()?;
+        Ok(summary)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(out_path);
+    }
+    result
+}
+
+/// 卷设备路径（如 `\\.\D:`）
+pub fn volume_device_path(letter: char) -> String {
+    format!(r"\\.\{}:", letter.to_ascii_uppercase())
+}
+
+/// 物理磁盘设备路径（如 `\\.\PhysicalDrive1`）
+pub fn disk_device_path(disk_number: u32) -> String {
+    format!(r"\\.\PhysicalDrive{}", disk_number)
+}
+
+/// 打开的来源 / 目标设备
+pub struct Device {
+    pub file: File,
+    pub size: u64,
+    pub sector_size: u32,
+}
+
+/// 打开设备（Windows 卷或物理磁盘，其他平台为块设备或普通文件）
+#[cfg(not(windows))]
+pub fn open_device(path: &str, write: bool) -> Result<Device> {
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(path)
+        .with_context(|| format!("打开 {} 失败", path))?;
+    let size = file.seek(SeekFrom::End(0))?;
+    Ok(Device { file, size, sector_size: 512 })
+}
+
+#[cfg(windows)]
+pub fn open_device(path: &str, write: bool) -> Result<Device> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(path)
+        .with_context(|| format!("打开 {} 失败", path))?;
+    let (size, sector_size) = unsafe {
+        use windows::Win32::System::Ioctl::{
+            DISK_GEOMETRY, FSCTL_ALLOW_EXTENDED_DASD_IO, GET_LENGTH_INFORMATION, IOCTL_DISK_GET_DRIVE_GEOMETRY,
+            IOCTL_DISK_GET_LENGTH_INFO,
+        };
+
+        let handle = device_handle(&file);
+        // 允许读写卷末尾文件系统之外的扇区（如 NTFS 备份引导扇区）
+        let _ = ioctl::<()>(handle, FSCTL_ALLOW_EXTENDED_DASD_IO, None);
+        let mut length = GET_LENGTH_INFORMATION::default();
+        ioctl(handle, IOCTL_DISK_GET_LENGTH_INFO, Some(&mut length)).context("获取设备大小失败")?;
+        let mut geometry = DISK_GEOMETRY::default();
+        let sector_size = match ioctl(handle, IOCTL_DISK_GET_DRIVE_GEOMETRY, Some(&mut geometry)) {
+            Ok(()) => geometry.BytesPerSector.max(512),
+            Err(_) => 512,
+        };
+        (length.Length as u64, sector_size)
+    };
+    Ok(Device { file, size, sector_size })
+}
+
+#[cfg(windows)]
+fn device_handle(file: &File) -> windows::Win32::Foundation::HANDLE {
+    use std::os::windows::io::AsRawHandle;
+    windows::Win32::Foundation::HANDLE(file.as_raw_handle() as _)
+}
+
+/// 调用无输入的 IOCTL，输出写入 `out`
+#[cfg(windows)]
+unsafe fn ioctl<T>(handle: windows::Win32::Foundation::HANDLE, code: u32, out: Option<&mut T>) -> windows::core::Result<()> {
+    let mut bytes_returned = 0u32;
+    let (ptr, size) = match out {
+        Some(out) => (Some(out as *mut T as *mut _), std::mem::size_of::<T>() as u32),
+        None => (None, 0),
+    };
+    windows::Win32::System::IO::DeviceIoControl(handle, code, None, 0, ptr, size, Some(&mut bytes_returned), None)
+}
+
+/// 锁定卷，防止读写过程中被其他程序修改（`dismount` 时同时卸载文件系统）
+#[cfg(windows)]
+pub fn lock_volume(file: &File, dismount: bool) -> Result<()> {
+    use windows::Win32::System::Ioctl::{FSCTL_DISMOUNT_VOLUME, FSCTL_LOCK_VOLUME};
+    unsafe {
+        ioctl::<()>(device_handle(file), FSCTL_LOCK_VOLUME, None)?;
+        if dismount {
+            let _ = ioctl::<()>(device_handle(file), FSCTL_DISMOUNT_VOLUME, None);
+        }
+    }
+    Ok(())
+}
+
+/// 读取卷的引导扇区识别文件系统（BitLocker 锁定的卷识别为 [`FileSystemKind::BitLocker`]）
+pub fn volume_file_system(letter: char) -> Result<FileSystemKind> {
+    let mut device = open_device(&volume_device_path(letter), false)?;
+    let mut boot = vec![0u8; device.sector_size.max(512) as usize];
+    device.file.read_exact(&mut boot).context("读取引导扇区失败")?;
+    Ok(identify_file_system(&boot))
+}
+
+/// 备份设备（卷或整块磁盘）到镜像文件
+pub fn backup_device(
+    device: &str,
+    image_path: &Path,
+    options: &RawBackupOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<RawImageSummary> {
+    let mut dev = open_device(device, false)?;
+    #[cfg(windows)]
+    {
+        if options.source_kind == SourceKind::Partition {
+            if let Err(e) = lock_volume(&dev.file, false) {
+                log::warn!("无法锁定 {}，备份期间的写入可能导致镜像不一致: {}", device, e);
+            }
+        }
+    }
+    let options = RawBackupOptions {
+        source: device.to_string(),
+        sector_size: dev.sector_size,
+        ..options.clone()
+    };
+    let size = dev.size;
+    backup_to_file(&mut dev.file, size, image_path, &options, progress)
+}
+
+/// 把镜像文件还原到设备
+///
+/// Windows 下只支持还原到卷：还原前锁定并卸载卷，句柄关闭后系统会重新识别文件系统。
+pub fn restore_device(
+    image_path: &Path,
+    device: &str,
+    options: &RawRestoreOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<RawImageSummary> {
+    let mut image = open_image(image_path)?;
+    let mut dev = open_device(device, true)?;
+    #[cfg(windows)]
+    {
+        if !device.ends_with(':') {
+            bail!("只支持还原到分区: {}", device);
+        }
+        if image.header().source_kind != SourceKind::Partition {
+            bail!("整盘镜像不能还原到分区，请导出或挂载后使用");
+        }
+        lock_volume(&dev.file, true).with_context(|| format!("锁定 {} 失败，请关闭正在使用该分区的程序", device))?;
+    }
+    log::info!("还原原始镜像 {} -> {}", image_path.display(), device);
+    let size = dev.size;
+    restore(&mut image, &mut dev.file, size, options, progress)
+}
+
+/// 文件系统对应的 MBR 分区类型（分区镜像转换为虚拟磁盘时使用）
+fn mbr_partition_type(file_system: FileSystemKind) -> u8 {
+    use super::partition_table::{MBR_TYPE_FAT32_LBA, MBR_TYPE_NTFS};
+    match file_system {
+        FileSystemKind::Fat12 => 0x01,
+        FileSystemKind::Fat16 => 0x0E,
+        FileSystemKind::Fat32 => MBR_TYPE_FAT32_LBA,
+        FileSystemKind::Ntfs | FileSystemKind::ExFat | FileSystemKind::BitLocker => MBR_TYPE_NTFS,
+        FileSystemKind::Unknown => 0x83,
+    }
+}
+
+/// 挂载镜像时使用的临时虚拟磁盘路径（超过 VHD 容量上限时使用 VHDX）
+pub fn mount_vhd_path(image_path: &Path, header: &RawImageHeader) -> std::path::PathBuf {
+    let extension = if header.size_bytes + 1024 * 1024 <= super::vhd::VHD_MAX_SIZE { "vhd" } else { "vhdx" };
+    let stem = image_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    std::env::temp_dir()
+        .join("LetRecovery")
+        .join(format!("{}_{}.{}", stem, std::process::id(), extension))
+}
+
+/// 把镜像转换为动态扩展的虚拟磁盘（分区镜像外加一个 1 MiB 对齐的 MBR 分区）
+///
+/// 格式由 `vhd_path` 的扩展名决定。只写入存入镜像的非零数据块，
+/// 虚拟磁盘文件大小与镜像的已用空间相当。
+pub fn export_virtual_disk(image_path: &Path, vhd_path: &Path, progress: &mut dyn FnMut(u8, &str)) -> Result<()> {
+    use super::partition_table::PartitionIo;
+    use super::vhd::{
+        create_virtual_disk, write_single_partition_mbr, VirtualDisk, VirtualDiskFormat, VirtualDiskSpec,
+        VirtualDiskType,
+    };
+
+    let format = VirtualDiskFormat::from_path(vhd_path).context("虚拟磁盘文件扩展名应为 .vhd 或 .vhdx")?;
+    let mut image = open_image(image_path)?;
+    let header = image.header().clone();
+    let disk_size = match header.source_kind {
+        SourceKind::Partition => header.size_bytes + 1024 * 1024,
+        SourceKind::Disk => header.size_bytes,
+    }
+    .max(8 * 1024 * 1024);
+    let spec = VirtualDiskSpec::new(format, VirtualDiskType::Dynamic, disk_size);
+    if let Some(parent) = vhd_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    create_virtual_disk(vhd_path, &spec)?;
+
+    let result = (|| -> Result<()> {
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(vhd_path)?;
+        let mut disk = VirtualDisk::open(file)?;
+        let options = RawRestoreOptions { sparse_target: true, ..Default::default() };
+        match header.source_kind {
+            SourceKind::Partition => {
+                let (offset, _) =
+                    write_single_partition_mbr(&mut disk, spec.size_bytes, mbr_partition_type(header.file_system))?;
+                let mut partition = PartitionIo::new(disk, offset, header.size_bytes);
+                restore(&mut image, &mut partition, header.size_bytes, &options, progress)?;
+                partition.into_inner().into_inner().sync_all()?;
+            }
+            SourceKind::Disk => {
+                let size = disk.size();
+                restore(&mut image, &mut disk, size, &options, progress)?;
+                disk.into_inner().sync_all()?;
+            }
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(vhd_path);
+    }
+    result
+}
+
+/// 只读挂载的镜像（离开作用域时卸载并删除临时虚拟磁盘）
+#[cfg(windows)]
+pub struct MountedRawImage {
+    disk: Option<super::vhd::AttachedVirtualDisk>,
+    vhd_path: std::path::PathBuf,
+}
+
+#[cfg(windows)]
+impl MountedRawImage {
+    /// 只读挂载 `export_virtual_disk` 生成的虚拟磁盘（系统自动分配盘符）
+    pub fn attach(vhd_path: std::path::PathBuf) -> Result<Self> {
+        let format = super::vhd::VirtualDiskFormat::from_path(&vhd_path).unwrap_or_default();
+        match super::vhd::AttachedVirtualDisk::attach_read_only(&vhd_path, format) {
+            Ok(disk) => Ok(Self { disk: Some(disk), vhd_path }),
+            Err(e) => {
+                let _ = std::fs::remove_file(&vhd_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// 挂载后的物理磁盘号
+    pub fn disk_number(&self) -> u32 {
+        self.disk.as_ref().map(|d| d.disk_number).unwrap_or_default()
+    }
+}
+
+#[cfg(windows)]
+impl Drop for MountedRawImage {
+    fn drop(&mut self) {
+        if let Some(disk) = self.disk.take() {
+            if let Err(e) = disk.detach() {
+                log::warn!("卸载镜像失败: {}", e);
+            }
+        }
+        let _ = std::fs::remove_file(&self.vhd_path);
+    }
+}
+
+// ==================== 工具函数 ====================
+
+fn read_at<D: Read + Seek>(dev: &mut D, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.read_exact(buf)
+}
+
+fn write_at<D: Write + Seek>(dev: &mut D, offset: u64, data: &[u8]) -> io::Result<()> {
+    dev.seek(SeekFrom::Start(offset))?;
+    dev.write_all(data)
+}
+
+fn le16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn le32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn le64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn gb(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0 / 1024.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fat32_writer::Fat32Volume;
+    use crate::fat_format::{format_volume, FatFormatOptions, NativeFileSystem, VolumeGeometry};
+    use std::io::Cursor;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("letrecovery_raw_image_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    /// 不可压缩的伪随机数据
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn small_chunks() -> RawBackupOptions {
+        RawBackupOptions { chunk_size: 64 * 1024, ..Default::default() }
+    }
+
+    fn backup_to_vec(dev: &mut Cursor<Vec<u8>>, options: &RawBackupOptions) -> (Vec<u8>, RawImageSummary) {
+        let size = dev.get_ref().len() as u64;
+        let mut image = Cursor::new(Vec::new());
+        let mut last = 0;
+        let summary = backup(dev, size, &mut image, options, &mut |p, _| {
+            assert!(p >= last);
+            last = p;
+        })
+        .unwrap();
+        assert_eq!(last, 100);
+        (image.into_inner(), summary)
+    }
+
+    /// 构造 4 MiB 的最小 NTFS 卷：簇 4 KiB，`$Bitmap` 位于簇 2，MFT 位于簇 4
+    fn ntfs_volume(used: &[u64]) -> Vec<u8> {
+        const CLUSTER: usize = 4096;
+        let mut volume = noise(4 * MIB as usize, 7);
+        let boot = &mut volume[..512];
+        boot[3..11].copy_from_slice(b"NTFS    ");
+        boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+        boot[13] = 8;
+        boot[40..48].copy_from_slice(&8191u64.to_le_bytes());
+        boot[48..56].copy_from_slice(&4u64.to_le_bytes());
+        boot[64] = 0xF6; // 1024 字节的 MFT 记录
+
+        let mut record = vec![0u8; 1024];
+        record[0..4].copy_from_slice(b"FILE");
+        record[4..6].copy_from_slice(&48u16.to_le_bytes());
+        record[6..8].copy_from_slice(&3u16.to_le_bytes());
+        record[20..22].copy_from_slice(&56u16.to_le_bytes());
+        let attr = &mut record[56..128];
+        attr[0..4].copy_from_slice(&0x80u32.to_le_bytes());
+        attr[4..8].copy_from_slice(&72u32.to_le_bytes());
+        attr[8] = 1;
+        attr[32..34].copy_from_slice(&64u16.to_le_bytes());
+        attr[48..56].copy_from_slice(&128u64.to_le_bytes());
+        attr[64..67].copy_from_slice(&[0x11, 0x01, 0x02]);
+        record[128..132].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        // 更新序列：每个扇区末尾替换为序列号，原值保存在数组中
+        record[48..50].copy_from_slice(&[0x01, 0x00]);
+        for i in 1..3 {
+            let end = i * 512;
+            let original = [record[end - 2], record[end - 1]];
+            record[48 + i * 2..50 + i * 2].copy_from_slice(&original);
+            record[end - 2..end].copy_from_slice(&[0x01, 0x00]);
+        }
+        let mft = 4 * CLUSTER + 6 * 1024;
+        volume[mft..mft + 1024].copy_from_slice(&record);
+
+        let bitmap = &mut volume[2 * CLUSTER..3 * CLUSTER];
+        bitmap.fill(0);
+        for &cluster in used {
+            bitmap[cluster as usize / 8] |= 1 << (cluster % 8);
+        }
+        volume
+    }
+
+    #[test]
+    fn test_ntfs_bitmap() {
+        let used: Vec<u64> = (0..8).chain([100]).collect();
+        let volume = ntfs_volume(&used);
+        let map = AllocationMap::detect(&mut Cursor::new(volume.clone()), volume.len() as u64);
+        assert_eq!(map.file_system, FileSystemKind::Ntfs);
+        // 簇 0-7、簇 100，以及簇区之后的最后一个簇
+        assert_eq!(map.used_bytes(), 10 * 4096);
+        assert!(map.is_used(100 * 4096, 1));
+        assert!(!map.is_used(8 * 4096, 92 * 4096));
+        assert!(map.is_used(4 * MIB - 512, 512));
+
+        // 未使用簇中的残留数据还原后为零，已使用的簇保持不变
+        let (image, summary) = backup_to_vec(&mut Cursor::new(volume.clone()), &small_chunks());
+        assert_eq!(summary.stored_chunks, 3);
+        assert_eq!(summary.skipped_chunks, 61);
+        let mut reader = RawImageReader::new(RawImage::open(Cursor::new(image)).unwrap());
+        let mut restored = Vec::new();
+        reader.read_to_end(&mut restored).unwrap();
+        assert_eq!(restored[..8 * 4096], volume[..8 * 4096]);
+        assert_eq!(restored[100 * 4096..101 * 4096], volume[100 * 4096..101 * 4096]);
+        assert!(restored[101 * 4096..102 * 4096].iter().all(|&b| b == 0));
+
+        // $Bitmap 记录损坏时退回完整备份
+        let mut broken = volume;
+        broken[4 * 4096 + 6 * 1024 + 511] ^= 0xFF;
+        let map = AllocationMap::detect(&mut Cursor::new(broken), 4 * MIB);
+        assert_eq!(map.used_bytes(), 4 * MIB);
+    }
+
+    #[test]
+    fn test_decode_runlist() {
+        // 0x100 簇 @ 0x1000，稀疏 0x10 簇，0x20 簇 @ 0x1000 - 0x10
+        let runs = [0x22, 0x00, 0x01, 0x00, 0x10, 0x01, 0x10, 0x11, 0x20, 0xF0, 0x00];
+        assert_eq!(
+            decode_runlist(&runs).unwrap(),
+            vec![(Some(0x1000), 0x100), (None, 0x10), (Some(0xFF0), 0x20)]
+        );
+        assert!(decode_runlist(&[0x21, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_fat32_roundtrip_skips_free_space() {
+        let size = 64 * MIB;
+        // 残留数据模拟格式化前的旧内容
+        let mut dev = Cursor::new(noise(size as usize, 3));
+        let geometry = VolumeGeometry { total_sectors: size / 512, sector_size: 512, hidden_sectors: 0 };
+        let options = FatFormatOptions {
+            file_system: NativeFileSystem::Fat32,
+            label: "RAWTEST".to_string(),
+            quick: true,
+            cluster_size: None,
+        };
+        format_volume(&mut dev, &geometry, &options, &mut |_, _| {}).unwrap();
+        let content = noise(3 * MIB as usize, 11);
+        let mut volume = Fat32Volume::open(dev).unwrap();
+        volume.write_file(r"data\payload.bin", &mut content.as_slice(), content.len() as u64).unwrap();
+        volume.flush().unwrap();
+        let mut dev = volume.into_inner();
+
+        let (image, summary) = backup_to_vec(&mut dev, &small_chunks());
+        let header = RawImage::open(Cursor::new(image.clone())).unwrap().header().clone();
+        assert_eq!(header.file_system, FileSystemKind::Fat32);
+        assert!(summary.skipped_chunks > 900, "{:?}", summary);
+        assert!((image.len() as u64) < 8 * MIB, "{}", image.len());
+
+        // 还原到另一块写满残留数据的设备
+        let mut target = Cursor::new(noise(size as usize, 5));
+        let mut image = RawImage::open(Cursor::new(image)).unwrap();
+        let restored = restore(&mut image, &mut target, size, &RawRestoreOptions::default(), &mut |_, _| {}).unwrap();
+        assert_eq!(restored.stored_chunks, summary.stored_chunks);
+        let mut volume = Fat32Volume::open(target).unwrap();
+        assert_eq!(volume.read_file(r"data\payload.bin").unwrap(), content);
+
+        // 目标容量不足
+        let mut small = Cursor::new(vec![0u8; MIB as usize]);
+        assert!(restore(&mut image, &mut small, MIB, &RawRestoreOptions::default(), &mut |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn test_reader_verify_and_corruption() {
+        // 未知文件系统：完整备份，中间 1 MiB 为零
+        let mut data = noise(3 * MIB as usize + 4096, 9);
+        data[MIB as usize..2 * MIB as usize].fill(0);
+        let (image, summary) = backup_to_vec(&mut Cursor::new(data.clone()), &small_chunks());
+        assert_eq!(summary.used_bytes, data.len() as u64);
+        assert_eq!(summary.zero_chunks, 16);
+        assert_eq!(summary.skipped_chunks, 0);
+
+        let mut opened = RawImage::open(Cursor::new(image.clone())).unwrap();
+        assert!(opened.is_complete());
+        assert_eq!(opened.header().file_system, FileSystemKind::Unknown);
+        let verified = verify(&mut opened, &mut |_, _| {}).unwrap();
+        assert_eq!(verified.stored_chunks, summary.stored_chunks);
+        assert_eq!(verified.stored_bytes, summary.stored_bytes);
+
+        // 跨块随机读取
+        let mut reader = RawImageReader::new(opened);
+        for &(offset, len) in &[(0u64, 100usize), (65_530, 20), (MIB - 10, 30), (3 * MIB, 4096)] {
+            let mut buf = vec![0u8; len];
+            reader.seek(SeekFrom::Start(offset)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[offset as usize..offset as usize + len]);
+        }
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), data.len() as u64 - 1);
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, [data[data.len() - 1]]);
+
+        // 篡改第一个数据块的存储内容
+        let mut corrupted = image.clone();
+        let header_len = le32(&corrupted, 12) as usize;
+        corrupted[16 + header_len + RECORD_SIZE + 100] ^= 0x55;
+        let mut opened = RawImage::open(Cursor::new(corrupted)).unwrap();
+        let error = verify(&mut opened, &mut |_, _| {}).unwrap_err();
+        assert!(format!("{:#}", error).contains("数据块 0"), "{:#}", error);
+
+        // 去掉索引和尾部（备份中断）：可以扫描读取，但拒绝校验和还原
+        let index_pos = le64(&image, image.len() - 16) as usize;
+        let mut opened = RawImage::open(Cursor::new(image[..index_pos].to_vec())).unwrap();
+        assert!(!opened.is_complete());
+        assert_eq!(opened.stored_chunks(), summary.stored_chunks);
+        assert_eq!(opened.read_chunk(2).unwrap().unwrap(), data[2 * 65536..3 * 65536]);
+        assert!(verify(&mut opened, &mut |_, _| {}).is_err());
+
+        assert!(RawImage::open(Cursor::new(b"not an image at all".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_export_raw_and_virtual_disk() {
+        use crate::vhd::VirtualDisk;
+
+        let volume = ntfs_volume(&[0, 1, 2, 3, 4, 5, 6, 7, 50]);
+        let source = temp_path("ntfs.bin");
+        std::fs::write(&source, &volume).unwrap();
+        let image_path = temp_path("ntfs.lri");
+        let mut dev = open_device(source.to_str().unwrap(), false).unwrap();
+        assert_eq!(dev.size, 4 * MIB);
+        let size = dev.size;
+        backup_to_file(&mut dev.file, size, &image_path, &small_chunks(), &mut |_, _| {}).unwrap();
+        assert!(verify_image(&image_path, &mut |_, _| {}).is_ok());
+
+        // 导出为普通镜像
+        let raw_path = temp_path("ntfs.img");
+        export_raw(&image_path, &raw_path, &mut |_, _| {}).unwrap();
+        let exported = std::fs::read(&raw_path).unwrap();
+        assert_eq!(exported.len(), volume.len());
+        assert_eq!(exported[..8 * 4096], volume[..8 * 4096]);
+        assert_eq!(exported[50 * 4096..51 * 4096], volume[50 * 4096..51 * 4096]);
+        assert!(exported[51 * 4096..52 * 4096].iter().all(|&b| b == 0));
+
+        // 导出为带 MBR 的动态 VHD
+        let vhd_path = temp_path("ntfs.vhd");
+        let _ = std::fs::remove_file(&vhd_path);
+        export_virtual_disk(&image_path, &vhd_path, &mut |_, _| {}).unwrap();
+        let mut disk = VirtualDisk::open(File::options().read(true).write(true).open(&vhd_path).unwrap()).unwrap();
+        let mut mbr = vec![0u8; 512];
+        read_at(&mut disk, 0, &mut mbr).unwrap();
+        assert_eq!(mbr[0x1BE + 4], crate::partition_table::MBR_TYPE_NTFS);
+        let mut partition = vec![0u8; volume.len()];
+        read_at(&mut disk, MIB, &mut partition).unwrap();
+        assert_eq!(partition, exported);
+        assert!(disk.allocated_blocks().unwrap() <= 2);
+
+        for path in [source, image_path, raw_path, vhd_path] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
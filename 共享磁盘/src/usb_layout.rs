@@ -0,0 +1,190 @@
+//! 安装 U 盘文件布局模块
+//!
+//! 决定安装介质中的每个文件放在 U 盘的哪个分区、是否需要分割：
+//! - 单分区 FAT32：超过 4 GB 的 install.wim 分割为 SWM 分卷，其他超过 4 GB 的文件无法放入
+//! - FAT32 + NTFS 双分区：sources\install.* 和超过 4 GB 的文件放在 NTFS 数据分区
+//!
+//! 实际的分区、格式化和复制由正常系统端的 `usb_creator` 完成。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::fat32_writer::FAT32_MAX_FILE_SIZE;
+
+/// U 盘分区布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbLayout {
+    /// 单个 FAT32 分区（兼容性最好）
+    SingleFat32,
+    /// FAT32 引导分区 + NTFS 数据分区（不分割 install.wim）
+    Fat32Ntfs,
+}
+
+impl fmt::Display for UsbLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsbLayout::SingleFat32 => write!(f, "单分区 FAT32"),
+            UsbLayout::Fat32Ntfs => write!(f, "FAT32 + NTFS 双分区"),
+        }
+    }
+}
+
+/// 文件所在的 U 盘分区
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbVolume {
+    /// FAT32 引导分区
+    Boot,
+    /// NTFS 数据分区（仅双分区布局）
+    Data,
+}
+
+/// 复制清单中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyItem {
+    pub source: PathBuf,
+    /// U 盘上的相对路径（反斜杠分隔）
+    pub dest: String,
+    pub size: u64,
+    pub volume: UsbVolume,
+    /// 需要分割为 SWM 分卷
+    pub split: bool,
+}
+
+/// 是否为安装镜像（sources\install.wim/esd/swm 及其分卷）
+fn is_install_image(dest: &str) -> bool {
+    let lower = dest.to_ascii_lowercase();
+    lower.starts_with(r"sources\install")
+        && !lower[r"sources\".len()..].contains('\\')
+        && [".wim", ".esd", ".swm"].iter().any(|ext| lower.ends_with(ext))
+}
+
+/// 决定文件放在哪个分区以及是否需要分割，`preferred` 为双分区布局下普通文件的默认位置
+pub fn place_file(dest: &str, size: u64, layout: UsbLayout, preferred: UsbVolume) -> Result<(UsbVolume, bool)> {
+    let too_large = size > FAT32_MAX_FILE_SIZE;
+    match layout {
+        UsbLayout::Fat32Ntfs if is_install_image(dest) || too_large => Ok((UsbVolume::Data, false)),
+        UsbLayout::Fat32Ntfs => Ok((preferred, false)),
+        UsbLayout::SingleFat32 if !too_large => Ok((UsbVolume::Boot, false)),
+        UsbLayout::SingleFat32 if is_install_image(dest) && dest.to_ascii_lowercase().ends_with(".wim") => {
+            Ok((UsbVolume::Boot, true))
+        }
+        UsbLayout::SingleFat32 if is_install_image(dest) => {
+            bail!("{} 超过 4 GB 且无法分割，请使用 FAT32 + NTFS 双分区布局", dest)
+        }
+        UsbLayout::SingleFat32 => bail!("{} 超过 4 GB，无法放入 FAT32 分区，请使用 FAT32 + NTFS 双分区布局", dest),
+    }
+}
+
+/// 扫描目录生成复制清单，文件在 U 盘上的路径为 `dest_prefix\相对路径`
+///
+/// `skip_install_image` 为 true 时跳过目录中的 sources\install.*（改用单独指定的系统镜像）
+pub fn plan_tree(
+    root: &Path,
+    dest_prefix: &str,
+    layout: UsbLayout,
+    preferred: UsbVolume,
+    skip_install_image: bool,
+) -> Result<Vec<CopyItem>> {
+    let mut items = Vec::new();
+    for entry in walkdir::WalkDir::new(root).sort_by_file_name() {
+        let entry = entry.with_context(|| format!("读取 {} 失败", root.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative: Vec<String> = entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let relative = relative.join("\\");
+        if skip_install_image && is_install_image(&relative) {
+            continue;
+        }
+        let dest = if dest_prefix.is_empty() {
+            relative
+        } else {
+            format!("{}\\{}", dest_prefix, relative)
+        };
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let (volume, split) = place_file(&dest, size, layout, preferred)?;
+        items.push(CopyItem { source: entry.path().to_path_buf(), dest, size, volume, split });
+    }
+    Ok(items)
+}
+
+/// 把单独指定的系统镜像加入复制清单（放在 sources\install.wim 或 sources\install.esd）
+pub fn plan_install_image(image: &Path, layout: UsbLayout) -> Result<CopyItem> {
+    let ext = image
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if ext != "wim" && ext != "esd" {
+        bail!("不支持的系统镜像格式: {}（仅支持 .wim / .esd）", image.display());
+    }
+    let size = std::fs::metadata(image)
+        .with_context(|| format!("读取 {} 失败", image.display()))?
+        .len();
+    let dest = format!(r"sources\install.{}", ext);
+    let (volume, split) = place_file(&dest, size, layout, UsbVolume::Boot)?;
+    Ok(CopyItem { source: image.to_path_buf(), dest, size, volume, split })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("letrecovery_usb_{}", std::process::id()))
+            .join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, relative: &str, data: &[u8]) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_place_file() {
+        let big = FAT32_MAX_FILE_SIZE + 1;
+        let single = UsbLayout::SingleFat32;
+        let dual = UsbLayout::Fat32Ntfs;
+        assert_eq!(place_file(r"sources\install.wim", big, single, UsbVolume::Boot).unwrap(), (UsbVolume::Boot, true));
+        assert_eq!(place_file(r"sources\install.wim", 100, single, UsbVolume::Boot).unwrap(), (UsbVolume::Boot, false));
+        assert!(place_file(r"sources\install.esd", big, single, UsbVolume::Boot).is_err());
+        assert!(place_file(r"sources\huge.bin", big, single, UsbVolume::Boot).is_err());
+
+        assert_eq!(place_file(r"sources\install.wim", big, dual, UsbVolume::Boot).unwrap(), (UsbVolume::Data, false));
+        assert_eq!(place_file(r"Sources\Install.esd", 100, dual, UsbVolume::Boot).unwrap(), (UsbVolume::Data, false));
+        assert_eq!(place_file(r"sources\boot.wim", 100, dual, UsbVolume::Boot).unwrap(), (UsbVolume::Boot, false));
+        assert_eq!(place_file(r"LetRecovery\drivers\a.inf", 1, dual, UsbVolume::Data).unwrap(), (UsbVolume::Data, false));
+        assert!(!is_install_image(r"sources\sxs\install.wim"));
+    }
+
+    #[test]
+    fn test_plan_tree_skips_install_image() {
+        let root = temp_dir("plan");
+        write(&root, "bootmgr", b"1");
+        write(&root, r"sources/install.wim", b"22");
+        write(&root, r"sources/boot.wim", b"333");
+
+        let items = plan_tree(&root, "", UsbLayout::Fat32Ntfs, UsbVolume::Boot, false).unwrap();
+        let dests: Vec<&str> = items.iter().map(|i| i.dest.as_str()).collect();
+        assert_eq!(dests, ["bootmgr", r"sources\boot.wim", r"sources\install.wim"]);
+        assert_eq!(items[2].volume, UsbVolume::Data);
+
+        let items = plan_tree(&root, "extra", UsbLayout::SingleFat32, UsbVolume::Data, true).unwrap();
+        let dests: Vec<&str> = items.iter().map(|i| i.dest.as_str()).collect();
+        assert_eq!(dests, [r"extra\bootmgr", r"extra\sources\boot.wim"]);
+        assert!(items.iter().all(|i| i.volume == UsbVolume::Boot));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,37 @@
+//! 外部命令辅助函数
+
+use encoding_rs::GBK;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Windows CREATE_NO_WINDOW 标志
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// 创建一个隐藏控制台窗口的 Command
+pub(crate) fn new_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
+    let mut cmd = Command::new(program);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    cmd
+}
+
+/// 将 GBK 编码的字节转换为 UTF-8 字符串
+pub(crate) fn gbk_to_utf8(bytes: &[u8]) -> String {
+    let (cow, _, _) = GBK.decode(bytes);
+    cow.into_owned()
+}
+
+/// 程序目录下的 bin 目录
+pub(crate) fn get_bin_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.join("bin")))
+        .unwrap_or_else(|| PathBuf::from("bin"))
+}
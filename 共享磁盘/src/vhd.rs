@@ -0,0 +1,1367 @@
+//! 虚拟磁盘（VHD / VHDX）模块
+//!
+//! - 纯 Rust 实现 VHD 与 VHDX 文件的创建（固定大小 / 动态扩展）
+//! - `VirtualDisk` 把虚拟磁盘文件当作普通磁盘读写：分区表、格式化都可以直接写入，
+//!   动态磁盘在首次写入某个数据块时才分配，便于在任何平台上校验文件布局
+//! - Windows 下通过 Virtual Disk API 挂载；格式化、释放系统镜像和添加本机启动（Native Boot）
+//!   引导项由正常系统端完成，实现多个系统共存于同一分区
+//!
+//! 只支持非差分磁盘；VHDX 日志不为空（上次未正常卸载）时拒绝直接读写。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::partition_table::{DiskGeometry, Guid, Mbr, MbrEntry, MbrLayout, PartitionTable};
+
+const MB: u64 = 1024 * 1024;
+/// 虚拟磁盘的逻辑扇区大小
+const SECTOR_SIZE: u64 = 512;
+/// 分区起始扇区（1 MiB 对齐）
+const PARTITION_START_LBA: u64 = 2048;
+/// 允许创建的最小容量
+const MIN_SIZE: u64 = 8 * MB;
+
+/// VHD 格式支持的最大容量（2040 GB）
+pub const VHD_MAX_SIZE: u64 = 2040 * 1024 * MB;
+/// VHDX 格式支持的最大容量（64 TB）
+pub const VHDX_MAX_SIZE: u64 = 64 * 1024 * 1024 * MB;
+/// 安装系统时建议的最小容量（GB）
+pub const RECOMMENDED_MIN_SIZE_GB: u64 = 32;
+/// 宿主分区上存放虚拟磁盘文件的目录
+pub const VHD_DIR: &str = "VHD";
+
+// ==================== VHD 常量 ====================
+
+const VHD_COOKIE: &[u8; 8] = b"conectix";
+const VHD_DYNAMIC_COOKIE: &[u8; 8] = b"cxsparse";
+const VHD_FOOTER_SIZE: usize = 512;
+const VHD_DYNAMIC_HEADER_SIZE: usize = 1024;
+/// 动态 VHD 的块大小（与 Windows 创建的 VHD 一致）
+const VHD_BLOCK_SIZE: u64 = 2 * MB;
+const VHD_UNUSED_BLOCK: u32 = 0xFFFF_FFFF;
+const VHD_TYPE_FIXED: u32 = 2;
+const VHD_TYPE_DYNAMIC: u32 = 3;
+const VHD_TYPE_DIFFERENCING: u32 = 4;
+const VHD_VERSION: u32 = 0x0001_0000;
+const VHD_CREATOR_APP: &[u8; 4] = b"lrcv";
+/// 创建者系统 "Wi2k"
+const VHD_CREATOR_HOST_WINDOWS: u32 = 0x5769_326B;
+/// VHD 时间戳起点（2000-01-01 00:00:00 UTC）相对 Unix 纪元的秒数
+const VHD_EPOCH_OFFSET: u64 = 946_684_800;
+
+// ==================== VHDX 常量 ====================
+
+const VHDX_SIGNATURE: &[u8; 8] = b"vhdxfile";
+const VHDX_HEADER_SIGNATURE: &[u8; 4] = b"head";
+const VHDX_REGION_SIGNATURE: &[u8; 4] = b"regi";
+const VHDX_METADATA_SIGNATURE: &[u8; 8] = b"metadata";
+const VHDX_HEADER_OFFSETS: [u64; 2] = [64 * 1024, 128 * 1024];
+const VHDX_REGION_OFFSETS: [u64; 2] = [192 * 1024, 256 * 1024];
+const VHDX_HEADER_SIZE: usize = 4 * 1024;
+const VHDX_REGION_TABLE_SIZE: usize = 64 * 1024;
+const VHDX_LOG_OFFSET: u64 = MB;
+const VHDX_LOG_SIZE: u64 = MB;
+const VHDX_METADATA_OFFSET: u64 = 2 * MB;
+const VHDX_METADATA_SIZE: u64 = MB;
+/// 元数据项的数据区相对元数据区域的起始偏移
+const VHDX_METADATA_ITEMS_OFFSET: usize = 64 * 1024;
+const VHDX_BAT_OFFSET: u64 = 3 * MB;
+/// VHDX 块大小（与 Hyper-V 默认值一致）
+const VHDX_BLOCK_SIZE: u64 = 32 * MB;
+const VHDX_PHYSICAL_SECTOR_SIZE: u32 = 4096;
+const VHDX_PAYLOAD_NOT_PRESENT: u64 = 0;
+const VHDX_PAYLOAD_FULLY_PRESENT: u64 = 6;
+const VHDX_BAT_STATE_MASK: u64 = 0x7;
+const VHDX_BAT_OFFSET_MASK: u64 = !(MB - 1);
+const VHDX_META_IS_VIRTUAL_DISK: u32 = 0x2;
+const VHDX_META_IS_REQUIRED: u32 = 0x4;
+const VHDX_FILE_LEAVE_BLOCKS_ALLOCATED: u32 = 0x1;
+const VHDX_FILE_HAS_PARENT: u32 = 0x2;
+
+const VHDX_BAT_REGION: &str = "2DC27766-F623-4200-9D64-115E9BFD4A08";
+const VHDX_METADATA_REGION: &str = "8B7CA206-4790-4B9A-B8FE-575F050F886E";
+const VHDX_FILE_PARAMETERS: &str = "CAA16737-FA36-4D43-B3B6-33F0AA44E76B";
+const VHDX_VIRTUAL_DISK_SIZE: &str = "2FA54224-CD1B-4876-B211-5DBED83BF4B8";
+const VHDX_VIRTUAL_DISK_ID: &str = "BECA12AB-B2E6-4523-93EF-C309E000C746";
+const VHDX_LOGICAL_SECTOR_SIZE: &str = "8141BF1D-A96F-4709-BA47-F233A8FAAB5F";
+const VHDX_PHYSICAL_SECTOR_SIZE_ITEM: &str = "CDA348C7-445D-4471-9CC9-E9885251C556";
+
+/// 虚拟磁盘文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VirtualDiskFormat {
+    /// VHD（兼容 Windows 7，最大 2040 GB）
+    Vhd,
+    /// VHDX（Windows 8 及以上，断电后更不容易损坏）
+    #[default]
+    Vhdx,
+}
+
+impl fmt::Display for VirtualDiskFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VirtualDiskFormat::Vhd => write!(f, "VHD"),
+            VirtualDiskFormat::Vhdx => write!(f, "VHDX"),
+        }
+    }
+}
+
+impl VirtualDiskFormat {
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VirtualDiskFormat::Vhd => "vhd",
+            VirtualDiskFormat::Vhdx => "vhdx",
+        }
+    }
+
+    /// 格式支持的最大容量
+    pub fn max_size(&self) -> u64 {
+        match self {
+            VirtualDiskFormat::Vhd => VHD_MAX_SIZE,
+            VirtualDiskFormat::Vhdx => VHDX_MAX_SIZE,
+        }
+    }
+
+    /// 根据文件扩展名判断格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "vhd" => Some(VirtualDiskFormat::Vhd),
+            "vhdx" => Some(VirtualDiskFormat::Vhdx),
+            _ => None,
+        }
+    }
+}
+
+/// 虚拟磁盘类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VirtualDiskType {
+    /// 固定大小：创建时分配全部空间，性能更稳定
+    Fixed,
+    /// 动态扩展：写入数据时才占用宿主分区空间
+    #[default]
+    Dynamic,
+}
+
+impl fmt::Display for VirtualDiskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VirtualDiskType::Fixed => write!(f, "固定大小"),
+            VirtualDiskType::Dynamic => write!(f, "动态扩展"),
+        }
+    }
+}
+
+/// 虚拟磁盘规格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualDiskSpec {
+    pub format: VirtualDiskFormat,
+    pub disk_type: VirtualDiskType,
+    /// 虚拟磁盘容量（字节，按 1 MiB 向上对齐）
+    pub size_bytes: u64,
+}
+
+impl VirtualDiskSpec {
+    pub fn new(format: VirtualDiskFormat, disk_type: VirtualDiskType, size_bytes: u64) -> Self {
+        Self {
+            format,
+            disk_type,
+            size_bytes: round_up(size_bytes, MB),
+        }
+    }
+
+    /// 检查容量是否在格式支持的范围内
+    pub fn validate(&self) -> Result<()> {
+        if self.size_bytes < MIN_SIZE {
+            bail!("虚拟磁盘容量不能小于 {} MB", MIN_SIZE / MB);
+        }
+        if self.size_bytes > self.format.max_size() {
+            bail!(
+                "{} 格式最大支持 {} GB",
+                self.format,
+                self.format.max_size() / 1024 / MB
+            );
+        }
+        Ok(())
+    }
+
+    /// 创建后文件在宿主分区上的大小
+    pub fn initial_file_size(&self) -> u64 {
+        match (self.format, self.disk_type) {
+            (VirtualDiskFormat::Vhd, VirtualDiskType::Fixed) => self.size_bytes + VHD_FOOTER_SIZE as u64,
+            (VirtualDiskFormat::Vhd, VirtualDiskType::Dynamic) => {
+                vhd_bat_offset() + vhd_bat_bytes(self.size_bytes) + VHD_FOOTER_SIZE as u64
+            }
+            (VirtualDiskFormat::Vhdx, VirtualDiskType::Fixed) => {
+                vhdx_data_offset(self.size_bytes) + vhdx_payload_blocks(self.size_bytes) * VHDX_BLOCK_SIZE
+            }
+            (VirtualDiskFormat::Vhdx, VirtualDiskType::Dynamic) => vhdx_data_offset(self.size_bytes),
+        }
+    }
+}
+
+// ==================== 工具函数 ====================
+
+fn round_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+fn put_be16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_be32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_be64(buf: &mut [u8], offset: usize, value: u64) {
+    buf[offset..offset + 8].copy_from_slice(&value.to_be_bytes());
+}
+
+fn be32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn be64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn le16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+fn le32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn le64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn known_guid(text: &str) -> Guid {
+    Guid::parse(text).expect("内置 GUID 格式错误")
+}
+
+fn read_at<F: Read + Seek>(file: &mut F, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_at<F: Write + Seek>(file: &mut F, offset: u64, data: &[u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(data)
+}
+
+/// 把文件扩展到指定长度（新增部分读取为零）
+fn extend_to<F: Write + Seek>(file: &mut F, len: u64) -> io::Result<()> {
+    if len > 0 {
+        write_at(file, len - 1, &[0])?;
+    }
+    Ok(())
+}
+
+/// CRC-32C（Castagnoli），VHDX 头部、区域表使用
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// 校验带 CRC-32C 字段（偏移 4）的结构
+fn crc32c_valid(data: &[u8]) -> bool {
+    let stored = le32(data, 4);
+    let mut copy = data.to_vec();
+    copy[4..8].fill(0);
+    crc32c(&copy) == stored
+}
+
+fn seal_crc32c(data: &mut [u8]) {
+    data[4..8].fill(0);
+    let crc = crc32c(data);
+    data[4..8].copy_from_slice(&crc.to_le_bytes());
+}
+
+// ==================== VHD ====================
+
+/// VHD 校验和：除校验和字段外所有字节之和取反
+fn vhd_checksum(data: &[u8], checksum_offset: usize) -> u32 {
+    let sum = data
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !(checksum_offset..checksum_offset + 4).contains(i))
+        .fold(0u32, |acc, (_, b)| acc.wrapping_add(*b as u32));
+    !sum
+}
+
+fn vhd_timestamp() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().saturating_sub(VHD_EPOCH_OFFSET))
+        .unwrap_or(0)
+        .min(u32::MAX as u64) as u32
+}
+
+/// VHD 规范中的 CHS 几何计算
+fn vhd_chs(total_sectors: u64) -> (u16, u8, u8) {
+    let total = total_sectors.min(65535 * 16 * 255);
+    let (sectors_per_track, heads, cylinder_times_heads) = if total >= 65535 * 16 * 63 {
+        (255, 16, total / 255)
+    } else {
+        let mut spt = 17;
+        let mut cth = total / spt;
+        let mut heads = cth.div_ceil(1024).max(4);
+        if cth >= heads * 1024 || heads > 16 {
+            spt = 31;
+            heads = 16;
+            cth = total / spt;
+        }
+        if cth >= heads * 1024 {
+            spt = 63;
+            heads = 16;
+            cth = total / spt;
+        }
+        (spt, heads, cth)
+    };
+    ((cylinder_times_heads / heads) as u16, heads as u8, sectors_per_track as u8)
+}
+
+fn vhd_footer(size: u64, disk_type: u32, data_offset: u64, unique_id: &Guid) -> [u8; VHD_FOOTER_SIZE] {
+    let mut footer = [0u8; VHD_FOOTER_SIZE];
+    footer[0..8].copy_from_slice(VHD_COOKIE);
+    put_be32(&mut footer, 8, 0x0000_0002);
+    put_be32(&mut footer, 12, VHD_VERSION);
+    put_be64(&mut footer, 16, data_offset);
+    put_be32(&mut footer, 24, vhd_timestamp());
+    footer[28..32].copy_from_slice(VHD_CREATOR_APP);
+    put_be32(&mut footer, 32, VHD_VERSION);
+    put_be32(&mut footer, 36, VHD_CREATOR_HOST_WINDOWS);
+    put_be64(&mut footer, 40, size);
+    put_be64(&mut footer, 48, size);
+    let (cylinders, heads, sectors) = vhd_chs(size / SECTOR_SIZE);
+    put_be16(&mut footer, 56, cylinders);
+    footer[58] = heads;
+    footer[59] = sectors;
+    put_be32(&mut footer, 60, disk_type);
+    footer[68..84].copy_from_slice(&unique_id.0);
+    let checksum = vhd_checksum(&footer, 64);
+    put_be32(&mut footer, 64, checksum);
+    footer
+}
+
+fn vhd_dynamic_header(table_offset: u64, max_entries: u32) -> [u8; VHD_DYNAMIC_HEADER_SIZE] {
+    let mut header = [0u8; VHD_DYNAMIC_HEADER_SIZE];
+    header[0..8].copy_from_slice(VHD_DYNAMIC_COOKIE);
+    put_be64(&mut header, 8, u64::MAX);
+    put_be64(&mut header, 16, table_offset);
+    put_be32(&mut header, 24, VHD_VERSION);
+    put_be32(&mut header, 28, max_entries);
+    put_be32(&mut header, 32, VHD_BLOCK_SIZE as u32);
+    let checksum = vhd_checksum(&header, 36);
+    put_be32(&mut header, 36, checksum);
+    header
+}
+
+fn vhd_bat_offset() -> u64 {
+    (VHD_FOOTER_SIZE + VHD_DYNAMIC_HEADER_SIZE) as u64
+}
+
+fn vhd_block_count(size: u64) -> u64 {
+    size.div_ceil(VHD_BLOCK_SIZE)
+}
+
+fn vhd_bat_bytes(size: u64) -> u64 {
+    round_up(vhd_block_count(size) * 4, SECTOR_SIZE)
+}
+
+/// 块内扇区位图的大小（按扇区对齐）
+fn vhd_bitmap_size(block_size: u64) -> u64 {
+    round_up(block_size / SECTOR_SIZE / 8, SECTOR_SIZE)
+}
+
+fn write_vhd<F: Write + Seek>(file: &mut F, spec: &VirtualDiskSpec) -> Result<()> {
+    let unique_id = Guid::new_random();
+    match spec.disk_type {
+        VirtualDiskType::Fixed => {
+            let footer = vhd_footer(spec.size_bytes, VHD_TYPE_FIXED, u64::MAX, &unique_id);
+            write_at(file, spec.size_bytes, &footer)?;
+        }
+        VirtualDiskType::Dynamic => {
+            let footer = vhd_footer(spec.size_bytes, VHD_TYPE_DYNAMIC, VHD_FOOTER_SIZE as u64, &unique_id);
+            let bat_offset = vhd_bat_offset();
+            let bat_bytes = vhd_bat_bytes(spec.size_bytes);
+            let header = vhd_dynamic_header(bat_offset, vhd_block_count(spec.size_bytes) as u32);
+
+            write_at(file, 0, &footer)?;
+            write_at(file, VHD_FOOTER_SIZE as u64, &header)?;
+            write_at(file, bat_offset, &vec![0xFF; bat_bytes as usize])?;
+            write_at(file, bat_offset + bat_bytes, &footer)?;
+        }
+    }
+    Ok(())
+}
+
+// ==================== VHDX ====================
+
+/// 每个扇区位图块对应的数据块数
+fn vhdx_chunk_ratio(block_size: u64, logical_sector_size: u64) -> u64 {
+    (1u64 << 23) * logical_sector_size / block_size
+}
+
+fn vhdx_payload_blocks(size: u64) -> u64 {
+    size.div_ceil(VHDX_BLOCK_SIZE)
+}
+
+/// 块分配表的项数（非差分磁盘：数据块项之间穿插扇区位图项）
+fn vhdx_bat_entries(payload_blocks: u64, chunk_ratio: u64) -> u64 {
+    if payload_blocks == 0 {
+        return 0;
+    }
+    payload_blocks + (payload_blocks - 1) / chunk_ratio
+}
+
+fn vhdx_bat_length(size: u64) -> u64 {
+    let chunk_ratio = vhdx_chunk_ratio(VHDX_BLOCK_SIZE, SECTOR_SIZE);
+    round_up(vhdx_bat_entries(vhdx_payload_blocks(size), chunk_ratio) * 8, MB)
+}
+
+/// 第一个数据块在文件中的偏移
+fn vhdx_data_offset(size: u64) -> u64 {
+    VHDX_BAT_OFFSET + vhdx_bat_length(size)
+}
+
+fn vhdx_file_identifier() -> Vec<u8> {
+    let mut data = vec![0u8; 64 * 1024];
+    data[0..8].copy_from_slice(VHDX_SIGNATURE);
+    for (i, unit) in "LetRecovery".encode_utf16().enumerate() {
+        data[8 + i * 2..10 + i * 2].copy_from_slice(&unit.to_le_bytes());
+    }
+    data
+}
+
+fn vhdx_header(sequence: u64, file_write: &Guid, data_write: &Guid) -> Vec<u8> {
+    let mut header = vec![0u8; VHDX_HEADER_SIZE];
+    header[0..4].copy_from_slice(VHDX_HEADER_SIGNATURE);
+    header[8..16].copy_from_slice(&sequence.to_le_bytes());
+    header[16..32].copy_from_slice(&file_write.0);
+    header[32..48].copy_from_slice(&data_write.0);
+    // 48..64 为日志 GUID，全零表示没有需要回放的日志
+    header[64..66].copy_from_slice(&0u16.to_le_bytes());
+    header[66..68].copy_from_slice(&1u16.to_le_bytes());
+    header[68..72].copy_from_slice(&(VHDX_LOG_SIZE as u32).to_le_bytes());
+    header[72..80].copy_from_slice(&VHDX_LOG_OFFSET.to_le_bytes());
+    seal_crc32c(&mut header);
+    header
+}
+
+fn vhdx_region_table(bat_length: u64) -> Vec<u8> {
+    let mut table = vec![0u8; VHDX_REGION_TABLE_SIZE];
+    table[0..4].copy_from_slice(VHDX_REGION_SIGNATURE);
+    table[8..12].copy_from_slice(&2u32.to_le_bytes());
+
+    let regions = [
+        (VHDX_BAT_REGION, VHDX_BAT_OFFSET, bat_length),
+        (VHDX_METADATA_REGION, VHDX_METADATA_OFFSET, VHDX_METADATA_SIZE),
+    ];
+    for (i, (guid, offset, length)) in regions.iter().enumerate() {
+        let entry = 16 + i * 32;
+        table[entry..entry + 16].copy_from_slice(&known_guid(guid).0);
+        table[entry + 16..entry + 24].copy_from_slice(&offset.to_le_bytes());
+        table[entry + 24..entry + 28].copy_from_slice(&(*length as u32).to_le_bytes());
+        table[entry + 28..entry + 32].copy_from_slice(&1u32.to_le_bytes());
+    }
+    seal_crc32c(&mut table);
+    table
+}
+
+fn vhdx_metadata(spec: &VirtualDiskSpec, disk_id: &Guid) -> Vec<u8> {
+    let mut region = vec![0u8; VHDX_METADATA_SIZE as usize];
+    region[0..8].copy_from_slice(VHDX_METADATA_SIGNATURE);
+
+    let file_flags = match spec.disk_type {
+        VirtualDiskType::Fixed => VHDX_FILE_LEAVE_BLOCKS_ALLOCATED,
+        VirtualDiskType::Dynamic => 0,
+    };
+    let mut file_parameters = (VHDX_BLOCK_SIZE as u32).to_le_bytes().to_vec();
+    file_parameters.extend_from_slice(&file_flags.to_le_bytes());
+
+    let disk_flags = VHDX_META_IS_VIRTUAL_DISK | VHDX_META_IS_REQUIRED;
+    let items: [(&str, u32, Vec<u8>); 5] = [
+        (VHDX_FILE_PARAMETERS, VHDX_META_IS_REQUIRED, file_parameters),
+        (VHDX_VIRTUAL_DISK_SIZE, disk_flags, spec.size_bytes.to_le_bytes().to_vec()),
+        (VHDX_VIRTUAL_DISK_ID, disk_flags, disk_id.0.to_vec()),
+        (VHDX_LOGICAL_SECTOR_SIZE, disk_flags, (SECTOR_SIZE as u32).to_le_bytes().to_vec()),
+        (VHDX_PHYSICAL_SECTOR_SIZE_ITEM, disk_flags, VHDX_PHYSICAL_SECTOR_SIZE.to_le_bytes().to_vec()),
+    ];
+
+    region[10..12].copy_from_slice(&(items.len() as u16).to_le_bytes());
+    let mut data_offset = VHDX_METADATA_ITEMS_OFFSET;
+    for (i, (guid, flags, data)) in items.iter().enumerate() {
+        let entry = 32 + i * 32;
+        region[entry..entry + 16].copy_from_slice(&known_guid(guid).0);
+        region[entry + 16..entry + 20].copy_from_slice(&(data_offset as u32).to_le_bytes());
+        region[entry + 20..entry + 24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        region[entry + 24..entry + 28].copy_from_slice(&flags.to_le_bytes());
+        region[data_offset..data_offset + data.len()].copy_from_slice(data);
+        data_offset += data.len();
+    }
+    region
+}
+
+fn write_vhdx<F: Write + Seek>(file: &mut F, spec: &VirtualDiskSpec) -> Result<()> {
+    let file_write = Guid::new_random();
+    let data_write = Guid::new_random();
+    let bat_length = vhdx_bat_length(spec.size_bytes);
+    let data_offset = vhdx_data_offset(spec.size_bytes);
+
+    write_at(file, 0, &vhdx_file_identifier())?;
+    for (i, offset) in VHDX_HEADER_OFFSETS.iter().enumerate() {
+        write_at(file, *offset, &vhdx_header(i as u64 + 1, &file_write, &data_write))?;
+    }
+    let region_table = vhdx_region_table(bat_length);
+    for offset in VHDX_REGION_OFFSETS {
+        write_at(file, offset, &region_table)?;
+    }
+    write_at(file, VHDX_LOG_OFFSET, &vec![0u8; VHDX_LOG_SIZE as usize])?;
+    write_at(file, VHDX_METADATA_OFFSET, &vhdx_metadata(spec, &Guid::new_random()))?;
+
+    let mut bat = vec![0u8; bat_length as usize];
+    let payload_blocks = vhdx_payload_blocks(spec.size_bytes);
+    if spec.disk_type == VirtualDiskType::Fixed {
+        let chunk_ratio = vhdx_chunk_ratio(VHDX_BLOCK_SIZE, SECTOR_SIZE);
+        for block in 0..payload_blocks {
+            let index = (block + block / chunk_ratio) as usize;
+            let entry = (data_offset + block * VHDX_BLOCK_SIZE) | VHDX_PAYLOAD_FULLY_PRESENT;
+            bat[index * 8..index * 8 + 8].copy_from_slice(&entry.to_le_bytes());
+        }
+    }
+    write_at(file, VHDX_BAT_OFFSET, &bat)?;
+
+    if spec.disk_type == VirtualDiskType::Fixed {
+        extend_to(file, data_offset + payload_blocks * VHDX_BLOCK_SIZE)?;
+    }
+    Ok(())
+}
+
+/// 在任意可写流上创建虚拟磁盘（流应为空）
+pub fn write_virtual_disk<F: Write + Seek>(file: &mut F, spec: &VirtualDiskSpec) -> Result<()> {
+    spec.validate()?;
+    match spec.format {
+        VirtualDiskFormat::Vhd => write_vhd(file, spec)?,
+        VirtualDiskFormat::Vhdx => write_vhdx(file, spec)?,
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// 创建虚拟磁盘文件（文件已存在时报错，失败时删除半成品）
+pub fn create_virtual_disk(path: &Path, spec: &VirtualDiskSpec) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("创建 {} 失败", path.display()))?;
+
+    if let Err(e) = write_virtual_disk(&mut file, spec) {
+        drop(file);
+        let _ = std::fs::remove_file(path);
+        return Err(e.context(format!("写入 {} 失败", path.display())));
+    }
+    Ok(())
+}
+
+// ==================== 虚拟磁盘读写 ====================
+
+/// 虚拟磁盘数据块映射
+enum BlockMap {
+    /// 固定大小 VHD：数据从文件开头连续存放
+    Flat,
+    /// 动态 VHD：BAT 项为位图所在扇区号
+    Vhd {
+        bat_offset: u64,
+        bat: Vec<u32>,
+        block_size: u64,
+        footer: Vec<u8>,
+        /// 文件尾部脚注的位置（新块从这里分配）
+        end: u64,
+    },
+    /// VHDX：BAT 项包含状态和 MB 对齐的文件偏移
+    Vhdx {
+        bat_offset: u64,
+        bat: Vec<u64>,
+        block_size: u64,
+        chunk_ratio: u64,
+        end: u64,
+    },
+}
+
+/// 虚拟地址在文件中的位置
+struct Extent {
+    block: u64,
+    within: u64,
+    len: usize,
+    /// 文件偏移，`None` 表示数据块尚未分配（读取为零）
+    offset: Option<u64>,
+}
+
+/// 把 VHD / VHDX 文件作为磁盘设备读写
+pub struct VirtualDisk<F> {
+    file: F,
+    format: VirtualDiskFormat,
+    disk_type: VirtualDiskType,
+    size: u64,
+    map: BlockMap,
+    pos: u64,
+}
+
+impl<F: Read + Write + Seek> VirtualDisk<F> {
+    /// 打开虚拟磁盘，根据文件签名识别 VHD / VHDX
+    pub fn open(mut file: F) -> Result<Self> {
+        let len = file.seek(SeekFrom::End(0))?;
+        if len >= 8 && read_at(&mut file, 0, 8)? == VHDX_SIGNATURE {
+            Self::open_vhdx(file, len)
+        } else {
+            Self::open_vhd(file, len)
+        }
+    }
+
+    fn open_vhd(mut file: F, len: u64) -> Result<Self> {
+        if len < VHD_FOOTER_SIZE as u64 {
+            bail!("文件太小，不是有效的虚拟磁盘");
+        }
+        let footer = read_at(&mut file, len - VHD_FOOTER_SIZE as u64, VHD_FOOTER_SIZE)?;
+        if &footer[0..8] != VHD_COOKIE {
+            bail!("未找到 VHD 脚注，不是有效的虚拟磁盘");
+        }
+        if vhd_checksum(&footer, 64) != be32(&footer, 64) {
+            bail!("VHD 脚注校验和错误");
+        }
+
+        let size = be64(&footer, 48);
+        let (disk_type, map) = match be32(&footer, 60) {
+            VHD_TYPE_FIXED => {
+                if len < size + VHD_FOOTER_SIZE as u64 {
+                    bail!("固定大小 VHD 文件不完整");
+                }
+                (VirtualDiskType::Fixed, BlockMap::Flat)
+            }
+            VHD_TYPE_DYNAMIC => {
+                let header = read_at(&mut file, be64(&footer, 16), VHD_DYNAMIC_HEADER_SIZE)?;
+                if &header[0..8] != VHD_DYNAMIC_COOKIE || vhd_checksum(&header, 36) != be32(&header, 36) {
+                    bail!("VHD 动态磁盘头损坏");
+                }
+                let bat_offset = be64(&header, 16);
+                let entries = be32(&header, 28) as usize;
+                let block_size = be32(&header, 32) as u64;
+                if block_size == 0 || block_size & (SECTOR_SIZE - 1) != 0 || (entries as u64) < size.div_ceil(block_size) {
+                    bail!("VHD 动态磁盘头参数无效");
+                }
+                let raw = read_at(&mut file, bat_offset, entries * 4)?;
+                let bat = raw.chunks_exact(4).map(|c| be32(c, 0)).collect();
+                let map = BlockMap::Vhd {
+                    bat_offset,
+                    bat,
+                    block_size,
+                    footer,
+                    end: len - VHD_FOOTER_SIZE as u64,
+                };
+                (VirtualDiskType::Dynamic, map)
+            }
+            VHD_TYPE_DIFFERENCING => bail!("不支持差分 VHD"),
+            other => bail!("未知的 VHD 类型: {}", other),
+        };
+
+        Ok(Self {
+            file,
+            format: VirtualDiskFormat::Vhd,
+            disk_type,
+            size,
+            map,
+            pos: 0,
+        })
+    }
+
+    fn open_vhdx(mut file: F, len: u64) -> Result<Self> {
+        // 选取有效且序号最大的头部
+        let mut current: Option<Vec<u8>> = None;
+        for offset in VHDX_HEADER_OFFSETS {
+            let header = read_at(&mut file, offset, VHDX_HEADER_SIZE)?;
+            if &header[0..4] != VHDX_HEADER_SIGNATURE || !crc32c_valid(&header) {
+                continue;
+            }
+            if current.as_ref().map(|c| le64(&header, 8) > le64(c, 8)).unwrap_or(true) {
+                current = Some(header);
+            }
+        }
+        let header = current.context("VHDX 头部均已损坏")?;
+        if header[48..64].iter().any(|b| *b != 0) {
+            bail!("VHDX 日志尚未回放（上次未正常卸载），请先在 Windows 中挂载一次");
+        }
+
+        let mut table = None;
+        for offset in VHDX_REGION_OFFSETS {
+            let data = read_at(&mut file, offset, VHDX_REGION_TABLE_SIZE)?;
+            if &data[0..4] == VHDX_REGION_SIGNATURE && crc32c_valid(&data) {
+                table = Some(data);
+                break;
+            }
+        }
+        let table = table.context("VHDX 区域表均已损坏")?;
+
+        let mut bat_region = None;
+        let mut metadata_region = None;
+        let count = (le32(&table, 8) as usize).min((VHDX_REGION_TABLE_SIZE - 16) / 32);
+        for i in 0..count {
+            let entry = &table[16 + i * 32..48 + i * 32];
+            let guid = Guid(entry[0..16].try_into().unwrap());
+            let region = (le64(entry, 16), le32(entry, 24) as u64);
+            if guid == known_guid(VHDX_BAT_REGION) {
+                bat_region = Some(region);
+            } else if guid == known_guid(VHDX_METADATA_REGION) {
+                metadata_region = Some(region);
+            } else if le32(entry, 28) & 1 != 0 {
+                bail!("VHDX 包含不支持的必需区域 {}", guid);
+            }
+        }
+        let (bat_offset, bat_length) = bat_region.context("VHDX 缺少块分配表区域")?;
+        let (metadata_offset, metadata_length) = metadata_region.context("VHDX 缺少元数据区域")?;
+
+        let metadata = read_at(&mut file, metadata_offset, metadata_length as usize)?;
+        if &metadata[0..8] != VHDX_METADATA_SIGNATURE {
+            bail!("VHDX 元数据表损坏");
+        }
+        let item = |id: &str, len: usize| -> Result<&[u8]> {
+            let guid = known_guid(id);
+            let count = le16(&metadata, 10) as usize;
+            for i in 0..count {
+                let entry = 32 + i * 32;
+                if entry + 32 > metadata.len() {
+                    break;
+                }
+                if metadata[entry..entry + 16] == guid.0 {
+                    let offset = le32(&metadata, entry + 16) as usize;
+                    if le32(&metadata, entry + 20) as usize >= len && offset + len <= metadata.len() {
+                        return Ok(&metadata[offset..offset + len]);
+                    }
+                }
+            }
+            bail!("VHDX 缺少元数据项 {}", id)
+        };
+
+        let parameters = item(VHDX_FILE_PARAMETERS, 8)?;
+        let block_size = le32(parameters, 0) as u64;
+        let file_flags = le32(parameters, 4);
+        if file_flags & VHDX_FILE_HAS_PARENT != 0 {
+            bail!("不支持差分 VHDX");
+        }
+        let size = le64(item(VHDX_VIRTUAL_DISK_SIZE, 8)?, 0);
+        let logical_sector_size = le32(item(VHDX_LOGICAL_SECTOR_SIZE, 4)?, 0) as u64;
+        if !block_size.is_power_of_two() || !(MB..=256 * MB).contains(&block_size) {
+            bail!("VHDX 块大小无效: {}", block_size);
+        }
+        if logical_sector_size != SECTOR_SIZE && logical_sector_size != 4096 {
+            bail!("VHDX 逻辑扇区大小无效: {}", logical_sector_size);
+        }
+
+        let chunk_ratio = vhdx_chunk_ratio(block_size, logical_sector_size);
+        let entries = vhdx_bat_entries(size.div_ceil(block_size), chunk_ratio);
+        if entries * 8 > bat_length {
+            bail!("VHDX 块分配表太小");
+        }
+        let raw = read_at(&mut file, bat_offset, (entries * 8) as usize)?;
+        let bat = raw.chunks_exact(8).map(|c| le64(c, 0)).collect();
+
+        let disk_type = if file_flags & VHDX_FILE_LEAVE_BLOCKS_ALLOCATED != 0 {
+            VirtualDiskType::Fixed
+        } else {
+            VirtualDiskType::Dynamic
+        };
+
+        Ok(Self {
+            file,
+            format: VirtualDiskFormat::Vhdx,
+            disk_type,
+            size,
+            map: BlockMap::Vhdx {
+                bat_offset,
+                bat,
+                block_size,
+                chunk_ratio,
+                end: round_up(len, MB),
+            },
+            pos: 0,
+        })
+    }
+
+    /// 为数据块分配文件空间，返回块数据的起始偏移
+    fn allocate(&mut self, block: u64) -> io::Result<u64> {
+        match &mut self.map {
+            BlockMap::Flat => Err(io::Error::other("固定大小磁盘不需要分配数据块")),
+            BlockMap::Vhd { bat_offset, bat, block_size, footer, end } => {
+                let start = *end;
+                let bitmap_size = vhd_bitmap_size(*block_size);
+                let data_start = start + bitmap_size;
+                let new_end = data_start + *block_size;
+
+                // 位图全部置位：新块按全零数据处理
+                write_at(&mut self.file, start, &vec![0xFF; bitmap_size as usize])?;
+                write_at(&mut self.file, new_end, footer)?;
+
+                let entry = (start / SECTOR_SIZE) as u32;
+                write_at(&mut self.file, *bat_offset + block * 4, &entry.to_be_bytes())?;
+                bat[block as usize] = entry;
+                *end = new_end;
+                Ok(data_start)
+            }
+            BlockMap::Vhdx { bat_offset, bat, block_size, chunk_ratio, end } => {
+                let start = *end;
+                extend_to(&mut self.file, start + *block_size)?;
+
+                let index = block + block / *chunk_ratio;
+                let entry = start | VHDX_PAYLOAD_FULLY_PRESENT;
+                write_at(&mut self.file, *bat_offset + index * 8, &entry.to_le_bytes())?;
+                bat[index as usize] = entry;
+                *end = start + *block_size;
+                Ok(start)
+            }
+        }
+    }
+}
+
+impl<F> VirtualDisk<F> {
+    /// 虚拟磁盘容量（字节）
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn format(&self) -> VirtualDiskFormat {
+        self.format
+    }
+
+    pub fn disk_type(&self) -> VirtualDiskType {
+        self.disk_type
+    }
+
+    /// 已分配的数据块数（固定大小磁盘返回 `None`）
+    pub fn allocated_blocks(&self) -> Option<usize> {
+        match &self.map {
+            BlockMap::Flat => None,
+            BlockMap::Vhd { bat, .. } => Some(bat.iter().filter(|e| **e != VHD_UNUSED_BLOCK).count()),
+            BlockMap::Vhdx { bat, .. } => Some(
+                bat.iter()
+                    .filter(|e| *e & VHDX_BAT_STATE_MASK == VHDX_PAYLOAD_FULLY_PRESENT)
+                    .count(),
+            ),
+        }
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    /// 定位虚拟地址 `pos` 开始、最多 `len` 字节（不跨数据块）在文件中的位置
+    fn locate(&self, pos: u64, len: usize) -> Extent {
+        match &self.map {
+            BlockMap::Flat => Extent {
+                block: 0,
+                within: pos,
+                len,
+                offset: Some(pos),
+            },
+            BlockMap::Vhd { bat, block_size, .. } => {
+                let block = pos / block_size;
+                let within = pos % block_size;
+                let len = len.min((block_size - within) as usize);
+                let offset = match bat.get(block as usize) {
+                    Some(&entry) if entry != VHD_UNUSED_BLOCK => {
+                        Some(entry as u64 * SECTOR_SIZE + vhd_bitmap_size(*block_size) + within)
+                    }
+                    _ => None,
+                };
+                Extent { block, within, len, offset }
+            }
+            BlockMap::Vhdx { bat, block_size, chunk_ratio, .. } => {
+                let block = pos / block_size;
+                let within = pos % block_size;
+                let len = len.min((block_size - within) as usize);
+                let entry = bat
+                    .get((block + block / chunk_ratio) as usize)
+                    .copied()
+                    .unwrap_or(VHDX_PAYLOAD_NOT_PRESENT);
+                let offset = (entry & VHDX_BAT_STATE_MASK == VHDX_PAYLOAD_FULLY_PRESENT)
+                    .then(|| (entry & VHDX_BAT_OFFSET_MASK) + within);
+                Extent { block, within, len, offset }
+            }
+        }
+    }
+}
+
+impl<F: Read + Write + Seek> Read for VirtualDisk<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.size.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        let extent = self.locate(self.pos, n);
+        match extent.offset {
+            Some(offset) => {
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.read_exact(&mut buf[..extent.len])?;
+            }
+            None => buf[..extent.len].fill(0),
+        }
+        self.pos += extent.len as u64;
+        Ok(extent.len)
+    }
+}
+
+impl<F: Read + Write + Seek> Write for VirtualDisk<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.size.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        if n == 0 {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "写入超出虚拟磁盘容量"));
+        }
+        let extent = self.locate(self.pos, n);
+        let offset = match extent.offset {
+            Some(offset) => offset,
+            None => self.allocate(extent.block)? + extent.within,
+        };
+        write_at(&mut self.file, offset, &buf[..extent.len])?;
+        self.pos += extent.len as u64;
+        Ok(extent.len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl<F> Seek for VirtualDisk<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+            SeekFrom::End(d) => self.size.checked_add_signed(d),
+        };
+        self.pos = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无效的定位位置"))?;
+        Ok(self.pos)
+    }
+}
+
+/// 写入只含一个主分区的 MBR 分区表（1 MiB 对齐，占满整个磁盘）
+///
+/// 返回分区的起始偏移和长度（字节）。
+pub fn write_single_partition_mbr<D: Write + Seek>(
+    dev: &mut D,
+    disk_size: u64,
+    partition_type: u8,
+) -> Result<(u64, u64)> {
+    let total_sectors = disk_size / SECTOR_SIZE;
+    let sector_count = total_sectors.saturating_sub(PARTITION_START_LBA);
+    if sector_count == 0 || sector_count > u32::MAX as u64 {
+        bail!("虚拟磁盘容量无效，无法使用 MBR 分区表: {} 字节", disk_size);
+    }
+
+    let geometry = DiskGeometry::new(SECTOR_SIZE, total_sectors * SECTOR_SIZE);
+    let mut mbr = Mbr {
+        disk_signature: u32::from_le_bytes(Guid::new_random().0[..4].try_into().unwrap()),
+        ..Default::default()
+    };
+    mbr.entries[0] = MbrEntry {
+        bootable: false,
+        partition_type,
+        start_lba: PARTITION_START_LBA as u32,
+        sector_count: sector_count as u32,
+    };
+    PartitionTable::Mbr(MbrLayout { mbr, logical: Vec::new() }).write(dev, &geometry)?;
+    Ok((PARTITION_START_LBA * SECTOR_SIZE, sector_count * SECTOR_SIZE))
+}
+
+// ==================== 安装到虚拟磁盘 ====================
+
+/// 安装目标：宿主分区上的虚拟磁盘文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualDiskInstallTarget {
+    /// 存放虚拟磁盘文件的宿主分区（如 `D:`）
+    pub host_partition: String,
+    /// 文件名（不含目录）
+    pub file_name: String,
+    pub spec: VirtualDiskSpec,
+    /// 虚拟磁盘内 NTFS 分区的卷标
+    pub label: String,
+}
+
+impl VirtualDiskInstallTarget {
+    /// 虚拟磁盘文件的完整路径（`D:\VHD\xxx.vhdx`）
+    pub fn file_path(&self) -> PathBuf {
+        PathBuf::from(format!("{}\\", self.host_partition.trim_end_matches('\\')))
+            .join(VHD_DIR)
+            .join(&self.file_name)
+    }
+
+    /// BCD 中引用该虚拟磁盘的设备描述（如 `vhd=[D:]\VHD\xxx.vhdx`）
+    pub fn bcd_device(&self) -> String {
+        format!(
+            "vhd=[{}]\\{}\\{}",
+            self.host_partition.trim_end_matches('\\'),
+            VHD_DIR,
+            self.file_name
+        )
+    }
+
+    /// 检查文件名与容量
+    pub fn validate(&self) -> Result<()> {
+        self.spec.validate()?;
+        let name = self.file_name.trim();
+        if name.is_empty() {
+            bail!("请输入虚拟磁盘文件名");
+        }
+        if name.contains(['\\', '/', ':', '*', '?', '"', '<', '>', '|']) {
+            bail!("文件名包含非法字符: {}", name);
+        }
+        if VirtualDiskFormat::from_path(Path::new(name)) != Some(self.spec.format) {
+            bail!("文件扩展名应为 .{}", self.spec.format.extension());
+        }
+        Ok(())
+    }
+}
+
+/// 根据文件名和格式补全扩展名
+pub fn normalize_file_name(name: &str, format: VirtualDiskFormat) -> String {
+    let name = name.trim();
+    let stem = match VirtualDiskFormat::from_path(Path::new(name)) {
+        Some(_) => Path::new(name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        None => name.to_string(),
+    };
+    format!("{}.{}", stem, format.extension())
+}
+
+/// 已挂载的虚拟磁盘（离开作用域时自动卸载）
+#[cfg(windows)]
+pub struct AttachedVirtualDisk {
+    handle: windows::Win32::Foundation::HANDLE,
+    /// 物理磁盘路径（如 `\\.\PhysicalDrive3`）
+    pub physical_path: String,
+    /// 物理磁盘号
+    pub disk_number: u32,
+}
+
+#[cfg(windows)]
+impl AttachedVirtualDisk {
+    /// 以读写方式挂载虚拟磁盘（不分配盘符，句柄关闭时自动卸载）
+    pub fn attach(path: &Path, format: VirtualDiskFormat) -> Result<Self> {
+        Self::attach_with(path, format, false)
+    }
+
+    /// 以只读方式挂载虚拟磁盘（由系统自动分配盘符，句柄关闭时自动卸载）
+    pub fn attach_read_only(path: &Path, format: VirtualDiskFormat) -> Result<Self> {
+        Self::attach_with(path, format, true)
+    }
+
+    fn attach_with(path: &Path, format: VirtualDiskFormat, read_only: bool) -> Result<Self> {
+        use std::os::windows::ffi::OsStrExt;
+        use windows::core::{PCWSTR, PWSTR};
+        use windows::Win32::Foundation::{CloseHandle, HANDLE, WIN32_ERROR};
+        use windows::Win32::Storage::Vhd::{
+            AttachVirtualDisk, GetVirtualDiskPhysicalPath, OpenVirtualDisk, ATTACH_VIRTUAL_DISK_FLAG_NO_DRIVE_LETTER,
+            ATTACH_VIRTUAL_DISK_FLAG_READ_ONLY, ATTACH_VIRTUAL_DISK_PARAMETERS, ATTACH_VIRTUAL_DISK_VERSION_1, OPEN_VIRTUAL_DISK_FLAG_NONE,
+            OPEN_VIRTUAL_DISK_PARAMETERS, OPEN_VIRTUAL_DISK_VERSION_1, VIRTUAL_DISK_ACCESS_ALL, VIRTUAL_STORAGE_TYPE,
+            VIRTUAL_STORAGE_TYPE_DEVICE_VHD, VIRTUAL_STORAGE_TYPE_DEVICE_VHDX,
+        };
+
+        const VENDOR_MICROSOFT: windows::core::GUID =
+            windows::core::GUID::from_u128(0xEC984AEC_A0F9_47e9_901F_71415A66345B);
+
+        println!("[VHD] 挂载虚拟磁盘: {}{}", path.display(), if read_only { "（只读）" } else { "" });
+        let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+
+        unsafe {
+            let storage_type = VIRTUAL_STORAGE_TYPE {
+                DeviceId: match format {
+                    VirtualDiskFormat::Vhd => VIRTUAL_STORAGE_TYPE_DEVICE_VHD,
+                    VirtualDiskFormat::Vhdx => VIRTUAL_STORAGE_TYPE_DEVICE_VHDX,
+                },
+                VendorId: VENDOR_MICROSOFT,
+            };
+            let mut open_params: OPEN_VIRTUAL_DISK_PARAMETERS = std::mem::zeroed();
+            open_params.Version = OPEN_VIRTUAL_DISK_VERSION_1;
+            open_params.Anonymous.Version1.RWDepth = 1;
+
+            let mut handle = HANDLE::default();
+            let result = OpenVirtualDisk(
+                &storage_type,
+                PCWSTR::from_raw(wide_path.as_ptr()),
+                VIRTUAL_DISK_ACCESS_ALL,
+                OPEN_VIRTUAL_DISK_FLAG_NONE,
+                Some(&open_params),
+                &mut handle,
+            );
+            if result != WIN32_ERROR(0) {
+                bail!("OpenVirtualDisk 失败: {:?}", result);
+            }
+
+            let mut attach_params: ATTACH_VIRTUAL_DISK_PARAMETERS = std::mem::zeroed();
+            attach_params.Version = ATTACH_VIRTUAL_DISK_VERSION_1;
+            let flags = if read_only {
+                ATTACH_VIRTUAL_DISK_FLAG_READ_ONLY
+            } else {
+                ATTACH_VIRTUAL_DISK_FLAG_NO_DRIVE_LETTER
+            };
+            let result = AttachVirtualDisk(
+                handle,
+                None,
+                flags,
+                0,
+                Some(&attach_params),
+                None,
+            );
+            if result != WIN32_ERROR(0) {
+                let _ = CloseHandle(handle);
+                bail!("AttachVirtualDisk 失败: {:?}", result);
+            }
+
+            let mut buffer = [0u16; 260];
+            let mut buffer_size = (buffer.len() * 2) as u32;
+            let result = GetVirtualDiskPhysicalPath(handle, &mut buffer_size, PWSTR::from_raw(buffer.as_mut_ptr()));
+            let mut disk = Self {
+                handle,
+                physical_path: String::new(),
+                disk_number: 0,
+            };
+            if result != WIN32_ERROR(0) {
+                bail!("GetVirtualDiskPhysicalPath 失败: {:?}", result);
+            }
+
+            disk.physical_path = String::from_utf16_lossy(&buffer[..buffer_size as usize / 2])
+                .trim_end_matches('\0')
+                .to_string();
+            disk.disk_number = parse_physical_drive_number(&disk.physical_path)
+                .with_context(|| format!("无法识别物理磁盘路径: {}", disk.physical_path))?;
+            println!("[VHD] 已挂载为 {} (磁盘 {})", disk.physical_path, disk.disk_number);
+            Ok(disk)
+        }
+    }
+
+    /// 卸载虚拟磁盘
+    pub fn detach(self) -> Result<()> {
+        let result = self.detach_inner();
+        std::mem::forget(self);
+        result
+    }
+
+    fn detach_inner(&self) -> Result<()> {
+        use windows::Win32::Foundation::{CloseHandle, WIN32_ERROR};
+        use windows::Win32::Storage::Vhd::{DetachVirtualDisk, DETACH_VIRTUAL_DISK_FLAG_NONE};
+
+        unsafe {
+            let result = DetachVirtualDisk(self.handle, DETACH_VIRTUAL_DISK_FLAG_NONE, 0);
+            let _ = CloseHandle(self.handle);
+            if result != WIN32_ERROR(0) {
+                bail!("DetachVirtualDisk 失败: {:?}", result);
+            }
+        }
+        println!("[VHD] 已卸载 {}", self.physical_path);
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for AttachedVirtualDisk {
+    fn drop(&mut self) {
+        if let Err(e) = self.detach_inner() {
+            println!("[VHD] 警告: {}", e);
+        }
+    }
+}
+
+/// 从 `\\.\PhysicalDriveN` 中解析磁盘号
+pub fn parse_physical_drive_number(path: &str) -> Option<u32> {
+    let upper = path.to_uppercase();
+    let index = upper.rfind("PHYSICALDRIVE")?;
+    upper[index + "PHYSICALDRIVE".len()..].trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fat32_writer::Fat32Volume;
+    use crate::fat_format::{self, FatFormatOptions, NativeFileSystem, VolumeGeometry};
+    use crate::partition_table::{read_sectors, PartitionIo, MBR_TYPE_FAT32_LBA};
+    use std::io::Cursor;
+
+    fn create(spec: &VirtualDiskSpec) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        write_virtual_disk(&mut cursor, spec).unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    }
+
+    #[test]
+    fn test_vhd_fixed_layout() {
+        let spec = VirtualDiskSpec::new(VirtualDiskFormat::Vhd, VirtualDiskType::Fixed, 16 * MB);
+        let data = create(&spec);
+        assert_eq!(data.len() as u64, spec.initial_file_size());
+
+        let footer = &data[data.len() - 512..];
+        assert_eq!(&footer[0..8], VHD_COOKIE);
+        assert_eq!(be32(footer, 60), VHD_TYPE_FIXED);
+        assert_eq!(be64(footer, 16), u64::MAX);
+        assert_eq!(be64(footer, 48), 16 * MB);
+        assert_eq!(be32(footer, 64), vhd_checksum(footer, 64));
+        // 32768 扇区 → 481/4/17
+        assert_eq!((be32(footer, 56) >> 16, footer[58], footer[59]), (481, 4, 17));
+
+        let disk = VirtualDisk::open(Cursor::new(data)).unwrap();
+        assert_eq!((disk.format(), disk.disk_type(), disk.size()), (VirtualDiskFormat::Vhd, VirtualDiskType::Fixed, 16 * MB));
+    }
+
+    #[test]
+    fn test_vhd_dynamic_allocates_blocks() {
+        let spec = VirtualDiskSpec::new(VirtualDiskFormat::Vhd, VirtualDiskType::Dynamic, 10 * MB);
+        let data = create(&spec);
+        assert_eq!(data.len(), 512 + 1024 + 512 + 512);
+        assert_eq!(data[..512], data[data.len() - 512..]);
+        assert_eq!(&data[512..520], VHD_DYNAMIC_COOKIE);
+        assert_eq!(be32(&data, 512 + 28), 5);
+
+        let mut disk = VirtualDisk::open(Cursor::new(data)).unwrap();
+        assert_eq!(disk.allocated_blocks(), Some(0));
+        disk.seek(SeekFrom::Start(4 * MB - 2)).unwrap();
+        disk.write_all(b"hello").unwrap();
+        assert_eq!(disk.allocated_blocks(), Some(2));
+
+        let data = disk.into_inner().into_inner();
+        assert_eq!(data.len() as u64, 2560 + 2 * (512 + VHD_BLOCK_SIZE));
+        assert_eq!(data[..512], data[data.len() - 512..]);
+        assert_eq!(be32(&data, 1536), VHD_UNUSED_BLOCK);
+        assert_ne!(be32(&data, 1536 + 4), VHD_UNUSED_BLOCK);
+
+        let mut disk = VirtualDisk::open(Cursor::new(data)).unwrap();
+        let mut buf = [0u8; 9];
+        disk.seek(SeekFrom::Start(4 * MB - 4)).unwrap();
+        disk.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"\0\0hello\0\0");
+    }
+
+    #[test]
+    fn test_vhdx_structures() {
+        let spec = VirtualDiskSpec::new(VirtualDiskFormat::Vhdx, VirtualDiskType::Dynamic, 100 * MB);
+        let data = create(&spec);
+        assert_eq!(data.len() as u64, 4 * MB);
+        assert_eq!(data.len() as u64, spec.initial_file_size());
+        assert_eq!(&data[0..8], VHDX_SIGNATURE);
+
+        for offset in VHDX_HEADER_OFFSETS {
+            let header = &data[offset as usize..offset as usize + VHDX_HEADER_SIZE];
+            assert_eq!(&header[0..4], VHDX_HEADER_SIGNATURE);
+            assert!(crc32c_valid(header));
+        }
+        for offset in VHDX_REGION_OFFSETS {
+            let table = &data[offset as usize..offset as usize + VHDX_REGION_TABLE_SIZE];
+            assert!(crc32c_valid(table));
+            assert_eq!(le32(table, 8), 2);
+            assert_eq!(Guid(table[16..32].try_into().unwrap()).to_string(), VHDX_BAT_REGION);
+            assert_eq!(le64(table, 32), VHDX_BAT_OFFSET);
+        }
+
+        let metadata = &data[VHDX_METADATA_OFFSET as usize..];
+        assert_eq!(&metadata[0..8], VHDX_METADATA_SIGNATURE);
+        assert_eq!(le16(metadata, 10), 5);
+        assert_eq!(le32(metadata, VHDX_METADATA_ITEMS_OFFSET) as u64, VHDX_BLOCK_SIZE);
+        assert_eq!(le64(metadata, VHDX_METADATA_ITEMS_OFFSET + 8), 100 * MB);
+
+        let disk = VirtualDisk::open(Cursor::new(data)).unwrap();
+        assert_eq!((disk.format(), disk.disk_type(), disk.size()), (VirtualDiskFormat::Vhdx, VirtualDiskType::Dynamic, 100 * MB));
+        assert_eq!(disk.allocated_blocks(), Some(0));
+    }
+
+    #[test]
+    fn test_vhdx_fixed_bat() {
+        let spec = VirtualDiskSpec::new(VirtualDiskFormat::Vhdx, VirtualDiskType::Fixed, 64 * MB);
+        let data = create(&spec);
+        assert_eq!(data.len() as u64, 4 * MB + 64 * MB);
+        assert_eq!(le64(&data, VHDX_BAT_OFFSET as usize), (4 * MB) | VHDX_PAYLOAD_FULLY_PRESENT);
+        assert_eq!(le64(&data, VHDX_BAT_OFFSET as usize + 8), (36 * MB) | VHDX_PAYLOAD_FULLY_PRESENT);
+
+        let mut disk = VirtualDisk::open(Cursor::new(data)).unwrap();
+        assert_eq!(disk.disk_type(), VirtualDiskType::Fixed);
+        disk.seek(SeekFrom::Start(40 * MB)).unwrap();
+        disk.write_all(b"fixed").unwrap();
+        assert_eq!(disk.allocated_blocks(), Some(2));
+        let data = disk.into_inner().into_inner();
+        assert_eq!(&data[(36 * MB + 8 * MB) as usize..][..5], b"fixed");
+
+        // 超过 128 个数据块时 BAT 中穿插扇区位图项
+        assert_eq!(vhdx_bat_entries(129, 128), 130);
+        assert!(VirtualDiskSpec::new(VirtualDiskFormat::Vhd, VirtualDiskType::Fixed, VHD_MAX_SIZE + MB).validate().is_err());
+    }
+
+    #[test]
+    fn test_partition_and_format_roundtrip() {
+        let spec = VirtualDiskSpec::new(VirtualDiskFormat::Vhdx, VirtualDiskType::Dynamic, 96 * MB);
+        let mut disk = VirtualDisk::open(Cursor::new(create(&spec))).unwrap();
+        let (offset, len) = write_single_partition_mbr(&mut disk, spec.size_bytes, MBR_TYPE_FAT32_LBA).unwrap();
+        assert_eq!(offset, MB);
+
+        let geometry = VolumeGeometry {
+            total_sectors: len / SECTOR_SIZE,
+            sector_size: SECTOR_SIZE as u32,
+            hidden_sectors: offset / SECTOR_SIZE,
+        };
+        let options = FatFormatOptions {
+            file_system: NativeFileSystem::Fat32,
+            label: "VHDTEST".to_string(),
+            quick: true,
+            cluster_size: None,
+        };
+        let mut partition = PartitionIo::new(disk, offset, len);
+        fat_format::format_volume(&mut partition, &geometry, &options, &mut |_, _| {}).unwrap();
+        let mut volume = Fat32Volume::open(partition).unwrap();
+        volume.write_file(r"Windows\marker.txt", &mut &b"native boot"[..], 11).unwrap();
+        volume.flush().unwrap();
+
+        let disk = volume.into_inner().into_inner();
+        assert!(disk.allocated_blocks().unwrap() < 3);
+        let mut disk = VirtualDisk::open(disk.into_inner()).unwrap();
+        let geometry = DiskGeometry::new(SECTOR_SIZE, spec.size_bytes);
+        let sector = read_sectors(&mut disk, &geometry, 0, 1).unwrap();
+        assert_eq!(Mbr::decode(&sector).unwrap().entries[0].partition_type, MBR_TYPE_FAT32_LBA);
+
+        let mut volume = Fat32Volume::open(PartitionIo::new(disk, offset, len)).unwrap();
+        assert_eq!(volume.read_file(r"Windows\marker.txt").unwrap(), b"native boot");
+    }
+
+    #[test]
+    fn test_install_target_paths() {
+        let target = VirtualDiskInstallTarget {
+            host_partition: "D:".to_string(),
+            file_name: normalize_file_name("Win11-test.vhd", VirtualDiskFormat::Vhdx),
+            spec: VirtualDiskSpec::new(VirtualDiskFormat::Vhdx, VirtualDiskType::Dynamic, 64 * 1024 * MB),
+            label: "Win11".to_string(),
+        };
+        assert_eq!(target.file_name, "Win11-test.vhdx");
+        assert_eq!(target.bcd_device(), r"vhd=[D:]\VHD\Win11-test.vhdx");
+        assert!(target.validate().is_ok());
+        assert_eq!(normalize_file_name("win10", VirtualDiskFormat::Vhd), "win10.vhd");
+        assert_eq!(parse_physical_drive_number(r"\\.\PhysicalDrive3"), Some(3));
+    }
+}
@@ -0,0 +1,455 @@
+//! 卷标识模块
+//!
+//! 进入 PE 后盘符经常被重新分配，按盘符查找目标分区并不可靠。本模块用不随盘符变化的
+//! 信息标识一个卷，在正常系统中写入配置，在 PE 中解析回当前的盘符：
+//!
+//! - GPT 分区：分区 GUID（PartitionId）
+//! - MBR 分区：磁盘签名 + 分区起始偏移
+//! - 两者都附带卷序列号，分区信息不可用时作为备用依据
+//!
+//! 配置中的格式为 `GPT:{GUID}|Serial:1A2B3C4D` 或 `MBR:1A2B3C4D@1048576|Serial:1A2B3C4D`。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+
+/// 分区的固定标识
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PartitionId {
+    /// GPT 分区 GUID（大写，带花括号）
+    Gpt(String),
+    /// MBR 磁盘签名和分区起始偏移（字节）
+    Mbr { signature: u32, offset: u64 },
+    /// 无法获取（如动态磁盘、虚拟卷）
+    #[default]
+    Unknown,
+}
+
+/// 卷标识
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VolumeIdentity {
+    pub partition: PartitionId,
+    /// 卷序列号（格式化后会改变），0 表示未知
+    pub serial: u32,
+}
+
+impl VolumeIdentity {
+    /// 是否包含可用于匹配的信息
+    pub fn is_known(&self) -> bool {
+        self.partition != PartitionId::Unknown || self.serial != 0
+    }
+
+    /// 解析配置中的标识字符串
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut identity = VolumeIdentity::default();
+        for part in value.trim().split('|').filter(|p| !p.is_empty()) {
+            let (key, val) = part.split_once(':').with_context(|| format!("无效的卷标识: {}", value))?;
+            match key.trim() {
+                "GPT" => {
+                    let guid = val.trim().trim_start_matches('{').trim_end_matches('}');
+                    if guid.len() != 36 || !guid.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+                        bail!("无效的分区 GUID: {}", val);
+                    }
+                    identity.partition = PartitionId::Gpt(format!("{{{}}}", guid.to_ascii_uppercase()));
+                }
+                "MBR" => {
+                    let (signature, offset) = val.split_once('@').with_context(|| format!("无效的 MBR 标识: {}", val))?;
+                    identity.partition = PartitionId::Mbr {
+                        signature: u32::from_str_radix(signature.trim(), 16).context("无效的磁盘签名")?,
+                        offset: offset.trim().parse().context("无效的分区偏移")?,
+                    };
+                }
+                "Serial" => identity.serial = u32::from_str_radix(val.trim(), 16).context("无效的卷序列号")?,
+                _ => {}
+            }
+        }
+        if !identity.is_known() {
+            bail!("空的卷标识");
+        }
+        Ok(identity)
+    }
+
+    /// 与实际卷的匹配程度，`None` 表示肯定不是同一个卷
+    ///
+    /// 分区标识一致为 2（序列号也一致再加 1），只有序列号一致为 1。
+    pub fn match_score(&self, actual: &VolumeIdentity) -> Option<u8> {
+        let serial_match = self.serial != 0 && self.serial == actual.serial;
+        match (&self.partition, &actual.partition) {
+            (PartitionId::Unknown, _) | (_, PartitionId::Unknown) => serial_match.then_some(1),
+            (expected, actual) if expected == actual => Some(2 + serial_match as u8),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VolumeIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        match &self.partition {
+            PartitionId::Gpt(guid) => parts.push(format!("GPT:{}", guid)),
+            PartitionId::Mbr { signature, offset } => parts.push(format!("MBR:{:08X}@{}", signature, offset)),
+            PartitionId::Unknown => {}
+        }
+        if self.serial != 0 {
+            parts.push(format!("Serial:{:08X}", self.serial));
+        }
+        write!(f, "{}", parts.join("|"))
+    }
+}
+
+/// 当前系统中的一个卷
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountedVolume {
+    /// 卷 GUID 路径（`\\?\Volume{...}\`）
+    pub volume_name: String,
+    /// 盘符
+    pub letter: Option<char>,
+    pub identity: VolumeIdentity,
+}
+
+impl MountedVolume {
+    /// 访问路径：有盘符时为 `D:`，否则为不带结尾反斜杠的卷 GUID 路径
+    pub fn path(&self) -> String {
+        match self.letter {
+            Some(letter) => format!("{}:", letter),
+            None => self.volume_name.trim_end_matches('\\').to_string(),
+        }
+    }
+}
+
+/// 在卷列表中查找与标识最匹配的卷
+///
+/// 只有序列号可用且有多个卷匹配时无法确定，返回 `None`。
+pub fn find_volume<'a>(expected: &VolumeIdentity, volumes: &'a [MountedVolume]) -> Option<&'a MountedVolume> {
+    let mut scored: Vec<(u8, &MountedVolume)> = volumes
+        .iter()
+        .filter_map(|v| expected.match_score(&v.identity).map(|score| (score, v)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    match scored.as_slice() {
+        [] => None,
+        [(1, _), (1, _), ..] => {
+            log::warn!("有多个卷的序列号与 {} 一致，无法确定", expected);
+            None
+        }
+        [(_, volume), ..] => Some(volume),
+    }
+}
+
+/// 获取盘符对应卷的标识
+#[cfg(windows)]
+pub fn identify_letter(letter: char) -> Result<VolumeIdentity> {
+    let root = format!("{}:\\", letter.to_ascii_uppercase());
+    let device = format!("\\\\.\\{}:", letter.to_ascii_uppercase());
+    let identity = VolumeIdentity {
+        partition: query_partition_id(&device).unwrap_or_else(|e| {
+            log::warn!("获取 {} 的分区信息失败: {:#}", device, e);
+            PartitionId::Unknown
+        }),
+        serial: volume_serial(&root),
+    };
+    if !identity.is_known() {
+        bail!("无法获取 {}: 的卷标识", letter);
+    }
+    Ok(identity)
+}
+
+#[cfg(not(windows))]
+pub fn identify_letter(_letter: char) -> Result<VolumeIdentity> {
+    bail!("仅支持Windows系统")
+}
+
+/// 获取分区当前的大小、序列号和卷标，用于核对交接配置中的目标分区
+///
+/// `path` 为 `D:` 或不带结尾反斜杠的卷 GUID 路径。
+#[cfg(windows)]
+pub fn partition_snapshot(path: &str) -> Result<letrecovery_config::PartitionSnapshot> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetVolumeInformationW};
+
+    let root = to_wide(&format!("{}\\", path.trim_end_matches('\\')));
+    let mut total_bytes = 0u64;
+    let mut serial = 0u32;
+    let mut label = [0u16; 261];
+    unsafe {
+        GetDiskFreeSpaceExW(PCWSTR(root.as_ptr()), None, Some(&mut total_bytes as *mut u64), None)
+            .with_context(|| format!("获取 {} 的大小失败", path))?;
+        GetVolumeInformationW(PCWSTR(root.as_ptr()), Some(&mut label), Some(&mut serial as *mut u32), None, None, None)
+            .with_context(|| format!("获取 {} 的卷信息失败", path))?;
+    }
+    Ok(letrecovery_config::PartitionSnapshot { size_bytes: total_bytes, serial, label: wide_to_string(&label) })
+}
+
+#[cfg(not(windows))]
+pub fn partition_snapshot(_path: &str) -> Result<letrecovery_config::PartitionSnapshot> {
+    bail!("仅支持Windows系统")
+}
+
+/// 枚举当前系统中的全部卷（包括没有盘符的卷）
+#[cfg(windows)]
+pub fn list_volumes() -> Vec<MountedVolume> {
+    use windows::Win32::Storage::FileSystem::{FindFirstVolumeW, FindNextVolumeW, FindVolumeClose};
+
+    let mut volumes = Vec::new();
+    let mut buf = [0u16; 260];
+    let Ok(handle) = (unsafe { FindFirstVolumeW(&mut buf) }) else {
+        return volumes;
+    };
+    loop {
+        let volume_name = wide_to_string(&buf);
+        // CreateFile 打开卷时不能带结尾反斜杠
+        let device = volume_name.trim_end_matches('\\').to_string();
+        let identity = VolumeIdentity {
+            partition: query_partition_id(&device).unwrap_or_default(),
+            serial: volume_serial(&volume_name),
+        };
+        volumes.push(MountedVolume { letter: volume_letter(&volume_name), volume_name, identity });
+        if unsafe { FindNextVolumeW(handle, &mut buf) }.is_err() {
+            break;
+        }
+    }
+    unsafe {
+        let _ = FindVolumeClose(handle);
+    }
+    volumes
+}
+
+#[cfg(not(windows))]
+pub fn list_volumes() -> Vec<MountedVolume> {
+    Vec::new()
+}
+
+/// 把标识解析为当前的访问路径
+pub fn resolve(expected: &VolumeIdentity) -> Option<String> {
+    let volumes = list_volumes();
+    find_volume(expected, &volumes).map(mount_path)
+}
+
+/// 卷的可用访问路径
+///
+/// 卷没有盘符时尝试分配一个空闲盘符（后续的 DISM、bcdboot 等工具需要盘符），
+/// 分配失败则返回卷 GUID 路径。
+pub fn mount_path(volume: &MountedVolume) -> String {
+    if volume.letter.is_some() {
+        return volume.path();
+    }
+    match assign_letter(&volume.volume_name) {
+        Ok(letter) => {
+            log::info!("已为卷 {} 分配盘符 {}:", volume.volume_name, letter);
+            format!("{}:", letter)
+        }
+        Err(e) => {
+            log::warn!("为卷 {} 分配盘符失败，使用卷路径: {:#}", volume.volume_name, e);
+            volume.path()
+        }
+    }
+}
+
+#[cfg(windows)]
+fn wide_to_string(buf: &[u16]) -> String {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..len])
+}
+
+#[cfg(windows)]
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// 卷的第一个盘符挂载点
+#[cfg(windows)]
+fn volume_letter(volume_name: &str) -> Option<char> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetVolumePathNamesForVolumeNameW;
+
+    let wide = to_wide(volume_name);
+    let mut buf = [0u16; 1024];
+    let mut len = 0u32;
+    unsafe { GetVolumePathNamesForVolumeNameW(PCWSTR(wide.as_ptr()), Some(&mut buf), &mut len) }.ok()?;
+    // 结果为以 NUL 分隔的路径列表
+    buf[..len as usize]
+        .split(|&c| c == 0)
+        .map(String::from_utf16_lossy)
+        .find(|path| path.len() == 3 && path.ends_with(":\\"))
+        .and_then(|path| path.chars().next())
+}
+
+/// 卷序列号，获取失败时为 0
+#[cfg(windows)]
+fn volume_serial(root: &str) -> u32 {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetVolumeInformationW;
+
+    let wide = to_wide(root);
+    let mut serial = 0u32;
+    unsafe {
+        let _ = GetVolumeInformationW(PCWSTR(wide.as_ptr()), None, Some(&mut serial), None, None, None);
+    }
+    serial
+}
+
+/// 调用无输入的 IOCTL，输出写入 `out`
+#[cfg(windows)]
+unsafe fn ioctl<T>(handle: windows::Win32::Foundation::HANDLE, code: u32, out: &mut T) -> windows::core::Result<()> {
+    let mut bytes_returned = 0u32;
+    windows::Win32::System::IO::DeviceIoControl(
+        handle,
+        code,
+        None,
+        0,
+        Some(out as *mut T as *mut _),
+        std::mem::size_of::<T>() as u32,
+        Some(&mut bytes_returned),
+        None,
+    )
+}
+
+#[cfg(windows)]
+fn file_handle(file: &std::fs::File) -> windows::Win32::Foundation::HANDLE {
+    use std::os::windows::io::AsRawHandle;
+    windows::Win32::Foundation::HANDLE(file.as_raw_handle() as _)
+}
+
+#[cfg(windows)]
+fn format_guid(guid: &windows::core::GUID) -> String {
+    format!(
+        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        guid.data1,
+        guid.data2,
+        guid.data3,
+        guid.data4[0],
+        guid.data4[1],
+        guid.data4[2],
+        guid.data4[3],
+        guid.data4[4],
+        guid.data4[5],
+        guid.data4[6],
+        guid.data4[7]
+    )
+}
+
+/// 读取卷所在分区的固定标识
+#[cfg(windows)]
+fn query_partition_id(device: &str) -> Result<PartitionId> {
+    use std::io::Read;
+    use windows::Win32::System::Ioctl::{
+        IOCTL_DISK_GET_PARTITION_INFO_EX, IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS, PARTITION_INFORMATION_EX,
+        PARTITION_STYLE_GPT, PARTITION_STYLE_MBR, VOLUME_DISK_EXTENTS,
+    };
+
+    let volume = std::fs::File::open(device).with_context(|| format!("打开 {} 失败", device))?;
+    let mut info = PARTITION_INFORMATION_EX::default();
+    unsafe { ioctl(file_handle(&volume), IOCTL_DISK_GET_PARTITION_INFO_EX, &mut info) }.context("获取分区信息失败")?;
+
+    if info.PartitionStyle == PARTITION_STYLE_GPT {
+        return Ok(PartitionId::Gpt(format_guid(unsafe { &info.Anonymous.Gpt.PartitionId })));
+    }
+    if info.PartitionStyle != PARTITION_STYLE_MBR {
+        return Ok(PartitionId::Unknown);
+    }
+
+    // MBR 磁盘签名位于所在磁盘第 0 扇区的 440 字节处
+    let mut extents = VOLUME_DISK_EXTENTS::default();
+    unsafe { ioctl(file_handle(&volume), IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS, &mut extents) }
+        .context("获取卷所在磁盘失败")?;
+    if extents.NumberOfDiskExtents != 1 {
+        return Ok(PartitionId::Unknown);
+    }
+    let disk_path = format!("\\\\.\\PhysicalDrive{}", extents.Extents[0].DiskNumber);
+    let mut sector = [0u8; 512];
+    std::fs::File::open(&disk_path)
+        .and_then(|mut disk| disk.read_exact(&mut sector))
+        .with_context(|| format!("读取 {} 失败", disk_path))?;
+    Ok(PartitionId::Mbr {
+        signature: u32::from_le_bytes([sector[440], sector[441], sector[442], sector[443]]),
+        offset: info.StartingOffset as u64,
+    })
+}
+
+/// 给没有盘符的卷分配一个空闲盘符
+#[cfg(windows)]
+fn assign_letter(volume_name: &str) -> Result<char> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{GetLogicalDrives, SetVolumeMountPointW};
+
+    let used = unsafe { GetLogicalDrives() };
+    let letter = ('D'..='Z')
+        .find(|&c| used & (1 << (c as u8 - b'A')) == 0)
+        .context("没有可用的盘符")?;
+    let mount_point = to_wide(&format!("{}:\\", letter));
+    let volume = to_wide(volume_name);
+    unsafe { SetVolumeMountPointW(PCWSTR(mount_point.as_ptr()), PCWSTR(volume.as_ptr())) }?;
+    Ok(letter)
+}
+
+#[cfg(not(windows))]
+fn assign_letter(_volume_name: &str) -> Result<char> {
+    bail!("仅支持Windows系统")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpt(guid: &str, serial: u32) -> VolumeIdentity {
+        VolumeIdentity { partition: PartitionId::Gpt(guid.to_string()), serial }
+    }
+
+    fn volume(letter: Option<char>, identity: VolumeIdentity) -> MountedVolume {
+        MountedVolume {
+            volume_name: format!("\\\\?\\Volume{{{}}}\\", letter.map(|l| l as u32).unwrap_or(0)),
+            letter,
+            identity,
+        }
+    }
+
+    #[test]
+    fn test_identity_roundtrip() {
+        let identity = gpt("{0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9}", 0x1234ABCD);
+        let text = identity.to_string();
+        assert_eq!(text, "GPT:{0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9}|Serial:1234ABCD");
+        assert_eq!(VolumeIdentity::parse(&text).unwrap(), identity);
+        // GUID 大小写和花括号不影响解析
+        assert_eq!(
+            VolumeIdentity::parse("GPT:0a1b2c3d-4e5f-6071-8293-a4b5c6d7e8f9|Serial:1234abcd").unwrap(),
+            identity
+        );
+
+        let mbr = VolumeIdentity { partition: PartitionId::Mbr { signature: 0xDEADBEEF, offset: 1048576 }, serial: 0 };
+        assert_eq!(mbr.to_string(), "MBR:DEADBEEF@1048576");
+        assert_eq!(VolumeIdentity::parse(&mbr.to_string()).unwrap(), mbr);
+
+        assert!(VolumeIdentity::parse("").is_err());
+        assert!(VolumeIdentity::parse("GPT:not-a-guid").is_err());
+        assert!(VolumeIdentity::parse("MBR:DEADBEEF").is_err());
+    }
+
+    #[test]
+    fn test_find_volume() {
+        let guid = "{0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9}";
+        let volumes = vec![
+            volume(Some('C'), gpt("{11111111-1111-1111-1111-111111111111}", 0x1111)),
+            // 进入 PE 后目标分区被分配到 M:
+            volume(Some('M'), gpt(guid, 0x2222)),
+            // 没有盘符的 MBR 分区
+            volume(None, VolumeIdentity { partition: PartitionId::Mbr { signature: 0xAA55, offset: 2048 }, serial: 0x3333 }),
+        ];
+
+        // 格式化后序列号变化，仍按分区 GUID 找到
+        let found = find_volume(&gpt(guid, 0x9999), &volumes).unwrap();
+        assert_eq!(found.path(), "M:");
+
+        let mbr = VolumeIdentity { partition: PartitionId::Mbr { signature: 0xAA55, offset: 2048 }, serial: 0 };
+        let found = find_volume(&mbr, &volumes).unwrap();
+        assert!(found.path().starts_with("\\\\?\\Volume{") && !found.path().ends_with('\\'));
+
+        // 分区标识不同时即使序列号一致也不匹配
+        assert!(find_volume(&gpt("{22222222-2222-2222-2222-222222222222}", 0x2222), &volumes).is_none());
+
+        // 只有序列号：唯一时匹配，重复时无法确定
+        let serial_only = VolumeIdentity { partition: PartitionId::Unknown, serial: 0x1111 };
+        assert_eq!(find_volume(&serial_only, &volumes).unwrap().path(), "C:");
+        let mut duplicated = volumes.clone();
+        duplicated.push(volume(Some('N'), VolumeIdentity { partition: PartitionId::Unknown, serial: 0x1111 }));
+        assert!(find_volume(&serial_only, &duplicated).is_none());
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ConfigKind, HandoffConfig};
+
+/// 备份格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFormat {
+    #[default]
+    Wim,
+    Esd,
+    Swm,
+    Gho,
+    /// 原始扇区镜像
+    Raw,
+}
+
+fn default_swm_split_size() -> u32 {
+    4096
+}
+
+/// 系统备份配置（用于PE环境内备份）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// 备份保存路径
+    pub save_path: String,
+    /// 备份名称
+    pub name: String,
+    /// 备份描述
+    pub description: String,
+    /// 源分区盘符
+    pub source_partition: String,
+    /// 源分区卷标识，PE 中优先据此查找源分区
+    pub source_volume_id: String,
+    /// 数据分区卷标识
+    pub data_volume_id: String,
+    /// 是否增量备份
+    pub incremental: bool,
+    /// 备份格式
+    pub format: BackupFormat,
+    /// SWM分卷大小（MB）
+    #[serde(default = "default_swm_split_size")]
+    pub swm_split_size: u32,
+}
+
+impl HandoffConfig for BackupConfig {
+    const KIND: ConfigKind = ConfigKind::Backup;
+}
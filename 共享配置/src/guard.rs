@@ -0,0 +1,121 @@
+//! 交接配置的防篡改与过期校验
+//!
+//! - 正常系统端为每次操作生成一次性校验码（nonce），写入 PE 引导项的 loadoptions，
+//!   PE 启动后可从注册表 `SystemStartOptions` 读回
+//! - 配置内容用以校验码为密钥的 HMAC-SHA256 签名，校验码本身不写入配置文件，
+//!   因此残留的配置无法被其他 PE 引导项（或手动启动的 PE）使用
+//! - 安装配置记录目标分区的大小、序列号和卷标，PE 格式化前逐项核对
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::hash::{BuildHasher, Hasher};
+
+/// PE 引导项 loadoptions 中校验码的键名
+pub const NONCE_OPTION: &str = "LETRECOVERY_NONCE";
+
+/// 配置最长有效期（秒）
+pub const MAX_AGE_SECS: u64 = 72 * 3600;
+
+/// 允许创建时间晚于当前时间的幅度（秒）
+///
+/// PE 默认时区通常与原系统不同，而两者都把主板时钟当作本地时间，换算出的 UTC 可能相差一天左右。
+pub const MAX_FUTURE_SKEW_SECS: u64 = 26 * 3600;
+
+/// 当前 Unix 时间（秒）
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 生成一次性校验码（32 位十六进制大写）
+pub fn generate_nonce() -> String {
+    // RandomState 的密钥来自系统随机源，再混入时间和进程号
+    let mut hasher = Sha256::new();
+    for i in 0..4u64 {
+        let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+        h.write_u64(i);
+        hasher.update(h.finish().to_le_bytes());
+    }
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    to_hex(&hasher.finalize()[..16])
+}
+
+/// 写入 PE 引导项 loadoptions 的内容
+pub fn nonce_load_option(nonce: &str) -> String {
+    format!("{}={}", NONCE_OPTION, nonce)
+}
+
+/// 从 `SystemStartOptions` 中取出校验码
+pub fn nonce_from_start_options(options: &str) -> Option<String> {
+    options
+        .split_whitespace()
+        .map(|token| token.trim_start_matches('/'))
+        .find_map(|token| {
+            let (key, value) = token.split_once('=')?;
+            (key.eq_ignore_ascii_case(NONCE_OPTION) && !value.is_empty()).then(|| value.to_ascii_uppercase())
+        })
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// HMAC-SHA256，结果为十六进制大写
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+    const BLOCK: usize = 64;
+    let mut block_key = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block_key.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block_key.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    to_hex(&outer.finalize())
+}
+
+/// 比较两个签名，耗时与内容无关
+pub(crate) fn signatures_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 分区快照：写入配置时目标分区的状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartitionSnapshot {
+    /// 分区总大小（字节）
+    pub size_bytes: u64,
+    /// 卷序列号
+    pub serial: u32,
+    /// 卷标
+    pub label: String,
+}
+
+impl PartitionSnapshot {
+    /// 与当前状态逐项比较，返回所有不一致之处
+    pub fn differences(&self, actual: &PartitionSnapshot) -> Vec<String> {
+        let mut diffs = Vec::new();
+        if self.size_bytes != actual.size_bytes {
+            diffs.push(format!("大小由 {} 字节变为 {} 字节", self.size_bytes, actual.size_bytes));
+        }
+        if self.serial != actual.serial {
+            diffs.push(format!("卷序列号由 {:08X} 变为 {:08X}", self.serial, actual.serial));
+        }
+        if self.label != actual.label {
+            diffs.push(format!("卷标由「{}」变为「{}」", self.label, actual.label));
+        }
+        diffs
+    }
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ConfigKind, HandoffConfig, PartitionSnapshot};
+
+/// 驱动操作模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriverActionMode {
+    /// 无操作
+    #[default]
+    None,
+    /// 仅保存驱动（到数据目录）
+    SaveOnly,
+    /// 自动导入（保存并导入到新系统）
+    AutoImport,
+}
+
+impl DriverActionMode {
+    /// 是否需要导入驱动
+    pub fn should_import(&self) -> bool {
+        *self == Self::AutoImport
+    }
+
+    /// 是否有驱动目录（SaveOnly 或 AutoImport 时都有）
+    pub fn has_drivers(&self) -> bool {
+        *self != Self::None
+    }
+}
+
+fn default_volume_index() -> u32 {
+    1
+}
+
+/// 系统安装配置（用于PE环境内安装）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstallConfig {
+    /// 无人值守安装
+    pub unattended: bool,
+    /// 驱动还原（兼容旧版本）
+    pub restore_drivers: bool,
+    /// 驱动操作模式
+    pub driver_action_mode: DriverActionMode,
+    /// 立即重启
+    pub auto_reboot: bool,
+    /// 原系统引导GUID（用于删除旧引导项）
+    pub original_guid: String,
+    /// 多系统并存：保留原系统引导项，新系统引导项追加在后
+    pub boot_side_by_side: bool,
+    /// 多系统并存时新引导项的描述
+    pub boot_entry_description: String,
+    /// 安装分卷索引
+    #[serde(default = "default_volume_index")]
+    pub volume_index: u32,
+    /// 目标分区盘符
+    pub target_partition: String,
+    /// 目标分区卷标识，PE 中优先据此查找目标分区
+    pub target_volume_id: String,
+    /// 写入配置时目标分区的状态，PE 格式化前核对
+    pub target_snapshot: PartitionSnapshot,
+    /// 数据分区卷标识
+    pub data_volume_id: String,
+    /// 镜像文件路径（相对于数据分区）
+    pub image_path: String,
+    /// 是否为GHO格式
+    pub is_gho: bool,
+    /// CAB更新包安装
+    pub install_cab_packages: bool,
+
+    // 高级选项
+    /// 移除快捷方式小箭头
+    pub remove_shortcut_arrow: bool,
+    /// Win11恢复经典右键
+    pub restore_classic_context_menu: bool,
+    /// OOBE绕过强制联网
+    pub bypass_nro: bool,
+    /// 禁用Windows更新
+    pub disable_windows_update: bool,
+    /// 禁用Windows安全中心
+    pub disable_windows_defender: bool,
+    /// 禁用系统保留空间
+    pub disable_reserved_storage: bool,
+    /// 禁用用户账户控制
+    pub disable_uac: bool,
+    /// 禁用自动设备加密
+    pub disable_device_encryption: bool,
+    /// 删除预装UWP应用
+    pub remove_uwp_apps: bool,
+    /// 导入磁盘控制器驱动
+    pub import_storage_controller_drivers: bool,
+    /// 自定义用户名
+    pub custom_username: String,
+    /// 自定义系统盘卷标
+    pub volume_label: String,
+
+    // Win7 专用选项
+    /// Win7 UEFI 补丁（使用 UefiSeven）
+    pub win7_uefi_patch: bool,
+    /// Win7 注入USB3驱动
+    pub win7_inject_usb3_driver: bool,
+    /// Win7 注入NVMe驱动
+    pub win7_inject_nvme_driver: bool,
+    /// Win7 修复ACPI蓝屏
+    pub win7_fix_acpi_bsod: bool,
+    /// Win7 修复存储控制器蓝屏
+    pub win7_fix_storage_bsod: bool,
+}
+
+impl InstallConfig {
+    /// 判断是否需要导入驱动
+    /// 优先使用新的driver_action_mode，兼容旧的restore_drivers
+    pub fn should_import_drivers(&self) -> bool {
+        if self.driver_action_mode != DriverActionMode::None {
+            self.driver_action_mode.should_import()
+        } else {
+            self.restore_drivers
+        }
+    }
+
+    /// 判断是否有驱动目录需要处理
+    pub fn has_driver_data(&self) -> bool {
+        self.driver_action_mode.has_drivers() || self.restore_drivers
+    }
+
+    /// 向离线系统应用的高级选项名称，按应用顺序排列（Win7 UEFI 补丁随引导修复应用，不在其中）
+    pub fn enabled_tweaks(&self) -> Vec<&'static str> {
+        [
+            (self.remove_shortcut_arrow, "移除快捷方式小箭头"),
+            (self.restore_classic_context_menu, "恢复经典右键菜单"),
+            (self.bypass_nro, "OOBE绕过强制联网"),
+            (self.disable_windows_update, "禁用Windows更新"),
+            (self.disable_windows_defender, "禁用Windows安全中心"),
+            (self.disable_reserved_storage, "禁用系统保留空间"),
+            (self.disable_uac, "禁用用户账户控制"),
+            (self.disable_device_encryption, "禁用自动设备加密"),
+            (self.remove_uwp_apps, "删除预装UWP应用"),
+            (self.import_storage_controller_drivers, "导入磁盘控制器驱动"),
+            (self.win7_inject_usb3_driver, "Win7注入USB3驱动"),
+            (self.win7_inject_nvme_driver, "Win7注入NVMe驱动"),
+            (self.win7_fix_acpi_bsod, "Win7修复ACPI蓝屏"),
+            (self.win7_fix_storage_bsod, "Win7修复存储控制器蓝屏"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+impl HandoffConfig for InstallConfig {
+    const KIND: ConfigKind = ConfigKind::Install;
+}
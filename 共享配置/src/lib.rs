@@ -0,0 +1,286 @@
+//! LetRecovery 交接配置
+//!
+//! 正常系统端在重启进入 PE 前写入安装/备份配置，PE 端读取后执行。两端共用本 crate 的
+//! 类型定义和（反）序列化，字段不会再出现一端有、另一端没有的情况。
+//!
+//! 配置文件为带版本号和签名的 JSON：
+//!
+//! ```json
+//! { "schema_version": 2, "kind": "install", "created_at": 1760000000, "config": { ... }, "hmac": "..." }
+//! ```
+//!
+//! - 版本号与本程序不一致时拒绝读取，并给出明确的提示
+//! - 未知字段视为错误，不再静默丢弃
+//! - 签名、有效期和目标分区校验见 [`guard`] 模块
+//!
+//! PE 中操作失败回滚后留给正常系统端的报告见 [`FailureReport`]，演练模式输出的安装计划见 [`InstallPlan`]。
+
+mod backup;
+pub mod guard;
+mod install;
+mod plan;
+mod report;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+pub use backup::{BackupConfig, BackupFormat};
+pub use guard::{generate_nonce, nonce_from_start_options, nonce_load_option, unix_now, PartitionSnapshot};
+pub use install::{DriverActionMode, InstallConfig};
+pub use plan::{InstallPlan, PlanAction};
+pub use report::{FailureReport, FAILURE_REPORT_FILE};
+
+/// 当前配置格式版本，修改字段时递增
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// 配置文件名
+pub const INSTALL_CONFIG_FILE: &str = "LetRecovery_Install.json";
+pub const BACKUP_CONFIG_FILE: &str = "LetRecovery_Backup.json";
+
+/// 旧版 INI 配置文件名（仅用于提示，不再读取）
+pub const LEGACY_INSTALL_CONFIG_FILE: &str = "LetRecovery_Install.ini";
+pub const LEGACY_BACKUP_CONFIG_FILE: &str = "LetRecovery_Backup.ini";
+
+/// 配置类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigKind {
+    Install,
+    Backup,
+}
+
+impl fmt::Display for ConfigKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigKind::Install => write!(f, "安装"),
+            ConfigKind::Backup => write!(f, "备份"),
+        }
+    }
+}
+
+/// 配置读取错误
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("配置文件格式错误: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("配置文件缺少 schema_version 字段，可能已损坏")]
+    MissingVersion,
+    #[error("配置文件版本为 {found}，本程序支持的版本为 {supported}，请使用相同版本的 LetRecovery 重新发起操作")]
+    UnsupportedVersion { found: u64, supported: u32 },
+    #[error("配置类型不符：需要{expected}配置，实际为{found}配置")]
+    KindMismatch { expected: ConfigKind, found: ConfigKind },
+    #[error("配置文件缺少签名，拒绝执行")]
+    MissingSignature,
+    #[error("当前 PE 不是由本次操作创建的引导项启动的（引导参数中没有校验码），拒绝执行")]
+    NonceMissing,
+    #[error("配置签名校验失败：配置被修改过，或不属于本次启动的 PE 引导项，拒绝执行")]
+    SignatureMismatch,
+    #[error("配置创建于 {age_hours} 小时前，已超过有效期，请在正常系统中重新发起操作")]
+    Stale { age_hours: u64 },
+    #[error("配置创建时间晚于当前时间 {ahead_hours} 小时，系统时间可能有误，拒绝执行")]
+    FromFuture { ahead_hours: u64 },
+}
+
+/// 可交接给 PE 的配置
+pub trait HandoffConfig: Serialize + DeserializeOwned {
+    /// 配置类型
+    const KIND: ConfigKind;
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a, T> {
+    schema_version: u32,
+    kind: ConfigKind,
+    created_at: u64,
+    config: &'a T,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct Envelope<T> {
+    schema_version: u32,
+    kind: ConfigKind,
+    created_at: u64,
+    config: T,
+}
+
+/// 签名覆盖的内容：去掉 hmac 字段后的紧凑 JSON
+fn signed_payload(value: &serde_json::Value) -> String {
+    serde_json::to_string(value).expect("JSON 值序列化不会失败")
+}
+
+/// 序列化为带版本号和签名的 JSON
+///
+/// `nonce` 为写入 PE 引导项的一次性校验码，不会出现在输出中。
+pub fn to_json<T: HandoffConfig>(config: &T, created_at: u64, nonce: &str) -> String {
+    let envelope = EnvelopeRef { schema_version: SCHEMA_VERSION, kind: T::KIND, created_at, config };
+    let mut value = serde_json::to_value(&envelope).expect("配置序列化不会失败");
+    let hmac = guard::hmac_sha256(nonce.as_bytes(), signed_payload(&value).as_bytes());
+    if let Some(object) = value.as_object_mut() {
+        object.insert("hmac".to_string(), hmac.into());
+    }
+    serde_json::to_string_pretty(&value).expect("配置序列化不会失败")
+}
+
+/// 从 JSON 读取配置，依次校验版本、类型、签名和有效期
+///
+/// `nonce` 为当前 PE 引导参数中的校验码，`now` 为当前 Unix 时间（秒）。
+pub fn from_json<T: HandoffConfig>(content: &str, nonce: Option<&str>, now: u64) -> Result<T, ConfigError> {
+    let mut value: serde_json::Value = serde_json::from_str(content)?;
+    let version = value
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .ok_or(ConfigError::MissingVersion)?;
+    // 以后升级版本时，在这里把旧版本的 JSON 逐级迁移到当前版本
+    if version != SCHEMA_VERSION as u64 {
+        return Err(ConfigError::UnsupportedVersion { found: version, supported: SCHEMA_VERSION });
+    }
+    // 先单独检查类型，避免把安装配置当成备份配置读取时只报出字段错误
+    if let Some(kind) = value.get("kind").and_then(|k| ConfigKind::deserialize(k).ok()) {
+        if kind != T::KIND {
+            return Err(ConfigError::KindMismatch { expected: T::KIND, found: kind });
+        }
+    }
+
+    let hmac = value
+        .as_object_mut()
+        .and_then(|object| object.remove("hmac"))
+        .and_then(|v| v.as_str().map(str::to_string))
+        .ok_or(ConfigError::MissingSignature)?;
+    let nonce = nonce.ok_or(ConfigError::NonceMissing)?;
+    let expected = guard::hmac_sha256(nonce.as_bytes(), signed_payload(&value).as_bytes());
+    if !guard::signatures_equal(&hmac.to_ascii_uppercase(), &expected) {
+        return Err(ConfigError::SignatureMismatch);
+    }
+
+    let envelope: Envelope<T> = serde_json::from_value(value)?;
+    if envelope.created_at > now + guard::MAX_FUTURE_SKEW_SECS {
+        return Err(ConfigError::FromFuture { ahead_hours: (envelope.created_at - now) / 3600 });
+    }
+    if now > envelope.created_at + guard::MAX_AGE_SECS {
+        return Err(ConfigError::Stale { age_hours: (now - envelope.created_at) / 3600 });
+    }
+    Ok(envelope.config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONCE: &str = "00112233445566778899AABBCCDDEEFF";
+    const NOW: u64 = 1_760_000_000;
+
+    fn sample_install() -> InstallConfig {
+        InstallConfig {
+            unattended: true,
+            driver_action_mode: DriverActionMode::AutoImport,
+            original_guid: "{3c5f1a2e-0000-4d7e-9a7b-1234567890ab}".to_string(),
+            boot_entry_description: "Windows 11 = 新系统".to_string(),
+            volume_index: 6,
+            target_partition: "D:".to_string(),
+            target_volume_id: "GPT:{0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9}|Serial:1234ABCD".to_string(),
+            target_snapshot: PartitionSnapshot { size_bytes: 128 << 30, serial: 0x1234ABCD, label: "系统".to_string() },
+            image_path: "install.wim".to_string(),
+            install_cab_packages: true,
+            custom_username: "用户".to_string(),
+            win7_inject_nvme_driver: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let install = sample_install();
+        let json = to_json(&install, NOW, NONCE);
+        assert!(!json.contains(NONCE));
+        assert_eq!(from_json::<InstallConfig>(&json, Some(NONCE), NOW + 60).unwrap(), install);
+
+        // 含 `=`、换行和引号的值不再被截断
+        let backup = BackupConfig {
+            save_path: "E:\\Backup\\sys.wim".to_string(),
+            name: "每周备份".to_string(),
+            description: "第一行\n第二行 a=b \"引号\"".to_string(),
+            source_partition: "C:".to_string(),
+            format: BackupFormat::Raw,
+            swm_split_size: 4096,
+            ..Default::default()
+        };
+        let json = to_json(&backup, NOW, NONCE);
+        assert_eq!(from_json::<BackupConfig>(&json, Some(NONCE), NOW).unwrap(), backup);
+    }
+
+    #[test]
+    fn test_rejects_mismatch() {
+        let json = to_json(&sample_install(), NOW, NONCE);
+
+        let newer = json.replace("\"schema_version\": 2", "\"schema_version\": 3");
+        match from_json::<InstallConfig>(&newer, Some(NONCE), NOW) {
+            Err(ConfigError::UnsupportedVersion { found: 3, supported: 2 }) => {}
+            other => panic!("unexpected: {:?}", other),
+        }
+        assert!(matches!(
+            from_json::<BackupConfig>(&json, Some(NONCE), NOW),
+            Err(ConfigError::KindMismatch { expected: ConfigKind::Backup, found: ConfigKind::Install })
+        ));
+        assert!(matches!(from_json::<InstallConfig>("{\"kind\":\"install\"}", None, NOW), Err(ConfigError::MissingVersion)));
+    }
+
+    #[test]
+    fn test_rejects_tampered_or_foreign() {
+        let json = to_json(&sample_install(), NOW, NONCE);
+
+        // 手动启动的 PE 或其他引导项
+        assert!(matches!(from_json::<InstallConfig>(&json, None, NOW), Err(ConfigError::NonceMissing)));
+        assert!(matches!(
+            from_json::<InstallConfig>(&json, Some("FFEEDDCCBBAA99887766554433221100"), NOW),
+            Err(ConfigError::SignatureMismatch)
+        ));
+
+        // 修改目标分区或追加字段都会使签名失效
+        let tampered = json.replace("\"target_partition\": \"D:\"", "\"target_partition\": \"E:\"");
+        assert!(matches!(from_json::<InstallConfig>(&tampered, Some(NONCE), NOW), Err(ConfigError::SignatureMismatch)));
+        let injected = json.replace("\"unattended\": true", "\"unattended\": true,\n    \"format_all_disks\": true");
+        assert!(matches!(from_json::<InstallConfig>(&injected, Some(NONCE), NOW), Err(ConfigError::SignatureMismatch)));
+
+        let unsigned = json.lines().filter(|l| !l.contains("\"hmac\"")).collect::<Vec<_>>().join("\n");
+        let unsigned = unsigned.replace("},\n}", "}\n}");
+        assert!(matches!(from_json::<InstallConfig>(&unsigned, Some(NONCE), NOW), Err(ConfigError::MissingSignature)));
+    }
+
+    #[test]
+    fn test_rejects_stale() {
+        let json = to_json(&sample_install(), NOW, NONCE);
+        let later = NOW + guard::MAX_AGE_SECS + 3600;
+        assert!(matches!(from_json::<InstallConfig>(&json, Some(NONCE), later), Err(ConfigError::Stale { age_hours: 73 })));
+        // 时区差异造成的偏差可以接受，偏差过大则拒绝
+        assert!(from_json::<InstallConfig>(&json, Some(NONCE), NOW - 16 * 3600).is_ok());
+        assert!(matches!(
+            from_json::<InstallConfig>(&json, Some(NONCE), NOW - 30 * 3600),
+            Err(ConfigError::FromFuture { ahead_hours: 30 })
+        ));
+    }
+
+    #[test]
+    fn test_guard_helpers() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            guard::hmac_sha256(b"Jefe", b"what do ya want for nothing?"),
+            "5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843"
+        );
+
+        let nonce = generate_nonce();
+        assert_eq!(nonce.len(), 32);
+        assert_ne!(nonce, generate_nonce());
+        let options = format!(" NOEXECUTE=OPTIN  {}  MININT", nonce_load_option(&nonce).to_lowercase());
+        assert_eq!(nonce_from_start_options(&options), Some(nonce));
+        assert_eq!(nonce_from_start_options(" MININT"), None);
+
+        let expected = PartitionSnapshot { size_bytes: 100, serial: 1, label: "系统".to_string() };
+        assert!(expected.differences(&expected.clone()).is_empty());
+        let actual = PartitionSnapshot { size_bytes: 200, serial: 1, label: "数据".to_string() };
+        assert_eq!(expected.differences(&actual).len(), 2);
+    }
+}
@@ -0,0 +1,196 @@
+//! 安装计划（演练模式）
+//!
+//! 演练时安装流程照常做出每一项决定，但不改动磁盘、引导和注册表，
+//! 而是把将要执行的操作按顺序记入 [`InstallPlan`]，输出为可读文本或 JSON。
+
+use serde::{Deserialize, Serialize};
+
+/// 计划中的一项操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlanAction {
+    /// 从分区分割出临时数据分区
+    CreateDataPartition { shrink_partition: String, size_mb: u64 },
+    /// 导出当前系统的驱动
+    ExportDrivers { destination: String },
+    /// 复制文件
+    CopyFile { source: String, destination: String },
+    /// 写入文件
+    WriteFile { path: String, purpose: String },
+    /// 添加 PE 引导项并设为下次启动
+    AddPeBootEntry { pe_file: String, description: String },
+    /// 格式化分区（NTFS）
+    FormatPartition { partition: String, label: Option<String> },
+    /// 清空分区（保留 System Volume Information）
+    ClearPartition { partition: String },
+    /// 释放镜像
+    ApplyImage { image: String, volume_index: u32, ghost: bool, partition: String },
+    /// 向离线系统导入驱动
+    InjectDrivers { partition: String, source: String, drivers: Vec<String> },
+    /// 向离线系统安装 CAB 包
+    InstallPackages { partition: String, source: String, packages: Vec<String> },
+    /// 向离线系统注册表应用高级选项
+    ApplyTweaks { partition: String, tweaks: Vec<String> },
+    /// 写入目标系统的引导项；`uefi` 为空表示运行时按启动模式决定
+    WriteBootEntry { partition: String, uefi: Option<bool>, description: String, side_by_side: bool },
+    /// 删除引导项
+    DeleteBootEntry { identifier: String, reason: String },
+    /// 删除标记文件、数据目录和 PE 目录
+    CleanupHandoff { data_partition: String, target_partition: String },
+    /// 删除自动创建的数据分区，并把空间合并到目标分区
+    DeletePartition { partition: String, merge_into: String },
+    /// 重启
+    Reboot,
+}
+
+impl PlanAction {
+    /// 一行可读说明
+    pub fn describe(&self) -> String {
+        match self {
+            Self::CreateDataPartition { shrink_partition, size_mb } => {
+                format!("从 {} 分割出 {} MB 的临时数据分区", shrink_partition, size_mb)
+            }
+            Self::ExportDrivers { destination } => format!("导出当前系统的驱动到 {}", destination),
+            Self::CopyFile { source, destination } => format!("复制 {} 到 {}", source, destination),
+            Self::WriteFile { path, purpose } => format!("写入{}: {}", purpose, path),
+            Self::AddPeBootEntry { pe_file, description } => {
+                format!("添加 PE 引导项「{}」({}) 并设为下次启动", description, pe_file)
+            }
+            Self::FormatPartition { partition, label } => match label {
+                Some(label) => format!("格式化 {} 为 NTFS，卷标「{}」", partition, label),
+                None => format!("格式化 {} 为 NTFS，不设卷标", partition),
+            },
+            Self::ClearPartition { partition } => format!("清空 {} 上的全部文件", partition),
+            Self::ApplyImage { image, volume_index, ghost, partition } => {
+                if *ghost {
+                    format!("使用 Ghost 把 {} 恢复到 {}", image, partition)
+                } else {
+                    format!("使用 DISM 把 {} 的第 {} 个分卷释放到 {}", image, volume_index, partition)
+                }
+            }
+            Self::InjectDrivers { partition, source, drivers } => {
+                format!("向 {} 导入 {} 中的驱动{}", partition, source, list_suffix(drivers))
+            }
+            Self::InstallPackages { partition, source, packages } => {
+                format!("向 {} 安装 {} 中的 CAB 包{}", partition, source, list_suffix(packages))
+            }
+            Self::ApplyTweaks { partition, tweaks } => {
+                if tweaks.is_empty() {
+                    format!("向 {} 写入默认设置（未启用任何高级选项）", partition)
+                } else {
+                    format!("向 {} 应用高级选项: {}", partition, tweaks.join("、"))
+                }
+            }
+            Self::WriteBootEntry { partition, uefi, description, side_by_side } => {
+                let mode = match uefi {
+                    Some(true) => "UEFI",
+                    Some(false) => "Legacy",
+                    None => "按启动模式",
+                };
+                let description = if description.is_empty() { "默认名称" } else { description };
+                let placement = if *side_by_side { "，保留原有引导项" } else { "" };
+                format!("为 {} 写入 {} 引导项「{}」{}", partition, mode, description, placement)
+            }
+            Self::DeleteBootEntry { identifier, reason } => format!("删除引导项 {}（{}）", identifier, reason),
+            Self::CleanupHandoff { data_partition, target_partition } => {
+                format!("删除 {} 上的临时数据和 {} 上的标记文件", data_partition, target_partition)
+            }
+            Self::DeletePartition { partition, merge_into } => {
+                format!("删除自动创建的分区 {}，并把空间合并到 {}", partition, merge_into)
+            }
+            Self::Reboot => "重启".to_string(),
+        }
+    }
+}
+
+/// 列表较短时附在说明后，较长时只给数量
+fn list_suffix(items: &[String]) -> String {
+    const MAX_LISTED: usize = 10;
+    match items.len() {
+        0 => String::new(),
+        n if n <= MAX_LISTED => format!(": {}", items.join("、")),
+        n => format!(": {} 等 {} 个", items[..MAX_LISTED].join("、"), n),
+    }
+}
+
+/// 安装计划
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallPlan {
+    /// 按执行顺序排列的操作
+    pub actions: Vec<PlanAction>,
+    /// 提示（如实际运行时会失败的原因）
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+impl InstallPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, action: PlanAction) {
+        self.actions.push(action);
+    }
+
+    pub fn note(&mut self, note: impl Into<String>) {
+        self.notes.push(note.into());
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// 显示给用户的文本
+    pub fn to_text(&self) -> String {
+        let mut lines = vec!["安装计划（演练，未改动任何磁盘）：".to_string()];
+        if self.actions.is_empty() {
+            lines.push("（没有要执行的操作）".to_string());
+        }
+        lines.extend(
+            self.actions
+                .iter()
+                .enumerate()
+                .map(|(i, action)| format!("{:>2}. {}", i + 1, action.describe())),
+        );
+        if !self.notes.is_empty() {
+            lines.push(String::new());
+            lines.push("注意：".to_string());
+            lines.extend(self.notes.iter().map(|n| format!("- {}", n)));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_text_and_json() {
+        let mut plan = InstallPlan::new();
+        plan.push(PlanAction::FormatPartition { partition: "C:".to_string(), label: Some("系统".to_string()) });
+        plan.push(PlanAction::ApplyImage {
+            image: "D:\\install.wim".to_string(),
+            volume_index: 6,
+            ghost: false,
+            partition: "C:".to_string(),
+        });
+        plan.push(PlanAction::DeletePartition { partition: "Y:".to_string(), merge_into: "C:".to_string() });
+        plan.push(PlanAction::Reboot);
+        plan.note("Ghost 工具不可用");
+
+        let text = plan.to_text();
+        assert!(text.contains(" 1. 格式化 C: 为 NTFS，卷标「系统」"));
+        assert!(text.contains(" 2. 使用 DISM 把 D:\\install.wim 的第 6 个分卷释放到 C:"));
+        assert!(text.contains(" 3. 删除自动创建的分区 Y:，并把空间合并到 C:"));
+        assert!(text.contains("- Ghost 工具不可用"));
+
+        let json = plan.to_json();
+        assert!(json.contains("\"action\": \"format_partition\""));
+        assert_eq!(InstallPlan::from_json(&json).unwrap(), plan);
+    }
+}
@@ -0,0 +1,88 @@
+//! PE 操作失败报告
+//!
+//! PE 中的操作在改动目标分区前失败时回滚到原系统，并在原系统盘根目录写入报告，
+//! 正常系统端下次启动时读取、显示后删除。
+
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigKind;
+
+/// 报告文件名（位于原系统盘根目录）
+pub const FAILURE_REPORT_FILE: &str = "LetRecovery_FailureReport.json";
+
+/// 失败报告
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureReport {
+    /// 失败的操作
+    pub operation: ConfigKind,
+    /// 失败时间（Unix 秒）
+    pub failed_at: u64,
+    /// 失败时所处的阶段
+    pub stage: String,
+    /// 错误信息
+    pub error: String,
+    /// 回滚已完成的操作
+    #[serde(default)]
+    pub rollback_actions: Vec<String>,
+    /// 回滚中未能完成的操作
+    #[serde(default)]
+    pub rollback_warnings: Vec<String>,
+}
+
+impl FailureReport {
+    pub fn new(operation: ConfigKind, stage: &str, error: &str) -> Self {
+        Self {
+            operation,
+            failed_at: crate::unix_now(),
+            stage: stage.to_string(),
+            error: error.to_string(),
+            rollback_actions: Vec::new(),
+            rollback_warnings: Vec::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// 显示给用户的说明
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!("上次在 PE 中的{}操作在「{}」阶段失败：", self.operation, self.stage),
+            self.error.clone(),
+            String::new(),
+            "目标分区未被改动，已回滚到原系统。".to_string(),
+        ];
+        lines.extend(self.rollback_actions.iter().map(|a| format!("- {}", a)));
+        if !self.rollback_warnings.is_empty() {
+            lines.push(String::new());
+            lines.push("以下回滚操作未完成，可能需要手动处理：".to_string());
+            lines.extend(self.rollback_warnings.iter().map(|w| format!("- {}", w)));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_roundtrip_and_summary() {
+        let mut report = FailureReport::new(ConfigKind::Install, "检查目标分区", "目标分区已被 BitLocker 锁定");
+        report.rollback_actions.push("已删除 PE 引导项".to_string());
+        report.rollback_warnings.push("删除 PE 文件失败".to_string());
+
+        let parsed = FailureReport::from_json(&report.to_json()).unwrap();
+        assert_eq!(parsed, report);
+
+        let summary = parsed.summary();
+        assert!(summary.contains("安装操作在「检查目标分区」阶段失败"));
+        assert!(summary.contains("- 已删除 PE 引导项"));
+        assert!(summary.contains("- 删除 PE 文件失败"));
+    }
+}
@@ -1,4 +1,5 @@
 use eframe::egui;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::{self, Receiver};
 use std::sync::{Arc, Mutex};
@@ -44,7 +45,7 @@ pub struct InstallProgress {
 }
 
 /// 引导模式选择
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum BootModeSelection {
     #[default]
     Auto,
@@ -70,6 +71,23 @@ pub enum InstallMode {
     ViaPE,        // 通过PE安装（目标分区是当前系统分区）
 }
 
+/// 安装目标类型
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InstallTargetKind {
+    #[default]
+    Partition,    // 安装到分区
+    VirtualDisk,  // 安装到虚拟磁盘（VHD/VHDX，本机启动）
+}
+
+impl std::fmt::Display for InstallTargetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallTargetKind::Partition => write!(f, "分区"),
+            InstallTargetKind::VirtualDisk => write!(f, "虚拟磁盘 (VHD/VHDX)"),
+        }
+    }
+}
+
 /// 备份模式
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum BackupMode {
@@ -86,6 +104,7 @@ pub enum BackupFormat {
     Esd,          // ESD格式（高压缩）
     Swm,          // SWM格式（分卷）
     Gho,          // GHO格式（Ghost）
+    Raw,          // 原始扇区镜像（逐扇区，zstd压缩）
 }
 
 impl std::fmt::Display for BackupFormat {
@@ -95,6 +114,7 @@ impl std::fmt::Display for BackupFormat {
             BackupFormat::Esd => write!(f, "ESD"),
             BackupFormat::Swm => write!(f, "SWM"),
             BackupFormat::Gho => write!(f, "GHO"),
+            BackupFormat::Raw => write!(f, "RAW"),
         }
     }
 }
@@ -107,6 +127,7 @@ impl BackupFormat {
             BackupFormat::Esd => "esd",
             BackupFormat::Swm => "swm",
             BackupFormat::Gho => "gho",
+            BackupFormat::Raw => crate::core::raw_image::IMAGE_EXTENSION,
         }
     }
     
@@ -117,33 +138,35 @@ impl BackupFormat {
             BackupFormat::Esd => "ESD镜像",
             BackupFormat::Swm => "SWM分卷镜像",
             BackupFormat::Gho => "GHO镜像",
+            BackupFormat::Raw => "原始扇区镜像",
         }
     }
     
-    /// 转换为配置文件中的数值
-    pub fn to_config_value(&self) -> u8 {
+    /// 转换为交接配置中的备份格式
+    pub fn to_config_value(&self) -> letrecovery_config::BackupFormat {
         match self {
-            BackupFormat::Wim => 0,
-            BackupFormat::Esd => 1,
-            BackupFormat::Swm => 2,
-            BackupFormat::Gho => 3,
+            BackupFormat::Wim => letrecovery_config::BackupFormat::Wim,
+            BackupFormat::Esd => letrecovery_config::BackupFormat::Esd,
+            BackupFormat::Swm => letrecovery_config::BackupFormat::Swm,
+            BackupFormat::Gho => letrecovery_config::BackupFormat::Gho,
+            BackupFormat::Raw => letrecovery_config::BackupFormat::Raw,
         }
     }
     
-    /// 从配置文件数值转换
-    pub fn from_config_value(value: u8) -> Self {
+    /// 从交接配置中的备份格式转换
+    pub fn from_config_value(value: letrecovery_config::BackupFormat) -> Self {
         match value {
-            0 => BackupFormat::Wim,
-            1 => BackupFormat::Esd,
-            2 => BackupFormat::Swm,
-            3 => BackupFormat::Gho,
-            _ => BackupFormat::Wim,
+            letrecovery_config::BackupFormat::Wim => BackupFormat::Wim,
+            letrecovery_config::BackupFormat::Esd => BackupFormat::Esd,
+            letrecovery_config::BackupFormat::Swm => BackupFormat::Swm,
+            letrecovery_config::BackupFormat::Gho => BackupFormat::Gho,
+            letrecovery_config::BackupFormat::Raw => BackupFormat::Raw,
         }
     }
 }
 
 /// 驱动操作选项
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum DriverAction {
     /// 无操作
     None,
@@ -173,8 +196,12 @@ pub struct InstallOptions {
     pub export_drivers: bool,
     pub auto_reboot: bool,
     pub boot_mode: BootModeSelection,
+    pub boot_side_by_side: bool,
+    pub boot_entry_description: String,
     pub advanced_options: AdvancedOptions,
     pub driver_action: DriverAction,
+    /// 安装到虚拟磁盘时的目标（为 None 时安装到分区）
+    pub virtual_disk: Option<crate::core::vhd::VirtualDiskInstallTarget>,
 }
 
 /// 主应用结构
@@ -222,13 +249,26 @@ pub struct App {
     pub export_drivers: bool,
     pub auto_reboot: bool,
     pub selected_boot_mode: BootModeSelection,
+    pub boot_side_by_side: bool,
+    pub boot_entry_description: String,
     pub driver_action: DriverAction,
+    // 安装目标（分区 / 虚拟磁盘）
+    pub install_target_kind: InstallTargetKind,
+    pub vhd_format: crate::core::vhd::VirtualDiskFormat,
+    pub vhd_type: crate::core::vhd::VirtualDiskType,
+    pub vhd_size_gb: u64,
+    pub vhd_file_name: String,
 
     // 高级选项
     pub advanced_options: AdvancedOptions,
     pub show_advanced_options: bool,
     pub storage_driver_default_target: Option<String>,
 
+    // 安装配置方案
+    pub install_profiles: Vec<crate::core::install_profile::InstallProfile>,
+    pub selected_install_profile: Option<String>,
+    pub profile_name_input: String,
+
     // 安装相关
     pub install_options: InstallOptions,
     pub install_target_partition: String,
@@ -400,6 +440,12 @@ pub struct App {
     pub batch_format_selected: std::collections::HashSet<String>,
     pub batch_format_rx: Option<Receiver<crate::ui::tools::batch_format::BatchFormatResult>>,
     pub batch_format_partitions_rx: Option<Receiver<Vec<crate::ui::tools::FormatablePartition>>>,
+    pub batch_format_file_system: String,
+    pub batch_format_label: String,
+    pub batch_format_quick: bool,
+    /// 格式化进度 (盘符, 进度, 当前步骤)
+    pub batch_format_progress: Option<(String, u8, String)>,
+    pub batch_format_progress_rx: Option<Receiver<(String, u8, String)>>,
     
     // GHO密码查看对话框
     pub show_gho_password_dialog: bool,
@@ -440,6 +486,65 @@ pub struct App {
     pub quick_partition_result_rx: Option<Receiver<crate::core::quick_partition::QuickPartitionResult>>,
     pub resize_existing_result_rx: Option<Receiver<crate::core::quick_partition::ResizePartitionResult>>,
     
+    // 恢复分区表对话框
+    pub show_partition_restore_dialog: bool,
+    pub partition_restore_state: crate::ui::tools::PartitionRestoreDialogState,
+    pub partition_restore_disks_rx: Option<Receiver<Vec<crate::core::quick_partition::PhysicalDisk>>>,
+    pub partition_restore_result_rx: Option<Receiver<Result<crate::core::partition_backup::RestoreReport, String>>>,
+    
+    // 丢失分区扫描对话框
+    pub show_partition_scan_dialog: bool,
+    pub partition_scan_state: crate::ui::tools::PartitionScanDialogState,
+    pub partition_scan_disks_rx: Option<Receiver<Vec<crate::core::quick_partition::PhysicalDisk>>>,
+    pub partition_scan_progress_rx: Option<Receiver<f32>>,
+    pub partition_scan_result_rx: Option<Receiver<Result<crate::core::partition_scan::ScanReport, String>>>,
+    pub partition_scan_write_rx: Option<Receiver<Result<String, String>>>,
+    pub partition_scan_cancel_flag: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
+    
+    // MBR 转 GPT 对话框
+    pub show_mbr_to_gpt_dialog: bool,
+    pub mbr_to_gpt_selected_partition: Option<String>,
+    pub mbr_to_gpt_confirm: bool,
+    pub mbr_to_gpt_running: bool,
+    pub mbr_to_gpt_message: String,
+    pub mbr_to_gpt_result_rx: Option<Receiver<Result<crate::core::mbr_to_gpt::ConversionReport, String>>>,
+    
+    // 制作安装 U 盘对话框
+    pub show_usb_creator_dialog: bool,
+    pub usb_creator_state: crate::ui::tools::UsbCreatorDialogState,
+    pub usb_creator_disks_rx: Option<Receiver<Vec<crate::core::quick_partition::PhysicalDisk>>>,
+    pub usb_creator_progress_rx: Option<Receiver<(u8, String)>>,
+    pub usb_creator_result_rx: Option<Receiver<Result<crate::core::usb_creator::UsbReport, String>>>,
+    pub usb_creator_cancel_flag: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
+
+    // 原始扇区镜像工具
+    pub show_raw_image_dialog: bool,
+    pub raw_image_state: crate::ui::tools::RawImageDialogState,
+    pub raw_image_disks_rx: Option<Receiver<Vec<crate::core::quick_partition::PhysicalDisk>>>,
+    pub raw_image_progress_rx: Option<Receiver<(u8, String)>>,
+    pub raw_image_result_rx: Option<Receiver<Result<crate::ui::tools::raw_image::RawImageOutcome, String>>>,
+    /// 只读挂载中的镜像（关闭对话框时卸载）
+    #[cfg(windows)]
+    pub raw_image_mounted: Option<crate::core::raw_image::MountedRawImage>,
+
+    // 磁盘表面测试
+    pub show_surface_test_dialog: bool,
+    pub surface_test_state: crate::ui::tools::SurfaceTestDialogState,
+    pub surface_test_disks_rx: Option<Receiver<Vec<crate::core::quick_partition::PhysicalDisk>>>,
+    pub surface_test_progress_rx: Option<Receiver<crate::core::surface_test::SurfaceScan>>,
+    pub surface_test_result_rx: Option<Receiver<Result<crate::core::surface_test::SurfaceScan, String>>>,
+    pub surface_test_cancel_flag: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
+    /// 安装目标分区的表面测试警告缓存（盘符, 警告）
+    pub install_surface_warning: Option<(char, Option<crate::core::surface_test::SurfaceWarning>)>,
+
+    // 安全擦除
+    pub show_secure_wipe_dialog: bool,
+    pub secure_wipe_state: crate::ui::tools::SecureWipeDialogState,
+    pub secure_wipe_disks_rx: Option<Receiver<(Vec<crate::core::quick_partition::PhysicalDisk>, Vec<crate::ui::tools::FormatablePartition>)>>,
+    pub secure_wipe_progress_rx: Option<Receiver<(u8, String)>>,
+    pub secure_wipe_result_rx: Option<Receiver<Result<crate::ui::tools::secure_wipe::WipeOutcome, String>>>,
+    pub secure_wipe_cancel_flag: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
+    
     // 镜像校验对话框
     pub show_image_verify_dialog: bool,
     pub image_verify_file_path: String,
@@ -470,6 +575,12 @@ pub struct App {
     /// 小白模式待自动开始标志：镜像加载完成后自动开始安装
     pub easy_mode_pending_auto_start: bool,
     
+    // 技师自动任务
+    /// 是否已检查过可移动磁盘上的自动任务
+    pub auto_job_checked: bool,
+    /// 等待倒计时或正在分区的自动任务
+    pub auto_job: Option<crate::ui::auto_job::AutoJobState>,
+    
     // 内嵌资源管理器
     pub embedded_assets: crate::ui::EmbeddedAssets,
     
@@ -485,6 +596,12 @@ pub struct App {
     /// 是否显示无人值守冲突提示对话框
     pub show_unattend_conflict_modal: bool,
     
+    // 演练安装
+    /// 开始安装时只生成安装计划，不改动磁盘
+    pub install_dry_run: bool,
+    /// 演练生成的安装计划（有值时显示）
+    pub install_plan: Option<letrecovery_config::InstallPlan>,
+    
     // 安装时BitLocker解锁对话框
     /// 是否显示安装前BitLocker解锁对话框
     pub show_install_bitlocker_dialog: bool,
@@ -623,9 +740,19 @@ impl Default for App {
             export_drivers: true,
             auto_reboot: false,
             selected_boot_mode: BootModeSelection::Auto,
+            boot_side_by_side: false,
+            boot_entry_description: String::new(),
             driver_action: DriverAction::AutoImport,
+            install_target_kind: InstallTargetKind::Partition,
+            vhd_format: crate::core::vhd::VirtualDiskFormat::Vhdx,
+            vhd_type: crate::core::vhd::VirtualDiskType::Dynamic,
+            vhd_size_gb: 64,
+            vhd_file_name: "Windows".to_string(),
             advanced_options: AdvancedOptions::default(),
             show_advanced_options: false,
+            install_profiles: Vec::new(),
+            selected_install_profile: None,
+            profile_name_input: String::new(),
             storage_driver_default_target: None,
             install_options: InstallOptions::default(),
             install_target_partition: String::new(),
@@ -738,6 +865,11 @@ impl Default for App {
             batch_format_selected: HashSet::new(),
             batch_format_rx: None,
             batch_format_partitions_rx: None,
+            batch_format_file_system: "NTFS".to_string(),
+            batch_format_label: "新加卷".to_string(),
+            batch_format_quick: true,
+            batch_format_progress: None,
+            batch_format_progress_rx: None,
             // GHO密码查看对话框
             show_gho_password_dialog: false,
             gho_password_file_path: String::new(),
@@ -773,6 +905,53 @@ impl Default for App {
             quick_partition_disks_rx: None,
             quick_partition_result_rx: None,
             resize_existing_result_rx: None,
+            // 恢复分区表对话框
+            show_partition_restore_dialog: false,
+            partition_restore_state: crate::ui::tools::PartitionRestoreDialogState::default(),
+            partition_restore_disks_rx: None,
+            partition_restore_result_rx: None,
+            // 丢失分区扫描对话框
+            show_partition_scan_dialog: false,
+            partition_scan_state: crate::ui::tools::PartitionScanDialogState::default(),
+            partition_scan_disks_rx: None,
+            partition_scan_progress_rx: None,
+            partition_scan_result_rx: None,
+            partition_scan_write_rx: None,
+            partition_scan_cancel_flag: None,
+            // MBR 转 GPT 对话框
+            show_mbr_to_gpt_dialog: false,
+            mbr_to_gpt_selected_partition: None,
+            mbr_to_gpt_confirm: false,
+            mbr_to_gpt_running: false,
+            mbr_to_gpt_message: String::new(),
+            mbr_to_gpt_result_rx: None,
+            // 制作安装 U 盘对话框
+            show_usb_creator_dialog: false,
+            usb_creator_state: crate::ui::tools::UsbCreatorDialogState::default(),
+            usb_creator_disks_rx: None,
+            usb_creator_progress_rx: None,
+            usb_creator_result_rx: None,
+            usb_creator_cancel_flag: None,
+            show_raw_image_dialog: false,
+            raw_image_state: crate::ui::tools::RawImageDialogState::default(),
+            raw_image_disks_rx: None,
+            raw_image_progress_rx: None,
+            raw_image_result_rx: None,
+            #[cfg(windows)]
+            raw_image_mounted: None,
+            show_surface_test_dialog: false,
+            surface_test_state: crate::ui::tools::SurfaceTestDialogState::default(),
+            surface_test_disks_rx: None,
+            surface_test_progress_rx: None,
+            surface_test_result_rx: None,
+            surface_test_cancel_flag: None,
+            install_surface_warning: None,
+            show_secure_wipe_dialog: false,
+            secure_wipe_state: crate::ui::tools::SecureWipeDialogState::default(),
+            secure_wipe_disks_rx: None,
+            secure_wipe_progress_rx: None,
+            secure_wipe_result_rx: None,
+            secure_wipe_cancel_flag: None,
             // 镜像校验对话框
             show_image_verify_dialog: false,
             image_verify_file_path: String::new(),
@@ -796,6 +975,9 @@ impl Default for App {
             easy_mode_logo_loading: HashSet::new(),
             easy_mode_auto_install: false,
             easy_mode_pending_auto_start: false,
+            // 技师自动任务
+            auto_job_checked: false,
+            auto_job: None,
             // 内嵌资源管理器
             embedded_assets: crate::ui::EmbeddedAssets::new(),
             // 无人值守检测相关
@@ -804,6 +986,9 @@ impl Default for App {
             unattend_check_rx: None,
             last_unattend_check_partition: None,
             show_unattend_conflict_modal: false,
+            // 演练安装
+            install_dry_run: false,
+            install_plan: None,
             // 安装时BitLocker解锁对话框
             show_install_bitlocker_dialog: false,
             install_bitlocker_loading: false,
@@ -862,6 +1047,14 @@ impl App {
         
         log::info!("加载预加载数据...");
         app.load_initial_data_with_preloaded(preloaded);
+
+        // 应用默认安装配置方案
+        app.apply_default_install_profile();
+
+        // 上次在 PE 中安装失败并回滚时，显示失败原因
+        if let Some(report) = crate::core::install_config::ConfigFileManager::take_failure_report() {
+            app.show_error(&report.summary());
+        }
         
         log::info!("App::new_with_preloaded 完成");
         app
@@ -1310,6 +1503,10 @@ impl eframe::App for App {
         // 检查工具箱异步操作结果
         self.check_tools_async_operations();
         
+        // 可移动磁盘上的技师自动任务
+        self.check_auto_job();
+        self.show_auto_job_dialog(ctx);
+        
         // 错误对话框
         if self.show_error_dialog {
             egui::Window::new("错误")
@@ -1332,6 +1529,37 @@ impl eframe::App for App {
                 });
         }
         
+        // 演练安装计划
+        if let Some(plan) = &self.install_plan {
+            let mut close = false;
+            egui::Window::new("安装计划（演练）")
+                .collapsible(false)
+                .resizable(true)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .min_width(520.0)
+                .show(ctx, |ui| {
+                    let text = plan.to_text();
+                    egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                        ui.label(&text);
+                    });
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("复制文本").clicked() {
+                            ui.ctx().copy_text(text.clone());
+                        }
+                        if ui.button("复制 JSON").clicked() {
+                            ui.ctx().copy_text(plan.to_json());
+                        }
+                        if ui.button("关闭").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+            if close {
+                self.install_plan = None;
+            }
+        }
+        
         // 无人值守冲突提示对话框
         if self.show_unattend_conflict_modal {
             egui::Window::new("无人值守选项不可用")
@@ -0,0 +1,798 @@
+//! 命令行模式
+//!
+//! 不打开窗口，直接调用 core 模块完成常用操作，便于部署脚本调用：
+//!
+//! ```text
+//! LetRecovery list-images <镜像文件>
+//! LetRecovery verify <镜像文件>
+//! LetRecovery install --image <镜像文件> --target <分区> [--index <分卷>] [--unattend <xml>]
+//!                     [--no-format] [--no-repair-boot] [--no-unattend] [--boot auto|uefi|legacy]
+//!                     [--side-by-side] [--boot-description <名称>] [--drivers none|save|import] [--reboot]
+//!                     [--profile <配置方案>]
+//! LetRecovery backup --source <分区> --dest <镜像文件> [--format wim|esd|raw]
+//!                    [--name <名称>] [--description <描述>] [--append]
+//! LetRecovery repair-boot --target <分区> [--boot auto|uefi|legacy] [--side-by-side] [--boot-description <名称>]
+//! LetRecovery export-drivers --dest <目录> [--source <系统分区>]
+//! LetRecovery partitions
+//! LetRecovery download <URL> --dest <目录> [--name <文件名>]
+//! ```
+//!
+//! 执行过程中的日志照常输出，标准输出的最后一行是单行 JSON 结果：
+//! `{"command":…,"success":…,"exit_code":…,"data":…,"error":…}`。
+//! 指定 `--output <文件>` 时同时把结果写入该文件。
+//!
+//! 退出码：0 成功，1 执行失败，2 参数错误，3 镜像校验未通过，4 需要管理员权限。
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::mpsc;
+
+use serde_json::{json, Value};
+
+use crate::app::{BootModeSelection, DriverAction, InstallOptions};
+use crate::core::disk::{DiskManager, Partition, PartitionStyle};
+use crate::core::dism::{Dism, DismProgress};
+use crate::core::install_profile::{InstallProfile, ProfileStore};
+use crate::ui::install_progress::{self, DirectInstallJob};
+
+/// 成功
+pub const EXIT_OK: i32 = 0;
+/// 执行失败
+pub const EXIT_FAILED: i32 = 1;
+/// 参数错误
+pub const EXIT_USAGE: i32 = 2;
+/// 镜像校验未通过
+pub const EXIT_VERIFY_FAILED: i32 = 3;
+/// 需要管理员权限
+pub const EXIT_NOT_ADMIN: i32 = 4;
+
+/// 支持的子命令
+const COMMANDS: &[&str] = &[
+    "list-images",
+    "verify",
+    "install",
+    "backup",
+    "repair-boot",
+    "export-drivers",
+    "partitions",
+    "download",
+    "help",
+];
+
+/// 需要管理员权限的子命令
+const ADMIN_COMMANDS: &[&str] = &["install", "backup", "repair-boot", "export-drivers"];
+
+/// 带参数值的选项，其余 `--xxx` 均为开关
+const VALUE_OPTIONS: &[&str] = &[
+    "--image",
+    "--index",
+    "--target",
+    "--unattend",
+    "--boot",
+    "--boot-description",
+    "--drivers",
+    "--source",
+    "--dest",
+    "--format",
+    "--name",
+    "--description",
+    "--output",
+    "--profile",
+];
+
+const USAGE: &str = "用法:
+  LetRecovery list-images <镜像文件>
+  LetRecovery verify <镜像文件>
+  LetRecovery install --image <镜像文件> --target <分区> [--index <分卷>] [--unattend <xml>]
+                      [--no-format] [--no-repair-boot] [--no-unattend] [--boot auto|uefi|legacy]
+                      [--side-by-side] [--boot-description <名称>] [--drivers none|save|import] [--reboot]
+                      [--profile <配置方案>]
+  LetRecovery backup --source <分区> --dest <镜像文件> [--format wim|esd|raw]
+                     [--name <名称>] [--description <描述>] [--append]
+  LetRecovery repair-boot --target <分区> [--boot auto|uefi|legacy] [--side-by-side] [--boot-description <名称>]
+  LetRecovery export-drivers --dest <目录> [--source <系统分区>]
+  LetRecovery partitions
+  LetRecovery download <URL> --dest <目录> [--name <文件名>]
+
+install 的 --profile 使用已保存的配置方案作为默认值，命令行中显式给出的选项优先。
+所有子命令都支持 --output <文件>，把 JSON 结果同时写入文件。";
+
+/// 命令行参数是否为子命令（第一个参数为程序路径）
+pub fn is_cli_command(args: &[String]) -> bool {
+    args.get(1)
+        .is_some_and(|arg| COMMANDS.contains(&arg.as_str()) || arg == "--help")
+}
+
+/// 解析后的参数
+#[derive(Debug, Default)]
+struct CliArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl CliArgs {
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut parsed = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let Some(value) = iter.next() else {
+                    return Err(CliError::usage(format!("{} 缺少参数值", arg)));
+                };
+                parsed.options.insert(arg.clone(), value.clone());
+            } else if arg.starts_with("--") {
+                parsed.flags.insert(arg.clone());
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.option(name)
+            .ok_or_else(|| CliError::usage(format!("缺少参数 {}", name)))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    /// 第一个位置参数，或指定选项的值
+    fn target_file(&self, option: &str, what: &str) -> Result<&str, CliError> {
+        self.positional
+            .first()
+            .map(String::as_str)
+            .or_else(|| self.option(option))
+            .ok_or_else(|| CliError::usage(format!("缺少{}", what)))
+    }
+
+    /// 检查是否有不认识的开关，避免拼写错误被悄悄忽略
+    fn check_flags(&self, allowed: &[&str]) -> Result<(), CliError> {
+        match self.flags.iter().find(|f| !allowed.contains(&f.as_str())) {
+            Some(flag) => Err(CliError::usage(format!("未知参数 {}", flag))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 命令执行错误
+#[derive(Debug)]
+struct CliError {
+    code: i32,
+    message: String,
+}
+
+impl CliError {
+    fn usage(message: impl Into<String>) -> Self {
+        Self { code: EXIT_USAGE, message: message.into() }
+    }
+
+    fn failed(message: impl Into<String>) -> Self {
+        Self { code: EXIT_FAILED, message: message.into() }
+    }
+}
+
+impl From<anyhow::Error> for CliError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::failed(format!("{:#}", e))
+    }
+}
+
+/// 命令的执行结果
+struct Outcome {
+    code: i32,
+    data: Value,
+    error: Option<String>,
+}
+
+impl Outcome {
+    fn ok(data: Value) -> Self {
+        Self { code: EXIT_OK, data, error: None }
+    }
+}
+
+/// 执行子命令，返回退出码（`args` 不含程序路径）
+pub fn run(args: &[String]) -> i32 {
+    attach_parent_console();
+
+    let command = args.first().map(String::as_str).unwrap_or("help");
+    let (output_file, outcome) = match CliArgs::parse(args.get(1..).unwrap_or_default()) {
+        Ok(cli_args) => (cli_args.option("--output").map(str::to_string), execute(command, &cli_args)),
+        Err(e) => (None, Err(e)),
+    };
+
+    let (code, data, error) = match outcome {
+        Ok(outcome) => (outcome.code, outcome.data, outcome.error),
+        Err(e) => (e.code, Value::Null, Some(e.message)),
+    };
+
+    let report = json!({
+        "command": command,
+        "success": code == EXIT_OK,
+        "exit_code": code,
+        "data": data,
+        "error": error,
+    });
+    let line = report.to_string();
+
+    if let Some(path) = output_file {
+        if let Err(e) = std::fs::write(&path, &line) {
+            eprintln!("[CLI] 写入结果文件失败 {}: {}", path, e);
+        }
+    }
+    println!("{}", line);
+    code
+}
+
+fn execute(command: &str, args: &CliArgs) -> Result<Outcome, CliError> {
+    log::info!("命令行模式: {}", command);
+
+    if ADMIN_COMMANDS.contains(&command) && !crate::utils::privilege::is_admin() {
+        return Err(CliError {
+            code: EXIT_NOT_ADMIN,
+            message: format!("{} 需要以管理员身份运行", command),
+        });
+    }
+
+    match command {
+        "list-images" => list_images(args),
+        "verify" => verify(args),
+        "install" => install(args),
+        "backup" => backup(args),
+        "repair-boot" => repair_boot(args),
+        "export-drivers" => export_drivers(args),
+        "partitions" => partitions(args),
+        "download" => download(args),
+        "help" | "--help" => {
+            eprintln!("{}", USAGE);
+            Ok(Outcome::ok(json!({ "commands": COMMANDS, "usage": USAGE })))
+        }
+        other => Err(CliError::usage(format!("未知命令 {}", other))),
+    }
+}
+
+// ==================== 镜像 ====================
+
+fn list_images(args: &CliArgs) -> Result<Outcome, CliError> {
+    args.check_flags(&[])?;
+    let file = args.target_file("--image", "镜像文件")?;
+    if !Path::new(file).exists() {
+        return Err(CliError::failed(format!("镜像文件不存在: {}", file)));
+    }
+
+    if install_progress::is_gho_image(file) {
+        let info = crate::core::ghost::Ghost::new().get_image_info(file)?;
+        return Ok(Outcome::ok(json!({
+            "file": file,
+            "format": "gho",
+            "images": [{
+                "index": 1,
+                "name": info.description,
+                "size_bytes": info.file_size,
+            }],
+        })));
+    }
+
+    let images = Dism::new().get_image_info(file)?;
+    let images: Vec<Value> = images
+        .iter()
+        .map(|image| {
+            json!({
+                "index": image.index,
+                "name": image.name,
+                "size_bytes": image.size_bytes,
+                "installation_type": image.installation_type,
+                "major_version": image.major_version,
+                "minor_version": image.minor_version,
+                "image_type": format!("{:?}", image.image_type),
+                "installable": image.verified_installable,
+            })
+        })
+        .collect();
+
+    Ok(Outcome::ok(json!({
+        "file": file,
+        "format": crate::core::image_verify::ImageType::from_extension(file).to_string().to_lowercase(),
+        "images": images,
+    })))
+}
+
+fn verify(args: &CliArgs) -> Result<Outcome, CliError> {
+    use crate::core::image_verify::{ImageVerifier, VerifyProgress, VerifyStatus};
+
+    args.check_flags(&[])?;
+    let file = args.target_file("--image", "镜像文件")?;
+
+    let (progress_tx, progress_rx) = mpsc::channel::<VerifyProgress>();
+    let reporter = std::thread::spawn(move || {
+        let mut last = None;
+        while let Ok(progress) = progress_rx.recv() {
+            if last != Some(progress.percentage) {
+                last = Some(progress.percentage);
+                eprintln!("[CLI] 校验 {}% {}", progress.percentage, progress.status);
+            }
+        }
+    });
+    let result = ImageVerifier::new().verify(file, Some(progress_tx));
+    let _ = reporter.join();
+
+    let data = json!({
+        "file": result.file_path,
+        "format": result.image_type.to_string().to_lowercase(),
+        "status": format!("{:?}", result.status).to_lowercase(),
+        "file_size": result.file_size,
+        "image_count": result.image_count,
+        "part_count": result.part_count,
+        "message": result.message,
+        "details": result.details,
+    });
+
+    if result.status == VerifyStatus::Valid {
+        Ok(Outcome::ok(data))
+    } else {
+        Ok(Outcome {
+            code: EXIT_VERIFY_FAILED,
+            data,
+            error: Some(format!("{}: {}", result.status, result.message)),
+        })
+    }
+}
+
+// ==================== 安装 / 备份 / 引导 ====================
+
+fn install(args: &CliArgs) -> Result<Outcome, CliError> {
+    args.check_flags(&["--no-format", "--no-repair-boot", "--no-unattend", "--side-by-side", "--reboot"])?;
+    let image_path = args.required("--image")?;
+    let volume_index = match args.option("--index") {
+        Some(index) => index
+            .parse::<u32>()
+            .ok()
+            .filter(|i| *i > 0)
+            .ok_or_else(|| CliError::usage(format!("无效的分卷索引: {}", index)))?,
+        None => 1,
+    };
+    let profile = match args.option("--profile") {
+        Some(name) => ProfileStore::new()
+            .load(name)
+            .map_err(|e| CliError::failed(format!("{:#}", e)))?,
+        None => InstallProfile::default(),
+    };
+    let boot_mode = match args.option("--boot") {
+        Some(_) => parse_boot_mode(args.option("--boot"))?,
+        None => profile.boot_mode,
+    };
+    let driver_action = match args.option("--drivers") {
+        Some(_) => parse_driver_action(args.option("--drivers"))?,
+        None => profile.driver_action,
+    };
+    let unattend_file = args.option("--unattend").map(str::to_string);
+
+    if !Path::new(image_path).exists() {
+        return Err(CliError::failed(format!("镜像文件不存在: {}", image_path)));
+    }
+    if let Some(ref file) = unattend_file {
+        if !Path::new(file).exists() {
+            return Err(CliError::failed(format!("无人值守文件不存在: {}", file)));
+        }
+    }
+
+    let partitions = DiskManager::get_partitions()?;
+    let target = find_partition(&partitions, args.required("--target")?)?;
+    if target.is_system_partition && !DiskManager::is_pe_environment() {
+        return Err(CliError::failed(format!(
+            "{} 是当前系统分区，需要通过 PE 安装，请使用图形界面",
+            target.letter
+        )));
+    }
+    check_unlocked(target)?;
+
+    let options = InstallOptions {
+        format_partition: profile.format_partition && !args.flag("--no-format"),
+        repair_boot: profile.repair_boot && !args.flag("--no-repair-boot"),
+        unattended_install: profile.unattended_install && !args.flag("--no-unattend"),
+        export_drivers: matches!(driver_action, DriverAction::SaveOnly | DriverAction::AutoImport),
+        auto_reboot: profile.auto_reboot || args.flag("--reboot"),
+        boot_mode,
+        boot_side_by_side: profile.boot_side_by_side || args.flag("--side-by-side"),
+        boot_entry_description: args
+            .option("--boot-description")
+            .unwrap_or(&profile.boot_entry_description)
+            .trim()
+            .to_string(),
+        advanced_options: profile.advanced_options.clone(),
+        driver_action,
+        virtual_disk: None,
+    };
+
+    let job = DirectInstallJob {
+        target_partition: target.letter.clone(),
+        image_path: image_path.to_string(),
+        volume_index,
+        options: options.clone(),
+        partitions: partitions.clone(),
+        partition_style: target.partition_style,
+        unattend_file: unattend_file.clone(),
+    };
+
+    let (progress_tx, progress_rx) = mpsc::channel::<DismProgress>();
+    let reporter = spawn_progress_reporter("安装", progress_rx);
+    let errors = install_progress::run_direct_install(job, &progress_tx);
+    drop(progress_tx);
+    let _ = reporter.join();
+
+    let data = json!({
+        "image": image_path,
+        "index": volume_index,
+        "target": target.letter,
+        "profile": args.option("--profile"),
+        "formatted": options.format_partition,
+        "boot_repaired": options.repair_boot,
+        "unattend": unattend_file.as_deref().unwrap_or(if options.unattended_install { "generated" } else { "none" }),
+        "errors": errors,
+    });
+
+    if !errors.is_empty() {
+        return Ok(Outcome {
+            code: EXIT_FAILED,
+            data,
+            error: Some(errors.join("; ")),
+        });
+    }
+
+    if options.auto_reboot {
+        println!("[CLI] 安装完成，即将重启...");
+        let _ = crate::utils::cmd::create_command("shutdown")
+            .args(["/r", "/t", "10", "/c", "LetRecovery 系统安装完成，即将重启..."])
+            .spawn();
+    }
+    Ok(Outcome::ok(data))
+}
+
+fn backup(args: &CliArgs) -> Result<Outcome, CliError> {
+    use crate::core::raw_image::{self, RawBackupOptions};
+
+    args.check_flags(&["--append"])?;
+    let dest = args.required("--dest")?;
+    let format = args.option("--format").unwrap_or("wim").to_lowercase();
+    let name = args.option("--name").unwrap_or("LetRecovery Backup").to_string();
+    let description = args.option("--description").unwrap_or_default().to_string();
+    let append = args.flag("--append");
+
+    if !matches!(format.as_str(), "wim" | "esd" | "raw") {
+        return Err(CliError::usage(format!(
+            "不支持的备份格式 {}（命令行支持 wim、esd、raw，SWM/GHO 请使用图形界面）",
+            format
+        )));
+    }
+    if append && format == "raw" {
+        return Err(CliError::usage("原始扇区镜像不支持增量备份"));
+    }
+
+    let partitions = DiskManager::get_partitions()?;
+    let source = find_partition(&partitions, args.required("--source")?)?;
+    check_unlocked(source)?;
+
+    let (progress_tx, progress_rx) = mpsc::channel::<DismProgress>();
+    let reporter = spawn_progress_reporter("备份", progress_rx);
+
+    let result = if format == "raw" {
+        let letter = source.letter.chars().next().unwrap_or('C');
+        let options = RawBackupOptions {
+            description: format!("{} {}", name, description).trim().to_string(),
+            ..Default::default()
+        };
+        let tx = progress_tx.clone();
+        raw_image::backup_device(
+            &raw_image::volume_device_path(letter),
+            Path::new(dest),
+            &options,
+            &mut |percentage, status| {
+                let _ = tx.send(DismProgress { percentage, status: status.to_string() });
+            },
+        )
+        .map(|summary| Some(summary.summary()))
+    } else {
+        let dism = Dism::new();
+        let capture_dir = format!("{}\\", source.letter);
+        let captured = if append && Path::new(dest).exists() {
+            dism.append_image(dest, &capture_dir, &name, &description, Some(progress_tx.clone()))
+        } else {
+            dism.capture_image(dest, &capture_dir, &name, &description, Some(progress_tx.clone()))
+        };
+        captured.map(|_| None)
+    };
+    drop(progress_tx);
+    let _ = reporter.join();
+
+    let summary = result?;
+    let size = std::fs::metadata(dest).map(|m| m.len()).ok();
+    Ok(Outcome::ok(json!({
+        "source": source.letter,
+        "dest": dest,
+        "format": format,
+        "name": name,
+        "appended": append,
+        "size_bytes": size,
+        "summary": summary,
+    })))
+}
+
+fn repair_boot(args: &CliArgs) -> Result<Outcome, CliError> {
+    use crate::core::bcdedit::{BootManager, BootRepairOptions};
+
+    args.check_flags(&["--side-by-side"])?;
+    let boot_mode = parse_boot_mode(args.option("--boot"))?;
+    let partitions = DiskManager::get_partitions()?;
+    let target = find_partition(&partitions, args.required("--target")?)?;
+
+    let use_uefi = install_progress::use_uefi_for(boot_mode, target.partition_style);
+    let options = BootRepairOptions {
+        side_by_side: args.flag("--side-by-side"),
+        entry_description: args.option("--boot-description").unwrap_or_default().trim().to_string(),
+    };
+    let report = BootManager::new().repair_boot_with_options(&target.letter, use_uefi, &options)?;
+
+    Ok(Outcome::ok(json!({
+        "target": target.letter,
+        "mode": if use_uefi { "uefi" } else { "legacy" },
+        "esp": report.esp_letter,
+        "entry": report.new_entry_guid,
+        "kept_entries": report
+            .other_windows_entries
+            .iter()
+            .map(|e| json!({ "identifier": e.identifier, "description": e.description }))
+            .collect::<Vec<_>>(),
+        "summary": report.summary(),
+    })))
+}
+
+fn export_drivers(args: &CliArgs) -> Result<Outcome, CliError> {
+    args.check_flags(&[])?;
+    let dest = args.required("--dest")?;
+    let dism = Dism::new();
+
+    match args.option("--source") {
+        Some(source) => {
+            let source = normalize_letter(source)
+                .ok_or_else(|| CliError::usage(format!("无效的分区: {}", source)))?;
+            dism.export_drivers_from_system(&format!("{}\\", source), dest)?;
+        }
+        None => dism.export_drivers(dest)?,
+    }
+
+    let drivers = walkdir::WalkDir::new(dest)
+        .into_iter()
+        .flatten()
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("inf"))
+        })
+        .count();
+
+    Ok(Outcome::ok(json!({
+        "dest": dest,
+        "source": args.option("--source"),
+        "driver_count": drivers,
+    })))
+}
+
+fn partitions(args: &CliArgs) -> Result<Outcome, CliError> {
+    args.check_flags(&[])?;
+    let partitions = DiskManager::get_partitions()?;
+    let list: Vec<Value> = partitions
+        .iter()
+        .map(|p| {
+            json!({
+                "letter": p.letter,
+                "label": p.label,
+                "total_size_mb": p.total_size_mb,
+                "free_size_mb": p.free_size_mb,
+                "is_system_partition": p.is_system_partition,
+                "has_windows": p.has_windows,
+                "partition_style": match p.partition_style {
+                    PartitionStyle::GPT => "gpt",
+                    PartitionStyle::MBR => "mbr",
+                    PartitionStyle::Unknown => "unknown",
+                },
+                "disk_number": p.disk_number,
+                "partition_number": p.partition_number,
+                "bitlocker": format!("{:?}", p.bitlocker_status),
+            })
+        })
+        .collect();
+    Ok(Outcome::ok(json!({ "partitions": list })))
+}
+
+// ==================== 下载 ====================
+
+fn download(args: &CliArgs) -> Result<Outcome, CliError> {
+    use crate::download::aria2::DownloadStatus;
+    use crate::download::manager::DownloadManager;
+
+    args.check_flags(&[])?;
+    let url = args.target_file("--url", "下载地址")?.to_string();
+    let dest = args.required("--dest")?.to_string();
+    let name = args.option("--name").map(str::to_string);
+    std::fs::create_dir_all(&dest).map_err(|e| CliError::failed(format!("创建目录失败 {}: {}", dest, e)))?;
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| CliError::failed(format!("启动异步运行时失败: {}", e)))?;
+    let progress = runtime.block_on(async {
+        let manager = DownloadManager::new();
+        manager.init().await?;
+        let gid = manager.add_task(&url, &dest, name.as_deref()).await?;
+
+        let mut last_percent = None;
+        let result = loop {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            let progress = match manager.get_progress(&gid).await {
+                Ok(progress) => progress,
+                Err(e) => break Err(e),
+            };
+            match progress.status {
+                DownloadStatus::Complete => break Ok(progress),
+                DownloadStatus::Error(ref e) => break Err(anyhow::anyhow!("下载失败: {}", e)),
+                _ => {
+                    let percent = progress.percentage as u32;
+                    if last_percent != Some(percent) {
+                        last_percent = Some(percent);
+                        eprintln!("[CLI] 下载 {}% ({} KB/s)", percent, progress.download_speed / 1024);
+                    }
+                }
+            }
+        };
+        let _ = manager.shutdown().await;
+        result
+    })?;
+
+    Ok(Outcome::ok(json!({
+        "url": url,
+        "dest": dest,
+        "name": name,
+        "size_bytes": progress.total_length,
+    })))
+}
+
+// ==================== 辅助 ====================
+
+/// 把 `c`、`C:`、`C:\` 统一为 `C:`
+fn normalize_letter(value: &str) -> Option<String> {
+    let trimmed = value.trim().trim_end_matches('\\');
+    let trimmed = trimmed.strip_suffix(':').unwrap_or(trimmed);
+    let mut chars = trimmed.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => Some(format!("{}:", c.to_ascii_uppercase())),
+        _ => None,
+    }
+}
+
+fn find_partition<'a>(partitions: &'a [Partition], value: &str) -> Result<&'a Partition, CliError> {
+    let letter = normalize_letter(value).ok_or_else(|| CliError::usage(format!("无效的分区: {}", value)))?;
+    partitions
+        .iter()
+        .find(|p| p.letter.eq_ignore_ascii_case(&letter))
+        .ok_or_else(|| CliError::failed(format!("找不到分区 {}", letter)))
+}
+
+fn check_unlocked(partition: &Partition) -> Result<(), CliError> {
+    if partition.bitlocker_status == crate::core::bitlocker::VolumeStatus::EncryptedLocked {
+        return Err(CliError::failed(format!("{} 已被 BitLocker 锁定，请先解锁", partition.letter)));
+    }
+    Ok(())
+}
+
+fn parse_boot_mode(value: Option<&str>) -> Result<BootModeSelection, CliError> {
+    match value.map(str::to_lowercase).as_deref() {
+        None | Some("auto") => Ok(BootModeSelection::Auto),
+        Some("uefi") => Ok(BootModeSelection::UEFI),
+        Some("legacy") | Some("bios") => Ok(BootModeSelection::Legacy),
+        Some(other) => Err(CliError::usage(format!("无效的引导模式 {}（可选 auto、uefi、legacy）", other))),
+    }
+}
+
+fn parse_driver_action(value: Option<&str>) -> Result<DriverAction, CliError> {
+    match value.map(str::to_lowercase).as_deref() {
+        None => Ok(DriverAction::default()),
+        Some("none") => Ok(DriverAction::None),
+        Some("save") => Ok(DriverAction::SaveOnly),
+        Some("import") => Ok(DriverAction::AutoImport),
+        Some(other) => Err(CliError::usage(format!("无效的驱动选项 {}（可选 none、save、import）", other))),
+    }
+}
+
+/// 把进度输出到标准错误，避免混入 JSON 结果
+fn spawn_progress_reporter(task: &'static str, rx: mpsc::Receiver<DismProgress>) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut last = None;
+        while let Ok(progress) = rx.recv() {
+            let key = (progress.status.clone(), progress.percentage / 10);
+            if last.as_ref() != Some(&key) {
+                eprintln!("[CLI] {} {}% {}", task, progress.percentage, progress.status);
+                last = Some(key);
+            }
+        }
+    })
+}
+
+/// 发布版使用 Windows 子系统，没有控制台；从命令行启动时挂到父进程的控制台上。
+/// 输出已被重定向（如部署脚本捕获输出）时保持原样。
+fn attach_parent_console() {
+    #[cfg(windows)]
+    {
+        const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+        const ATTACH_PARENT_PROCESS: u32 = -1i32 as u32;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetStdHandle(nStdHandle: u32) -> *mut std::ffi::c_void;
+            fn AttachConsole(dwProcessId: u32) -> i32;
+        }
+
+        unsafe {
+            let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+            if handle.is_null() || handle as isize == -1 {
+                AttachConsole(ATTACH_PARENT_PROCESS);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = CliArgs::parse(&args(&[
+            "--image", "D:\\install.wim", "--index", "6", "--target", "e", "--no-format", "extra",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.option("--image"), Some("D:\\install.wim"));
+        assert_eq!(parsed.option("--index"), Some("6"));
+        assert!(parsed.flag("--no-format"));
+        assert_eq!(parsed.positional, vec!["extra".to_string()]);
+        assert!(parsed.check_flags(&["--no-format"]).is_ok());
+        assert_eq!(parsed.check_flags(&[]).unwrap_err().code, EXIT_USAGE);
+
+        let err = CliArgs::parse(&args(&["--dest"])).unwrap_err();
+        assert_eq!(err.code, EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_is_cli_command() {
+        assert!(is_cli_command(&args(&["LetRecovery.exe", "list-images", "a.wim"])));
+        assert!(is_cli_command(&args(&["LetRecovery.exe", "--help"])));
+        assert!(!is_cli_command(&args(&["LetRecovery.exe", "/PEINSTALL"])));
+        assert!(!is_cli_command(&args(&["LetRecovery.exe"])));
+    }
+
+    #[test]
+    fn test_normalize_letter() {
+        assert_eq!(normalize_letter("c").as_deref(), Some("C:"));
+        assert_eq!(normalize_letter("D:").as_deref(), Some("D:"));
+        assert_eq!(normalize_letter("e:\\").as_deref(), Some("E:"));
+        assert_eq!(normalize_letter("CD"), None);
+        assert_eq!(normalize_letter("1:"), None);
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(parse_boot_mode(None).unwrap(), BootModeSelection::Auto);
+        assert_eq!(parse_boot_mode(Some("UEFI")).unwrap(), BootModeSelection::UEFI);
+        assert_eq!(parse_boot_mode(Some("bios")).unwrap(), BootModeSelection::Legacy);
+        assert!(parse_boot_mode(Some("efi")).is_err());
+        assert_eq!(parse_driver_action(Some("save")).unwrap(), DriverAction::SaveOnly);
+        assert!(parse_driver_action(Some("all")).is_err());
+    }
+}
@@ -32,6 +32,14 @@ pub struct AppConfig {
     /// 界面语言代码（默认 "zh-CN"）
     #[serde(default = "default_language")]
     pub language: String,
+    
+    /// 启动时自动应用的安装配置方案（为空表示不使用）
+    #[serde(default)]
+    pub default_profile: String,
+    
+    /// 小白模式使用的安装配置方案（为空表示使用内置推荐设置）
+    #[serde(default)]
+    pub easy_mode_profile: String,
 }
 
 /// 日志默认启用
@@ -58,6 +66,8 @@ impl Default for AppConfig {
             log_enabled: true,  // 日志默认启用
             log_retention_days: 7,  // 默认保留7天
             language: String::from("zh-CN"),  // 默认简体中文
+            default_profile: String::new(),
+            easy_mode_profile: String::new(),
         }
     }
 }
@@ -192,6 +202,22 @@ impl AppConfig {
             log::warn!("保存配置失败: {}", e);
         }
     }
+    
+    /// 设置默认安装配置方案并保存（为空表示取消）
+    pub fn set_default_profile(&mut self, name: &str) {
+        self.default_profile = name.to_string();
+        if let Err(e) = self.save() {
+            log::warn!("保存配置失败: {}", e);
+        }
+    }
+    
+    /// 设置小白模式使用的安装配置方案并保存（为空表示使用内置推荐设置）
+    pub fn set_easy_mode_profile(&mut self, name: &str) {
+        self.easy_mode_profile = name.to_string();
+        if let Err(e) = self.save() {
+            log::warn!("保存配置失败: {}", e);
+        }
+    }
 }
 
 /// 获取当前Windows用户名
@@ -0,0 +1,527 @@
+//! 技师自动任务
+//!
+//! 启动时在可移动磁盘根目录查找 `LetRecovery.job.json`，按其中的描述无人值守地完成重装：
+//! 镜像（本地路径或在线系统列表中的条目）、分卷、目标选择规则、可选的一键分区模板、
+//! 配置方案和安装后操作。倒计时结束后按普通安装流程执行，需要经过 PE 时沿用 `/PEINSTALL` 自动安装。
+//!
+//! ```json
+//! {
+//!   "image": { "path": "images\\win11.wim" },
+//!   "index": 6,
+//!   "target": "largest_ssd",
+//!   "partition_template": "auto",
+//!   "profile": "维修台",
+//!   "after_install": "reboot",
+//!   "countdown_secs": 15
+//! }
+//! ```
+//!
+//! - `image`：`{ "path": ... }`（相对路径相对于任务文件所在目录）或 `{ "catalog": "在线系统名称" }`
+//! - `target`：`current_windows`、`largest_ssd`、`largest_disk` 或 `{ "disk": 磁盘号 }`
+//! - `partition_template`：`auto`、`windows_uefi`、`windows_bios`、`system_data_half`，会清空整个目标磁盘
+//! - `profile`：配置方案名称，先在任务文件旁的 profiles 文件夹中查找，再查找程序目录
+//! - `after_install`：`reboot`（默认）或 `none`
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use super::disk::{DiskManager, Partition};
+use super::install_profile::{InstallProfile, ProfileStore};
+use super::layout_planner::{LayoutPlanner, LayoutPreset, PartitionRole};
+use super::partition_table::DiskGeometry;
+use super::quick_partition::{
+    get_next_available_drive_letter, get_used_drive_letters, PartitionLayout, PhysicalDisk,
+};
+use crate::download::config::OnlineSystem;
+
+/// 任务文件名
+pub const JOB_FILE_NAME: &str = "LetRecovery.job.json";
+
+/// 默认倒计时（秒）
+const DEFAULT_COUNTDOWN_SECS: u32 = 10;
+/// 最短倒计时，留出取消的时间
+const MIN_COUNTDOWN_SECS: u32 = 5;
+
+/// 镜像来源
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobImage {
+    /// 本地镜像文件
+    Path(String),
+    /// 在线系统列表中的条目（按显示名称匹配）
+    Catalog(String),
+}
+
+/// 目标选择规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetRule {
+    /// 装有当前 Windows 的分区（PE 中为唯一装有 Windows 的分区）
+    CurrentWindows,
+    /// 容量最大的固态硬盘
+    LargestSsd,
+    /// 容量最大的磁盘
+    LargestDisk,
+    /// 指定磁盘号
+    Disk(u32),
+}
+
+impl std::fmt::Display for TargetRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetRule::CurrentWindows => write!(f, "当前 Windows 所在分区"),
+            TargetRule::LargestSsd => write!(f, "容量最大的固态硬盘"),
+            TargetRule::LargestDisk => write!(f, "容量最大的磁盘"),
+            TargetRule::Disk(n) => write!(f, "磁盘 {}", n),
+        }
+    }
+}
+
+/// 一键分区模板
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionTemplate {
+    /// 按启动模式选择 UEFI 或 BIOS 标准布局
+    Auto,
+    WindowsUefi,
+    WindowsBios,
+    SystemDataHalf,
+}
+
+impl PartitionTemplate {
+    pub fn preset(self, uefi: bool) -> LayoutPreset {
+        match self {
+            PartitionTemplate::Auto if uefi => LayoutPreset::WindowsUefi,
+            PartitionTemplate::Auto => LayoutPreset::WindowsBios,
+            PartitionTemplate::WindowsUefi => LayoutPreset::WindowsUefi,
+            PartitionTemplate::WindowsBios => LayoutPreset::WindowsBios,
+            PartitionTemplate::SystemDataHalf => LayoutPreset::SystemDataHalf,
+        }
+    }
+}
+
+/// 安装完成后的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterInstall {
+    #[default]
+    Reboot,
+    None,
+}
+
+/// 自动任务
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoJob {
+    pub image: JobImage,
+    #[serde(default = "default_index")]
+    pub index: u32,
+    pub target: TargetRule,
+    #[serde(default)]
+    pub partition_template: Option<PartitionTemplate>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub after_install: AfterInstall,
+    #[serde(default = "default_countdown")]
+    pub countdown_secs: u32,
+}
+
+fn default_index() -> u32 {
+    1
+}
+
+fn default_countdown() -> u32 {
+    DEFAULT_COUNTDOWN_SECS
+}
+
+impl AutoJob {
+    /// 解析任务文件；未知字段视为错误，避免拼写错误的选项被静默忽略
+    pub fn from_json(content: &str) -> Result<Self> {
+        let mut job: AutoJob = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+            .context("自动任务文件格式不正确")?;
+        if job.index == 0 {
+            bail!("分卷索引从 1 开始");
+        }
+        let (JobImage::Path(ref image) | JobImage::Catalog(ref image)) = job.image;
+        if image.trim().is_empty() {
+            bail!("没有指定镜像");
+        }
+        job.countdown_secs = job.countdown_secs.max(MIN_COUNTDOWN_SECS);
+        Ok(job)
+    }
+}
+
+/// 从可移动磁盘读取的任务文件
+#[derive(Debug, Clone)]
+pub struct JobFile {
+    pub path: PathBuf,
+    pub job: AutoJob,
+}
+
+impl JobFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取自动任务文件失败: {}", path.display()))?;
+        Ok(Self { path: path.to_path_buf(), job: AutoJob::from_json(&content)? })
+    }
+
+    /// 任务文件所在目录
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// 任务文件所在盘符
+    pub fn drive_letter(&self) -> Option<char> {
+        self.path
+            .to_str()
+            .and_then(|p| p.chars().next())
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_uppercase())
+    }
+
+    /// 配置方案：先找任务文件旁的 profiles 文件夹，再找程序目录
+    pub fn load_profile(&self) -> Result<Option<InstallProfile>> {
+        let Some(name) = self.job.profile.as_deref().filter(|n| !n.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let beside_job = ProfileStore::with_dir(self.dir().join("profiles"));
+        if beside_job.exists(name) {
+            return beside_job.load(name).map(Some);
+        }
+        ProfileStore::new()
+            .load(name)
+            .map(Some)
+            .with_context(|| format!("找不到配置方案「{}」", name))
+    }
+}
+
+/// 在可移动磁盘根目录查找任务文件
+pub fn find_job_file() -> Option<PathBuf> {
+    ('A'..='Z')
+        .filter(|letter| DiskManager::is_removable_drive(*letter))
+        .map(|letter| PathBuf::from(format!("{}:\\{}", letter, JOB_FILE_NAME)))
+        .find(|path| path.is_file())
+}
+
+/// 解析后的镜像
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedImage {
+    Local(String),
+    Download { url: String, file_name: String, display_name: String },
+}
+
+/// 确定镜像位置：本地镜像检查是否存在，在线镜像在系统列表中按名称查找
+pub fn resolve_image(job_file: &JobFile, catalog: &[OnlineSystem]) -> Result<ResolvedImage> {
+    match &job_file.job.image {
+        JobImage::Path(path) => {
+            let path = Path::new(path.trim());
+            let full_path = if path.is_absolute() { path.to_path_buf() } else { job_file.dir().join(path) };
+            if !full_path.is_file() {
+                bail!("镜像文件不存在: {}", full_path.display());
+            }
+            Ok(ResolvedImage::Local(full_path.to_string_lossy().to_string()))
+        }
+        JobImage::Catalog(name) => {
+            let name = name.trim();
+            let system = catalog
+                .iter()
+                .find(|s| s.display_name.trim().eq_ignore_ascii_case(name))
+                .with_context(|| format!("在线系统列表中没有「{}」（需要联网获取列表）", name))?;
+            let file_name = system.download_url.split('/').next_back().unwrap_or("system.esd").to_string();
+            Ok(ResolvedImage::Download {
+                url: system.download_url.clone(),
+                file_name,
+                display_name: system.display_name.clone(),
+            })
+        }
+    }
+}
+
+/// 选择目标磁盘所需的磁盘信息
+#[derive(Debug, Clone)]
+pub struct DiskCandidate {
+    pub disk_number: u32,
+    pub size_bytes: u64,
+    pub is_ssd: bool,
+    /// 任务文件所在的磁盘，不能作为目标
+    pub holds_job_file: bool,
+    /// 正在运行的系统所在的磁盘，不能清空重新分区
+    pub holds_running_system: bool,
+}
+
+/// 收集本机磁盘信息
+pub fn disk_candidates(disks: &[PhysicalDisk], job_drive: Option<char>) -> Vec<DiskCandidate> {
+    let system_drive = std::env::var("SystemDrive")
+        .ok()
+        .and_then(|d| d.chars().next())
+        .map(|c| c.to_ascii_uppercase());
+
+    disks
+        .iter()
+        .map(|disk| {
+            let has_letter = |letter: Option<char>| {
+                letter.is_some_and(|l| disk.partitions.iter().any(|p| p.drive_letter == Some(l)))
+            };
+            DiskCandidate {
+                disk_number: disk.disk_number,
+                size_bytes: disk.size_bytes,
+                is_ssd: is_ssd(disk),
+                holds_job_file: has_letter(job_drive),
+                holds_running_system: has_letter(system_drive),
+            }
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn is_ssd(disk: &PhysicalDisk) -> bool {
+    super::hardware_info::detect_disk_is_ssd(disk.disk_number, &disk.model, "")
+}
+
+#[cfg(not(windows))]
+fn is_ssd(_disk: &PhysicalDisk) -> bool {
+    false
+}
+
+/// 按规则确定的安装目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTarget {
+    pub disk_number: Option<u32>,
+    /// 不使用分区模板时的目标分区盘符
+    pub partition: Option<String>,
+}
+
+impl ResolvedTarget {
+    pub fn describe(&self) -> String {
+        match (&self.partition, self.disk_number) {
+            (Some(letter), Some(disk)) => format!("{} (磁盘 {})", letter, disk),
+            (Some(letter), None) => letter.clone(),
+            (None, Some(disk)) => format!("磁盘 {}（清空后重新分区）", disk),
+            (None, None) => "未知".to_string(),
+        }
+    }
+}
+
+/// 按规则选择目标
+///
+/// 使用分区模板时目标为整块磁盘，该磁盘不能是正在运行的系统所在的磁盘；
+/// 否则目标为分区：规则为磁盘时只选择磁盘上装有 Windows 的分区，没有时报错，
+/// 避免把数据盘上的分区当作系统分区覆盖。
+pub fn resolve_target(
+    rule: TargetRule,
+    use_template: bool,
+    disks: &[DiskCandidate],
+    partitions: &[Partition],
+) -> Result<ResolvedTarget> {
+    let disk_number = match rule {
+        TargetRule::CurrentWindows => {
+            let windows: Vec<&Partition> = partitions.iter().filter(|p| p.is_system_partition).collect();
+            match windows.as_slice() {
+                [] => bail!("没有找到装有 Windows 的分区"),
+                [partition] if !use_template => {
+                    return Ok(ResolvedTarget {
+                        disk_number: partition.disk_number,
+                        partition: Some(partition.letter.clone()),
+                    });
+                }
+                [partition] => partition
+                    .disk_number
+                    .with_context(|| format!("无法确定分区 {} 所在的磁盘", partition.letter))?,
+                _ => {
+                    let letters: Vec<&str> = windows.iter().map(|p| p.letter.as_str()).collect();
+                    bail!("找到多个装有 Windows 的分区 ({})，请改用 {{\"disk\": 磁盘号}} 指定目标", letters.join(", "));
+                }
+            }
+        }
+        TargetRule::LargestSsd | TargetRule::LargestDisk => disks
+            .iter()
+            .filter(|d| !d.holds_job_file)
+            .filter(|d| rule != TargetRule::LargestSsd || d.is_ssd)
+            .max_by_key(|d| d.size_bytes)
+            .map(|d| d.disk_number)
+            .with_context(|| format!("没有找到符合规则的磁盘: {}", rule))?,
+        TargetRule::Disk(number) => {
+            if !disks.iter().any(|d| d.disk_number == number) {
+                bail!("磁盘 {} 不存在", number);
+            }
+            number
+        }
+    };
+
+    if let Some(disk) = disks.iter().find(|d| d.disk_number == disk_number) {
+        if disk.holds_job_file {
+            bail!("磁盘 {} 是任务文件所在的磁盘", disk_number);
+        }
+        if use_template && disk.holds_running_system {
+            bail!("磁盘 {} 上是正在运行的系统，分区模板只能在 PE 中使用", disk_number);
+        }
+    }
+
+    if use_template {
+        return Ok(ResolvedTarget { disk_number: Some(disk_number), partition: None });
+    }
+
+    let on_disk: Vec<&Partition> = partitions.iter().filter(|p| p.disk_number == Some(disk_number)).collect();
+    let partition = on_disk
+        .iter()
+        .find(|p| p.is_system_partition)
+        .with_context(|| {
+            format!("磁盘 {} 上没有装有 Windows 的分区，为避免覆盖数据分区，请指定 partition_template", disk_number)
+        })?;
+    Ok(ResolvedTarget { disk_number: Some(disk_number), partition: Some(partition.letter.clone()) })
+}
+
+/// 按模板生成一键分区布局，返回布局和系统分区的盘符
+pub fn template_layouts(disk: &PhysicalDisk, preset: LayoutPreset) -> Result<(Vec<PartitionLayout>, char)> {
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+    let plan = LayoutPlanner::plan_preset(geometry, preset)?;
+
+    let mut used_letters = get_used_drive_letters();
+    let mut system_letter = None;
+    let mut layouts = Vec::with_capacity(plan.partitions.len());
+    for partition in &plan.partitions {
+        let drive_letter = if partition.role == PartitionRole::Basic {
+            let letter = get_next_available_drive_letter(&used_letters).context("没有可用的盘符")?;
+            used_letters.push(letter);
+            system_letter.get_or_insert(letter);
+            Some(letter)
+        } else {
+            None
+        };
+        layouts.push(PartitionLayout {
+            size_gb: plan.size_bytes(partition) as f64 / 1024.0 / 1024.0 / 1024.0,
+            drive_letter,
+            label: partition.label.clone(),
+            is_esp: partition.role == PartitionRole::Esp,
+            is_msr: partition.role == PartitionRole::Msr,
+            is_recovery: partition.role == PartitionRole::Recovery,
+            is_active: partition.role == PartitionRole::SystemReserved,
+            fill_remaining: false,
+            file_system: partition.file_system.clone(),
+        });
+    }
+
+    let system_letter = system_letter.context("分区模板中没有系统分区")?;
+    Ok((layouts, system_letter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bitlocker::VolumeStatus;
+    use crate::core::disk::PartitionStyle;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn disk(disk_number: u32, size_gb: u64, is_ssd: bool) -> DiskCandidate {
+        DiskCandidate {
+            disk_number,
+            size_bytes: size_gb * GIB,
+            is_ssd,
+            holds_job_file: false,
+            holds_running_system: false,
+        }
+    }
+
+    fn partition(letter: &str, disk_number: u32, size_gb: u64, is_system: bool) -> Partition {
+        Partition {
+            letter: letter.to_string(),
+            total_size_mb: size_gb * 1024,
+            free_size_mb: 0,
+            label: String::new(),
+            is_system_partition: is_system,
+            has_windows: is_system,
+            partition_style: PartitionStyle::GPT,
+            disk_number: Some(disk_number),
+            partition_number: Some(1),
+            bitlocker_status: VolumeStatus::NotEncrypted,
+        }
+    }
+
+    #[test]
+    fn test_parse_job() {
+        let job = AutoJob::from_json(
+            r#"{"image":{"catalog":"Windows 11 24H2"},"target":{"disk":1},"partition_template":"auto","countdown_secs":1}"#,
+        )
+        .unwrap();
+        assert_eq!(job.image, JobImage::Catalog("Windows 11 24H2".to_string()));
+        assert_eq!(job.index, 1);
+        assert_eq!(job.target, TargetRule::Disk(1));
+        assert_eq!(job.partition_template, Some(PartitionTemplate::Auto));
+        assert_eq!(job.after_install, AfterInstall::Reboot);
+        assert_eq!(job.countdown_secs, MIN_COUNTDOWN_SECS);
+
+        let job = AutoJob::from_json(
+            "\u{feff}{\"image\":{\"path\":\"win.wim\"},\"index\":6,\"target\":\"largest_ssd\",\"after_install\":\"none\"}",
+        )
+        .unwrap();
+        assert_eq!(job.target, TargetRule::LargestSsd);
+        assert_eq!(job.after_install, AfterInstall::None);
+
+        // 拼错的字段、缺少目标或分卷为 0 都拒绝执行
+        assert!(AutoJob::from_json(r#"{"image":{"path":"a.wim"},"target":"largest_disk","reboot":true}"#).is_err());
+        assert!(AutoJob::from_json(r#"{"image":{"path":"a.wim"}}"#).is_err());
+        assert!(AutoJob::from_json(r#"{"image":{"path":"a.wim"},"index":0,"target":"largest_disk"}"#).is_err());
+    }
+
+    #[test]
+    fn test_resolve_target_by_disk_rule() {
+        let mut usb = disk(2, 500, true);
+        usb.holds_job_file = true;
+        let disks = vec![disk(0, 256, true), disk(1, 1000, false), usb];
+        let partitions = vec![
+            partition("C:", 0, 200, true),
+            partition("D:", 1, 600, false),
+            partition("E:", 1, 400, false),
+        ];
+
+        let target = resolve_target(TargetRule::LargestSsd, false, &disks, &partitions).unwrap();
+        assert_eq!(target.partition.as_deref(), Some("C:"));
+        // 磁盘 1 只有数据分区，不使用分区模板时不能选它
+        assert!(resolve_target(TargetRule::LargestDisk, false, &disks, &partitions).is_err());
+        let target = resolve_target(TargetRule::LargestDisk, true, &disks, &partitions).unwrap();
+        assert_eq!(target, ResolvedTarget { disk_number: Some(1), partition: None });
+
+        let target = resolve_target(TargetRule::Disk(1), true, &disks, &partitions).unwrap();
+        assert_eq!(target, ResolvedTarget { disk_number: Some(1), partition: None });
+        assert!(resolve_target(TargetRule::Disk(2), false, &disks, &partitions).is_err());
+        assert!(resolve_target(TargetRule::Disk(9), false, &disks, &partitions).is_err());
+    }
+
+    #[test]
+    fn test_resolve_target_current_windows() {
+        let mut disks = vec![disk(0, 256, true), disk(1, 1000, false)];
+        let partitions = vec![partition("C:", 0, 200, true), partition("D:", 1, 600, false)];
+
+        let target = resolve_target(TargetRule::CurrentWindows, false, &disks, &partitions).unwrap();
+        assert_eq!(target, ResolvedTarget { disk_number: Some(0), partition: Some("C:".to_string()) });
+        let target = resolve_target(TargetRule::CurrentWindows, true, &disks, &partitions).unwrap();
+        assert_eq!(target.disk_number, Some(0));
+
+        // 正常系统中不能清空正在运行的系统盘
+        disks[0].holds_running_system = true;
+        assert!(resolve_target(TargetRule::CurrentWindows, true, &disks, &partitions).is_err());
+        assert!(resolve_target(TargetRule::CurrentWindows, false, &disks, &partitions).is_ok());
+
+        // PE 中有多个 Windows 时不猜测
+        let partitions = vec![partition("C:", 0, 200, true), partition("D:", 1, 600, true)];
+        assert!(resolve_target(TargetRule::CurrentWindows, false, &disks, &partitions).is_err());
+    }
+
+    #[test]
+    fn test_resolve_target_data_only_disk() {
+        let disks = vec![disk(0, 256, true), disk(1, 1000, false)];
+        let partitions = vec![
+            partition("C:", 0, 200, true),
+            partition("D:", 1, 600, false),
+            partition("E:", 1, 400, false),
+        ];
+
+        let error = resolve_target(TargetRule::Disk(1), false, &disks, &partitions).unwrap_err();
+        assert!(error.to_string().contains("partition_template"), "{:#}", error);
+        let target = resolve_target(TargetRule::Disk(0), false, &disks, &partitions).unwrap();
+        assert_eq!(target.partition.as_deref(), Some("C:"));
+    }
+}
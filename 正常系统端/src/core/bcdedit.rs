@@ -5,6 +5,65 @@ use crate::utils::cmd::create_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
+use super::diskpart::DiskpartScript;
+use super::boot_code::{self, BiosBootReport};
+use super::esp_inventory::{self, BcdLoaderEntry, EspLoader, FallbackGuard};
+
+/// 引导修复选项
+#[derive(Debug, Clone, Default)]
+pub struct BootRepairOptions {
+    /// 多系统并存：保留原默认引导项，只新增一个引导项
+    pub side_by_side: bool,
+    /// 新引导项的描述（为空时使用 bcdboot 默认描述）
+    pub entry_description: String,
+}
+
+/// 引导修复报告
+#[derive(Debug, Clone, Default)]
+pub struct BootRepairReport {
+    /// 使用的 ESP 盘符（UEFI 模式）
+    pub esp_letter: Option<String>,
+    /// ESP 上保留的其他系统引导程序
+    pub kept_loaders: Vec<EspLoader>,
+    /// 被保护的其他系统回退引导程序（`EFI\Boot\bootx64.efi`）
+    pub preserved_fallback: Option<EspLoader>,
+    /// BCD 中保留的其他 Windows 引导项
+    pub other_windows_entries: Vec<BcdLoaderEntry>,
+    /// 目标系统的引导项 GUID
+    pub new_entry_guid: Option<String>,
+    /// 写入的 BIOS 引导代码（Legacy 模式）
+    pub bios_boot: Option<BiosBootReport>,
+}
+
+impl BootRepairReport {
+    /// 生成保留内容的摘要文本
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(ref fallback) = self.preserved_fallback {
+            lines.push(format!("已保留回退引导程序 {} ({})", fallback.relative_path, fallback.kind));
+        }
+        for loader in &self.kept_loaders {
+            lines.push(format!("已保留引导程序 {} ({})", loader.relative_path, loader.kind));
+        }
+        for entry in &self.other_windows_entries {
+            lines.push(format!("已保留引导项 {} {}", entry.identifier, entry.description));
+        }
+        if let Some(ref boot) = self.bios_boot {
+            lines.push(format!(
+                "已写入 BIOS 引导代码：磁盘 {} 第 {} 个主分区设为活动分区",
+                boot.disk_number,
+                boot.partition_index + 1
+            ));
+            lines.extend(boot.notes.iter().cloned());
+        }
+        if lines.is_empty() {
+            "未发现其他系统的引导程序".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
 pub struct BootManager {
     bcdedit_path: String,
     bcdboot_path: String,
@@ -49,18 +108,11 @@ impl BootManager {
         let drive_letter = windows_partition.trim_end_matches(':').trim_end_matches('\\');
         
         // Step 1: 使用 diskpart 获取该分区所在的磁盘号
-        let script1 = format!(r#"select volume {}
-detail volume
-"#, drive_letter);
-        
-        let script1_path = std::env::temp_dir().join("find_disk.txt");
-        std::fs::write(&script1_path, &script1)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script1_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = DiskpartScript::new()
+            .select_volume(drive_letter)
+            .detail_volume()
+            .run()?
+            .output;
         println!("[BOOT] 查找磁盘号:\n{}", stdout);
         
         // 解析磁盘号
@@ -87,18 +139,11 @@ detail volume
         println!("[BOOT] 目标分区在磁盘 {}", disk_num);
         
         // Step 2: 查找该磁盘上的 ESP 分区（使用 GPT 类型）
-        let script2 = format!(r#"select disk {}
-list partition
-"#, disk_num);
-        
-        let script2_path = std::env::temp_dir().join("list_part.txt");
-        std::fs::write(&script2_path, &script2)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script2_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = DiskpartScript::new()
+            .select_disk(disk_num as u32)
+            .list_partition()
+            .run()?
+            .output;
         println!("[BOOT] 分区列表:\n{}", stdout);
         
         // 查找 System/系统 类型的分区（ESP）
@@ -133,19 +178,12 @@ list partition
         let _ = create_command("mountvol").args(["S:", "/d"]).output();
         std::thread::sleep(std::time::Duration::from_millis(200));
         
-        let script3 = format!(r#"select disk {}
-select partition {}
-assign letter=S
-"#, disk_num, esp_partition);
-        
-        let script3_path = std::env::temp_dir().join("assign_esp.txt");
-        std::fs::write(&script3_path, &script3)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script3_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
+        let stdout = DiskpartScript::new()
+            .select_disk(disk_num as u32)
+            .select_partition(esp_partition as u32)
+            .assign(Some('S'))
+            .run()?
+            .output;
         println!("[BOOT] 分配 ESP 盘符:\n{}", stdout);
         
         // 等待盘符生效
@@ -190,19 +228,12 @@ assign letter=S
         println!("[BOOT] 使用 diskpart 查找 ESP");
         
         // 遍历磁盘0-3
-        for disk in 0..4 {
-            let script = format!(r#"select disk {}
-list partition
-"#, disk);
-            
-            let script_path = std::env::temp_dir().join("check_disk.txt");
-            std::fs::write(&script_path, &script)?;
-            
-            let output = create_command("diskpart")
-                .args(["/s", &script_path.to_string_lossy()])
-                .output()?;
-            
-            let stdout = gbk_to_utf8(&output.stdout);
+        for disk in 0..4u32 {
+            let stdout = DiskpartScript::new()
+                .select_disk(disk)
+                .list_partition()
+                .run()?
+                .output;
             
             // 查找 System 类型分区
             for line in stdout.lines() {
@@ -215,17 +246,11 @@ list partition
                             if let Some(num_str) = parts.get(i + 1) {
                                 if let Ok(part_num) = num_str.parse::<usize>() {
                                     // 找到了，分配盘符
-                                    let assign_script = format!(r#"select disk {}
-select partition {}
-assign letter=S
-"#, disk, part_num);
-                                    
-                                    let assign_path = std::env::temp_dir().join("assign_esp2.txt");
-                                    std::fs::write(&assign_path, &assign_script)?;
-                                    
-                                    let _ = create_command("diskpart")
-                                        .args(["/s", &assign_path.to_string_lossy()])
-                                        .output();
+                                    let _ = DiskpartScript::new()
+                                        .select_disk(disk)
+                                        .select_partition(part_num as u32)
+                                        .assign(Some('S'))
+                                        .run();
                                     
                                     std::thread::sleep(std::time::Duration::from_millis(500));
                                     
@@ -285,19 +310,107 @@ assign letter=S
         self.repair_boot_advanced(windows_partition, true)
     }
 
+    /// 列出 BCD 中的所有 Windows 启动加载器条目
+    pub fn list_loader_entries(&self) -> Result<Vec<BcdLoaderEntry>> {
+        let output = create_command(&self.bcdedit_path)
+            .args(["/enum", "osloader", "/v"])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("Failed to enumerate boot entries");
+        }
+        Ok(esp_inventory::parse_bcd_loader_entries(&gbk_to_utf8(&output.stdout)))
+    }
+
+    /// 设置引导项描述
+    pub fn set_entry_description(&self, guid: &str, description: &str) -> Result<()> {
+        let output = create_command(&self.bcdedit_path)
+            .args(["/set", guid, "description", description])
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!("Failed to set boot entry description");
+        }
+        Ok(())
+    }
+
+    /// 删除安装前记录的原系统引导项
+    ///
+    /// 只删除仍指向目标分区的条目，其他系统的引导项不会被删除。
+    /// 返回 `true` 表示已删除。
+    pub fn delete_stale_original_entry(
+        &self,
+        original_guid: &str,
+        windows_partition: &str,
+        new_entry_guid: Option<&str>,
+    ) -> Result<bool> {
+        if original_guid.is_empty() {
+            return Ok(false);
+        }
+        if new_entry_guid
+            .map(|g| g.eq_ignore_ascii_case(original_guid))
+            .unwrap_or(false)
+        {
+            return Ok(false);
+        }
+
+        let entries = self.list_loader_entries()?;
+        let stale = entries.iter().any(|e| {
+            e.identifier.eq_ignore_ascii_case(original_guid) && e.is_on_partition(windows_partition)
+        });
+        if !stale {
+            println!("[BOOT] 原引导项 {} 不指向 {}，保留", original_guid, windows_partition);
+            return Ok(false);
+        }
+
+        self.delete_boot_entry(original_guid)?;
+        println!("[BOOT] 已删除原引导项 {}", original_guid);
+        Ok(true)
+    }
+
     /// 修复指定分区的引导（高级版本，支持指定引导模式）
     pub fn repair_boot_advanced(&self, windows_partition: &str, use_uefi: bool) -> Result<()> {
+        let report =
+            self.repair_boot_with_options(windows_partition, use_uefi, &BootRepairOptions::default())?;
+        println!("[BOOT] {}", report.summary());
+        Ok(())
+    }
+
+    /// 修复指定分区的引导，清点并保留其他系统的引导程序
+    pub fn repair_boot_with_options(
+        &self,
+        windows_partition: &str,
+        use_uefi: bool,
+        options: &BootRepairOptions,
+    ) -> Result<BootRepairReport> {
         let windows_path = format!("{}\\Windows", windows_partition);
+        let mut report = BootRepairReport::default();
         
         println!("[BOOT] ========== 修复引导 ==========");
         println!("[BOOT] Windows 路径: {}", windows_path);
         println!("[BOOT] 引导模式: {}", if use_uefi { "UEFI" } else { "Legacy/BIOS" });
+        println!("[BOOT] 多系统并存: {}", options.side_by_side);
 
         // 验证 Windows 目录存在
         if !Path::new(&windows_path).exists() {
             anyhow::bail!("Windows 目录不存在: {}", windows_path);
         }
 
+        // 记录其他 Windows 引导项（bcdboot 会保留它们，这里只用于报告）
+        if let Ok(entries) = self.list_loader_entries() {
+            report.other_windows_entries = entries
+                .into_iter()
+                .filter(|e| !e.is_on_partition(windows_partition))
+                .collect();
+        }
+
+        // 多系统并存时保留原默认引导项（/d），UEFI 下新引导项排在固件启动顺序末尾（/addlast）
+        let extra_args: Vec<&str> = match (options.side_by_side, use_uefi) {
+            (true, true) => vec!["/d", "/addlast"],
+            (true, false) => vec!["/d"],
+            (false, _) => Vec::new(),
+        };
+
         if use_uefi {
             // UEFI 模式：需要找到并挂载 ESP 分区
             println!("[BOOT] UEFI 模式：查找 ESP 分区");
@@ -317,59 +430,32 @@ assign letter=S
                     // 创建必要的目录
                     let _ = std::fs::create_dir_all(&efi_ms_dir);
                     let _ = std::fs::create_dir_all(&efi_boot_dir);
-                    
-                    // 使用 bcdboot 写入 UEFI 引导文件
-                    // bcdboot C:\Windows /s S: /f UEFI /l zh-cn
-                    println!("[BOOT] 执行: bcdboot {} /s {} /f UEFI /l zh-cn", windows_path, esp_letter);
-                    let output = create_command(&self.bcdboot_path)
-                        .args([
-                            &windows_path,
-                            "/s", &esp_letter,
-                            "/f", "UEFI",
-                            "/l", "zh-cn"
-                        ])
-                        .output()?;
-                    
-                    let stdout = gbk_to_utf8(&output.stdout);
-                    let stderr = gbk_to_utf8(&output.stderr);
-                    
-                    println!("[BOOT] bcdboot stdout: {}", stdout);
-                    println!("[BOOT] bcdboot stderr: {}", stderr);
-                    
-                    if !output.status.success() {
-                        // 尝试使用 ALL 参数（同时创建 UEFI 和 BIOS 引导）
-                        println!("[BOOT] 重试：使用 ALL 模式");
-                        let output = create_command(&self.bcdboot_path)
-                            .args([
-                                &windows_path,
-                                "/s", &esp_letter,
-                                "/f", "ALL",
-                                "/l", "zh-cn"
-                            ])
-                            .output()?;
-                        
-                        let stdout = gbk_to_utf8(&output.stdout);
-                        let stderr = gbk_to_utf8(&output.stderr);
-                        println!("[BOOT] bcdboot (ALL) stdout: {}", stdout);
-                        println!("[BOOT] bcdboot (ALL) stderr: {}", stderr);
-                        
-                        if !output.status.success() {
-                            // 最后尝试不指定 /f 参数
-                            println!("[BOOT] 重试：不指定引导类型");
-                            let output = create_command(&self.bcdboot_path)
-                                .args([
-                                    &windows_path,
-                                    "/s", &esp_letter,
-                                    "/l", "zh-cn"
-                                ])
-                                .output()?;
-                            
-                            let stderr = gbk_to_utf8(&output.stderr);
-                            if !output.status.success() {
-                                anyhow::bail!("UEFI 引导修复失败: {}", stderr);
-                            }
+
+                    // 清点 ESP 上的引导程序，保护其他系统的回退引导程序
+                    let esp_root = format!("{}\\", esp_letter);
+                    let inventory = esp_inventory::scan_esp(Path::new(&esp_root));
+                    for loader in inventory.foreign_loaders() {
+                        println!("[BOOT] 发现其他系统引导程序: {} ({})", loader.relative_path, loader.kind);
+                    }
+                    report.kept_loaders = inventory.foreign_loaders().into_iter().cloned().collect();
+                    report.esp_letter = Some(esp_letter.clone());
+
+                    let fallback_guard = match FallbackGuard::protect(Path::new(&esp_root), &inventory) {
+                        Ok(guard) => guard,
+                        Err(e) => anyhow::bail!("备份其他系统的回退引导程序失败，已停止修复引导: {}", e),
+                    };
+
+                    let bcdboot_result = self.run_bcdboot_uefi(&windows_path, &esp_letter, &extra_args);
+
+                    // 无论 bcdboot 是否成功，都写回其他系统的回退引导程序
+                    if let Some(guard) = fallback_guard {
+                        let loader = guard.loader().clone();
+                        match guard.restore() {
+                            Ok(_) => report.preserved_fallback = Some(loader),
+                            Err(e) => println!("[BOOT] 警告: 恢复回退引导程序失败: {}", e),
                         }
                     }
+                    bcdboot_result?;
                     
                     // 验证引导文件是否创建成功
                     let bootmgfw = format!("{}\\EFI\\Microsoft\\Boot\\bootmgfw.efi", esp_letter);
@@ -382,7 +468,7 @@ assign letter=S
                     }
                     
                     if Path::new(&bootx64).exists() {
-                        println!("[BOOT] 引导文件已创建: {}", bootx64);
+                        println!("[BOOT] 回退引导文件已存在: {}", bootx64);
                     } else {
                         // 复制 bootmgfw.efi 到 bootx64.efi
                         if Path::new(&bootmgfw).exists() {
@@ -397,8 +483,10 @@ assign letter=S
                     println!("[BOOT] 查找 ESP 失败: {}，尝试默认方式", e);
                     
                     // 尝试默认方式（让 bcdboot 自动处理）
+                    let mut args = vec![windows_path.as_str(), "/f", "UEFI", "/l", "zh-cn"];
+                    args.extend(&extra_args);
                     let output = create_command(&self.bcdboot_path)
-                        .args([&windows_path, "/f", "UEFI", "/l", "zh-cn"])
+                        .args(&args)
                         .output()?;
                     
                     let stdout = gbk_to_utf8(&output.stdout);
@@ -415,27 +503,27 @@ assign letter=S
             // Legacy/BIOS 模式
             println!("[BOOT] Legacy 模式：写入 MBR 引导");
             
-            // 使用 bootsect 写入引导扇区
-            let bootsect_path = get_bin_dir().join("bootsect.exe");
-            if bootsect_path.exists() {
-                println!("[BOOT] 使用 bootsect 写入引导扇区");
-                let output = create_command(&bootsect_path)
-                    .args(["/nt60", windows_partition, "/mbr"])
-                    .output()?;
-                
-                let stdout = gbk_to_utf8(&output.stdout);
-                let stderr = gbk_to_utf8(&output.stderr);
-                println!("[BOOT] bootsect stdout: {}", stdout);
-                println!("[BOOT] bootsect stderr: {}", stderr);
+            // 写入 MBR 引导代码、设置活动分区并更新卷引导记录
+            let boot = boot_code::write_bios_boot_code(windows_partition)
+                .map_err(|e| anyhow::anyhow!("写入 BIOS 引导代码失败: {:#}", e))?;
+            println!(
+                "[BOOT] 已写入 BIOS 引导代码 (磁盘 {}, 主分区 {}, {} 原引导程序: {})",
+                boot.disk_number,
+                boot.partition_index + 1,
+                boot.file_system,
+                boot.loader_before
+            );
+            for note in &boot.notes {
+                println!("[BOOT] {}", note);
             }
-            
+            println!("[BOOT] 扇区变更:\n{}", boot.diff);
+            report.bios_boot = Some(boot);
+
             // bcdboot C:\Windows /f BIOS /l zh-cn
+            let mut args = vec![windows_path.as_str(), "/f", "BIOS", "/l", "zh-cn"];
+            args.extend(&extra_args);
             let output = create_command(&self.bcdboot_path)
-                .args([
-                    &windows_path,
-                    "/f", "BIOS",
-                    "/l", "zh-cn"
-                ])
+                .args(&args)
                 .output()?;
             
             let stdout = gbk_to_utf8(&output.stdout);
@@ -459,7 +547,173 @@ assign letter=S
             println!("[BOOT] Legacy 引导修复成功");
         }
 
+        // 查找目标系统的引导项，多系统并存时设置自定义描述
+        if let Ok(entries) = self.list_loader_entries() {
+            report.new_entry_guid = entries
+                .iter()
+                .rev()
+                .find(|e| e.is_on_partition(windows_partition))
+                .map(|e| e.identifier.clone());
+        }
+        if options.side_by_side && !options.entry_description.is_empty() {
+            match report.new_entry_guid {
+                Some(ref guid) => {
+                    if let Err(e) = self.set_entry_description(guid, &options.entry_description) {
+                        println!("[BOOT] 警告: 设置引导项描述失败: {}", e);
+                    } else {
+                        println!("[BOOT] 引导项 {} 描述已设置为: {}", guid, options.entry_description);
+                    }
+                }
+                None => println!("[BOOT] 警告: 未找到新引导项，无法设置描述"),
+            }
+        }
+
         println!("[BOOT] ========== 引导修复完成 ==========");
+        Ok(report)
+    }
+
+    /// 为虚拟磁盘中的系统添加本机启动（Native Boot）引导项
+    ///
+    /// 引导文件写入宿主系统的引导分区，保留原默认引导项，新引导项追加在启动菜单末尾。
+    /// `vhd_device` 为 BCD 中的设备描述（如 `vhd=[D:]\VHD\win11.vhdx`），用于找到新引导项。
+    pub fn add_vhd_boot_entry(
+        &self,
+        windows_partition: &str,
+        vhd_device: &str,
+        use_uefi: bool,
+        description: &str,
+    ) -> Result<BootRepairReport> {
+        let windows_path = format!("{}\\Windows", windows_partition);
+        let mut report = BootRepairReport::default();
+
+        println!("[BOOT] ========== 添加虚拟磁盘引导 ==========");
+        println!("[BOOT] Windows 路径: {}", windows_path);
+        println!("[BOOT] 虚拟磁盘: {}", vhd_device);
+        println!("[BOOT] 引导模式: {}", if use_uefi { "UEFI" } else { "Legacy/BIOS" });
+
+        if !Path::new(&windows_path).exists() {
+            anyhow::bail!("Windows 目录不存在: {}", windows_path);
+        }
+
+        if let Ok(entries) = self.list_loader_entries() {
+            report.other_windows_entries = entries;
+        }
+
+        if use_uefi {
+            let esp_letter = self.find_and_mount_esp()?;
+            println!("[BOOT] ESP 分区: {}", esp_letter);
+
+            let esp_root = format!("{}\\", esp_letter);
+            let inventory = esp_inventory::scan_esp(Path::new(&esp_root));
+            report.kept_loaders = inventory.foreign_loaders().into_iter().cloned().collect();
+            report.esp_letter = Some(esp_letter.clone());
+
+            let fallback_guard = match FallbackGuard::protect(Path::new(&esp_root), &inventory) {
+                Ok(guard) => guard,
+                Err(e) => anyhow::bail!("备份其他系统的回退引导程序失败，已停止添加引导: {}", e),
+            };
+            let bcdboot_result = self.run_bcdboot_uefi(&windows_path, &esp_letter, &["/d", "/addlast"]);
+            if let Some(guard) = fallback_guard {
+                let loader = guard.loader().clone();
+                match guard.restore() {
+                    Ok(_) => report.preserved_fallback = Some(loader),
+                    Err(e) => println!("[BOOT] 警告: 恢复回退引导程序失败: {}", e),
+                }
+            }
+            bcdboot_result?;
+        } else {
+            // 宿主磁盘的 MBR 和活动分区保持不变，只在宿主系统的 BCD 中添加引导项
+            let output = create_command(&self.bcdboot_path)
+                .args([windows_path.as_str(), "/f", "BIOS", "/l", "zh-cn", "/d"])
+                .output()?;
+
+            let stdout = gbk_to_utf8(&output.stdout);
+            let stderr = gbk_to_utf8(&output.stderr);
+            println!("[BOOT] bcdboot stdout: {}", stdout);
+            println!("[BOOT] bcdboot stderr: {}", stderr);
+
+            if !output.status.success() {
+                anyhow::bail!("添加虚拟磁盘引导失败: {}", stderr);
+            }
+        }
+
+        if let Ok(entries) = self.list_loader_entries() {
+            report.new_entry_guid = entries
+                .iter()
+                .rev()
+                .find(|e| e.device.eq_ignore_ascii_case(vhd_device))
+                .map(|e| e.identifier.clone());
+        }
+        report
+            .other_windows_entries
+            .retain(|e| Some(&e.identifier) != report.new_entry_guid.as_ref());
+
+        if !description.is_empty() {
+            match report.new_entry_guid {
+                Some(ref guid) => {
+                    if let Err(e) = self.set_entry_description(guid, description) {
+                        println!("[BOOT] 警告: 设置引导项描述失败: {}", e);
+                    } else {
+                        println!("[BOOT] 引导项 {} 描述已设置为: {}", guid, description);
+                    }
+                }
+                None => println!("[BOOT] 警告: 未找到虚拟磁盘引导项，无法设置描述"),
+            }
+        }
+
+        println!("[BOOT] ========== 虚拟磁盘引导添加完成 ==========");
+        Ok(report)
+    }
+
+    /// 执行 bcdboot 写入 UEFI 引导文件（失败时依次重试 ALL 模式和默认模式）
+    fn run_bcdboot_uefi(&self, windows_path: &str, esp_letter: &str, extra_args: &[&str]) -> Result<()> {
+        // bcdboot C:\Windows /s S: /f UEFI /l zh-cn
+        println!("[BOOT] 执行: bcdboot {} /s {} /f UEFI /l zh-cn {}", windows_path, esp_letter, extra_args.join(" "));
+        let mut args = vec![windows_path, "/s", esp_letter, "/f", "UEFI", "/l", "zh-cn"];
+        args.extend(extra_args);
+        let output = create_command(&self.bcdboot_path)
+            .args(&args)
+            .output()?;
+
+        let stdout = gbk_to_utf8(&output.stdout);
+        let stderr = gbk_to_utf8(&output.stderr);
+
+        println!("[BOOT] bcdboot stdout: {}", stdout);
+        println!("[BOOT] bcdboot stderr: {}", stderr);
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        // 尝试使用 ALL 参数（同时创建 UEFI 和 BIOS 引导）
+        println!("[BOOT] 重试：使用 ALL 模式");
+        let mut args = vec![windows_path, "/s", esp_letter, "/f", "ALL", "/l", "zh-cn"];
+        args.extend(extra_args);
+        let output = create_command(&self.bcdboot_path)
+            .args(&args)
+            .output()?;
+
+        let stdout = gbk_to_utf8(&output.stdout);
+        let stderr = gbk_to_utf8(&output.stderr);
+        println!("[BOOT] bcdboot (ALL) stdout: {}", stdout);
+        println!("[BOOT] bcdboot (ALL) stderr: {}", stderr);
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        // 最后尝试不指定 /f 参数
+        println!("[BOOT] 重试：不指定引导类型");
+        let mut args = vec![windows_path, "/s", esp_letter, "/l", "zh-cn"];
+        args.extend(extra_args);
+        let output = create_command(&self.bcdboot_path)
+            .args(&args)
+            .output()?;
+
+        let stderr = gbk_to_utf8(&output.stderr);
+        if !output.status.success() {
+            anyhow::bail!("UEFI 引导修复失败: {}", stderr);
+        }
         Ok(())
     }
 
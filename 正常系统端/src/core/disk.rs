@@ -4,6 +4,7 @@ use crate::utils::cmd::create_command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 use crate::core::bitlocker::{BitLockerManager, VolumeStatus};
+use crate::core::diskpart::{get_diskpart_path, CreatePartitionKind, DiskpartScript};
 
 #[cfg(windows)]
 use windows::{
@@ -21,7 +22,6 @@ use windows::{
 };
 
 // 驱动器类型常量
-#[allow(dead_code)]
 const DRIVE_REMOVABLE: u32 = 2;
 const DRIVE_FIXED: u32 = 3;
 #[allow(dead_code)]
@@ -30,40 +30,11 @@ const DRIVE_CDROM: u32 = 5;
 #[allow(dead_code)]
 const DRIVE_RAMDISK: u32 = 6;
 
-/// 获取 diskpart 可执行文件路径
-/// 优先使用内置的 diskpart，如果不存在则使用系统的
-fn get_diskpart_path() -> String {
-    let builtin_diskpart = get_bin_dir().join("diskpart").join("diskpart.exe");
-    if builtin_diskpart.exists() {
-        log::info!("使用内置 diskpart: {}", builtin_diskpart.display());
-        builtin_diskpart.to_string_lossy().to_string()
-    } else {
-        log::debug!("使用系统 diskpart");
-        "diskpart.exe".to_string()
-    }
-}
-
 /// 自动创建分区的标志文件名
 pub const AUTO_CREATED_PARTITION_MARKER: &str = "LetRecovery_AutoCreated.marker";
 
 /// 分区表类型
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub enum PartitionStyle {
-    GPT,
-    MBR,
-    #[default]
-    Unknown,
-}
-
-impl std::fmt::Display for PartitionStyle {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PartitionStyle::GPT => write!(f, "GPT"),
-            PartitionStyle::MBR => write!(f, "MBR"),
-            PartitionStyle::Unknown => write!(f, "未知"),
-        }
-    }
-}
+pub use letrecovery_disk::partition_table::PartitionStyle;
 
 #[derive(Debug, Clone)]
 pub struct Partition {
@@ -87,6 +58,19 @@ pub struct PartitionDetail {
     pub partition_number: Option<u32>,
 }
 
+/// 数据分区的选择结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataPartitionChoice {
+    /// 使用现有分区
+    Existing(String),
+    /// 从目标分区分割出新分区
+    Shrink {
+        from: char,
+        size_mb: u64,
+        max_shrink_mb: u64,
+    },
+}
+
 /// STORAGE_DEVICE_NUMBER 结构
 #[cfg(windows)]
 #[repr(C)]
@@ -375,45 +359,30 @@ impl DiskManager {
         new_letter: &str,
         size_mb: u64,
     ) -> Result<String> {
-        let script_content = format!(
-            "select volume {}\nshrink desired={}\ncreate partition primary size={}\nformat fs=ntfs quick\nassign letter={}",
-            source_partition.chars().next().unwrap_or('C'),
-            size_mb,
-            size_mb,
-            new_letter.chars().next().unwrap_or('Y').to_ascii_lowercase()
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("dp_script.txt");
-        std::fs::write(&script_path, &script_content)?;
-
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        Ok(gbk_to_utf8(&output.stdout))
+        let outcome = DiskpartScript::new()
+            .select_volume(source_partition.chars().next().unwrap_or('C'))
+            .shrink(size_mb, None)
+            .create_partition(CreatePartitionKind::Primary, Some(size_mb), None)
+            .format("ntfs", None)
+            .assign(Some(new_letter.chars().next().unwrap_or('Y').to_ascii_lowercase()))
+            .run()?
+            .ensure_success("缩小并创建分区失败")?;
+
+        Ok(outcome.output)
     }
 
     /// 删除指定分区
     pub fn delete_partition(partition_letter: &str) -> Result<String> {
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            partition_letter.chars().next().unwrap_or('Y')
-        );
+        let letter = partition_letter.chars().next().unwrap_or('Y');
+        crate::core::partition_backup::backup_disks_for_letters(&[letter], "删除分区")?;
 
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("dp_delete.txt");
-        std::fs::write(&script_path, &script_content)?;
+        let outcome = DiskpartScript::new()
+            .select_volume(letter)
+            .delete_partition(true)
+            .run()?
+            .ensure_success("删除分区失败")?;
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        Ok(gbk_to_utf8(&output.stdout))
+        Ok(outcome.output)
     }
 
     /// 检查指定分区是否包含有效的 Windows 系统
@@ -539,6 +508,22 @@ impl DiskManager {
         false
     }
 
+    /// 检查指定盘符是否为可移动磁盘（U 盘等）
+    #[cfg(windows)]
+    pub fn is_removable_drive(letter: char) -> bool {
+        let path = format!("{}:\\", letter);
+        let wide_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+        unsafe {
+            let drive_type = GetDriveTypeW(PCWSTR(wide_path.as_ptr()));
+            drive_type == DRIVE_REMOVABLE
+        }
+    }
+
+    #[cfg(not(windows))]
+    pub fn is_removable_drive(_letter: char) -> bool {
+        false
+    }
+
     /// 获取指定分区的剩余空间（字节）
     #[cfg(windows)]
     pub fn get_free_space_bytes(partition: &str) -> Option<u64> {
@@ -601,91 +586,38 @@ impl DiskManager {
 
     /// 查询指定分区可缩小的最大空间（MB）
     pub fn query_shrink_max(letter: char) -> Result<u64> {
-        let script_content = format!(
-            "select volume {}\nshrink querymax",
-            letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_query_shrink.txt");
-        std::fs::write(&script_path, &script_content)?;
+        let script = DiskpartScript::new().select_volume(letter).shrink_querymax();
 
         // 首先尝试使用内置 diskpart，如果失败则使用系统 diskpart
         let diskpart_path = get_diskpart_path();
-        let output = create_command(&diskpart_path)
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
+        let mut outcome = script.run_with(&diskpart_path)?;
 
-        let output_text = gbk_to_utf8(&output.stdout);
-        let error_text = gbk_to_utf8(&output.stderr);
-        
         println!("[DISK] Shrink querymax 使用: {}", diskpart_path);
-        println!("[DISK] Shrink querymax stdout 长度: {} 字节", output.stdout.len());
-        println!("[DISK] Shrink querymax 输出: {}", output_text);
-        if !error_text.is_empty() {
-            println!("[DISK] Shrink querymax 错误: {}", error_text);
-        }
+        println!("[DISK] Shrink querymax 输出: {}", outcome.output);
 
         // 如果输出为空且使用的是内置 diskpart，尝试使用系统 diskpart
-        let output_text = if output_text.trim().is_empty() || output.stdout.len() < 50 {
+        if outcome.output.trim().is_empty() || outcome.output.len() < 50 {
             println!("[DISK] 内置 diskpart 输出异常，尝试使用系统 diskpart");
-            
-            let sys_output = create_command("diskpart.exe")
-                .args(["/s", script_path.to_str().unwrap()])
-                .output()?;
-            
-            let sys_output_text = gbk_to_utf8(&sys_output.stdout);
-            println!("[DISK] 系统 diskpart stdout 长度: {} 字节", sys_output.stdout.len());
-            println!("[DISK] 系统 diskpart 输出: {}", sys_output_text);
-            
-            sys_output_text
-        } else {
-            output_text
-        };
-        
-        let _ = std::fs::remove_file(&script_path);
+            outcome = script.run_with("diskpart.exe")?;
+            println!("[DISK] 系统 diskpart 输出: {}", outcome.output);
+        }
 
-        // 解析输出，查找可回收的最大空间
-        // 英文: "The maximum number of reclaimable bytes is: XXX MB"
-        // 中文: "可回收的最大字节数为:  XXX MB" 或 "最多可从此卷收回 XXX MB"
-        
-        // 尝试多种模式匹配
-        let max_mb = Self::parse_shrink_max_output(&output_text)
-            .or_else(|| Self::parse_shrink_max_output_cn(&output_text))
-            .or_else(|| Self::parse_shrink_max_generic(&output_text))
+        if !outcome.is_success() {
+            println!("[DISK] Shrink querymax 失败: {}", outcome.error_message());
+            return Ok(0);
+        }
+
+        // 按 diskpart 消息资源解析，失败时再尝试其他措辞
+        let max_mb = outcome
+            .shrink_querymax_mb()
+            .or_else(|| Self::parse_shrink_max_output_cn(&outcome.output))
+            .or_else(|| Self::parse_shrink_max_generic(&outcome.output))
             .unwrap_or(0);
 
         println!("[DISK] 分区 {}: 可缩小的最大空间: {} MB", letter, max_mb);
         Ok(max_mb)
     }
 
-    /// 解析 shrink querymax 输出（英文）
-    fn parse_shrink_max_output(output: &str) -> Option<u64> {
-        // 匹配 "XXX MB" 或 "XXX GB" 格式
-        for line in output.lines() {
-            let line_lower = line.to_lowercase();
-            if line_lower.contains("reclaimable") || line_lower.contains("maximum") {
-                // 提取数字
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if let Ok(num) = part.replace(",", "").parse::<u64>() {
-                        // 检查单位
-                        if i + 1 < parts.len() {
-                            let unit = parts[i + 1].to_lowercase();
-                            if unit.starts_with("gb") {
-                                return Some(num * 1024);
-                            } else if unit.starts_with("mb") {
-                                return Some(num);
-                            }
-                        }
-                        return Some(num); // 默认 MB
-                    }
-                }
-            }
-        }
-        None
-    }
-
     /// 解析 shrink querymax 输出（中文）
     fn parse_shrink_max_output_cn(output: &str) -> Option<u64> {
         for line in output.lines() {
@@ -838,46 +770,23 @@ impl DiskManager {
 
         // 使用 diskpart 执行操作
         // 注意：shrink 之后的未分配空间会紧跟在当前卷之后
-        let script_content = format!(
-            "select volume {}\n\
-            shrink desired={}\n\
-            create partition primary\n\
-            format fs=ntfs quick label=\"LetRecovery\"\n\
-            assign letter={}",
-            source_letter,
-            actual_size_mb,
-            new_letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_shrink_script.txt");
-        std::fs::write(&script_path, &script_content)?;
-
-        println!("[DISK] Diskpart 脚本内容:\n{}", script_content);
-
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
+        let script = DiskpartScript::new()
+            .select_volume(source_letter)
+            .shrink(actual_size_mb, None)
+            .create_partition(CreatePartitionKind::Primary, None, None)
+            .format("ntfs", Some("LetRecovery"))
+            .assign(Some(new_letter));
 
-        let _ = std::fs::remove_file(&script_path);
+        println!("[DISK] Diskpart 脚本内容:\n{}", script.render());
 
-        let output_text = gbk_to_utf8(&output.stdout);
-        let error_text = gbk_to_utf8(&output.stderr);
+        let outcome = script.run()?;
+        println!("[DISK] Diskpart 输出: {}", outcome.output);
 
-        println!("[DISK] Diskpart 输出: {}", output_text);
-        if !error_text.is_empty() {
-            println!("[DISK] Diskpart 错误: {}", error_text);
-        }
-
-        // 检查输出是否包含错误信息
-        let output_lower = output_text.to_lowercase();
-        if output_lower.contains("error") || output_lower.contains("错误") 
-            || output_lower.contains("失败") || output_lower.contains("failed")
-            || output_lower.contains("无效") || output_lower.contains("invalid")
-            || output_lower.contains("不支持") || output_lower.contains("无法")
-            || output_lower.contains("拒绝") || output_lower.contains("denied") {
-            anyhow::bail!("Diskpart 执行失败: {}", output_text);
+        // 按命令逐条检查执行结果
+        if !outcome.is_success() {
+            anyhow::bail!("Diskpart 执行失败: {}", outcome.error_message());
         }
+        let output_text = outcome.output;
 
         // 等待系统识别新分区
         std::thread::sleep(std::time::Duration::from_secs(2));
@@ -937,24 +846,13 @@ impl DiskManager {
 
         println!("[DISK] 准备删除自动创建的分区 {}:", letter);
 
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            letter
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("lr_delete_script.txt");
-        std::fs::write(&script_path, &script_content)?;
-
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().unwrap()])
-            .output()?;
-
-        let _ = std::fs::remove_file(&script_path);
-
-        let output_text = gbk_to_utf8(&output.stdout);
-        println!("[DISK] Diskpart 删除输出: {}", output_text);
+        let outcome = DiskpartScript::new()
+            .select_volume(letter)
+            .delete_partition(true)
+            .run()?;
+        println!("[DISK] Diskpart 删除输出: {}", outcome.output);
 
+        outcome.ensure_success("删除自动创建的分区失败")?;
         Ok(())
     }
 
@@ -972,6 +870,25 @@ impl DiskManager {
         exclude_partition: &str,
         required_size_bytes: u64,
     ) -> Result<Option<(String, bool)>> {
+        match Self::choose_data_partition(exclude_partition, required_size_bytes)? {
+            Some(DataPartitionChoice::Existing(partition)) => Ok(Some((partition, false))),
+            Some(DataPartitionChoice::Shrink { from, size_mb, max_shrink_mb }) => {
+                // 创建新分区（传入预查询的 max_shrink_mb，避免重复查询）
+                let new_letter = Self::shrink_and_create_partition_with_marker(from, size_mb, Some(max_shrink_mb))?;
+                Ok(Some((format!("{}:", new_letter), true)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 选择数据分区，只查询不改动磁盘（演练时直接使用）
+    ///
+    /// 参数和返回值含义同 [`DiskManager::find_suitable_data_partition`]，
+    /// 需要分割新分区时返回分割的大小而不实际创建。
+    pub fn choose_data_partition(
+        exclude_partition: &str,
+        required_size_bytes: u64,
+    ) -> Result<Option<DataPartitionChoice>> {
         let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
         
         println!("[DISK] 查找数据分区，排除: {}, 需要空间: {} bytes ({:.2} GB)", 
@@ -1039,7 +956,7 @@ impl DiskManager {
 
             let selected = candidates[0].0;
             println!("[DISK] 选择数据分区: {}:", selected);
-            return Ok(Some((format!("{}:", selected), false)));
+            return Ok(Some(DataPartitionChoice::Existing(format!("{}:", selected))));
         }
 
         // ========================================================================
@@ -1146,9 +1063,10 @@ impl DiskManager {
             ));
         }
 
-        // 创建新分区（传入预查询的 max_shrink_mb，避免重复查询）
-        let new_letter = Self::shrink_and_create_partition_with_marker(exclude_letter, actual_size_mb, Some(max_shrink_mb))?;
-        
-        Ok(Some((format!("{}:", new_letter), true)))
+        Ok(Some(DataPartitionChoice::Shrink {
+            from: exclude_letter,
+            size_mb: actual_size_mb,
+            max_shrink_mb,
+        }))
     }
 }
@@ -4,6 +4,7 @@
 //! - 离线驱动导入（Add-Driver）
 //! - 离线 CAB 包导入（Add-Package）
 //! - 驱动导出
+//! - WIM 分割（Split-Image）
 //!
 //! 优先使用程序目录下的 `bin\Dism\dism.exe`，
 //! 如果不存在则回退到系统 DISM。
@@ -473,6 +474,60 @@ impl DismCmd {
         self.execute_with_progress_args(&args, progress_tx, "驱动导出")
     }
 
+    // ========================================================================
+    // 镜像分割
+    // ========================================================================
+
+    /// 把 WIM 分割为多个 SWM 文件
+    ///
+    /// 等效于: `dism /Split-Image /ImageFile:<image_file> /SWMFile:<swm_file> /FileSize:<size_mb>`
+    ///
+    /// 第一个分卷为 `swm_file`，后续依次为 `install2.swm`、`install3.swm`……
+    /// 返回全部分卷路径（按顺序）
+    pub fn split_image(
+        &self,
+        image_file: &str,
+        swm_file: &str,
+        file_size_mb: u64,
+        progress_tx: Option<Sender<DismCmdProgress>>,
+    ) -> Result<Vec<PathBuf>> {
+        if !Path::new(image_file).exists() {
+            bail!("镜像文件不存在: {}", image_file);
+        }
+        let swm_path = Path::new(swm_file);
+        if let Some(parent) = swm_path.parent() {
+            std::fs::create_dir_all(parent).context("创建分卷目录失败")?;
+        }
+
+        log::info!("[DismCmd] 分割镜像: {} -> {} ({} MB)", image_file, swm_file, file_size_mb);
+        Self::send_progress(&progress_tx, 0, "正在分割镜像...");
+
+        let scratch_dir = Self::ensure_scratch_directory();
+        let args = [
+            &format!("/ImageFile:{}", image_file),
+            "/Split-Image",
+            &format!("/SWMFile:{}", swm_file),
+            &format!("/FileSize:{}", file_size_mb),
+            &format!("/scratchdir:{}", scratch_dir),
+        ];
+        self.execute_with_progress_args(&args, progress_tx, "镜像分割")?;
+
+        let stem = swm_path.file_stem().and_then(|s| s.to_str()).unwrap_or("install");
+        let ext = swm_path.extension().and_then(|s| s.to_str()).unwrap_or("swm");
+        let mut parts = vec![swm_path.to_path_buf()];
+        for index in 2.. {
+            let part = swm_path.with_file_name(format!("{}{}.{}", stem, index, ext));
+            if !part.exists() {
+                break;
+            }
+            parts.push(part);
+        }
+        if !parts[0].exists() {
+            bail!("镜像分割完成但未找到分卷文件: {}", swm_file);
+        }
+        Ok(parts)
+    }
+
     // ========================================================================
     // 综合驱动和 CAB 导入
     // ========================================================================
@@ -1740,7 +1740,7 @@ fn is_known_usb_ssd(model: &str) -> bool {
 
 /// 综合检测磁盘类型
 /// 返回 true 表示是 SSD，false 表示是 HDD
-fn detect_disk_is_ssd(disk_index: u32, model: &str, interface: &str) -> bool {
+pub fn detect_disk_is_ssd(disk_index: u32, model: &str, interface: &str) -> bool {
     // 获取 WMI 信息（如果可用）
     static WMI_DISK_INFO: std::sync::OnceLock<HashMap<u32, WmiDiskMediaInfo>> = std::sync::OnceLock::new();
     let wmi_info = WMI_DISK_INFO.get_or_init(get_wmi_disk_media_info);
@@ -1,119 +1,23 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::path::Path;
 
-/// 系统安装配置（用于PE环境内安装）
-#[derive(Debug, Clone, Default)]
-pub struct InstallConfig {
-    /// 无人值守安装
-    pub unattended: bool,
-    /// 驱动还原（兼容旧版本）
-    pub restore_drivers: bool,
-    /// 驱动操作模式: 0=无, 1=仅保存, 2=自动导入
-    pub driver_action_mode: u8,
-    /// 立即重启
-    pub auto_reboot: bool,
-    /// 原系统引导GUID（用于删除旧引导项）
-    pub original_guid: String,
-    /// 安装分卷索引
-    pub volume_index: u32,
-    /// 目标分区盘符
-    pub target_partition: String,
-    /// 镜像文件路径（相对于数据分区）
-    pub image_path: String,
-    /// 是否为GHO格式
-    pub is_gho: bool,
-    
-    // 高级选项
-    /// 移除快捷方式小箭头
-    pub remove_shortcut_arrow: bool,
-    /// Win11恢复经典右键
-    pub restore_classic_context_menu: bool,
-    /// OOBE绕过强制联网
-    pub bypass_nro: bool,
-    /// 禁用Windows更新
-    pub disable_windows_update: bool,
-    /// 禁用Windows安全中心
-    pub disable_windows_defender: bool,
-    /// 禁用系统保留空间
-    pub disable_reserved_storage: bool,
-    /// 禁用用户账户控制
-    pub disable_uac: bool,
-    /// 禁用自动设备加密
-    pub disable_device_encryption: bool,
-    /// 删除预装UWP应用
-    pub remove_uwp_apps: bool,
-    /// 导入磁盘控制器驱动
-    pub import_storage_controller_drivers: bool,
-    /// 自定义用户名
-    pub custom_username: String,
-    /// 自定义系统盘卷标
-    pub volume_label: String,
-    
-    // Win7 专用选项
-    /// Win7 UEFI 补丁（使用 UefiSeven）
-    pub win7_uefi_patch: bool,
-    /// Win7 注入USB3驱动
-    pub win7_inject_usb3_driver: bool,
-    /// Win7 注入NVMe驱动
-    pub win7_inject_nvme_driver: bool,
-    /// Win7 修复ACPI蓝屏
-    pub win7_fix_acpi_bsod: bool,
-    /// Win7 修复存储控制器蓝屏
-    pub win7_fix_storage_bsod: bool,
-}
+use letrecovery_config::HandoffConfig;
 
-impl InstallConfig {
-    /// 根据DriverAction获取driver_action_mode值
-    pub fn driver_action_to_mode(action: crate::app::DriverAction) -> u8 {
+use crate::core::volume_id::{self, VolumeIdentity};
+
+// 配置类型与序列化由正常系统端和PE端共用的 letrecovery-config 提供
+pub use letrecovery_config::{BackupConfig, DriverActionMode, InstallConfig};
+
+impl From<crate::app::DriverAction> for DriverActionMode {
+    fn from(action: crate::app::DriverAction) -> Self {
         match action {
-            crate::app::DriverAction::None => 0,
-            crate::app::DriverAction::SaveOnly => 1,
-            crate::app::DriverAction::AutoImport => 2,
-        }
-    }
-    
-    /// 从driver_action_mode获取DriverAction
-    pub fn mode_to_driver_action(mode: u8) -> crate::app::DriverAction {
-        match mode {
-            0 => crate::app::DriverAction::None,
-            1 => crate::app::DriverAction::SaveOnly,
-            2 => crate::app::DriverAction::AutoImport,
-            // 兼容旧版本：如果restore_drivers为true则默认AutoImport
-            _ => crate::app::DriverAction::AutoImport,
-        }
-    }
-    
-    /// 判断是否需要导入驱动
-    pub fn should_import_drivers(&self) -> bool {
-        // 优先使用新的driver_action_mode
-        if self.driver_action_mode > 0 {
-            self.driver_action_mode == 2 // AutoImport
-        } else {
-            // 兼容旧版本
-            self.restore_drivers
+            crate::app::DriverAction::None => DriverActionMode::None,
+            crate::app::DriverAction::SaveOnly => DriverActionMode::SaveOnly,
+            crate::app::DriverAction::AutoImport => DriverActionMode::AutoImport,
         }
     }
 }
 
-/// 系统备份配置（用于PE环境内备份）
-#[derive(Debug, Clone, Default)]
-pub struct BackupConfig {
-    /// 备份保存路径（相对路径）
-    pub save_path: String,
-    /// 备份名称
-    pub name: String,
-    /// 备份描述
-    pub description: String,
-    /// 源分区盘符
-    pub source_partition: String,
-    /// 是否增量备份
-    pub incremental: bool,
-    /// 备份格式: 0=WIM, 1=ESD, 2=SWM, 3=GHO
-    pub format: u8,
-    /// SWM分卷大小（MB）
-    pub swm_split_size: u32,
-}
-
 /// 配置文件管理器
 pub struct ConfigFileManager;
 
@@ -123,8 +27,8 @@ impl ConfigFileManager {
     const BACKUP_MARKER: &'static str = "LetRecovery_Backup.marker";
     
     /// 配置文件名
-    const INSTALL_CONFIG: &'static str = "LetRecovery_Install.ini";
-    const BACKUP_CONFIG: &'static str = "LetRecovery_Backup.ini";
+    const INSTALL_CONFIG: &'static str = letrecovery_config::INSTALL_CONFIG_FILE;
+    const BACKUP_CONFIG: &'static str = letrecovery_config::BACKUP_CONFIG_FILE;
     
     /// PE文件目录名
     const PE_DIR: &'static str = "LetRecovery_PE";
@@ -137,19 +41,17 @@ impl ConfigFileManager {
 
     /// 查找包含安装标记文件的分区
     pub fn find_install_marker_partition() -> Option<String> {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
-            let marker_path = format!("{}:\\{}", letter, Self::INSTALL_MARKER);
-            if Path::new(&marker_path).exists() {
-                return Some(format!("{}:", letter));
-            }
-        }
-        None
+        Self::find_marker_partition(Self::INSTALL_MARKER)
     }
 
     /// 查找包含备份标记文件的分区
     pub fn find_backup_marker_partition() -> Option<String> {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
-            let marker_path = format!("{}:\\{}", letter, Self::BACKUP_MARKER);
+        Self::find_marker_partition(Self::BACKUP_MARKER)
+    }
+
+    fn find_marker_partition(marker: &str) -> Option<String> {
+        for letter in 'C'..='Z' {
+            let marker_path = format!("{}:\\{}", letter, marker);
             if Path::new(&marker_path).exists() {
                 return Some(format!("{}:", letter));
             }
@@ -158,18 +60,86 @@ impl ConfigFileManager {
     }
 
     /// 查找包含配置文件的数据分区
+    ///
+    /// 扫描所有盘符和没有盘符的卷。有多个分区存在配置时（如上次失败残留），
+    /// 优先选择配置中记录的数据卷标识与所在卷一致的分区。
     pub fn find_data_partition() -> Option<String> {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
-            let config_path = format!("{}:\\{}\\{}", letter, Self::DATA_DIR, Self::INSTALL_CONFIG);
-            if Path::new(&config_path).exists() {
-                return Some(format!("{}:", letter));
+        let volumes = volume_id::list_volumes();
+        let mut found: Vec<String> = ('C'..='Z')
+            .map(|letter| format!("{}:", letter))
+            .chain(volumes.iter().filter(|v| v.letter.is_none()).map(|v| v.path()))
+            .filter(|partition| Self::has_config(partition))
+            .collect();
+        if found.is_empty() {
+            return None;
+        }
+
+        let index = found
+            .iter()
+            .position(|partition| {
+                let recorded = Self::read_install_config(partition)
+                    .map(|c| c.data_volume_id)
+                    .or_else(|_| Self::read_backup_config(partition).map(|c| c.data_volume_id))
+                    .unwrap_or_default();
+                VolumeIdentity::parse(&recorded)
+                    .ok()
+                    .and_then(|identity| volume_id::find_volume(&identity, &volumes))
+                    .is_some_and(|v| v.path() == *partition)
+            })
+            .unwrap_or(0);
+        let partition = found.swap_remove(index);
+        println!("[CONFIG] 找到配置分区: {}", partition);
+
+        // 没有盘符的卷分配盘符，便于后续工具访问
+        match volumes.iter().find(|v| v.letter.is_none() && v.path() == partition) {
+            Some(volume) => Some(volume_id::mount_path(volume)),
+            None => Some(partition),
+        }
+    }
+
+    /// 解析安装目标分区：优先按卷标识查找，其次查找标记文件，最后使用配置中的盘符
+    pub fn resolve_install_target(config: &InstallConfig) -> String {
+        Self::resolve_volume(&config.target_volume_id)
+            .or_else(Self::find_install_marker_partition)
+            .unwrap_or_else(|| config.target_partition.clone())
+    }
+
+    /// 解析备份源分区：优先按卷标识查找，其次查找标记文件，最后使用配置中的盘符
+    pub fn resolve_backup_source(config: &BackupConfig) -> String {
+        Self::resolve_volume(&config.source_volume_id)
+            .or_else(Self::find_backup_marker_partition)
+            .unwrap_or_else(|| config.source_partition.clone())
+    }
+
+    fn resolve_volume(recorded: &str) -> Option<String> {
+        if recorded.is_empty() {
+            return None;
+        }
+        let identity = match VolumeIdentity::parse(recorded) {
+            Ok(identity) => identity,
+            Err(e) => {
+                println!("[CONFIG] 卷标识无效 ({}): {}", recorded, e);
+                return None;
             }
-            let backup_config_path = format!("{}:\\{}\\{}", letter, Self::DATA_DIR, Self::BACKUP_CONFIG);
-            if Path::new(&backup_config_path).exists() {
-                return Some(format!("{}:", letter));
+        };
+        let resolved = volume_id::resolve(&identity);
+        match &resolved {
+            Some(partition) => println!("[CONFIG] 按卷标识 {} 找到分区: {}", recorded, partition),
+            None => println!("[CONFIG] 未找到卷标识为 {} 的分区，改用标记文件", recorded),
+        }
+        resolved
+    }
+
+    /// 获取分区的卷标识字符串，失败时返回空字符串（PE 中回退到标记文件）
+    fn volume_id_of(partition: &str) -> String {
+        let letter = partition.chars().next().unwrap_or('C');
+        match volume_id::identify_letter(letter) {
+            Ok(identity) => identity.to_string(),
+            Err(e) => {
+                println!("[CONFIG] 获取 {} 的卷标识失败: {}", partition, e);
+                String::new()
             }
         }
-        None
     }
 
     /// 写入安装配置
@@ -177,7 +147,12 @@ impl ConfigFileManager {
         target_partition: &str,
         data_partition: &str,
         config: &InstallConfig,
+        nonce: &str,
     ) -> Result<()> {
+        // PE 格式化前据此核对目标分区
+        let target_snapshot = volume_id::partition_snapshot(target_partition)
+            .context("获取目标分区信息失败")?;
+
         // 创建数据目录
         let data_dir = format!("{}\\{}", data_partition, Self::DATA_DIR);
         std::fs::create_dir_all(&data_dir)
@@ -188,14 +163,19 @@ impl ConfigFileManager {
         std::fs::write(&marker_path, "LetRecovery Install Marker")
             .context("写入安装标记文件失败")?;
 
-        // 写入配置文件
+        // 写入配置文件，记录目标分区和数据分区的卷标识，并用引导校验码签名
+        let mut config = config.clone();
+        config.target_volume_id = Self::volume_id_of(target_partition);
+        config.target_snapshot = target_snapshot;
+        config.data_volume_id = Self::volume_id_of(data_partition);
         let config_path = format!("{}\\{}", data_dir, Self::INSTALL_CONFIG);
-        let content = Self::serialize_install_config(config);
+        let content = letrecovery_config::to_json(&config, letrecovery_config::unix_now(), nonce);
         std::fs::write(&config_path, &content)
             .context("写入安装配置文件失败")?;
 
         println!("[CONFIG] 安装配置已写入: {}", config_path);
         println!("[CONFIG] 安装标记已写入: {}", marker_path);
+        println!("[CONFIG] 目标卷标识: {}", config.target_volume_id);
 
         Ok(())
     }
@@ -205,6 +185,7 @@ impl ConfigFileManager {
         source_partition: &str,
         data_partition: &str,
         config: &BackupConfig,
+        nonce: &str,
     ) -> Result<()> {
         // 创建数据目录
         let data_dir = format!("{}\\{}", data_partition, Self::DATA_DIR);
@@ -216,37 +197,103 @@ impl ConfigFileManager {
         std::fs::write(&marker_path, "LetRecovery Backup Marker")
             .context("写入备份标记文件失败")?;
 
-        // 写入配置文件
+        // 写入配置文件，记录源分区和数据分区的卷标识，并用引导校验码签名
+        let mut config = config.clone();
+        config.source_volume_id = Self::volume_id_of(source_partition);
+        config.data_volume_id = Self::volume_id_of(data_partition);
         let config_path = format!("{}\\{}", data_dir, Self::BACKUP_CONFIG);
-        let content = Self::serialize_backup_config(config);
+        let content = letrecovery_config::to_json(&config, letrecovery_config::unix_now(), nonce);
         std::fs::write(&config_path, &content)
             .context("写入备份配置文件失败")?;
 
         println!("[CONFIG] 备份配置已写入: {}", config_path);
         println!("[CONFIG] 备份标记已写入: {}", marker_path);
+        println!("[CONFIG] 源卷标识: {}", config.source_volume_id);
 
         Ok(())
     }
 
     /// 读取安装配置
     pub fn read_install_config(data_partition: &str) -> Result<InstallConfig> {
-        let config_path = format!("{}\\{}\\{}", data_partition, Self::DATA_DIR, Self::INSTALL_CONFIG);
-        let content = std::fs::read_to_string(&config_path)
-            .context("读取安装配置文件失败")?;
-        Self::deserialize_install_config(&content)
+        Self::read_config(data_partition, Self::INSTALL_CONFIG, letrecovery_config::LEGACY_INSTALL_CONFIG_FILE)
     }
 
     /// 读取备份配置
     pub fn read_backup_config(data_partition: &str) -> Result<BackupConfig> {
-        let config_path = format!("{}\\{}\\{}", data_partition, Self::DATA_DIR, Self::BACKUP_CONFIG);
-        let content = std::fs::read_to_string(&config_path)
-            .context("读取备份配置文件失败")?;
-        Self::deserialize_backup_config(&content)
+        Self::read_config(data_partition, Self::BACKUP_CONFIG, letrecovery_config::LEGACY_BACKUP_CONFIG_FILE)
+    }
+
+    /// 读取配置并校验签名、有效期
+    ///
+    /// 旧版 INI 配置没有签名，不再读取，提示重新发起操作。
+    fn read_config<T: HandoffConfig>(data_partition: &str, file: &str, legacy_file: &str) -> Result<T> {
+        let data_dir = Self::get_data_dir(data_partition);
+        let config_path = format!("{}\\{}", data_dir, file);
+        let content = match std::fs::read_to_string(&config_path) {
+            Ok(content) => content,
+            Err(e) => {
+                let legacy_path = format!("{}\\{}", data_dir, legacy_file);
+                if Path::new(&legacy_path).exists() {
+                    bail!("检测到旧版配置 {}，其中没有防篡改校验信息，请在正常系统中重新发起操作", legacy_path);
+                }
+                return Err(e).with_context(|| format!("读取配置文件失败: {}", config_path));
+            }
+        };
+
+        let nonce = Self::boot_nonce();
+        letrecovery_config::from_json(&content, nonce.as_deref(), letrecovery_config::unix_now())
+            .with_context(|| format!("配置文件校验失败: {}", config_path))
+    }
+
+    /// 当前启动项引导参数中的一次性校验码（由 PeManager 写入PE引导项）
+    #[cfg(windows)]
+    fn boot_nonce() -> Option<String> {
+        use winreg::enums::HKEY_LOCAL_MACHINE;
+        use winreg::RegKey;
+
+        let key = RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_subkey("SYSTEM\\CurrentControlSet\\Control")
+            .ok()?;
+        let options: String = key.get_value("SystemStartOptions").ok()?;
+        letrecovery_config::nonce_from_start_options(&options)
+    }
+
+    #[cfg(not(windows))]
+    fn boot_nonce() -> Option<String> {
+        None
+    }
+
+    /// 核对目标分区与发起安装时是否一致（大小、序列号、卷标），不一致时拒绝格式化
+    pub fn check_install_target(config: &InstallConfig, target_partition: &str) -> Result<()> {
+        let actual = volume_id::partition_snapshot(target_partition)
+            .with_context(|| format!("无法读取目标分区 {} 的信息，拒绝格式化", target_partition))?;
+        let differences = config.target_snapshot.differences(&actual);
+        if !differences.is_empty() {
+            bail!(
+                "目标分区 {} 与发起安装时不一致（{}），拒绝格式化",
+                target_partition,
+                differences.join("；")
+            );
+        }
+        Ok(())
+    }
+
+    /// 分区上是否存在安装或备份配置（包括旧版 INI 配置，读取时给出提示）
+    fn has_config(partition: &str) -> bool {
+        let data_dir = Self::get_data_dir(partition);
+        [
+            Self::INSTALL_CONFIG,
+            Self::BACKUP_CONFIG,
+            letrecovery_config::LEGACY_INSTALL_CONFIG_FILE,
+            letrecovery_config::LEGACY_BACKUP_CONFIG_FILE,
+        ]
+        .iter()
+        .any(|file| Path::new(&format!("{}\\{}", data_dir, file)).exists())
     }
 
     /// 清理所有分区上的标记和配置文件
     pub fn cleanup_all_markers() {
-        for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
+        for letter in 'C'..='Z' {
             let _ = std::fs::remove_file(format!("{}:\\{}", letter, Self::INSTALL_MARKER));
             let _ = std::fs::remove_file(format!("{}:\\{}", letter, Self::BACKUP_MARKER));
             let _ = std::fs::remove_dir_all(format!("{}:\\{}", letter, Self::DATA_DIR));
@@ -292,6 +339,24 @@ impl ConfigFileManager {
         Path::new(&marker_path).exists()
     }
 
+    /// 读取并删除 PE 回滚后留在系统盘根目录的失败报告
+    pub fn take_failure_report() -> Option<letrecovery_config::FailureReport> {
+        let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+        let path = format!("{}\\{}", system_drive, letrecovery_config::FAILURE_REPORT_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        let _ = std::fs::remove_file(&path);
+        match letrecovery_config::FailureReport::from_json(&content) {
+            Ok(report) => {
+                println!("[CONFIG] 读取失败报告: {}", path);
+                Some(report)
+            }
+            Err(e) => {
+                println!("[CONFIG] 失败报告无法解析 ({}): {}", path, e);
+                None
+            }
+        }
+    }
+
     /// 获取数据目录路径
     pub fn get_data_dir(partition: &str) -> String {
         format!("{}\\{}", partition, Self::DATA_DIR)
@@ -301,169 +366,4 @@ impl ConfigFileManager {
     pub fn get_pe_dir(partition: &str) -> String {
         format!("{}\\{}", partition, Self::PE_DIR)
     }
-
-    /// 序列化安装配置为INI格式
-    fn serialize_install_config(config: &InstallConfig) -> String {
-        format!(
-            r#"[Install]
-Unattended={}
-RestoreDrivers={}
-DriverActionMode={}
-AutoReboot={}
-OriginalGUID={}
-VolumeIndex={}
-TargetPartition={}
-ImagePath={}
-IsGho={}
-
-[Advanced]
-RemoveShortcutArrow={}
-RestoreClassicContextMenu={}
-BypassNRO={}
-DisableWindowsUpdate={}
-DisableWindowsDefender={}
-DisableReservedStorage={}
-DisableUAC={}
-DisableDeviceEncryption={}
-RemoveUWPApps={}
-ImportStorageControllerDrivers={}
-CustomUsername={}
-VolumeLabel={}
-
-[Win7]
-Win7UefiPatch={}
-Win7InjectUsb3Driver={}
-Win7InjectNvmeDriver={}
-Win7FixAcpiBsod={}
-Win7FixStorageBsod={}
-"#,
-            config.unattended,
-            config.restore_drivers,
-            config.driver_action_mode,
-            config.auto_reboot,
-            config.original_guid,
-            config.volume_index,
-            config.target_partition,
-            config.image_path,
-            config.is_gho,
-            config.remove_shortcut_arrow,
-            config.restore_classic_context_menu,
-            config.bypass_nro,
-            config.disable_windows_update,
-            config.disable_windows_defender,
-            config.disable_reserved_storage,
-            config.disable_uac,
-            config.disable_device_encryption,
-            config.remove_uwp_apps,
-            config.import_storage_controller_drivers,
-            config.custom_username,
-            config.volume_label,
-            config.win7_uefi_patch,
-            config.win7_inject_usb3_driver,
-            config.win7_inject_nvme_driver,
-            config.win7_fix_acpi_bsod,
-            config.win7_fix_storage_bsod,
-        )
-    }
-
-    /// 序列化备份配置为INI格式
-    fn serialize_backup_config(config: &BackupConfig) -> String {
-        format!(
-            r#"[Backup]
-SavePath={}
-Name={}
-Description={}
-SourcePartition={}
-Incremental={}
-Format={}
-SwmSplitSize={}
-"#,
-            config.save_path,
-            config.name,
-            config.description,
-            config.source_partition,
-            config.incremental,
-            config.format,
-            config.swm_split_size,
-        )
-    }
-
-    /// 反序列化安装配置
-    fn deserialize_install_config(content: &str) -> Result<InstallConfig> {
-        let mut config = InstallConfig::default();
-        
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('[') || line.starts_with('#') {
-                continue;
-            }
-            
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim();
-                
-                match key {
-                    "Unattended" => config.unattended = value.parse().unwrap_or(false),
-                    "RestoreDrivers" => config.restore_drivers = value.parse().unwrap_or(false),
-                    "DriverActionMode" => config.driver_action_mode = value.parse().unwrap_or(0),
-                    "AutoReboot" => config.auto_reboot = value.parse().unwrap_or(false),
-                    "OriginalGUID" => config.original_guid = value.to_string(),
-                    "VolumeIndex" => config.volume_index = value.parse().unwrap_or(1),
-                    "TargetPartition" => config.target_partition = value.to_string(),
-                    "ImagePath" => config.image_path = value.to_string(),
-                    "IsGho" => config.is_gho = value.parse().unwrap_or(false),
-                    "RemoveShortcutArrow" => config.remove_shortcut_arrow = value.parse().unwrap_or(false),
-                    "RestoreClassicContextMenu" => config.restore_classic_context_menu = value.parse().unwrap_or(false),
-                    "BypassNRO" => config.bypass_nro = value.parse().unwrap_or(false),
-                    "DisableWindowsUpdate" => config.disable_windows_update = value.parse().unwrap_or(false),
-                    "DisableWindowsDefender" => config.disable_windows_defender = value.parse().unwrap_or(false),
-                    "DisableReservedStorage" => config.disable_reserved_storage = value.parse().unwrap_or(false),
-                    "DisableUAC" => config.disable_uac = value.parse().unwrap_or(false),
-                    "DisableDeviceEncryption" => config.disable_device_encryption = value.parse().unwrap_or(false),
-                    "RemoveUWPApps" => config.remove_uwp_apps = value.parse().unwrap_or(false),
-                    "ImportStorageControllerDrivers" => config.import_storage_controller_drivers = value.parse().unwrap_or(false),
-                    "CustomUsername" => config.custom_username = value.to_string(),
-                    "VolumeLabel" => config.volume_label = value.to_string(),
-                    "Win7UefiPatch" => config.win7_uefi_patch = value.parse().unwrap_or(false),
-                    "Win7InjectUsb3Driver" => config.win7_inject_usb3_driver = value.parse().unwrap_or(false),
-                    "Win7InjectNvmeDriver" => config.win7_inject_nvme_driver = value.parse().unwrap_or(false),
-                    "Win7FixAcpiBsod" => config.win7_fix_acpi_bsod = value.parse().unwrap_or(false),
-                    "Win7FixStorageBsod" => config.win7_fix_storage_bsod = value.parse().unwrap_or(false),
-                    _ => {}
-                }
-            }
-        }
-        
-        Ok(config)
-    }
-
-    /// 反序列化备份配置
-    fn deserialize_backup_config(content: &str) -> Result<BackupConfig> {
-        let mut config = BackupConfig::default();
-        
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('[') || line.starts_with('#') {
-                continue;
-            }
-            
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim();
-                
-                match key {
-                    "SavePath" => config.save_path = value.to_string(),
-                    "Name" => config.name = value.to_string(),
-                    "Description" => config.description = value.to_string(),
-                    "SourcePartition" => config.source_partition = value.to_string(),
-                    "Incremental" => config.incremental = value.parse().unwrap_or(false),
-                    "Format" => config.format = value.parse().unwrap_or(0),
-                    "SwmSplitSize" => config.swm_split_size = value.parse().unwrap_or(4096),
-                    _ => {}
-                }
-            }
-        }
-        
-        Ok(config)
-    }
 }
@@ -0,0 +1,247 @@
+//! 安装配置方案
+//!
+//! 把安装选项和高级选项（驱动处理、无人值守、脚本、自定义文件路径等）保存为命名方案，
+//! 每个方案一个 JSON 文件，存放在程序目录的 profiles 文件夹中，可导入导出以便共享。
+//! 默认方案和小白模式使用的方案记录在 config.json 中。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::app::{BootModeSelection, DriverAction};
+use crate::ui::advanced_options::AdvancedOptions;
+use crate::utils::path::get_exe_dir;
+
+/// 方案文件扩展名
+const PROFILE_EXTENSION: &str = "json";
+
+/// 安装配置方案
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallProfile {
+    pub name: String,
+    pub format_partition: bool,
+    pub repair_boot: bool,
+    pub unattended_install: bool,
+    pub driver_action: DriverAction,
+    pub auto_reboot: bool,
+    pub boot_mode: BootModeSelection,
+    pub boot_side_by_side: bool,
+    pub boot_entry_description: String,
+    pub advanced_options: AdvancedOptions,
+}
+
+impl Default for InstallProfile {
+    /// 与界面初始状态一致
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            format_partition: true,
+            repair_boot: true,
+            unattended_install: true,
+            driver_action: DriverAction::AutoImport,
+            auto_reboot: false,
+            boot_mode: BootModeSelection::Auto,
+            boot_side_by_side: false,
+            boot_entry_description: String::new(),
+            advanced_options: AdvancedOptions::default(),
+        }
+    }
+}
+
+impl InstallProfile {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        serde_json::from_str(content).context("配置方案格式不正确")
+    }
+}
+
+/// 方案名对应的文件名（去掉 Windows 文件名中不允许的字符）
+pub fn profile_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let stem = stem.trim_end_matches(['.', ' ']);
+    format!("{}.{}", stem, PROFILE_EXTENSION)
+}
+
+/// 方案存储目录
+pub struct ProfileStore {
+    dir: PathBuf,
+}
+
+impl ProfileStore {
+    /// 程序目录下的 profiles 文件夹
+    pub fn new() -> Self {
+        Self::with_dir(get_exe_dir().join("profiles"))
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        self.dir.join(profile_file_name(name))
+    }
+
+    /// 所有方案，按名称排序；无法解析的文件跳过
+    pub fn list(&self) -> Vec<InstallProfile> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut profiles: Vec<InstallProfile> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(PROFILE_EXTENSION))
+            })
+            .filter_map(|path| match Self::read_file(&path) {
+                Ok(profile) => Some(profile),
+                Err(e) => {
+                    log::warn!("跳过无法读取的配置方案 {}: {:#}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
+    pub fn load(&self, name: &str) -> Result<InstallProfile> {
+        Self::read_file(&self.path_for(name))
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path_for(name).exists()
+    }
+
+    /// 保存方案（同名方案会被覆盖）
+    pub fn save(&self, profile: &InstallProfile) -> Result<PathBuf> {
+        if profile.name.trim().is_empty() {
+            anyhow::bail!("配置方案名称不能为空");
+        }
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("创建目录失败: {}", self.dir.display()))?;
+
+        let mut profile = profile.clone();
+        profile.name = profile.name.trim().to_string();
+        let path = self.path_for(&profile.name);
+        std::fs::write(&path, profile.to_json()?)
+            .with_context(|| format!("写入配置方案失败: {}", path.display()))?;
+        log::info!("配置方案已保存: {}", path.display());
+        Ok(path)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path_for(name);
+        std::fs::remove_file(&path).with_context(|| format!("删除配置方案失败: {}", path.display()))?;
+        log::info!("配置方案已删除: {}", path.display());
+        Ok(())
+    }
+
+    /// 从外部文件导入方案并保存到存储目录；文件中没有名称时使用文件名
+    pub fn import(&self, file: &Path) -> Result<InstallProfile> {
+        let mut profile = Self::read_file(file)?;
+        if profile.name.trim().is_empty() {
+            profile.name = file
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "导入的方案".to_string());
+        }
+        self.save(&profile)?;
+        Ok(profile)
+    }
+
+    /// 导出方案到外部文件
+    pub fn export(&self, name: &str, file: &Path) -> Result<()> {
+        let profile = self.load(name)?;
+        std::fs::write(file, profile.to_json()?)
+            .with_context(|| format!("导出配置方案失败: {}", file.display()))?;
+        Ok(())
+    }
+
+    fn read_file(path: &Path) -> Result<InstallProfile> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取配置方案失败: {}", path.display()))?;
+        InstallProfile::from_json(&content)
+    }
+}
+
+impl Default for ProfileStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> ProfileStore {
+        let dir = std::env::temp_dir().join(format!("lr_profile_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        ProfileStore::with_dir(dir)
+    }
+
+    #[test]
+    fn test_profile_file_name() {
+        assert_eq!(profile_file_name("办公室"), "办公室.json");
+        assert_eq!(profile_file_name(" a/b:c? "), "a_b_c_.json");
+        assert_eq!(profile_file_name("trailing. "), "trailing.json");
+    }
+
+    #[test]
+    fn test_save_list_import_export() {
+        let store = temp_store("roundtrip");
+        let mut profile = InstallProfile {
+            name: "机房".to_string(),
+            driver_action: DriverAction::SaveOnly,
+            boot_mode: BootModeSelection::UEFI,
+            ..Default::default()
+        };
+        profile.advanced_options.bypass_nro = true;
+        profile.advanced_options.run_script_first_login = true;
+        profile.advanced_options.first_login_script_path = "D:\\scripts\\init.bat".to_string();
+        store.save(&profile).unwrap();
+
+        let listed = store.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "机房");
+        assert_eq!(listed[0].driver_action, DriverAction::SaveOnly);
+        assert_eq!(listed[0].boot_mode, BootModeSelection::UEFI);
+        assert!(listed[0].advanced_options.bypass_nro);
+        assert_eq!(listed[0].advanced_options.first_login_script_path, "D:\\scripts\\init.bat");
+
+        let exported = store.dir.join("exported.txt");
+        store.export("机房", &exported).unwrap();
+        let other = temp_store("roundtrip_import");
+        let imported = other.import(&exported).unwrap();
+        assert_eq!(imported.name, "机房");
+        assert!(other.exists("机房"));
+
+        store.delete("机房").unwrap();
+        assert!(store.list().is_empty());
+        let _ = std::fs::remove_dir_all(&store.dir);
+        let _ = std::fs::remove_dir_all(&other.dir);
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let profile = InstallProfile::from_json(r#"{"name":"旧方案","advanced_options":{"bypass_nro":true}}"#).unwrap();
+        assert!(profile.format_partition);
+        assert_eq!(profile.driver_action, DriverAction::AutoImport);
+        assert!(profile.advanced_options.bypass_nro);
+        assert!(!profile.advanced_options.remove_uwp_apps);
+    }
+}
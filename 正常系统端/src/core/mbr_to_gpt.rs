@@ -0,0 +1,115 @@
+//! MBR 转 GPT（物理磁盘）
+//!
+//! 转换规划和分区表写入见 `letrecovery_disk::mbr_to_gpt`，
+//! 本模块负责缩小分区、备份分区表、写入物理磁盘后格式化 ESP 并重建 UEFI 引导。
+
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+pub use letrecovery_disk::mbr_to_gpt::*;
+
+use super::disk::PartitionStyle;
+use super::partition_table::{DiskGeometry, PartitionTable};
+
+/// 转换报告
+#[derive(Debug, Clone, Default)]
+pub struct ConversionReport {
+    /// 转换前的分区表备份
+    pub backup_path: PathBuf,
+    /// 转换前缩小过的分区
+    pub shrunk: Vec<ShrinkRequest>,
+    /// ESP 盘符
+    pub esp_letter: Option<char>,
+    /// 是否创建了 MSR
+    pub created_msr: bool,
+    pub warnings: Vec<String>,
+    /// 引导修复摘要
+    pub boot_summary: String,
+}
+
+/// 物理磁盘设备路径
+fn physical_drive_path(disk_number: u32) -> String {
+    format!(r"\\.\PhysicalDrive{}", disk_number)
+}
+
+/// 读取物理磁盘的 MBR 并规划转换
+fn plan_physical_disk(disk_number: u32) -> Result<(super::quick_partition::PhysicalDisk, ConversionStep)> {
+    let disk = super::quick_partition::get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .ok_or_else(|| anyhow::anyhow!("找不到磁盘 {}", disk_number))?;
+    if disk.partition_style != PartitionStyle::MBR {
+        bail!("磁盘 {} 不是 MBR 磁盘", disk_number);
+    }
+
+    let mut device = std::fs::File::open(physical_drive_path(disk_number))
+        .with_context(|| format!("打开磁盘 {} 失败", disk_number))?;
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+    let PartitionTable::Mbr(layout) = PartitionTable::read(&mut device, &geometry)? else {
+        bail!("磁盘 {} 的 MBR 分区表无效", disk_number);
+    };
+    let step = plan_conversion(&layout, &geometry)?;
+    Ok((disk, step))
+}
+
+/// 把系统盘从 MBR 无损转换为 GPT，并为 `windows_partition`（如 "C:"）重建 UEFI 引导
+pub fn convert_system_disk(disk_number: u32, windows_partition: &str) -> Result<ConversionReport> {
+    let mut report = ConversionReport::default();
+    log::info!("开始 MBR 转 GPT: 磁盘 {}, 系统分区 {}", disk_number, windows_partition);
+
+    let (mut disk, mut step) = plan_physical_disk(disk_number)?;
+    if let ConversionStep::NeedsShrink(requests) = step {
+        for request in &requests {
+            let mb = request.shrink_mb(disk.sector_size);
+            log::info!("缩小分区 {} {} MB: {}", request.partition_number, mb, request.reason);
+            super::quick_partition::shrink_partition(disk_number, request.partition_number, mb)
+                .with_context(|| format!("缩小分区 {} 失败", request.partition_number))?;
+        }
+        report.shrunk = requests;
+        (disk, step) = plan_physical_disk(disk_number)?;
+    }
+    let ConversionStep::Ready(conversion) = step else {
+        bail!("缩小分区后仍没有足够的空间");
+    };
+    report.warnings = conversion.warnings.clone();
+    report.created_msr = conversion.msr_index.is_some();
+
+    report.backup_path = super::partition_backup::backup_physical_disk(&disk, "MBR 转 GPT")?;
+
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+    {
+        let mut device = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(physical_drive_path(disk_number))
+            .with_context(|| format!("以写入方式打开磁盘 {} 失败", disk_number))?;
+        apply_conversion(&mut device, &geometry, &conversion)?;
+    }
+    log::info!("磁盘 {} 已写入 GPT 分区表", disk_number);
+
+    // 让系统重新读取分区表，格式化新建的 ESP 并分配盘符
+    let used = super::quick_partition::get_used_drive_letters();
+    let esp_letter = super::quick_partition::get_next_available_drive_letter(&used)
+        .ok_or_else(|| anyhow::anyhow!("没有可用的盘符挂载 ESP"))?;
+    let mut script = super::diskpart::DiskpartScript::new()
+        .rescan()
+        .select_disk(disk_number)
+        .select_partition(conversion.esp_partition_number());
+    if conversion.esp_created {
+        script = script.format("fat32", Some("System"));
+    }
+    script
+        .assign(Some(esp_letter))
+        .run()?
+        .ensure_success("格式化 ESP 失败")?;
+    report.esp_letter = Some(esp_letter);
+
+    // 重建 UEFI 引导
+    let boot = super::bcdedit::BootManager::new()
+        .repair_boot_with_options(windows_partition, true, &super::bcdedit::BootRepairOptions::default())
+        .context("修复 UEFI 引导失败")?;
+    report.boot_summary = boot.summary();
+
+    log::info!("磁盘 {} MBR 转 GPT 完成，ESP 盘符 {}:", disk_number, esp_letter);
+    Ok(report)
+}
@@ -1,4 +1,5 @@
 pub mod app_config;
+pub mod auto_job;
 pub mod bcdedit;
 pub mod bitlocker;
 pub mod fveapi;
@@ -12,12 +13,26 @@ pub mod gho_password;
 pub mod hardware_info;
 pub mod image_verify;
 pub mod install_config;
+pub mod install_profile;
 pub mod iso;
+pub mod mbr_to_gpt;
 pub mod nvidia_driver;
+pub mod partition_backup;
+pub mod partition_scan;
 pub mod pe;
 pub mod quick_partition;
 pub mod registry;
+pub mod secure_wipe;
+pub mod surface_test;
 pub mod system_info;
 pub mod system_utils;
+pub mod usb_creator;
+pub mod vhd;
 pub mod wimgapi;
 pub mod wimlib;
+
+// 与 PE 端共用的磁盘模块
+pub use letrecovery_disk::{
+    boot_code, diskpart, esp_inventory, fat32_writer, fat_format, layout_planner, partition_table, raw_image,
+    volume_id,
+};
@@ -0,0 +1,754 @@
+//! 分区表备份与恢复模块
+//!
+//! 在清除磁盘、删除分区、批量格式化等破坏性操作之前，把磁盘开头（MBR、GPT 主头和分区项数组）、
+//! GPT 备份头和分区项数组、扩展分区的 EBR 链以及每个分区开头的若干扇区保存到带时间戳的备份文件。
+//! 误执行 `clean` 之后把这些扇区原样写回，通常就能找回原来的分区。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::partition_table::{crc32, read_sectors, write_sectors, DiskGeometry, Mbr, PartitionTable};
+use super::quick_partition::PhysicalDisk;
+use crate::utils::path::get_exe_dir;
+
+/// 备份文件魔数
+const BACKUP_MAGIC: &[u8; 8] = b"LRPTBAK1";
+/// 备份文件格式版本
+const BACKUP_VERSION: u32 = 1;
+/// 备份文件扩展名
+pub const BACKUP_EXTENSION: &str = "lrpt";
+/// 磁盘开头最多保存的字节数（第一个分区之前的部分）
+const DISK_HEAD_BYTES: u64 = 1024 * 1024;
+/// 每个分区开头保存的字节数（覆盖 NTFS/FAT/exFAT 引导扇区及其备份）
+pub const PARTITION_HEAD_BYTES: u64 = 64 * 1024;
+/// 没有有效 GPT 时按 128 项 × 128 字节估算分区项数组大小
+const DEFAULT_GPT_ENTRY_ARRAY_BYTES: u64 = 128 * 128;
+/// EBR 链最大长度
+const MAX_EBR_CHAIN: usize = 128;
+
+/// 备份区域类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// 磁盘开头：MBR / 保护性 MBR、GPT 主头和分区项数组
+    DiskHead,
+    /// 磁盘末尾：GPT 备份分区项数组和备份头
+    GptBackup,
+    /// 扩展引导记录
+    Ebr,
+    /// 分区开头的引导扇区
+    PartitionHead,
+}
+
+impl RegionKind {
+    fn code(self) -> u8 {
+        match self {
+            RegionKind::DiskHead => 1,
+            RegionKind::GptBackup => 2,
+            RegionKind::Ebr => 3,
+            RegionKind::PartitionHead => 4,
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self> {
+        Ok(match code {
+            1 => RegionKind::DiskHead,
+            2 => RegionKind::GptBackup,
+            3 => RegionKind::Ebr,
+            4 => RegionKind::PartitionHead,
+            _ => bail!("未知的备份区域类型: {}", code),
+        })
+    }
+
+    /// 是否属于分区表本身（恢复分区表时总是写回）
+    pub fn is_table(self) -> bool {
+        !matches!(self, RegionKind::PartitionHead)
+    }
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionKind::DiskHead => write!(f, "磁盘头 (MBR/GPT)"),
+            RegionKind::GptBackup => write!(f, "GPT 备份"),
+            RegionKind::Ebr => write!(f, "EBR"),
+            RegionKind::PartitionHead => write!(f, "分区引导扇区"),
+        }
+    }
+}
+
+/// 一段备份的连续扇区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupRegion {
+    pub kind: RegionKind,
+    /// 起始扇区
+    pub lba: u64,
+    /// 扇区数据（整扇区）
+    pub data: Vec<u8>,
+}
+
+/// 恢复结果
+#[derive(Debug, Clone, Default)]
+pub struct RestoreReport {
+    /// 成功写回的区域数
+    pub written: usize,
+    /// 写入失败的区域（描述）
+    pub failed: Vec<String>,
+}
+
+/// 分区表备份
+#[derive(Debug, Clone)]
+pub struct PartitionTableBackup {
+    /// 备份时的磁盘编号
+    pub disk_number: u32,
+    /// 磁盘型号
+    pub model: String,
+    /// 触发备份的操作
+    pub reason: String,
+    /// 备份时间（本地时间）
+    pub created: String,
+    pub sector_size: u64,
+    pub total_sectors: u64,
+    pub regions: Vec<BackupRegion>,
+}
+
+impl PartitionTableBackup {
+    /// 从磁盘（或磁盘镜像）读取需要备份的扇区
+    ///
+    /// `partition_offsets` 为已知分区的起始偏移（字节），会与分区表中读到的分区合并
+    pub fn capture<D: Read + Seek>(
+        dev: &mut D,
+        geometry: &DiskGeometry,
+        partition_offsets: &[u64],
+    ) -> Result<Self> {
+        let sector_size = geometry.sector_size;
+        let table = PartitionTable::read(dev, geometry).ok();
+        let partitions = table.as_ref().map(|t| t.partitions()).unwrap_or_default();
+
+        let mut starts: Vec<u64> = partitions.iter().map(|p| p.start_lba).collect();
+        starts.extend(
+            partition_offsets
+                .iter()
+                .filter(|offset| *offset % sector_size == 0)
+                .map(|offset| offset / sector_size),
+        );
+        starts.retain(|lba| *lba > 0 && *lba < geometry.total_sectors);
+        starts.sort_unstable();
+        starts.dedup();
+
+        let entry_sectors = match &table {
+            Some(PartitionTable::Gpt(gpt, _)) => gpt.entry_array_sectors(sector_size),
+            _ => DEFAULT_GPT_ENTRY_ARRAY_BYTES.div_ceil(sector_size),
+        };
+        let mut regions = Vec::new();
+
+        // 磁盘开头：至少覆盖 GPT 主分区项数组，最多到第一个分区之前
+        let min_head = 2 + entry_sectors;
+        let head = geometry
+            .bytes_to_sectors(DISK_HEAD_BYTES)
+            .min(starts.first().copied().unwrap_or(u64::MAX))
+            .max(min_head)
+            .min(geometry.total_sectors);
+        regions.push(BackupRegion {
+            kind: RegionKind::DiskHead,
+            lba: 0,
+            data: read_sectors(dev, geometry, 0, head)?,
+        });
+
+        // 磁盘末尾：GPT 备份分区项数组和备份头（MBR 磁盘上也保存，便于识别曾经的 GPT）
+        let tail = 1 + entry_sectors;
+        if geometry.total_sectors > head + tail {
+            let lba = geometry.total_sectors - tail;
+            regions.push(BackupRegion {
+                kind: RegionKind::GptBackup,
+                lba,
+                data: read_sectors(dev, geometry, lba, tail)?,
+            });
+        }
+
+        // 扩展分区的 EBR 链
+        if let Some(PartitionTable::Mbr(layout)) = &table {
+            if let Some(extended) = layout.extended() {
+                for lba in ebr_chain(dev, geometry, extended.start_lba as u64)? {
+                    regions.push(BackupRegion {
+                        kind: RegionKind::Ebr,
+                        lba,
+                        data: read_sectors(dev, geometry, lba, 1)?,
+                    });
+                }
+            }
+        }
+
+        // 每个分区开头的引导扇区
+        for lba in starts {
+            let count = geometry
+                .bytes_to_sectors(PARTITION_HEAD_BYTES)
+                .min(geometry.total_sectors - lba);
+            regions.push(BackupRegion {
+                kind: RegionKind::PartitionHead,
+                lba,
+                data: read_sectors(dev, geometry, lba, count)?,
+            });
+        }
+
+        Ok(Self {
+            disk_number: 0,
+            model: String::new(),
+            reason: String::new(),
+            created: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            sector_size,
+            total_sectors: geometry.total_sectors,
+            regions,
+        })
+    }
+
+    /// 备份对应的磁盘几何信息
+    pub fn geometry(&self) -> DiskGeometry {
+        DiskGeometry {
+            sector_size: self.sector_size,
+            total_sectors: self.total_sectors,
+        }
+    }
+
+    /// 解析备份中的分区表（用于恢复前预览）
+    pub fn partition_table(&self) -> Result<PartitionTable> {
+        let geometry = self.geometry();
+        PartitionTable::read(&mut BackupView::new(self), &geometry)
+    }
+
+    /// 把备份写回磁盘
+    ///
+    /// 分区表相关区域（磁盘头、GPT 备份、EBR）总是写回；
+    /// `include_partition_heads` 为 true 时同时写回各分区开头的引导扇区
+    pub fn restore<D: Write + Seek>(
+        &self,
+        dev: &mut D,
+        geometry: &DiskGeometry,
+        include_partition_heads: bool,
+    ) -> Result<RestoreReport> {
+        if geometry.sector_size != self.sector_size || geometry.total_sectors != self.total_sectors {
+            bail!(
+                "磁盘与备份不匹配：备份为 {} 扇区 × {} 字节，当前磁盘为 {} 扇区 × {} 字节",
+                self.total_sectors,
+                self.sector_size,
+                geometry.total_sectors,
+                geometry.sector_size
+            );
+        }
+
+        let mut report = RestoreReport::default();
+        for region in &self.regions {
+            if !region.kind.is_table() && !include_partition_heads {
+                continue;
+            }
+            match write_sectors(dev, geometry, region.lba, &region.data) {
+                Ok(()) => report.written += 1,
+                Err(e) if region.kind.is_table() => {
+                    return Err(e).with_context(|| format!("写回{}失败", region.kind));
+                }
+                Err(e) => report
+                    .failed
+                    .push(format!("{} (扇区 {}): {}", region.kind, region.lba, e)),
+            }
+        }
+        dev.flush()?;
+        Ok(report)
+    }
+
+    /// 序列化为备份文件内容
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(BACKUP_MAGIC);
+        buf.extend_from_slice(&BACKUP_VERSION.to_le_bytes());
+        buf.extend_from_slice(&self.disk_number.to_le_bytes());
+        buf.extend_from_slice(&self.sector_size.to_le_bytes());
+        buf.extend_from_slice(&self.total_sectors.to_le_bytes());
+        for text in [&self.created, &self.model, &self.reason] {
+            let bytes = text.as_bytes();
+            let len = bytes.len().min(u16::MAX as usize);
+            buf.extend_from_slice(&(len as u16).to_le_bytes());
+            buf.extend_from_slice(&bytes[..len]);
+        }
+        buf.extend_from_slice(&(self.regions.len() as u32).to_le_bytes());
+        for region in &self.regions {
+            buf.push(region.kind.code());
+            buf.extend_from_slice(&region.lba.to_le_bytes());
+            buf.extend_from_slice(&(region.data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&crc32(&region.data).to_le_bytes());
+            buf.extend_from_slice(&region.data);
+        }
+        let crc = crc32(&buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    /// 从备份文件内容解析
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < BACKUP_MAGIC.len() + 4 || &bytes[..BACKUP_MAGIC.len()] != BACKUP_MAGIC {
+            bail!("不是有效的分区表备份文件");
+        }
+        let (body, trailer) = bytes.split_at(bytes.len() - 4);
+        if crc32(body) != u32::from_le_bytes(trailer.try_into().unwrap()) {
+            bail!("备份文件校验失败，文件可能已损坏");
+        }
+
+        let mut reader = ByteReader::new(&body[BACKUP_MAGIC.len()..]);
+        let version = reader.u32()?;
+        if version != BACKUP_VERSION {
+            bail!("不支持的备份文件版本: {}", version);
+        }
+        let disk_number = reader.u32()?;
+        let sector_size = reader.u64()?;
+        let total_sectors = reader.u64()?;
+        let created = reader.string()?;
+        let model = reader.string()?;
+        let reason = reader.string()?;
+
+        let count = reader.u32()?;
+        let mut regions = Vec::with_capacity(count.min(1024) as usize);
+        for _ in 0..count {
+            let kind = RegionKind::from_code(reader.u8()?)?;
+            let lba = reader.u64()?;
+            let len = reader.u32()? as usize;
+            let crc = reader.u32()?;
+            let data = reader.bytes(len)?.to_vec();
+            if crc32(&data) != crc {
+                bail!("备份区域 (扇区 {}) 校验失败", lba);
+            }
+            regions.push(BackupRegion { kind, lba, data });
+        }
+
+        Ok(Self {
+            disk_number,
+            model,
+            reason,
+            created,
+            sector_size,
+            total_sectors,
+            regions,
+        })
+    }
+
+    /// 保存到目录，文件名带磁盘编号和时间戳
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("创建备份目录失败: {}", dir.display()))?;
+        let path = dir.join(format!(
+            "disk{}_{}.{}",
+            self.disk_number,
+            chrono::Local::now().format("%Y%m%d_%H%M%S"),
+            BACKUP_EXTENSION
+        ));
+        std::fs::write(&path, self.encode())
+            .with_context(|| format!("写入备份文件失败: {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// 从文件加载
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("读取备份文件失败: {}", path.display()))?;
+        Self::decode(&bytes)
+    }
+
+    /// 备份内容摘要
+    pub fn summary(&self) -> String {
+        let size_gb = (self.sector_size * self.total_sectors) as f64 / 1024.0 / 1024.0 / 1024.0;
+        let table = match self.partition_table() {
+            Ok(PartitionTable::Raw) => "未初始化".to_string(),
+            Ok(table) => {
+                let style = if matches!(table, PartitionTable::Gpt(_, _)) { "GPT" } else { "MBR" };
+                format!("{}，{} 个分区", style, table.partitions().len())
+            }
+            Err(e) => format!("分区表无效: {}", e),
+        };
+        format!(
+            "磁盘 {} {} ({:.1} GB) - {} - {} [{}]",
+            self.disk_number,
+            self.model,
+            size_gb,
+            table,
+            self.reason,
+            self.created
+        )
+    }
+}
+
+/// 沿 EBR 链收集每个 EBR 所在扇区
+fn ebr_chain<D: Read + Seek>(dev: &mut D, geometry: &DiskGeometry, ext_start: u64) -> Result<Vec<u64>> {
+    let mut lbas = Vec::new();
+    let mut lba = ext_start;
+    for _ in 0..MAX_EBR_CHAIN {
+        lbas.push(lba);
+        let ebr = Mbr::decode(&read_sectors(dev, geometry, lba, 1)?)?;
+        let link = ebr.entries[1];
+        if link.is_empty() || !link.is_extended() {
+            break;
+        }
+        let next = ext_start + link.start_lba as u64;
+        if next <= lba || next >= geometry.total_sectors {
+            break;
+        }
+        lba = next;
+    }
+    Ok(lbas)
+}
+
+/// 把备份当作稀疏磁盘读取（未备份的扇区读为 0）
+struct BackupView<'a> {
+    backup: &'a PartitionTableBackup,
+    position: u64,
+}
+
+impl<'a> BackupView<'a> {
+    fn new(backup: &'a PartitionTableBackup) -> Self {
+        Self { backup, position: 0 }
+    }
+}
+
+impl Read for BackupView<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.backup.sector_size * self.backup.total_sectors;
+        if self.position >= size {
+            return Ok(0);
+        }
+        let len = (buf.len() as u64).min(size - self.position) as usize;
+        let buf = &mut buf[..len];
+        buf.fill(0);
+
+        let start = self.position;
+        let end = start + len as u64;
+        for region in &self.backup.regions {
+            let region_start = region.lba * self.backup.sector_size;
+            let region_end = region_start + region.data.len() as u64;
+            let from = start.max(region_start);
+            let to = end.min(region_end);
+            if from < to {
+                buf[(from - start) as usize..(to - start) as usize].copy_from_slice(
+                    &region.data[(from - region_start) as usize..(to - region_start) as usize],
+                );
+            }
+        }
+        self.position = end;
+        Ok(len)
+    }
+}
+
+impl Seek for BackupView<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let size = (self.backup.sector_size * self.backup.total_sectors) as i64;
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => size + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if target < 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek 到负偏移"));
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+/// 按顺序读取备份文件字段
+struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow::anyhow!("备份文件被截断"))?;
+        let slice = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()) as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).to_string())
+    }
+}
+
+/// 默认备份目录
+pub fn default_backup_dir() -> PathBuf {
+    get_exe_dir().join("PartitionBackup")
+}
+
+/// 为指定磁盘选择备份目录
+///
+/// 程序目录位于被操作的磁盘上时，改用其他磁盘上的分区保存，避免备份随磁盘一起被清除
+pub fn backup_dir_for(disk: &PhysicalDisk) -> PathBuf {
+    let default_dir = default_backup_dir();
+    let on_disk = |letter: char| {
+        disk.partitions
+            .iter()
+            .any(|p| p.drive_letter.map(|l| l.eq_ignore_ascii_case(&letter)).unwrap_or(false))
+    };
+
+    let exe_letter = default_dir
+        .to_string_lossy()
+        .chars()
+        .next()
+        .filter(|c| c.is_ascii_alphabetic());
+    match exe_letter {
+        Some(letter) if on_disk(letter) => {
+            for letter in super::quick_partition::get_used_drive_letters() {
+                if on_disk(letter) || letter == 'X' {
+                    continue;
+                }
+                let root = PathBuf::from(format!("{}:\\", letter));
+                if super::disk::DiskManager::is_fixed_drive(letter) && root.exists() {
+                    return root.join("LetRecovery").join("PartitionBackup");
+                }
+            }
+            log::warn!("没有其他磁盘可以保存分区表备份，仍保存到程序目录");
+            default_dir
+        }
+        _ => default_dir,
+    }
+}
+
+/// 列出备份目录中的备份文件（新的在前）
+pub fn list_backups(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.extension()
+                        .map(|ext| ext.eq_ignore_ascii_case(BACKUP_EXTENSION))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// 查找所有备份文件：程序目录以及各分区上的 LetRecovery\PartitionBackup
+pub fn find_all_backups() -> Vec<PathBuf> {
+    let mut dirs = vec![default_backup_dir()];
+    for letter in super::quick_partition::get_used_drive_letters() {
+        dirs.push(PathBuf::from(format!("{}:\\LetRecovery\\PartitionBackup", letter)));
+    }
+    dirs.dedup();
+
+    let mut files: Vec<PathBuf> = dirs.iter().flat_map(|dir| list_backups(dir)).collect();
+    files.sort_by_key(|p| std::cmp::Reverse(p.file_name().map(|n| n.to_os_string())));
+    files.dedup();
+    files
+}
+
+/// 物理磁盘设备路径
+fn physical_drive_path(disk_number: u32) -> String {
+    format!(r"\\.\PhysicalDrive{}", disk_number)
+}
+
+/// 在破坏性操作前备份物理磁盘的分区表
+pub fn backup_physical_disk(disk: &PhysicalDisk, reason: &str) -> Result<PathBuf> {
+    let mut device = std::fs::File::open(physical_drive_path(disk.disk_number))
+        .with_context(|| format!("打开磁盘 {} 失败", disk.disk_number))?;
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+    let offsets: Vec<u64> = disk.partitions.iter().map(|p| p.offset_bytes).collect();
+
+    let mut backup = PartitionTableBackup::capture(&mut device, &geometry, &offsets)?;
+    backup.disk_number = disk.disk_number;
+    backup.model = disk.model.clone();
+    backup.reason = reason.to_string();
+
+    let path = backup.save(&backup_dir_for(disk))?;
+    log::info!("磁盘 {} 分区表已备份到: {}", disk.disk_number, path.display());
+    Ok(path)
+}
+
+/// 按磁盘编号备份分区表
+pub fn backup_disk_number(disk_number: u32, reason: &str) -> Result<PathBuf> {
+    let disk = super::quick_partition::get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .ok_or_else(|| anyhow::anyhow!("找不到磁盘 {}", disk_number))?;
+    backup_physical_disk(&disk, reason)
+}
+
+/// 备份包含指定盘符的所有磁盘的分区表
+pub fn backup_disks_for_letters(letters: &[char], reason: &str) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for disk in super::quick_partition::get_physical_disks() {
+        let contains = disk.partitions.iter().any(|p| {
+            p.drive_letter
+                .map(|l| letters.iter().any(|x| x.eq_ignore_ascii_case(&l)))
+                .unwrap_or(false)
+        });
+        if contains {
+            paths.push(backup_physical_disk(&disk, reason)?);
+        }
+    }
+    Ok(paths)
+}
+
+/// 把备份写回物理磁盘
+///
+/// 目标磁盘的大小和扇区大小必须与备份一致
+pub fn restore_physical_disk(
+    backup: &PartitionTableBackup,
+    disk: &PhysicalDisk,
+    include_partition_heads: bool,
+) -> Result<RestoreReport> {
+    let mut device = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(physical_drive_path(disk.disk_number))
+        .with_context(|| format!("以写入方式打开磁盘 {} 失败", disk.disk_number))?;
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+
+    let report = backup.restore(&mut device, &geometry, include_partition_heads)?;
+    drop(device);
+
+    // 让系统重新读取分区表
+    if let Err(e) = super::diskpart::DiskpartScript::new().rescan().run() {
+        log::warn!("diskpart rescan 失败: {}", e);
+    }
+    log::info!(
+        "磁盘 {} 分区表已恢复，写回 {} 个区域，失败 {} 个",
+        disk.disk_number,
+        report.written,
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::partition_table::{
+        Gpt, GptEntry, GptHealth, MbrEntry, MbrLayout, BASIC_DATA_PARTITION_TYPE,
+        ESP_PARTITION_TYPE, MBR_TYPE_EXTENDED_LBA, MBR_TYPE_NTFS,
+    };
+
+    fn temp_disk(name: &str, size: u64) -> (PathBuf, std::fs::File) {
+        let dir = std::env::temp_dir().join(format!("letrecovery_ptbak_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join(name);
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(size).unwrap();
+        (path, file)
+    }
+
+    #[test]
+    fn test_gpt_backup_restores_after_clean() {
+        let (path, mut file) = temp_disk("gpt.img", 128 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, 512).unwrap();
+        let mut gpt = Gpt::new(&geometry).unwrap();
+        gpt.entries.push(GptEntry::new(ESP_PARTITION_TYPE, 2048, 206_847, "EFI"));
+        gpt.entries.push(GptEntry::new(BASIC_DATA_PARTITION_TYPE, 206_848, 260_000, "Data"));
+        let table = PartitionTable::Gpt(gpt, GptHealth::default());
+        table.write(&mut file, &geometry).unwrap();
+        write_sectors(&mut file, &geometry, 206_848, b"\xEBR\x90NTFS    ").unwrap();
+
+        let backup = PartitionTableBackup::capture(&mut file, &geometry, &[206_848 * 512]).unwrap();
+        let decoded = PartitionTableBackup::decode(&backup.encode()).unwrap();
+        assert_eq!(decoded.regions, backup.regions);
+        assert_eq!(decoded.partition_table().unwrap().partitions().len(), 2);
+
+        // 模拟 clean：清零磁盘开头和末尾的 1 MiB 以及分区引导扇区
+        let zeros = vec![0u8; 1024 * 1024];
+        write_sectors(&mut file, &geometry, 0, &zeros).unwrap();
+        write_sectors(&mut file, &geometry, geometry.total_sectors - 2048, &zeros).unwrap();
+        write_sectors(&mut file, &geometry, 206_848, &[0u8; 512]).unwrap();
+        assert!(matches!(PartitionTable::read(&mut file, &geometry).unwrap(), PartitionTable::Raw));
+
+        let report = decoded.restore(&mut file, &geometry, true).unwrap();
+        assert!(report.failed.is_empty());
+        let restored = PartitionTable::read(&mut file, &geometry).unwrap();
+        assert!(matches!(&restored, PartitionTable::Gpt(_, health) if health.is_healthy()));
+        assert_eq!(restored.partitions(), table.partitions());
+        let boot = read_sectors(&mut file, &geometry, 206_848, 1).unwrap();
+        assert_eq!(&boot[3..7], b"NTFS");
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_mbr_backup_includes_ebr_chain() {
+        let (path, mut file) = temp_disk("mbr.img", 64 * 1024 * 1024);
+        let geometry = DiskGeometry::from_stream(&mut file, 512).unwrap();
+        let mut layout = MbrLayout::default();
+        layout.mbr.disk_signature = 0x1234_5678;
+        layout.mbr.entries[0] = MbrEntry {
+            bootable: true,
+            partition_type: MBR_TYPE_NTFS,
+            start_lba: 2048,
+            sector_count: 40_960,
+        };
+        layout.mbr.entries[1] = MbrEntry {
+            bootable: false,
+            partition_type: MBR_TYPE_EXTENDED_LBA,
+            start_lba: 43_008,
+            sector_count: 80_000,
+        };
+        for start in [45_056u32, 90_112] {
+            layout.logical.push(MbrEntry {
+                bootable: false,
+                partition_type: MBR_TYPE_NTFS,
+                start_lba: start,
+                sector_count: 20_000,
+            });
+        }
+        let table = PartitionTable::Mbr(layout);
+        table.write(&mut file, &geometry).unwrap();
+
+        let backup = PartitionTableBackup::capture(&mut file, &geometry, &[]).unwrap();
+        let ebrs: Vec<u64> = backup
+            .regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Ebr)
+            .map(|r| r.lba)
+            .collect();
+        assert_eq!(ebrs.len(), 2);
+        assert_eq!(backup.partition_table().unwrap().partitions(), table.partitions());
+
+        // 尺寸不一致的磁盘拒绝恢复
+        let other = DiskGeometry::new(512, 32 * 1024 * 1024);
+        assert!(backup.restore(&mut file, &other, false).is_err());
+
+        let mut corrupted = backup.encode();
+        let middle = corrupted.len() / 2;
+        corrupted[middle] ^= 0xFF;
+        assert!(PartitionTableBackup::decode(&corrupted).is_err());
+
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+}
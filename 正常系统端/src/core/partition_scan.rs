@@ -0,0 +1,68 @@
+//! 丢失分区扫描（物理磁盘）
+//!
+//! 扫描和重建分区表的逻辑见 `letrecovery_disk::partition_scan`，
+//! 本模块负责打开物理磁盘、写回前备份分区表并通知系统重新读取。
+
+use anyhow::{Context, Result};
+use std::sync::atomic::AtomicBool;
+
+pub use letrecovery_disk::partition_scan::*;
+
+use super::partition_table::{read_sectors, DiskGeometry, Mbr, PartitionTable};
+use super::quick_partition::PhysicalDisk;
+
+/// 物理磁盘设备路径
+fn physical_drive_path(disk_number: u32) -> String {
+    format!(r"\\.\PhysicalDrive{}", disk_number)
+}
+
+/// 扫描物理磁盘
+pub fn scan_physical_disk(
+    disk: &PhysicalDisk,
+    mode: ScanMode,
+    progress: impl FnMut(u64, u64),
+    cancel: Option<&AtomicBool>,
+) -> Result<ScanReport> {
+    let mut device = std::fs::File::open(physical_drive_path(disk.disk_number))
+        .with_context(|| format!("打开磁盘 {} 失败", disk.disk_number))?;
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+    log::info!("开始扫描磁盘 {} 上的丢失分区 ({:?})", disk.disk_number, mode);
+    let report = scan(&mut device, &geometry, mode, progress, cancel)?;
+    log::info!(
+        "磁盘 {} 扫描完成，候选分区 {} 个，建议恢复 {} 个",
+        disk.disk_number,
+        report.candidates.len(),
+        report.proposal.len()
+    );
+    Ok(report)
+}
+
+/// 把恢复出的分区表写入物理磁盘
+///
+/// 写入前先备份当前分区表，写入后让系统重新读取；
+/// 保留扇区 0 中现有的引导代码
+pub fn write_recovered_table(disk: &PhysicalDisk, table: &PartitionTable) -> Result<std::path::PathBuf> {
+    let backup = super::partition_backup::backup_physical_disk(disk, "写入恢复的分区表")?;
+
+    let mut device = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(physical_drive_path(disk.disk_number))
+        .with_context(|| format!("以写入方式打开磁盘 {} 失败", disk.disk_number))?;
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+
+    let mut table = table.clone();
+    if let PartitionTable::Mbr(layout) = &mut table {
+        if let Ok(existing) = Mbr::decode(&read_sectors(&mut device, &geometry, 0, 1)?) {
+            layout.mbr.boot_code = existing.boot_code;
+        }
+    }
+    table.write(&mut device, &geometry)?;
+    drop(device);
+
+    if let Err(e) = super::diskpart::DiskpartScript::new().rescan().run() {
+        log::warn!("diskpart rescan 失败: {}", e);
+    }
+    log::info!("磁盘 {} 已写入恢复的分区表", disk.disk_number);
+    Ok(backup)
+}
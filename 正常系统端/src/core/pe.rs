@@ -64,7 +64,9 @@ impl PeManager {
     /// 从ISO/WIM启动PE
     /// pe_path: PE文件路径 (.iso 或 .wim)
     /// display_name: 显示名称
-    pub fn boot_to_pe(&self, pe_path: &str, display_name: &str) -> Result<()> {
+    ///
+    /// 返回写入PE引导项的一次性校验码，写入交接配置时用它签名
+    pub fn boot_to_pe(&self, pe_path: &str, display_name: &str) -> Result<String> {
         println!("[PE] ========== 准备启动 PE ==========");
         println!("[PE] PE文件: {}", pe_path);
         println!("[PE] 显示名称: {}", display_name);
@@ -72,12 +74,37 @@ impl PeManager {
         let pe_path_lower = pe_path.to_lowercase();
         
         if pe_path_lower.ends_with(".iso") {
-            self.boot_from_iso(pe_path, display_name)
+            self.boot_from_iso(pe_path, display_name)?;
         } else if pe_path_lower.ends_with(".wim") {
-            self.boot_from_wim(pe_path, display_name)
+            self.boot_from_wim(pe_path, display_name)?;
         } else {
             anyhow::bail!("不支持的PE文件格式，请使用 .iso 或 .wim 文件")
         }
+
+        self.bind_handoff_nonce()
+    }
+
+    /// 生成一次性校验码并写入PE引导项的 loadoptions
+    ///
+    /// PE 启动后从 SystemStartOptions 读回，用于校验交接配置的签名。
+    fn bind_handoff_nonce(&self) -> Result<String> {
+        let guid_file = "C:\\LetRecovery_PE\\pe_guid.txt";
+        let content = std::fs::read_to_string(guid_file)?;
+        let loader_guid = content
+            .lines()
+            .nth(1)
+            .ok_or_else(|| anyhow::anyhow!("未找到PE引导项GUID"))?;
+
+        let nonce = letrecovery_config::generate_nonce();
+        let option = letrecovery_config::nonce_load_option(&nonce);
+        let output = create_command(&self.bcdedit_path)
+            .args(["/set", loader_guid, "loadoptions", &option])
+            .output()?;
+        if !output.status.success() {
+            anyhow::bail!("写入PE引导校验码失败: {}", gbk_to_utf8(&output.stderr));
+        }
+        println!("[PE] 已写入引导校验码: {}", loader_guid);
+        Ok(nonce)
     }
 
     /// 从ISO启动PE
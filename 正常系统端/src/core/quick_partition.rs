@@ -25,23 +25,13 @@ use windows::{
 #[cfg(windows)]
 const IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS: u32 = 0x00560000;
 
-use crate::utils::cmd::create_command;
-use crate::utils::encoding::gbk_to_utf8;
-use crate::utils::path::get_bin_dir;
-
 use super::disk::PartitionStyle;
+use super::diskpart::{CreatePartitionKind, DiskpartScript};
+use super::partition_backup::{backup_disk_number, backup_physical_disk};
+use super::layout_planner::{LayoutPlan, LayoutPlanner, PartitionIntent, PartitionRole, PartitionSize, MIB};
+use super::partition_table::{self, DiskGeometry, PartitionTable};
 use super::system_info::BootMode;
 
-/// 获取 diskpart 可执行文件路径
-fn get_diskpart_path() -> String {
-    let builtin_diskpart = get_bin_dir().join("diskpart").join("diskpart.exe");
-    if builtin_diskpart.exists() {
-        builtin_diskpart.to_string_lossy().to_string()
-    } else {
-        "diskpart.exe".to_string()
-    }
-}
-
 /// 物理磁盘信息
 #[derive(Debug, Clone)]
 pub struct PhysicalDisk {
@@ -49,6 +39,8 @@ pub struct PhysicalDisk {
     pub disk_number: u32,
     /// 磁盘大小（字节）
     pub size_bytes: u64,
+    /// 逻辑扇区大小（字节）
+    pub sector_size: u64,
     /// 磁盘型号/名称
     pub model: String,
     /// 分区表类型
@@ -163,6 +155,14 @@ pub struct PartitionLayout {
     pub label: String,
     /// 是否为 ESP 分区
     pub is_esp: bool,
+    /// 是否为 MSR 分区
+    pub is_msr: bool,
+    /// 是否为恢复分区
+    pub is_recovery: bool,
+    /// 是否为活动分区（MBR 系统保留分区）
+    pub is_active: bool,
+    /// 是否使用所有剩余空间
+    pub fill_remaining: bool,
     /// 文件系统类型
     pub file_system: String,
 }
@@ -174,17 +174,61 @@ impl Default for PartitionLayout {
             drive_letter: None,
             label: String::new(),
             is_esp: false,
+            is_msr: false,
+            is_recovery: false,
+            is_active: false,
+            fill_remaining: false,
             file_system: "NTFS".to_string(),
         }
     }
 }
 
+impl PartitionLayout {
+    /// 转换为分区规划意图
+    pub fn to_intent(&self) -> PartitionIntent {
+        let role = if self.is_esp {
+            PartitionRole::Esp
+        } else if self.is_msr {
+            PartitionRole::Msr
+        } else if self.is_recovery {
+            PartitionRole::Recovery
+        } else if self.is_active {
+            PartitionRole::SystemReserved
+        } else {
+            PartitionRole::Basic
+        };
+
+        let size = if self.fill_remaining {
+            PartitionSize::Fill
+        } else {
+            PartitionSize::Mib((self.size_gb * 1024.0).round().max(0.0) as u64)
+        };
+
+        let file_system = match role {
+            PartitionRole::Esp => "FAT32".to_string(),
+            PartitionRole::Msr => String::new(),
+            _ if self.file_system.is_empty() => "NTFS".to_string(),
+            _ => self.file_system.clone(),
+        };
+
+        PartitionIntent {
+            role,
+            size,
+            label: self.label.clone(),
+            drive_letter: self.drive_letter,
+            file_system,
+        }
+    }
+}
+
 /// 一键分区操作结果
 #[derive(Debug, Clone)]
 pub struct QuickPartitionResult {
     pub success: bool,
     pub message: String,
     pub created_partitions: Vec<String>,
+    /// 执行前保存的分区表备份文件
+    pub backup_path: Option<String>,
 }
 
 /// DISK_GEOMETRY_EX 结构
@@ -303,19 +347,13 @@ impl Default for PartitionInfoExMbr {
 }
 
 /// ESP 分区类型 GUID
-const ESP_PARTITION_TYPE_GUID: [u8; 16] = [
-    0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b,
-];
+const ESP_PARTITION_TYPE_GUID: [u8; 16] = partition_table::ESP_PARTITION_TYPE.0;
 
 /// MSR 分区类型 GUID
-const MSR_PARTITION_TYPE_GUID: [u8; 16] = [
-    0x16, 0xe3, 0xc9, 0xe3, 0x5c, 0x0b, 0xb8, 0x4d, 0x81, 0x7d, 0xf9, 0x2d, 0xf0, 0x02, 0x15, 0xae,
-];
+const MSR_PARTITION_TYPE_GUID: [u8; 16] = partition_table::MSR_PARTITION_TYPE.0;
 
 /// Windows 恢复分区类型 GUID
-const RECOVERY_PARTITION_TYPE_GUID: [u8; 16] = [
-    0xa4, 0xbb, 0x94, 0xde, 0xd1, 0x06, 0x40, 0x4d, 0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6, 0xac,
-];
+const RECOVERY_PARTITION_TYPE_GUID: [u8; 16] = partition_table::RECOVERY_PARTITION_TYPE.0;
 
 /// 获取所有物理磁盘列表
 #[cfg(windows)]
@@ -385,6 +423,25 @@ fn get_disk_info(disk_number: u32) -> Option<PhysicalDisk> {
             return None;
         };
 
+        // 优先直接读取磁盘上的分区表，失败时回退到 IOCTL
+        let sector_size = match geometry.geometry_bytes_per_sector {
+            0 => partition_table::DEFAULT_SECTOR_SIZE,
+            n => n as u64,
+        };
+        let disk_geometry = DiskGeometry::new(sector_size, size_bytes);
+        let table = std::fs::File::open(&disk_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut file| PartitionTable::read(&mut file, &disk_geometry));
+        if let Ok(table) = table {
+            let _ = CloseHandle(handle);
+            let model = get_disk_model(disk_number).unwrap_or_default();
+            let mut disk = physical_disk_from_table(disk_number, model, &disk_geometry, &table);
+            for partition in &mut disk.partitions {
+                fill_volume_info(partition);
+            }
+            return Some(disk);
+        }
+
         // 获取分区布局信息
         let mut buffer = vec![0u8; 65536]; // 足够大的缓冲区
         let mut bytes_returned: u32 = 0;
@@ -434,6 +491,7 @@ fn get_disk_info(disk_number: u32) -> Option<PhysicalDisk> {
         Some(PhysicalDisk {
             disk_number,
             size_bytes,
+            sector_size,
             model,
             partition_style,
             is_initialized,
@@ -563,6 +621,79 @@ fn parse_partition_layout(
     partitions
 }
 
+/// 根据分区表构建磁盘信息（不含盘符和卷信息）
+pub fn physical_disk_from_table(
+    disk_number: u32,
+    model: String,
+    geometry: &DiskGeometry,
+    table: &PartitionTable,
+) -> PhysicalDisk {
+    let partition_style = match table {
+        PartitionTable::Gpt(..) => PartitionStyle::GPT,
+        PartitionTable::Mbr(_) => PartitionStyle::MBR,
+        PartitionTable::Raw => PartitionStyle::Unknown,
+    };
+
+    let mut partitions: Vec<DiskPartitionInfo> = table
+        .partitions()
+        .iter()
+        .map(|entry| DiskPartitionInfo {
+            partition_number: entry.number,
+            size_bytes: geometry.sectors_to_bytes(entry.sector_count),
+            offset_bytes: geometry.sectors_to_bytes(entry.start_lba),
+            drive_letter: None,
+            label: String::new(),
+            file_system: String::new(),
+            is_esp: entry.is_esp(),
+            is_msr: entry.is_msr(),
+            is_recovery: entry.is_recovery(),
+            partition_type: entry.type_string(),
+            used_bytes: 0,
+            free_bytes: 0,
+        })
+        .collect();
+    partitions.sort_by_key(|p| p.offset_bytes);
+
+    let allocated: u64 = partitions.iter().map(|p| p.size_bytes).sum();
+    let size_bytes = geometry.size_bytes();
+
+    PhysicalDisk {
+        disk_number,
+        size_bytes,
+        sector_size: geometry.sector_size,
+        model,
+        partition_style,
+        is_initialized: partition_style != PartitionStyle::Unknown,
+        partitions,
+        unallocated_bytes: size_bytes.saturating_sub(allocated),
+    }
+}
+
+/// 读取磁盘镜像文件的分区布局
+pub fn read_disk_image(path: &Path) -> Result<PhysicalDisk> {
+    let mut file = std::fs::File::open(path)?;
+    let geometry = DiskGeometry::from_stream(&mut file, partition_table::DEFAULT_SECTOR_SIZE)?;
+    let table = PartitionTable::read(&mut file, &geometry)?;
+    let model = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(physical_disk_from_table(0, model, &geometry, &table))
+}
+
+/// 补充分区的盘符、卷标、文件系统和空间使用信息
+#[cfg(windows)]
+fn fill_volume_info(partition: &mut DiskPartitionInfo) {
+    partition.drive_letter = get_drive_letter_for_partition(partition.offset_bytes);
+    if let Some(letter) = partition.drive_letter {
+        let (label, file_system, used_bytes, free_bytes) = get_volume_info(letter);
+        partition.label = label;
+        partition.file_system = file_system;
+        partition.used_bytes = used_bytes;
+        partition.free_bytes = free_bytes;
+    }
+}
+
 /// 根据分区偏移量获取对应的盘符
 #[cfg(windows)]
 fn get_drive_letter_for_partition(offset: u64) -> Option<char> {
@@ -713,6 +844,7 @@ fn get_volume_info(letter: char) -> (String, String, u64, u64) {
 #[cfg(windows)]
 fn get_disk_model(disk_number: u32) -> Option<String> {
     use crate::utils::cmd::create_command;
+    use crate::utils::encoding::gbk_to_utf8;
 
     // 使用 PowerShell 获取磁盘型号
     let output = create_command("powershell")
@@ -760,150 +892,151 @@ fn get_disk_model(disk_number: u32) -> Option<String> {
     None
 }
 
-/// 执行一键分区操作
+/// 执行一键分区
+///
+/// 先用 [`LayoutPlanner`] 计算并校验精确的分区范围，校验通过后才会修改磁盘
 pub fn execute_quick_partition(
-    disk_number: u32,
+    disk: &PhysicalDisk,
     partition_style: PartitionStyle,
     layouts: &[PartitionLayout],
 ) -> QuickPartitionResult {
     log::info!(
         "开始一键分区: 磁盘 {}, 分区表类型: {:?}, 分区数量: {}",
-        disk_number,
+        disk.disk_number,
         partition_style,
         layouts.len()
     );
 
-    // 构建 diskpart 脚本
-    let mut script = String::new();
-
-    // 选择磁盘
-    script.push_str(&format!("select disk {}\n", disk_number));
-
-    // 清除磁盘（删除所有分区）
-    script.push_str("clean\n");
-
-    // 转换分区表类型
-    match partition_style {
-        PartitionStyle::GPT => {
-            script.push_str("convert gpt\n");
-        }
-        PartitionStyle::MBR => {
-            script.push_str("convert mbr\n");
-        }
-        _ => {
+    let intents: Vec<PartitionIntent> = layouts.iter().map(PartitionLayout::to_intent).collect();
+    let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+    let plan = match LayoutPlanner::new(geometry, partition_style).plan(&intents) {
+        Ok(plan) => plan,
+        Err(e) => {
             return QuickPartitionResult {
                 success: false,
-                message: "无效的分区表类型".to_string(),
+                message: format!("分区布局无效: {}", e),
                 created_partitions: Vec::new(),
+                backup_path: None,
             };
         }
+    };
+    for warning in &plan.warnings {
+        log::warn!("分区布局警告: {}", warning);
     }
 
-    let mut created_partitions = Vec::new();
-
-    // 创建分区
-    for (i, layout) in layouts.iter().enumerate() {
-        let is_last = i == layouts.len() - 1;
-
-        if layout.is_esp {
-            // 创建 ESP 分区
-            let size_mb = (layout.size_gb * 1024.0) as u64;
-            script.push_str(&format!("create partition efi size={}\n", size_mb));
-            script.push_str("format fs=fat32 quick label=\"EFI\"\n");
-            created_partitions.push("ESP".to_string());
-        } else {
-            // 创建普通分区
-            if is_last {
-                // 最后一个分区使用剩余空间
-                script.push_str("create partition primary\n");
-            } else {
-                let size_mb = (layout.size_gb * 1024.0) as u64;
-                script.push_str(&format!("create partition primary size={}\n", size_mb));
-            }
-
-            // 格式化
-            let label = if layout.label.is_empty() {
-                "新加卷".to_string()
-            } else {
-                layout.label.clone()
-            };
-            let fs = if layout.file_system.is_empty() {
-                "NTFS"
-            } else {
-                &layout.file_system
+    // clean 之前备份分区表，备份失败则不继续
+    let backup_path = match backup_physical_disk(disk, "一键分区") {
+        Ok(path) => Some(path.to_string_lossy().to_string()),
+        Err(e) => {
+            return QuickPartitionResult {
+                success: false,
+                message: format!("备份分区表失败，已取消分区操作: {}", e),
+                created_partitions: Vec::new(),
+                backup_path: None,
             };
-            script.push_str(&format!("format fs={} quick label=\"{}\"\n", fs, label));
-
-            // 分配盘符
-            if let Some(letter) = layout.drive_letter {
-                script.push_str(&format!("assign letter={}\n", letter));
-                created_partitions.push(format!("{}:", letter));
-            } else {
-                script.push_str("assign\n");
-                created_partitions.push(format!("分区 {}", i + 1));
-            }
         }
-    }
+    };
+
+    let (script, created_partitions) = build_quick_partition_script(disk.disk_number, &plan);
 
     // 执行脚本
-    match execute_diskpart_script(&script) {
-        Ok(output) => {
-            // 检查输出是否包含错误
-            let output_lower = output.to_lowercase();
-            if output_lower.contains("错误")
-                || output_lower.contains("error")
-                || output_lower.contains("失败")
-                || output_lower.contains("failed")
-            {
-                QuickPartitionResult {
-                    success: false,
-                    message: format!("分区操作失败: {}", output),
-                    created_partitions: Vec::new(),
-                }
-            } else {
-                QuickPartitionResult {
-                    success: true,
-                    message: "分区操作完成".to_string(),
-                    created_partitions,
-                }
-            }
-        }
+    match script.run() {
+        Ok(outcome) if outcome.is_success() => QuickPartitionResult {
+            success: true,
+            message: "分区操作完成".to_string(),
+            created_partitions,
+            backup_path,
+        },
+        Ok(outcome) => QuickPartitionResult {
+            success: false,
+            message: format!("分区操作失败: {}", outcome.error_message()),
+            created_partitions: Vec::new(),
+            backup_path,
+        },
         Err(e) => QuickPartitionResult {
             success: false,
             message: format!("执行 diskpart 失败: {}", e),
             created_partitions: Vec::new(),
+            backup_path,
         },
     }
 }
 
-/// 执行 diskpart 脚本
-fn execute_diskpart_script(script: &str) -> Result<String> {
-    let temp_dir = std::env::temp_dir();
-    let script_path = temp_dir.join("lr_quick_partition.txt");
-
-    log::debug!("Diskpart 脚本内容:\n{}", script);
+/// 根据规划结果生成 diskpart 脚本（使用精确的 offset 和 size）
+fn build_quick_partition_script(disk_number: u32, plan: &LayoutPlan) -> (DiskpartScript, Vec<String>) {
+    let mut created_partitions = Vec::new();
 
-    std::fs::write(&script_path, script)?;
+    // 选择磁盘，清除磁盘（删除所有分区）
+    let mut script = DiskpartScript::new().select_disk(disk_number).clean();
 
-    let output = create_command(&get_diskpart_path())
-        .args(["/s", script_path.to_str().unwrap()])
-        .output()?;
+    // 转换分区表类型
+    script = if plan.style == PartitionStyle::MBR {
+        script.convert_mbr()
+    } else {
+        script.convert_gpt()
+    };
 
-    let _ = std::fs::remove_file(&script_path);
+    for (i, partition) in plan.partitions.iter().enumerate() {
+        let size_mb = Some(plan.size_bytes(partition) / MIB);
+        let offset_kb = Some(plan.offset_bytes(partition) / 1024);
+        let label = if partition.label.is_empty() {
+            "新加卷"
+        } else {
+            partition.label.as_str()
+        };
 
-    let output_text = gbk_to_utf8(&output.stdout);
-    let error_text = gbk_to_utf8(&output.stderr);
+        match partition.role {
+            PartitionRole::Esp => {
+                script = script
+                    .create_partition(CreatePartitionKind::Efi, size_mb, offset_kb)
+                    .format("fat32", Some(label));
+                created_partitions.push("ESP".to_string());
+            }
+            PartitionRole::Msr => {
+                script = script.create_partition(CreatePartitionKind::Msr, size_mb, offset_kb);
+                created_partitions.push("MSR".to_string());
+            }
+            PartitionRole::Recovery => {
+                script = script
+                    .create_partition(CreatePartitionKind::Primary, size_mb, offset_kb)
+                    .format(&partition.file_system, Some(label));
+                script = if plan.style == PartitionStyle::MBR {
+                    script.set_id("27")
+                } else {
+                    script
+                        .set_id("de94bba4-06d1-4d40-a16a-bfd50179d6ac")
+                        .gpt_attributes(0x8000000000000001)
+                };
+                created_partitions.push("恢复分区".to_string());
+            }
+            PartitionRole::SystemReserved | PartitionRole::Basic => {
+                script = script
+                    .create_partition(CreatePartitionKind::Primary, size_mb, offset_kb)
+                    .format(&partition.file_system, Some(label));
+                if partition.role == PartitionRole::SystemReserved {
+                    script = script.active();
+                }
 
-    log::info!("Diskpart 输出: {}", output_text);
-    if !error_text.is_empty() {
-        log::warn!("Diskpart 错误: {}", error_text);
+                // 分配盘符
+                if let Some(letter) = partition.drive_letter {
+                    script = script.assign(Some(letter));
+                    created_partitions.push(format!("{}:", letter));
+                } else if partition.role == PartitionRole::Basic {
+                    script = script.assign(None);
+                    created_partitions.push(format!("分区 {}", i + 1));
+                } else {
+                    created_partitions.push(partition.role.to_string());
+                }
+            }
+        }
     }
 
-    if !error_text.is_empty() && !output.status.success() {
-        anyhow::bail!("{}", error_text);
-    }
+    (script, created_partitions)
+}
 
-    Ok(output_text)
+/// 执行 diskpart 脚本，失败时返回带命令上下文的错误
+fn run_diskpart_script(script: DiskpartScript, context: &str) -> Result<String> {
+    Ok(script.run()?.ensure_success(context)?.output)
 }
 
 /// 检查磁盘是否可以安全分区（没有系统盘）
@@ -989,60 +1122,49 @@ pub fn create_single_partition(
     drive_letter: Option<char>,
     label: &str,
 ) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    
-    if size_mb > 0 {
-        script.push_str(&format!("create partition primary size={}\n", size_mb));
-    } else {
-        // 使用所有剩余空间
-        script.push_str("create partition primary\n");
-    }
-
+    // size_mb 为 0 时使用所有剩余空间
+    let size = if size_mb > 0 { Some(size_mb) } else { None };
     let vol_label = if label.is_empty() { "OS" } else { label };
-    script.push_str(&format!("format fs=ntfs quick label=\"{}\"\n", vol_label));
 
-    if let Some(letter) = drive_letter {
-        script.push_str(&format!("assign letter={}\n", letter));
-    } else {
-        script.push_str("assign\n");
-    }
+    let script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .create_partition(CreatePartitionKind::Primary, size, None)
+        .format("ntfs", Some(vol_label))
+        .assign(drive_letter);
 
-    execute_diskpart_script(&script)
+    run_diskpart_script(script, "创建分区失败")
 }
 
 /// 创建 ESP 分区
 pub fn create_esp_partition(disk_number: u32, size_mb: u64) -> Result<String> {
-    let mut script = String::new();
+    let script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .create_partition(CreatePartitionKind::Efi, Some(size_mb), None)
+        .format("fat32", Some("EFI"));
 
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("create partition efi size={}\n", size_mb));
-    script.push_str("format fs=fat32 quick label=\"EFI\"\n");
-
-    execute_diskpart_script(&script)
+    run_diskpart_script(script, "创建 ESP 分区失败")
 }
 
 /// 删除指定分区
 pub fn delete_partition(disk_number: u32, partition_number: u32) -> Result<String> {
-    let mut script = String::new();
+    backup_disk_number(disk_number, "删除分区")?;
 
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("select partition {}\n", partition_number));
-    script.push_str("delete partition override\n");
+    let script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .select_partition(partition_number)
+        .delete_partition(true);
 
-    execute_diskpart_script(&script)
+    run_diskpart_script(script, "删除分区失败")
 }
 
 /// 缩小分区
 pub fn shrink_partition(disk_number: u32, partition_number: u32, shrink_mb: u64) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("select partition {}\n", partition_number));
-    script.push_str(&format!("shrink desired={}\n", shrink_mb));
+    let script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .select_partition(partition_number)
+        .shrink(shrink_mb, None);
 
-    execute_diskpart_script(&script)
+    run_diskpart_script(script, "缩小分区失败")
 }
 
 /// 扩展分区
@@ -1051,19 +1173,13 @@ pub fn extend_partition(
     partition_number: u32,
     extend_mb: Option<u64>,
 ) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("select partition {}\n", partition_number));
-
-    if let Some(size) = extend_mb {
-        script.push_str(&format!("extend size={}\n", size));
-    } else {
-        // 使用所有可用空间
-        script.push_str("extend\n");
-    }
+    // extend_mb 为 None 时使用所有可用空间
+    let script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .select_partition(partition_number)
+        .extend(extend_mb);
 
-    execute_diskpart_script(&script)
+    run_diskpart_script(script, "扩展分区失败")
 }
 
 /// 调整已有分区大小的结果
@@ -1142,45 +1258,20 @@ pub fn resize_existing_partition(
         log::info!("缩小分区 {} MB", shrink_amount_mb);
 
         // 使用 diskpart shrink 命令
-        // 注意：diskpart 的 shrink 命令需要通过卷来选择，而不是分区
-        let result = if let Some(letter) = drive_letter {
-            // 通过盘符选择卷进行缩小
-            let mut script = String::new();
-            script.push_str(&format!("select volume {}\n", letter));
-            script.push_str(&format!("shrink desired={} minimum={}\n", shrink_amount_mb, shrink_amount_mb));
-            execute_diskpart_script(&script)
-        } else {
-            // 没有盘符的分区使用分区编号
-            let mut script = String::new();
-            script.push_str(&format!("select disk {}\n", disk_number));
-            script.push_str(&format!("select partition {}\n", partition_number));
-            script.push_str(&format!("shrink desired={} minimum={}\n", shrink_amount_mb, shrink_amount_mb));
-            execute_diskpart_script(&script)
-        };
-
-        match result {
-            Ok(output) => {
-                let output_lower = output.to_lowercase();
-                if output_lower.contains("错误")
-                    || output_lower.contains("error")
-                    || output_lower.contains("失败")
-                    || output_lower.contains("failed")
-                    || output_lower.contains("没有足够")
-                    || output_lower.contains("insufficient")
-                {
-                    ResizePartitionResult {
-                        success: false,
-                        message: format!("缩小分区失败: {}", output.trim()),
-                        new_size_mb: current_size_mb,
-                    }
-                } else {
-                    ResizePartitionResult {
-                        success: true,
-                        message: format!("分区已成功缩小 {} MB", shrink_amount_mb),
-                        new_size_mb,
-                    }
-                }
-            }
+        let script = select_target(disk_number, partition_number, drive_letter)
+            .shrink(shrink_amount_mb, Some(shrink_amount_mb));
+
+        match script.run() {
+            Ok(outcome) if outcome.is_success() => ResizePartitionResult {
+                success: true,
+                message: format!("分区已成功缩小 {} MB", shrink_amount_mb),
+                new_size_mb,
+            },
+            Ok(outcome) => ResizePartitionResult {
+                success: false,
+                message: format!("缩小分区失败: {}", outcome.error_message()),
+                new_size_mb: current_size_mb,
+            },
             Err(e) => ResizePartitionResult {
                 success: false,
                 message: format!("执行 diskpart 失败: {}", e),
@@ -1194,44 +1285,20 @@ pub fn resize_existing_partition(
         log::info!("扩大分区 {} MB", extend_amount_mb);
 
         // 使用 diskpart extend 命令
-        let result = if let Some(letter) = drive_letter {
-            // 通过盘符选择卷进行扩展
-            let mut script = String::new();
-            script.push_str(&format!("select volume {}\n", letter));
-            script.push_str(&format!("extend size={}\n", extend_amount_mb));
-            execute_diskpart_script(&script)
-        } else {
-            // 没有盘符的分区使用分区编号
-            let mut script = String::new();
-            script.push_str(&format!("select disk {}\n", disk_number));
-            script.push_str(&format!("select partition {}\n", partition_number));
-            script.push_str(&format!("extend size={}\n", extend_amount_mb));
-            execute_diskpart_script(&script)
-        };
-
-        match result {
-            Ok(output) => {
-                let output_lower = output.to_lowercase();
-                if output_lower.contains("错误")
-                    || output_lower.contains("error")
-                    || output_lower.contains("失败")
-                    || output_lower.contains("failed")
-                    || output_lower.contains("没有足够")
-                    || output_lower.contains("insufficient")
-                {
-                    ResizePartitionResult {
-                        success: false,
-                        message: format!("扩展分区失败: {}", output.trim()),
-                        new_size_mb: current_size_mb,
-                    }
-                } else {
-                    ResizePartitionResult {
-                        success: true,
-                        message: format!("分区已成功扩展 {} MB", extend_amount_mb),
-                        new_size_mb,
-                    }
-                }
-            }
+        let script = select_target(disk_number, partition_number, drive_letter)
+            .extend(Some(extend_amount_mb));
+
+        match script.run() {
+            Ok(outcome) if outcome.is_success() => ResizePartitionResult {
+                success: true,
+                message: format!("分区已成功扩展 {} MB", extend_amount_mb),
+                new_size_mb,
+            },
+            Ok(outcome) => ResizePartitionResult {
+                success: false,
+                message: format!("扩展分区失败: {}", outcome.error_message()),
+                new_size_mb: current_size_mb,
+            },
             Err(e) => ResizePartitionResult {
                 success: false,
                 message: format!("执行 diskpart 失败: {}", e),
@@ -1241,44 +1308,34 @@ pub fn resize_existing_partition(
     }
 }
 
+/// 选择要调整的分区
+///
+/// diskpart 的 shrink 命令需要通过卷来选择；有盘符时按盘符选择卷，
+/// 没有盘符的分区使用磁盘号和分区编号
+fn select_target(disk_number: u32, partition_number: u32, drive_letter: Option<char>) -> DiskpartScript {
+    match drive_letter {
+        Some(letter) => DiskpartScript::new().select_volume(letter),
+        None => DiskpartScript::new()
+            .select_disk(disk_number)
+            .select_partition(partition_number),
+    }
+}
+
 /// 查询分区可缩小的最大空间（MB）
 /// 
 /// 使用 diskpart 的 shrink querymax 命令获取
 pub fn query_shrink_max(drive_letter: char) -> Result<u64> {
-    let mut script = String::new();
-    script.push_str(&format!("select volume {}\n", drive_letter));
-    script.push_str("shrink querymax\n");
-
-    let output = execute_diskpart_script(&script)?;
-    
-    // 解析输出，查找可缩小的最大值
-    // 输出格式通常为 "可回收的最大字节数:  XXX MB" 或 "The maximum number of reclaimable bytes is: XXX MB"
-    for line in output.lines() {
-        let line_lower = line.to_lowercase();
-        if line_lower.contains("可回收") || line_lower.contains("reclaimable") || line_lower.contains("maximum") {
-            // 尝试提取数字
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            for (i, part) in parts.iter().enumerate() {
-                if let Ok(num) = part.replace(",", "").replace(".", "").parse::<u64>() {
-                    // 检查下一个部分是否是 MB 或 GB
-                    if i + 1 < parts.len() {
-                        let unit = parts[i + 1].to_uppercase();
-                        if unit.starts_with("MB") || unit.starts_with("M") {
-                            return Ok(num);
-                        } else if unit.starts_with("GB") || unit.starts_with("G") {
-                            return Ok(num * 1024);
-                        }
-                    }
-                    // 如果没有单位，假设是 MB
-                    return Ok(num);
-                }
-            }
-        }
-    }
+    let outcome = DiskpartScript::new()
+        .select_volume(drive_letter)
+        .shrink_querymax()
+        .run()?
+        .ensure_success("查询可缩小空间失败")?;
 
     // 如果无法解析，返回0
-    log::warn!("无法解析 shrink querymax 输出: {}", output);
-    Ok(0)
+    Ok(outcome.shrink_querymax_mb().unwrap_or_else(|| {
+        log::warn!("无法解析 shrink querymax 输出: {}", outcome.output);
+        0
+    }))
 }
 
 /// 获取磁盘上指定分区后面的未分配空间大小（MB）
@@ -1413,6 +1470,59 @@ mod tests {
         assert!(letters.contains(&'C'));
     }
 
+    #[test]
+    fn test_physical_disk_from_image() {
+        use partition_table::{Gpt, GptEntry, GptHealth};
+
+        let dir = std::env::temp_dir().join(format!("letrecovery_qp_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join("disk.img");
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(256 * 1024 * 1024).unwrap();
+
+        let geometry = DiskGeometry::from_stream(&mut file, 512).unwrap();
+        let mut gpt = Gpt::new(&geometry).unwrap();
+        gpt.entries.push(GptEntry::new(partition_table::ESP_PARTITION_TYPE, 2048, 206_847, ""));
+        gpt.entries.push(GptEntry::new(partition_table::BASIC_DATA_PARTITION_TYPE, 206_848, 411_647, ""));
+        PartitionTable::Gpt(gpt, GptHealth::default())
+            .write(&mut file, &geometry)
+            .unwrap();
+        drop(file);
+
+        let disk = read_disk_image(&path).unwrap();
+        assert_eq!(disk.partition_style, PartitionStyle::GPT);
+        assert!(disk.is_initialized);
+        assert_eq!(disk.partitions.len(), 2);
+        assert!(disk.partitions[0].is_esp);
+        assert_eq!(disk.partitions[1].offset_bytes, 206_848 * 512);
+        assert_eq!(disk.partitions[1].size_bytes, 100 * 1024 * 1024);
+        assert_eq!(disk.unallocated_bytes, disk.size_bytes - 200 * 1024 * 1024);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_quick_partition_script_uses_exact_offsets() {
+        use super::super::layout_planner::LayoutPreset;
+
+        let geometry = DiskGeometry::new(512, 64 * 1024 * 1024 * 1024);
+        let plan = LayoutPlanner::plan_preset(geometry, LayoutPreset::WindowsUefi).unwrap();
+        let (script, created) = build_quick_partition_script(1, &plan);
+        let script = script.render();
+
+        assert!(script.starts_with("select disk 1\nclean\nconvert gpt\n"));
+        assert!(script.contains("create partition efi size=300 offset=1024\n"));
+        assert!(script.contains("create partition msr size=16 offset=308224\n"));
+        assert!(script.contains("set id=de94bba4-06d1-4d40-a16a-bfd50179d6ac\n"));
+        assert_eq!(created, vec!["ESP", "MSR", "分区 3", "恢复分区"]);
+    }
+
     #[test]
     fn test_get_next_available_drive_letter() {
         let used = vec!['C', 'D', 'E'];
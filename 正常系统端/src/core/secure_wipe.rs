@@ -0,0 +1,863 @@
+//! 安全擦除模块
+//!
+//! 格式化只重建文件系统结构，原有数据仍可被恢复软件读出。本模块逐扇区覆盖整块磁盘
+//! 或分区，也可以只擦除已挂载卷的剩余空间：
+//!
+//! - 单次写零、单次随机数据、DoD 5220.22-M 三次（0x00 / 0xFF / 随机）
+//!   和七次（ECE）覆盖
+//! - 随机数据由种子和偏移确定，校验时无需保存写入内容即可重新生成比对
+//! - 剩余空间擦除在卷上创建临时文件直到写满，再用小文件占满空闲的 MFT 记录，
+//!   完成后删除
+//! - 完成后生成证书式的擦除报告（文本文件）
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// 每次写入的大小
+const BLOCK_SIZE: usize = 1024 * 1024;
+/// 剩余空间擦除时单个临时文件的最大大小
+const FILL_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+/// 剩余空间擦除时用于占满 MFT 记录的小文件数量上限
+const MAX_SMALL_FILES: usize = 20_000;
+/// 小文件大小（小于 MFT 记录，数据驻留在记录内）
+const SMALL_FILE_SIZE: usize = 600;
+/// 抽样校验的间隔（块，2 的幂）
+const SAMPLE_INTERVAL: u64 = 64;
+/// 剩余空间擦除使用的临时目录名
+const FILL_DIR_NAME: &str = "LetRecovery_Wipe";
+
+/// 单次覆盖写入的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassPattern {
+    /// 固定字节
+    Fill(u8),
+    /// 伪随机数据
+    Random,
+}
+
+impl fmt::Display for PassPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassPattern::Fill(byte) => write!(f, "0x{:02X}", byte),
+            PassPattern::Random => write!(f, "随机数据"),
+        }
+    }
+}
+
+/// 擦除方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WipeMethod {
+    /// 单次写零
+    #[default]
+    Zero,
+    /// 单次随机数据
+    Random,
+    /// DoD 5220.22-M 三次覆盖
+    Dod3,
+    /// DoD 5220.22-M ECE 七次覆盖
+    Dod7,
+}
+
+impl WipeMethod {
+    /// 各次覆盖的内容
+    pub fn passes(&self) -> Vec<PassPattern> {
+        use PassPattern::*;
+        match self {
+            WipeMethod::Zero => vec![Fill(0x00)],
+            WipeMethod::Random => vec![Random],
+            WipeMethod::Dod3 => vec![Fill(0x00), Fill(0xFF), Random],
+            WipeMethod::Dod7 => vec![Fill(0x00), Fill(0xFF), Random, Fill(0x96), Fill(0x00), Fill(0xFF), Random],
+        }
+    }
+}
+
+impl fmt::Display for WipeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WipeMethod::Zero => write!(f, "单次写零"),
+            WipeMethod::Random => write!(f, "单次随机数据"),
+            WipeMethod::Dod3 => write!(f, "DoD 5220.22-M（3 次）"),
+            WipeMethod::Dod7 => write!(f, "DoD 5220.22-M ECE（7 次）"),
+        }
+    }
+}
+
+/// 擦除后的校验方式（读取最后一次写入的内容比对）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WipeVerify {
+    /// 不校验
+    Off,
+    /// 每 64 MiB 抽查 1 MiB，以及首尾
+    #[default]
+    Sample,
+    /// 全部读回比对
+    Full,
+}
+
+impl fmt::Display for WipeVerify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WipeVerify::Off => write!(f, "不校验"),
+            WipeVerify::Sample => write!(f, "抽样校验"),
+            WipeVerify::Full => write!(f, "完整校验"),
+        }
+    }
+}
+
+/// 擦除对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeTarget {
+    /// 整块物理磁盘（分区表一并清除）
+    Disk(u32),
+    /// 分区（文件系统一并清除）
+    Partition(char),
+    /// 已挂载卷的剩余空间（保留现有文件）
+    FreeSpace(char),
+}
+
+impl fmt::Display for WipeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WipeTarget::Disk(n) => write!(f, "磁盘 {}", n),
+            WipeTarget::Partition(letter) => write!(f, "分区 {}:", letter.to_ascii_uppercase()),
+            WipeTarget::FreeSpace(letter) => write!(f, "分区 {}: 的剩余空间", letter.to_ascii_uppercase()),
+        }
+    }
+}
+
+/// 擦除选项
+#[derive(Debug, Clone, Default)]
+pub struct WipeOptions {
+    pub method: WipeMethod,
+    pub verify: WipeVerify,
+}
+
+/// 单次覆盖的结果
+#[derive(Debug, Clone)]
+pub struct PassResult {
+    pub pattern: PassPattern,
+    pub bytes: u64,
+    pub secs: u64,
+}
+
+/// 擦除报告
+#[derive(Debug, Clone)]
+pub struct WipeReport {
+    pub target: String,
+    /// 磁盘型号或卷标
+    pub description: String,
+    /// 擦除的字节数（剩余空间擦除时为实际写入的临时文件大小）
+    pub size_bytes: u64,
+    pub method: WipeMethod,
+    pub verify: WipeVerify,
+    pub passes: Vec<PassResult>,
+    /// 校验读取的字节数
+    pub verified_bytes: u64,
+    /// 校验不一致的字节数
+    pub mismatched_bytes: u64,
+    /// 剩余空间擦除时创建的小文件数量
+    pub small_files: usize,
+    /// 随机数据种子
+    pub seed: u64,
+    pub started: chrono::DateTime<chrono::Local>,
+    pub finished: chrono::DateTime<chrono::Local>,
+    pub notes: Vec<String>,
+}
+
+impl WipeReport {
+    fn new(target: &str, description: &str, options: &WipeOptions, seed: u64) -> Self {
+        let now = chrono::Local::now();
+        Self {
+            target: target.to_string(),
+            description: description.to_string(),
+            size_bytes: 0,
+            method: options.method,
+            verify: options.verify,
+            passes: Vec::new(),
+            verified_bytes: 0,
+            mismatched_bytes: 0,
+            small_files: 0,
+            seed,
+            started: now,
+            finished: now,
+            notes: Vec::new(),
+        }
+    }
+
+    /// 是否全部覆盖且校验通过
+    pub fn success(&self) -> bool {
+        self.passes.len() == self.method.passes().len() && self.mismatched_bytes == 0
+    }
+
+    /// 证书编号（由完成时间和种子生成）
+    pub fn certificate_id(&self) -> String {
+        format!("LR-WIPE-{}-{:04X}", self.finished.format("%Y%m%d-%H%M%S"), self.seed & 0xFFFF)
+    }
+
+    /// 摘要
+    pub fn summary(&self) -> String {
+        format!(
+            "{}：{}，写入 {:.2} GB，{}",
+            self.target,
+            self.method,
+            self.passes.iter().map(|p| p.bytes).sum::<u64>() as f64 / 1024.0 / 1024.0 / 1024.0,
+            self.verify_text()
+        )
+    }
+
+    fn verify_text(&self) -> String {
+        match self.verify {
+            WipeVerify::Off => "未校验".to_string(),
+            _ if self.mismatched_bytes > 0 => format!(
+                "{}失败：{} 字节与写入内容不一致",
+                self.verify, self.mismatched_bytes
+            ),
+            _ => format!("{}通过（读取 {} 字节）", self.verify, self.verified_bytes),
+        }
+    }
+
+    /// 生成证书文本
+    pub fn certificate(&self) -> String {
+        let line = "=".repeat(56);
+        let mut text = String::new();
+        text.push_str(&format!("{}\n{:^50}\n{}\n\n", line, "数据擦除证书", line));
+        text.push_str(&format!("证书编号:   {}\n", self.certificate_id()));
+        text.push_str(&format!("计算机名:   {}\n", std::env::var("COMPUTERNAME").unwrap_or_default()));
+        text.push_str(&format!("擦除对象:   {}\n", self.target));
+        if !self.description.is_empty() {
+            text.push_str(&format!("设备描述:   {}\n", self.description));
+        }
+        text.push_str(&format!(
+            "擦除容量:   {} 字节（{:.2} GB）\n",
+            self.size_bytes,
+            self.size_bytes as f64 / 1024.0 / 1024.0 / 1024.0
+        ));
+        text.push_str(&format!("擦除方式:   {}\n", self.method));
+        for (i, pass) in self.passes.iter().enumerate() {
+            text.push_str(&format!(
+                "  第 {} 次:  {}，{} 字节，用时 {} 秒\n",
+                i + 1,
+                pass.pattern,
+                pass.bytes,
+                pass.secs
+            ));
+        }
+        if self.small_files > 0 {
+            text.push_str(&format!("MFT 记录:   已用 {} 个小文件覆盖空闲记录\n", self.small_files));
+        }
+        text.push_str(&format!("校验结果:   {}\n", self.verify_text()));
+        text.push_str(&format!("开始时间:   {}\n", self.started.format("%Y-%m-%d %H:%M:%S")));
+        text.push_str(&format!("完成时间:   {}\n", self.finished.format("%Y-%m-%d %H:%M:%S")));
+        text.push_str(&format!(
+            "擦除结果:   {}\n",
+            if self.success() { "成功" } else { "失败" }
+        ));
+        if !self.notes.is_empty() {
+            text.push_str("\n备注:\n");
+            for note in &self.notes {
+                text.push_str(&format!("  - {}\n", note));
+            }
+        }
+        text.push_str(&format!("\n{}\n由 LetRecovery 生成\n", line));
+        text
+    }
+
+    /// 保存证书到目录，返回文件路径
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).with_context(|| format!("创建目录 {} 失败", dir.display()))?;
+        let path = dir.join(format!("{}.txt", self.certificate_id()));
+        std::fs::write(&path, self.certificate()).with_context(|| format!("保存 {} 失败", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// 擦除报告目录（程序目录下的 `wipe_reports`）
+pub fn reports_dir() -> PathBuf {
+    crate::utils::path::get_exe_dir().join("wipe_reports")
+}
+
+/// 新的随机种子
+fn new_seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    splitmix64(nanos ^ ((std::process::id() as u64) << 32))
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// 生成 `offset` 处的覆盖内容（随机数据只由种子、遍数和偏移决定）
+fn fill_pattern(pattern: PassPattern, seed: u64, pass: usize, offset: u64, buf: &mut [u8]) {
+    match pattern {
+        PassPattern::Fill(byte) => buf.fill(byte),
+        PassPattern::Random => {
+            let key = seed ^ (pass as u64).wrapping_mul(0xA24B_AED4_963E_E407);
+            // offset 按 8 字节对齐调用
+            let first_word = offset / 8;
+            for (i, chunk) in buf.chunks_mut(8).enumerate() {
+                let word = splitmix64(key ^ (first_word + i as u64)).to_le_bytes();
+                chunk.copy_from_slice(&word[..chunk.len()]);
+            }
+        }
+    }
+}
+
+/// 是否需要校验第 `block` 块
+fn should_verify(verify: WipeVerify, block: u64, blocks: u64) -> bool {
+    match verify {
+        WipeVerify::Off => false,
+        WipeVerify::Sample => block & (SAMPLE_INTERVAL - 1) == 0 || block + 1 == blocks,
+        WipeVerify::Full => true,
+    }
+}
+
+fn check_cancel(cancel: &AtomicBool) -> Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        bail!("用户已取消操作");
+    }
+    Ok(())
+}
+
+/// 擦除进度（按写入和校验的总字节数折算百分比）
+struct WipeProgress<'a> {
+    done: u64,
+    total: u64,
+    last_percent: u8,
+    progress: &'a mut dyn FnMut(u8, &str),
+}
+
+impl<'a> WipeProgress<'a> {
+    fn new(total: u64, progress: &'a mut dyn FnMut(u8, &str)) -> Self {
+        Self { done: 0, total: total.max(1), last_percent: 0, progress }
+    }
+
+    fn percent(&self) -> u8 {
+        (self.done.min(self.total) * 99 / self.total) as u8
+    }
+
+    fn status(&mut self, message: &str) {
+        let percent = self.percent();
+        (self.progress)(percent, message);
+    }
+
+    fn advance(&mut self, bytes: u64, message: &str) {
+        self.done += bytes;
+        let percent = self.percent();
+        if percent != self.last_percent {
+            self.last_percent = percent;
+            (self.progress)(percent, message);
+        }
+    }
+}
+
+/// 对设备（或任意可读写的流）执行擦除
+pub fn wipe_stream<D: Read + Write + Seek>(
+    dev: &mut D,
+    size: u64,
+    target: &str,
+    description: &str,
+    options: &WipeOptions,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<WipeReport> {
+    let passes = options.method.passes();
+    let mut report = WipeReport::new(target, description, options, new_seed());
+    report.size_bytes = size;
+
+    let blocks = size.div_ceil(BLOCK_SIZE as u64);
+    let verify_bytes = match options.verify {
+        WipeVerify::Off => 0,
+        WipeVerify::Sample => (blocks / SAMPLE_INTERVAL + 2) * BLOCK_SIZE as u64,
+        WipeVerify::Full => size,
+    };
+    let mut tracker = WipeProgress::new(size * passes.len() as u64 + verify_bytes, progress);
+    let mut buf = vec![0u8; BLOCK_SIZE];
+
+    for (pass, &pattern) in passes.iter().enumerate() {
+        let status = format!("第 {}/{} 次覆盖（{}）", pass + 1, passes.len(), pattern);
+        tracker.status(&status);
+        let start = Instant::now();
+        dev.seek(SeekFrom::Start(0))?;
+        let mut offset = 0u64;
+        while offset < size {
+            check_cancel(cancel)?;
+            let len = (BLOCK_SIZE as u64).min(size - offset) as usize;
+            fill_pattern(pattern, report.seed, pass, offset, &mut buf[..len]);
+            dev.write_all(&buf[..len]).with_context(|| format!("写入偏移 {} 失败", offset))?;
+            offset += len as u64;
+            tracker.advance(len as u64, &status);
+        }
+        dev.flush()?;
+        report.passes.push(PassResult { pattern, bytes: size, secs: start.elapsed().as_secs() });
+        log::info!("{} 第 {} 次覆盖完成", target, pass + 1);
+    }
+
+    if options.verify != WipeVerify::Off {
+        let last = passes.len() - 1;
+        let status = format!("正在{}...", options.verify);
+        tracker.status(&status);
+        let mut expected = vec![0u8; BLOCK_SIZE];
+        for block in 0..blocks {
+            if !should_verify(options.verify, block, blocks) {
+                continue;
+            }
+            check_cancel(cancel)?;
+            let offset = block * BLOCK_SIZE as u64;
+            let len = (BLOCK_SIZE as u64).min(size - offset) as usize;
+            dev.seek(SeekFrom::Start(offset))?;
+            dev.read_exact(&mut buf[..len]).with_context(|| format!("校验读取偏移 {} 失败", offset))?;
+            fill_pattern(passes[last], report.seed, last, offset, &mut expected[..len]);
+            report.verified_bytes += len as u64;
+            report.mismatched_bytes += count_mismatch(&buf[..len], &expected[..len]);
+            tracker.advance(len as u64, &status);
+        }
+    }
+
+    report.finished = chrono::Local::now();
+    progress_done(&mut tracker, &report);
+    Ok(report)
+}
+
+fn count_mismatch(actual: &[u8], expected: &[u8]) -> u64 {
+    actual.iter().zip(expected).filter(|(a, b)| a != b).count() as u64
+}
+
+fn progress_done(tracker: &mut WipeProgress, report: &WipeReport) {
+    (tracker.progress)(100, &format!("擦除完成：{}", report.verify_text()));
+}
+
+/// 写入时磁盘已满
+fn is_disk_full(e: &io::Error) -> bool {
+    // ERROR_DISK_FULL / ERROR_HANDLE_DISK_FULL / ENOSPC
+    e.kind() == io::ErrorKind::StorageFull || matches!(e.raw_os_error(), Some(112) | Some(39) | Some(28))
+}
+
+/// 擦除卷的剩余空间
+///
+/// 在 `root` 下创建临时目录，用临时文件写满 `free_bytes`（或写到磁盘已满），
+/// 后续各次覆盖原位重写同样的文件，最后用小文件占满空闲的 MFT 记录，
+/// 校验后删除全部临时文件。
+pub fn wipe_free_space(
+    root: &Path,
+    free_bytes: u64,
+    target: &str,
+    options: &WipeOptions,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<WipeReport> {
+    let dir = root.join(FILL_DIR_NAME);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).with_context(|| format!("创建临时目录 {} 失败", dir.display()))?;
+    let result = fill_free_space(&dir, free_bytes, target, options, cancel, progress);
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        log::warn!("删除临时目录 {} 失败: {}", dir.display(), e);
+    }
+    result
+}
+
+fn fill_free_space(
+    dir: &Path,
+    free_bytes: u64,
+    target: &str,
+    options: &WipeOptions,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<WipeReport> {
+    let passes = options.method.passes();
+    let mut report = WipeReport::new(target, "", options, new_seed());
+    let verify_bytes = match options.verify {
+        WipeVerify::Off => 0,
+        WipeVerify::Sample => free_bytes / SAMPLE_INTERVAL,
+        WipeVerify::Full => free_bytes,
+    };
+    let mut tracker = WipeProgress::new(free_bytes * passes.len() as u64 + verify_bytes, progress);
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    // 各临时文件的实际大小
+    let mut files: Vec<(PathBuf, u64)> = Vec::new();
+
+    for (pass, &pattern) in passes.iter().enumerate() {
+        let status = format!("第 {}/{} 次覆盖剩余空间（{}）", pass + 1, passes.len(), pattern);
+        tracker.status(&status);
+        let start = Instant::now();
+        let mut written = 0u64;
+
+        if pass == 0 {
+            // 第一次：创建文件直到写满
+            let mut full = false;
+            while !full && written < free_bytes {
+                let path = dir.join(format!("fill_{:05}.tmp", files.len()));
+                let mut file = File::create(&path).with_context(|| format!("创建 {} 失败", path.display()))?;
+                let mut file_len = 0u64;
+                while file_len < FILL_FILE_SIZE && written < free_bytes {
+                    check_cancel(cancel)?;
+                    let len = (BLOCK_SIZE as u64).min(free_bytes - written) as usize;
+                    fill_pattern(pattern, report.seed, pass, written, &mut buf[..len]);
+                    match file.write_all(&buf[..len]) {
+                        Ok(()) => {}
+                        Err(e) if is_disk_full(&e) => {
+                            full = true;
+                            break;
+                        }
+                        Err(e) => return Err(e).with_context(|| format!("写入 {} 失败", path.display())),
+                    }
+                    file_len += len as u64;
+                    written += len as u64;
+                    tracker.advance(len as u64, &status);
+                }
+                // 写满时最后一块可能只写入了一部分，截掉以便后续原位重写和校验
+                file.set_len(file_len).ok();
+                file.sync_all().ok();
+                files.push((path, file_len));
+            }
+        } else {
+            // 之后各次：原位重写同样的文件
+            let mut stream_offset = 0u64;
+            for (path, len) in &files {
+                let mut file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(path)
+                    .with_context(|| format!("打开 {} 失败", path.display()))?;
+                let mut pos = 0u64;
+                while pos < *len {
+                    check_cancel(cancel)?;
+                    let chunk = (BLOCK_SIZE as u64).min(len - pos) as usize;
+                    fill_pattern(pattern, report.seed, pass, stream_offset + pos, &mut buf[..chunk]);
+                    file.write_all(&buf[..chunk]).with_context(|| format!("写入 {} 失败", path.display()))?;
+                    pos += chunk as u64;
+                    tracker.advance(chunk as u64, &status);
+                }
+                file.sync_all()?;
+                stream_offset += len;
+                written += len;
+            }
+        }
+
+        report.passes.push(PassResult { pattern, bytes: written, secs: start.elapsed().as_secs() });
+    }
+    report.size_bytes = files.iter().map(|(_, len)| len).sum();
+
+    // 数据区写满后，小文件会驻留在空闲的 MFT 记录中，覆盖已删除文件留下的记录内容
+    tracker.status("正在覆盖空闲的 MFT 记录...");
+    let small = vec![0u8; SMALL_FILE_SIZE];
+    let small_dir = dir.join("mft");
+    if std::fs::create_dir(&small_dir).is_ok() {
+        while report.small_files < MAX_SMALL_FILES {
+            check_cancel(cancel)?;
+            let path = small_dir.join(format!("{:05}", report.small_files));
+            if std::fs::write(&path, &small).is_err() {
+                break;
+            }
+            report.small_files += 1;
+        }
+    }
+
+    if options.verify != WipeVerify::Off {
+        let last = passes.len() - 1;
+        let status = format!("正在{}...", options.verify);
+        tracker.status(&status);
+        let mut expected = vec![0u8; BLOCK_SIZE];
+        let mut stream_offset = 0u64;
+        let mut block = 0u64;
+        let blocks = report.size_bytes.div_ceil(BLOCK_SIZE as u64);
+        for (path, len) in &files {
+            let mut file = File::open(path).with_context(|| format!("打开 {} 失败", path.display()))?;
+            let mut pos = 0u64;
+            while pos < *len {
+                let chunk = (BLOCK_SIZE as u64).min(len - pos) as usize;
+                if should_verify(options.verify, block, blocks) {
+                    check_cancel(cancel)?;
+                    file.seek(SeekFrom::Start(pos))?;
+                    file.read_exact(&mut buf[..chunk])?;
+                    fill_pattern(passes[last], report.seed, last, stream_offset + pos, &mut expected[..chunk]);
+                    report.verified_bytes += chunk as u64;
+                    report.mismatched_bytes += count_mismatch(&buf[..chunk], &expected[..chunk]);
+                    tracker.advance(chunk as u64, &status);
+                }
+                pos += chunk as u64;
+                block += 1;
+            }
+            stream_offset += len;
+        }
+    }
+
+    report.notes.push("仅擦除剩余空间，现有文件未受影响".to_string());
+    report.finished = chrono::Local::now();
+    progress_done(&mut tracker, &report);
+    Ok(report)
+}
+
+/// 执行擦除并保存报告，返回报告和报告文件路径
+///
+/// 整盘擦除前先清除分区表（避免已挂载的卷阻止写入），分区擦除前锁定并卸载卷。
+/// 当前系统盘和 LetRecovery 所在的分区（及其所在磁盘）拒绝擦除。
+pub fn wipe_target(
+    target: WipeTarget,
+    description: &str,
+    options: &WipeOptions,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<(WipeReport, PathBuf)> {
+    let target_name = target.to_string();
+    log::info!("开始擦除 {}（{}）", target_name, options.method);
+
+    let mut report = match target {
+        WipeTarget::FreeSpace(letter) => {
+            ensure_not_protected_volume(letter)?;
+            let root = PathBuf::from(format!("{}:\\", letter));
+            let free_bytes = super::disk::DiskManager::get_free_space_bytes(&format!("{}:", letter))
+                .with_context(|| format!("获取 {}: 剩余空间失败", letter))?;
+            wipe_free_space(&root, free_bytes, &target_name, options, cancel, progress)?
+        }
+        WipeTarget::Disk(disk_number) => {
+            ensure_not_system_disk(disk_number)?;
+            progress(0, "正在清除分区表...");
+            super::diskpart::DiskpartScript::new()
+                .select_disk(disk_number)
+                .clean()
+                .rescan()
+                .run()?
+                .ensure_success("清除分区表失败")?;
+            let mut dev = super::raw_image::open_device(&super::raw_image::disk_device_path(disk_number), true)?;
+            let size = dev.size;
+            let mut report = wipe_stream(&mut dev.file, size, &target_name, description, options, cancel, progress)?;
+            report.notes.push("分区表已清除，磁盘需要重新初始化".to_string());
+            report
+        }
+        WipeTarget::Partition(letter) => {
+            ensure_not_protected_volume(letter)?;
+            let mut dev = super::raw_image::open_device(&super::raw_image::volume_device_path(letter), true)?;
+            #[cfg(windows)]
+            {
+                super::raw_image::lock_volume(&dev.file, true)
+                    .with_context(|| format!("锁定 {}: 失败，请关闭正在使用该分区的程序", letter))?;
+            }
+            let size = dev.size;
+            let mut report = wipe_stream(&mut dev.file, size, &target_name, description, options, cancel, progress)?;
+            report.notes.push("分区文件系统已清除，需要重新格式化后使用".to_string());
+            report
+        }
+    };
+
+    if matches!(target, WipeTarget::Disk(_) | WipeTarget::Partition(_)) {
+        report
+            .notes
+            .push("固态硬盘存在预留空间和磨损均衡，覆盖写入无法保证擦除全部闪存单元，建议配合厂商安全擦除工具".to_string());
+    }
+    let path = report.save(&reports_dir())?;
+    log::info!("{}，报告已保存到 {}", report.summary(), path.display());
+    Ok((report, path))
+}
+
+/// 拒绝擦除当前系统或 LetRecovery 所在的磁盘
+fn ensure_not_system_disk(disk_number: u32) -> Result<()> {
+    let (system_drive, running_from) = protected_volumes();
+    let disk = super::quick_partition::get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .with_context(|| format!("找不到磁盘 {}", disk_number))?;
+    for letter in disk.partitions.iter().filter_map(|p| p.drive_letter) {
+        if let Some(reason) = protected_reason(letter, system_drive, running_from) {
+            bail!("磁盘 {} 包含{} {}:，无法擦除", disk_number, reason, letter);
+        }
+    }
+    Ok(())
+}
+
+/// 拒绝擦除当前系统盘和 LetRecovery 所在的分区（包括剩余空间擦除）
+fn ensure_not_protected_volume(letter: char) -> Result<()> {
+    let (system_drive, running_from) = protected_volumes();
+    if let Some(reason) = protected_reason(letter, system_drive, running_from) {
+        bail!("{}: 是{}，无法擦除", letter.to_ascii_uppercase(), reason);
+    }
+    Ok(())
+}
+
+/// 当前系统盘和 LetRecovery 所在分区的盘符
+fn protected_volumes() -> (char, Option<char>) {
+    let system_drive = std::env::var("SystemDrive")
+        .ok()
+        .and_then(|s| s.chars().next())
+        .unwrap_or('C');
+    let running_from = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.to_str().map(str::to_string))
+        .filter(|exe| exe.get(1..2) == Some(":"))
+        .and_then(|exe| exe.chars().next());
+    (system_drive, running_from)
+}
+
+/// 盘符受保护的原因，不受保护时返回 `None`
+fn protected_reason(letter: char, system_drive: char, running_from: Option<char>) -> Option<&'static str> {
+    if letter.eq_ignore_ascii_case(&system_drive) {
+        Some("当前系统盘")
+    } else if running_from.is_some_and(|l| l.eq_ignore_ascii_case(&letter)) {
+        Some("LetRecovery 所在的分区")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("letrecovery_wipe_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_random_pattern_is_offset_addressable() {
+        let mut whole = vec![0u8; 4096];
+        fill_pattern(PassPattern::Random, 42, 2, 0, &mut whole);
+        let mut part = vec![0u8; 1024];
+        fill_pattern(PassPattern::Random, 42, 2, 2048, &mut part);
+        assert_eq!(&whole[2048..3072], &part[..]);
+
+        // 不同遍数、不同种子的内容不同
+        let mut other = vec![0u8; 4096];
+        fill_pattern(PassPattern::Random, 42, 6, 0, &mut other);
+        assert_ne!(whole, other);
+        fill_pattern(PassPattern::Random, 43, 2, 0, &mut other);
+        assert_ne!(whole, other);
+        assert!(whole.iter().filter(|&&b| b == 0).count() < 64);
+    }
+
+    #[test]
+    fn test_wipe_stream_dod() {
+        let size = 3 * BLOCK_SIZE as u64 + 4096;
+        let mut dev = Cursor::new(vec![0x5Au8; size as usize]);
+        let options = WipeOptions { method: WipeMethod::Dod3, verify: WipeVerify::Full };
+        let mut percents = Vec::new();
+        let report = wipe_stream(&mut dev, size, "磁盘 9", "Test Disk", &options, &AtomicBool::new(false), &mut |p, _| {
+            percents.push(p)
+        })
+        .unwrap();
+
+        assert!(report.success());
+        assert_eq!(report.passes.len(), 3);
+        assert_eq!(report.verified_bytes, size);
+        assert_eq!(*percents.last().unwrap(), 100);
+        assert!(percents.windows(2).all(|w| w[0] <= w[1]));
+
+        // 最后一次为随机数据
+        let data = dev.into_inner();
+        assert!(!data.contains(&0x5A) || data.iter().filter(|&&b| b == 0x5A).count() < data.len() / 100);
+        let mut expected = vec![0u8; data.len()];
+        fill_pattern(PassPattern::Random, report.seed, 2, 0, &mut expected);
+        assert_eq!(data, expected);
+
+        let certificate = report.certificate();
+        assert!(certificate.contains("数据擦除证书"));
+        assert!(certificate.contains("DoD 5220.22-M（3 次）"));
+        assert!(certificate.contains("第 3 次:  随机数据"));
+        assert!(certificate.contains("擦除结果:   成功"));
+    }
+
+    /// 写入被截断的设备：校验应能发现不一致
+    struct StuckDevice {
+        inner: Cursor<Vec<u8>>,
+    }
+
+    impl Read for StuckDevice {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for StuckDevice {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            // 第二块开始的 512 字节写不进去
+            let pos = self.inner.position();
+            let stuck = BLOCK_SIZE as u64..BLOCK_SIZE as u64 + 512;
+            let n = self.inner.write(buf)?;
+            for offset in pos..pos + n as u64 {
+                if stuck.contains(&offset) {
+                    self.inner.get_mut()[offset as usize] = 0xEE;
+                }
+            }
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for StuckDevice {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_mismatch_and_cancel() {
+        let size = 2 * BLOCK_SIZE as u64;
+        let mut dev = StuckDevice { inner: Cursor::new(vec![0u8; size as usize]) };
+        let options = WipeOptions { method: WipeMethod::Zero, verify: WipeVerify::Sample };
+        let report = wipe_stream(&mut dev, size, "分区 E:", "", &options, &AtomicBool::new(false), &mut |_, _| {}).unwrap();
+        assert_eq!(report.verified_bytes, size);
+        assert_eq!(report.mismatched_bytes, 512);
+        assert!(!report.success());
+        assert!(report.certificate().contains("擦除结果:   失败"));
+
+        let err = wipe_stream(&mut dev, size, "分区 E:", "", &options, &AtomicBool::new(true), &mut |_, _| {}).unwrap_err();
+        assert!(err.to_string().contains("取消"));
+    }
+
+    #[test]
+    fn test_protected_reason() {
+        assert_eq!(protected_reason('c', 'C', Some('E')), Some("当前系统盘"));
+        assert_eq!(protected_reason('E', 'C', Some('e')), Some("LetRecovery 所在的分区"));
+        assert_eq!(protected_reason('D', 'C', Some('E')), None);
+        assert_eq!(protected_reason('D', 'C', None), None);
+    }
+
+    #[test]
+    fn test_wipe_target_rejects_system_volume() {
+        let (system_drive, _) = protected_volumes();
+        let options = WipeOptions { method: WipeMethod::Zero, verify: WipeVerify::Off };
+        for target in [WipeTarget::Partition(system_drive), WipeTarget::FreeSpace(system_drive)] {
+            let error = wipe_target(target, "", &options, &AtomicBool::new(false), &mut |_, _| {}).unwrap_err();
+            assert!(error.to_string().contains("当前系统盘"), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn test_wipe_free_space() {
+        let root = temp_dir("free");
+        std::fs::write(root.join("keep.txt"), b"important").unwrap();
+        let free = 2 * BLOCK_SIZE as u64 + 1000;
+        let options = WipeOptions { method: WipeMethod::Dod3, verify: WipeVerify::Full };
+        let report = wipe_free_space(&root, free, "分区 D: 的剩余空间", &options, &AtomicBool::new(false), &mut |_, _| {}).unwrap();
+
+        assert!(report.success());
+        assert_eq!(report.size_bytes, free);
+        assert_eq!(report.verified_bytes, free);
+        assert!(report.passes.iter().all(|p| p.bytes == free));
+        assert!(report.small_files > 0);
+        // 临时文件已删除，原有文件保留
+        assert!(!root.join(FILL_DIR_NAME).exists());
+        assert_eq!(std::fs::read(root.join("keep.txt")).unwrap(), b"important");
+
+        let path = report.save(&root.join("reports")).unwrap();
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(text.contains(&report.certificate_id()));
+        assert!(text.contains("仅擦除剩余空间"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,812 @@
+//! 磁盘表面读取测试模块
+//!
+//! 很多安装失败其实是磁盘本身已经损坏，用户并不知情。本模块按 1 MiB 对齐的大块
+//! 顺序读取整块磁盘或单个分区：
+//!
+//! - 单次读取耗时超过阈值的区域记为"慢速"
+//! - 读取失败的块先按 64 KiB 再按扇区重读，精确定位不可读的扇区
+//! - 扫描结果按磁盘位置汇总到固定数量的热力格，供界面绘制扇区分布图
+//! - 结果以 JSON 保存在程序目录的 `surface_test` 下，可随时取消并在之后继续扫描
+//!
+//! 安装前通过 [`check_partition`] 查询已保存的结果，目标分区存在坏道时给出警告。
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// 默认读取块大小
+pub const DEFAULT_BLOCK_SIZE: u32 = 1024 * 1024;
+/// 默认慢速阈值（毫秒）
+pub const DEFAULT_SLOW_THRESHOLD_MS: u32 = 200;
+/// 热力图格数
+pub const HEAT_CELLS: usize = 256;
+/// 读取失败时的第一级重读粒度
+const PROBE_SIZE: u64 = 64 * 1024;
+/// 最多记录的区域数量（严重损坏的磁盘只保留前面的记录，统计仍然完整）
+const MAX_REGIONS: usize = 10_000;
+/// 扫描进度回调间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(300);
+/// 扫描过程中保存结果的间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 扫描对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SurfaceTarget {
+    /// 整块物理磁盘
+    Disk(u32),
+    /// 分区（盘符）
+    Partition(char),
+}
+
+impl SurfaceTarget {
+    /// 设备路径
+    pub fn device_path(&self) -> String {
+        match self {
+            SurfaceTarget::Disk(n) => super::raw_image::disk_device_path(*n),
+            SurfaceTarget::Partition(letter) => super::raw_image::volume_device_path(*letter),
+        }
+    }
+
+    /// 保存结果使用的文件名
+    pub fn file_name(&self) -> String {
+        match self {
+            SurfaceTarget::Disk(n) => format!("disk{}.json", n),
+            SurfaceTarget::Partition(letter) => format!("partition_{}.json", letter.to_ascii_uppercase()),
+        }
+    }
+}
+
+impl fmt::Display for SurfaceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurfaceTarget::Disk(n) => write!(f, "磁盘 {}", n),
+            SurfaceTarget::Partition(letter) => write!(f, "分区 {}:", letter.to_ascii_uppercase()),
+        }
+    }
+}
+
+/// 问题区域类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionKind {
+    /// 可以读取但耗时超过阈值
+    Slow,
+    /// 读取失败
+    Unreadable,
+}
+
+/// 扫描发现的问题区域（相对扫描对象起始位置的字节偏移）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SurfaceRegion {
+    pub kind: RegionKind,
+    pub offset: u64,
+    pub len: u64,
+    /// 区域内最慢一次读取的耗时（毫秒），不可读区域为 0
+    pub latency_ms: u32,
+}
+
+impl SurfaceRegion {
+    pub fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+
+    fn overlaps(&self, offset: u64, len: u64) -> bool {
+        self.offset < offset + len && offset < self.end()
+    }
+}
+
+/// 热力图中的一格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeatCell {
+    /// 已扫描的字节数
+    pub scanned_bytes: u64,
+    /// 最慢一次读取的耗时（毫秒）
+    pub max_latency_ms: u32,
+    /// 慢速读取次数
+    pub slow_reads: u32,
+    /// 不可读的扇区数
+    pub bad_sectors: u32,
+}
+
+/// 热力格的显示级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellLevel {
+    Unscanned,
+    Good,
+    /// 耗时超过阈值的一半
+    Fair,
+    Slow,
+    Bad,
+}
+
+/// 扫描选项
+#[derive(Debug, Clone)]
+pub struct SurfaceTestOptions {
+    /// 每次读取的大小（扇区大小的整数倍）
+    pub block_size: u32,
+    /// 慢速阈值（毫秒）
+    pub slow_threshold_ms: u32,
+}
+
+impl Default for SurfaceTestOptions {
+    fn default() -> Self {
+        Self {
+            block_size: DEFAULT_BLOCK_SIZE,
+            slow_threshold_ms: DEFAULT_SLOW_THRESHOLD_MS,
+        }
+    }
+}
+
+/// 整盘扫描时记录的分区位置，安装前据此把磁盘上的坏道对应到分区
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScannedPartition {
+    pub letter: Option<char>,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// 一次表面测试的状态和结果（可保存后继续）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceScan {
+    pub target: SurfaceTarget,
+    /// 磁盘型号或分区卷标
+    pub description: String,
+    pub size_bytes: u64,
+    pub sector_size: u32,
+    pub block_size: u32,
+    pub slow_threshold_ms: u32,
+    /// 下一次读取的位置（继续扫描时从这里开始）
+    pub position: u64,
+    pub completed: bool,
+    /// 已读取的字节数（包含重读）
+    pub scanned_bytes: u64,
+    /// 累计扫描耗时（秒）
+    pub elapsed_secs: u64,
+    /// 问题区域，按偏移排序
+    pub regions: Vec<SurfaceRegion>,
+    /// 因数量上限未记录的区域个数
+    pub dropped_regions: u64,
+    /// 不可读的字节总数
+    pub unreadable_bytes: u64,
+    /// 慢速读取次数
+    pub slow_reads: u64,
+    pub cells: Vec<HeatCell>,
+    /// 整盘扫描时的分区布局
+    #[serde(default)]
+    pub partitions: Vec<ScannedPartition>,
+    /// 开始时间（Unix 秒）
+    pub started: u64,
+    /// 最后更新时间（Unix 秒）
+    pub updated: u64,
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl SurfaceScan {
+    pub fn new(target: SurfaceTarget, description: &str, size_bytes: u64, sector_size: u32, options: &SurfaceTestOptions) -> Self {
+        let sector_size = sector_size.max(512);
+        // 块大小向下对齐到扇区
+        let block_size = (options.block_size / sector_size).max(1) * sector_size;
+        let now = unix_now();
+        Self {
+            target,
+            description: description.to_string(),
+            size_bytes,
+            sector_size,
+            block_size,
+            slow_threshold_ms: options.slow_threshold_ms.max(1),
+            position: 0,
+            completed: false,
+            scanned_bytes: 0,
+            elapsed_secs: 0,
+            regions: Vec::new(),
+            dropped_regions: 0,
+            unreadable_bytes: 0,
+            slow_reads: 0,
+            cells: vec![HeatCell::default(); HEAT_CELLS],
+            partitions: Vec::new(),
+            started: now,
+            updated: now,
+        }
+    }
+
+    /// 扫描进度（0-100）
+    pub fn percent(&self) -> u8 {
+        if self.size_bytes == 0 {
+            return 100;
+        }
+        (self.position.min(self.size_bytes) as u128 * 100 / self.size_bytes as u128) as u8
+    }
+
+    /// 平均读取速度（字节/秒）
+    pub fn speed(&self) -> u64 {
+        self.scanned_bytes / self.elapsed_secs.max(1)
+    }
+
+    /// 是否可以在同样大小的设备上继续扫描
+    pub fn can_resume(&self, size_bytes: u64) -> bool {
+        !self.completed && self.position > 0 && self.position < self.size_bytes && self.size_bytes == size_bytes
+    }
+
+    /// 不可读的区域数量
+    pub fn unreadable_count(&self) -> usize {
+        self.regions.iter().filter(|r| r.kind == RegionKind::Unreadable).count()
+    }
+
+    /// 与指定范围重叠的问题区域
+    pub fn regions_in(&self, offset: u64, len: u64) -> impl Iterator<Item = &SurfaceRegion> {
+        self.regions.iter().filter(move |r| r.overlaps(offset, len))
+    }
+
+    /// 热力格对应的字节范围
+    pub fn cell_range(&self, index: usize) -> (u64, u64) {
+        let cells = self.cells.len().max(1) as u128;
+        let start = (self.size_bytes as u128 * index as u128 / cells) as u64;
+        let end = (self.size_bytes as u128 * (index as u128 + 1) / cells) as u64;
+        (start, end)
+    }
+
+    /// 热力格的显示级别
+    pub fn cell_level(&self, index: usize) -> CellLevel {
+        let Some(cell) = self.cells.get(index) else {
+            return CellLevel::Unscanned;
+        };
+        if cell.bad_sectors > 0 {
+            CellLevel::Bad
+        } else if cell.slow_reads > 0 {
+            CellLevel::Slow
+        } else if cell.scanned_bytes == 0 {
+            CellLevel::Unscanned
+        } else if cell.max_latency_ms * 2 >= self.slow_threshold_ms {
+            CellLevel::Fair
+        } else {
+            CellLevel::Good
+        }
+    }
+
+    /// 结果摘要
+    pub fn summary(&self) -> String {
+        let state = if self.completed {
+            "已完成".to_string()
+        } else {
+            format!("已扫描 {}%", self.percent())
+        };
+        format!(
+            "{}（{}）：不可读 {} 处共 {} 个扇区，慢速读取 {} 次，平均速度 {:.1} MB/s",
+            self.target,
+            state,
+            self.unreadable_count() as u64 + self.dropped_regions,
+            self.unreadable_bytes / self.sector_size.max(1) as u64,
+            self.slow_reads,
+            self.speed() as f64 / 1024.0 / 1024.0
+        )
+    }
+
+    /// 保存到结果目录
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir).with_context(|| format!("创建目录 {} 失败", dir.display()))?;
+        let path = dir.join(self.target.file_name());
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("保存 {} 失败", path.display()))
+    }
+
+    /// 读取已保存的结果
+    pub fn load(dir: &Path, target: SurfaceTarget) -> Option<Self> {
+        let data = std::fs::read_to_string(dir.join(target.file_name())).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// 把一次读取的结果计入热力格
+    fn update_cells(&mut self, offset: u64, len: u64, latency_ms: u32, slow: bool, bad_sectors: u64) {
+        if self.size_bytes == 0 || len == 0 {
+            return;
+        }
+        let cells = self.cells.len() as u128;
+        let first = (offset as u128 * cells / self.size_bytes as u128) as usize;
+        let last = (((offset + len - 1) as u128 * cells / self.size_bytes as u128) as usize).min(self.cells.len() - 1);
+        for index in first..=last {
+            let (start, end) = self.cell_range(index);
+            let overlap = (offset + len).min(end).saturating_sub(offset.max(start));
+            let cell = &mut self.cells[index];
+            cell.scanned_bytes += overlap;
+            if bad_sectors > 0 {
+                // 坏扇区计入其起始位置所在的格
+                if index == first {
+                    cell.bad_sectors = cell.bad_sectors.saturating_add(bad_sectors as u32);
+                }
+            } else {
+                cell.max_latency_ms = cell.max_latency_ms.max(latency_ms);
+                if slow && index == first {
+                    cell.slow_reads += 1;
+                }
+            }
+        }
+    }
+
+    /// 记录问题区域，与前一个同类区域相接时合并
+    fn push_region(&mut self, kind: RegionKind, offset: u64, len: u64, latency_ms: u32) {
+        if let Some(last) = self.regions.last_mut() {
+            if last.kind == kind && last.end() >= offset {
+                last.len = last.len.max(offset + len - last.offset);
+                last.latency_ms = last.latency_ms.max(latency_ms);
+                return;
+            }
+        }
+        if self.regions.len() >= MAX_REGIONS {
+            self.dropped_regions += 1;
+            return;
+        }
+        self.regions.push(SurfaceRegion { kind, offset, len, latency_ms });
+    }
+
+    fn record_read(&mut self, offset: u64, len: u64, latency_ms: u32) {
+        let slow = latency_ms >= self.slow_threshold_ms;
+        if slow {
+            self.slow_reads += 1;
+            self.push_region(RegionKind::Slow, offset, len, latency_ms);
+        }
+        self.scanned_bytes += len;
+        self.update_cells(offset, len, latency_ms, slow, 0);
+    }
+
+    fn record_unreadable(&mut self, offset: u64, len: u64) {
+        self.unreadable_bytes += len;
+        self.push_region(RegionKind::Unreadable, offset, len, 0);
+        let sectors = len.div_ceil(self.sector_size as u64);
+        self.update_cells(offset, len, 0, false, sectors);
+    }
+}
+
+/// 结果目录（程序目录下的 `surface_test`）
+pub fn results_dir() -> PathBuf {
+    crate::utils::path::get_exe_dir().join("surface_test")
+}
+
+/// 读取结果目录中的全部扫描结果
+pub fn load_all(dir: &Path) -> Vec<SurfaceScan> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scans: Vec<SurfaceScan> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|data| serde_json::from_str(&data).ok())
+        .collect();
+    scans.sort_by_key(|s| std::cmp::Reverse(s.updated));
+    scans
+}
+
+/// 定位一次读取的结果
+enum ReadOutcome {
+    Ok(u32),
+    Failed,
+}
+
+fn timed_read<D: Read + Seek>(dev: &mut D, offset: u64, buf: &mut [u8]) -> ReadOutcome {
+    let start = Instant::now();
+    let result = dev.seek(SeekFrom::Start(offset)).and_then(|_| dev.read_exact(buf));
+    match result {
+        Ok(()) => ReadOutcome::Ok(start.elapsed().as_millis().min(u32::MAX as u128) as u32),
+        Err(e) => {
+            log::warn!("读取偏移 {} 长度 {} 失败: {}", offset, buf.len(), e);
+            ReadOutcome::Failed
+        }
+    }
+}
+
+/// 从 `scan.position` 开始顺序扫描，直到完成或被取消
+///
+/// 取消时已扫描的位置保存在 `scan.position`，之后可用同一个 `scan` 继续。
+/// `progress` 每隔一段时间以及结束时调用一次。
+pub fn run_scan<D: Read + Seek>(
+    dev: &mut D,
+    scan: &mut SurfaceScan,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(&SurfaceScan),
+) -> Result<()> {
+    let session_start = Instant::now();
+    let elapsed_base = scan.elapsed_secs;
+    let mut last_progress = Instant::now();
+    let mut buf = vec![0u8; scan.block_size as usize];
+    let sector = scan.sector_size as u64;
+
+    while scan.position < scan.size_bytes {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let offset = scan.position;
+        let len = (scan.block_size as u64).min(scan.size_bytes - offset);
+        match timed_read(dev, offset, &mut buf[..len as usize]) {
+            ReadOutcome::Ok(latency_ms) => {
+                scan.record_read(offset, len, latency_ms);
+                scan.position = offset + len;
+            }
+            ReadOutcome::Failed => probe_block(dev, scan, offset, len, sector, &mut buf, cancel),
+        }
+
+        scan.elapsed_secs = elapsed_base + session_start.elapsed().as_secs();
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            scan.updated = unix_now();
+            progress(scan);
+        }
+    }
+
+    scan.completed = scan.position >= scan.size_bytes;
+    scan.elapsed_secs = elapsed_base + session_start.elapsed().as_secs();
+    scan.updated = unix_now();
+    progress(scan);
+    Ok(())
+}
+
+/// 大块读取失败后分段重读，定位不可读的扇区
+///
+/// 先按 64 KiB 重读，仍然失败的段再逐扇区读取。被取消时 `scan.position`
+/// 停在最后一个已确认的扇区之后。
+fn probe_block<D: Read + Seek>(
+    dev: &mut D,
+    scan: &mut SurfaceScan,
+    offset: u64,
+    len: u64,
+    sector: u64,
+    buf: &mut [u8],
+    cancel: &AtomicBool,
+) {
+    let end = offset + len;
+    let probe = PROBE_SIZE.max(sector) / sector * sector;
+    let mut part = offset;
+    while part < end {
+        let part_len = probe.min(end - part);
+        match timed_read(dev, part, &mut buf[..part_len as usize]) {
+            ReadOutcome::Ok(latency_ms) => scan.record_read(part, part_len, latency_ms),
+            ReadOutcome::Failed => {
+                let mut pos = part;
+                while pos < part + part_len {
+                    if cancel.load(Ordering::Relaxed) {
+                        scan.position = pos;
+                        return;
+                    }
+                    let sector_len = sector.min(part + part_len - pos);
+                    match timed_read(dev, pos, &mut buf[..sector_len as usize]) {
+                        ReadOutcome::Ok(latency_ms) => scan.record_read(pos, sector_len, latency_ms),
+                        ReadOutcome::Failed => scan.record_unreadable(pos, sector_len),
+                    }
+                    pos += sector_len;
+                }
+            }
+        }
+        part += part_len;
+        scan.position = part;
+    }
+}
+
+/// 一次扫描任务
+#[derive(Debug, Clone)]
+pub struct SurfaceTestJob {
+    pub target: SurfaceTarget,
+    /// 磁盘型号或分区卷标
+    pub description: String,
+    /// 整盘扫描时的分区布局
+    pub partitions: Vec<ScannedPartition>,
+    pub options: SurfaceTestOptions,
+    /// 存在未完成的结果时从上次的位置继续
+    pub resume: bool,
+}
+
+/// 打开设备并扫描，结果定期保存到 `dir`
+pub fn scan_device(
+    job: &SurfaceTestJob,
+    dir: &Path,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(&SurfaceScan),
+) -> Result<SurfaceScan> {
+    let target = job.target;
+    let mut dev = super::raw_image::open_device(&target.device_path(), false)?;
+    if dev.size == 0 {
+        bail!("{} 大小为 0", target);
+    }
+
+    let mut scan = match SurfaceScan::load(dir, target) {
+        Some(saved) if job.resume && saved.can_resume(dev.size) => {
+            log::info!("继续扫描 {}，从偏移 {} 开始", target, saved.position);
+            saved
+        }
+        _ => {
+            log::info!("开始扫描 {}，大小 {} 字节", target, dev.size);
+            SurfaceScan::new(target, &job.description, dev.size, dev.sector_size, &job.options)
+        }
+    };
+    if !job.partitions.is_empty() {
+        scan.partitions = job.partitions.clone();
+    }
+    scan.save(dir)?;
+
+    let mut last_save = Instant::now();
+    let result = run_scan(&mut dev.file, &mut scan, cancel, &mut |scan| {
+        if last_save.elapsed() >= SAVE_INTERVAL {
+            last_save = Instant::now();
+            if let Err(e) = scan.save(dir) {
+                log::warn!("保存扫描进度失败: {:#}", e);
+            }
+        }
+        progress(scan);
+    });
+    scan.save(dir)?;
+    result?;
+    log::info!("{}", scan.summary());
+    Ok(scan)
+}
+
+/// 安装目标分区上的表面测试警告
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurfaceWarning {
+    /// 结果来源（如 "磁盘 1"）
+    pub source: String,
+    pub unreadable_regions: usize,
+    pub unreadable_bytes: u64,
+    pub slow_regions: usize,
+    /// 扫描是否覆盖了整个分区
+    pub completed: bool,
+}
+
+impl SurfaceWarning {
+    /// 是否存在不可读的扇区
+    pub fn has_bad_sectors(&self) -> bool {
+        self.unreadable_regions > 0
+    }
+
+    pub fn message(&self) -> String {
+        let scope = if self.completed { "" } else { "（扫描未完成）" };
+        if self.has_bad_sectors() {
+            format!(
+                "⚠ {}的表面测试{}发现目标分区有 {} 处不可读区域（{} KB），安装可能失败，建议更换磁盘或分区",
+                self.source,
+                scope,
+                self.unreadable_regions,
+                self.unreadable_bytes.div_ceil(1024)
+            )
+        } else {
+            format!(
+                "⚠ {}的表面测试{}发现目标分区有 {} 处读取缓慢的区域，磁盘可能正在老化",
+                self.source, scope, self.slow_regions
+            )
+        }
+    }
+}
+
+/// 在一次扫描结果中查找与 `[offset, offset + len)` 重叠的问题
+fn warning_for(scan: &SurfaceScan, offset: u64, len: u64) -> Option<SurfaceWarning> {
+    let mut warning = SurfaceWarning {
+        source: scan.target.to_string(),
+        unreadable_regions: 0,
+        unreadable_bytes: 0,
+        slow_regions: 0,
+        completed: scan.completed || scan.position >= offset + len,
+    };
+    for region in scan.regions_in(offset, len) {
+        match region.kind {
+            RegionKind::Unreadable => {
+                warning.unreadable_regions += 1;
+                warning.unreadable_bytes += region.end().min(offset + len) - region.offset.max(offset);
+            }
+            RegionKind::Slow => warning.slow_regions += 1,
+        }
+    }
+    (warning.unreadable_regions > 0 || warning.slow_regions > 0).then_some(warning)
+}
+
+/// 查询已保存的扫描结果中目标分区的问题
+///
+/// 同时检查该分区自身的扫描结果和记录了该分区位置的整盘扫描结果，
+/// 返回不可读数据最多的一条。
+pub fn check_partition(dir: &Path, letter: char) -> Option<SurfaceWarning> {
+    let letter = letter.to_ascii_uppercase();
+    load_all(dir)
+        .iter()
+        .filter_map(|scan| match scan.target {
+            SurfaceTarget::Partition(l) if l.eq_ignore_ascii_case(&letter) => warning_for(scan, 0, scan.size_bytes),
+            SurfaceTarget::Partition(_) => None,
+            SurfaceTarget::Disk(_) => scan
+                .partitions
+                .iter()
+                .find(|p| p.letter.is_some_and(|l| l.eq_ignore_ascii_case(&letter)))
+                .and_then(|p| warning_for(scan, p.offset, p.size)),
+        })
+        .max_by_key(|w| (w.unreadable_bytes, w.slow_regions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+
+    const MIB: u64 = 1024 * 1024;
+
+    /// 模拟磁盘：指定扇区读取失败，指定范围读取变慢
+    struct FaultyDisk {
+        inner: Cursor<Vec<u8>>,
+        bad_sectors: Vec<u64>,
+        slow: Option<(u64, u64)>,
+        delay: Duration,
+    }
+
+    impl FaultyDisk {
+        fn new(size: u64) -> Self {
+            Self {
+                inner: Cursor::new(vec![0u8; size as usize]),
+                bad_sectors: Vec::new(),
+                slow: None,
+                delay: Duration::ZERO,
+            }
+        }
+    }
+
+    impl Read for FaultyDisk {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let start = self.inner.position();
+            let end = start + buf.len() as u64;
+            if self.bad_sectors.iter().any(|s| s * 512 < end && start < (s + 1) * 512) {
+                return Err(io::Error::other("CRC error"));
+            }
+            if self.slow.is_some_and(|(s, e)| s < end && start < e) {
+                std::thread::sleep(self.delay);
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for FaultyDisk {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("letrecovery_surface_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_bad_and_slow_regions() {
+        let mut disk = FaultyDisk::new(8 * MIB);
+        // 3 MiB 处连续两个坏扇区，6 MiB 之后一个
+        disk.bad_sectors = vec![3 * MIB / 512 + 10, 3 * MIB / 512 + 11, 6 * MIB / 512 + 100];
+        disk.slow = Some((MIB, 2 * MIB));
+        disk.delay = Duration::from_millis(60);
+
+        let options = SurfaceTestOptions { slow_threshold_ms: 30, ..Default::default() };
+        let mut scan = SurfaceScan::new(SurfaceTarget::Disk(1), "test", 8 * MIB, 512, &options);
+        run_scan(&mut disk, &mut scan, &AtomicBool::new(false), &mut |_| {}).unwrap();
+
+        assert!(scan.completed);
+        assert_eq!(scan.unreadable_bytes, 3 * 512);
+        let bad: Vec<_> = scan.regions.iter().filter(|r| r.kind == RegionKind::Unreadable).collect();
+        assert_eq!(bad.len(), 2);
+        assert_eq!((bad[0].offset, bad[0].len), (3 * MIB + 10 * 512, 1024));
+        assert_eq!((bad[1].offset, bad[1].len), (6 * MIB + 100 * 512, 512));
+
+        let slow: Vec<_> = scan.regions.iter().filter(|r| r.kind == RegionKind::Slow).collect();
+        assert_eq!(slow.len(), 1);
+        assert_eq!((slow[0].offset, slow[0].len), (MIB, MIB));
+
+        // 8 MiB / 256 格 = 32 KiB 一格
+        let cell = |offset: u64| (offset * HEAT_CELLS as u64 / (8 * MIB)) as usize;
+        assert_eq!(scan.cell_level(cell(3 * MIB + 10 * 512)), CellLevel::Bad);
+        assert_eq!(scan.cells[cell(3 * MIB + 10 * 512)].bad_sectors, 2);
+        assert_eq!(scan.cell_level(cell(MIB)), CellLevel::Slow);
+        assert_eq!(scan.cell_level(cell(5 * MIB)), CellLevel::Good);
+        assert!(scan.cells.iter().all(|c| c.scanned_bytes > 0));
+    }
+
+    #[test]
+    fn test_cancel_and_resume() {
+        let mut disk = FaultyDisk::new(4 * MIB);
+        disk.bad_sectors = vec![2 * MIB / 512];
+        let options = SurfaceTestOptions::default();
+        let mut scan = SurfaceScan::new(SurfaceTarget::Partition('d'), "data", 4 * MIB, 512, &options);
+
+        // 第一次调用前就取消：不读取任何数据
+        let cancel = AtomicBool::new(true);
+        run_scan(&mut disk, &mut scan, &cancel, &mut |_| {}).unwrap();
+        assert_eq!(scan.position, 0);
+        assert!(!scan.completed);
+
+        // 读完第二个块后取消
+        let cancel = AtomicBool::new(false);
+        scan.position = MIB;
+        let mut blocks = 0;
+        let mut limited = LimitedReads { inner: &mut disk, remaining: 1, cancel: &cancel };
+        run_scan(&mut limited, &mut scan, &cancel, &mut |_| blocks += 1).unwrap();
+        assert_eq!(scan.position, 2 * MIB);
+        assert!(!scan.completed);
+        assert!(blocks >= 1);
+
+        // 保存后重新读取并继续
+        let dir = temp_dir("resume");
+        scan.save(&dir).unwrap();
+        let mut resumed = SurfaceScan::load(&dir, SurfaceTarget::Partition('D')).unwrap();
+        assert_eq!(resumed, scan);
+        assert!(resumed.can_resume(4 * MIB));
+        assert!(!resumed.can_resume(8 * MIB));
+
+        run_scan(&mut disk, &mut resumed, &AtomicBool::new(false), &mut |_| {}).unwrap();
+        assert!(resumed.completed);
+        assert_eq!(resumed.unreadable_bytes, 512);
+        assert_eq!(resumed.percent(), 100);
+        assert!(!resumed.can_resume(4 * MIB));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 允许若干次成功读取后设置取消标志
+    struct LimitedReads<'a> {
+        inner: &'a mut FaultyDisk,
+        remaining: usize,
+        cancel: &'a AtomicBool,
+    }
+
+    impl Read for LimitedReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.remaining = self.remaining.saturating_sub(1);
+            if self.remaining == 0 {
+                self.cancel.store(true, Ordering::Relaxed);
+            }
+            Ok(n)
+        }
+    }
+
+    impl Seek for LimitedReads<'_> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_check_partition() {
+        let dir = temp_dir("check");
+        let options = SurfaceTestOptions::default();
+
+        // 整盘扫描：坏道位于 E: 内部，C: 完好
+        let mut disk = SurfaceScan::new(SurfaceTarget::Disk(0), "disk", 100 * MIB, 512, &options);
+        disk.partitions = vec![
+            ScannedPartition { letter: Some('C'), offset: MIB, size: 49 * MIB },
+            ScannedPartition { letter: Some('E'), offset: 50 * MIB, size: 50 * MIB },
+        ];
+        disk.record_unreadable(60 * MIB, 4096);
+        disk.position = 100 * MIB;
+        disk.completed = true;
+        disk.save(&dir).unwrap();
+
+        // 单独扫描过的 F: 有慢速区域
+        let mut part = SurfaceScan::new(SurfaceTarget::Partition('F'), "data", 10 * MIB, 512, &options);
+        part.record_read(MIB, MIB, 500);
+        part.position = 2 * MIB;
+        part.save(&dir).unwrap();
+
+        assert_eq!(check_partition(&dir, 'c'), None);
+        let e = check_partition(&dir, 'e').unwrap();
+        assert!(e.has_bad_sectors());
+        assert_eq!((e.unreadable_regions, e.unreadable_bytes), (1, 4096));
+        assert!(e.completed);
+        assert!(e.message().contains("磁盘 0"));
+
+        let f = check_partition(&dir, 'F').unwrap();
+        assert!(!f.has_bad_sectors());
+        assert_eq!(f.slow_regions, 1);
+        assert!(!f.completed);
+        assert!(f.message().contains("扫描未完成"));
+
+        assert_eq!(load_all(&dir).len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
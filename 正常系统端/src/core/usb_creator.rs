@@ -0,0 +1,883 @@
+//! 安装 U 盘制作模块
+//!
+//! 把 Windows 安装 ISO（或系统镜像 + 引导介质）写入 U 盘：
+//! - 单分区 FAT32：超过 4 GB 的 install.wim 分割为 install.swm、install2.swm……
+//! - FAT32 + NTFS 双分区：FAT32 存放引导文件，NTFS 存放完整的 sources\install.*
+//!   （Windows 安装程序会在所有磁盘上查找 sources 目录）
+//! - 可选附带 LetRecovery PE（添加到 U 盘的启动菜单）和程序目录下的驱动
+//! - BIOS 启动写入 MBR 和 FAT32 BOOTMGR 引导代码，UEFI 启动使用 efi\boot\bootx64.efi
+//!
+//! 目标也可以是磁盘镜像文件：分区表、格式化、文件复制和引导代码全部由程序直接写入，
+//! 不经过 diskpart 和系统挂载，便于校验。
+//!
+//! ISO 通过 `IsoMounter` 挂载后读取（Windows 安装 ISO 的文件位于 UDF 文件系统中）。
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::boot_code::{self, VbrFileSystem, VbrTemplate};
+use super::fat32_writer::{Fat32Volume, FAT32_MAX_FILE_SIZE};
+use super::fat_format::{self, FatFormatOptions, NativeFileSystem, VolumeGeometry};
+use super::partition_table::{
+    DiskGeometry, Guid, Mbr, MbrEntry, MbrLayout, PartitionIo, PartitionTable, MBR_TYPE_FAT32_LBA,
+};
+use crate::utils::cmd::create_command;
+use crate::utils::encoding::gbk_to_utf8;
+use crate::utils::path::{get_bin_dir, get_exe_dir};
+
+pub use letrecovery_disk::usb_layout::{place_file, plan_install_image, plan_tree, CopyItem, UsbLayout, UsbVolume};
+
+/// 分割 install.wim 时每个分卷的大小（MB）
+const SWM_PART_SIZE_MB: u64 = 3800;
+/// 镜像文件目标的分区起始扇区（1 MiB 对齐）
+const PARTITION_START_LBA: u64 = 2048;
+/// 镜像文件目标的扇区大小
+const IMAGE_SECTOR_SIZE: u64 = 512;
+/// 双分区布局默认的 FAT32 引导分区大小（MB）
+pub const DEFAULT_BOOT_PARTITION_MB: u64 = 2048;
+/// U 盘上 LetRecovery PE 的存放目录
+const PE_DIR: &str = r"LetRecovery\PE";
+/// U 盘上驱动的存放目录
+const DRIVERS_DIR: &str = r"LetRecovery\drivers";
+/// 复制阶段占用的进度区间
+const COPY_PROGRESS_START: u64 = 10;
+const COPY_PROGRESS_END: u64 = 95;
+
+/// 安装文件来源
+#[derive(Debug, Clone)]
+pub enum UsbSource {
+    /// Windows 安装 ISO
+    Iso(PathBuf),
+    /// 已解压的安装文件目录（或已挂载的安装介质盘符）
+    Directory(PathBuf),
+    /// 系统镜像（.wim/.esd），引导文件取自 `boot_media`（ISO 或目录，如 LetRecovery PE 的 ISO）
+    Wim { image: PathBuf, boot_media: PathBuf },
+}
+
+/// 写入目标
+#[derive(Debug, Clone)]
+pub enum UsbTarget {
+    /// 物理磁盘编号（整盘重新分区）
+    Disk(u32),
+    /// 磁盘镜像文件（只支持单 FAT32 布局）
+    Image { path: PathBuf, size_bytes: u64 },
+}
+
+impl fmt::Display for UsbTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsbTarget::Disk(n) => write!(f, "磁盘 {}", n),
+            UsbTarget::Image { path, .. } => write!(f, "镜像文件 {}", path.display()),
+        }
+    }
+}
+
+/// 制作选项
+#[derive(Debug, Clone)]
+pub struct UsbOptions {
+    pub source: UsbSource,
+    pub target: UsbTarget,
+    pub layout: UsbLayout,
+    /// 卷标
+    pub label: String,
+    /// 双分区布局中 FAT32 引导分区的大小（MB）
+    pub boot_partition_mb: u64,
+    /// 附带的 LetRecovery PE（.iso 或 .wim）
+    pub pe_path: Option<PathBuf>,
+    /// 附带程序目录下的 drivers 文件夹
+    pub include_drivers: bool,
+    /// 镜像文件目标使用的 FAT32 BOOTMGR 引导代码模板，None 时自动查找（写入 U 盘时始终自动查找）
+    pub vbr_template: Option<VbrTemplate>,
+}
+
+/// 制作结果
+#[derive(Debug, Clone, Default)]
+pub struct UsbReport {
+    pub target: String,
+    pub files_copied: usize,
+    pub bytes_copied: u64,
+    /// install.wim 分割后的分卷（U 盘上的相对路径）
+    pub split_parts: Vec<String>,
+    pub bios_boot: bool,
+    pub uefi_boot: bool,
+    pub pe_added: bool,
+    /// 分区表备份文件（仅物理磁盘）
+    pub backup_path: Option<PathBuf>,
+    pub notes: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl UsbReport {
+    /// 生成摘要文本
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!("目标: {}", self.target),
+            format!(
+                "已复制 {} 个文件，共 {:.2} GB",
+                self.files_copied,
+                self.bytes_copied as f64 / 1024.0 / 1024.0 / 1024.0
+            ),
+        ];
+        if !self.split_parts.is_empty() {
+            lines.push(format!("install.wim 已分割为: {}", self.split_parts.join(", ")));
+        }
+        lines.push(format!(
+            "BIOS 启动: {}，UEFI 启动: {}",
+            if self.bios_boot { "可用" } else { "不可用" },
+            if self.uefi_boot { "可用" } else { "不可用" }
+        ));
+        if self.pe_added {
+            lines.push("已添加 LetRecovery PE".to_string());
+        }
+        if let Some(path) = &self.backup_path {
+            lines.push(format!("原分区表已备份到: {}", path.display()));
+        }
+        lines.extend(self.notes.iter().cloned());
+        lines.extend(self.warnings.iter().map(|w| format!("警告: {}", w)));
+        lines.join("\n")
+    }
+}
+
+/// 清单中是否包含指定路径（不区分大小写）
+fn has_item(items: &[CopyItem], dest: &str) -> bool {
+    items.iter().any(|i| i.dest.eq_ignore_ascii_case(dest))
+}
+
+/// 安装介质的文件树（ISO 会被挂载，结束时自动卸载）
+struct SourceTree {
+    root: PathBuf,
+    mounted: bool,
+}
+
+impl SourceTree {
+    fn open(path: &Path) -> Result<Self> {
+        let is_iso = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("iso"))
+            .unwrap_or(false);
+        if is_iso {
+            let drive = super::iso::IsoMounter::mount_iso(&path.to_string_lossy())
+                .with_context(|| format!("挂载 {} 失败", path.display()))?;
+            return Ok(Self { root: PathBuf::from(format!("{}\\", drive)), mounted: true });
+        }
+        if !path.is_dir() {
+            bail!("安装介质不存在: {}", path.display());
+        }
+        Ok(Self { root: path.to_path_buf(), mounted: false })
+    }
+}
+
+impl Drop for SourceTree {
+    fn drop(&mut self) {
+        if self.mounted {
+            let _ = super::iso::IsoMounter::unmount();
+        }
+    }
+}
+
+/// 复制进度（按字节数）与取消检查
+struct CopyTracker<'a> {
+    done: u64,
+    total: u64,
+    last_percent: u8,
+    current: String,
+    progress: &'a mut dyn FnMut(u8, &str),
+    cancel: &'a AtomicBool,
+}
+
+impl<'a> CopyTracker<'a> {
+    fn new(total: u64, progress: &'a mut dyn FnMut(u8, &str), cancel: &'a AtomicBool) -> Self {
+        Self { done: 0, total: total.max(1), last_percent: 0, current: String::new(), progress, cancel }
+    }
+
+    fn percent(&self) -> u8 {
+        let span = COPY_PROGRESS_END - COPY_PROGRESS_START;
+        (COPY_PROGRESS_START + self.done.min(self.total) * span / self.total) as u8
+    }
+
+    fn start_file(&mut self, dest: &str) -> Result<()> {
+        self.check_cancel()?;
+        self.current = format!("正在复制 {}", dest);
+        let percent = self.percent();
+        (self.progress)(percent, &self.current);
+        Ok(())
+    }
+
+    fn status(&mut self, message: &str) {
+        let percent = self.percent();
+        (self.progress)(percent, message);
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        let percent = self.percent();
+        if percent != self.last_percent {
+            self.last_percent = percent;
+            (self.progress)(percent, &self.current);
+        }
+    }
+
+    fn check_cancel(&self) -> Result<()> {
+        if self.cancel.load(Ordering::Relaxed) {
+            bail!("用户已取消操作");
+        }
+        Ok(())
+    }
+}
+
+/// 读取时更新进度，取消后返回错误
+struct TrackedReader<'a, 'b, R> {
+    inner: R,
+    tracker: &'a mut CopyTracker<'b>,
+}
+
+impl<R: Read> Read for TrackedReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.tracker.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::other("用户已取消操作"));
+        }
+        let n = self.inner.read(buf)?;
+        self.tracker.advance(n as u64);
+        Ok(n)
+    }
+}
+
+/// U 盘分区的写入方式
+enum VolumeWriter {
+    /// 已挂载的卷（盘符根目录），直接使用文件系统 API
+    Directory(PathBuf),
+    /// 镜像文件中的 FAT32 分区
+    Image(Box<Fat32Volume<PartitionIo<File>>>),
+}
+
+impl VolumeWriter {
+    fn write_file(&mut self, dest: &str, source: &Path, size: u64, tracker: &mut CopyTracker) -> Result<()> {
+        let file = File::open(source).with_context(|| format!("打开 {} 失败", source.display()))?;
+        let mut reader = TrackedReader { inner: file, tracker };
+        match self {
+            VolumeWriter::Directory(root) => {
+                let path = root.join(dest);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("创建目录 {} 失败", parent.display()))?;
+                }
+                let mut out = File::create(&path).with_context(|| format!("创建 {} 失败", path.display()))?;
+                let copied = io::copy(&mut reader, &mut out).with_context(|| format!("复制 {} 失败", dest))?;
+                if copied != size {
+                    bail!("{} 复制了 {} 字节，预期 {} 字节", dest, copied, size);
+                }
+                Ok(())
+            }
+            VolumeWriter::Image(volume) => volume.write_file(dest, &mut reader, size),
+        }
+    }
+
+    /// 卷上目录对应的本地路径（镜像文件中的分区没有本地路径）
+    fn local_path(&self, dest: &str) -> Option<PathBuf> {
+        match self {
+            VolumeWriter::Directory(root) => Some(root.join(dest)),
+            VolumeWriter::Image(_) => None,
+        }
+    }
+}
+
+/// 准备好的写入目标
+struct UsbTargets {
+    boot: VolumeWriter,
+    data: Option<VolumeWriter>,
+    boot_letter: Option<char>,
+}
+
+impl UsbTargets {
+    fn writer(&mut self, volume: UsbVolume) -> &mut VolumeWriter {
+        match (volume, self.data.as_mut()) {
+            (UsbVolume::Data, Some(data)) => data,
+            _ => &mut self.boot,
+        }
+    }
+}
+
+/// 制作安装 U 盘（或磁盘镜像）
+///
+/// `progress` 接收 0-100 的进度和当前步骤描述，`cancel` 置位后在下一次读取时中止
+pub fn create_install_usb(
+    options: &UsbOptions,
+    progress: &mut dyn FnMut(u8, &str),
+    cancel: &AtomicBool,
+) -> Result<UsbReport> {
+    if matches!(options.target, UsbTarget::Image { .. }) && options.layout == UsbLayout::Fat32Ntfs {
+        bail!("镜像文件目标只支持单 FAT32 分区布局");
+    }
+    let mut report = UsbReport { target: options.target.to_string(), ..Default::default() };
+    log::info!("制作安装 U 盘: {}, 布局 {}", report.target, options.layout);
+
+    // 扫描安装介质
+    progress(0, "正在读取安装介质...");
+    let (media, install_image) = match &options.source {
+        UsbSource::Iso(path) | UsbSource::Directory(path) => (path, None),
+        UsbSource::Wim { image, boot_media } => (boot_media, Some(image)),
+    };
+    let tree = SourceTree::open(media)?;
+    let mut items = plan_tree(&tree.root, "", options.layout, UsbVolume::Boot, install_image.is_some())?;
+    if let Some(image) = install_image {
+        items.push(plan_install_image(image, options.layout)?);
+    }
+    if !has_item(&items, "bootmgr") && !has_item(&items, r"efi\boot\bootx64.efi") {
+        bail!("{} 不是可启动的安装介质（缺少 bootmgr 和 efi\\boot\\bootx64.efi）", media.display());
+    }
+    if options.include_drivers {
+        let drivers = get_exe_dir().join("drivers");
+        if drivers.is_dir() {
+            items.extend(plan_tree(&drivers, DRIVERS_DIR, options.layout, UsbVolume::Data, false)?);
+        } else {
+            report.warnings.push(format!("驱动目录不存在: {}", drivers.display()));
+        }
+    }
+    let pe_size = match &options.pe_path {
+        Some(pe) => std::fs::metadata(pe).with_context(|| format!("PE 文件不存在: {}", pe.display()))?.len(),
+        None => 0,
+    };
+    let total: u64 = items.iter().map(|i| i.size).sum::<u64>() + pe_size;
+    log::info!("复制清单: {} 个文件，共 {} 字节", items.len(), total);
+
+    // 分区并格式化
+    let mut targets = match &options.target {
+        UsbTarget::Disk(disk_number) => {
+            check_disk_capacity(*disk_number, options, &items, pe_size)?;
+            prepare_disk(*disk_number, options, &mut report, progress)?
+        }
+        UsbTarget::Image { path, size_bytes } => prepare_image(path, *size_bytes, options, progress)?,
+    };
+
+    // 复制文件
+    let mut pe_sdi = None;
+    {
+        let mut tracker = CopyTracker::new(total, progress, cancel);
+        for item in &items {
+            copy_item(&mut targets, item, &mut tracker, &mut report)?;
+        }
+        // 挂载 PE 的 ISO 前先卸载安装介质
+        drop(tree);
+        if let Some(pe) = &options.pe_path {
+            pe_sdi = Some(copy_pe(&mut targets, pe, &mut tracker, &mut report)?);
+        }
+    }
+
+    // 引导
+    progress(96, "正在写入引导代码...");
+    report.uefi_boot = has_item(&items, r"efi\boot\bootx64.efi");
+    if !report.uefi_boot {
+        report.warnings.push("安装介质没有 efi\\boot\\bootx64.efi，U 盘不能以 UEFI 方式启动".to_string());
+    }
+    let has_bootmgr = has_item(&items, "bootmgr");
+    if !has_bootmgr {
+        report.warnings.push("安装介质没有 bootmgr，U 盘不能以 BIOS 方式启动".to_string());
+    }
+
+    match targets {
+        UsbTargets { boot: VolumeWriter::Image(volume), .. } => {
+            let mut volume = *volume;
+            volume.flush()?;
+            let mut file = volume.into_inner().into_inner();
+            if has_bootmgr {
+                write_image_boot_code(&mut file, options, &mut report);
+            }
+            file.flush()?;
+            if report.pe_added {
+                report.warnings.push("镜像文件目标不会修改 BCD，PE 启动项需要在写入 U 盘后添加".to_string());
+            }
+        }
+        UsbTargets { boot_letter: Some(letter), .. } => {
+            if has_bootmgr {
+                match boot_code::write_bios_boot_code(&format!("{}:", letter)) {
+                    Ok(bios) => {
+                        report.bios_boot = true;
+                        report.notes.extend(bios.notes);
+                    }
+                    Err(e) => report.warnings.push(format!("未写入 BIOS 引导代码: {:#}", e)),
+                }
+            }
+            if let Some(sdi) = pe_sdi {
+                progress(98, "正在添加 PE 启动项...");
+                let root = PathBuf::from(format!("{}:\\", letter));
+                match add_pe_boot_entries(&root, &sdi) {
+                    Ok(0) => report.warnings.push("安装介质没有 BCD，未添加 PE 启动项".to_string()),
+                    Ok(n) => report.notes.push(format!("已在 {} 个 BCD 中添加 LetRecovery PE 启动项", n)),
+                    Err(e) => report.warnings.push(format!("添加 PE 启动项失败: {:#}", e)),
+                }
+            }
+        }
+        _ => {}
+    }
+
+    progress(100, "安装 U 盘制作完成");
+    log::info!("安装 U 盘制作完成:\n{}", report.summary());
+    Ok(report)
+}
+
+fn copy_item(targets: &mut UsbTargets, item: &CopyItem, tracker: &mut CopyTracker, report: &mut UsbReport) -> Result<()> {
+    if !item.split {
+        tracker.start_file(&item.dest)?;
+        targets.writer(item.volume).write_file(&item.dest, &item.source, item.size, tracker)?;
+        report.files_copied += 1;
+        report.bytes_copied += item.size;
+        return Ok(());
+    }
+
+    // 分割 install.wim：已挂载的卷直接分割到 U 盘，镜像文件先分割到临时目录
+    tracker.check_cancel()?;
+    let dest_dir = item.dest.rsplit_once('\\').map(|(dir, _)| dir).unwrap_or("");
+    let writer = targets.writer(item.volume);
+    let direct = writer.local_path(dest_dir);
+    let staging = direct
+        .clone()
+        .unwrap_or_else(|| get_exe_dir().join("temp").join("usb_swm"));
+    let parts = split_wim(&item.source, &staging.join("install.swm"), tracker)?;
+
+    let result = (|| -> Result<()> {
+        for part in &parts {
+            let name = part.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let dest = format!("{}\\{}", dest_dir, name);
+            let size = std::fs::metadata(part)?.len();
+            if direct.is_none() {
+                tracker.start_file(&dest)?;
+                writer.write_file(&dest, part, size, tracker)?;
+            }
+            report.files_copied += 1;
+            report.bytes_copied += size;
+            report.split_parts.push(dest);
+        }
+        Ok(())
+    })();
+    if direct.is_some() {
+        tracker.advance(item.size);
+    } else {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// 用 DISM 分割 WIM，分割期间把 DISM 的进度显示为状态文字
+fn split_wim(image: &Path, swm: &Path, tracker: &mut CopyTracker) -> Result<Vec<PathBuf>> {
+    let image_file = image.to_string_lossy().to_string();
+    let swm_file = swm.to_string_lossy().to_string();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = std::thread::spawn(move || {
+        super::dism_cmd::DismCmd::new()?.split_image(&image_file, &swm_file, SWM_PART_SIZE_MB, Some(tx))
+    });
+    for update in rx {
+        tracker.status(&format!("正在分割 install.wim: {}%", update.percentage));
+    }
+    handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("分割 install.wim 的线程异常退出"))?
+        .context("分割 install.wim 失败")
+}
+
+/// 复制 LetRecovery PE，返回 BCD 中使用的 boot.sdi 路径
+fn copy_pe(targets: &mut UsbTargets, pe_path: &Path, tracker: &mut CopyTracker, report: &mut UsbReport) -> Result<String> {
+    let is_iso = pe_path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("iso"))
+        .unwrap_or(false);
+    let tree = if is_iso { Some(SourceTree::open(pe_path)?) } else { None };
+
+    let wim = match &tree {
+        Some(tree) => [r"sources\boot.wim", r"boot\boot.wim", "boot.wim"]
+            .iter()
+            .map(|p| tree.root.join(p))
+            .find(|p| p.exists())
+            .with_context(|| format!("{} 中没有 boot.wim", pe_path.display()))?,
+        None => pe_path.to_path_buf(),
+    };
+    let mut sdi_candidates: Vec<PathBuf> = Vec::new();
+    if let Some(tree) = &tree {
+        sdi_candidates.push(tree.root.join(r"boot\boot.sdi"));
+    }
+    sdi_candidates.push(PathBuf::from(r"C:\Windows\Boot\DVD\PCAT\boot.sdi"));
+    sdi_candidates.push(PathBuf::from(r"C:\Windows\Boot\DVD\EFI\boot.sdi"));
+
+    let wim_size = std::fs::metadata(&wim)?.len();
+    if wim_size > FAT32_MAX_FILE_SIZE {
+        bail!("PE 镜像 {} 超过 4 GB，无法放入 FAT32 分区", wim.display());
+    }
+    let dest = format!(r"{}\boot.wim", PE_DIR);
+    tracker.start_file(&dest)?;
+    targets.boot.write_file(&dest, &wim, wim_size, tracker)?;
+    report.files_copied += 1;
+    report.bytes_copied += wim_size;
+
+    // 找不到 boot.sdi 时使用安装介质自带的 \boot\boot.sdi
+    let sdi_path = match sdi_candidates.iter().find(|p| p.exists()) {
+        Some(sdi) => {
+            let size = std::fs::metadata(sdi)?.len();
+            let dest = format!(r"{}\boot.sdi", PE_DIR);
+            tracker.start_file(&dest)?;
+            targets.boot.write_file(&dest, sdi, size, tracker)?;
+            report.files_copied += 1;
+            report.bytes_copied += size;
+            format!(r"\{}", dest)
+        }
+        None => r"\boot\boot.sdi".to_string(),
+    };
+    report.pe_added = true;
+    Ok(sdi_path)
+}
+
+/// 检查 U 盘容量是否足够
+fn check_disk_capacity(disk_number: u32, options: &UsbOptions, items: &[CopyItem], pe_size: u64) -> Result<()> {
+    let disk = super::quick_partition::get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .with_context(|| format!("找不到磁盘 {}", disk_number))?;
+    let sum = |volume: UsbVolume| items.iter().filter(|i| i.volume == volume).map(|i| i.size).sum::<u64>();
+    let boot_bytes = sum(UsbVolume::Boot) + pe_size;
+    let data_bytes = sum(UsbVolume::Data);
+    const MB: u64 = 1024 * 1024;
+    match options.layout {
+        UsbLayout::SingleFat32 if boot_bytes + boot_bytes / 50 > disk.size_bytes => {
+            bail!("U 盘容量不足：需要 {} MB，磁盘只有 {} MB", boot_bytes / MB, disk.size_bytes / MB)
+        }
+        UsbLayout::Fat32Ntfs if boot_bytes + boot_bytes / 50 > options.boot_partition_mb * MB => bail!(
+            "FAT32 引导分区容量不足：需要 {} MB，请把引导分区调大到至少 {} MB",
+            boot_bytes / MB,
+            (boot_bytes + boot_bytes / 50) / MB + 1
+        ),
+        UsbLayout::Fat32Ntfs if boot_bytes + data_bytes + data_bytes / 50 > disk.size_bytes => bail!(
+            "U 盘容量不足：需要 {} MB，磁盘只有 {} MB",
+            (boot_bytes + data_bytes) / MB,
+            disk.size_bytes / MB
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// 物理磁盘：备份分区表、重新分区并格式化
+fn prepare_disk(
+    disk_number: u32,
+    options: &UsbOptions,
+    report: &mut UsbReport,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<UsbTargets> {
+    use super::diskpart::{CreatePartitionKind, DiskpartScript};
+    use super::quick_partition::{can_safely_partition, get_next_available_drive_letter, get_used_drive_letters};
+
+    let disk = super::quick_partition::get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .with_context(|| format!("找不到磁盘 {}", disk_number))?;
+    let (safe, reason) = can_safely_partition(&disk);
+    if !safe {
+        bail!("{}", reason);
+    }
+
+    progress(2, "正在备份分区表...");
+    report.backup_path = Some(super::partition_backup::backup_physical_disk(&disk, "制作安装 U 盘")?);
+
+    let mut used = get_used_drive_letters();
+    let boot_letter = get_next_available_drive_letter(&used).context("没有可用的盘符")?;
+    used.push(boot_letter);
+
+    progress(4, "正在重新分区...");
+    let mut script = DiskpartScript::new().select_disk(disk_number).clean().convert_mbr();
+    let data_letter = match options.layout {
+        UsbLayout::SingleFat32 => {
+            script = script
+                .create_partition(CreatePartitionKind::Primary, None, None)
+                .set_id("0c")
+                .active()
+                .assign(Some(boot_letter));
+            None
+        }
+        UsbLayout::Fat32Ntfs => {
+            let data_letter = get_next_available_drive_letter(&used).context("没有可用的盘符")?;
+            script = script
+                .create_partition(CreatePartitionKind::Primary, Some(options.boot_partition_mb), None)
+                .set_id("0c")
+                .active()
+                .assign(Some(boot_letter))
+                .create_partition(CreatePartitionKind::Primary, None, None)
+                .format("ntfs", Some(&options.label))
+                .assign(Some(data_letter));
+            Some(data_letter)
+        }
+    };
+    script.rescan().run()?.ensure_success("U 盘分区失败")?;
+
+    // diskpart 不能格式化 32 GB 以上的 FAT32，统一使用原生格式化
+    let format_options = FatFormatOptions {
+        file_system: NativeFileSystem::Fat32,
+        label: options.label.clone(),
+        quick: true,
+        cluster_size: None,
+    };
+    fat_format::format_drive(boot_letter, &format_options, &mut |p, status| {
+        progress(5 + p / 20, status)
+    })
+    .with_context(|| format!("格式化 {}: 失败", boot_letter))?;
+
+    report.target = format!("磁盘 {} ({}:)", disk_number, boot_letter);
+    Ok(UsbTargets {
+        boot: VolumeWriter::Directory(PathBuf::from(format!("{}:\\", boot_letter))),
+        data: data_letter.map(|l| VolumeWriter::Directory(PathBuf::from(format!("{}:\\", l)))),
+        boot_letter: Some(boot_letter),
+    })
+}
+
+/// 镜像文件：写入 MBR 分区表并格式化唯一的 FAT32 分区
+fn prepare_image(
+    path: &Path,
+    size_bytes: u64,
+    options: &UsbOptions,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<UsbTargets> {
+    let total_sectors = size_bytes / IMAGE_SECTOR_SIZE;
+    let sector_count = total_sectors.saturating_sub(PARTITION_START_LBA);
+    if sector_count == 0 || sector_count > u32::MAX as u64 {
+        bail!("镜像文件大小无效: {} 字节", size_bytes);
+    }
+
+    progress(2, "正在创建镜像文件...");
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .with_context(|| format!("创建 {} 失败", path.display()))?;
+    file.set_len(total_sectors * IMAGE_SECTOR_SIZE)?;
+
+    let geometry = DiskGeometry::new(IMAGE_SECTOR_SIZE, total_sectors * IMAGE_SECTOR_SIZE);
+    let mut mbr = Mbr {
+        disk_signature: u32::from_le_bytes(Guid::new_random().0[..4].try_into().unwrap()),
+        ..Default::default()
+    };
+    mbr.entries[0] = MbrEntry {
+        bootable: true,
+        partition_type: MBR_TYPE_FAT32_LBA,
+        start_lba: PARTITION_START_LBA as u32,
+        sector_count: sector_count as u32,
+    };
+    PartitionTable::Mbr(MbrLayout { mbr, logical: Vec::new() }).write(&mut file, &geometry)?;
+
+    let mut partition = PartitionIo::new(file, PARTITION_START_LBA * IMAGE_SECTOR_SIZE, sector_count * IMAGE_SECTOR_SIZE);
+    let volume_geometry = VolumeGeometry {
+        total_sectors: sector_count,
+        sector_size: IMAGE_SECTOR_SIZE as u32,
+        hidden_sectors: PARTITION_START_LBA,
+    };
+    let format_options = FatFormatOptions {
+        file_system: NativeFileSystem::Fat32,
+        label: options.label.clone(),
+        quick: true,
+        cluster_size: None,
+    };
+    fat_format::format_volume(&mut partition, &volume_geometry, &format_options, &mut |p, status| {
+        progress(2 + p / 15, status)
+    })?;
+
+    Ok(UsbTargets {
+        boot: VolumeWriter::Image(Box::new(Fat32Volume::open(partition)?)),
+        data: None,
+        boot_letter: None,
+    })
+}
+
+/// 镜像文件：写入 MBR 引导代码和 FAT32 BOOTMGR 引导扇区
+fn write_image_boot_code(file: &mut File, options: &UsbOptions, report: &mut UsbReport) {
+    let template = options
+        .vbr_template
+        .clone()
+        .or_else(|| boot_code::find_template(VbrFileSystem::Fat32, None));
+    let result = boot_code::plan_bios_boot(file, IMAGE_SECTOR_SIZE, 0, template.as_ref())
+        .and_then(|plan| plan.apply(file).map(|_| plan.notes));
+    match result {
+        Ok(notes) => {
+            report.bios_boot = true;
+            report.notes.extend(notes);
+        }
+        Err(e) => report.warnings.push(format!("未写入 BIOS 引导代码: {:#}", e)),
+    }
+}
+
+/// 在 U 盘的 BIOS 和 UEFI 两个 BCD 中添加 LetRecovery PE 启动项，返回修改的 BCD 数量
+fn add_pe_boot_entries(root: &Path, sdi_path: &str) -> Result<usize> {
+    let bcdedit = get_bin_dir().join("bcdedit.exe");
+    let bcdedit = if bcdedit.exists() { bcdedit } else { PathBuf::from("bcdedit") };
+    let run = |args: &[&str]| -> Result<String> {
+        let output = create_command(&bcdedit).args(args).output().context("执行 bcdedit 失败")?;
+        let stdout = gbk_to_utf8(&output.stdout);
+        if !output.status.success() {
+            bail!("bcdedit {} 失败: {} {}", args.join(" "), stdout.trim(), gbk_to_utf8(&output.stderr).trim());
+        }
+        Ok(stdout)
+    };
+    let guid_of = |output: &str| -> Result<String> {
+        output
+            .split_whitespace()
+            .find(|w| w.starts_with('{') && w.ends_with('}'))
+            .map(str::to_string)
+            .context("无法从 bcdedit 输出中获取 GUID")
+    };
+
+    let wim_path = format!(r"\{}\boot.wim", PE_DIR);
+    let mut updated = 0;
+    for (store, winload) in [
+        (r"boot\bcd", r"\windows\system32\boot\winload.exe"),
+        (r"efi\microsoft\boot\bcd", r"\windows\system32\boot\winload.efi"),
+    ] {
+        let store = root.join(store);
+        if !store.exists() {
+            continue;
+        }
+        let store = store.to_string_lossy().to_string();
+        let ramdisk = guid_of(&run(&["/store", &store, "/create", "/d", "LetRecovery PE", "/device"])?)?;
+        run(&["/store", &store, "/set", &ramdisk, "ramdisksdidevice", "boot"])?;
+        run(&["/store", &store, "/set", &ramdisk, "ramdisksdipath", sdi_path])?;
+
+        let loader = guid_of(&run(&["/store", &store, "/create", "/d", "LetRecovery PE", "/application", "osloader"])?)?;
+        let device = format!("ramdisk=[boot]{},{}", wim_path, ramdisk);
+        for (name, value) in [
+            ("device", device.as_str()),
+            ("osdevice", device.as_str()),
+            ("path", winload),
+            ("systemroot", r"\windows"),
+            ("detecthal", "yes"),
+            ("winpe", "yes"),
+        ] {
+            run(&["/store", &store, "/set", &loader, name, value])?;
+        }
+        run(&["/store", &store, "/displayorder", &loader, "/addlast"])?;
+        run(&["/store", &store, "/timeout", "10"])?;
+        log::info!("已在 {} 中添加 PE 启动项 {}", store, loader);
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::partition_table::read_sectors;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("letrecovery_usb_{}", std::process::id()))
+            .join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, relative: &str, data: &[u8]) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    /// 加载 BOOTMGR 的 FAT32 引导区模板
+    fn fat32_template() -> VbrTemplate {
+        let mut region = vec![0u8; 13 * 512];
+        region[..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+        region[11..13].copy_from_slice(&512u16.to_le_bytes());
+        region[0x52..0x5A].copy_from_slice(b"FAT32   ");
+        region[0x100..0x108].copy_from_slice(b"BOOTMGR ");
+        region[510..512].copy_from_slice(&[0x55, 0xAA]);
+        region[12 * 512..12 * 512 + 8].copy_from_slice(b"NT6CODE\0");
+        VbrTemplate::from_bytes(region, "test").unwrap()
+    }
+
+    #[test]
+    fn test_create_image_from_directory() {
+        let source = temp_dir("media");
+        let install: Vec<u8> = (0..2 * MIB as usize).map(|i| (i % 253) as u8).collect();
+        write(&source, "bootmgr", b"BOOTMGR");
+        write(&source, "setup.exe", b"MZ");
+        write(&source, "boot/bcd", b"BCD");
+        write(&source, "efi/boot/bootx64.efi", b"EFI");
+        write(&source, "sources/install.wim", &install);
+        let image = temp_dir("target").join("usb.img");
+
+        let options = UsbOptions {
+            source: UsbSource::Directory(source.clone()),
+            target: UsbTarget::Image { path: image.clone(), size_bytes: 80 * MIB },
+            layout: UsbLayout::SingleFat32,
+            label: "WIN_INSTALL".to_string(),
+            boot_partition_mb: DEFAULT_BOOT_PARTITION_MB,
+            pe_path: None,
+            include_drivers: false,
+            vbr_template: Some(fat32_template()),
+        };
+        let mut last = 0;
+        let report = create_install_usb(
+            &options,
+            &mut |p, _| {
+                assert!(p >= last);
+                last = p;
+            },
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(last, 100);
+        assert_eq!(report.files_copied, 5);
+        assert!(report.bios_boot && report.uefi_boot);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        // MBR：活动的 FAT32 分区和引导代码
+        let mut file = File::options().read(true).write(true).open(&image).unwrap();
+        let geometry = DiskGeometry::from_stream(&mut file, IMAGE_SECTOR_SIZE).unwrap();
+        let mbr = Mbr::decode(&read_sectors(&mut file, &geometry, 0, 1).unwrap()).unwrap();
+        assert!(mbr.entries[0].bootable);
+        assert_eq!(mbr.entries[0].partition_type, MBR_TYPE_FAT32_LBA);
+        assert_eq!(mbr.boot_code[..boot_code::MBR_BOOT_CODE.len()], boot_code::MBR_BOOT_CODE[..]);
+        let vbr = read_sectors(&mut file, &geometry, PARTITION_START_LBA, 13).unwrap();
+        assert_eq!(boot_code::detect_loader(VbrFileSystem::Fat32, &vbr), boot_code::BootLoaderKind::Bootmgr);
+
+        // 文件内容
+        let mut volume = Fat32Volume::open(PartitionIo::new(
+            file,
+            PARTITION_START_LBA * IMAGE_SECTOR_SIZE,
+            geometry.size_bytes() - PARTITION_START_LBA * IMAGE_SECTOR_SIZE,
+        ))
+        .unwrap();
+        assert_eq!(volume.read_file("bootmgr").unwrap(), b"BOOTMGR");
+        assert_eq!(volume.read_file(r"efi\boot\bootx64.efi").unwrap(), b"EFI");
+        assert_eq!(volume.read_file(r"sources\install.wim").unwrap(), install);
+        let root: Vec<String> = volume.read_dir("").unwrap().into_iter().map(|e| e.short_name).collect();
+        assert!(root.contains(&"BOOTMGR".to_string()));
+
+        let _ = std::fs::remove_dir_all(source);
+        let _ = std::fs::remove_file(image);
+    }
+
+    #[test]
+    fn test_image_rejects_dual_layout_and_cancel() {
+        let source = temp_dir("cancel_media");
+        write(&source, "bootmgr", b"BOOTMGR");
+        let image = temp_dir("cancel_target").join("usb.img");
+        let mut options = UsbOptions {
+            source: UsbSource::Directory(source.clone()),
+            target: UsbTarget::Image { path: image.clone(), size_bytes: 80 * MIB },
+            layout: UsbLayout::Fat32Ntfs,
+            label: String::new(),
+            boot_partition_mb: DEFAULT_BOOT_PARTITION_MB,
+            pe_path: None,
+            include_drivers: false,
+            vbr_template: None,
+        };
+        assert!(create_install_usb(&options, &mut |_, _| {}, &AtomicBool::new(false)).is_err());
+
+        options.layout = UsbLayout::SingleFat32;
+        let err = create_install_usb(&options, &mut |_, _| {}, &AtomicBool::new(true)).unwrap_err();
+        assert!(format!("{:#}", err).contains("取消"));
+
+        let _ = std::fs::remove_dir_all(source);
+        let _ = std::fs::remove_file(image);
+    }
+}
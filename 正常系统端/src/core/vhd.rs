@@ -0,0 +1,83 @@
+//! 虚拟磁盘（VHD / VHDX）安装
+//!
+//! 虚拟磁盘文件的创建和读写见 `letrecovery_disk::vhd`，
+//! 本模块负责挂载后用 diskpart 格式化，供安装流程释放系统镜像并添加本机启动引导项。
+
+#[cfg(windows)]
+use anyhow::{bail, Context, Result};
+#[cfg(windows)]
+use std::io::Write;
+
+pub use letrecovery_disk::vhd::*;
+
+#[cfg(windows)]
+const MB: u64 = 1024 * 1024;
+
+/// 准备好的虚拟磁盘（已挂载并格式化）
+#[cfg(windows)]
+pub struct PreparedVirtualDisk {
+    pub disk: AttachedVirtualDisk,
+    /// 虚拟磁盘内系统分区的盘符（如 `V:`）
+    pub letter: String,
+}
+
+/// 创建虚拟磁盘、写入分区表、挂载并格式化为 NTFS
+///
+/// 固定大小的磁盘需要在宿主分区上写满全部空间，耗时与容量成正比。
+#[cfg(windows)]
+pub fn prepare_virtual_disk(
+    target: &VirtualDiskInstallTarget,
+    progress: &mut dyn FnMut(u8, &str),
+) -> Result<PreparedVirtualDisk> {
+    use super::diskpart::DiskpartScript;
+    use super::partition_table::MBR_TYPE_NTFS;
+    use super::quick_partition::{get_next_available_drive_letter, get_used_drive_letters};
+
+    target.validate()?;
+    let path = target.file_path();
+    println!("[VHD] 创建 {} {} 虚拟磁盘: {} ({} MB)",
+        target.spec.disk_type, target.spec.format, path.display(), target.spec.size_bytes / MB);
+
+    if path.exists() {
+        bail!("虚拟磁盘文件已存在: {}", path.display());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("创建目录 {} 失败", parent.display()))?;
+    }
+
+    progress(5, "正在创建虚拟磁盘文件...");
+    create_virtual_disk(&path, &target.spec)?;
+
+    progress(40, "正在写入分区表...");
+    let written = (|| -> Result<()> {
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+        let mut disk = VirtualDisk::open(file)?;
+        let size = disk.size();
+        write_single_partition_mbr(&mut disk, size, MBR_TYPE_NTFS)?;
+        disk.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&path);
+        return Err(e.context("写入虚拟磁盘分区表失败"));
+    }
+
+    progress(50, "正在挂载虚拟磁盘...");
+    let disk = AttachedVirtualDisk::attach(&path, target.spec.format)?;
+
+    progress(60, "正在格式化虚拟磁盘...");
+    let letter = get_next_available_drive_letter(&get_used_drive_letters()).context("没有可用的盘符")?;
+    DiskpartScript::new()
+        .select_disk(disk.disk_number)
+        .select_partition(1)
+        .format("ntfs", Some(&target.label))
+        .assign(Some(letter))
+        .run()?
+        .ensure_success("格式化虚拟磁盘失败")?;
+
+    progress(100, "虚拟磁盘已就绪");
+    Ok(PreparedVirtualDisk {
+        disk,
+        letter: format!("{}:", letter),
+    })
+}
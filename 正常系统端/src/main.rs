@@ -2,6 +2,7 @@
 #![allow(dead_code)]
 
 mod app;
+mod cli;
 mod core;
 mod download;
 mod ui;
@@ -40,8 +41,13 @@ fn main() -> eframe::Result<()> {
 
     log::info!("LetRecovery 启动中...");
 
-    // 检查命令行参数，处理PE环境下的自动安装/备份
+    // 检查命令行参数，处理命令行模式和PE环境下的自动安装/备份
     let args: Vec<String> = std::env::args().collect();
+
+    // 命令行子命令（不打开窗口）
+    if cli::is_cli_command(&args) {
+        std::process::exit(cli::run(&args[1..]));
+    }
     
     if args.contains(&"/PEINSTALL".to_string()) || args.contains(&"--pe-install".to_string()) {
         log::info!("检测到PE安装模式，执行自动安装...");
@@ -232,7 +238,6 @@ fn check_dependencies() -> Result<(), Vec<String>> {
         // bin 目录 - 核心工具
         "bin/bcdedit.exe",
         "bin/bcdboot.exe",
-        "bin/bootsect.exe",
         "bin/format.com",
         "bin/aria2c.exe",
         "bin/ghost/ghost64.exe",
@@ -311,8 +316,8 @@ fn run_pe_install() -> eframe::Result<()> {
     let config = match ConfigFileManager::read_install_config(&data_partition) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("[PE INSTALL] 错误: 读取配置失败: {}", e);
-            show_error_message(&format!("读取安装配置失败: {}", e));
+            eprintln!("[PE INSTALL] 错误: 读取配置失败: {:#}", e);
+            show_error_message(&format!("读取安装配置失败: {:#}", e));
             return Ok(());
         }
     };
@@ -320,12 +325,17 @@ fn run_pe_install() -> eframe::Result<()> {
     println!("[PE INSTALL] 目标分区: {}", config.target_partition);
     println!("[PE INSTALL] 镜像文件: {}", config.image_path);
     
-    // 查找安装标记分区
-    let target_partition = match ConfigFileManager::find_install_marker_partition() {
-        Some(p) => p,
-        None => config.target_partition.clone(),
-    };
-    
+    // 按卷标识解析目标分区（盘符在 PE 中可能已变化）
+    let target_partition = ConfigFileManager::resolve_install_target(&config);
+    println!("[PE INSTALL] 实际目标分区: {}", target_partition);
+
+    // 格式化前核对目标分区是否仍是发起安装时的那个分区
+    if let Err(e) = ConfigFileManager::check_install_target(&config, &target_partition) {
+        eprintln!("[PE INSTALL] 错误: {:#}", e);
+        show_error_message(&format!("{:#}", e));
+        return Ok(());
+    }
+
     // 构建完整镜像路径
     let data_dir = ConfigFileManager::get_data_dir(&data_partition);
     let image_path = format!("{}\\{}", data_dir, config.image_path);
@@ -387,8 +397,8 @@ fn run_pe_backup() -> eframe::Result<()> {
     let config = match ConfigFileManager::read_backup_config(&data_partition) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("[PE BACKUP] 错误: 读取配置失败: {}", e);
-            show_error_message(&format!("读取备份配置失败: {}", e));
+            eprintln!("[PE BACKUP] 错误: 读取配置失败: {:#}", e);
+            show_error_message(&format!("读取备份配置失败: {:#}", e));
             return Ok(());
         }
     };
@@ -396,11 +406,9 @@ fn run_pe_backup() -> eframe::Result<()> {
     println!("[PE BACKUP] 源分区: {}", config.source_partition);
     println!("[PE BACKUP] 保存路径: {}", config.save_path);
     
-    // 查找备份标记分区
-    let source_partition = match ConfigFileManager::find_backup_marker_partition() {
-        Some(p) => p,
-        None => config.source_partition.clone(),
-    };
+    // 按卷标识解析源分区（盘符在 PE 中可能已变化）
+    let source_partition = ConfigFileManager::resolve_backup_source(&config);
+    println!("[PE BACKUP] 实际源分区: {}", source_partition);
     
     // 执行备份
     let result = execute_pe_backup(&source_partition, &config);
@@ -131,6 +131,29 @@ impl App {
                     );
                 });
                 
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label(tr!("小白模式使用的配置方案:"));
+                    let current = self.app_config.easy_mode_profile.clone();
+                    let selected_text = if current.is_empty() {
+                        tr!("内置推荐设置").to_string()
+                    } else {
+                        current.clone()
+                    };
+                    egui::ComboBox::from_id_salt("easy_mode_profile_select")
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            if ui.selectable_label(current.is_empty(), tr!("内置推荐设置")).clicked() {
+                                self.app_config.set_easy_mode_profile("");
+                            }
+                            for profile in &self.install_profiles {
+                                if ui.selectable_label(current == profile.name, &profile.name).clicked() {
+                                    self.app_config.set_easy_mode_profile(&profile.name);
+                                }
+                            }
+                        });
+                });
+                
                 ui.add_space(10.0);
                 ui.separator();
                 
@@ -7,6 +7,7 @@ use std::path::PathBuf;
 
 /// 系统安装高级选项
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AdvancedOptions {
     // 系统优化选项
     pub remove_shortcut_arrow: bool,
@@ -0,0 +1,295 @@
+use egui;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::app::{App, Panel};
+use crate::core::auto_job::{self, AfterInstall, JobFile, ResolvedImage, ResolvedTarget};
+use crate::core::install_profile::InstallProfile;
+use crate::core::layout_planner::LayoutPreset;
+use crate::core::quick_partition::{execute_quick_partition, get_physical_disks};
+
+/// 等待执行的自动任务
+pub struct AutoJobState {
+    pub file: JobFile,
+    pub image: ResolvedImage,
+    pub target: ResolvedTarget,
+    pub preset: Option<LayoutPreset>,
+    pub profile: Option<InstallProfile>,
+    /// 倒计时结束时间
+    pub deadline: Instant,
+    /// 按模板分区的结果（成功时为系统分区盘符）
+    pub partition_rx: Option<mpsc::Receiver<Result<String, String>>>,
+}
+
+impl App {
+    /// 远程配置加载完成后检查一次可移动磁盘上的自动任务
+    pub fn check_auto_job(&mut self) {
+        if self.auto_job_checked || self.remote_config_loading {
+            return;
+        }
+        self.auto_job_checked = true;
+
+        let Some(path) = auto_job::find_job_file() else {
+            return;
+        };
+        log::info!("[AUTO JOB] 发现自动任务: {}", path.display());
+
+        match self.prepare_auto_job(&path) {
+            Ok(state) => {
+                log::info!(
+                    "[AUTO JOB] 目标: {}，{} 秒后开始",
+                    state.target.describe(),
+                    state.file.job.countdown_secs
+                );
+                self.auto_job = Some(state);
+            }
+            Err(e) => {
+                log::error!("[AUTO JOB] 自动任务无法执行: {:#}", e);
+                self.show_error(&format!("自动任务 {} 无法执行:\n{:#}", path.display(), e));
+            }
+        }
+    }
+
+    /// 读取任务并确定镜像、配置方案和目标，任何一项不满足都不开始倒计时
+    fn prepare_auto_job(&self, path: &std::path::Path) -> anyhow::Result<AutoJobState> {
+        let file = JobFile::load(path)?;
+        let profile = file.load_profile()?;
+        let catalog = self.config.as_ref().map(|c| c.systems.as_slice()).unwrap_or_default();
+        let image = auto_job::resolve_image(&file, catalog)?;
+
+        let uefi = self
+            .system_info
+            .as_ref()
+            .map(|info| info.boot_mode == crate::core::system_info::BootMode::UEFI)
+            .unwrap_or_else(crate::core::pe::PeManager::is_uefi_boot);
+        let preset = file.job.partition_template.map(|t| t.preset(uefi));
+
+        let disks = auto_job::disk_candidates(&get_physical_disks(), file.drive_letter());
+        let target = auto_job::resolve_target(file.job.target, preset.is_some(), &disks, &self.partitions)?;
+
+        let deadline = Instant::now() + Duration::from_secs(file.job.countdown_secs as u64);
+        Ok(AutoJobState { file, image, target, preset, profile, deadline, partition_rx: None })
+    }
+
+    /// 自动任务倒计时对话框
+    pub fn show_auto_job_dialog(&mut self, ctx: &egui::Context) {
+        let Some(state) = &self.auto_job else {
+            return;
+        };
+
+        if state.partition_rx.is_some() {
+            self.check_auto_job_partition();
+            egui::Window::new("自动任务")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("正在按模板分区，请稍候...");
+                    });
+                });
+            ctx.request_repaint_after(Duration::from_millis(250));
+            return;
+        }
+
+        let remaining = state.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            self.run_auto_job();
+            return;
+        }
+
+        let job = &state.file.job;
+        let image = match &state.image {
+            ResolvedImage::Local(path) => path.clone(),
+            ResolvedImage::Download { display_name, .. } => format!("{}（在线下载）", display_name),
+        };
+        let mut start_now = false;
+        let mut cancel = false;
+
+        egui::Window::new("自动任务")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .min_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!("任务文件: {}", state.file.path.display()));
+                ui.add_space(5.0);
+                egui::Grid::new("auto_job_summary")
+                    .num_columns(2)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("镜像:");
+                        ui.label(&image);
+                        ui.end_row();
+                        ui.label("分卷:");
+                        ui.label(job.index.to_string());
+                        ui.end_row();
+                        ui.label("目标:");
+                        ui.label(format!("{} → {}", job.target, state.target.describe()));
+                        ui.end_row();
+                        if let Some(preset) = state.preset {
+                            ui.label("分区模板:");
+                            ui.label(preset.name());
+                            ui.end_row();
+                        }
+                        ui.label("配置方案:");
+                        ui.label(state.profile.as_ref().map(|p| p.name.as_str()).unwrap_or("当前设置"));
+                        ui.end_row();
+                        ui.label("安装后:");
+                        ui.label(match job.after_install {
+                            AfterInstall::Reboot => "自动重启",
+                            AfterInstall::None => "不重启",
+                        });
+                        ui.end_row();
+                    });
+
+                ui.add_space(10.0);
+                let warning = match (state.preset, state.target.disk_number) {
+                    (Some(_), Some(disk)) => format!("磁盘 {} 上的所有数据将被清除！", disk),
+                    _ => "目标分区上的数据将被清除！".to_string(),
+                };
+                ui.colored_label(egui::Color32::RED, warning);
+                ui.add_space(10.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading(format!("{} 秒后自动开始", remaining.as_secs() + 1));
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("立即开始").clicked() {
+                            start_now = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            cancel = true;
+                        }
+                    });
+                });
+            });
+
+        if cancel {
+            log::info!("[AUTO JOB] 已取消自动任务");
+            self.auto_job = None;
+        } else if start_now {
+            self.run_auto_job();
+        } else {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+    }
+
+    /// 倒计时结束：应用配置方案，需要时先按模板分区
+    fn run_auto_job(&mut self) {
+        let Some(state) = self.auto_job.as_mut() else {
+            return;
+        };
+        log::info!("[AUTO JOB] 开始执行自动任务");
+
+        let profile = state.profile.clone();
+        let after_install = state.file.job.after_install;
+        let template = state.preset.zip(state.target.disk_number);
+        let target_partition = state.target.partition.clone();
+
+        if let Some(profile) = profile {
+            self.apply_install_profile(&profile);
+        }
+        self.auto_reboot = after_install == AfterInstall::Reboot;
+
+        if let Some((preset, disk_number)) = template {
+            let (tx, rx) = mpsc::channel();
+            if let Some(state) = self.auto_job.as_mut() {
+                state.partition_rx = Some(rx);
+            }
+            std::thread::spawn(move || {
+                let _ = tx.send(partition_with_template(disk_number, preset));
+            });
+            return;
+        }
+
+        match target_partition {
+            Some(letter) => self.launch_auto_job_install(&letter),
+            None => {
+                self.auto_job = None;
+                self.show_error("自动任务: 没有确定目标分区");
+            }
+        }
+    }
+
+    fn check_auto_job_partition(&mut self) {
+        let Some(rx) = self.auto_job.as_ref().and_then(|s| s.partition_rx.as_ref()) else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Ok(letter)) => {
+                log::info!("[AUTO JOB] 分区完成，系统分区: {}", letter);
+                self.launch_auto_job_install(&letter);
+            }
+            Ok(Err(e)) => {
+                log::error!("[AUTO JOB] 分区失败: {}", e);
+                self.auto_job = None;
+                self.show_error(&format!("自动任务分区失败: {}", e));
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.auto_job = None;
+                self.show_error("自动任务分区线程异常退出");
+            }
+        }
+    }
+
+    /// 选中目标分区和镜像，按小白模式相同的流程在镜像信息加载后自动开始安装
+    fn launch_auto_job_install(&mut self, target_partition: &str) {
+        let Some(state) = self.auto_job.take() else {
+            return;
+        };
+
+        self.refresh_partitions();
+        let Some(index) = self
+            .partitions
+            .iter()
+            .position(|p| p.letter.eq_ignore_ascii_case(target_partition))
+        else {
+            self.show_error(&format!("自动任务: 找不到目标分区 {}", target_partition));
+            return;
+        };
+        self.selected_partition = Some(index);
+        self.install_volume_index = state.file.job.index;
+
+        match state.image {
+            ResolvedImage::Local(path) => {
+                self.local_image_path = path;
+                self.load_image_volumes();
+                self.easy_mode_pending_auto_start = true;
+                self.current_panel = Panel::SystemInstall;
+            }
+            ResolvedImage::Download { url, file_name, .. } => {
+                let save_dir = crate::utils::path::get_exe_dir()
+                    .join("downloads")
+                    .to_string_lossy()
+                    .to_string();
+                let _ = std::fs::create_dir_all(&save_dir);
+
+                self.pending_download_url = Some(url);
+                self.pending_download_filename = Some(file_name.clone());
+                self.download_save_path = save_dir.clone();
+                self.download_then_install = true;
+                self.download_then_install_path = Some(format!("{}\\{}", save_dir, file_name));
+                self.easy_mode_auto_install = true;
+                self.current_panel = Panel::DownloadProgress;
+            }
+        }
+    }
+}
+
+/// 清空磁盘并按模板分区，返回系统分区盘符
+fn partition_with_template(disk_number: u32, preset: LayoutPreset) -> Result<String, String> {
+    let disk = get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .ok_or_else(|| format!("磁盘 {} 不存在", disk_number))?;
+    let (layouts, system_letter) =
+        auto_job::template_layouts(&disk, preset).map_err(|e| format!("{:#}", e))?;
+
+    let result = execute_quick_partition(&disk, preset.style(), &layouts);
+    if !result.success {
+        return Err(result.message);
+    }
+    Ok(format!("{}:", system_letter))
+}
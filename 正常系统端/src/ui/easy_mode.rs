@@ -425,6 +425,10 @@ impl App {
         let volume = selected_volume.unwrap();
         
         let window_width = 420.0;
+        let pinned_profile = self
+            .easy_mode_pinned_profile()
+            .map(|p| p.name)
+            .unwrap_or_default();
         
         egui::Window::new("确认重装系统")
             .collapsible(false)
@@ -489,17 +493,21 @@ impl App {
                 ui.horizontal(|ui| {
                     let grid_width = 280.0;
                     ui.add_space((window_width - grid_width) / 2.0 - 16.0);
-                    egui::Grid::new("easy_mode_options_grid")
-                        .num_columns(2)
-                        .spacing([20.0, 4.0])
-                        .show(ui, |ui| {
-                            ui.label(egui::RichText::new("• OOBE绕过强制联网").small());
-                            ui.label(egui::RichText::new("• 删除预装UWP应用").small());
-                            ui.end_row();
-                            ui.label(egui::RichText::new("• 导入磁盘控制器驱动").small());
-                            ui.label(egui::RichText::new("• 自动导入当前驱动").small());
-                            ui.end_row();
-                        });
+                    if pinned_profile.is_empty() {
+                        egui::Grid::new("easy_mode_options_grid")
+                            .num_columns(2)
+                            .spacing([20.0, 4.0])
+                            .show(ui, |ui| {
+                                ui.label(egui::RichText::new("• OOBE绕过强制联网").small());
+                                ui.label(egui::RichText::new("• 删除预装UWP应用").small());
+                                ui.end_row();
+                                ui.label(egui::RichText::new("• 导入磁盘控制器驱动").small());
+                                ui.label(egui::RichText::new("• 自动导入当前驱动").small());
+                                ui.end_row();
+                            });
+                    } else {
+                        ui.label(egui::RichText::new(format!("• 配置方案「{}」中的设置", pinned_profile)).small());
+                    }
                 });
                 
                 ui.add_space(20.0);
@@ -533,6 +541,21 @@ impl App {
             });
     }
     
+    /// 设置中为小白模式指定的配置方案（未指定或无法读取时为 None）
+    fn easy_mode_pinned_profile(&self) -> Option<crate::core::install_profile::InstallProfile> {
+        let name = &self.app_config.easy_mode_profile;
+        if name.is_empty() {
+            return None;
+        }
+        match crate::core::install_profile::ProfileStore::new().load(name) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                log::warn!("[EASY MODE] 读取配置方案 {} 失败，使用推荐设置: {:#}", name, e);
+                None
+            }
+        }
+    }
+    
     /// 开始小白模式安装
     fn start_easy_mode_install(
         &mut self,
@@ -548,25 +571,32 @@ impl App {
             .unwrap_or("system.esd")
             .to_string();
         
-        // 设置高级选项（小白模式默认选项）
-        self.advanced_options.bypass_nro = true;  // OOBE绕过强制联网
-        self.advanced_options.remove_uwp_apps = true;  // 删除预装UWP应用
-        self.advanced_options.import_storage_controller_drivers = true;  // 导入磁盘控制器驱动
-        self.advanced_options.custom_volume_label = true;  // 自定义卷标
-        self.advanced_options.volume_label = "OS".to_string();  // 系统盘卷标设置为"OS"
-        
-        // 设置用户名
-        let username = crate::core::app_config::get_current_username()
-            .unwrap_or_else(|| "User".to_string());
-        self.advanced_options.custom_username = true;
-        self.advanced_options.username = username;
+        if let Some(profile) = self.easy_mode_pinned_profile() {
+            // 使用设置中为小白模式指定的配置方案
+            self.apply_install_profile(&profile);
+        } else {
+            // 设置高级选项（小白模式默认选项）
+            self.advanced_options.bypass_nro = true;  // OOBE绕过强制联网
+            self.advanced_options.remove_uwp_apps = true;  // 删除预装UWP应用
+            self.advanced_options.import_storage_controller_drivers = true;  // 导入磁盘控制器驱动
+            self.advanced_options.custom_volume_label = true;  // 自定义卷标
+            self.advanced_options.volume_label = "OS".to_string();  // 系统盘卷标设置为"OS"
+            
+            // 设置用户名
+            let username = crate::core::app_config::get_current_username()
+                .unwrap_or_else(|| "User".to_string());
+            self.advanced_options.custom_username = true;
+            self.advanced_options.username = username;
+            
+            // 设置安装选项
+            self.unattended_install = true;
+            self.driver_action = crate::app::DriverAction::AutoImport;
+            self.auto_reboot = true;
+        }
         
-        // 设置安装选项
+        // 小白模式总是格式化系统盘并写入引导
         self.format_partition = true;
         self.repair_boot = true;
-        self.unattended_install = true;
-        self.driver_action = crate::app::DriverAction::AutoImport;
-        self.auto_reboot = true;
         
         // 选择系统分区
         let system_partition_idx = self.partitions.iter()
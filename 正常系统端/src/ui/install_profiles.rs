@@ -0,0 +1,186 @@
+use egui;
+
+use crate::app::App;
+use crate::core::install_profile::{InstallProfile, ProfileStore};
+
+impl App {
+    /// 当前界面上的安装选项和高级选项
+    pub fn current_install_profile(&self, name: &str) -> InstallProfile {
+        InstallProfile {
+            name: name.trim().to_string(),
+            format_partition: self.format_partition,
+            repair_boot: self.repair_boot,
+            unattended_install: self.unattended_install,
+            driver_action: self.driver_action,
+            auto_reboot: self.auto_reboot,
+            boot_mode: self.selected_boot_mode,
+            boot_side_by_side: self.boot_side_by_side,
+            boot_entry_description: self.boot_entry_description.clone(),
+            advanced_options: self.advanced_options.clone(),
+        }
+    }
+
+    /// 把方案应用到界面上的安装选项
+    pub fn apply_install_profile(&mut self, profile: &InstallProfile) {
+        log::info!("应用配置方案: {}", profile.name);
+        self.format_partition = profile.format_partition;
+        self.repair_boot = profile.repair_boot;
+        self.unattended_install = profile.unattended_install;
+        self.driver_action = profile.driver_action;
+        self.auto_reboot = profile.auto_reboot;
+        self.selected_boot_mode = profile.boot_mode;
+        self.boot_side_by_side = profile.boot_side_by_side;
+        self.boot_entry_description = profile.boot_entry_description.clone();
+        self.advanced_options = profile.advanced_options.clone();
+        self.selected_install_profile = Some(profile.name.clone());
+    }
+
+    /// 重新读取方案列表
+    pub fn reload_install_profiles(&mut self) {
+        self.install_profiles = ProfileStore::new().list();
+        if let Some(ref name) = self.selected_install_profile {
+            if !self.install_profiles.iter().any(|p| &p.name == name) {
+                self.selected_install_profile = None;
+            }
+        }
+    }
+
+    /// 启动时应用默认方案
+    pub fn apply_default_install_profile(&mut self) {
+        self.reload_install_profiles();
+        let name = self.app_config.default_profile.clone();
+        if name.is_empty() {
+            return;
+        }
+        match self.install_profiles.iter().find(|p| p.name == name).cloned() {
+            Some(profile) => self.apply_install_profile(&profile),
+            None => log::warn!("默认配置方案不存在: {}", name),
+        }
+    }
+
+    /// 配置方案栏：选择、保存、删除、设为默认、导入、导出
+    pub fn show_install_profile_bar(&mut self, ui: &mut egui::Ui) {
+        let mut selected = None;
+        ui.horizontal(|ui| {
+            ui.label("配置方案:");
+            let default_profile = self.app_config.default_profile.clone();
+            egui::ComboBox::from_id_salt("install_profile_select")
+                .selected_text(self.selected_install_profile.as_deref().unwrap_or("未选择"))
+                .width(160.0)
+                .show_ui(ui, |ui| {
+                    for profile in &self.install_profiles {
+                        let label = if profile.name == default_profile {
+                            format!("{} (默认)", profile.name)
+                        } else {
+                            profile.name.clone()
+                        };
+                        let is_selected = self.selected_install_profile.as_deref() == Some(profile.name.as_str());
+                        if ui.selectable_label(is_selected, label).clicked() {
+                            selected = Some(profile.clone());
+                        }
+                    }
+                    if self.install_profiles.is_empty() {
+                        ui.label("（还没有保存的方案）");
+                    }
+                });
+
+            if let Some(name) = self.selected_install_profile.clone() {
+                if ui.button("保存").on_hover_text("用当前选项覆盖此方案").clicked() {
+                    self.save_install_profile(&name);
+                }
+                if ui.button("删除").clicked() {
+                    self.delete_install_profile(&name);
+                }
+                if default_profile == name {
+                    if ui.button("取消默认").clicked() {
+                        self.app_config.set_default_profile("");
+                    }
+                } else if ui.button("设为默认").on_hover_text("启动时自动应用此方案").clicked() {
+                    self.app_config.set_default_profile(&name);
+                }
+                if ui.button("导出...").clicked() {
+                    self.export_install_profile(&name);
+                }
+            }
+            if ui.button("导入...").clicked() {
+                self.import_install_profile();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.profile_name_input)
+                    .hint_text("新方案名称")
+                    .desired_width(160.0),
+            );
+            let name = self.profile_name_input.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("另存为方案"))
+                .on_hover_text("保存当前安装选项和高级选项（驱动、无人值守、脚本、自定义文件等）")
+                .clicked()
+            {
+                self.save_install_profile(&name);
+                self.profile_name_input.clear();
+            }
+        });
+
+        if let Some(profile) = selected {
+            self.apply_install_profile(&profile);
+        }
+    }
+
+    fn save_install_profile(&mut self, name: &str) {
+        let profile = self.current_install_profile(name);
+        match ProfileStore::new().save(&profile) {
+            Ok(_) => {
+                self.reload_install_profiles();
+                self.selected_install_profile = Some(profile.name);
+            }
+            Err(e) => self.show_error(&format!("保存配置方案失败: {:#}", e)),
+        }
+    }
+
+    fn delete_install_profile(&mut self, name: &str) {
+        if let Err(e) = ProfileStore::new().delete(name) {
+            self.show_error(&format!("{:#}", e));
+            return;
+        }
+        if self.app_config.default_profile == name {
+            self.app_config.set_default_profile("");
+        }
+        if self.app_config.easy_mode_profile == name {
+            self.app_config.set_easy_mode_profile("");
+        }
+        self.selected_install_profile = None;
+        self.reload_install_profiles();
+    }
+
+    fn import_install_profile(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("配置方案", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+        match ProfileStore::new().import(&path) {
+            Ok(profile) => {
+                self.reload_install_profiles();
+                self.apply_install_profile(&profile);
+            }
+            Err(e) => self.show_error(&format!("导入配置方案失败: {:#}", e)),
+        }
+    }
+
+    fn export_install_profile(&mut self, name: &str) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("配置方案", &["json"])
+            .set_file_name(crate::core::install_profile::profile_file_name(name))
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(e) = ProfileStore::new().export(name, &path) {
+            self.show_error(&format!("{:#}", e));
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use egui;
 use std::sync::mpsc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::app::{App, BootModeSelection, InstallMode};
+use letrecovery_config::{InstallPlan, PlanAction};
+
+use crate::app::{App, BootModeSelection, InstallMode, InstallOptions};
 use crate::core::dism::DismProgress;
-use crate::core::disk::{Partition, PartitionStyle};
+use crate::core::disk::{DataPartitionChoice, Partition, PartitionStyle};
 use crate::core::ghost::Ghost;
 use crate::core::install_config::{ConfigFileManager, InstallConfig};
 use crate::ui::advanced_options::AdvancedOptions;
@@ -189,6 +191,10 @@ impl App {
                     // 使用实际的解密进度（从加密百分比计算得出）
                     self.install_progress.step_progress = progress.percentage;
                     return;
+                } else if let Some(error) = progress.status.strip_prefix("ERROR:") {
+                    println!("[INSTALL UI] 安装失败: {}", error);
+                    self.install_error = Some(error.to_string());
+                    return;
                 }
 
                 if let Some((step, name)) = parse_step_from_status(&progress.status) {
@@ -262,236 +268,28 @@ impl App {
         self.install_progress_rx = Some(progress_rx);
 
         let target_partition = self.install_target_partition.clone();
-        let image_path = self.install_image_path.clone();
-        let volume_index = self.install_volume_index;
-        let options = self.install_options.clone();
-        let advanced_options = self.advanced_options.clone();
-        let partitions: Vec<Partition> = self.partitions.clone();
-        
         let partition_style = self.partitions
             .iter()
             .find(|p| p.letter == target_partition)
             .map(|p| p.partition_style)
             .unwrap_or(PartitionStyle::Unknown);
 
+        let job = DirectInstallJob {
+            target_partition,
+            image_path: self.install_image_path.clone(),
+            volume_index: self.install_volume_index,
+            options: self.install_options.clone(),
+            partitions: self.partitions.clone(),
+            partition_style,
+            unattend_file: None,
+        };
+
         self.install_step = 1;
         self.install_progress.current_step = "格式化分区".to_string();
 
         std::thread::spawn(move || {
             println!("[INSTALL THREAD] 安装线程启动");
-            
-            let temp_dir = std::env::temp_dir();
-            let driver_backup_path = temp_dir.join("LetRecovery_DriverBackup");
-            let driver_backup_str = driver_backup_path.to_string_lossy().to_string();
-
-            // Step 1: 格式化分区
-            send_step(&progress_tx, 1, "格式化分区", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.format_partition {
-                println!("[INSTALL STEP 1] 开始格式化分区: {}", target_partition);
-                send_step(&progress_tx, 1, "格式化分区", 30);
-                match format_partition(&target_partition) {
-                    Ok(_) => println!("[INSTALL STEP 1] 格式化完成"),
-                    Err(e) => println!("[INSTALL STEP 1] 格式化失败: {}", e),
-                }
-                send_step(&progress_tx, 1, "格式化分区", 100);
-            } else {
-                println!("[INSTALL STEP 1] 跳过格式化");
-                send_step(&progress_tx, 1, "格式化分区", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 2: 导出驱动
-            send_step(&progress_tx, 2, "导出驱动", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.export_drivers {
-                println!("[INSTALL STEP 2] 开始导出驱动到: {}", driver_backup_str);
-                send_step(&progress_tx, 2, "导出驱动", 20);
-                
-                match export_drivers(&driver_backup_str) {
-                    Ok(_) => {
-                        println!("[INSTALL STEP 2] 驱动导出成功");
-                        send_step(&progress_tx, 2, "导出驱动", 100);
-                    }
-                    Err(e) => {
-                        println!("[INSTALL STEP 2] 驱动导出失败: {} (继续安装)", e);
-                        send_step(&progress_tx, 2, "导出驱动", 100);
-                    }
-                }
-            } else {
-                println!("[INSTALL STEP 2] 跳过导出驱动");
-                send_step(&progress_tx, 2, "导出驱动", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 3: 释放系统镜像
-            send_step(&progress_tx, 3, "释放系统镜像", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            println!("[INSTALL STEP 3] 开始释放系统镜像");
-
-            let image_lower = image_path.to_lowercase();
-            let is_gho = image_lower.ends_with(".gho") || image_lower.ends_with(".ghs");
-
-            if is_gho {
-                println!("[INSTALL STEP 3] 检测到 GHO 镜像，使用 Ghost 恢复");
-                
-                let ghost = Ghost::new();
-                
-                if !ghost.is_available() {
-                    println!("[INSTALL STEP 3] 错误: Ghost 可执行文件不存在");
-                    send_step(&progress_tx, 3, "释放系统镜像", 100);
-                } else {
-                    let ghost_tx = progress_tx.clone();
-                    let (inner_tx, inner_rx) = mpsc::channel::<DismProgress>();
-                    
-                    std::thread::spawn(move || {
-                        while let Ok(p) = inner_rx.recv() {
-                            let _ = ghost_tx.send(p);
-                        }
-                    });
-                    
-                    match ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, Some(inner_tx)) {
-                        Ok(_) => println!("[INSTALL STEP 3] Ghost 镜像恢复成功"),
-                        Err(e) => println!("[INSTALL STEP 3] Ghost 镜像恢复失败: {}", e),
-                    }
-                }
-                
-                send_step(&progress_tx, 3, "释放系统镜像", 100);
-            } else {
-                println!("[INSTALL STEP 3] 使用 DISM 应用 WIM/ESD 镜像");
-                let dism = crate::core::dism::Dism::new();
-                let apply_dir = format!("{}\\", target_partition);
-                
-                let step_tx = progress_tx.clone();
-                let (inner_tx, inner_rx) = mpsc::channel::<DismProgress>();
-                
-                std::thread::spawn(move || {
-                    while let Ok(p) = inner_rx.recv() {
-                        let _ = step_tx.send(DismProgress {
-                            percentage: p.percentage,
-                            status: "STEP:3:释放系统镜像".to_string(),
-                        });
-                    }
-                });
-                
-                match dism.apply_image(&image_path, &apply_dir, volume_index, Some(inner_tx)) {
-                    Ok(_) => println!("[INSTALL STEP 3] DISM 镜像释放成功"),
-                    Err(e) => println!("[INSTALL STEP 3] DISM 镜像释放失败: {}", e),
-                }
-                send_step(&progress_tx, 3, "释放系统镜像", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 4: 导入驱动（仅在 AutoImport 模式下导入）
-            send_step(&progress_tx, 4, "导入驱动", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            
-            // 判断是否需要导入驱动（只有 AutoImport 模式才导入）
-            let should_import = matches!(options.driver_action, crate::app::DriverAction::AutoImport);
-            
-            if should_import && driver_backup_path.exists() {
-                println!("[INSTALL STEP 4] 开始导入驱动 (AutoImport模式)");
-                send_step(&progress_tx, 4, "导入驱动", 30);
-                
-                match import_drivers(&target_partition, &driver_backup_str) {
-                    Ok(_) => {
-                        println!("[INSTALL STEP 4] 驱动导入成功");
-                        let _ = std::fs::remove_dir_all(&driver_backup_path);
-                        send_step(&progress_tx, 4, "导入驱动", 100);
-                    }
-                    Err(e) => {
-                        println!("[INSTALL STEP 4] 驱动导入失败: {}", e);
-                        let _ = std::fs::remove_dir_all(&driver_backup_path);
-                        send_step(&progress_tx, 4, "导入驱动", 100);
-                    }
-                }
-            } else if matches!(options.driver_action, crate::app::DriverAction::SaveOnly) && driver_backup_path.exists() {
-                // SaveOnly 模式：保留驱动备份到目标分区
-                println!("[INSTALL STEP 4] 仅保存驱动 (SaveOnly模式)");
-                send_step(&progress_tx, 4, "保存驱动", 30);
-                
-                let target_driver_dir = format!("{}\\LetRecovery_Drivers", target_partition);
-                if let Err(e) = copy_dir_recursive(&driver_backup_str, &target_driver_dir) {
-                    println!("[INSTALL STEP 4] 保存驱动到目标分区失败: {}", e);
-                } else {
-                    println!("[INSTALL STEP 4] 驱动已保存到: {}", target_driver_dir);
-                }
-                
-                let _ = std::fs::remove_dir_all(&driver_backup_path);
-                send_step(&progress_tx, 4, "保存驱动", 100);
-            } else {
-                println!("[INSTALL STEP 4] 跳过驱动处理 (driver_action: {:?})", options.driver_action);
-                send_step(&progress_tx, 4, "导入驱动", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 5: 修复引导
-            send_step(&progress_tx, 5, "修复引导", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            if options.repair_boot {
-                println!("[INSTALL STEP 5] 开始修复引导");
-                send_step(&progress_tx, 5, "修复引导", 20);
-                
-                let use_uefi = match options.boot_mode {
-                    BootModeSelection::UEFI => true,
-                    BootModeSelection::Legacy => false,
-                    BootModeSelection::Auto => matches!(partition_style, PartitionStyle::GPT),
-                };
-                
-                println!("[INSTALL STEP 5] 引导模式: {}", if use_uefi { "UEFI" } else { "Legacy" });
-                send_step(&progress_tx, 5, "修复引导", 50);
-                
-                let boot_manager = crate::core::bcdedit::BootManager::new();
-                match boot_manager.repair_boot_advanced(&target_partition, use_uefi) {
-                    Ok(_) => {
-                        println!("[INSTALL STEP 5] 引导修复成功");
-                        
-                        // 如果是 Win7 + UEFI 模式，且启用了 UefiSeven 补丁
-                        if use_uefi && advanced_options.win7_uefi_patch {
-                            println!("[INSTALL STEP 5] 检测到 Win7 UEFI 补丁选项，开始应用 UefiSeven");
-                            send_step(&progress_tx, 5, "应用Win7 UEFI补丁", 70);
-                            
-                            match advanced_options.apply_uefiseven_patch(&target_partition) {
-                                Ok(_) => println!("[INSTALL STEP 5] UefiSeven 补丁应用成功"),
-                                Err(e) => println!("[INSTALL STEP 5] UefiSeven 补丁应用失败: {} (继续安装)", e),
-                            }
-                        }
-                    }
-                    Err(e) => println!("[INSTALL STEP 5] 引导修复失败: {}", e),
-                }
-                send_step(&progress_tx, 5, "修复引导", 100);
-            } else {
-                println!("[INSTALL STEP 5] 跳过修复引导");
-                send_step(&progress_tx, 5, "修复引导", 100);
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 6: 应用高级选项
-            send_step(&progress_tx, 6, "应用高级选项", 0);
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            println!("[INSTALL STEP 6] 应用高级选项");
-            send_step(&progress_tx, 6, "应用高级选项", 20);
-            
-            match advanced_options.apply_to_system(&target_partition) {
-                Ok(_) => println!("[INSTALL STEP 6] 高级选项应用成功"),
-                Err(e) => println!("[INSTALL STEP 6] 高级选项应用失败: {}", e),
-            }
-            send_step(&progress_tx, 6, "应用高级选项", 50);
-            
-            if options.unattended_install {
-                println!("[INSTALL STEP 6] 生成无人值守配置");
-                match generate_unattend_xml(&target_partition, &advanced_options) {
-                    Ok(_) => println!("[INSTALL STEP 6] 无人值守配置生成成功"),
-                    Err(e) => println!("[INSTALL STEP 6] 无人值守配置生成失败: {}", e),
-                }
-            }
-            send_step(&progress_tx, 6, "应用高级选项", 100);
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            // Step 7: 完成
-            send_step(&progress_tx, 7, "完成安装", 100);
-            println!("[INSTALL STEP 7] 安装完成!");
-            println!("[INSTALL] ========== 安装结束 ==========");
+            run_direct_install(job, &progress_tx);
         });
     }
 
@@ -557,14 +355,17 @@ impl App {
             send_step(&progress_tx, 2, "安装PE引导", 30);
             
             let pe_manager = crate::core::pe::PeManager::new();
-            match pe_manager.boot_to_pe(&pe_path, &pe_info.display_name) {
-                Ok(_) => println!("[INSTALL PE STEP 2] PE引导安装成功"),
+            let handoff_nonce = match pe_manager.boot_to_pe(&pe_path, &pe_info.display_name) {
+                Ok(nonce) => {
+                    println!("[INSTALL PE STEP 2] PE引导安装成功");
+                    nonce
+                }
                 Err(e) => {
                     println!("[INSTALL PE STEP 2] PE引导安装失败: {}", e);
                     send_step(&progress_tx, 2, "安装PE引导", 100);
                     return;
                 }
-            }
+            };
             send_step(&progress_tx, 2, "安装PE引导", 100);
             std::thread::sleep(std::time::Duration::from_millis(100));
 
@@ -683,47 +484,18 @@ impl App {
             
             println!("[INSTALL PE STEP 5] 写入配置文件");
             
-            let is_gho = image_path.to_lowercase().ends_with(".gho") 
-                || image_path.to_lowercase().ends_with(".ghs");
-            
-            let install_config = InstallConfig {
-                unattended: options.unattended_install,
-                restore_drivers: options.export_drivers,
-                driver_action_mode: InstallConfig::driver_action_to_mode(options.driver_action),
-                auto_reboot: options.auto_reboot,
-                original_guid: String::new(),
+            // 记录目标分区当前的引导项，PE 修复引导后据此清理旧引导项
+            let install_config = build_install_config(
+                &options,
+                &advanced_options,
                 volume_index,
-                target_partition: target_partition.clone(),
-                image_path: image_filename,
-                is_gho,
-                remove_shortcut_arrow: advanced_options.remove_shortcut_arrow,
-                restore_classic_context_menu: advanced_options.restore_classic_context_menu,
-                bypass_nro: advanced_options.bypass_nro,
-                disable_windows_update: advanced_options.disable_windows_update,
-                disable_windows_defender: advanced_options.disable_windows_defender,
-                disable_reserved_storage: advanced_options.disable_reserved_storage,
-                disable_uac: advanced_options.disable_uac,
-                disable_device_encryption: advanced_options.disable_device_encryption,
-                remove_uwp_apps: advanced_options.remove_uwp_apps,
-                import_storage_controller_drivers: advanced_options.import_storage_controller_drivers,
-                custom_username: if advanced_options.custom_username {
-                    advanced_options.username.clone()
-                } else {
-                    String::new()
-                },
-                volume_label: if advanced_options.custom_volume_label {
-                    advanced_options.volume_label.clone()
-                } else {
-                    String::new()
-                },
-                win7_uefi_patch: advanced_options.win7_uefi_patch,
-                win7_inject_usb3_driver: advanced_options.win7_inject_usb3_driver,
-                win7_inject_nvme_driver: advanced_options.win7_inject_nvme_driver,
-                win7_fix_acpi_bsod: advanced_options.win7_fix_acpi_bsod,
-                win7_fix_storage_bsod: advanced_options.win7_fix_storage_bsod,
-            };
+                &target_partition,
+                image_filename,
+                is_gho_image(&image_path),
+                original_boot_entry(&target_partition),
+            );
             
-            match ConfigFileManager::write_install_config(&target_partition, &data_partition, &install_config) {
+            match ConfigFileManager::write_install_config(&target_partition, &data_partition, &install_config, &handoff_nonce) {
                 Ok(_) => println!("[INSTALL PE STEP 5] 配置文件写入成功"),
                 Err(e) => println!("[INSTALL PE STEP 5] 配置文件写入失败: {}", e),
             }
@@ -746,6 +518,329 @@ impl App {
     }
 }
 
+
+/// 直接安装任务
+pub struct DirectInstallJob {
+    pub target_partition: String,
+    pub image_path: String,
+    pub volume_index: u32,
+    pub options: InstallOptions,
+    pub partitions: Vec<Partition>,
+    pub partition_style: PartitionStyle,
+    /// 自定义无人值守文件（为 None 时按选项生成）
+    pub unattend_file: Option<String>,
+}
+
+/// 执行直接安装（图形界面和命令行共用），返回失败的步骤
+///
+/// 单个步骤失败时记录后继续执行后续步骤，与图形界面的行为一致。
+pub fn run_direct_install(job: DirectInstallJob, progress_tx: &mpsc::Sender<DismProgress>) -> Vec<String> {
+    let DirectInstallJob {
+        target_partition,
+        image_path,
+        volume_index,
+        options,
+        partitions,
+        partition_style,
+        unattend_file,
+    } = job;
+    let advanced_options = &options.advanced_options;
+    let mut errors = Vec::new();
+
+    let driver_backup_path = driver_backup_dir();
+    let driver_backup_str = driver_backup_path.to_string_lossy().to_string();
+
+    // Step 1: 格式化分区（安装到虚拟磁盘时为创建虚拟磁盘）
+    let mut target_partition = target_partition;
+    let mut virtual_disk = None;
+    if let Some(ref vhd_target) = options.virtual_disk {
+        println!("[INSTALL STEP 1] 创建虚拟磁盘: {}", vhd_target.file_path().display());
+        send_step(progress_tx, 1, "创建虚拟磁盘", 0);
+        let step_tx = progress_tx.clone();
+        match crate::core::vhd::prepare_virtual_disk(vhd_target, &mut |p, status| {
+            println!("[INSTALL STEP 1] {}", status);
+            send_step(&step_tx, 1, "创建虚拟磁盘", p);
+        }) {
+            Ok(prepared) => {
+                println!("[INSTALL STEP 1] 虚拟磁盘已挂载到 {}", prepared.letter);
+                target_partition = prepared.letter.clone();
+                virtual_disk = Some(prepared);
+            }
+            Err(e) => {
+                // 没有可写入的目标，后续步骤无法进行
+                println!("[INSTALL STEP 1] 创建虚拟磁盘失败: {:#}", e);
+                let _ = progress_tx.send(DismProgress {
+                    percentage: 0,
+                    status: format!("ERROR:创建虚拟磁盘失败: {:#}", e),
+                });
+                errors.push(format!("创建虚拟磁盘失败: {:#}", e));
+                return errors;
+            }
+        }
+    }
+
+    send_step(progress_tx, 1, "格式化分区", 0);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    if options.format_partition {
+        println!("[INSTALL STEP 1] 开始格式化分区: {}", target_partition);
+        send_step(progress_tx, 1, "格式化分区", 30);
+        match format_partition(&target_partition) {
+            Ok(_) => println!("[INSTALL STEP 1] 格式化完成"),
+            Err(e) => {
+                println!("[INSTALL STEP 1] 格式化失败: {}", e);
+                errors.push(format!("格式化失败: {}", e));
+            }
+        }
+        send_step(progress_tx, 1, "格式化分区", 100);
+    } else {
+        println!("[INSTALL STEP 1] 跳过格式化");
+        send_step(progress_tx, 1, "格式化分区", 100);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // Step 2: 导出驱动
+    send_step(progress_tx, 2, "导出驱动", 0);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    if options.export_drivers {
+        println!("[INSTALL STEP 2] 开始导出驱动到: {}", driver_backup_str);
+        send_step(progress_tx, 2, "导出驱动", 20);
+        
+        match export_drivers(&driver_backup_str) {
+            Ok(_) => {
+                println!("[INSTALL STEP 2] 驱动导出成功");
+                send_step(progress_tx, 2, "导出驱动", 100);
+            }
+            Err(e) => {
+                println!("[INSTALL STEP 2] 驱动导出失败: {} (继续安装)", e);
+                send_step(progress_tx, 2, "导出驱动", 100);
+            }
+        }
+    } else {
+        println!("[INSTALL STEP 2] 跳过导出驱动");
+        send_step(progress_tx, 2, "导出驱动", 100);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // Step 3: 释放系统镜像
+    send_step(progress_tx, 3, "释放系统镜像", 0);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    println!("[INSTALL STEP 3] 开始释放系统镜像");
+
+    if is_gho_image(&image_path) {
+        println!("[INSTALL STEP 3] 检测到 GHO 镜像，使用 Ghost 恢复");
+        
+        let ghost = Ghost::new();
+        
+        if !ghost.is_available() {
+            println!("[INSTALL STEP 3] 错误: Ghost 可执行文件不存在");
+            errors.push("Ghost 可执行文件不存在".to_string());
+            send_step(progress_tx, 3, "释放系统镜像", 100);
+        } else {
+            let ghost_tx = progress_tx.clone();
+            let (inner_tx, inner_rx) = mpsc::channel::<DismProgress>();
+            
+            std::thread::spawn(move || {
+                while let Ok(p) = inner_rx.recv() {
+                    let _ = ghost_tx.send(p);
+                }
+            });
+            
+            match ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, Some(inner_tx)) {
+                Ok(_) => println!("[INSTALL STEP 3] Ghost 镜像恢复成功"),
+                Err(e) => {
+                    println!("[INSTALL STEP 3] Ghost 镜像恢复失败: {}", e);
+                    errors.push(format!("Ghost 镜像恢复失败: {}", e));
+                }
+            }
+        }
+        
+        send_step(progress_tx, 3, "释放系统镜像", 100);
+    } else {
+        println!("[INSTALL STEP 3] 使用 DISM 应用 WIM/ESD 镜像");
+        let dism = crate::core::dism::Dism::new();
+        let apply_dir = format!("{}\\", target_partition);
+        
+        let step_tx = progress_tx.clone();
+        let (inner_tx, inner_rx) = mpsc::channel::<DismProgress>();
+        
+        std::thread::spawn(move || {
+            while let Ok(p) = inner_rx.recv() {
+                let _ = step_tx.send(DismProgress {
+                    percentage: p.percentage,
+                    status: "STEP:3:释放系统镜像".to_string(),
+                });
+            }
+        });
+        
+        match dism.apply_image(&image_path, &apply_dir, volume_index, Some(inner_tx)) {
+            Ok(_) => println!("[INSTALL STEP 3] DISM 镜像释放成功"),
+            Err(e) => {
+                println!("[INSTALL STEP 3] DISM 镜像释放失败: {}", e);
+                errors.push(format!("DISM 镜像释放失败: {}", e));
+            }
+        }
+        send_step(progress_tx, 3, "释放系统镜像", 100);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // Step 4: 导入驱动（仅在 AutoImport 模式下导入）
+    send_step(progress_tx, 4, "导入驱动", 0);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    
+    // 判断是否需要导入驱动（只有 AutoImport 模式才导入）
+    let should_import = matches!(options.driver_action, crate::app::DriverAction::AutoImport);
+    
+    if should_import && driver_backup_path.exists() {
+        println!("[INSTALL STEP 4] 开始导入驱动 (AutoImport模式)");
+        send_step(progress_tx, 4, "导入驱动", 30);
+        
+        match import_drivers(&target_partition, &driver_backup_str) {
+            Ok(_) => {
+                println!("[INSTALL STEP 4] 驱动导入成功");
+                let _ = std::fs::remove_dir_all(&driver_backup_path);
+                send_step(progress_tx, 4, "导入驱动", 100);
+            }
+            Err(e) => {
+                println!("[INSTALL STEP 4] 驱动导入失败: {}", e);
+                let _ = std::fs::remove_dir_all(&driver_backup_path);
+                send_step(progress_tx, 4, "导入驱动", 100);
+            }
+        }
+    } else if matches!(options.driver_action, crate::app::DriverAction::SaveOnly) && driver_backup_path.exists() {
+        // SaveOnly 模式：保留驱动备份到目标分区
+        println!("[INSTALL STEP 4] 仅保存驱动 (SaveOnly模式)");
+        send_step(progress_tx, 4, "保存驱动", 30);
+        
+        let target_driver_dir = format!("{}\\LetRecovery_Drivers", target_partition);
+        if let Err(e) = copy_dir_recursive(&driver_backup_str, &target_driver_dir) {
+            println!("[INSTALL STEP 4] 保存驱动到目标分区失败: {}", e);
+        } else {
+            println!("[INSTALL STEP 4] 驱动已保存到: {}", target_driver_dir);
+        }
+        
+        let _ = std::fs::remove_dir_all(&driver_backup_path);
+        send_step(progress_tx, 4, "保存驱动", 100);
+    } else {
+        println!("[INSTALL STEP 4] 跳过驱动处理 (driver_action: {:?})", options.driver_action);
+        send_step(progress_tx, 4, "导入驱动", 100);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // Step 5: 修复引导
+    send_step(progress_tx, 5, "修复引导", 0);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    if options.repair_boot {
+        println!("[INSTALL STEP 5] 开始修复引导");
+        send_step(progress_tx, 5, "修复引导", 20);
+        
+        let use_uefi = use_uefi_for(options.boot_mode, partition_style);
+        
+        println!("[INSTALL STEP 5] 引导模式: {}", if use_uefi { "UEFI" } else { "Legacy" });
+        send_step(progress_tx, 5, "修复引导", 50);
+        
+        let boot_manager = crate::core::bcdedit::BootManager::new();
+        if let Some(ref vhd_target) = options.virtual_disk {
+            // 虚拟磁盘：在宿主系统的引导菜单中追加本机启动引导项
+            let description = if options.boot_entry_description.is_empty() {
+                vhd_target.file_name.clone()
+            } else {
+                options.boot_entry_description.clone()
+            };
+            match boot_manager.add_vhd_boot_entry(&target_partition, &vhd_target.bcd_device(), use_uefi, &description) {
+                Ok(report) => {
+                    println!("[INSTALL STEP 5] 虚拟磁盘引导添加成功");
+                    println!("[INSTALL STEP 5] {}", report.summary());
+                }
+                Err(e) => {
+                    println!("[INSTALL STEP 5] 虚拟磁盘引导添加失败: {}", e);
+                    errors.push(format!("虚拟磁盘引导添加失败: {}", e));
+                }
+            }
+        } else {
+            let repair_options = crate::core::bcdedit::BootRepairOptions {
+                side_by_side: options.boot_side_by_side,
+                entry_description: options.boot_entry_description.clone(),
+            };
+            match boot_manager.repair_boot_with_options(&target_partition, use_uefi, &repair_options) {
+                Ok(report) => {
+                    println!("[INSTALL STEP 5] 引导修复成功");
+                    println!("[INSTALL STEP 5] {}", report.summary());
+                
+                    // 如果是 Win7 + UEFI 模式，且启用了 UefiSeven 补丁
+                    if use_uefi && advanced_options.win7_uefi_patch {
+                        println!("[INSTALL STEP 5] 检测到 Win7 UEFI 补丁选项，开始应用 UefiSeven");
+                        send_step(progress_tx, 5, "应用Win7 UEFI补丁", 70);
+                    
+                        match advanced_options.apply_uefiseven_patch(&target_partition) {
+                            Ok(_) => println!("[INSTALL STEP 5] UefiSeven 补丁应用成功"),
+                            Err(e) => println!("[INSTALL STEP 5] UefiSeven 补丁应用失败: {} (继续安装)", e),
+                        }
+                    }
+                }
+                Err(e) => {
+                    println!("[INSTALL STEP 5] 引导修复失败: {}", e);
+                    errors.push(format!("引导修复失败: {}", e));
+                }
+            }
+        }
+        send_step(progress_tx, 5, "修复引导", 100);
+    } else {
+        println!("[INSTALL STEP 5] 跳过修复引导");
+        send_step(progress_tx, 5, "修复引导", 100);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // Step 6: 应用高级选项
+    send_step(progress_tx, 6, "应用高级选项", 0);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    println!("[INSTALL STEP 6] 应用高级选项");
+    send_step(progress_tx, 6, "应用高级选项", 20);
+    
+    match advanced_options.apply_to_system(&target_partition) {
+        Ok(_) => println!("[INSTALL STEP 6] 高级选项应用成功"),
+        Err(e) => {
+            println!("[INSTALL STEP 6] 高级选项应用失败: {}", e);
+            errors.push(format!("高级选项应用失败: {}", e));
+        }
+    }
+    send_step(progress_tx, 6, "应用高级选项", 50);
+    
+    if let Some(ref unattend_file) = unattend_file {
+        println!("[INSTALL STEP 6] 使用自定义无人值守配置: {}", unattend_file);
+        match install_unattend_file(&target_partition, unattend_file) {
+            Ok(_) => println!("[INSTALL STEP 6] 无人值守配置写入成功"),
+            Err(e) => {
+                println!("[INSTALL STEP 6] 无人值守配置写入失败: {}", e);
+                errors.push(format!("无人值守配置写入失败: {}", e));
+            }
+        }
+    } else if options.unattended_install {
+        println!("[INSTALL STEP 6] 生成无人值守配置");
+        match generate_unattend_xml(&target_partition, advanced_options) {
+            Ok(_) => println!("[INSTALL STEP 6] 无人值守配置生成成功"),
+            Err(e) => {
+                println!("[INSTALL STEP 6] 无人值守配置生成失败: {}", e);
+                errors.push(format!("无人值守配置生成失败: {}", e));
+            }
+        }
+    }
+    send_step(progress_tx, 6, "应用高级选项", 100);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // 卸载虚拟磁盘（本机启动时由引导程序直接挂载）
+    if let Some(prepared) = virtual_disk {
+        if let Err(e) = prepared.disk.detach() {
+            println!("[INSTALL] 卸载虚拟磁盘失败: {}", e);
+        }
+    }
+
+    // Step 7: 完成
+    send_step(progress_tx, 7, "完成安装", 100);
+    println!("[INSTALL STEP 7] 安装完成!");
+    println!("[INSTALL] ========== 安装结束 ==========");
+
+    errors
+}
 /// 发送步骤消息
 fn send_step(tx: &mpsc::Sender<DismProgress>, step: usize, name: &str, percentage: u8) {
     let _ = tx.send(DismProgress {
@@ -767,6 +862,403 @@ fn parse_step_from_status(status: &str) -> Option<(usize, String)> {
     None
 }
 
+// ==================== 安装决策（实际安装与演练共用） ====================
+
+/// 直接安装时驱动的临时备份目录
+fn driver_backup_dir() -> PathBuf {
+    std::env::temp_dir().join("LetRecovery_DriverBackup")
+}
+
+/// 是否为 Ghost 镜像
+pub fn is_gho_image(image_path: &str) -> bool {
+    let lower = image_path.to_lowercase();
+    lower.ends_with(".gho") || lower.ends_with(".ghs")
+}
+
+/// 修复引导时是否使用 UEFI 模式
+pub fn use_uefi_for(boot_mode: BootModeSelection, partition_style: PartitionStyle) -> bool {
+    match boot_mode {
+        BootModeSelection::UEFI => true,
+        BootModeSelection::Legacy => false,
+        BootModeSelection::Auto => matches!(partition_style, PartitionStyle::GPT),
+    }
+}
+
+/// 目标分区当前的引导项 GUID（没有时为空）
+fn original_boot_entry(target_partition: &str) -> String {
+    crate::core::bcdedit::BootManager::new()
+        .list_loader_entries()
+        .ok()
+        .and_then(|entries| {
+            entries
+                .into_iter()
+                .find(|e| e.is_on_partition(target_partition))
+                .map(|e| e.identifier)
+        })
+        .unwrap_or_default()
+}
+
+/// 生成交给 PE 的安装配置
+fn build_install_config(
+    options: &InstallOptions,
+    advanced_options: &AdvancedOptions,
+    volume_index: u32,
+    target_partition: &str,
+    image_filename: String,
+    is_gho: bool,
+    original_guid: String,
+) -> InstallConfig {
+    InstallConfig {
+        unattended: options.unattended_install,
+        restore_drivers: options.export_drivers,
+        driver_action_mode: options.driver_action.into(),
+        auto_reboot: options.auto_reboot,
+        original_guid,
+        boot_side_by_side: options.boot_side_by_side,
+        boot_entry_description: options.boot_entry_description.clone(),
+        volume_index,
+        target_partition: target_partition.to_string(),
+        image_path: image_filename,
+        is_gho,
+        remove_shortcut_arrow: advanced_options.remove_shortcut_arrow,
+        restore_classic_context_menu: advanced_options.restore_classic_context_menu,
+        bypass_nro: advanced_options.bypass_nro,
+        disable_windows_update: advanced_options.disable_windows_update,
+        disable_windows_defender: advanced_options.disable_windows_defender,
+        disable_reserved_storage: advanced_options.disable_reserved_storage,
+        disable_uac: advanced_options.disable_uac,
+        disable_device_encryption: advanced_options.disable_device_encryption,
+        remove_uwp_apps: advanced_options.remove_uwp_apps,
+        import_storage_controller_drivers: advanced_options.import_storage_controller_drivers,
+        custom_username: if advanced_options.custom_username {
+            advanced_options.username.clone()
+        } else {
+            String::new()
+        },
+        volume_label: if advanced_options.custom_volume_label {
+            advanced_options.volume_label.clone()
+        } else {
+            String::new()
+        },
+        win7_uefi_patch: advanced_options.win7_uefi_patch,
+        win7_inject_usb3_driver: advanced_options.win7_inject_usb3_driver,
+        win7_inject_nvme_driver: advanced_options.win7_inject_nvme_driver,
+        win7_fix_acpi_bsod: advanced_options.win7_fix_acpi_bsod,
+        win7_fix_storage_bsod: advanced_options.win7_fix_storage_bsod,
+        // 卷标识和目标分区快照由 write_install_config 填写
+        ..Default::default()
+    }
+}
+
+/// 高级选项中要向离线系统应用的调整
+fn tweak_names(options: &InstallOptions) -> Vec<String> {
+    build_install_config(options, &options.advanced_options, 1, "", String::new(), false, String::new())
+        .enabled_tweaks()
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+// ==================== 演练 ====================
+
+impl App {
+    /// 生成安装计划（演练），不改动任何磁盘
+    ///
+    /// 直接安装按直接安装线程的步骤生成；通过 PE 安装时，
+    /// 先列出本端的准备工作，再按写入的安装配置列出 PE 将执行的操作。
+    pub fn plan_installation(
+        &self,
+        partition: &Partition,
+        mode: InstallMode,
+        options: &InstallOptions,
+        image_path: &str,
+        volume_index: u32,
+    ) -> InstallPlan {
+        match mode {
+            InstallMode::Direct => plan_direct_install(partition, options, image_path, volume_index),
+            InstallMode::ViaPE => {
+                let pe_info = self.selected_pe_for_install.and_then(|idx| {
+                    self.config.as_ref().and_then(|c| c.pe_list.get(idx).cloned())
+                });
+                plan_pe_install(&partition.letter, options, image_path, volume_index, pe_info)
+            }
+        }
+    }
+}
+
+/// 直接安装的计划
+fn plan_direct_install(
+    partition: &Partition,
+    options: &InstallOptions,
+    image_path: &str,
+    volume_index: u32,
+) -> InstallPlan {
+    let mut plan = InstallPlan::new();
+    let is_gho = is_gho_image(image_path);
+
+    // Step 1: 格式化分区（安装到虚拟磁盘时为创建虚拟磁盘）
+    let target_partition = match &options.virtual_disk {
+        Some(vhd_target) => {
+            plan.push(PlanAction::WriteFile {
+                path: vhd_target.file_path().display().to_string(),
+                purpose: format!(
+                    "{} {} 虚拟磁盘（{} MB）",
+                    vhd_target.spec.disk_type,
+                    vhd_target.spec.format,
+                    vhd_target.spec.size_bytes / (1024 * 1024)
+                ),
+            });
+            plan.push(PlanAction::FormatPartition {
+                partition: "虚拟磁盘".to_string(),
+                label: Some(vhd_target.label.clone()),
+            });
+            "虚拟磁盘".to_string()
+        }
+        None => partition.letter.clone(),
+    };
+    if options.format_partition {
+        plan.push(PlanAction::FormatPartition {
+            partition: target_partition.clone(),
+            label: None,
+        });
+    }
+
+    // Step 2: 导出驱动
+    let driver_backup = driver_backup_dir().display().to_string();
+    if options.export_drivers {
+        plan.push(PlanAction::ExportDrivers {
+            destination: driver_backup.clone(),
+        });
+    }
+
+    // Step 3: 释放系统镜像
+    if is_gho && !Ghost::new().is_available() {
+        plan.note("Ghost 可执行文件不存在，实际安装时无法释放镜像");
+    }
+    plan.push(PlanAction::ApplyImage {
+        image: image_path.to_string(),
+        volume_index,
+        ghost: is_gho,
+        partition: target_partition.clone(),
+    });
+
+    // Step 4: 导入驱动
+    if options.export_drivers {
+        match options.driver_action {
+            crate::app::DriverAction::AutoImport => plan.push(PlanAction::InjectDrivers {
+                partition: target_partition.clone(),
+                source: driver_backup,
+                drivers: Vec::new(),
+            }),
+            crate::app::DriverAction::SaveOnly => plan.push(PlanAction::CopyFile {
+                source: driver_backup,
+                destination: format!("{}\\LetRecovery_Drivers", target_partition),
+            }),
+            _ => {}
+        }
+    }
+
+    // Step 5: 修复引导
+    if options.repair_boot {
+        let use_uefi = use_uefi_for(options.boot_mode, partition.partition_style);
+        match &options.virtual_disk {
+            Some(vhd_target) => plan.push(PlanAction::WriteBootEntry {
+                partition: vhd_target.bcd_device(),
+                uefi: Some(use_uefi),
+                description: if options.boot_entry_description.is_empty() {
+                    vhd_target.file_name.clone()
+                } else {
+                    options.boot_entry_description.clone()
+                },
+                side_by_side: true,
+            }),
+            None => plan.push(PlanAction::WriteBootEntry {
+                partition: target_partition.clone(),
+                uefi: Some(use_uefi),
+                description: options.boot_entry_description.clone(),
+                side_by_side: options.boot_side_by_side,
+            }),
+        }
+    }
+
+    // Step 6: 应用高级选项
+    plan.push(PlanAction::ApplyTweaks {
+        partition: target_partition.clone(),
+        tweaks: tweak_names(options),
+    });
+    if options.unattended_install {
+        plan.push(PlanAction::WriteFile {
+            path: format!("{}\\Windows\\Panther\\unattend.xml", target_partition),
+            purpose: "无人值守配置".to_string(),
+        });
+    }
+
+    if options.auto_reboot {
+        plan.push(PlanAction::Reboot);
+    }
+    plan
+}
+
+/// 通过 PE 安装的计划
+fn plan_pe_install(
+    target_partition: &str,
+    options: &InstallOptions,
+    image_path: &str,
+    volume_index: u32,
+    pe_info: Option<crate::download::config::OnlinePE>,
+) -> InstallPlan {
+    let mut plan = InstallPlan::new();
+    let advanced_options = &options.advanced_options;
+
+    // Step 1-2: 检查PE环境、安装PE引导
+    let Some(pe_info) = pe_info else {
+        plan.note("未选择PE环境，无法安装");
+        return plan;
+    };
+    let (pe_exists, pe_path) = crate::core::pe::PeManager::check_pe_exists(&pe_info.filename);
+    if !pe_exists {
+        plan.note(format!("PE文件 {} 不存在，实际安装前会先下载", pe_info.filename));
+    }
+    plan.push(PlanAction::AddPeBootEntry {
+        pe_file: if pe_exists { pe_path } else { pe_info.filename.clone() },
+        description: pe_info.display_name.clone(),
+    });
+
+    // Step 3: 选择数据分区（只查询，不分割）
+    let image_size = match std::fs::metadata(image_path) {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            plan.note(format!("无法获取镜像文件大小: {}", e));
+            return plan;
+        }
+    };
+    let (data_partition, auto_created) =
+        match crate::core::disk::DiskManager::choose_data_partition(target_partition, image_size) {
+            Ok(Some(DataPartitionChoice::Existing(partition))) => (partition, false),
+            Ok(Some(DataPartitionChoice::Shrink { from, size_mb, .. })) => {
+                plan.push(PlanAction::CreateDataPartition {
+                    shrink_partition: format!("{}:", from),
+                    size_mb,
+                });
+                ("新分区".to_string(), true)
+            }
+            Ok(None) => {
+                plan.note("没有找到可用的数据分区，且无法自动创建");
+                return plan;
+            }
+            Err(e) => {
+                plan.note(format!("查找数据分区失败: {}", e));
+                return plan;
+            }
+        };
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+
+    let should_export = matches!(
+        options.driver_action,
+        crate::app::DriverAction::SaveOnly | crate::app::DriverAction::AutoImport
+    );
+    if should_export {
+        plan.push(PlanAction::ExportDrivers {
+            destination: format!("{}\\drivers", data_dir),
+        });
+    }
+
+    // Step 4: 复制镜像文件
+    let image_filename = Path::new(image_path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let staged_image = format!("{}\\{}", data_dir, image_filename);
+    plan.push(PlanAction::CopyFile {
+        source: image_path.to_string(),
+        destination: staged_image.clone(),
+    });
+    if advanced_options.win7_uefi_patch {
+        plan.push(PlanAction::CopyFile {
+            source: "uefiseven".to_string(),
+            destination: format!("{}\\uefiseven", data_dir),
+        });
+    }
+
+    // Step 5: 写入配置文件
+    let config = build_install_config(
+        options,
+        advanced_options,
+        volume_index,
+        target_partition,
+        image_filename,
+        is_gho_image(image_path),
+        original_boot_entry(target_partition),
+    );
+    plan.push(PlanAction::WriteFile {
+        path: format!("{}\\{}", data_dir, letrecovery_config::INSTALL_CONFIG_FILE),
+        purpose: format!("安装配置（并在 {} 写入安装标记）", target_partition),
+    });
+    plan.push(PlanAction::Reboot);
+
+    // PE 中按安装配置执行的操作
+    plan.push(PlanAction::FormatPartition {
+        partition: target_partition.to_string(),
+        label: Some(config.volume_label.clone()).filter(|label| !label.is_empty()),
+    });
+    if !config.is_gho {
+        plan.push(PlanAction::ClearPartition {
+            partition: target_partition.to_string(),
+        });
+    }
+    plan.push(PlanAction::ApplyImage {
+        image: staged_image,
+        volume_index: config.volume_index,
+        ghost: config.is_gho,
+        partition: target_partition.to_string(),
+    });
+    if config.should_import_drivers() {
+        plan.push(PlanAction::InjectDrivers {
+            partition: target_partition.to_string(),
+            source: format!("{}\\drivers", data_dir),
+            drivers: Vec::new(),
+        });
+    }
+    plan.push(PlanAction::WriteBootEntry {
+        partition: target_partition.to_string(),
+        uefi: None,
+        description: config.boot_entry_description.clone(),
+        side_by_side: config.boot_side_by_side,
+    });
+    if !config.boot_side_by_side && !config.original_guid.is_empty() {
+        plan.push(PlanAction::DeleteBootEntry {
+            identifier: config.original_guid.clone(),
+            reason: format!("原系统引导项，仍指向 {} 时删除", target_partition),
+        });
+    }
+    plan.push(PlanAction::ApplyTweaks {
+        partition: target_partition.to_string(),
+        tweaks: config.enabled_tweaks().into_iter().map(str::to_string).collect(),
+    });
+    if config.unattended {
+        plan.push(PlanAction::WriteFile {
+            path: format!("{}\\Windows\\Panther\\unattend.xml", target_partition),
+            purpose: "无人值守配置".to_string(),
+        });
+    }
+    plan.push(PlanAction::CleanupHandoff {
+        data_partition: data_partition.clone(),
+        target_partition: target_partition.to_string(),
+    });
+    if auto_created {
+        plan.push(PlanAction::DeletePartition {
+            partition: data_partition,
+            merge_into: target_partition.to_string(),
+        });
+    }
+    if config.auto_reboot {
+        plan.push(PlanAction::Reboot);
+    }
+    plan.note("PE 中的操作按安装配置推算；进入 PE 后可用 --dry-run 参数查看实际计划");
+    plan
+}
+
 /// 格式化分区
 fn format_partition(partition: &str) -> anyhow::Result<()> {
     use crate::utils::cmd::create_command;
@@ -1029,17 +1521,31 @@ fn generate_unattend_xml(target_partition: &str, options: &AdvancedOptions) -> a
     </settings>
 </unattend>"#, arch = arch_str, oobe_section = oobe_section, username = username, first_logon_commands = first_logon_commands);
 
+    write_unattend_xml(target_partition, &xml_content)
+}
+
+/// 使用自定义无人值守文件
+fn install_unattend_file(target_partition: &str, unattend_file: &str) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let xml_content = std::fs::read_to_string(unattend_file)
+        .with_context(|| format!("读取无人值守文件失败: {}", unattend_file))?;
+    write_unattend_xml(target_partition, &xml_content)
+}
+
+/// 把无人值守配置写入目标系统的 Panther 目录（存在 Sysprep 目录时一并写入）
+fn write_unattend_xml(target_partition: &str, xml_content: &str) -> anyhow::Result<()> {
     let panther_dir = format!("{}\\Windows\\Panther", target_partition);
     std::fs::create_dir_all(&panther_dir)?;
     
     let unattend_path = format!("{}\\unattend.xml", panther_dir);
-    std::fs::write(&unattend_path, &xml_content)?;
+    std::fs::write(&unattend_path, xml_content)?;
     println!("[UNATTEND] 已写入: {}", unattend_path);
     
     let sysprep_dir = format!("{}\\Windows\\System32\\Sysprep", target_partition);
     if Path::new(&sysprep_dir).exists() {
         let sysprep_unattend = format!("{}\\unattend.xml", sysprep_dir);
-        let _ = std::fs::write(&sysprep_unattend, &xml_content);
+        let _ = std::fs::write(&sysprep_unattend, xml_content);
         println!("[UNATTEND] 已写入: {}", sysprep_unattend);
     }
     
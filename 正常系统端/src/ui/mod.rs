@@ -1,9 +1,11 @@
 pub mod about;
 pub mod advanced_options;
+pub mod auto_job;
 pub mod download_progress;
 pub mod easy_mode;
 pub mod embedded_assets;
 pub mod hardware_info;
+pub mod install_profiles;
 pub mod install_progress;
 pub mod online_download;
 pub mod system_backup;
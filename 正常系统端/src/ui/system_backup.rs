@@ -4,6 +4,7 @@ use std::path::Path;
 
 use crate::app::{App, BackupFormat, BackupMode, Panel};
 use crate::core::dism::{Dism, DismProgress};
+use crate::core::raw_image::{self, RawBackupOptions};
 use crate::core::install_config::{BackupConfig, ConfigFileManager};
 
 impl App {
@@ -125,6 +126,11 @@ impl App {
                         BackupFormat::Gho,
                         "GHO (Ghost)",
                     );
+                    ui.selectable_value(
+                        &mut self.backup_format,
+                        BackupFormat::Raw,
+                        "RAW (扇区级)",
+                    );
                 });
             
             // 显示格式说明
@@ -141,6 +147,9 @@ impl App {
                 BackupFormat::Gho => {
                     ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "需要Ghost工具支持");
                 }
+                BackupFormat::Raw => {
+                    ui.label("逐扇区备份，支持BitLocker、Linux等任意分区");
+                }
             }
         });
 
@@ -200,7 +209,13 @@ impl App {
         ui.add_space(15.0);
 
         // 备份选项
-        ui.checkbox(&mut self.backup_incremental, "增量备份 (追加到现有镜像)");
+        if self.backup_format == BackupFormat::Raw {
+            self.backup_incremental = false;
+        }
+        ui.add_enabled(
+            self.backup_format != BackupFormat::Raw,
+            egui::Checkbox::new(&mut self.backup_incremental, "增量备份 (追加到现有镜像)"),
+        );
 
         // PE选择（仅在需要通过PE备份时显示）
         if show_pe_selector {
@@ -374,6 +389,11 @@ impl App {
     fn check_bitlocker_for_backup(&self) -> Vec<crate::ui::tools::BitLockerPartition> {
         use crate::core::bitlocker::BitLockerManager;
         
+        // 原始扇区镜像直接读取加密后的扇区，不需要解锁
+        if self.backup_format == BackupFormat::Raw {
+            return Vec::new();
+        }
+
         let manager = BitLockerManager::new();
         if !manager.is_available() {
             return Vec::new();
@@ -525,11 +545,32 @@ impl App {
         let name = self.backup_name.clone();
         let description = self.backup_description.clone();
         let is_incremental = self.backup_incremental;
+        let backup_format = self.backup_format;
+        let letter = source_partition.letter.chars().next().unwrap_or('C');
 
         std::thread::spawn(move || {
             let dism = Dism::new();
             
-            let result = if is_incremental && Path::new(&image_file).exists() {
+            let result = if backup_format == BackupFormat::Raw {
+                let options = RawBackupOptions {
+                    description: format!("{} {}", name, description).trim().to_string(),
+                    ..Default::default()
+                };
+                let tx = progress_tx.clone();
+                raw_image::backup_device(
+                    &raw_image::volume_device_path(letter),
+                    Path::new(&image_file),
+                    &options,
+                    &mut |percentage, status| {
+                        // 100% 由下面的完成消息发送
+                        let _ = tx.send(DismProgress {
+                            percentage: percentage.min(99),
+                            status: status.to_string(),
+                        });
+                    },
+                )
+                .map(|summary| println!("[BACKUP] 原始镜像备份完成: {}", summary.summary()))
+            } else if is_incremental && Path::new(&image_file).exists() {
                 dism.append_image(&image_file, &capture_dir, &name, &description, Some(progress_tx.clone()))
             } else {
                 dism.capture_image(&image_file, &capture_dir, &name, &description, Some(progress_tx.clone()))
@@ -604,13 +645,16 @@ impl App {
             });
             
             let pe_manager = crate::core::pe::PeManager::new();
-            if let Err(e) = pe_manager.boot_to_pe(&pe_path, &pe_info.display_name) {
-                let _ = progress_tx.send(DismProgress {
-                    percentage: 0,
-                    status: format!("备份失败: PE引导安装失败 {}", e),
-                });
-                return;
-            }
+            let handoff_nonce = match pe_manager.boot_to_pe(&pe_path, &pe_info.display_name) {
+                Ok(nonce) => nonce,
+                Err(e) => {
+                    let _ = progress_tx.send(DismProgress {
+                        percentage: 0,
+                        status: format!("备份失败: PE引导安装失败 {}", e),
+                    });
+                    return;
+                }
+            };
 
             // Step 3: 写入配置文件
             let _ = progress_tx.send(DismProgress {
@@ -629,9 +673,11 @@ impl App {
                 incremental: is_incremental,
                 format: backup_format,
                 swm_split_size: swm_split_size,
+                // 卷标识由 write_backup_config 填写
+                ..Default::default()
             };
             
-            if let Err(e) = ConfigFileManager::write_backup_config(&source_letter, &data_partition, &backup_config) {
+            if let Err(e) = ConfigFileManager::write_backup_config(&source_letter, &data_partition, &backup_config, &handoff_nonce) {
                 let _ = progress_tx.send(DismProgress {
                     percentage: 0,
                     status: format!("备份失败: 配置文件写入失败 {}", e),
@@ -1,9 +1,10 @@
 use egui;
 use std::sync::mpsc;
 
-use crate::app::{App, BootModeSelection, UnattendCheckResult};
+use crate::app::{App, BootModeSelection, InstallTargetKind, UnattendCheckResult};
 use crate::core::disk::{Partition, PartitionStyle};
 use crate::core::dism::ImageInfo;
+use crate::core::vhd::{self, VirtualDiskFormat, VirtualDiskInstallTarget, VirtualDiskSpec, VirtualDiskType};
 
 /// ISO 挂载结果
 pub enum IsoMountResult {
@@ -177,8 +178,17 @@ impl App {
         ui.add_space(10.0);
         ui.separator();
 
+        // 安装目标类型
+        let vhd_mode = self.install_target_kind == InstallTargetKind::VirtualDisk;
+        ui.horizontal(|ui| {
+            ui.label("安装目标:");
+            ui.radio_value(&mut self.install_target_kind, InstallTargetKind::Partition, "分区");
+            ui.radio_value(&mut self.install_target_kind, InstallTargetKind::VirtualDisk, "虚拟磁盘 (VHD/VHDX)")
+                .on_hover_text("在数据分区上创建虚拟磁盘文件并安装系统，开机时从启动菜单选择进入，\n适合在同一台机器上保留多个 Windows 版本");
+        });
+
         // 分区选择表格
-        ui.label("选择安装分区:");
+        ui.label(if vhd_mode { "选择存放虚拟磁盘文件的分区:" } else { "选择安装分区:" });
 
         let partitions_clone: Vec<Partition> = self.partitions.clone();
         let mut partition_clicked: Option<usize> = None;
@@ -261,17 +271,27 @@ impl App {
         // 检查无人值守检测状态
         self.check_unattend_status();
 
+        // 虚拟磁盘选项
+        if vhd_mode {
+            self.show_virtual_disk_options(ui);
+        }
+
         ui.add_space(10.0);
         ui.separator();
 
+        // 配置方案
+        self.show_install_profile_bar(ui);
+
         // 安装选项
         ui.horizontal(|ui| {
-            ui.checkbox(&mut self.format_partition, "格式化分区");
+            ui.add_enabled(!vhd_mode, egui::Checkbox::new(&mut self.format_partition, "格式化分区"))
+                .on_disabled_hover_text("安装到虚拟磁盘时不会格式化宿主分区");
             ui.checkbox(&mut self.repair_boot, "添加引导");
             
             // 无人值守选项 - 根据检测结果处理
             // 如果勾选了格式化分区，则无人值守不受限制（因为格式化会清除现有配置）
-            let unattend_disabled = self.partition_has_unattend && !self.format_partition;
+            // 虚拟磁盘是新建的，不受宿主分区中配置文件的影响
+            let unattend_disabled = self.partition_has_unattend && !self.format_partition && !vhd_mode;
             let unattend_tooltip = if self.partition_has_unattend && !self.format_partition {
                 "目标分区已存在无人值守配置文件，无法启用此选项以避免冲突。\n勾选「格式化分区」可解除此限制。"
             } else if self.partition_has_unattend && self.format_partition {
@@ -351,6 +371,29 @@ impl App {
             }
         });
 
+        // 多系统并存（虚拟磁盘总是保留原系统）
+        ui.horizontal(|ui| {
+            if vhd_mode {
+                ui.add_enabled(false, egui::Checkbox::new(&mut true, "保留原系统（多系统并存）"))
+                    .on_disabled_hover_text("虚拟磁盘中的系统总是作为新引导项追加在启动菜单末尾");
+            } else {
+                ui.add_enabled(
+                    self.repair_boot,
+                    egui::Checkbox::new(&mut self.boot_side_by_side, "保留原系统（多系统并存）"),
+                )
+                .on_hover_text("保留原有引导项和其他系统的引导程序，新系统引导项追加在启动菜单末尾");
+            }
+
+            if self.repair_boot && (self.boot_side_by_side || vhd_mode) {
+                ui.label("引导项名称:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.boot_entry_description)
+                        .hint_text("默认")
+                        .desired_width(160.0),
+                );
+            }
+        });
+
         // PE选择（仅在需要通过PE安装时显示）
         if show_pe_selector {
             ui.add_space(10.0);
@@ -426,22 +469,28 @@ impl App {
             && !self.local_image_path.is_empty()
             && (self.local_image_path.ends_with(".gho") || self.selected_volume.is_some())
             && !install_blocked
-            && (!show_pe_selector || self.selected_pe_for_install.is_some());
+            && (!show_pe_selector || self.selected_pe_for_install.is_some())
+            && (!vhd_mode || self.check_virtual_disk_target().is_ok());
 
         ui.horizontal(|ui| {
             if ui
                 .add_enabled(
                     can_install && !self.is_installing,
-                    egui::Button::new("开始安装").min_size(egui::vec2(120.0, 35.0)),
+                    egui::Button::new(if self.install_dry_run { "演练安装" } else { "开始安装" })
+                        .min_size(egui::vec2(120.0, 35.0)),
                 )
                 .clicked()
             {
                 self.start_installation();
             }
+            ui.checkbox(&mut self.install_dry_run, "仅演练")
+                .on_hover_text("只列出将要执行的操作（格式化、释放镜像、驱动、引导项等），不改动任何磁盘");
 
             // 显示安装模式提示
             if can_install {
-                if needs_pe && !is_pe {
+                if vhd_mode {
+                    ui.label("(安装到虚拟磁盘)");
+                } else if needs_pe && !is_pe {
                     ui.label("(将通过PE环境安装)");
                 } else {
                     ui.label("(直接安装)");
@@ -450,7 +499,7 @@ impl App {
         });
 
         // 警告：安装到有系统的分区
-        if let Some(idx) = self.selected_partition {
+        if let Some(idx) = self.selected_partition.filter(|_| !vhd_mode) {
             if let Some(partition) = self.partitions.get(idx) {
                 if partition.has_windows && !self.format_partition {
                     ui.add_space(5.0);
@@ -461,6 +510,32 @@ impl App {
                 }
             }
         }
+
+        // 警告：表面测试发现目标分区有坏道（虚拟磁盘模式下为宿主分区）
+        if let Some(warning) = self.surface_warning_for_target() {
+            ui.add_space(5.0);
+            let color = if warning.has_bad_sectors() {
+                egui::Color32::RED
+            } else {
+                egui::Color32::from_rgb(255, 165, 0)
+            };
+            ui.colored_label(color, warning.message());
+        }
+    }
+
+    /// 目标分区的表面测试警告（切换分区或重新扫描后才重新读取结果）
+    fn surface_warning_for_target(&mut self) -> Option<crate::core::surface_test::SurfaceWarning> {
+        use crate::core::surface_test;
+
+        let letter = self
+            .selected_partition
+            .and_then(|i| self.partitions.get(i))
+            .and_then(|p| p.letter.chars().next())?;
+        if self.install_surface_warning.as_ref().map(|(l, _)| *l) != Some(letter) {
+            let warning = surface_test::check_partition(&surface_test::results_dir(), letter);
+            self.install_surface_warning = Some((letter, warning));
+        }
+        self.install_surface_warning.as_ref().and_then(|(_, w)| w.clone())
     }
 
     /// 检查是否需要通过PE安装
@@ -469,6 +544,11 @@ impl App {
         if self.is_pe_environment() {
             return false;
         }
+
+        // 安装到虚拟磁盘不会改动当前系统分区
+        if self.install_target_kind == InstallTargetKind::VirtualDisk {
+            return false;
+        }
         
         // 检查目标分区是否是当前系统分区
         if let Some(idx) = self.selected_partition {
@@ -802,6 +882,105 @@ impl App {
         }
     }
 
+    /// 虚拟磁盘选项（格式、类型、容量、文件名）
+    fn show_virtual_disk_options(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("虚拟磁盘:");
+            egui::ComboBox::from_id_salt("vhd_format_select")
+                .selected_text(format!("{}", self.vhd_format))
+                .width(70.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.vhd_format, VirtualDiskFormat::Vhdx, "VHDX");
+                    ui.selectable_value(&mut self.vhd_format, VirtualDiskFormat::Vhd, "VHD");
+                });
+            egui::ComboBox::from_id_salt("vhd_type_select")
+                .selected_text(format!("{}", self.vhd_type))
+                .width(90.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.vhd_type, VirtualDiskType::Dynamic, "动态扩展");
+                    ui.selectable_value(&mut self.vhd_type, VirtualDiskType::Fixed, "固定大小");
+                });
+            ui.label("容量:");
+            ui.add(egui::DragValue::new(&mut self.vhd_size_gb).range(1..=65536).suffix(" GB"));
+            ui.label("文件名:");
+            ui.add(egui::TextEdit::singleline(&mut self.vhd_file_name).desired_width(140.0));
+            ui.label(format!(".{}", self.vhd_format.extension()));
+        });
+
+        if let Some(partition) = self.selected_partition.and_then(|i| self.partitions.get(i)) {
+            let target = self.build_virtual_disk_target(partition);
+            match self.check_virtual_disk_target() {
+                Ok(_) => {
+                    ui.label(format!("将创建: {}", target.file_path().display()));
+                    if self.vhd_type == VirtualDiskType::Dynamic
+                        && partition.free_size_mb < self.vhd_size_gb * 1024
+                    {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 165, 0),
+                            "⚠ 宿主分区可用空间小于虚拟磁盘容量，写满后虚拟磁盘中的系统将无法启动",
+                        );
+                    }
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::RED, format!("❌ {}", e));
+                }
+            }
+        }
+
+        if self.vhd_size_gb < vhd::RECOMMENDED_MIN_SIZE_GB {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 165, 0),
+                format!("⚠ 建议容量不小于 {} GB", vhd::RECOMMENDED_MIN_SIZE_GB),
+            );
+        }
+        if self.vhd_type == VirtualDiskType::Fixed {
+            ui.label("固定大小的虚拟磁盘需要在宿主分区上写满全部容量，创建耗时较长");
+        }
+        if self.vhd_format == VirtualDiskFormat::Vhd {
+            ui.label("VHD 适用于 Windows 7，Windows 8 及以上建议使用 VHDX");
+        }
+    }
+
+    /// 根据界面选项生成虚拟磁盘安装目标
+    fn build_virtual_disk_target(&self, host: &Partition) -> VirtualDiskInstallTarget {
+        VirtualDiskInstallTarget {
+            host_partition: host.letter.clone(),
+            file_name: vhd::normalize_file_name(&self.vhd_file_name, self.vhd_format),
+            spec: VirtualDiskSpec::new(self.vhd_format, self.vhd_type, self.vhd_size_gb * 1024 * 1024 * 1024),
+            label: self
+                .vhd_file_name
+                .trim()
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .take(32)
+                .collect(),
+        }
+    }
+
+    /// 检查虚拟磁盘安装目标是否可用
+    fn check_virtual_disk_target(&self) -> anyhow::Result<()> {
+        let partition = self
+            .selected_partition
+            .and_then(|i| self.partitions.get(i))
+            .ok_or_else(|| anyhow::anyhow!("请选择存放虚拟磁盘文件的分区"))?;
+        let target = self.build_virtual_disk_target(partition);
+        target.validate()?;
+
+        if target.file_path().exists() {
+            anyhow::bail!("文件已存在: {}", target.file_path().display());
+        }
+        let required_mb = target.spec.initial_file_size().div_ceil(1024 * 1024);
+        if partition.free_size_mb < required_mb {
+            anyhow::bail!(
+                "{} 可用空间不足，需要 {}",
+                partition.letter,
+                Self::format_size(required_mb)
+            );
+        }
+        Ok(())
+    }
+
     pub fn format_size(size_mb: u64) -> String {
         if size_mb >= 1024 {
             format!("{:.1} GB", size_mb as f64 / 1024.0)
@@ -969,6 +1148,12 @@ impl App {
         }
         let partition = partition.unwrap();
 
+        // 演练：只生成安装计划
+        if self.install_dry_run {
+            self.preview_installation(&partition);
+            return;
+        }
+
         // 1. 检查是否有需要解锁的 BitLocker 分区 (优先级最高)
         let locked_partitions = self.check_bitlocker_for_install();
         if !locked_partitions.is_empty() {
@@ -1003,36 +1188,78 @@ impl App {
         self.continue_installation_after_bitlocker();
     }
     
-    /// 初始化安装状态变量
-    fn initialize_install_state(&mut self, partition: &crate::core::disk::Partition, image_path: String) {
+    /// 按界面上的选项确定安装方式、安装选项和分卷索引
+    fn resolve_install_options(
+        &self,
+        partition: &crate::core::disk::Partition,
+    ) -> (crate::app::InstallMode, crate::app::InstallOptions, u32) {
         let volume_index = self
             .selected_volume
             .and_then(|i| self.image_volumes.get(i).map(|v| v.index))
             .unwrap_or(1);
 
-        let is_system_partition = partition.is_system_partition;
         let is_pe = self.is_pe_environment();
+        let virtual_disk = match self.install_target_kind {
+            InstallTargetKind::VirtualDisk => Some(self.build_virtual_disk_target(partition)),
+            InstallTargetKind::Partition => None,
+        };
 
-        self.install_mode = if is_pe || !is_system_partition {
+        let install_mode = if is_pe || !partition.is_system_partition || virtual_disk.is_some() {
             crate::app::InstallMode::Direct
         } else {
             crate::app::InstallMode::ViaPE
         };
 
-        self.install_options = crate::app::InstallOptions {
-            format_partition: self.format_partition,
+        let install_options = crate::app::InstallOptions {
+            format_partition: self.format_partition && virtual_disk.is_none(),
             repair_boot: self.repair_boot,
             unattended_install: self.unattended_install,
             export_drivers: matches!(self.driver_action, crate::app::DriverAction::SaveOnly | crate::app::DriverAction::AutoImport),
             auto_reboot: self.auto_reboot,
             boot_mode: self.selected_boot_mode,
+            boot_side_by_side: self.boot_side_by_side,
+            boot_entry_description: self.boot_entry_description.trim().to_string(),
             advanced_options: self.advanced_options.clone(),
             driver_action: self.driver_action,
+            virtual_disk,
         };
 
+        (install_mode, install_options, volume_index)
+    }
+
+    /// 演练安装：生成安装计划并显示，不解锁/解密 BitLocker，也不改动任何磁盘
+    fn preview_installation(&mut self, partition: &crate::core::disk::Partition) {
+        let (install_mode, install_options, volume_index) = self.resolve_install_options(partition);
+        let mut plan = self.plan_installation(
+            partition,
+            install_mode,
+            &install_options,
+            &self.local_image_path,
+            volume_index,
+        );
+
+        let locked_partitions = self.check_bitlocker_for_install();
+        if !locked_partitions.is_empty() {
+            let letters: Vec<&str> = locked_partitions.iter().map(|p| p.letter.as_str()).collect();
+            plan.notes.insert(0, format!("实际安装前需要先解锁 BitLocker 分区: {}", letters.join(", ")));
+        }
+
+        println!("[INSTALL] ========== 演练安装 ==========");
+        println!("{}", plan.to_text());
+        self.install_plan = Some(plan);
+    }
+
+    /// 初始化安装状态变量
+    fn initialize_install_state(&mut self, partition: &crate::core::disk::Partition, image_path: String) {
+        let is_system_partition = partition.is_system_partition;
+        let (install_mode, install_options, volume_index) = self.resolve_install_options(partition);
+        self.install_mode = install_mode;
+        self.install_options = install_options;
+
         self.is_installing = true;
         self.current_panel = crate::app::Panel::InstallProgress;
         self.install_progress = crate::app::InstallProgress::default();
+        self.install_error = None;
         self.auto_reboot_triggered = false;
 
         self.install_target_partition = partition.letter.clone();
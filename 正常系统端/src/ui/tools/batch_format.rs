@@ -1,9 +1,13 @@
 //! 批量格式化模块
 //!
-//! 提供分区格式化功能，使用系统 format 命令实现
+//! 提供分区格式化功能：NTFS 使用系统 format 命令，FAT32/exFAT 使用原生格式化
+//! （不受 Windows 32 GB FAT32 限制）
 
 use std::path::Path;
 
+#[cfg(windows)]
+use crate::core::fat_format::{self, FatFormatOptions, NativeFileSystem};
+
 #[cfg(windows)]
 use windows::{
     core::PCWSTR,
@@ -190,9 +194,56 @@ fn get_partition_info(_drive: &str) -> Option<FormatablePartition> {
     None
 }
 
-/// 使用 format.com 格式化分区
+/// 使用原生格式化把分区格式化为 FAT32/exFAT
 #[cfg(windows)]
-pub fn format_partition(letter: &str, label: &str, file_system: &str) -> Result<(), String> {
+fn format_partition_native<F>(
+    drive_letter: char,
+    label: &str,
+    file_system: NativeFileSystem,
+    quick: bool,
+    progress_callback: F,
+) -> Result<(), String>
+where
+    F: Fn(u8, &str),
+{
+    let options = FatFormatOptions {
+        file_system,
+        label: label.to_string(),
+        quick,
+        cluster_size: None,
+    };
+
+    log::info!(
+        "开始原生格式化分区: {}: (文件系统: {}, 卷标: {}, 快速: {})",
+        drive_letter,
+        file_system,
+        label,
+        quick
+    );
+
+    match fat_format::format_drive(drive_letter, &options, &mut |percent, message| {
+        progress_callback(percent, message)
+    }) {
+        Ok(summary) => {
+            log::info!(
+                "分区 {}: 格式化成功 ({}, 簇大小 {} 字节, {} 个簇)",
+                drive_letter,
+                summary.file_system,
+                summary.cluster_size,
+                summary.cluster_count
+            );
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("原生格式化失败: {:#}", e);
+            Err(format!("格式化失败: {:#}", e))
+        }
+    }
+}
+
+/// 格式化分区（NTFS 使用 format.com，FAT32/exFAT 使用原生格式化）
+#[cfg(windows)]
+pub fn format_partition(letter: &str, label: &str, file_system: &str, quick: bool) -> Result<(), String> {
     use crate::utils::cmd::create_command;
     use crate::utils::encoding::gbk_to_utf8;
     
@@ -218,6 +269,10 @@ pub fn format_partition(letter: &str, label: &str, file_system: &str) -> Result<
     // 卷标处理
     let vol_label = if label.is_empty() { "OS" } else { label };
 
+    if let Some(native_fs) = NativeFileSystem::from_name(fs) {
+        return format_partition_native(drive_letter, vol_label, native_fs, quick, |_, _| {});
+    }
+
     log::info!(
         "开始格式化分区: {} (文件系统: {}, 卷标: {})",
         drive,
@@ -226,7 +281,8 @@ pub fn format_partition(letter: &str, label: &str, file_system: &str) -> Result<
     );
 
     // 使用系统 format 命令: format D: /FS:NTFS /V:Label /Q /Y
-    let cmd_args = format!("format {} /FS:{} /V:{} /Q /Y", drive, fs, vol_label);
+    let quick_arg = if quick { " /Q" } else { "" };
+    let cmd_args = format!("format {} /FS:{} /V:{}{} /Y", drive, fs, vol_label, quick_arg);
     
     log::info!("执行命令: cmd /c {}", cmd_args);
 
@@ -272,6 +328,7 @@ pub fn format_partition_with_progress<F>(
     letter: &str, 
     label: &str, 
     file_system: &str,
+    quick: bool,
     progress_callback: F,
 ) -> Result<(), String> 
 where
@@ -300,6 +357,10 @@ where
     };
     let vol_label = if label.is_empty() { "OS" } else { label };
 
+    if let Some(native_fs) = NativeFileSystem::from_name(fs) {
+        return format_partition_native(drive_letter, vol_label, native_fs, quick, progress_callback);
+    }
+
     log::info!(
         "开始格式化分区: {} (文件系统: {}, 卷标: {})",
         drive,
@@ -312,7 +373,8 @@ where
     progress_callback(10, "启动格式化进程...");
 
     // 使用系统 format 命令
-    let cmd_args = format!("format {} /FS:{} /V:{} /Q /Y", drive, fs, vol_label);
+    let quick_arg = if quick { " /Q" } else { "" };
+    let cmd_args = format!("format {} /FS:{} /V:{}{} /Y", drive, fs, vol_label, quick_arg);
 
     log::info!("执行命令: cmd /c {}", cmd_args);
 
@@ -352,7 +414,7 @@ where
 }
 
 #[cfg(not(windows))]
-pub fn format_partition(_letter: &str, _label: &str, _file_system: &str) -> Result<(), String> {
+pub fn format_partition(_letter: &str, _label: &str, _file_system: &str, _quick: bool) -> Result<(), String> {
     Err("仅支持Windows系统".to_string())
 }
 
@@ -361,6 +423,7 @@ pub fn format_partition_with_progress<F>(
     _letter: &str, 
     _label: &str, 
     _file_system: &str,
+    _quick: bool,
     _progress_callback: F,
 ) -> Result<(), String> 
 where
@@ -370,17 +433,44 @@ where
 }
 
 /// 批量格式化分区
-pub fn batch_format_partitions(
+///
+/// `progress` 接收 (盘符, 进度 0-100, 当前步骤)
+pub fn batch_format_partitions<P>(
     partitions: &[String],
     label: &str,
     file_system: &str,
-) -> BatchFormatResult {
+    quick: bool,
+    progress: P,
+) -> BatchFormatResult
+where
+    P: Fn(&str, u8, &str) + Clone + Send + 'static,
+{
     let mut results = Vec::new();
     let mut success_count = 0;
     let mut fail_count = 0;
 
+    // 格式化会覆盖分区引导扇区，先备份所在磁盘的分区表，备份失败则全部取消
+    let letters: Vec<char> = partitions.iter().filter_map(|p| p.chars().next()).collect();
+    if let Err(e) = crate::core::partition_backup::backup_disks_for_letters(&letters, "批量格式化") {
+        return BatchFormatResult {
+            success_count: 0,
+            fail_count: partitions.len(),
+            results: partitions
+                .iter()
+                .map(|partition| FormatResult {
+                    letter: partition.clone(),
+                    success: false,
+                    message: format!("备份分区表失败，已取消格式化: {}", e),
+                })
+                .collect(),
+        };
+    }
+
     for partition in partitions {
-        match format_partition(partition, label, file_system) {
+        let partition_progress = progress.clone();
+        let letter = partition.clone();
+        let on_progress = move |percent: u8, message: &str| partition_progress(&letter, percent, message);
+        match format_partition_with_progress(partition, label, file_system, quick, on_progress) {
             Ok(_) => {
                 results.push(FormatResult {
                     letter: partition.clone(),
@@ -109,6 +109,13 @@ impl App {
             }
         }
         
+        // 检查批量格式化进度
+        if let Some(ref rx) = self.batch_format_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.batch_format_progress = Some(progress);
+            }
+        }
+
         // 检查批量格式化结果
         if let Some(ref rx) = self.batch_format_rx {
             if let Ok(result) = rx.try_recv() {
@@ -122,6 +129,8 @@ impl App {
                 self.batch_format_message = msg;
                 self.batch_format_loading = false;
                 self.batch_format_rx = None;
+                self.batch_format_progress = None;
+                self.batch_format_progress_rx = None;
                 // 刷新分区列表
                 self.start_load_formatable_partitions();
             }
@@ -1165,6 +1174,36 @@ impl App {
 
                 ui.add_space(10.0);
 
+                // 格式化选项
+                ui.add_enabled_ui(!self.batch_format_loading, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("文件系统:");
+                        for fs in ["NTFS", "FAT32", "exFAT"] {
+                            ui.radio_value(&mut self.batch_format_file_system, fs.to_string(), fs);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("卷标:");
+                        ui.add(egui::TextEdit::singleline(&mut self.batch_format_label).desired_width(150.0));
+                        ui.checkbox(&mut self.batch_format_quick, "快速格式化");
+                    });
+                    if self.batch_format_file_system != "NTFS" {
+                        ui.colored_label(
+                            egui::Color32::GRAY,
+                            "FAT32/exFAT 使用内置格式化，FAT32 不受 32 GB 限制（最大 2 TB）",
+                        );
+                    }
+                });
+
+                ui.add_space(10.0);
+
+                // 显示格式化进度
+                if let Some((ref letter, percent, ref step)) = self.batch_format_progress {
+                    ui.label(format!("{} {}", letter, step));
+                    ui.add(egui::ProgressBar::new(percent as f32 / 100.0).show_percentage());
+                    ui.add_space(10.0);
+                }
+
                 // 显示状态消息
                 if !self.batch_format_message.is_empty() {
                     let color = get_message_color(&self.batch_format_message);
@@ -1237,11 +1276,25 @@ impl App {
         self.batch_format_message = "正在格式化分区...".to_string();
 
         let selected: Vec<String> = self.batch_format_selected.iter().cloned().collect();
+        let label = self.batch_format_label.trim().to_string();
+        let file_system = self.batch_format_file_system.clone();
+        let quick = self.batch_format_quick;
         let (tx, rx) = mpsc::channel();
         self.batch_format_rx = Some(rx);
+        let (progress_tx, progress_rx) = mpsc::channel();
+        self.batch_format_progress_rx = Some(progress_rx);
+        self.batch_format_progress = None;
 
         std::thread::spawn(move || {
-            let result = super::batch_format::batch_format_partitions(&selected, "新加卷", "NTFS");
+            let result = super::batch_format::batch_format_partitions(
+                &selected,
+                &label,
+                &file_system,
+                quick,
+                move |letter: &str, percent: u8, step: &str| {
+                    let _ = progress_tx.send((letter.to_string(), percent, step.to_string()));
+                },
+            );
             let _ = tx.send(result);
         });
     }
@@ -0,0 +1,193 @@
+//! MBR 转 GPT 对话框模块
+//!
+//! 选择 MBR 磁盘上的 Windows 分区，无损转换所在磁盘为 GPT 并重建 UEFI 引导
+
+use egui;
+use std::sync::mpsc;
+
+use crate::app::App;
+use crate::core::disk::PartitionStyle;
+use crate::core::mbr_to_gpt::{convert_system_disk, ConversionReport};
+
+impl App {
+    /// 打开 MBR 转 GPT 对话框
+    pub fn init_mbr_to_gpt_dialog(&mut self) {
+        self.show_mbr_to_gpt_dialog = true;
+        self.mbr_to_gpt_confirm = false;
+        self.mbr_to_gpt_message.clear();
+        self.mbr_to_gpt_selected_partition = self
+            .partitions
+            .iter()
+            .find(|p| p.has_windows && p.partition_style == PartitionStyle::MBR && p.is_system_partition)
+            .map(|p| p.letter.clone());
+    }
+
+    /// 检查转换结果
+    fn check_mbr_to_gpt_result(&mut self) {
+        if let Some(ref rx) = self.mbr_to_gpt_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.mbr_to_gpt_running = false;
+                self.mbr_to_gpt_result_rx = None;
+                self.mbr_to_gpt_message = match result {
+                    Ok(report) => Self::format_conversion_report(&report),
+                    Err(e) => format!("✗ 转换失败: {}", e),
+                };
+                self.partitions = crate::core::disk::DiskManager::get_partitions().unwrap_or_default();
+            }
+        }
+    }
+
+    fn format_conversion_report(report: &ConversionReport) -> String {
+        let mut lines = vec!["✓ 已转换为 GPT，请在固件设置中切换为 UEFI 启动".to_string()];
+        for shrink in &report.shrunk {
+            lines.push(format!("已缩小分区 {}：{}", shrink.partition_number, shrink.reason));
+        }
+        if let Some(letter) = report.esp_letter {
+            lines.push(format!("ESP 分区: {}:", letter));
+        }
+        lines.extend(report.warnings.iter().cloned());
+        lines.push(format!("原分区表已备份到: {}", report.backup_path.display()));
+        lines.push(report.boot_summary.clone());
+        lines.join("\n")
+    }
+
+    /// 开始转换
+    fn start_mbr_to_gpt(&mut self) {
+        self.mbr_to_gpt_confirm = false;
+        let Some(letter) = self.mbr_to_gpt_selected_partition.clone() else {
+            return;
+        };
+        let Some(disk_number) = self
+            .partitions
+            .iter()
+            .find(|p| p.letter == letter)
+            .and_then(|p| p.disk_number)
+        else {
+            self.mbr_to_gpt_message = "✗ 无法确定分区所在的磁盘".to_string();
+            return;
+        };
+
+        self.mbr_to_gpt_running = true;
+        self.mbr_to_gpt_message = "正在转换，请勿中断...".to_string();
+
+        let (tx, rx) = mpsc::channel();
+        self.mbr_to_gpt_result_rx = Some(rx);
+        std::thread::spawn(move || {
+            let result = convert_system_disk(disk_number, &letter).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(result);
+        });
+    }
+
+    /// 渲染 MBR 转 GPT 对话框
+    pub fn render_mbr_to_gpt_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_mbr_to_gpt_dialog {
+            return;
+        }
+
+        self.check_mbr_to_gpt_result();
+
+        let mut should_close = false;
+        let mut should_start = false;
+        let mut window_open = self.show_mbr_to_gpt_dialog;
+
+        let candidates: Vec<(String, String)> = self
+            .partitions
+            .iter()
+            .filter(|p| p.has_windows && p.partition_style == PartitionStyle::MBR && p.disk_number.is_some())
+            .map(|p| {
+                (
+                    p.letter.clone(),
+                    format!(
+                        "{} {} (磁盘 {}, {:.1} GB)",
+                        p.letter,
+                        p.label,
+                        p.disk_number.unwrap_or_default(),
+                        p.total_size_mb as f64 / 1024.0
+                    ),
+                )
+            })
+            .collect();
+
+        egui::Window::new("MBR 转 GPT")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ui.ctx(), |ui| {
+                ui.label("保留现有分区和数据，把系统盘转换为 GPT，并创建 ESP/MSR、重建 UEFI 引导");
+                ui.add_space(10.0);
+
+                if candidates.is_empty() {
+                    ui.colored_label(egui::Color32::GRAY, "没有找到位于 MBR 磁盘上的 Windows 分区");
+                }
+                for (letter, text) in &candidates {
+                    ui.add_enabled_ui(!self.mbr_to_gpt_running, |ui| {
+                        ui.radio_value(&mut self.mbr_to_gpt_selected_partition, Some(letter.clone()), text);
+                    });
+                }
+
+                ui.add_space(10.0);
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    "空间不足时会先缩小分区；转换前会自动备份分区表",
+                );
+
+                if self.mbr_to_gpt_running {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("正在转换...");
+                    });
+                }
+                if !self.mbr_to_gpt_message.is_empty() {
+                    let color = if self.mbr_to_gpt_message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if self.mbr_to_gpt_message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &self.mbr_to_gpt_message);
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let can_start = !self.mbr_to_gpt_running && self.mbr_to_gpt_selected_partition.is_some();
+                    if ui.add_enabled(can_start, egui::Button::new("开始转换")).clicked() {
+                        self.mbr_to_gpt_confirm = true;
+                    }
+                    if ui.add_enabled(!self.mbr_to_gpt_running, egui::Button::new("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if self.mbr_to_gpt_confirm {
+            egui::Window::new("确认转换")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "⚠ 转换后此磁盘只能以 UEFI 方式启动，Legacy/BIOS 启动将失效！",
+                    );
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("确认转换").clicked() {
+                            should_start = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            self.mbr_to_gpt_confirm = false;
+                        }
+                    });
+                });
+        }
+
+        if should_start {
+            self.start_mbr_to_gpt();
+        }
+
+        if should_close || !window_open {
+            self.show_mbr_to_gpt_dialog = false;
+        }
+    }
+}
@@ -16,15 +16,28 @@ pub mod bitlocker;
 pub mod gho_password;
 pub mod nvidia_uninstall;
 pub mod partition_copy;
+pub mod partition_restore;
+pub mod partition_scan;
 pub mod quick_partition;
 pub mod image_verify;
+pub mod mbr_to_gpt;
+pub mod usb_creator;
+pub mod raw_image;
+pub mod surface_test;
+pub mod secure_wipe;
 
 // 重新导出常用类型
 pub use types::{DriverBackupMode, AppxPackageInfo, InstalledSoftware, WindowsPartitionInfo, ImageVerifyResult};
 pub use batch_format::FormatablePartition;
 pub use bitlocker::BitLockerPartition;
 pub use partition_copy::{CopyablePartition, CopyProgress};
+pub use partition_restore::PartitionRestoreDialogState;
+pub use partition_scan::PartitionScanDialogState;
 pub use quick_partition::QuickPartitionDialogState;
+pub use usb_creator::UsbCreatorDialogState;
+pub use raw_image::RawImageDialogState;
+pub use surface_test::SurfaceTestDialogState;
+pub use secure_wipe::SecureWipeDialogState;
 
 use egui;
 
@@ -237,6 +250,59 @@ impl App {
                     self.image_verify_progress = None;
                 }
 
+                if ui
+                    .add(egui::Button::new("恢复分区表").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_partition_restore_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new("丢失分区扫描").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_partition_scan_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new("MBR 转 GPT").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_mbr_to_gpt_dialog();
+                }
+
+                ui.end_row();
+
+                // ========== 第六行 ==========
+
+                if ui
+                    .add(egui::Button::new("制作安装 U 盘").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_usb_creator_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new("原始扇区镜像").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_raw_image_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new("磁盘表面测试").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_surface_test_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new("安全擦除").min_size(button_size))
+                    .clicked()
+                {
+                    self.init_secure_wipe_dialog();
+                }
+
                 ui.end_row();
             });
 
@@ -253,6 +319,13 @@ impl App {
         self.render_nvidia_uninstall_dialog(ui);
         self.render_partition_copy_dialog(ui);
         self.render_quick_partition_dialog(ui);
+        self.render_partition_restore_dialog(ui);
+        self.render_partition_scan_dialog(ui);
+        self.render_mbr_to_gpt_dialog(ui);
+        self.render_usb_creator_dialog(ui);
+        self.render_raw_image_dialog(ui);
+        self.render_surface_test_dialog(ui);
+        self.render_secure_wipe_dialog(ui);
         self.render_image_verify_dialog(ui);
         self.render_repair_boot_dialog(ui);
 
@@ -0,0 +1,359 @@
+//! 恢复分区表对话框模块
+//!
+//! 列出破坏性磁盘操作前自动保存的分区表备份，预览备份中的分区并写回到目标磁盘
+
+use egui;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use crate::app::App;
+use crate::core::partition_backup::{
+    find_all_backups, restore_physical_disk, PartitionTableBackup, BACKUP_EXTENSION,
+};
+use crate::core::partition_table::PartitionTable;
+use crate::core::quick_partition::{get_physical_disks, PhysicalDisk};
+
+/// 恢复分区表对话框状态
+#[derive(Debug, Clone, Default)]
+pub struct PartitionRestoreDialogState {
+    /// 找到的备份文件
+    pub backup_files: Vec<PathBuf>,
+    /// 当前选中的备份文件
+    pub selected_file: Option<PathBuf>,
+    /// 已加载的备份
+    pub backup: Option<PartitionTableBackup>,
+    /// 物理磁盘列表
+    pub physical_disks: Vec<PhysicalDisk>,
+    /// 恢复目标磁盘编号
+    pub target_disk: Option<u32>,
+    /// 同时恢复各分区引导扇区
+    pub include_partition_heads: bool,
+    /// 是否正在加载磁盘列表
+    pub loading: bool,
+    /// 是否正在恢复
+    pub restoring: bool,
+    /// 确认对话框是否显示
+    pub show_confirm_dialog: bool,
+    /// 状态消息
+    pub message: String,
+}
+
+impl App {
+    /// 初始化恢复分区表对话框
+    pub fn init_partition_restore_dialog(&mut self) {
+        self.show_partition_restore_dialog = true;
+        self.partition_restore_state = PartitionRestoreDialogState {
+            backup_files: find_all_backups(),
+            include_partition_heads: true,
+            loading: true,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.partition_restore_disks_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send(get_physical_disks());
+        });
+    }
+
+    /// 检查异步操作结果
+    fn check_partition_restore_async(&mut self) {
+        if let Some(ref rx) = self.partition_restore_disks_rx {
+            if let Ok(disks) = rx.try_recv() {
+                self.partition_restore_state.physical_disks = disks;
+                self.partition_restore_state.loading = false;
+                self.partition_restore_disks_rx = None;
+                self.select_default_restore_target();
+            }
+        }
+
+        if let Some(ref rx) = self.partition_restore_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                let state = &mut self.partition_restore_state;
+                state.restoring = false;
+                self.partition_restore_result_rx = None;
+
+                state.message = match result {
+                    Ok(report) if report.failed.is_empty() => {
+                        format!("✓ 分区表已恢复，共写回 {} 个区域", report.written)
+                    }
+                    Ok(report) => format!(
+                        "✓ 分区表已恢复，但 {} 个分区引导扇区写入失败:\n{}",
+                        report.failed.len(),
+                        report.failed.join("\n")
+                    ),
+                    Err(e) => format!("✗ 恢复失败: {}", e),
+                };
+                self.partitions = crate::core::disk::DiskManager::get_partitions().unwrap_or_default();
+            }
+        }
+    }
+
+    /// 加载选中的备份文件
+    fn select_partition_backup(&mut self, path: PathBuf) {
+        let state = &mut self.partition_restore_state;
+        match PartitionTableBackup::load(&path) {
+            Ok(backup) => {
+                state.backup = Some(backup);
+                state.message.clear();
+            }
+            Err(e) => {
+                state.backup = None;
+                state.message = format!("✗ 无法读取备份: {}", e);
+            }
+        }
+        state.selected_file = Some(path);
+        self.select_default_restore_target();
+    }
+
+    /// 默认恢复到编号和大小都与备份一致的磁盘
+    fn select_default_restore_target(&mut self) {
+        let state = &mut self.partition_restore_state;
+        let Some(backup) = &state.backup else {
+            return;
+        };
+        let size = backup.sector_size * backup.total_sectors;
+        state.target_disk = state
+            .physical_disks
+            .iter()
+            .filter(|d| d.size_bytes == size && d.sector_size == backup.sector_size)
+            .min_by_key(|d| d.disk_number != backup.disk_number)
+            .map(|d| d.disk_number);
+    }
+
+    /// 开始恢复
+    fn start_partition_restore(&mut self) {
+        let state = &mut self.partition_restore_state;
+        let (Some(backup), Some(target)) = (state.backup.clone(), state.target_disk) else {
+            return;
+        };
+        let Some(disk) = state.physical_disks.iter().find(|d| d.disk_number == target).cloned() else {
+            state.message = "无效的目标磁盘".to_string();
+            return;
+        };
+        let include_heads = state.include_partition_heads;
+
+        state.restoring = true;
+        state.show_confirm_dialog = false;
+        state.message = "正在恢复分区表...".to_string();
+
+        let (tx, rx) = mpsc::channel();
+        self.partition_restore_result_rx = Some(rx);
+        std::thread::spawn(move || {
+            let result = restore_physical_disk(&backup, &disk, include_heads).map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
+    }
+
+    /// 渲染恢复分区表对话框
+    pub fn render_partition_restore_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_partition_restore_dialog {
+            return;
+        }
+
+        self.check_partition_restore_async();
+
+        let mut should_close = false;
+        let mut should_select: Option<PathBuf> = None;
+        let mut should_restore = false;
+        let mut window_open = self.show_partition_restore_dialog;
+
+        egui::Window::new("恢复分区表")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(620.0)
+            .default_height(480.0)
+            .show(ui.ctx(), |ui| {
+                ui.label("一键分区、删除分区和批量格式化前会自动备份分区表，误操作后可在此写回");
+                ui.add_space(10.0);
+
+                let state = &mut self.partition_restore_state;
+                let busy = state.restoring;
+
+                // 备份文件列表
+                ui.horizontal(|ui| {
+                    ui.label("备份文件:");
+                    if ui.add_enabled(!busy, egui::Button::new("浏览...")).clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("分区表备份", &[BACKUP_EXTENSION])
+                            .pick_file()
+                        {
+                            should_select = Some(path);
+                        }
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .id_salt("partition_backup_files")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        if state.backup_files.is_empty() {
+                            ui.colored_label(egui::Color32::GRAY, "未找到分区表备份");
+                        }
+                        for path in &state.backup_files {
+                            let selected = state.selected_file.as_ref() == Some(path);
+                            if ui
+                                .selectable_label(selected, path.to_string_lossy().to_string())
+                                .clicked()
+                                && !busy
+                            {
+                                should_select = Some(path.clone());
+                            }
+                        }
+                    });
+
+                ui.add_space(10.0);
+                ui.separator();
+
+                // 备份内容预览
+                if let Some(backup) = &state.backup {
+                    ui.label(backup.summary());
+                    ui.add_space(5.0);
+                    match backup.partition_table() {
+                        Ok(table) if !matches!(table, PartitionTable::Raw) => {
+                            let sector_size = backup.sector_size;
+                            egui::Grid::new("partition_backup_preview")
+                                .num_columns(4)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.strong("序号");
+                                    ui.strong("起始位置");
+                                    ui.strong("大小");
+                                    ui.strong("类型");
+                                    ui.end_row();
+                                    for p in table.partitions() {
+                                        ui.label(p.number.to_string());
+                                        ui.label(format!("{} MB", p.start_lba * sector_size / 1024 / 1024));
+                                        ui.label(format!(
+                                            "{:.2} GB",
+                                            (p.sector_count * sector_size) as f64 / 1024.0 / 1024.0 / 1024.0
+                                        ));
+                                        ui.label(p.type_string());
+                                        ui.end_row();
+                                    }
+                                });
+                        }
+                        _ => {
+                            ui.colored_label(egui::Color32::YELLOW, "备份中没有可识别的分区");
+                        }
+                    }
+
+                    ui.add_space(10.0);
+
+                    // 目标磁盘
+                    if state.loading {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("正在加载磁盘列表...");
+                        });
+                    } else {
+                        let size = backup.sector_size * backup.total_sectors;
+                        let selected_text = state
+                            .target_disk
+                            .map(|n| format!("磁盘 {}", n))
+                            .unwrap_or_else(|| "请选择".to_string());
+                        ui.horizontal(|ui| {
+                            ui.label("恢复到:");
+                            egui::ComboBox::from_id_salt("partition_restore_target")
+                                .selected_text(selected_text)
+                                .width(320.0)
+                                .show_ui(ui, |ui| {
+                                    for disk in &state.physical_disks {
+                                        // 大小不一致的磁盘不能恢复
+                                        let matches = disk.size_bytes == size
+                                            && disk.sector_size == backup.sector_size;
+                                        let text = format!(
+                                            "磁盘 {} - {} ({:.1} GB)",
+                                            disk.disk_number,
+                                            disk.model,
+                                            disk.size_bytes as f64 / 1024.0 / 1024.0 / 1024.0
+                                        );
+                                        ui.add_enabled_ui(matches, |ui| {
+                                            ui.selectable_value(
+                                                &mut state.target_disk,
+                                                Some(disk.disk_number),
+                                                text,
+                                            );
+                                        });
+                                    }
+                                });
+                        });
+                        if state.target_disk.is_none() {
+                            ui.colored_label(egui::Color32::YELLOW, "没有与备份大小一致的磁盘");
+                        }
+                    }
+
+                    ui.checkbox(&mut state.include_partition_heads, "同时恢复各分区引导扇区");
+                }
+
+                ui.add_space(15.0);
+
+                if state.restoring {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("正在恢复分区表，请勿中断...");
+                    });
+                }
+
+                if !state.message.is_empty() {
+                    let color = if state.message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if state.message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &state.message);
+                }
+
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    let can_restore =
+                        !busy && state.backup.is_some() && state.target_disk.is_some();
+                    if ui.add_enabled(can_restore, egui::Button::new("恢复")).clicked() {
+                        state.show_confirm_dialog = true;
+                    }
+                    if ui.add_enabled(!busy, egui::Button::new("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        // 确认对话框
+        if self.partition_restore_state.show_confirm_dialog {
+            let target = self.partition_restore_state.target_disk.unwrap_or_default();
+            egui::Window::new("确认恢复分区表")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!("⚠ 将用备份覆盖磁盘 {} 当前的分区表！", target),
+                    );
+                    ui.label("恢复后磁盘上现有的分区将被替换为备份中的分区。");
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("确认恢复").clicked() {
+                            should_restore = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            self.partition_restore_state.show_confirm_dialog = false;
+                        }
+                    });
+                });
+        }
+
+        if let Some(path) = should_select {
+            self.select_partition_backup(path);
+        }
+
+        if should_restore {
+            self.start_partition_restore();
+        }
+
+        if should_close || !window_open {
+            self.show_partition_restore_dialog = false;
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! 丢失分区扫描对话框模块
+//!
+//! 扫描选中磁盘上残留的文件系统引导扇区，列出找到的分区，
+//! 由用户勾选后拼出新的 MBR/GPT 分区表并写回
+
+use egui;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use crate::app::App;
+use crate::core::disk::PartitionStyle;
+use crate::core::partition_scan::{
+    build_partition_table, scan_physical_disk, suggested_style, FoundPartition, ScanMode,
+    ScanReport,
+};
+use crate::core::partition_table::DiskGeometry;
+use crate::core::quick_partition::{get_physical_disks, PhysicalDisk};
+
+/// 丢失分区扫描对话框状态
+#[derive(Debug, Clone, Default)]
+pub struct PartitionScanDialogState {
+    /// 物理磁盘列表
+    pub physical_disks: Vec<PhysicalDisk>,
+    /// 选中的磁盘索引
+    pub selected_disk_index: Option<usize>,
+    /// 扫描模式
+    pub mode: ScanMode,
+    /// 是否正在加载磁盘列表
+    pub loading: bool,
+    /// 是否正在扫描
+    pub scanning: bool,
+    /// 扫描进度（0.0 - 1.0）
+    pub progress: f32,
+    /// 扫描结果
+    pub report: Option<ScanReport>,
+    /// 建议分区是否勾选恢复（与 report.proposal 一一对应）
+    pub selected: Vec<bool>,
+    /// 写入的分区表类型
+    pub partition_style: PartitionStyle,
+    /// 是否正在写入
+    pub writing: bool,
+    /// 确认对话框是否显示
+    pub show_confirm_dialog: bool,
+    /// 状态消息
+    pub message: String,
+}
+
+impl PartitionScanDialogState {
+    fn selected_disk(&self) -> Option<&PhysicalDisk> {
+        self.selected_disk_index.and_then(|i| self.physical_disks.get(i))
+    }
+
+    /// 勾选的分区
+    fn chosen_partitions(&self) -> Vec<FoundPartition> {
+        self.report
+            .as_ref()
+            .map(|r| {
+                r.proposal
+                    .iter()
+                    .zip(&self.selected)
+                    .filter(|(_, selected)| **selected)
+                    .map(|(p, _)| p.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl App {
+    /// 初始化丢失分区扫描对话框
+    pub fn init_partition_scan_dialog(&mut self) {
+        self.show_partition_scan_dialog = true;
+        self.partition_scan_state = PartitionScanDialogState {
+            loading: true,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.partition_scan_disks_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send(get_physical_disks());
+        });
+    }
+
+    /// 检查异步操作结果
+    fn check_partition_scan_async(&mut self) {
+        if let Some(ref rx) = self.partition_scan_disks_rx {
+            if let Ok(disks) = rx.try_recv() {
+                self.partition_scan_state.physical_disks = disks;
+                self.partition_scan_state.loading = false;
+                self.partition_scan_disks_rx = None;
+            }
+        }
+
+        if let Some(ref rx) = self.partition_scan_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.partition_scan_state.progress = progress;
+            }
+        }
+
+        if let Some(ref rx) = self.partition_scan_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.partition_scan_result_rx = None;
+                self.partition_scan_progress_rx = None;
+                self.partition_scan_cancel_flag = None;
+                let state = &mut self.partition_scan_state;
+                state.scanning = false;
+
+                match result {
+                    Ok(report) => {
+                        state.message = if report.proposal.is_empty() {
+                            "未找到可恢复的分区，可尝试深度扫描".to_string()
+                        } else if report.cancelled {
+                            format!("扫描已取消，已找到 {} 个分区", report.proposal.len())
+                        } else {
+                            format!("✓ 扫描完成，找到 {} 个分区", report.proposal.len())
+                        };
+                        if let Some(disk) = state.selected_disk() {
+                            let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+                            state.partition_style =
+                                suggested_style(&geometry, disk.partition_style, &report.proposal);
+                        }
+                        state.selected = vec![true; report.proposal.len()];
+                        state.report = Some(report);
+                    }
+                    Err(e) => state.message = format!("✗ 扫描失败: {}", e),
+                }
+            }
+        }
+
+        if let Some(ref rx) = self.partition_scan_write_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.partition_scan_write_rx = None;
+                let state = &mut self.partition_scan_state;
+                state.writing = false;
+                state.message = match result {
+                    Ok(backup) => format!("✓ 分区表已写入，原分区表已备份到: {}", backup),
+                    Err(e) => format!("✗ 写入分区表失败: {}", e),
+                };
+                self.partitions = crate::core::disk::DiskManager::get_partitions().unwrap_or_default();
+            }
+        }
+    }
+
+    /// 开始扫描
+    fn start_partition_scan(&mut self) {
+        let state = &mut self.partition_scan_state;
+        let Some(disk) = state.selected_disk().cloned() else {
+            state.message = "请先选择要扫描的磁盘".to_string();
+            return;
+        };
+        let mode = state.mode;
+        state.scanning = true;
+        state.progress = 0.0;
+        state.report = None;
+        state.selected.clear();
+        state.message.clear();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.partition_scan_cancel_flag = Some(cancel.clone());
+        let (progress_tx, progress_rx) = mpsc::channel();
+        self.partition_scan_progress_rx = Some(progress_rx);
+        let (tx, rx) = mpsc::channel();
+        self.partition_scan_result_rx = Some(rx);
+
+        std::thread::spawn(move || {
+            let mut last_percent = u64::MAX;
+            let progress = |done: u64, total: u64| {
+                let percent = done * 100 / total.max(1);
+                if percent != last_percent {
+                    last_percent = percent;
+                    let _ = progress_tx.send(percent as f32 / 100.0);
+                }
+            };
+            let result = scan_physical_disk(&disk, mode, progress, Some(&cancel)).map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
+    }
+
+    /// 写入恢复的分区表
+    fn start_write_recovered_table(&mut self) {
+        let state = &mut self.partition_scan_state;
+        state.show_confirm_dialog = false;
+        let Some(disk) = state.selected_disk().cloned() else {
+            return;
+        };
+        let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+        let table = match build_partition_table(&geometry, state.partition_style, &state.chosen_partitions()) {
+            Ok(table) => table,
+            Err(e) => {
+                state.message = format!("✗ {}", e);
+                return;
+            }
+        };
+        state.writing = true;
+        state.message = "正在写入分区表...".to_string();
+
+        let (tx, rx) = mpsc::channel();
+        self.partition_scan_write_rx = Some(rx);
+        std::thread::spawn(move || {
+            let result = crate::core::partition_scan::write_recovered_table(&disk, &table)
+                .map(|path| path.to_string_lossy().to_string())
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        });
+    }
+
+    /// 渲染丢失分区扫描对话框
+    pub fn render_partition_scan_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_partition_scan_dialog {
+            return;
+        }
+
+        self.check_partition_scan_async();
+
+        let mut should_scan = false;
+        let mut should_cancel = false;
+        let mut should_write = false;
+        let mut should_close = false;
+        let mut window_open = self.show_partition_scan_dialog;
+
+        egui::Window::new("丢失分区扫描")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(680.0)
+            .default_height(500.0)
+            .show(ui.ctx(), |ui| {
+                ui.label("扫描磁盘中残留的 NTFS/FAT32/exFAT/BitLocker 引导扇区，找回被清除的分区");
+                ui.add_space(10.0);
+
+                let state = &mut self.partition_scan_state;
+                let busy = state.scanning || state.writing;
+
+                if state.loading {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("正在加载磁盘列表...");
+                    });
+                    return;
+                }
+
+                // 磁盘选择和扫描模式
+                ui.horizontal(|ui| {
+                    ui.label("磁盘:");
+                    let selected_text = state
+                        .selected_disk()
+                        .map(|d| format!("磁盘 {} - {} ({:.1} GB)", d.disk_number, d.model, d.size_gb()))
+                        .unwrap_or_else(|| "请选择".to_string());
+                    ui.add_enabled_ui(!busy, |ui| {
+                        egui::ComboBox::from_id_salt("partition_scan_disk")
+                            .selected_text(selected_text)
+                            .width(360.0)
+                            .show_ui(ui, |ui| {
+                                for (i, disk) in state.physical_disks.iter().enumerate() {
+                                    let text = format!(
+                                        "磁盘 {} - {} ({:.1} GB, {})",
+                                        disk.disk_number,
+                                        disk.model,
+                                        disk.size_gb(),
+                                        disk.partition_style
+                                    );
+                                    if ui
+                                        .selectable_label(state.selected_disk_index == Some(i), text)
+                                        .clicked()
+                                    {
+                                        state.selected_disk_index = Some(i);
+                                        state.report = None;
+                                        state.selected.clear();
+                                    }
+                                }
+                            });
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!busy, |ui| {
+                        ui.radio_value(&mut state.mode, ScanMode::Quick, "快速扫描（常见对齐位置）");
+                        ui.radio_value(&mut state.mode, ScanMode::Deep, "深度扫描（逐扇区）");
+                    });
+                });
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let can_scan = !busy && state.selected_disk_index.is_some();
+                    if ui.add_enabled(can_scan, egui::Button::new("开始扫描")).clicked() {
+                        should_scan = true;
+                    }
+                    if state.scanning && ui.button("❌ 取消").clicked() {
+                        should_cancel = true;
+                    }
+                });
+                if state.scanning {
+                    ui.add(egui::ProgressBar::new(state.progress).show_percentage());
+                }
+
+                ui.add_space(10.0);
+                ui.separator();
+
+                // 扫描结果
+                if let (Some(report), Some(disk)) = (&state.report, state.selected_disk_index.and_then(|i| state.physical_disks.get(i))) {
+                    if !report.proposal.is_empty() {
+                        ui.label("找到的分区（勾选要恢复的分区）:");
+                        egui::ScrollArea::vertical()
+                            .id_salt("partition_scan_results")
+                            .max_height(180.0)
+                            .show(ui, |ui| {
+                                for (partition, selected) in report.proposal.iter().zip(state.selected.iter_mut()) {
+                                    ui.horizontal(|ui| {
+                                        ui.add_enabled(!busy, egui::Checkbox::new(selected, partition.describe(disk.sector_size)));
+                                        ui.colored_label(
+                                            egui::Color32::GRAY,
+                                            format!("可信度: {}", partition.confidence),
+                                        );
+                                    });
+                                }
+                            });
+
+                        let ignored = report.candidates.len().saturating_sub(report.proposal.len());
+                        if ignored > 0 {
+                            ui.colored_label(
+                                egui::Color32::GRAY,
+                                format!("另有 {} 个重叠或超出磁盘范围的候选已忽略", ignored),
+                            );
+                        }
+
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label("分区表类型:");
+                            ui.add_enabled_ui(!busy, |ui| {
+                                ui.radio_value(&mut state.partition_style, PartitionStyle::GPT, "GPT");
+                                ui.radio_value(&mut state.partition_style, PartitionStyle::MBR, "MBR");
+                            });
+                        });
+                        if state.partition_style == PartitionStyle::MBR {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                "MBR 磁盘恢复后如需从此磁盘启动，请再使用引导修复工具写入引导代码",
+                            );
+                        }
+                    }
+                }
+
+                ui.add_space(10.0);
+
+                if state.writing {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("正在写入分区表，请勿中断...");
+                    });
+                }
+
+                if !state.message.is_empty() {
+                    let color = if state.message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if state.message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &state.message);
+                }
+
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    let can_write = !busy && state.selected.iter().any(|s| *s);
+                    if ui.add_enabled(can_write, egui::Button::new("写入分区表")).clicked() {
+                        state.show_confirm_dialog = true;
+                    }
+                    if ui.add_enabled(!busy, egui::Button::new("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        // 确认对话框
+        if self.partition_scan_state.show_confirm_dialog {
+            let state = &mut self.partition_scan_state;
+            let disk_number = state.selected_disk().map(|d| d.disk_number).unwrap_or_default();
+            let count = state.selected.iter().filter(|s| **s).count();
+            egui::Window::new("确认写入分区表")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!(
+                            "⚠ 将用找到的 {} 个分区覆盖磁盘 {} 当前的分区表（{}）！",
+                            count, disk_number, state.partition_style
+                        ),
+                    );
+                    ui.label("写入前会自动备份当前分区表，可通过“恢复分区表”工具撤销。");
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("确认写入").clicked() {
+                            should_write = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            state.show_confirm_dialog = false;
+                        }
+                    });
+                });
+        }
+
+        if should_scan {
+            self.start_partition_scan();
+        }
+
+        if should_cancel {
+            if let Some(flag) = &self.partition_scan_cancel_flag {
+                flag.store(true, Ordering::Relaxed);
+            }
+        }
+
+        if should_write {
+            self.start_write_recovered_table();
+        }
+
+        if should_close || !window_open {
+            self.show_partition_scan_dialog = false;
+            if let Some(flag) = &self.partition_scan_cancel_flag {
+                flag.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
@@ -6,6 +6,8 @@ use std::sync::mpsc;
 
 use crate::app::App;
 use crate::core::disk::PartitionStyle;
+use crate::core::layout_planner::{LayoutPlanner, LayoutPreset, PartitionRole};
+use crate::core::partition_table::DiskGeometry;
 use crate::core::quick_partition::{
     execute_quick_partition, get_next_available_drive_letter, get_physical_disks,
     get_recommended_partition_style, get_unallocated_space_after_partition_with_disk,
@@ -94,6 +96,8 @@ pub struct EditablePartition {
     pub is_msr: bool,
     /// 是否为恢复分区
     pub is_recovery: bool,
+    /// 是否为活动分区（MBR 系统保留分区）
+    pub is_active: bool,
     /// 文件系统类型
     pub file_system: String,
     /// 唯一标识符
@@ -120,6 +124,7 @@ impl EditablePartition {
             is_esp: false,
             is_msr: false,
             is_recovery: false,
+            is_active: false,
             file_system: "NTFS".to_string(),
             id,
             is_existing: false,
@@ -139,6 +144,7 @@ impl EditablePartition {
             is_esp: true,
             is_msr: false,
             is_recovery: false,
+            is_active: false,
             file_system: "FAT32".to_string(),
             id,
             is_existing: false,
@@ -158,6 +164,7 @@ impl EditablePartition {
             is_esp: partition.is_esp,
             is_msr: partition.is_msr,
             is_recovery: partition.is_recovery,
+            is_active: false,
             file_system: partition.file_system.clone(),
             id,
             is_existing: true,
@@ -169,12 +176,16 @@ impl EditablePartition {
     }
 
     /// 转换为 PartitionLayout
-    fn to_layout(&self) -> PartitionLayout {
+    fn to_layout(&self, fill_remaining: bool) -> PartitionLayout {
         PartitionLayout {
             size_gb: self.size_gb,
             drive_letter: self.drive_letter,
             label: self.label.clone(),
             is_esp: self.is_esp,
+            is_msr: self.is_msr,
+            is_recovery: self.is_recovery,
+            is_active: self.is_active,
+            fill_remaining,
             file_system: self.file_system.clone(),
         }
     }
@@ -187,6 +198,8 @@ impl EditablePartition {
             "MSR".to_string()
         } else if self.is_recovery {
             "恢复分区".to_string()
+        } else if self.is_active && self.drive_letter.is_none() {
+            "系统保留".to_string()
         } else if let Some(letter) = self.drive_letter {
             format!("{}:", letter)
         } else {
@@ -317,6 +330,10 @@ impl App {
                         "✓ 分区成功！已创建分区: {}",
                         result.created_partitions.join(", ")
                     );
+                    if let Some(path) = &result.backup_path {
+                        self.quick_partition_state.message +=
+                            &format!("\n原分区表已备份到: {}", path);
+                    }
                     // 刷新磁盘列表
                     self.quick_partition_state.loading = true;
                     self.start_load_physical_disks();
@@ -324,6 +341,10 @@ impl App {
                     self.partitions = crate::core::disk::DiskManager::get_partitions().unwrap_or_default();
                 } else {
                     self.quick_partition_state.message = format!("✗ 分区失败: {}", result.message);
+                    if let Some(path) = &result.backup_path {
+                        self.quick_partition_state.message +=
+                            &format!("\n可通过“恢复分区表”工具还原: {}", path);
+                    }
                 }
             }
         }
@@ -497,6 +518,66 @@ impl App {
         self.quick_partition_state.message = "无法创建 ESP 分区：没有足够的可用空间".to_string();
     }
 
+    /// 应用预设分区布局（替换所有新规划的分区）
+    fn apply_layout_preset(&mut self, preset: LayoutPreset) {
+        let disk_idx = match self.quick_partition_state.editor.selected_disk_index {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        let disk = match self.quick_partition_state.physical_disks.get(disk_idx).cloned() {
+            Some(d) => d,
+            None => return,
+        };
+
+        if self.quick_partition_state.editor.partition_layouts.iter().any(|p| p.is_existing) {
+            self.quick_partition_state.message = "磁盘上已有分区，无法应用预设布局".to_string();
+            return;
+        }
+
+        let geometry = DiskGeometry::new(disk.sector_size, disk.size_bytes);
+        let plan = match LayoutPlanner::plan_preset(geometry, preset) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.quick_partition_state.message = format!("无法应用预设「{}」: {}", preset, e);
+                return;
+            }
+        };
+
+        let mut used_letters = get_used_drive_letters();
+        let mut layouts = Vec::with_capacity(plan.partitions.len());
+        for partition in &plan.partitions {
+            let size_gb = plan.size_bytes(partition) as f64 / 1024.0 / 1024.0 / 1024.0;
+            let letter = if partition.role == PartitionRole::Basic {
+                let letter = get_next_available_drive_letter(&used_letters);
+                used_letters.extend(letter);
+                letter
+            } else {
+                None
+            };
+
+            self.quick_partition_state.partition_id_counter += 1;
+            let mut layout = EditablePartition::new(
+                self.quick_partition_state.partition_id_counter,
+                size_gb,
+                letter,
+            );
+            layout.label = partition.label.clone();
+            layout.file_system = partition.file_system.clone();
+            layout.is_esp = partition.role == PartitionRole::Esp;
+            layout.is_msr = partition.role == PartitionRole::Msr;
+            layout.is_recovery = partition.role == PartitionRole::Recovery;
+            layout.is_active = partition.role == PartitionRole::SystemReserved;
+            layouts.push(layout);
+        }
+
+        let editor = &mut self.quick_partition_state.editor;
+        editor.partition_layouts = layouts;
+        editor.partition_style = preset.style();
+        editor.show_esp_button = preset.style() == PartitionStyle::GPT;
+        self.quick_partition_state.message = plan.warnings.join("\n");
+    }
+
     /// 删除指定分区
     fn delete_partition(&mut self, index: usize) {
         let layouts = &mut self.quick_partition_state.editor.partition_layouts;
@@ -549,13 +630,17 @@ impl App {
         }
 
         // 转换分区布局
+        // 规划的分区占满磁盘（未分配空间不足 1GB）时，最后一个分区使用所有剩余空间
+        let planned_total: f64 = state.editor.partition_layouts.iter().map(|p| p.size_gb).sum();
+        let fill_last = disk.size_gb() - planned_total < 1.0;
+        let last_index = new_partitions.len() - 1;
         let layouts: Vec<PartitionLayout> = new_partitions
             .iter()
-            .map(|p| p.to_layout())
+            .enumerate()
+            .map(|(i, p)| p.to_layout(fill_last && i == last_index))
             .collect();
 
         let partition_style = state.editor.partition_style;
-        let disk_number = disk.disk_number;
 
         self.quick_partition_state.executing = true;
         self.quick_partition_state.show_confirm_dialog = false;
@@ -565,7 +650,7 @@ impl App {
         self.quick_partition_result_rx = Some(rx);
 
         std::thread::spawn(move || {
-            let result = execute_quick_partition(disk_number, partition_style, &layouts);
+            let result = execute_quick_partition(&disk, partition_style, &layouts);
             let _ = tx.send(result);
         });
     }
@@ -585,6 +670,7 @@ impl App {
         let mut should_close = false;
         let mut should_add_partition = false;
         let mut should_add_esp = false;
+        let mut should_apply_preset: Option<LayoutPreset> = None;
         let mut should_delete_partition: Option<usize> = None;
         let mut should_execute = false;
         let mut should_show_confirm = false;
@@ -725,6 +811,16 @@ impl App {
                                         should_add_esp = true;
                                     }
                                 }
+
+                                egui::ComboBox::from_id_salt("quick_partition_preset")
+                                    .selected_text("预设布局")
+                                    .show_ui(ui, |ui| {
+                                        for preset in LayoutPreset::all() {
+                                            if ui.selectable_label(false, preset.name()).clicked() {
+                                                should_apply_preset = Some(*preset);
+                                            }
+                                        }
+                                    });
                             });
 
                             ui.add_space(15.0);
@@ -1296,6 +1392,10 @@ impl App {
             self.add_esp_partition();
         }
 
+        if let Some(preset) = should_apply_preset {
+            self.apply_layout_preset(preset);
+        }
+
         if let Some(idx) = should_delete_partition {
             self.delete_partition(idx);
         }
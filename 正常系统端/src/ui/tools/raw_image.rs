@@ -0,0 +1,476 @@
+//! 原始扇区镜像工具对话框模块
+//!
+//! 备份整块磁盘为原始扇区镜像（`.lri`），以及对已有镜像进行校验、还原到分区、
+//! 导出为普通磁盘镜像和只读挂载查看
+
+use egui;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use crate::app::App;
+use crate::core::quick_partition::{get_physical_disks, PhysicalDisk};
+use crate::core::raw_image::{
+    self, RawBackupOptions, RawImageHeader, RawRestoreOptions, SourceKind, IMAGE_EXTENSION,
+};
+
+/// 原始镜像工具的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RawImageAction {
+    /// 备份整块磁盘
+    BackupDisk,
+    #[default]
+    Verify,
+    /// 还原到分区
+    Restore,
+    /// 导出为普通磁盘镜像
+    Export,
+    /// 转换为虚拟磁盘后只读挂载
+    Mount,
+}
+
+impl std::fmt::Display for RawImageAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RawImageAction::BackupDisk => write!(f, "备份整个磁盘"),
+            RawImageAction::Verify => write!(f, "校验镜像"),
+            RawImageAction::Restore => write!(f, "还原到分区"),
+            RawImageAction::Export => write!(f, "导出为 IMG"),
+            RawImageAction::Mount => write!(f, "挂载查看"),
+        }
+    }
+}
+
+/// 后台任务的结果
+pub enum RawImageOutcome {
+    /// 操作完成，附带统计信息
+    Done(String),
+    /// 虚拟磁盘已生成，等待挂载
+    VhdReady(PathBuf),
+}
+
+/// 原始镜像工具对话框状态
+#[derive(Debug, Clone, Default)]
+pub struct RawImageDialogState {
+    pub action: RawImageAction,
+    /// 镜像文件（备份整个磁盘时为保存位置）
+    pub image_path: String,
+    /// 已读取的镜像头
+    pub header: Option<RawImageHeader>,
+    /// 物理磁盘列表
+    pub physical_disks: Vec<PhysicalDisk>,
+    pub selected_disk_index: Option<usize>,
+    pub loading: bool,
+    /// 还原目标分区盘符
+    pub target_partition: Option<String>,
+    /// 还原时把镜像中未使用的区域写零
+    pub zero_fill: bool,
+    /// 导出 IMG 的保存位置
+    pub export_path: String,
+    pub running: bool,
+    pub progress: Option<(u8, String)>,
+    pub show_confirm_dialog: bool,
+    pub message: String,
+}
+
+impl RawImageDialogState {
+    fn selected_disk(&self) -> Option<&PhysicalDisk> {
+        self.selected_disk_index.and_then(|i| self.physical_disks.get(i))
+    }
+
+    /// 读取镜像头，失败时显示错误
+    fn load_header(&mut self) {
+        self.header = None;
+        match raw_image::open_image(Path::new(self.image_path.trim())) {
+            Ok(image) => {
+                if !image.is_complete() {
+                    self.message = "✗ 镜像不完整（缺少尾部索引），备份可能被中断".to_string();
+                }
+                self.header = Some(image.header().clone());
+            }
+            Err(e) => self.message = format!("✗ {:#}", e),
+        }
+    }
+
+    fn can_start(&self) -> bool {
+        if self.running || self.image_path.trim().is_empty() {
+            return false;
+        }
+        match self.action {
+            RawImageAction::BackupDisk => self.selected_disk_index.is_some(),
+            RawImageAction::Verify | RawImageAction::Mount => self.header.is_some(),
+            RawImageAction::Restore => {
+                self.target_partition.is_some()
+                    && self.header.as_ref().is_some_and(|h| h.source_kind == SourceKind::Partition)
+            }
+            RawImageAction::Export => self.header.is_some() && !self.export_path.trim().is_empty(),
+        }
+    }
+}
+
+impl App {
+    /// 初始化原始镜像工具对话框
+    pub fn init_raw_image_dialog(&mut self) {
+        self.show_raw_image_dialog = true;
+        self.raw_image_state = RawImageDialogState { loading: true, ..Default::default() };
+
+        let (tx, rx) = mpsc::channel();
+        self.raw_image_disks_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send(get_physical_disks());
+        });
+    }
+
+    /// 检查异步操作结果
+    fn check_raw_image_async(&mut self) {
+        if let Some(ref rx) = self.raw_image_disks_rx {
+            if let Ok(disks) = rx.try_recv() {
+                self.raw_image_state.physical_disks = disks;
+                self.raw_image_state.loading = false;
+                self.raw_image_disks_rx = None;
+            }
+        }
+
+        if let Some(ref rx) = self.raw_image_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.raw_image_state.progress = Some(progress);
+            }
+        }
+
+        if let Some(ref rx) = self.raw_image_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.raw_image_result_rx = None;
+                self.raw_image_progress_rx = None;
+                let message = match result {
+                    Ok(RawImageOutcome::Done(message)) => format!("✓ {}", message),
+                    Ok(RawImageOutcome::VhdReady(vhd_path)) => self.attach_raw_image(vhd_path),
+                    Err(e) => format!("✗ {}", e),
+                };
+                let state = &mut self.raw_image_state;
+                state.running = false;
+                state.progress = None;
+                state.message = message;
+                if self.raw_image_state.action == RawImageAction::Restore {
+                    self.partitions = crate::core::disk::DiskManager::get_partitions().unwrap_or_default();
+                }
+            }
+        }
+    }
+
+    /// 挂载后台生成的虚拟磁盘
+    #[cfg(windows)]
+    fn attach_raw_image(&mut self, vhd_path: PathBuf) -> String {
+        match raw_image::MountedRawImage::attach(vhd_path) {
+            Ok(mounted) => {
+                let message = format!("✓ 已只读挂载为磁盘 {}，可在资源管理器中查看（关闭对话框时自动卸载）", mounted.disk_number());
+                self.raw_image_mounted = Some(mounted);
+                message
+            }
+            Err(e) => format!("✗ 挂载失败: {:#}", e),
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn attach_raw_image(&mut self, vhd_path: PathBuf) -> String {
+        format!("✓ 已生成虚拟磁盘: {}", vhd_path.display())
+    }
+
+    /// 开始执行所选操作
+    fn start_raw_image_task(&mut self) {
+        self.raw_image_state.show_confirm_dialog = false;
+        #[cfg(windows)]
+        {
+            self.raw_image_mounted = None;
+        }
+
+        let state = &mut self.raw_image_state;
+        let action = state.action;
+        let image_path = PathBuf::from(state.image_path.trim());
+        let export_path = PathBuf::from(state.export_path.trim());
+        let disk_number = state.selected_disk().map(|d| d.disk_number);
+        let target = state.target_partition.clone();
+        let mount_path = state.header.as_ref().map(|h| raw_image::mount_vhd_path(&image_path, h));
+        let restore_options = RawRestoreOptions { zero_fill: state.zero_fill, ..Default::default() };
+
+        state.running = true;
+        state.message.clear();
+        state.progress = Some((0, "正在准备...".to_string()));
+
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel::<Result<RawImageOutcome, String>>();
+        self.raw_image_progress_rx = Some(progress_rx);
+        self.raw_image_result_rx = Some(result_rx);
+
+        std::thread::spawn(move || {
+            let mut progress = |percent: u8, status: &str| {
+                let _ = progress_tx.send((percent, status.to_string()));
+            };
+            let result = match action {
+                RawImageAction::BackupDisk => disk_number
+                    .ok_or_else(|| anyhow::anyhow!("请选择要备份的磁盘"))
+                    .and_then(|disk| {
+                        let options = RawBackupOptions {
+                            source_kind: SourceKind::Disk,
+                            description: format!("磁盘 {}", disk),
+                            ..Default::default()
+                        };
+                        raw_image::backup_device(&raw_image::disk_device_path(disk), &image_path, &options, &mut progress)
+                    })
+                    .map(|summary| RawImageOutcome::Done(format!("备份完成\n{}", summary.summary()))),
+                RawImageAction::Verify => raw_image::verify_image(&image_path, &mut progress)
+                    .map(|summary| RawImageOutcome::Done(format!("镜像完好\n{}", summary.summary()))),
+                RawImageAction::Restore => target
+                    .and_then(|letter| letter.chars().next())
+                    .ok_or_else(|| anyhow::anyhow!("请选择目标分区"))
+                    .and_then(|letter| {
+                        raw_image::restore_device(
+                            &image_path,
+                            &raw_image::volume_device_path(letter),
+                            &restore_options,
+                            &mut progress,
+                        )
+                    })
+                    .map(|summary| RawImageOutcome::Done(format!("还原完成\n{}", summary.summary()))),
+                RawImageAction::Export => raw_image::export_raw(&image_path, &export_path, &mut progress)
+                    .map(|_| RawImageOutcome::Done(format!("已导出到 {}", export_path.display()))),
+                RawImageAction::Mount => mount_path
+                    .ok_or_else(|| anyhow::anyhow!("请先选择镜像文件"))
+                    .and_then(|vhd_path| {
+                        let _ = std::fs::remove_file(&vhd_path);
+                        raw_image::export_virtual_disk(&image_path, &vhd_path, &mut progress)?;
+                        Ok(RawImageOutcome::VhdReady(vhd_path))
+                    }),
+            };
+            let _ = result_tx.send(result.map_err(|e| format!("{:#}", e)));
+        });
+    }
+
+    /// 渲染原始镜像工具对话框
+    pub fn render_raw_image_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_raw_image_dialog {
+            return;
+        }
+
+        self.check_raw_image_async();
+
+        let mut should_close = false;
+        let mut should_start = false;
+        let mut should_unmount = false;
+        let mut window_open = self.show_raw_image_dialog;
+        let running = self.raw_image_state.running;
+        #[cfg(windows)]
+        let mounted = self.raw_image_mounted.is_some();
+        #[cfg(not(windows))]
+        let mounted = false;
+        let partitions: Vec<(String, String)> = self
+            .partitions
+            .iter()
+            .filter(|p| !p.is_system_partition)
+            .map(|p| (p.letter.clone(), format!("{} {} ({})", p.letter, p.label, Self::format_size(p.total_size_mb))))
+            .collect();
+
+        egui::Window::new("原始扇区镜像")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ui.ctx(), |ui| {
+                let state = &mut self.raw_image_state;
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("操作:");
+                        for action in [
+                            RawImageAction::BackupDisk,
+                            RawImageAction::Verify,
+                            RawImageAction::Restore,
+                            RawImageAction::Export,
+                            RawImageAction::Mount,
+                        ] {
+                            ui.radio_value(&mut state.action, action, action.to_string());
+                        }
+                    });
+
+                    // 镜像文件
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        ui.label("镜像文件:");
+                        ui.add(egui::TextEdit::singleline(&mut state.image_path).desired_width(330.0));
+                        if ui.button("浏览...").clicked() {
+                            let dialog = rfd::FileDialog::new().add_filter("原始扇区镜像", &[IMAGE_EXTENSION]);
+                            let picked = if state.action == RawImageAction::BackupDisk {
+                                dialog.set_file_name(format!("disk.{}", IMAGE_EXTENSION)).save_file()
+                            } else {
+                                dialog.pick_file()
+                            };
+                            if let Some(path) = picked {
+                                state.image_path = path.to_string_lossy().to_string();
+                                state.message.clear();
+                                if state.action != RawImageAction::BackupDisk {
+                                    state.load_header();
+                                }
+                            }
+                        }
+                    });
+
+                    if state.action == RawImageAction::BackupDisk {
+                        if state.loading {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("正在加载磁盘列表...");
+                            });
+                        } else {
+                            for (i, disk) in state.physical_disks.iter().enumerate() {
+                                let text = format!("磁盘 {}: {} ({:.1} GB)", disk.disk_number, disk.model, disk.size_gb());
+                                ui.radio_value(&mut state.selected_disk_index, Some(i), text);
+                            }
+                        }
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 165, 0),
+                            "备份当前系统所在磁盘时，运行中的写入可能导致镜像不一致，建议在PE中操作",
+                        );
+                    } else if let Some(header) = &state.header {
+                        egui::Grid::new("raw_image_header").num_columns(2).show(ui, |ui| {
+                            ui.label("来源:");
+                            ui.label(format!("{}（{}）", header.source, header.source_kind));
+                            ui.end_row();
+                            ui.label("文件系统:");
+                            ui.label(header.file_system.to_string());
+                            ui.end_row();
+                            ui.label("大小:");
+                            ui.label(format!(
+                                "{} （已用 {}）",
+                                Self::format_size(header.size_bytes / 1024 / 1024),
+                                Self::format_size(header.used_bytes / 1024 / 1024)
+                            ));
+                            ui.end_row();
+                            if let Some(created) = chrono::DateTime::from_timestamp(header.created as i64, 0) {
+                                ui.label("创建时间:");
+                                ui.label(created.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                                ui.end_row();
+                            }
+                            if !header.description.is_empty() {
+                                ui.label("描述:");
+                                ui.label(&header.description);
+                                ui.end_row();
+                            }
+                        });
+                    }
+
+                    ui.add_space(8.0);
+                    match state.action {
+                        RawImageAction::Restore => {
+                            ui.horizontal(|ui| {
+                                ui.label("目标分区:");
+                                egui::ComboBox::from_id_salt("raw_image_target")
+                                    .selected_text(state.target_partition.clone().unwrap_or_else(|| "请选择".to_string()))
+                                    .show_ui(ui, |ui| {
+                                        for (letter, text) in &partitions {
+                                            ui.selectable_value(&mut state.target_partition, Some(letter.clone()), text);
+                                        }
+                                    });
+                            });
+                            ui.checkbox(&mut state.zero_fill, "未使用的区域写零（否则保留目标分区原有数据）");
+                            if state.header.as_ref().is_some_and(|h| h.source_kind == SourceKind::Disk) {
+                                ui.colored_label(egui::Color32::RED, "整盘镜像不能还原到分区，可导出或挂载后使用");
+                            }
+                        }
+                        RawImageAction::Export => {
+                            ui.horizontal(|ui| {
+                                ui.label("导出到:");
+                                ui.add(egui::TextEdit::singleline(&mut state.export_path).desired_width(330.0));
+                                if ui.button("浏览...").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("磁盘镜像", &["img"])
+                                        .set_file_name("image.img")
+                                        .save_file()
+                                    {
+                                        state.export_path = path.to_string_lossy().to_string();
+                                    }
+                                }
+                            });
+                            ui.colored_label(egui::Color32::GRAY, "导出的文件与原分区/磁盘同样大小，可在 Linux 下通过 loop 设备挂载");
+                        }
+                        RawImageAction::Mount => {
+                            ui.colored_label(
+                                egui::Color32::GRAY,
+                                "镜像会转换为临时 VHD 后只读挂载，关闭对话框时自动卸载并删除",
+                            );
+                        }
+                        RawImageAction::BackupDisk | RawImageAction::Verify => {}
+                    }
+                });
+
+                if state.action == RawImageAction::Restore {
+                    ui.colored_label(egui::Color32::YELLOW, "目标分区上的数据将被镜像内容覆盖");
+                }
+
+                if let Some((percent, status)) = &state.progress {
+                    ui.add(egui::ProgressBar::new(*percent as f32 / 100.0).show_percentage());
+                    ui.label(status);
+                }
+                if !state.message.is_empty() {
+                    let color = if state.message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if state.message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &state.message);
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(state.can_start(), egui::Button::new("开始")).clicked() {
+                        if state.action == RawImageAction::Restore {
+                            state.show_confirm_dialog = true;
+                        } else {
+                            should_start = true;
+                        }
+                    }
+                    if mounted && ui.add_enabled(!running, egui::Button::new("卸载")).clicked() {
+                        should_unmount = true;
+                    }
+                    if ui.add_enabled(!running, egui::Button::new("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if self.raw_image_state.show_confirm_dialog {
+            let target = self.raw_image_state.target_partition.clone().unwrap_or_default();
+            egui::Window::new("确认还原")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    ui.colored_label(egui::Color32::RED, format!("⚠ 将覆盖分区 {} 上的全部数据！", target));
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("确认还原").clicked() {
+                            should_start = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            self.raw_image_state.show_confirm_dialog = false;
+                        }
+                    });
+                });
+        }
+
+        if should_start {
+            self.start_raw_image_task();
+        }
+
+        if should_unmount || should_close || !window_open {
+            #[cfg(windows)]
+            {
+                self.raw_image_mounted = None;
+            }
+            if should_unmount {
+                self.raw_image_state.message = "已卸载".to_string();
+            }
+        }
+
+        if should_close || !window_open {
+            self.show_raw_image_dialog = false;
+        }
+    }
+}
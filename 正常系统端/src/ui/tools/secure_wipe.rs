@@ -0,0 +1,383 @@
+//! 安全擦除对话框模块
+//!
+//! 擦除整块磁盘、分区或 NTFS 分区的剩余空间，完成后生成擦除证书
+
+use egui;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use crate::app::App;
+use crate::core::quick_partition::{get_physical_disks, PhysicalDisk};
+use crate::core::secure_wipe::{self, WipeMethod, WipeOptions, WipeTarget, WipeVerify};
+use super::batch_format::{self, FormatablePartition};
+
+/// 擦除对象类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WipeTargetKind {
+    #[default]
+    Partition,
+    Disk,
+    FreeSpace,
+}
+
+impl std::fmt::Display for WipeTargetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WipeTargetKind::Partition => write!(f, "擦除分区"),
+            WipeTargetKind::Disk => write!(f, "擦除整个磁盘"),
+            WipeTargetKind::FreeSpace => write!(f, "仅擦除剩余空间"),
+        }
+    }
+}
+
+/// 擦除任务的结果
+#[derive(Debug, Clone)]
+pub struct WipeOutcome {
+    pub summary: String,
+    pub success: bool,
+    pub report_path: PathBuf,
+    /// 擦除后重新格式化失败的原因
+    pub format_error: Option<String>,
+}
+
+/// 安全擦除对话框状态
+#[derive(Debug, Clone, Default)]
+pub struct SecureWipeDialogState {
+    pub target_kind: WipeTargetKind,
+    /// 物理磁盘列表
+    pub physical_disks: Vec<PhysicalDisk>,
+    pub selected_disk_index: Option<usize>,
+    /// 可擦除的分区（与批量格式化相同，不含系统盘）
+    pub partitions: Vec<FormatablePartition>,
+    pub selected_partition: Option<String>,
+    pub loading: bool,
+    pub method: WipeMethod,
+    pub verify: WipeVerify,
+    /// 擦除分区后重新格式化为 NTFS
+    pub reformat: bool,
+    pub running: bool,
+    pub progress: Option<(u8, String)>,
+    pub show_confirm_dialog: bool,
+    pub message: String,
+    /// 最近一次生成的擦除证书
+    pub report_path: Option<PathBuf>,
+}
+
+impl SecureWipeDialogState {
+    fn selected_disk(&self) -> Option<&PhysicalDisk> {
+        self.selected_disk_index.and_then(|i| self.physical_disks.get(i))
+    }
+
+    fn selected_partition_info(&self) -> Option<&FormatablePartition> {
+        let letter = self.selected_partition.as_ref()?;
+        self.partitions.iter().find(|p| &p.letter == letter)
+    }
+
+    fn target(&self) -> Option<WipeTarget> {
+        let letter = || self.selected_partition.as_ref().and_then(|l| l.chars().next());
+        match self.target_kind {
+            WipeTargetKind::Disk => self.selected_disk().map(|d| WipeTarget::Disk(d.disk_number)),
+            WipeTargetKind::Partition => letter().map(WipeTarget::Partition),
+            WipeTargetKind::FreeSpace => letter().map(WipeTarget::FreeSpace),
+        }
+    }
+
+    fn description(&self) -> String {
+        match self.target_kind {
+            WipeTargetKind::Disk => self.selected_disk().map(|d| d.model.clone()).unwrap_or_default(),
+            _ => self
+                .selected_partition_info()
+                .map(|p| format!("{} {} ({})", p.label, p.file_system, App::format_size(p.total_size_mb)))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn can_start(&self) -> bool {
+        if self.running || self.target().is_none() {
+            return false;
+        }
+        match self.target_kind {
+            WipeTargetKind::FreeSpace => self
+                .selected_partition_info()
+                .is_some_and(|p| p.file_system.eq_ignore_ascii_case("NTFS")),
+            _ => true,
+        }
+    }
+}
+
+impl App {
+    /// 初始化安全擦除对话框
+    pub fn init_secure_wipe_dialog(&mut self) {
+        self.show_secure_wipe_dialog = true;
+        if self.secure_wipe_state.running {
+            return;
+        }
+        self.secure_wipe_state = SecureWipeDialogState { loading: true, reformat: true, ..Default::default() };
+
+        let (tx, rx) = mpsc::channel();
+        self.secure_wipe_disks_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send((get_physical_disks(), batch_format::get_formatable_partitions()));
+        });
+    }
+
+    /// 检查异步操作结果
+    fn check_secure_wipe_async(&mut self) {
+        if let Some(ref rx) = self.secure_wipe_disks_rx {
+            if let Ok((disks, partitions)) = rx.try_recv() {
+                self.secure_wipe_state.physical_disks = disks;
+                self.secure_wipe_state.partitions = partitions;
+                self.secure_wipe_state.loading = false;
+                self.secure_wipe_disks_rx = None;
+            }
+        }
+
+        if let Some(ref rx) = self.secure_wipe_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.secure_wipe_state.progress = Some(progress);
+            }
+        }
+
+        if let Some(ref rx) = self.secure_wipe_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.secure_wipe_result_rx = None;
+                self.secure_wipe_progress_rx = None;
+                self.secure_wipe_cancel_flag = None;
+                let state = &mut self.secure_wipe_state;
+                state.running = false;
+                state.progress = None;
+                match result {
+                    Ok(outcome) => {
+                        let mark = if outcome.success { '✓' } else { '✗' };
+                        state.message = format!("{} {}\n擦除证书: {}", mark, outcome.summary, outcome.report_path.display());
+                        if let Some(e) = outcome.format_error {
+                            state.message.push_str(&format!("\n重新格式化失败: {}", e));
+                        }
+                        state.report_path = Some(outcome.report_path);
+                    }
+                    Err(e) => state.message = format!("✗ {}", e),
+                }
+                self.partitions = crate::core::disk::DiskManager::get_partitions().unwrap_or_default();
+            }
+        }
+    }
+
+    /// 开始擦除
+    fn start_secure_wipe(&mut self) {
+        let state = &mut self.secure_wipe_state;
+        state.show_confirm_dialog = false;
+        let Some(target) = state.target() else {
+            return;
+        };
+        let description = state.description();
+        let options = WipeOptions { method: state.method, verify: state.verify };
+        let reformat = state.reformat && matches!(target, WipeTarget::Partition(_));
+        let label = state.selected_partition_info().map(|p| p.label.clone()).unwrap_or_default();
+
+        state.running = true;
+        state.message.clear();
+        state.report_path = None;
+        state.progress = Some((0, "正在准备...".to_string()));
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.secure_wipe_cancel_flag = Some(cancel.clone());
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel::<Result<WipeOutcome, String>>();
+        self.secure_wipe_progress_rx = Some(progress_rx);
+        self.secure_wipe_result_rx = Some(result_rx);
+
+        std::thread::spawn(move || {
+            let mut progress = |percent: u8, status: &str| {
+                let _ = progress_tx.send((percent, status.to_string()));
+            };
+            let result = secure_wipe::wipe_target(target, &description, &options, &cancel, &mut progress).map(
+                |(report, report_path)| {
+                    let format_error = match target {
+                        WipeTarget::Partition(letter) if reformat => {
+                            let _ = progress_tx.send((100, format!("正在重新格式化 {}: ...", letter)));
+                            batch_format::format_partition(&format!("{}:", letter), &label, "NTFS", true).err()
+                        }
+                        _ => None,
+                    };
+                    WipeOutcome { summary: report.summary(), success: report.success(), report_path, format_error }
+                },
+            );
+            let _ = result_tx.send(result.map_err(|e| format!("{:#}", e)));
+        });
+    }
+
+    /// 渲染安全擦除对话框
+    pub fn render_secure_wipe_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_secure_wipe_dialog {
+            return;
+        }
+
+        self.check_secure_wipe_async();
+
+        let mut should_close = false;
+        let mut should_start = false;
+        let mut should_cancel = false;
+        let mut window_open = self.show_secure_wipe_dialog;
+        let running = self.secure_wipe_state.running;
+
+        egui::Window::new("安全擦除")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ui.ctx(), |ui| {
+                let state = &mut self.secure_wipe_state;
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("擦除对象:");
+                        for kind in [WipeTargetKind::Partition, WipeTargetKind::Disk, WipeTargetKind::FreeSpace] {
+                            ui.radio_value(&mut state.target_kind, kind, kind.to_string());
+                        }
+                    });
+
+                    ui.add_space(5.0);
+                    if state.loading {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("正在加载磁盘列表...");
+                        });
+                    } else if state.target_kind == WipeTargetKind::Disk {
+                        for (i, disk) in state.physical_disks.iter().enumerate() {
+                            let text = format!("磁盘 {}: {} ({:.1} GB)", disk.disk_number, disk.model, disk.size_gb());
+                            ui.radio_value(&mut state.selected_disk_index, Some(i), text);
+                        }
+                    } else {
+                        let free_space = state.target_kind == WipeTargetKind::FreeSpace;
+                        ui.horizontal(|ui| {
+                            ui.label("分区:");
+                            egui::ComboBox::from_id_salt("secure_wipe_partition")
+                                .selected_text(state.selected_partition.clone().unwrap_or_else(|| "请选择".to_string()))
+                                .show_ui(ui, |ui| {
+                                    for p in &state.partitions {
+                                        if free_space && !p.file_system.eq_ignore_ascii_case("NTFS") {
+                                            continue;
+                                        }
+                                        let text = format!(
+                                            "{} {} [{}] ({})",
+                                            p.letter,
+                                            p.label,
+                                            p.file_system,
+                                            Self::format_size(p.total_size_mb)
+                                        );
+                                        ui.selectable_value(&mut state.selected_partition, Some(p.letter.clone()), text);
+                                    }
+                                });
+                        });
+                        if free_space {
+                            ui.colored_label(egui::Color32::GRAY, "仅支持 NTFS 分区，现有文件保留，擦除期间分区会被临时写满");
+                        } else {
+                            ui.checkbox(&mut state.reformat, "擦除后重新格式化为 NTFS");
+                        }
+                    }
+
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        ui.label("擦除方式:");
+                        egui::ComboBox::from_id_salt("secure_wipe_method")
+                            .selected_text(state.method.to_string())
+                            .show_ui(ui, |ui| {
+                                for method in [WipeMethod::Zero, WipeMethod::Random, WipeMethod::Dod3, WipeMethod::Dod7] {
+                                    ui.selectable_value(&mut state.method, method, method.to_string());
+                                }
+                            });
+                        ui.label("校验:");
+                        egui::ComboBox::from_id_salt("secure_wipe_verify")
+                            .selected_text(state.verify.to_string())
+                            .show_ui(ui, |ui| {
+                                for verify in [WipeVerify::Off, WipeVerify::Sample, WipeVerify::Full] {
+                                    ui.selectable_value(&mut state.verify, verify, verify.to_string());
+                                }
+                            });
+                    });
+                    let passes: Vec<String> = state.method.passes().iter().map(|p| p.to_string()).collect();
+                    ui.colored_label(egui::Color32::GRAY, format!("覆盖顺序: {}", passes.join(" → ")));
+                });
+
+                if state.target_kind != WipeTargetKind::FreeSpace {
+                    ui.colored_label(egui::Color32::RED, "⚠ 擦除后数据无法恢复");
+                }
+
+                if let Some((percent, status)) = &state.progress {
+                    ui.add(egui::ProgressBar::new(*percent as f32 / 100.0).show_percentage());
+                    ui.label(status);
+                }
+                if !state.message.is_empty() {
+                    let color = if state.message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if state.message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &state.message);
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if running {
+                        if ui.button("取消").clicked() {
+                            should_cancel = true;
+                        }
+                    } else if ui.add_enabled(state.can_start(), egui::Button::new("开始擦除")).clicked() {
+                        state.show_confirm_dialog = true;
+                    }
+                    if let Some(path) = &state.report_path {
+                        if ui.button("打开擦除证书").clicked() {
+                            let _ = std::process::Command::new("notepad").arg(path).spawn();
+                        }
+                    }
+                    if ui.add_enabled(!running, egui::Button::new("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if self.secure_wipe_state.show_confirm_dialog {
+            let state = &self.secure_wipe_state;
+            let target = state.target().map(|t| t.to_string()).unwrap_or_default();
+            let description = state.description();
+            egui::Window::new("确认擦除")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    if self.secure_wipe_state.target_kind == WipeTargetKind::FreeSpace {
+                        ui.label(format!("将以 {} 方式擦除 {}", self.secure_wipe_state.method, target));
+                    } else {
+                        ui.colored_label(
+                            egui::Color32::RED,
+                            format!("⚠ {}（{}）上的全部数据将被永久擦除！", target, description),
+                        );
+                    }
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("确认擦除").clicked() {
+                            should_start = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            self.secure_wipe_state.show_confirm_dialog = false;
+                        }
+                    });
+                });
+        }
+
+        if should_start {
+            self.start_secure_wipe();
+        }
+
+        if should_cancel {
+            if let Some(cancel) = &self.secure_wipe_cancel_flag {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+
+        if should_close || (!window_open && !running) {
+            self.show_secure_wipe_dialog = false;
+        }
+    }
+}
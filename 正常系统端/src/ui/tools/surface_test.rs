@@ -0,0 +1,443 @@
+//! 磁盘表面测试对话框模块
+//!
+//! 顺序读取整块磁盘或分区，实时绘制扇区热力条，可取消并在之后继续扫描
+
+use egui;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use crate::app::App;
+use crate::core::quick_partition::{get_physical_disks, PhysicalDisk};
+use crate::core::surface_test::{
+    self, CellLevel, RegionKind, ScannedPartition, SurfaceScan, SurfaceTarget, SurfaceTestJob, SurfaceTestOptions,
+};
+
+/// 表面测试对象类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurfaceTargetKind {
+    #[default]
+    Disk,
+    Partition,
+}
+
+/// 表面测试对话框状态
+#[derive(Debug, Clone)]
+pub struct SurfaceTestDialogState {
+    pub target_kind: SurfaceTargetKind,
+    /// 物理磁盘列表
+    pub physical_disks: Vec<PhysicalDisk>,
+    pub selected_disk_index: Option<usize>,
+    pub selected_partition: Option<char>,
+    pub loading: bool,
+    pub slow_threshold_ms: u32,
+    /// 当前显示的扫描结果（已保存的或正在进行的）
+    pub scan: Option<SurfaceScan>,
+    /// `scan` 对应的扫描对象
+    pub loaded_target: Option<SurfaceTarget>,
+    pub running: bool,
+    pub message: String,
+}
+
+impl Default for SurfaceTestDialogState {
+    fn default() -> Self {
+        Self {
+            target_kind: SurfaceTargetKind::default(),
+            physical_disks: Vec::new(),
+            selected_disk_index: None,
+            selected_partition: None,
+            loading: false,
+            slow_threshold_ms: surface_test::DEFAULT_SLOW_THRESHOLD_MS,
+            scan: None,
+            loaded_target: None,
+            running: false,
+            message: String::new(),
+        }
+    }
+}
+
+impl SurfaceTestDialogState {
+    fn selected_disk(&self) -> Option<&PhysicalDisk> {
+        self.selected_disk_index.and_then(|i| self.physical_disks.get(i))
+    }
+
+    fn target(&self) -> Option<SurfaceTarget> {
+        match self.target_kind {
+            SurfaceTargetKind::Disk => self.selected_disk().map(|d| SurfaceTarget::Disk(d.disk_number)),
+            SurfaceTargetKind::Partition => self.selected_partition.map(SurfaceTarget::Partition),
+        }
+    }
+
+    /// 选择变化时读取该对象已保存的结果
+    fn sync_saved_scan(&mut self) {
+        let target = self.target();
+        if self.running || target == self.loaded_target {
+            return;
+        }
+        self.loaded_target = target;
+        self.scan = target.and_then(|t| SurfaceScan::load(&surface_test::results_dir(), t));
+        self.message.clear();
+    }
+
+    /// 已保存的结果是否可以继续
+    fn can_resume(&self) -> bool {
+        self.scan.as_ref().is_some_and(|s| s.can_resume(s.size_bytes))
+    }
+}
+
+impl App {
+    /// 初始化表面测试对话框
+    pub fn init_surface_test_dialog(&mut self) {
+        self.show_surface_test_dialog = true;
+        if self.surface_test_state.running {
+            return;
+        }
+        self.surface_test_state = SurfaceTestDialogState { loading: true, ..Default::default() };
+
+        let (tx, rx) = mpsc::channel();
+        self.surface_test_disks_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send(get_physical_disks());
+        });
+    }
+
+    /// 检查异步操作结果
+    fn check_surface_test_async(&mut self) {
+        if let Some(ref rx) = self.surface_test_disks_rx {
+            if let Ok(disks) = rx.try_recv() {
+                self.surface_test_state.physical_disks = disks;
+                self.surface_test_state.loading = false;
+                self.surface_test_disks_rx = None;
+            }
+        }
+
+        if let Some(ref rx) = self.surface_test_progress_rx {
+            while let Ok(scan) = rx.try_recv() {
+                self.surface_test_state.scan = Some(scan);
+            }
+        }
+
+        if let Some(ref rx) = self.surface_test_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.surface_test_result_rx = None;
+                self.surface_test_progress_rx = None;
+                self.surface_test_cancel_flag = None;
+                // 安装页的坏道警告需要重新读取结果
+                self.install_surface_warning = None;
+                let state = &mut self.surface_test_state;
+                state.running = false;
+                match result {
+                    Ok(scan) => {
+                        state.message = if scan.completed {
+                            format!("✓ 扫描完成\n{}", scan.summary())
+                        } else {
+                            format!("已暂停，可稍后继续\n{}", scan.summary())
+                        };
+                        state.scan = Some(scan);
+                    }
+                    Err(e) => state.message = format!("✗ {}", e),
+                }
+            }
+        }
+    }
+
+    /// 开始或继续扫描
+    fn start_surface_test(&mut self, resume: bool) {
+        let state = &mut self.surface_test_state;
+        let Some(target) = state.target() else {
+            return;
+        };
+
+        let (description, partitions) = match target {
+            SurfaceTarget::Disk(_) => {
+                let disk = state.selected_disk();
+                (
+                    disk.map(|d| d.model.clone()).unwrap_or_default(),
+                    disk.map(|d| {
+                        d.partitions
+                            .iter()
+                            .map(|p| ScannedPartition {
+                                letter: p.drive_letter,
+                                offset: p.offset_bytes,
+                                size: p.size_bytes,
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default(),
+                )
+            }
+            SurfaceTarget::Partition(letter) => {
+                let label = self
+                    .partitions
+                    .iter()
+                    .find(|p| p.letter.starts_with(letter))
+                    .map(|p| p.label.clone())
+                    .unwrap_or_default();
+                (label, Vec::new())
+            }
+        };
+
+        let job = SurfaceTestJob {
+            target,
+            description,
+            partitions,
+            options: SurfaceTestOptions {
+                slow_threshold_ms: state.slow_threshold_ms,
+                ..Default::default()
+            },
+            resume,
+        };
+
+        state.running = true;
+        state.message.clear();
+        if !resume {
+            state.scan = None;
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.surface_test_cancel_flag = Some(cancel.clone());
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel::<Result<SurfaceScan, String>>();
+        self.surface_test_progress_rx = Some(progress_rx);
+        self.surface_test_result_rx = Some(result_rx);
+
+        std::thread::spawn(move || {
+            let result = surface_test::scan_device(&job, &surface_test::results_dir(), &cancel, &mut |scan| {
+                let _ = progress_tx.send(scan.clone());
+            });
+            let _ = result_tx.send(result.map_err(|e| format!("{:#}", e)));
+        });
+    }
+
+    /// 绘制扇区热力条
+    fn render_surface_heat_bar(ui: &mut egui::Ui, scan: &SurfaceScan) {
+        let width = ui.available_width().max(200.0);
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 28.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let cell_width = rect.width() / scan.cells.len().max(1) as f32;
+
+        for index in 0..scan.cells.len() {
+            let color = match scan.cell_level(index) {
+                CellLevel::Unscanned => egui::Color32::from_gray(70),
+                CellLevel::Good => egui::Color32::from_rgb(60, 170, 80),
+                CellLevel::Fair => egui::Color32::from_rgb(190, 200, 60),
+                CellLevel::Slow => egui::Color32::from_rgb(255, 140, 0),
+                CellLevel::Bad => egui::Color32::RED,
+            };
+            let x = rect.left() + index as f32 * cell_width;
+            let cell_rect = egui::Rect::from_min_max(
+                egui::pos2(x, rect.top()),
+                egui::pos2(x + cell_width.max(1.0), rect.bottom()),
+            );
+            painter.rect_filled(cell_rect, 0.0, color);
+        }
+
+        // 当前扫描位置
+        if !scan.completed && scan.size_bytes > 0 {
+            let x = rect.left() + rect.width() * (scan.position as f32 / scan.size_bytes as f32);
+            painter.vline(x, rect.y_range(), egui::Stroke::new(2.0, egui::Color32::WHITE));
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let index = (((pos.x - rect.left()) / cell_width) as usize).min(scan.cells.len().saturating_sub(1));
+            let (start, end) = scan.cell_range(index);
+            let cell = scan.cells[index];
+            response.on_hover_text(format!(
+                "{} - {} MB\n最慢读取: {} ms\n慢速读取: {} 次\n不可读扇区: {}",
+                start / 1024 / 1024,
+                end / 1024 / 1024,
+                cell.max_latency_ms,
+                cell.slow_reads,
+                cell.bad_sectors
+            ));
+        }
+
+        ui.horizontal(|ui| {
+            for (color, text) in [
+                (egui::Color32::from_rgb(60, 170, 80), "正常"),
+                (egui::Color32::from_rgb(190, 200, 60), "偏慢"),
+                (egui::Color32::from_rgb(255, 140, 0), "慢速"),
+                (egui::Color32::RED, "不可读"),
+                (egui::Color32::from_gray(70), "未扫描"),
+            ] {
+                ui.colored_label(color, "■");
+                ui.label(text);
+            }
+        });
+    }
+
+    /// 渲染表面测试对话框
+    pub fn render_surface_test_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_surface_test_dialog {
+            return;
+        }
+
+        self.check_surface_test_async();
+        self.surface_test_state.sync_saved_scan();
+
+        let mut should_close = false;
+        let mut should_start = None;
+        let mut should_cancel = false;
+        let mut window_open = self.show_surface_test_dialog;
+        let running = self.surface_test_state.running;
+        let partitions: Vec<(char, String)> = self
+            .partitions
+            .iter()
+            .filter_map(|p| {
+                let letter = p.letter.chars().next()?;
+                Some((letter, format!("{} {} ({})", p.letter, p.label, Self::format_size(p.total_size_mb))))
+            })
+            .collect();
+
+        egui::Window::new("磁盘表面测试")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(600.0)
+            .show(ui.ctx(), |ui| {
+                let state = &mut self.surface_test_state;
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("测试对象:");
+                        ui.radio_value(&mut state.target_kind, SurfaceTargetKind::Disk, "整块磁盘");
+                        ui.radio_value(&mut state.target_kind, SurfaceTargetKind::Partition, "分区");
+                    });
+
+                    match state.target_kind {
+                        SurfaceTargetKind::Disk => {
+                            if state.loading {
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    ui.label("正在加载磁盘列表...");
+                                });
+                            } else {
+                                for (i, disk) in state.physical_disks.iter().enumerate() {
+                                    let text = format!("磁盘 {}: {} ({:.1} GB)", disk.disk_number, disk.model, disk.size_gb());
+                                    ui.radio_value(&mut state.selected_disk_index, Some(i), text);
+                                }
+                            }
+                        }
+                        SurfaceTargetKind::Partition => {
+                            ui.horizontal(|ui| {
+                                ui.label("分区:");
+                                egui::ComboBox::from_id_salt("surface_test_partition")
+                                    .selected_text(
+                                        state
+                                            .selected_partition
+                                            .map(|l| format!("{}:", l))
+                                            .unwrap_or_else(|| "请选择".to_string()),
+                                    )
+                                    .show_ui(ui, |ui| {
+                                        for (letter, text) in &partitions {
+                                            ui.selectable_value(&mut state.selected_partition, Some(*letter), text);
+                                        }
+                                    });
+                            });
+                        }
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("慢速阈值:");
+                        ui.add(egui::DragValue::new(&mut state.slow_threshold_ms).range(20..=5000).suffix(" ms"));
+                    });
+                });
+
+                ui.add_space(8.0);
+                if let Some(scan) = &state.scan {
+                    Self::render_surface_heat_bar(ui, scan);
+                    ui.label(format!(
+                        "进度 {}%（{} / {}），平均 {:.1} MB/s，不可读 {} KB，慢速读取 {} 次",
+                        scan.percent(),
+                        Self::format_size(scan.position / 1024 / 1024),
+                        Self::format_size(scan.size_bytes / 1024 / 1024),
+                        scan.speed() as f64 / 1024.0 / 1024.0,
+                        scan.unreadable_bytes.div_ceil(1024),
+                        scan.slow_reads
+                    ));
+                    if !running {
+                        if let Some(updated) = chrono::DateTime::from_timestamp(scan.updated as i64, 0) {
+                            ui.colored_label(
+                                egui::Color32::GRAY,
+                                format!("上次扫描: {}", updated.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+                            );
+                        }
+                    }
+
+                    if !scan.regions.is_empty() {
+                        ui.add_space(4.0);
+                        egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                            for region in &scan.regions {
+                                let (color, kind) = match region.kind {
+                                    RegionKind::Unreadable => (egui::Color32::RED, "不可读".to_string()),
+                                    RegionKind::Slow => (
+                                        egui::Color32::from_rgb(255, 165, 0),
+                                        format!("慢速 {} ms", region.latency_ms),
+                                    ),
+                                };
+                                ui.colored_label(
+                                    color,
+                                    format!(
+                                        "扇区 {} - {}  {}",
+                                        region.offset / scan.sector_size as u64,
+                                        region.end().div_ceil(scan.sector_size as u64) - 1,
+                                        kind
+                                    ),
+                                );
+                            }
+                            if scan.dropped_regions > 0 {
+                                ui.label(format!("……另有 {} 处未列出", scan.dropped_regions));
+                            }
+                        });
+                    }
+                } else if state.target().is_some() {
+                    ui.colored_label(egui::Color32::GRAY, "尚未扫描过该对象");
+                }
+
+                if !state.message.is_empty() {
+                    let color = if state.message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if state.message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &state.message);
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if running {
+                        ui.spinner();
+                        if ui.button("暂停").clicked() {
+                            should_cancel = true;
+                        }
+                    } else {
+                        let has_target = state.target().is_some();
+                        if state.can_resume() && ui.button("继续扫描").clicked() {
+                            should_start = Some(true);
+                        }
+                        let text = if state.scan.is_some() { "重新扫描" } else { "开始扫描" };
+                        if ui.add_enabled(has_target, egui::Button::new(text)).clicked() {
+                            should_start = Some(false);
+                        }
+                    }
+                    if ui.button("关闭").clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if let Some(resume) = should_start {
+            self.start_surface_test(resume);
+        }
+
+        // 关闭对话框时暂停扫描，进度已保存，可稍后继续
+        if should_cancel || should_close || !window_open {
+            if let Some(cancel) = &self.surface_test_cancel_flag {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+
+        if should_close || !window_open {
+            self.show_surface_test_dialog = false;
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! 制作安装 U 盘对话框模块
+//!
+//! 选择 Windows 安装 ISO（或 WIM 镜像）和目标 U 盘，重新分区后写入安装文件和引导代码；
+//! 也可以写入磁盘镜像文件以便校验
+
+use egui;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use crate::app::App;
+use crate::core::quick_partition::{get_physical_disks, PhysicalDisk};
+use crate::core::usb_creator::{
+    create_install_usb, UsbLayout, UsbOptions, UsbReport, UsbSource, UsbTarget, DEFAULT_BOOT_PARTITION_MB,
+};
+
+/// 制作安装 U 盘对话框状态
+#[derive(Debug, Clone)]
+pub struct UsbCreatorDialogState {
+    /// 物理磁盘列表
+    pub physical_disks: Vec<PhysicalDisk>,
+    /// 选中的磁盘索引
+    pub selected_disk_index: Option<usize>,
+    /// 是否正在加载磁盘列表
+    pub loading: bool,
+    /// 安装 ISO、WIM/ESD 镜像或已解压的目录
+    pub source_path: String,
+    /// 使用 WIM 来源时提供引导文件的 ISO 或目录
+    pub boot_media_path: String,
+    /// 写入镜像文件而不是物理磁盘
+    pub to_image: bool,
+    pub image_path: String,
+    pub image_size_mb: u64,
+    pub layout: UsbLayout,
+    pub label: String,
+    /// 双分区布局的 FAT32 引导分区大小（MB）
+    pub boot_partition_mb: u64,
+    /// 本地已下载的 LetRecovery PE
+    pub pe_path: Option<String>,
+    pub include_pe: bool,
+    pub include_drivers: bool,
+    pub running: bool,
+    /// 当前进度和步骤
+    pub progress: Option<(u8, String)>,
+    /// 确认对话框是否显示
+    pub show_confirm_dialog: bool,
+    /// 状态消息
+    pub message: String,
+}
+
+impl Default for UsbCreatorDialogState {
+    fn default() -> Self {
+        Self {
+            physical_disks: Vec::new(),
+            selected_disk_index: None,
+            loading: false,
+            source_path: String::new(),
+            boot_media_path: String::new(),
+            to_image: false,
+            image_path: String::new(),
+            image_size_mb: 8192,
+            layout: UsbLayout::SingleFat32,
+            label: "WININSTALL".to_string(),
+            boot_partition_mb: DEFAULT_BOOT_PARTITION_MB,
+            pe_path: None,
+            include_pe: false,
+            include_drivers: false,
+            running: false,
+            progress: None,
+            show_confirm_dialog: false,
+            message: String::new(),
+        }
+    }
+}
+
+impl UsbCreatorDialogState {
+    fn selected_disk(&self) -> Option<&PhysicalDisk> {
+        self.selected_disk_index.and_then(|i| self.physical_disks.get(i))
+    }
+
+    fn is_wim_source(&self) -> bool {
+        let lower = self.source_path.to_ascii_lowercase();
+        lower.ends_with(".wim") || lower.ends_with(".esd")
+    }
+
+    /// 根据界面输入生成制作选项
+    fn build_options(&self) -> Result<UsbOptions, String> {
+        let source_path = PathBuf::from(self.source_path.trim());
+        if self.source_path.trim().is_empty() {
+            return Err("请选择安装 ISO 或系统镜像".to_string());
+        }
+        let source = if self.is_wim_source() {
+            if self.boot_media_path.trim().is_empty() {
+                return Err("使用 WIM/ESD 镜像时需要选择提供引导文件的 ISO（如 LetRecovery PE）".to_string());
+            }
+            UsbSource::Wim { image: source_path, boot_media: PathBuf::from(self.boot_media_path.trim()) }
+        } else if source_path.is_dir() {
+            UsbSource::Directory(source_path)
+        } else {
+            UsbSource::Iso(source_path)
+        };
+
+        let target = if self.to_image {
+            if self.image_path.trim().is_empty() {
+                return Err("请选择镜像文件的保存位置".to_string());
+            }
+            UsbTarget::Image {
+                path: PathBuf::from(self.image_path.trim()),
+                size_bytes: self.image_size_mb * 1024 * 1024,
+            }
+        } else {
+            UsbTarget::Disk(self.selected_disk().ok_or("请选择目标 U 盘")?.disk_number)
+        };
+
+        Ok(UsbOptions {
+            source,
+            target,
+            layout: if self.to_image { UsbLayout::SingleFat32 } else { self.layout },
+            label: self.label.trim().to_string(),
+            boot_partition_mb: self.boot_partition_mb,
+            pe_path: self.pe_path.as_ref().filter(|_| self.include_pe).map(PathBuf::from),
+            include_drivers: self.include_drivers,
+            vbr_template: None,
+        })
+    }
+}
+
+impl App {
+    /// 初始化制作安装 U 盘对话框
+    pub fn init_usb_creator_dialog(&mut self) {
+        self.show_usb_creator_dialog = true;
+        let pe_path = self.config.as_ref().and_then(|config| {
+            config.pe_list.iter().find_map(|pe| {
+                let (exists, path) = crate::core::pe::PeManager::check_pe_exists(&pe.filename);
+                exists.then_some(path)
+            })
+        });
+        self.usb_creator_state = UsbCreatorDialogState {
+            loading: true,
+            boot_media_path: pe_path
+                .clone()
+                .filter(|p| p.to_ascii_lowercase().ends_with(".iso"))
+                .unwrap_or_default(),
+            pe_path,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.usb_creator_disks_rx = Some(rx);
+        std::thread::spawn(move || {
+            let _ = tx.send(get_physical_disks());
+        });
+    }
+
+    /// 检查异步操作结果
+    fn check_usb_creator_async(&mut self) {
+        if let Some(ref rx) = self.usb_creator_disks_rx {
+            if let Ok(disks) = rx.try_recv() {
+                self.usb_creator_state.physical_disks = disks;
+                self.usb_creator_state.loading = false;
+                self.usb_creator_disks_rx = None;
+            }
+        }
+
+        if let Some(ref rx) = self.usb_creator_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.usb_creator_state.progress = Some(progress);
+            }
+        }
+
+        if let Some(ref rx) = self.usb_creator_result_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.usb_creator_result_rx = None;
+                self.usb_creator_progress_rx = None;
+                self.usb_creator_cancel_flag = None;
+                let state = &mut self.usb_creator_state;
+                state.running = false;
+                state.progress = None;
+                state.message = match result {
+                    Ok(report) => format!("✓ 制作完成\n{}", report.summary()),
+                    Err(e) => format!("✗ 制作失败: {}", e),
+                };
+            }
+        }
+    }
+
+    /// 开始制作
+    fn start_usb_creator(&mut self) {
+        self.usb_creator_state.show_confirm_dialog = false;
+        let options = match self.usb_creator_state.build_options() {
+            Ok(options) => options,
+            Err(e) => {
+                self.usb_creator_state.message = format!("✗ {}", e);
+                return;
+            }
+        };
+
+        let state = &mut self.usb_creator_state;
+        state.running = true;
+        state.message.clear();
+        state.progress = Some((0, "正在准备...".to_string()));
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.usb_creator_cancel_flag = Some(cancel.clone());
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel::<Result<UsbReport, String>>();
+        self.usb_creator_progress_rx = Some(progress_rx);
+        self.usb_creator_result_rx = Some(result_rx);
+
+        std::thread::spawn(move || {
+            let result = create_install_usb(
+                &options,
+                &mut |percent, status| {
+                    let _ = progress_tx.send((percent, status.to_string()));
+                },
+                &cancel,
+            )
+            .map_err(|e| format!("{:#}", e));
+            let _ = result_tx.send(result);
+        });
+    }
+
+    /// 渲染制作安装 U 盘对话框
+    pub fn render_usb_creator_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_usb_creator_dialog {
+            return;
+        }
+
+        self.check_usb_creator_async();
+
+        let mut should_close = false;
+        let mut should_start = false;
+        let mut window_open = self.show_usb_creator_dialog;
+        let running = self.usb_creator_state.running;
+
+        egui::Window::new("制作安装 U 盘")
+            .open(&mut window_open)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ui.ctx(), |ui| {
+                let state = &mut self.usb_creator_state;
+                ui.add_enabled_ui(!running, |ui| {
+                    // 安装文件来源
+                    ui.horizontal(|ui| {
+                        ui.label("安装文件:");
+                        ui.add(egui::TextEdit::singleline(&mut state.source_path).desired_width(330.0));
+                        if ui.button("浏览...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("安装镜像", &["iso", "wim", "esd"])
+                                .pick_file()
+                            {
+                                state.source_path = path.to_string_lossy().to_string();
+                            }
+                        }
+                    });
+                    if state.is_wim_source() {
+                        ui.horizontal(|ui| {
+                            ui.label("引导介质:");
+                            ui.add(egui::TextEdit::singleline(&mut state.boot_media_path).desired_width(330.0));
+                            if ui.button("浏览...").clicked() {
+                                if let Some(path) = rfd::FileDialog::new().add_filter("ISO", &["iso"]).pick_file() {
+                                    state.boot_media_path = path.to_string_lossy().to_string();
+                                }
+                            }
+                        });
+                        ui.colored_label(egui::Color32::GRAY, "WIM/ESD 镜像本身不能启动，引导文件取自所选 ISO");
+                    }
+
+                    // 写入目标
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut state.to_image, false, "写入 U 盘");
+                        ui.radio_value(&mut state.to_image, true, "写入镜像文件");
+                    });
+                    if state.to_image {
+                        ui.horizontal(|ui| {
+                            ui.label("镜像文件:");
+                            ui.add(egui::TextEdit::singleline(&mut state.image_path).desired_width(280.0));
+                            if ui.button("浏览...").clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("磁盘镜像", &["img"])
+                                    .set_file_name("usb.img")
+                                    .save_file()
+                                {
+                                    state.image_path = path.to_string_lossy().to_string();
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("镜像大小:");
+                            ui.add(egui::DragValue::new(&mut state.image_size_mb).range(64..=2_000_000).suffix(" MB"));
+                        });
+                    } else if state.loading {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("正在加载磁盘列表...");
+                        });
+                    } else {
+                        for (i, disk) in state.physical_disks.iter().enumerate() {
+                            let text = format!("磁盘 {}: {} ({:.1} GB)", disk.disk_number, disk.model, disk.size_gb());
+                            ui.radio_value(&mut state.selected_disk_index, Some(i), text);
+                        }
+                    }
+
+                    // 分区布局
+                    ui.add_space(8.0);
+                    ui.add_enabled_ui(!state.to_image, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("分区布局:");
+                            ui.radio_value(&mut state.layout, UsbLayout::SingleFat32, UsbLayout::SingleFat32.to_string());
+                            ui.radio_value(&mut state.layout, UsbLayout::Fat32Ntfs, UsbLayout::Fat32Ntfs.to_string());
+                        });
+                        if state.layout == UsbLayout::Fat32Ntfs {
+                            ui.horizontal(|ui| {
+                                ui.label("FAT32 引导分区:");
+                                ui.add(egui::DragValue::new(&mut state.boot_partition_mb).range(512..=32768).suffix(" MB"));
+                            });
+                        }
+                    });
+                    if state.to_image {
+                        ui.colored_label(egui::Color32::GRAY, "镜像文件只支持单分区 FAT32 布局");
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("卷标:");
+                        ui.add(egui::TextEdit::singleline(&mut state.label).desired_width(150.0));
+                    });
+
+                    // 附加内容
+                    ui.add_space(8.0);
+                    ui.add_enabled_ui(state.pe_path.is_some(), |ui| {
+                        ui.checkbox(&mut state.include_pe, "附带 LetRecovery PE");
+                    });
+                    if state.pe_path.is_none() {
+                        ui.colored_label(egui::Color32::GRAY, "未找到已下载的 PE，可先在系统安装页下载");
+                    }
+                    ui.checkbox(&mut state.include_drivers, "附带程序目录下的驱动（drivers）");
+                });
+
+                ui.add_space(8.0);
+                if !state.to_image {
+                    ui.colored_label(egui::Color32::YELLOW, "所选磁盘上的全部数据将被清除（会先备份分区表）");
+                }
+
+                if let Some((percent, status)) = &state.progress {
+                    ui.add(egui::ProgressBar::new(*percent as f32 / 100.0).show_percentage());
+                    ui.label(status);
+                }
+                if !state.message.is_empty() {
+                    let color = if state.message.starts_with('✓') {
+                        egui::Color32::GREEN
+                    } else if state.message.starts_with('✗') {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::GRAY
+                    };
+                    ui.colored_label(color, &state.message);
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let has_target = state.to_image || state.selected_disk_index.is_some();
+                    let can_start = !running && has_target && !state.source_path.trim().is_empty();
+                    if ui.add_enabled(can_start, egui::Button::new("开始制作")).clicked() {
+                        if state.to_image {
+                            should_start = true;
+                        } else {
+                            state.show_confirm_dialog = true;
+                        }
+                    }
+                    if running {
+                        if let Some(cancel) = &self.usb_creator_cancel_flag {
+                            if ui.button("取消").clicked() {
+                                cancel.store(true, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                    if ui.add_enabled(!running, egui::Button::new("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if self.usb_creator_state.show_confirm_dialog {
+            let description = self
+                .usb_creator_state
+                .selected_disk()
+                .map(|d| format!("磁盘 {}: {} ({:.1} GB)", d.disk_number, d.model, d.size_gb()))
+                .unwrap_or_default();
+            egui::Window::new("确认制作")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    ui.colored_label(egui::Color32::RED, format!("⚠ 将清除 {} 上的全部数据！", description));
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("确认制作").clicked() {
+                            should_start = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            self.usb_creator_state.show_confirm_dialog = false;
+                        }
+                    });
+                });
+        }
+
+        if should_start {
+            self.start_usb_creator();
+        }
+
+        if should_close || !window_open {
+            self.show_usb_creator_dialog = false;
+        }
+    }
+}